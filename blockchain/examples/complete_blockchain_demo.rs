@@ -117,12 +117,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let compiler = Compiler::new();
     let mut runtime = Runtime::new();
     
-    let source_code = "contract HelloWorld { function greet() returns string { return 'Hello, World!'; } }";
+    let source_code = "contract Counter { uint count; function increment() public returns (uint) { count += 1; return count; } }";
     let bytecode = compiler.compile(source_code).await?;
     println!("   智能合约编译完成，字节码长度: {} bytes", bytecode.len());
     
-    let contract_address = vm.deploy(&bytecode).await?;
-    println!("   合约部署地址: {}", contract_address);
+    // 部署前会校验字节码，无效字节码被拒绝
+    match vm.deploy(&bytecode).await {
+        Ok(contract_address) => println!("   合约部署地址: {}", contract_address),
+        Err(e) => println!("   合约部署失败: {}", e),
+    }
     
     // 7. 演示网络组件
     println!("\n🌐 7. 演示网络组件");
@@ -94,6 +94,7 @@ fn demo_erc20_token(engine: &mut SmartContractEngine) {
             },
         ],
         events: vec![],
+        storage: vec![],
     };
 
     // 模拟合约代码（实际应该是编译后的 WASM 字节码）
@@ -156,6 +157,7 @@ fn demo_storage_contract(engine: &mut SmartContractEngine) {
             },
         ],
         events: vec![],
+        storage: vec![],
     };
 
     // 模拟合约代码
@@ -325,6 +327,7 @@ fn demo_batch_operations(engine: &mut SmartContractEngine) {
                 name: "Contract1".to_string(),
                 methods: vec![],
                 events: vec![],
+                storage: vec![],
             },
             initial_value: 1000,
         },
@@ -335,6 +338,7 @@ fn demo_batch_operations(engine: &mut SmartContractEngine) {
                 name: "Contract2".to_string(),
                 methods: vec![],
                 events: vec![],
+                storage: vec![],
             },
             initial_value: 2000,
         },
@@ -345,6 +349,7 @@ fn demo_batch_operations(engine: &mut SmartContractEngine) {
                 name: "Contract3".to_string(),
                 methods: vec![],
                 events: vec![],
+                storage: vec![],
             },
             initial_value: 3000,
         },
@@ -422,6 +427,7 @@ fn performance_test() {
             name: format!("TestContract{}", i),
             methods: vec![],
             events: vec![],
+            storage: vec![],
         };
         
         let _ = engine.deploy_contract(
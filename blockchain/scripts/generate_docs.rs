@@ -428,15 +428,19 @@ let compiler = Compiler::new();
 
 // 编译源代码
 let source_code = r#"
-contract HelloWorld {{
-    function greet() returns string {{
-        return "Hello, World!";
+contract Counter {{
+    uint count;
+
+    function increment() public returns (uint) {{
+        count += 1;
+        return count;
     }}
 }}
 "#;
 
-let bytecode = compiler.compile(source_code).await?;
-println!("编译完成，字节码长度: {{}} bytes", bytecode.len());
+let compiled = compiler.compile_contract(source_code)?;
+println!("编译完成，字节码长度: {{}} bytes", compiled.bytecode.len());
+println!("方法: {{:?}}", compiled.abi.methods.iter().map(|m| m.signature()).collect::<Vec<_>>());
 ```
 
 ## 📊 监控和统计
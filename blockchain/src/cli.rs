@@ -8,6 +8,7 @@ use clap::{Parser, Subcommand, ValueEnum};
 
 use crate::simple_blockchain::{Blockchain, Transaction};
 use crate::monitoring::BlockchainMonitor;
+use crate::components::network::{BanList, PeerManager, SyncProgressHandle, BAN_LIST_FILE};
 
 /// 区块链命令行工具
 /// Blockchain CLI Tool
@@ -215,10 +216,38 @@ pub enum NetworkCommands {
         address: String,
     },
     
-    /// 列出连接的节点
-    /// List connected nodes
+    /// 列出连接的节点及评分、封禁的地址
+    /// List connected nodes with scores and banned addresses
     List,
     
+    /// 封禁地址
+    /// Ban an address
+    Ban {
+        /// IP 地址
+        /// IP address
+        #[arg(short, long)]
+        address: String,
+        
+        /// 封禁时长（秒）
+        /// Ban duration in seconds
+        #[arg(short, long, default_value_t = 86400)]
+        duration: u64,
+        
+        /// 封禁原因
+        /// Ban reason
+        #[arg(short, long, default_value = "manual")]
+        reason: String,
+    },
+    
+    /// 解除封禁
+    /// Unban an address
+    Unban {
+        /// IP 地址
+        /// IP address
+        #[arg(short, long)]
+        address: String,
+    },
+    
     /// 广播消息
     /// Broadcast message
     Broadcast {
@@ -227,6 +256,10 @@ pub enum NetworkCommands {
         #[arg(short, long)]
         message: String,
     },
+    
+    /// 显示区块同步进度
+    /// Show block sync progress
+    SyncStatus,
 }
 
 /// 导出格式
@@ -247,6 +280,8 @@ pub struct CliHandler {
     monitor: BlockchainMonitor,
     data_dir: PathBuf,
     verbose: bool,
+    sync_progress: Option<SyncProgressHandle>,
+    peer_manager: Option<PeerManager>,
 }
 
 #[allow(dead_code)]
@@ -259,9 +294,25 @@ impl CliHandler {
             monitor: BlockchainMonitor::new(),
             data_dir,
             verbose,
+            sync_progress: None,
+            peer_manager: None,
         }
     }
 
+    /// 关联运行中节点的同步进度
+    /// Attach the sync progress of a running node
+    pub fn with_sync_progress(mut self, sync_progress: SyncProgressHandle) -> Self {
+        self.sync_progress = Some(sync_progress);
+        self
+    }
+
+    /// 关联运行中节点的对端管理器；未关联时封禁操作直接修改数据目录中的封禁列表，节点下次启动时加载
+    /// Attach the peer manager of a running node
+    pub fn with_peer_manager(mut self, peer_manager: PeerManager) -> Self {
+        self.peer_manager = Some(peer_manager);
+        self
+    }
+
     /// 加载区块链
     /// Load blockchain
     pub fn load_blockchain(&mut self, difficulty: usize) -> Result<(), String> {
@@ -533,10 +584,54 @@ impl CliHandler {
 
     /// 处理网络命令
     /// Handle network command
-    fn handle_network(&self, _network_command: NetworkCommands) -> Result<(), String> {
-        // 网络功能需要 P2P 模块支持
-        self.log("网络功能需要启用 P2P 特性");
-        Ok(())
+    fn handle_network(&self, network_command: NetworkCommands) -> Result<(), String> {
+        match network_command {
+            NetworkCommands::SyncStatus => {
+                match &self.sync_progress {
+                    Some(progress) => println!("同步进度: {}", progress.read().unwrap()),
+                    None => println!("同步未启动"),
+                }
+                Ok(())
+            }
+            NetworkCommands::List => {
+                let manager = self.peer_manager()?;
+                let mut peers = futures::executor::block_on(manager.get_all_peers());
+                peers.sort_by_key(|peer| std::cmp::Reverse(peer.score));
+                println!("连接的节点: {}", peers.len());
+                for peer in peers {
+                    let direction = if peer.inbound { "入站" } else { "出站" };
+                    println!("  {} {} {} 评分 {}", peer.id, peer.address, direction, peer.score);
+                }
+                let bans = manager.list_bans();
+                println!("封禁的地址: {}", bans.len());
+                for ban in bans {
+                    println!("  {} 至 {} ({})", ban.address, ban.banned_until, ban.reason);
+                }
+                Ok(())
+            }
+            NetworkCommands::Ban { address, duration, reason } => {
+                let ip = address.parse().map_err(|e| format!("无效的地址 {}: {}", address, e))?;
+                self.peer_manager()?
+                    .ban_address(ip, std::time::Duration::from_secs(duration), &reason)
+                    .map_err(|e| e.to_string())?;
+                println!("已封禁 {} {} 秒", address, duration);
+                Ok(())
+            }
+            NetworkCommands::Unban { address } => {
+                let ip = address.parse().map_err(|e| format!("无效的地址 {}: {}", address, e))?;
+                if self.peer_manager()?.unban_address(&ip).map_err(|e| e.to_string())? {
+                    println!("已解除封禁 {}", address);
+                } else {
+                    println!("{} 未被封禁", address);
+                }
+                Ok(())
+            }
+            _ => {
+                // 网络功能需要 P2P 模块支持
+                self.log("网络功能需要启用 P2P 特性");
+                Ok(())
+            }
+        }
     }
 
     /// 处理验证命令
@@ -616,6 +711,17 @@ impl CliHandler {
 
     /// 日志输出
     /// Log output
+    /// 关联的对端管理器，未关联时使用数据目录中的封禁列表
+    fn peer_manager(&self) -> Result<PeerManager, String> {
+        match &self.peer_manager {
+            Some(manager) => Ok(manager.clone()),
+            None => {
+                let ban_list = BanList::load(self.data_dir.join(BAN_LIST_FILE)).map_err(|e| e.to_string())?;
+                Ok(PeerManager::new().with_ban_list(ban_list))
+            }
+        }
+    }
+
     fn log(&self, message: &str) {
         if self.verbose {
             println!("[LOG] {}", message);
@@ -657,4 +763,27 @@ mod tests {
         assert!(result.is_ok());
         assert!(handler.blockchain.is_some());
     }
+
+    #[test]
+    fn test_network_ban_unban_persists() {
+        let data_dir = std::env::temp_dir().join(format!("cli-bans-{}", uuid::Uuid::new_v4()));
+        let handler = CliHandler::new(data_dir.clone(), false);
+        handler.handle_network(NetworkCommands::Ban {
+            address: "10.0.0.5".to_string(),
+            duration: 60,
+            reason: "manual".to_string(),
+        }).unwrap();
+        let ban_list = BanList::load(data_dir.join(BAN_LIST_FILE)).unwrap();
+        assert!(ban_list.is_banned(&"10.0.0.5".parse().unwrap()));
+
+        handler.handle_network(NetworkCommands::Unban { address: "10.0.0.5".to_string() }).unwrap();
+        let ban_list = BanList::load(data_dir.join(BAN_LIST_FILE)).unwrap();
+        assert!(ban_list.entries().is_empty());
+        assert!(handler.handle_network(NetworkCommands::Ban {
+            address: "not-an-ip".to_string(),
+            duration: 60,
+            reason: "manual".to_string(),
+        }).is_err());
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
 }
@@ -3,55 +3,68 @@
 use std::pin::Pin;
 use std::future::Future;
 
-use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError};
+use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError, ValidatorSet};
+use super::validator_set::sign_block;
 use crate::core::{ Block};
 
 /// 委托权益证明实现
 #[derive(Debug)]
 pub struct DelegatedProofOfStake {
-    delegates: std::collections::HashMap<String, u64>, // 委托者地址 -> 投票数
+    delegates: ValidatorSet, // 委托者地址 -> 投票数（作为权重）
     max_delegates: usize,
+    /// 本节点地址与 Ed25519 私钥
+    signer: Option<(String, Vec<u8>)>,
 }
 
 impl DelegatedProofOfStake {
     pub fn new(max_delegates: usize) -> Self {
         Self {
-            delegates: std::collections::HashMap::new(),
+            delegates: ValidatorSet::new(),
             max_delegates,
+            signer: None,
         }
     }
 
     pub fn vote_for_delegate(&mut self, delegate: String, votes: u64) {
-        *self.delegates.entry(delegate).or_insert(0) += votes;
+        let (current_votes, public_key) = self.delegates.get(&delegate)
+            .map(|d| (d.stake, d.public_key.clone()))
+            .unwrap_or_default();
+        self.delegates.insert(delegate, current_votes + votes, public_key);
     }
 
+    /// 登记委托者的出块公钥
+    pub fn register_delegate_key(&mut self, delegate: String, public_key: Vec<u8>) {
+        let votes = self.delegates.get(&delegate).map(|d| d.stake).unwrap_or(0);
+        self.delegates.insert(delegate, votes, public_key);
+    }
+
+    /// 得票最多的出块委托者（票数相同按地址排序，保证各节点一致）
     pub fn get_top_delegates(&self) -> Vec<(String, u64)> {
-        let mut delegates: Vec<_> = self.delegates.iter().collect();
-        delegates.sort_by(|a, b| b.1.cmp(a.1));
+        let mut delegates: Vec<_> = self.delegates.iter()
+            .map(|d| (d.address.clone(), d.stake))
+            .collect();
+        delegates.sort_by_key(|d| std::cmp::Reverse(d.1));
+        delegates.truncate(self.max_delegates);
         delegates
-            .into_iter()
-            .take(self.max_delegates)
-            .map(|(k, v)| (k.clone(), *v))
-            .collect()
     }
 
-    fn select_producer(&self) -> Option<String> {
+    /// 出块委托者按高度轮流出块
+    fn select_producer(&self, height: u64) -> Option<String> {
         let top_delegates = self.get_top_delegates();
         if top_delegates.is_empty() {
             return None;
         }
 
-        // 简化的轮询选择逻辑
-        let index = (std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() % top_delegates.len() as u64) as usize;
-
+        let index = (height % top_delegates.len() as u64) as usize;
         Some(top_delegates[index].0.clone())
     }
 }
 
 impl ConsensusComponent for DelegatedProofOfStake {
+    fn name(&self) -> &str {
+        "dpos"
+    }
+
     fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
         Box::pin(async move {
             Ok(())
@@ -64,23 +77,25 @@ impl ConsensusComponent for DelegatedProofOfStake {
         })
     }
 
-    #[allow(unused_variables)]
     fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>> {
+        let is_valid = block.header.hash() == block.block_hash
+            && self.select_producer(block.header.height)
+                .is_some_and(|producer| self.delegates.verify_block_signature(block, &producer));
         Box::pin(async move {
-            // 简化的验证逻辑
-            Ok(true)
+            Ok(is_valid)
         })
     }
 
-    #[allow(unused_variables)]
-    fn mine_block(&self, block: &mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>> {
         Box::pin(async move {
-            if let Some(producer) = self.select_producer() {
-                // 设置区块的生产者信息
-                // 这里需要根据实际的Block结构来设置
-                Ok(())
-            } else {
-                Err(ConsensusError::MiningFailed("没有可用的生产者".to_string()).into())
+            let producer = self.select_producer(block.header.height)
+                .ok_or_else(|| ConsensusError::MiningFailed("没有可用的生产者".to_string()))?;
+
+            match &self.signer {
+                Some((address, private_key)) if *address == producer => {
+                    sign_block(block, address, private_key)
+                }
+                _ => Err(ConsensusError::MiningFailed(format!("本高度的生产者是 {}", producer)).into()),
             }
         })
     }
@@ -89,13 +104,28 @@ impl ConsensusComponent for DelegatedProofOfStake {
         Box::pin(async move {
             Ok(ConsensusStats {
                 total_blocks_mined: 0,
-                total_votes: self.delegates.values().sum(),
+                total_votes: self.delegates.total_stake(),
                 consensus_participants: self.delegates.len() as u64,
                 last_consensus_time: std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_secs(),
+                last_finalized_height: 0,
             })
         })
     }
+
+    fn set_signer(&mut self, address: String, private_key: Vec<u8>) -> ConsensusResult<()> {
+        if !self.delegates.contains(&address) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 不是委托者", address)).into());
+        }
+        self.signer = Some((address, private_key));
+        Ok(())
+    }
+
+    fn select_proposer(&self, _parent_hash: &[u8; 32], height: u64) -> ConsensusResult<Option<String>> {
+        self.select_producer(height)
+            .map(Some)
+            .ok_or_else(|| ConsensusError::ValidationFailed("没有可用的生产者".to_string()).into())
+    }
 }
@@ -0,0 +1,491 @@
+//! 最终性小工具 (Finality Gadget)
+//!
+//! 运行在 PoW/PoS 等最长链出块之上的 Casper FFG 风格检查点投票：
+//! 每个纪元的第一个区块是检查点，验证者对“来源检查点 -> 目标检查点”的链接签名，
+//! 获得 2/3 以上权益支持的链接使目标检查点被证明 (justified)，
+//! 相邻纪元之间的证明链接使来源检查点最终确认 (finalized)。
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::validator_set::sign_message;
+use super::{ConsensusError, ConsensusResult, ValidatorSet};
+
+/// 最终性配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalityConfig {
+    /// 纪元长度（区块数），高度为其整数倍的区块是检查点
+    pub epoch_length: u64,
+}
+
+impl Default for FinalityConfig {
+    fn default() -> Self {
+        Self { epoch_length: 32 }
+    }
+}
+
+/// 检查点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub epoch: u64,
+    pub height: u64,
+    pub block_hash: [u8; 32],
+}
+
+impl Checkpoint {
+    /// 创世检查点，天然被证明且最终确认
+    pub fn genesis(genesis_hash: [u8; 32]) -> Self {
+        Self {
+            epoch: 0,
+            height: 0,
+            block_hash: genesis_hash,
+        }
+    }
+}
+
+/// 检查点投票
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointVote {
+    pub validator: String,
+    pub source: Checkpoint,
+    pub target: Checkpoint,
+    pub signature: Vec<u8>,
+}
+
+impl CheckpointVote {
+    /// 投票所签的消息
+    pub fn payload(source: &Checkpoint, target: &Checkpoint) -> Vec<u8> {
+        let mut payload = b"ffg-vote".to_vec();
+        for checkpoint in [source, target] {
+            payload.extend_from_slice(&checkpoint.epoch.to_be_bytes());
+            payload.extend_from_slice(&checkpoint.height.to_be_bytes());
+            payload.extend_from_slice(&checkpoint.block_hash);
+        }
+        payload
+    }
+
+    /// 两票是否构成可罚没的冲突（双重投票或环绕投票）
+    fn conflicts_with(&self, other: &CheckpointVote) -> bool {
+        let double_vote = self.target.epoch == other.target.epoch && self.target != other.target;
+        let surrounds = |a: &CheckpointVote, b: &CheckpointVote| {
+            a.source.epoch < b.source.epoch && b.target.epoch < a.target.epoch
+        };
+        double_vote || surrounds(self, other) || surrounds(other, self)
+    }
+}
+
+/// 最终性证明，可导出给轻客户端和跨链桥独立验证
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityProof {
+    /// 被最终确认的检查点
+    pub finalized: Checkpoint,
+    /// 下一纪元被证明的检查点
+    pub target: Checkpoint,
+    /// 验证者地址 -> 对 finalized -> target 链接的签名
+    pub signatures: BTreeMap<String, Vec<u8>>,
+}
+
+impl FinalityProof {
+    /// 使用验证者集合验证证明
+    pub fn verify(&self, validators: &ValidatorSet) -> bool {
+        if self.target.epoch != self.finalized.epoch + 1 {
+            return false;
+        }
+
+        let payload = CheckpointVote::payload(&self.finalized, &self.target);
+        let signers = self.signatures.iter()
+            .filter(|(validator, signature)| validators.verify(validator, &payload, signature))
+            .map(|(validator, _)| validator.as_str());
+        validators.has_quorum(signers)
+    }
+}
+
+/// 最终性小工具
+#[derive(Debug)]
+pub struct FinalityGadget {
+    config: FinalityConfig,
+    validators: ValidatorSet,
+    /// 本节点地址与 Ed25519 私钥
+    signer: Option<(String, Vec<u8>)>,
+    justified: Checkpoint,
+    finalized: Checkpoint,
+    /// 已被证明的检查点（区块哈希 -> 检查点）
+    justified_checkpoints: HashMap<[u8; 32], Checkpoint>,
+    /// 已知的检查点树（区块哈希 -> (检查点, 上一纪元检查点的区块哈希)），包括分叉上的检查点
+    checkpoints: HashMap<[u8; 32], (Checkpoint, [u8; 32])>,
+    /// 引用了尚未到达的检查点的投票（缺失的检查点哈希 -> 投票），检查点到达后重放
+    pending_votes: HashMap<[u8; 32], Vec<CheckpointVote>>,
+    /// 链接 (来源, 目标) -> 验证者签名
+    links: HashMap<(Checkpoint, Checkpoint), BTreeMap<String, Vec<u8>>>,
+    /// 每个验证者的历史投票（目标纪元 -> 投票），用于拒绝冲突投票
+    votes_by_validator: HashMap<String, BTreeMap<u64, CheckpointVote>>,
+    /// 最终确认高度 -> 最终性证明
+    proofs: BTreeMap<u64, FinalityProof>,
+}
+
+impl FinalityGadget {
+    pub fn new(config: FinalityConfig, validators: ValidatorSet, genesis_hash: [u8; 32]) -> Self {
+        let genesis = Checkpoint::genesis(genesis_hash);
+        Self {
+            config,
+            validators,
+            signer: None,
+            justified: genesis,
+            finalized: genesis,
+            justified_checkpoints: HashMap::from([(genesis_hash, genesis)]),
+            checkpoints: HashMap::from([(genesis_hash, (genesis, genesis_hash))]),
+            pending_votes: HashMap::new(),
+            links: HashMap::new(),
+            votes_by_validator: HashMap::new(),
+            proofs: BTreeMap::new(),
+        }
+    }
+
+    /// 设置本节点的投票身份，必须是验证者集合中的成员
+    pub fn set_signer(&mut self, address: String, private_key: Vec<u8>) -> ConsensusResult<()> {
+        if !self.validators.contains(&address) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 不是验证者", address)).into());
+        }
+        self.signer = Some((address, private_key));
+        Ok(())
+    }
+
+    pub fn validators(&self) -> &ValidatorSet {
+        &self.validators
+    }
+
+    pub fn epoch_length(&self) -> u64 {
+        self.config.epoch_length.max(1)
+    }
+
+    /// 指定高度的区块若是检查点则返回对应检查点
+    pub fn checkpoint_at(&self, height: u64, block_hash: [u8; 32]) -> Option<Checkpoint> {
+        let epoch_length = self.epoch_length();
+        height.is_multiple_of(epoch_length).then_some(Checkpoint {
+            epoch: height / epoch_length,
+            height,
+            block_hash,
+        })
+    }
+
+    /// 最新被证明的检查点
+    pub fn justified(&self) -> Checkpoint {
+        self.justified
+    }
+
+    /// 最新最终确认的检查点
+    pub fn finalized(&self) -> Checkpoint {
+        self.finalized
+    }
+
+    pub fn last_finalized_height(&self) -> u64 {
+        self.finalized.height
+    }
+
+    pub fn is_justified(&self, checkpoint: &Checkpoint) -> bool {
+        self.justified_checkpoints.get(&checkpoint.block_hash) == Some(checkpoint)
+    }
+
+    /// 登记本地收到的检查点区块，`parent_hash` 为它在上一纪元的检查点；
+    /// 返回因此得以处理的待定投票产生的最终性证明
+    pub fn add_checkpoint(&mut self, checkpoint: Checkpoint, parent_hash: [u8; 32]) -> ConsensusResult<Vec<FinalityProof>> {
+        if self.checkpoint_at(checkpoint.height, checkpoint.block_hash) != Some(checkpoint) {
+            return Err(ConsensusError::ValidationFailed(format!(
+                "检查点高度 {} 与纪元 {} 不符", checkpoint.height, checkpoint.epoch
+            )).into());
+        }
+        match self.checkpoints.get(&parent_hash) {
+            Some((parent, _)) if parent.epoch + 1 == checkpoint.epoch => {}
+            _ => {
+                return Err(ConsensusError::ValidationFailed(format!(
+                    "纪元 {} 的检查点缺少上一纪元的父检查点", checkpoint.epoch
+                )).into());
+            }
+        }
+        if self.checkpoints.insert(checkpoint.block_hash, (checkpoint, parent_hash)).is_some() {
+            return Ok(Vec::new());
+        }
+
+        let mut proofs = Vec::new();
+        for vote in self.pending_votes.remove(&checkpoint.block_hash).unwrap_or_default() {
+            match self.on_vote(vote) {
+                Ok(proof) => proofs.extend(proof),
+                Err(e) => log::debug!("Dropped pending checkpoint vote: {}", e),
+            }
+        }
+        Ok(proofs)
+    }
+
+    /// `descendant` 是否位于 `ancestor` 之后的同一条链上（两者都必须已登记）
+    fn descends_from(&self, descendant: &Checkpoint, ancestor: &Checkpoint) -> bool {
+        let mut cursor = descendant.block_hash;
+        while let Some((checkpoint, parent_hash)) = self.checkpoints.get(&cursor) {
+            if checkpoint.epoch <= ancestor.epoch {
+                return checkpoint == ancestor;
+            }
+            cursor = *parent_hash;
+        }
+        false
+    }
+
+    /// 以最新被证明的检查点为来源，对目标检查点投票
+    pub fn vote(&self, target: Checkpoint) -> ConsensusResult<CheckpointVote> {
+        let (address, private_key) = self.signer.as_ref()
+            .ok_or_else(|| ConsensusError::VotingFailed("本节点没有签名身份".to_string()))?;
+        if target.epoch <= self.justified.epoch {
+            return Err(ConsensusError::VotingFailed(format!(
+                "目标纪元 {} 不晚于已证明纪元 {}", target.epoch, self.justified.epoch
+            )).into());
+        }
+
+        let source = self.justified;
+        let signature = sign_message(private_key, &CheckpointVote::payload(&source, &target))?;
+        Ok(CheckpointVote {
+            validator: address.clone(),
+            source,
+            target,
+            signature,
+        })
+    }
+
+    /// 处理检查点投票，来源检查点因此被最终确认时返回最终性证明
+    pub fn on_vote(&mut self, vote: CheckpointVote) -> ConsensusResult<Option<FinalityProof>> {
+        let payload = CheckpointVote::payload(&vote.source, &vote.target);
+        if !self.validators.verify(&vote.validator, &payload, &vote.signature) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 的检查点投票签名无效", vote.validator)).into());
+        }
+        if vote.target.epoch <= vote.source.epoch {
+            return Err(ConsensusError::ValidationFailed("目标纪元必须晚于来源纪元".to_string()).into());
+        }
+        for checkpoint in [&vote.source, &vote.target] {
+            if self.checkpoint_at(checkpoint.height, checkpoint.block_hash) != Some(*checkpoint) {
+                return Err(ConsensusError::ValidationFailed(format!(
+                    "检查点高度 {} 与纪元 {} 不符", checkpoint.height, checkpoint.epoch
+                )).into());
+            }
+        }
+
+        // 检查点区块尚未到达时暂存投票，由 add_checkpoint 重放
+        if let Some(missing) = [vote.source, vote.target].into_iter()
+            .find(|checkpoint| !self.checkpoints.contains_key(&checkpoint.block_hash))
+        {
+            let pending = self.pending_votes.entry(missing.block_hash).or_default();
+            if !pending.contains(&vote) {
+                pending.push(vote);
+            }
+            return Ok(None);
+        }
+        if !self.descends_from(&vote.target, &vote.source) {
+            return Err(ConsensusError::ValidationFailed(format!(
+                "目标检查点（纪元 {}）不在来源检查点（纪元 {}）之后的链上", vote.target.epoch, vote.source.epoch
+            )).into());
+        }
+        if !self.is_justified(&vote.source) {
+            return Err(ConsensusError::ValidationFailed(format!(
+                "来源检查点（纪元 {}）尚未被证明", vote.source.epoch
+            )).into());
+        }
+
+        let history = self.votes_by_validator.entry(vote.validator.clone()).or_default();
+        if history.get(&vote.target.epoch) == Some(&vote) {
+            return Ok(None);
+        }
+        if let Some(conflict) = history.values().find(|previous| previous.conflicts_with(&vote)) {
+            return Err(ConsensusError::ValidationFailed(format!(
+                "{} 的投票与其纪元 {} 的投票冲突", vote.validator, conflict.target.epoch
+            )).into());
+        }
+        history.insert(vote.target.epoch, vote.clone());
+
+        let link = (vote.source, vote.target);
+        let signatures = self.links.entry(link).or_default();
+        signatures.insert(vote.validator, vote.signature);
+        if !self.validators.has_quorum(signatures.keys().map(String::as_str)) {
+            return Ok(None);
+        }
+
+        // 目标检查点被证明
+        let (source, target) = link;
+        self.justified_checkpoints.insert(target.block_hash, target);
+        if target.epoch > self.justified.epoch {
+            self.justified = target;
+        }
+
+        // 相邻纪元的证明链接使来源检查点最终确认
+        if target.epoch != source.epoch + 1 || self.proofs.contains_key(&source.height) {
+            return Ok(None);
+        }
+        let proof = FinalityProof {
+            finalized: source,
+            target,
+            signatures: self.links[&link].clone(),
+        };
+        self.proofs.insert(source.height, proof.clone());
+        if source.epoch >= self.finalized.epoch {
+            self.finalized = source;
+            self.prune();
+        }
+        Ok(Some(proof))
+    }
+
+    /// 导出指定高度检查点的最终性证明
+    pub fn finality_proof(&self, height: u64) -> Option<&FinalityProof> {
+        self.proofs.get(&height)
+    }
+
+    /// 导出最新的最终性证明
+    pub fn latest_finality_proof(&self) -> Option<&FinalityProof> {
+        self.proofs.values().next_back()
+    }
+
+    /// 清理最终确认纪元之前的链接（投票历史保留用于冲突检测）
+    fn prune(&mut self) {
+        let finalized_epoch = self.finalized.epoch;
+        self.links.retain(|(source, _), _| source.epoch >= finalized_epoch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+
+    async fn setup(n: usize) -> (ValidatorSet, Vec<(String, Vec<u8>)>) {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let mut set = ValidatorSet::new();
+        let mut keys = Vec::new();
+        for i in 0..n {
+            let (private_key, public_key) = engine.generate_keypair("ed25519").unwrap();
+            let address = format!("validator_{}", i);
+            set.insert(address.clone(), 10, public_key);
+            keys.push((address, private_key));
+        }
+        (set, keys)
+    }
+
+    fn gadgets(set: &ValidatorSet, keys: &[(String, Vec<u8>)]) -> Vec<FinalityGadget> {
+        keys.iter()
+            .map(|(address, private_key)| {
+                let mut gadget = FinalityGadget::new(FinalityConfig { epoch_length: 4 }, set.clone(), [0u8; 32]);
+                gadget.set_signer(address.clone(), private_key.clone()).unwrap();
+                gadget
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_justify_and_finalize() {
+        let (set, keys) = setup(4).await;
+        let mut nodes = gadgets(&set, &keys);
+        let checkpoint_1 = nodes[0].checkpoint_at(4, [1u8; 32]).unwrap();
+        let checkpoint_2 = nodes[0].checkpoint_at(8, [2u8; 32]).unwrap();
+        assert!(nodes[0].checkpoint_at(5, [1u8; 32]).is_none());
+        for node in nodes.iter_mut() {
+            node.add_checkpoint(checkpoint_1, [0u8; 32]).unwrap();
+            node.add_checkpoint(checkpoint_2, checkpoint_1.block_hash).unwrap();
+        }
+
+        for target in [checkpoint_1, checkpoint_2] {
+            let votes: Vec<_> = nodes.iter().take(3).map(|node| node.vote(target).unwrap()).collect();
+            for node in nodes.iter_mut() {
+                for vote in &votes {
+                    node.on_vote(vote.clone()).unwrap();
+                }
+            }
+        }
+
+        for node in &nodes {
+            assert_eq!(node.justified(), checkpoint_2);
+            assert_eq!(node.finalized(), checkpoint_1);
+            assert_eq!(node.last_finalized_height(), 4);
+        }
+
+        let proof = nodes[3].latest_finality_proof().unwrap();
+        assert_eq!(proof.finalized, checkpoint_1);
+        assert!(proof.verify(&set));
+    }
+
+    #[tokio::test]
+    async fn test_reject_double_vote() {
+        let (set, keys) = setup(4).await;
+        let mut nodes = gadgets(&set, &keys);
+        let a = nodes[0].checkpoint_at(4, [1u8; 32]).unwrap();
+        let b = nodes[0].checkpoint_at(4, [2u8; 32]).unwrap();
+        nodes[1].add_checkpoint(a, [0u8; 32]).unwrap();
+        nodes[1].add_checkpoint(b, [0u8; 32]).unwrap();
+
+        let vote_a = nodes[0].vote(a).unwrap();
+        let vote_b = nodes[0].vote(b).unwrap();
+        nodes[1].on_vote(vote_a.clone()).unwrap();
+        assert!(nodes[1].on_vote(vote_a).unwrap().is_none());
+        assert!(nodes[1].on_vote(vote_b).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proof_without_quorum_is_invalid() {
+        let (set, keys) = setup(4).await;
+        let mut nodes = gadgets(&set, &keys);
+        let target = nodes[0].checkpoint_at(4, [1u8; 32]).unwrap();
+        nodes[0].add_checkpoint(target, [0u8; 32]).unwrap();
+        let votes: Vec<_> = nodes.iter().take(3).map(|node| node.vote(target).unwrap()).collect();
+        let mut proof = None;
+        for vote in votes {
+            proof = nodes[0].on_vote(vote).unwrap().or(proof);
+        }
+
+        let mut proof = proof.unwrap();
+        assert_eq!(proof.finalized.height, 0);
+        assert!(proof.verify(&set));
+
+        proof.signatures.pop_first();
+        assert!(!proof.verify(&set));
+    }
+
+    #[tokio::test]
+    async fn test_reject_misplaced_and_unrelated_targets() {
+        let (set, keys) = setup(4).await;
+        let mut nodes = gadgets(&set, &keys);
+        let justified = nodes[0].checkpoint_at(4, [1u8; 32]).unwrap();
+        let sibling = nodes[0].checkpoint_at(4, [3u8; 32]).unwrap();
+        let on_sibling = nodes[0].checkpoint_at(8, [4u8; 32]).unwrap();
+        for node in nodes.iter_mut() {
+            node.add_checkpoint(justified, [0u8; 32]).unwrap();
+            node.add_checkpoint(sibling, [0u8; 32]).unwrap();
+            node.add_checkpoint(on_sibling, sibling.block_hash).unwrap();
+        }
+        let votes: Vec<_> = nodes.iter().take(3).map(|node| node.vote(justified).unwrap()).collect();
+        for vote in votes {
+            nodes[0].on_vote(vote.clone()).unwrap();
+            nodes[1].on_vote(vote).unwrap();
+        }
+        assert_eq!(nodes[1].justified(), justified);
+
+        // 高度不是纪元起点的目标
+        let misplaced = Checkpoint { epoch: 2, height: 9, block_hash: [5u8; 32] };
+        let vote = nodes[0].vote(misplaced).unwrap();
+        assert!(nodes[1].on_vote(vote).is_err());
+        // 目标不在来源之后的链上
+        let vote = nodes[0].vote(on_sibling).unwrap();
+        assert!(nodes[1].on_vote(vote).is_err());
+        assert!(nodes[1].add_checkpoint(misplaced, justified.block_hash).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_votes_wait_for_unknown_checkpoints() {
+        let (set, keys) = setup(4).await;
+        let mut nodes = gadgets(&set, &keys);
+        let target = nodes[0].checkpoint_at(4, [1u8; 32]).unwrap();
+        let votes: Vec<_> = nodes.iter().take(3).map(|node| node.vote(target).unwrap()).collect();
+        for vote in votes {
+            assert!(nodes[3].on_vote(vote).unwrap().is_none());
+        }
+        assert_eq!(nodes[3].justified().epoch, 0);
+
+        let proofs = nodes[3].add_checkpoint(target, [0u8; 32]).unwrap();
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(proofs[0].finalized.height, 0);
+        assert_eq!(nodes[3].justified(), target);
+    }
+}
@@ -0,0 +1,933 @@
+//! HotStuff 拜占庭容错共识实现
+//!
+//! 链式 HotStuff：轮换领导者、线性通信的投票聚合、锁定/最高 QC 规则、
+//! 超时证书驱动的视图切换，以及三链提交带来的即时最终性。
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::validator_set::sign_message;
+use super::{ConsensusAction, ConsensusComponent, ConsensusError, ConsensusResult, ConsensusStats, ValidatorSet};
+use crate::core::{Block, BlockHeader};
+
+/// HotStuff 配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotStuffConfig {
+    /// 基础视图超时（毫秒）
+    pub base_timeout_ms: u64,
+    /// 视图超时上限（毫秒），连续超时时指数退避
+    pub max_timeout_ms: u64,
+}
+
+impl Default for HotStuffConfig {
+    fn default() -> Self {
+        Self {
+            base_timeout_ms: 1000,
+            max_timeout_ms: 60_000,
+        }
+    }
+}
+
+/// 法定人数证书 (Quorum Certificate)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuorumCertificate {
+    pub view: u64,
+    pub block_hash: [u8; 32],
+    pub height: u64,
+    /// 投票者地址 -> 签名
+    pub signatures: BTreeMap<String, Vec<u8>>,
+}
+
+impl QuorumCertificate {
+    /// 创世区块的证书，不携带签名
+    pub fn genesis(genesis_hash: [u8; 32]) -> Self {
+        Self {
+            view: 0,
+            block_hash: genesis_hash,
+            height: 0,
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// 证书摘要：视图、区块、高度和按投票者排序的签名（均带长度前缀）的 SHA-256
+    pub fn digest(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.view.to_be_bytes());
+        hasher.update(self.block_hash);
+        hasher.update(self.height.to_be_bytes());
+        for (voter, signature) in &self.signatures {
+            hasher.update((voter.len() as u64).to_be_bytes());
+            hasher.update(voter.as_bytes());
+            hasher.update((signature.len() as u64).to_be_bytes());
+            hasher.update(signature);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// 超时证书 (Timeout Certificate)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeoutCertificate {
+    pub view: u64,
+    /// 超时投票中携带的最高 QC
+    pub high_qc: QuorumCertificate,
+    /// 投票者地址 -> (其最高 QC 视图, 签名)
+    pub signatures: BTreeMap<String, (u64, Vec<u8>)>,
+}
+
+/// 区块投票
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vote {
+    pub view: u64,
+    pub block_hash: [u8; 32],
+    pub height: u64,
+    pub voter: String,
+    pub signature: Vec<u8>,
+}
+
+/// 超时投票
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeoutVote {
+    pub view: u64,
+    pub high_qc: QuorumCertificate,
+    pub voter: String,
+    pub signature: Vec<u8>,
+}
+
+/// 区块提案
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    pub view: u64,
+    pub block_hash: [u8; 32],
+    pub parent_hash: [u8; 32],
+    pub height: u64,
+    /// 提案所扩展的 QC
+    pub justify: QuorumCertificate,
+    pub proposer: String,
+    pub signature: Vec<u8>,
+}
+
+/// HotStuff 消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotStuffMessage {
+    Proposal(Proposal),
+    Vote(Vote),
+    Timeout(TimeoutVote),
+    TimeoutCertificate(TimeoutCertificate),
+}
+
+/// 状态机处理消息后需要调用方执行的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotStuffAction {
+    /// 广播给所有验证者
+    Broadcast(HotStuffMessage),
+    /// 发送给指定的领导者
+    SendTo { recipient: String, message: HotStuffMessage },
+    /// 区块获得 QC，QC 写入区块封装后即可导入
+    Certify(QuorumCertificate),
+    /// 区块已最终确认，应调用 `core::Blockchain::finalize_block`
+    Finalize { height: u64, block_hash: [u8; 32] },
+}
+
+/// 区块树中的节点
+#[derive(Debug, Clone)]
+struct BlockNode {
+    parent_hash: [u8; 32],
+    height: u64,
+    justify: QuorumCertificate,
+}
+
+/// HotStuff 共识实现
+#[derive(Debug)]
+pub struct HotStuff {
+    config: HotStuffConfig,
+    validators: ValidatorSet,
+    /// 本节点地址与 Ed25519 私钥
+    signer: Option<(String, Vec<u8>)>,
+    genesis_hash: [u8; 32],
+    current_view: u64,
+    last_voted_view: u64,
+    high_qc: QuorumCertificate,
+    locked_qc: QuorumCertificate,
+    finalized_height: u64,
+    finalized_hash: [u8; 32],
+    /// 高度 -> 已最终确认的区块哈希
+    finalized_blocks: HashMap<u64, [u8; 32]>,
+    blocks: HashMap<[u8; 32], BlockNode>,
+    /// 已获得 QC 的区块（区块哈希 -> QC）
+    certificates: HashMap<[u8; 32], QuorumCertificate>,
+    pending_votes: HashMap<(u64, [u8; 32]), BTreeMap<String, Vec<u8>>>,
+    pending_timeouts: HashMap<u64, BTreeMap<String, TimeoutVote>>,
+    consecutive_timeouts: u32,
+    total_votes: u64,
+    last_consensus_time: u64,
+}
+
+impl HotStuff {
+    pub fn new(config: HotStuffConfig, validators: ValidatorSet, genesis_hash: [u8; 32]) -> Self {
+        let genesis_qc = QuorumCertificate::genesis(genesis_hash);
+        let mut blocks = HashMap::new();
+        blocks.insert(genesis_hash, BlockNode {
+            parent_hash: [0u8; 32],
+            height: 0,
+            justify: genesis_qc.clone(),
+        });
+
+        Self {
+            config,
+            validators,
+            signer: None,
+            genesis_hash,
+            current_view: 1,
+            last_voted_view: 0,
+            high_qc: genesis_qc.clone(),
+            locked_qc: genesis_qc.clone(),
+            finalized_height: 0,
+            finalized_hash: genesis_hash,
+            finalized_blocks: HashMap::from([(0, genesis_hash)]),
+            blocks,
+            certificates: HashMap::from([(genesis_hash, genesis_qc)]),
+            pending_votes: HashMap::new(),
+            pending_timeouts: HashMap::new(),
+            consecutive_timeouts: 0,
+            total_votes: 0,
+            last_consensus_time: 0,
+        }
+    }
+
+    /// 某个视图的领导者（按地址顺序轮换）
+    pub fn leader(&self, view: u64) -> Option<String> {
+        let addresses = self.validators.addresses();
+        if addresses.is_empty() {
+            return None;
+        }
+        Some(addresses[(view % addresses.len() as u64) as usize].clone())
+    }
+
+    /// 本节点是否为当前视图的领导者
+    pub fn is_leader(&self) -> bool {
+        match (&self.signer, self.leader(self.current_view)) {
+            (Some((address, _)), Some(leader)) => *address == leader,
+            _ => false,
+        }
+    }
+
+    pub fn current_view(&self) -> u64 {
+        self.current_view
+    }
+
+    pub fn high_qc(&self) -> &QuorumCertificate {
+        &self.high_qc
+    }
+
+    pub fn locked_qc(&self) -> &QuorumCertificate {
+        &self.locked_qc
+    }
+
+    /// 最新最终确认的区块（高度, 哈希）
+    pub fn finalized(&self) -> (u64, [u8; 32]) {
+        (self.finalized_height, self.finalized_hash)
+    }
+
+    /// 当前视图的超时时长，连续超时时指数退避
+    pub fn current_timeout(&self) -> Duration {
+        let factor = 1u64 << self.consecutive_timeouts.min(16);
+        let timeout = self.config.base_timeout_ms.saturating_mul(factor);
+        Duration::from_millis(timeout.min(self.config.max_timeout_ms))
+    }
+
+    /// 领导者为当前视图提出区块，区块必须扩展最高 QC
+    pub fn propose(&mut self, block: &Block) -> ConsensusResult<Vec<HotStuffAction>> {
+        if !self.is_leader() {
+            return Err(ConsensusError::ProposalFailed(format!(
+                "本节点不是视图 {} 的领导者", self.current_view
+            )).into());
+        }
+        if block.header.previous_hash != self.high_qc.block_hash {
+            return Err(ConsensusError::ProposalFailed("提案区块必须扩展最高 QC".to_string()).into());
+        }
+
+        let (proposer, private_key) = self.signer.clone().expect("leader has signer");
+        let mut proposal = Proposal {
+            view: self.current_view,
+            block_hash: block.block_hash,
+            parent_hash: block.header.previous_hash,
+            height: block.header.height,
+            justify: self.high_qc.clone(),
+            proposer,
+            signature: Vec::new(),
+        };
+        proposal.signature = sign_message(&private_key, &Self::proposal_payload(&proposal))?;
+
+        let mut actions = vec![HotStuffAction::Broadcast(HotStuffMessage::Proposal(proposal.clone()))];
+        actions.extend(self.on_proposal(proposal)?);
+        Ok(actions)
+    }
+
+    /// 处理任意 HotStuff 消息
+    pub fn handle_message(&mut self, message: HotStuffMessage) -> ConsensusResult<Vec<HotStuffAction>> {
+        match message {
+            HotStuffMessage::Proposal(proposal) => self.on_proposal(proposal),
+            HotStuffMessage::Vote(vote) => self.on_vote(vote),
+            HotStuffMessage::Timeout(timeout) => self.on_timeout_vote(timeout),
+            HotStuffMessage::TimeoutCertificate(tc) => self.on_timeout_certificate(tc),
+        }
+    }
+
+    /// 处理区块提案：验证后根据安全规则投票给下一视图的领导者
+    pub fn on_proposal(&mut self, proposal: Proposal) -> ConsensusResult<Vec<HotStuffAction>> {
+        if self.leader(proposal.view).as_deref() != Some(proposal.proposer.as_str()) {
+            return Err(ConsensusError::ValidationFailed("提案者不是该视图的领导者".to_string()).into());
+        }
+        let payload = Self::proposal_payload(&proposal);
+        if !self.validators.verify(&proposal.proposer, &payload, &proposal.signature) {
+            return Err(ConsensusError::ValidationFailed("提案签名无效".to_string()).into());
+        }
+        if proposal.justify.block_hash != proposal.parent_hash {
+            return Err(ConsensusError::ValidationFailed("提案必须直接扩展其 QC 对应的区块".to_string()).into());
+        }
+        self.verify_qc(&proposal.justify)?;
+
+        let parent_height = self.blocks.get(&proposal.parent_hash)
+            .map(|node| node.height)
+            .ok_or_else(|| ConsensusError::ValidationFailed("提案的父区块未知".to_string()))?;
+        if proposal.height != parent_height + 1 {
+            return Err(ConsensusError::ValidationFailed("提案区块高度不连续".to_string()).into());
+        }
+
+        self.blocks.insert(proposal.block_hash, BlockNode {
+            parent_hash: proposal.parent_hash,
+            height: proposal.height,
+            justify: proposal.justify.clone(),
+        });
+
+        let mut actions = self.process_qc(&proposal.justify);
+
+        if proposal.view < self.current_view
+            || proposal.view <= self.last_voted_view
+            || !self.is_safe(&proposal)
+        {
+            return Ok(actions);
+        }
+
+        self.advance_view(proposal.view + 1);
+        self.last_voted_view = proposal.view;
+
+        if let Some((voter, private_key)) = self.signer.clone() {
+            let signature = sign_message(&private_key, &Self::vote_payload(proposal.view, &proposal.block_hash))?;
+            let vote = Vote {
+                view: proposal.view,
+                block_hash: proposal.block_hash,
+                height: proposal.height,
+                voter,
+                signature,
+            };
+            let next_leader = self.leader(proposal.view + 1).unwrap_or_default();
+            if self.signer.as_ref().map(|(address, _)| address) == Some(&next_leader) {
+                actions.extend(self.on_vote(vote)?);
+            } else {
+                actions.push(HotStuffAction::SendTo {
+                    recipient: next_leader,
+                    message: HotStuffMessage::Vote(vote),
+                });
+            }
+        }
+
+        Ok(actions)
+    }
+
+    /// 收集投票，达到法定人数时形成 QC
+    pub fn on_vote(&mut self, vote: Vote) -> ConsensusResult<Vec<HotStuffAction>> {
+        let payload = Self::vote_payload(vote.view, &vote.block_hash);
+        if !self.validators.verify(&vote.voter, &payload, &vote.signature) {
+            return Err(ConsensusError::VotingFailed("投票签名无效".to_string()).into());
+        }
+        self.total_votes += 1;
+
+        if vote.view <= self.high_qc.view {
+            return Ok(Vec::new());
+        }
+
+        let key = (vote.view, vote.block_hash);
+        let votes = self.pending_votes.entry(key).or_default();
+        votes.insert(vote.voter, vote.signature);
+        if !self.validators.has_quorum(votes.keys().map(String::as_str)) {
+            return Ok(Vec::new());
+        }
+
+        let signatures = self.pending_votes.remove(&key).unwrap_or_default();
+        let qc = QuorumCertificate {
+            view: vote.view,
+            block_hash: vote.block_hash,
+            height: vote.height,
+            signatures,
+        };
+        self.pending_votes.retain(|(view, _), _| *view > qc.view);
+        self.advance_view(qc.view + 1);
+        Ok(self.process_qc(&qc))
+    }
+
+    /// 本地视图超时：广播携带最高 QC 的超时投票
+    pub fn on_local_timeout(&mut self) -> ConsensusResult<Vec<HotStuffAction>> {
+        let (voter, private_key) = self.signer.clone()
+            .ok_or_else(|| ConsensusError::VotingFailed("本节点没有签名身份".to_string()))?;
+        let view = self.current_view;
+        self.consecutive_timeouts = self.consecutive_timeouts.saturating_add(1);
+        // 超时后不再为该视图的提案投票
+        self.last_voted_view = self.last_voted_view.max(view);
+
+        let signature = sign_message(&private_key, &Self::timeout_payload(view, self.high_qc.view))?;
+        let timeout = TimeoutVote {
+            view,
+            high_qc: self.high_qc.clone(),
+            voter,
+            signature,
+        };
+
+        let mut actions = vec![HotStuffAction::Broadcast(HotStuffMessage::Timeout(timeout.clone()))];
+        actions.extend(self.on_timeout_vote(timeout)?);
+        Ok(actions)
+    }
+
+    /// 收集超时投票，达到法定人数时形成超时证书并进入下一视图
+    pub fn on_timeout_vote(&mut self, timeout: TimeoutVote) -> ConsensusResult<Vec<HotStuffAction>> {
+        let payload = Self::timeout_payload(timeout.view, timeout.high_qc.view);
+        if !self.validators.verify(&timeout.voter, &payload, &timeout.signature) {
+            return Err(ConsensusError::VotingFailed("超时投票签名无效".to_string()).into());
+        }
+        self.verify_qc(&timeout.high_qc)?;
+
+        let mut actions = self.process_qc(&timeout.high_qc);
+        if timeout.view < self.current_view {
+            return Ok(actions);
+        }
+
+        let view = timeout.view;
+        let timeouts = self.pending_timeouts.entry(view).or_default();
+        timeouts.insert(timeout.voter.clone(), timeout);
+        if !self.validators.has_quorum(timeouts.keys().map(String::as_str)) {
+            return Ok(actions);
+        }
+
+        let timeouts = self.pending_timeouts.remove(&view).unwrap_or_default();
+        let high_qc = timeouts.values()
+            .map(|t| &t.high_qc)
+            .max_by_key(|qc| qc.view)
+            .cloned()
+            .unwrap_or_else(|| self.high_qc.clone());
+        let signatures = timeouts.into_iter()
+            .map(|(voter, t)| (voter, (t.high_qc.view, t.signature)))
+            .collect();
+        let tc = TimeoutCertificate { view, high_qc, signatures };
+
+        self.pending_timeouts.retain(|v, _| *v > view);
+        self.advance_view(view + 1);
+        actions.push(HotStuffAction::Broadcast(HotStuffMessage::TimeoutCertificate(tc)));
+        Ok(actions)
+    }
+
+    /// 处理其他节点形成的超时证书
+    pub fn on_timeout_certificate(&mut self, tc: TimeoutCertificate) -> ConsensusResult<Vec<HotStuffAction>> {
+        if !self.validators.has_quorum(tc.signatures.keys().map(String::as_str)) {
+            return Err(ConsensusError::ValidationFailed("超时证书未达到法定人数".to_string()).into());
+        }
+        for (voter, (high_qc_view, signature)) in &tc.signatures {
+            if !self.validators.verify(voter, &Self::timeout_payload(tc.view, *high_qc_view), signature) {
+                return Err(ConsensusError::ValidationFailed("超时证书签名无效".to_string()).into());
+            }
+        }
+        self.verify_qc(&tc.high_qc)?;
+
+        let actions = self.process_qc(&tc.high_qc);
+        self.advance_view(tc.view + 1);
+        Ok(actions)
+    }
+
+    /// 验证 QC 的签名与法定人数
+    fn verify_qc(&self, qc: &QuorumCertificate) -> ConsensusResult<()> {
+        if qc.view == 0 {
+            if qc.block_hash == self.genesis_hash {
+                return Ok(());
+            }
+            return Err(ConsensusError::ValidationFailed("无效的创世 QC".to_string()).into());
+        }
+        if !self.validators.has_quorum(qc.signatures.keys().map(String::as_str)) {
+            return Err(ConsensusError::ValidationFailed("QC 未达到法定人数".to_string()).into());
+        }
+        let payload = Self::vote_payload(qc.view, &qc.block_hash);
+        for (voter, signature) in &qc.signatures {
+            if !self.validators.verify(voter, &payload, signature) {
+                return Err(ConsensusError::ValidationFailed("QC 签名无效".to_string()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// 安全规则：提案扩展锁定区块，或其 QC 比锁定 QC 更新
+    fn is_safe(&self, proposal: &Proposal) -> bool {
+        proposal.justify.view > self.locked_qc.view
+            || self.extends(proposal.parent_hash, self.locked_qc.block_hash)
+    }
+
+    /// `descendant` 是否等于或派生自 `ancestor`
+    fn extends(&self, descendant: [u8; 32], ancestor: [u8; 32]) -> bool {
+        let mut current = descendant;
+        loop {
+            if current == ancestor {
+                return true;
+            }
+            match self.blocks.get(&current) {
+                Some(node) if node.height > 0 => current = node.parent_hash,
+                _ => return false,
+            }
+        }
+    }
+
+    /// 根据新 QC 更新最高 QC、锁定 QC，并应用三链提交规则
+    fn process_qc(&mut self, qc: &QuorumCertificate) -> Vec<HotStuffAction> {
+        let mut actions = Vec::new();
+        if let Entry::Vacant(entry) = self.certificates.entry(qc.block_hash) {
+            entry.insert(qc.clone());
+            actions.push(HotStuffAction::Certify(qc.clone()));
+        }
+        if qc.view > self.high_qc.view {
+            self.high_qc = qc.clone();
+        }
+
+        // b2 <- qc, b1 <- b2.justify, b0 <- b1.justify
+        let Some(b2) = self.blocks.get(&qc.block_hash).cloned() else {
+            return actions;
+        };
+        let b1_hash = b2.justify.block_hash;
+        if b2.justify.view > self.locked_qc.view {
+            self.locked_qc = b2.justify.clone();
+        }
+
+        let Some(b1) = self.blocks.get(&b1_hash).cloned() else {
+            return actions;
+        };
+        let b0_hash = b1.justify.block_hash;
+        if b2.parent_hash == b1_hash && b1.parent_hash == b0_hash {
+            actions.extend(self.commit(b0_hash));
+        }
+        actions
+    }
+
+    /// 提交区块及其所有未最终确认的祖先
+    fn commit(&mut self, block_hash: [u8; 32]) -> Vec<HotStuffAction> {
+        let Some(height) = self.blocks.get(&block_hash).map(|node| node.height) else {
+            return Vec::new();
+        };
+        if height <= self.finalized_height {
+            return Vec::new();
+        }
+
+        let mut chain = Vec::new();
+        let mut current = block_hash;
+        while let Some(node) = self.blocks.get(&current) {
+            if node.height <= self.finalized_height {
+                break;
+            }
+            self.finalized_blocks.insert(node.height, current);
+            chain.push(HotStuffAction::Finalize { height: node.height, block_hash: current });
+            current = node.parent_hash;
+        }
+        chain.reverse();
+
+        self.finalized_height = height;
+        self.finalized_hash = block_hash;
+        self.last_consensus_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        // 与最终确认链冲突的分叉不会再被使用
+        let finalized_height = self.finalized_height;
+        let finalized_blocks = &self.finalized_blocks;
+        self.blocks.retain(|hash, node| {
+            node.height > finalized_height || finalized_blocks.get(&node.height) == Some(hash)
+        });
+        let blocks = &self.blocks;
+        self.certificates.retain(|hash, _| blocks.contains_key(hash));
+
+        chain
+    }
+
+    fn advance_view(&mut self, view: u64) {
+        if view > self.current_view {
+            self.current_view = view;
+            self.consecutive_timeouts = 0;
+        }
+    }
+
+    /// 区块封装中携带的 QC（由链在区块获得证书时写入）
+    fn seal_certificate(header: &BlockHeader) -> Option<QuorumCertificate> {
+        let seal = header.seal.as_ref().filter(|seal| !seal.commit_signatures.is_empty())?;
+        Some(QuorumCertificate {
+            view: seal.commit_view,
+            block_hash: header.block_hash,
+            height: header.height,
+            signatures: seal.commit_signatures.clone(),
+        })
+    }
+
+    /// 区块头已获得 QC（本地形成或封装中携带），且不与已最终确认的链冲突
+    fn is_certified(&self, header: &BlockHeader) -> bool {
+        let certified = self.certificates.contains_key(&header.block_hash)
+            || Self::seal_certificate(header).is_some_and(|qc| self.verify_qc(&qc).is_ok());
+        if !certified || header.hash() != header.block_hash {
+            return false;
+        }
+        if header.height <= self.finalized_height {
+            return self.finalized_blocks.get(&header.height) == Some(&header.block_hash);
+        }
+        // 从同步中收到的区块可能不在本地区块树中，只能检查已知的祖先
+        if self.blocks.contains_key(&header.block_hash) {
+            self.extends(header.block_hash, self.finalized_hash)
+        } else if self.blocks.contains_key(&header.previous_hash) {
+            self.extends(header.previous_hash, self.finalized_hash)
+        } else {
+            true
+        }
+    }
+
+    /// 把状态机动作转换为链执行的共识动作
+    fn into_consensus_actions(actions: Vec<HotStuffAction>) -> ConsensusResult<Vec<ConsensusAction>> {
+        let encode = |message: &HotStuffMessage| {
+            bincode::serialize(message)
+                .map_err(|e| ConsensusError::ProposalFailed(format!("无法编码 HotStuff 消息: {}", e)))
+        };
+        let mut converted = Vec::with_capacity(actions.len());
+        for action in actions {
+            converted.push(match action {
+                HotStuffAction::Broadcast(message) => ConsensusAction::Broadcast(encode(&message)?),
+                HotStuffAction::SendTo { recipient, message } => ConsensusAction::SendTo {
+                    recipient,
+                    message: encode(&message)?,
+                },
+                HotStuffAction::Certify(qc) => ConsensusAction::Certify {
+                    block_hash: qc.block_hash,
+                    view: qc.view,
+                    signatures: qc.signatures,
+                },
+                HotStuffAction::Finalize { height, block_hash } => ConsensusAction::Finalize { height, block_hash },
+            });
+        }
+        Ok(converted)
+    }
+
+    /// 提案签名覆盖除签名外的全部字段，中继者不能把提案改接到其他父区块或 QC 上
+    fn proposal_payload(proposal: &Proposal) -> Vec<u8> {
+        let mut payload = Self::payload(b"hotstuff-proposal", proposal.view, &proposal.block_hash);
+        payload.extend_from_slice(&proposal.parent_hash);
+        payload.extend_from_slice(&proposal.height.to_be_bytes());
+        payload.extend_from_slice(&proposal.justify.digest());
+        payload.extend_from_slice(proposal.proposer.as_bytes());
+        payload
+    }
+
+    fn vote_payload(view: u64, block_hash: &[u8; 32]) -> Vec<u8> {
+        Self::payload(b"hotstuff-vote", view, block_hash)
+    }
+
+    fn timeout_payload(view: u64, high_qc_view: u64) -> Vec<u8> {
+        let mut payload = b"hotstuff-timeout".to_vec();
+        payload.extend_from_slice(&view.to_be_bytes());
+        payload.extend_from_slice(&high_qc_view.to_be_bytes());
+        payload
+    }
+
+    fn payload(domain: &[u8], view: u64, block_hash: &[u8; 32]) -> Vec<u8> {
+        let mut payload = domain.to_vec();
+        payload.extend_from_slice(&view.to_be_bytes());
+        payload.extend_from_slice(block_hash);
+        payload
+    }
+}
+
+impl ConsensusComponent for HotStuff {
+    fn name(&self) -> &str {
+        "hotstuff"
+    }
+
+    fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+        Box::pin(async move {
+            if self.validators.len() < 4 {
+                return Err(ConsensusError::ValidationFailed(
+                    "HotStuff 至少需要 4 个验证者才能容忍一个拜占庭节点".to_string()
+                ).into());
+            }
+            Ok(())
+        })
+    }
+
+    fn shutdown(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+        Box::pin(async move {
+            Ok(())
+        })
+    }
+
+    fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>> {
+        // 区块必须已获得 QC，且不能与已最终确认的链冲突
+        let is_valid = self.is_certified(&block.header);
+        Box::pin(async move {
+            Ok(is_valid)
+        })
+    }
+
+    /// QC 随区块头的封装一起同步，区块头即可验证
+    fn validate_header<'a>(&'a self, header: &'a BlockHeader) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + 'a>> {
+        let is_valid = self.is_certified(header);
+        Box::pin(async move {
+            Ok(is_valid)
+        })
+    }
+
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>> {
+        let result = if !self.is_leader() {
+            Err(ConsensusError::MiningFailed(format!("本节点不是视图 {} 的领导者", self.current_view)).into())
+        } else if block.header.previous_hash != self.high_qc.block_hash {
+            Err(ConsensusError::MiningFailed("区块必须扩展最高 QC".to_string()).into())
+        } else {
+            Ok(())
+        };
+        Box::pin(async move {
+            result
+        })
+    }
+
+    fn get_stats(&self) -> Pin<Box<dyn Future<Output = ConsensusResult<ConsensusStats>> + Send + '_>> {
+        Box::pin(async move {
+            Ok(ConsensusStats {
+                total_blocks_mined: self.finalized_height,
+                total_votes: self.total_votes,
+                consensus_participants: self.validators.len() as u64,
+                last_consensus_time: self.last_consensus_time,
+                last_finalized_height: self.finalized_height,
+            })
+        })
+    }
+
+    /// 设置本节点的签名身份，必须是验证者集合中的成员
+    fn set_signer(&mut self, address: String, private_key: Vec<u8>) -> ConsensusResult<()> {
+        if !self.validators.contains(&address) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 不是验证者", address)).into());
+        }
+        self.signer = Some((address, private_key));
+        Ok(())
+    }
+
+    /// 出块者由视图决定，与高度无关
+    fn select_proposer(&self, _parent_hash: &[u8; 32], _height: u64) -> ConsensusResult<Option<String>> {
+        Ok(self.leader(self.current_view))
+    }
+
+    fn is_final(&self, block: &Block) -> bool {
+        self.finalized_blocks.get(&block.header.height) == Some(&block.block_hash)
+    }
+
+    /// 同步或转发导入的区块不经过提案，按封装中的 QC 补全区块树并应用提交规则
+    fn on_block_imported(&mut self, block: &Block) -> ConsensusResult<Vec<ConsensusAction>> {
+        let qc = match self.certificates.get(&block.block_hash) {
+            Some(qc) => qc.clone(),
+            None => {
+                let qc = Self::seal_certificate(&block.header)
+                    .ok_or_else(|| ConsensusError::ValidationFailed("区块缺少 QC".to_string()))?;
+                self.verify_qc(&qc)?;
+                qc
+            }
+        };
+        if !self.blocks.contains_key(&block.block_hash) {
+            // 提案扩展的 QC 即父区块的 QC
+            let justify = self.certificates.get(&block.header.previous_hash)
+                .cloned()
+                .unwrap_or_else(|| QuorumCertificate::genesis(self.genesis_hash));
+            self.blocks.insert(block.block_hash, BlockNode {
+                parent_hash: block.header.previous_hash,
+                height: block.header.height,
+                justify,
+            });
+        }
+        let actions = self.process_qc(&qc);
+        Self::into_consensus_actions(actions)
+    }
+
+    fn propose(&mut self, block: &Block) -> ConsensusResult<Vec<ConsensusAction>> {
+        let actions = HotStuff::propose(self, block)?;
+        Self::into_consensus_actions(actions)
+    }
+
+    fn handle_message(&mut self, message: &[u8]) -> ConsensusResult<Vec<ConsensusAction>> {
+        let message: HotStuffMessage = bincode::deserialize(message)
+            .map_err(|e| ConsensusError::ValidationFailed(format!("无法解码 HotStuff 消息: {}", e)))?;
+        let actions = HotStuff::handle_message(self, message)?;
+        Self::into_consensus_actions(actions)
+    }
+
+    fn on_timeout(&mut self) -> ConsensusResult<Vec<ConsensusAction>> {
+        let actions = self.on_local_timeout()?;
+        Self::into_consensus_actions(actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::components::cryptography::SignatureEngine;
+
+    async fn setup_network(count: usize) -> Vec<HotStuff> {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+
+        let keys: Vec<_> = (0..count)
+            .map(|_| engine.generate_keypair("ed25519").unwrap())
+            .collect();
+        let mut validators = ValidatorSet::new();
+        for (i, (_, public_key)) in keys.iter().enumerate() {
+            validators.insert(format!("validator_{}", i), 100, public_key.clone());
+        }
+
+        let genesis = Block::create_genesis_block().unwrap();
+        keys.into_iter()
+            .enumerate()
+            .map(|(i, (private_key, _))| {
+                let mut node = HotStuff::new(HotStuffConfig::default(), validators.clone(), genesis.block_hash);
+                node.set_signer(format!("validator_{}", i), private_key).unwrap();
+                node
+            })
+            .collect()
+    }
+
+    /// 在所有节点间投递消息直到没有新消息，返回各节点的最终确认动作
+    fn deliver(nodes: &mut [HotStuff], mut queue: Vec<HotStuffAction>) -> Vec<Vec<(u64, [u8; 32])>> {
+        let mut finalized = vec![Vec::new(); nodes.len()];
+        while let Some(action) = queue.pop() {
+            match action {
+                HotStuffAction::Broadcast(message) => {
+                    for (i, node) in nodes.iter_mut().enumerate() {
+                        for action in node.handle_message(message.clone()).unwrap_or_default() {
+                            match action {
+                                HotStuffAction::Finalize { height, block_hash } => finalized[i].push((height, block_hash)),
+                                other => queue.push(other),
+                            }
+                        }
+                    }
+                }
+                HotStuffAction::SendTo { recipient, message } => {
+                    let index = nodes.iter()
+                        .position(|node| node.signer.as_ref().map(|(a, _)| a) == Some(&recipient))
+                        .unwrap();
+                    for action in nodes[index].handle_message(message).unwrap_or_default() {
+                        match action {
+                            HotStuffAction::Finalize { height, block_hash } => finalized[index].push((height, block_hash)),
+                            other => queue.push(other),
+                        }
+                    }
+                }
+                HotStuffAction::Certify(_) | HotStuffAction::Finalize { .. } => {}
+            }
+        }
+        finalized
+    }
+
+    fn leader_index(nodes: &[HotStuff]) -> usize {
+        nodes.iter().position(|node| node.is_leader()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_leader_rotation() {
+        let nodes = setup_network(4).await;
+        let leaders: Vec<_> = (0..4).map(|view| nodes[0].leader(view).unwrap()).collect();
+        let unique: HashSet<_> = leaders.iter().collect();
+        assert_eq!(unique.len(), 4);
+        assert_eq!(nodes[0].leader(1), nodes[0].leader(5));
+    }
+
+    #[tokio::test]
+    async fn test_three_chain_finality() {
+        let mut nodes = setup_network(4).await;
+        let mut parent = Block::create_genesis_block().unwrap();
+        let mut finalized = vec![Vec::new(); nodes.len()];
+
+        for height in 1..=5 {
+            let block = Block::new(parent.block_hash, vec![], height, 1).unwrap();
+            let leader = leader_index(&nodes);
+            let actions = nodes[leader].propose(&block).unwrap();
+            for (i, f) in deliver(&mut nodes, actions).into_iter().enumerate() {
+                finalized[i].extend(f);
+            }
+            parent = block;
+        }
+
+        // 第 5 个提案携带第 4 个区块的 QC，所有节点据此按三链规则确认前 2 个区块
+        for f in &finalized {
+            assert!(f.len() >= 2);
+            assert_eq!(&f[..2], &finalized[0][..2]);
+            assert_eq!(f.iter().map(|(h, _)| *h).take(2).collect::<Vec<_>>(), vec![1, 2]);
+        }
+        for node in &nodes {
+            assert!(node.finalized().0 >= 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_certificate_advances_view() {
+        let mut nodes = setup_network(4).await;
+        let view = nodes[0].current_view();
+
+        let mut queue = Vec::new();
+        for node in nodes.iter_mut().take(3) {
+            queue.extend(node.on_local_timeout().unwrap());
+        }
+        deliver(&mut nodes, queue);
+
+        for node in &nodes {
+            assert_eq!(node.current_view(), view + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_proposal_from_non_leader() {
+        let mut nodes = setup_network(4).await;
+        let genesis = Block::create_genesis_block().unwrap();
+        let block = Block::new(genesis.block_hash, vec![], 1, 1).unwrap();
+
+        let non_leader = (leader_index(&nodes) + 1) % nodes.len();
+        assert!(nodes[non_leader].propose(&block).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proposal_signature_covers_parent_and_justify() {
+        let mut nodes = setup_network(4).await;
+        let genesis = Block::create_genesis_block().unwrap();
+        let block = Block::new(genesis.block_hash, vec![], 1, 1).unwrap();
+        let leader = leader_index(&nodes);
+        let actions = nodes[leader].propose(&block).unwrap();
+        let Some(HotStuffAction::Broadcast(HotStuffMessage::Proposal(proposal))) = actions.first().cloned() else {
+            panic!("leader broadcasts its proposal");
+        };
+        let follower = (leader + 1) % nodes.len();
+
+        // 父区块、高度和 QC 都在签名范围内
+        let mut rebound = proposal.clone();
+        rebound.justify.signatures.insert("validator_0".to_string(), vec![0u8; 64]);
+        let mut reparented = proposal.clone();
+        reparented.parent_hash = [7u8; 32];
+        let mut higher = proposal.clone();
+        higher.height = 2;
+        for tampered in [rebound, reparented, higher] {
+            let payload = HotStuff::proposal_payload(&tampered);
+            assert!(!nodes[follower].validators.verify(&tampered.proposer, &payload, &tampered.signature));
+            assert!(nodes[follower].on_proposal(tampered).is_err());
+        }
+        assert!(nodes[follower].on_proposal(proposal).is_ok());
+    }
+}
@@ -0,0 +1,361 @@
+//! 多线程工作量证明矿工
+//!
+//! 使用 rayon 线程池划分 nonce 空间，仅对预先序列化的区块头进行哈希，
+//! 支持取消（新的链头到达时）和实时算力统计。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use super::{ConsensusError, ConsensusResult};
+use crate::core::BlockHeader;
+
+/// 每个线程检查取消标志的间隔（哈希次数）
+const CHECK_INTERVAL: u64 = 1024;
+
+/// 将难度（前导零比特数）转换为目标值，哈希必须不大于目标值
+pub fn difficulty_to_target(difficulty: u32) -> [u8; 32] {
+    let mut target = [0xFFu8; 32];
+    let difficulty = difficulty.min(256) as usize;
+    let leading_zeros = difficulty / 8;
+    let remaining_bits = difficulty % 8;
+
+    for byte in target.iter_mut().take(leading_zeros) {
+        *byte = 0;
+    }
+
+    if leading_zeros < 32 {
+        target[leading_zeros] = 0xFF >> remaining_bits;
+    }
+
+    target
+}
+
+/// 矿工配置
+#[derive(Debug, Clone)]
+pub struct MinerConfig {
+    /// 工作线程数，0 表示使用 CPU 核心数
+    pub threads: usize,
+    /// 每个时间戳下搜索的 nonce 数量，耗尽后滚动时间戳继续搜索
+    pub nonce_space: u64,
+}
+
+impl Default for MinerConfig {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            nonce_space: u64::MAX,
+        }
+    }
+}
+
+/// 挖矿结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MiningSolution {
+    pub nonce: u64,
+    pub timestamp: u64,
+    pub block_hash: [u8; 32],
+}
+
+/// 预先序列化的区块头
+///
+/// 与 `Block` 的区块哈希格式一致：nonce 之前的字段作为固定前缀，每次尝试写入 nonce 和之后的字段
+#[derive(Clone)]
+struct HeaderTemplate {
+    prefix: Sha256,
+    height: [u8; 8],
+    balance_root: [u8; 32],
+    state_root: [u8; 32],
+}
+
+impl HeaderTemplate {
+    fn new(header: &BlockHeader, timestamp: u64) -> Self {
+        let mut prefix = Sha256::new();
+        prefix.update(header.version.to_be_bytes());
+        prefix.update(header.previous_hash);
+        prefix.update(header.merkle_root);
+        prefix.update(timestamp.to_be_bytes());
+        prefix.update(header.difficulty.to_be_bytes());
+
+        Self {
+            prefix,
+            height: header.height.to_be_bytes(),
+            balance_root: header.balance_root,
+            state_root: header.state_root,
+        }
+    }
+
+    fn hash(&self, nonce: u64) -> [u8; 32] {
+        let mut hasher = self.prefix.clone();
+        hasher.update(nonce.to_be_bytes());
+        hasher.update(self.height);
+        hasher.update(self.balance_root);
+        hasher.update(self.state_root);
+        hasher.finalize().into()
+    }
+}
+
+/// 挖矿统计
+#[derive(Debug)]
+struct MinerStats {
+    hashes: AtomicU64,
+    started_at: Mutex<Option<Instant>>,
+}
+
+/// 挖矿任务
+///
+/// 每个任务有独立的取消标志：在任务交给工作线程之前到达的 `cancel` 同样生效，
+/// 而旧任务的取消请求不会影响新任务
+#[derive(Debug, Clone)]
+pub struct MiningJob {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl MiningJob {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// 多线程矿工
+///
+/// 克隆得到的矿工共享线程池、当前任务和统计信息，可在其他任务中调用 `cancel`
+#[derive(Debug, Clone)]
+pub struct Miner {
+    config: MinerConfig,
+    pool: Arc<rayon::ThreadPool>,
+    current_job: Arc<Mutex<MiningJob>>,
+    stats: Arc<MinerStats>,
+}
+
+impl Miner {
+    /// 创建矿工并启动挖矿线程池
+    pub fn new(config: MinerConfig) -> Self {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.threads)
+            .thread_name(|index| format!("miner-{}", index))
+            .build()
+            .expect("无法创建挖矿线程池");
+        Self {
+            config,
+            pool: Arc::new(pool),
+            current_job: Arc::new(Mutex::new(MiningJob { cancelled: Arc::new(AtomicBool::new(false)) })),
+            stats: Arc::new(MinerStats {
+                hashes: AtomicU64::new(0),
+                started_at: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// 创建新任务并设为当前任务，之后的 `cancel` 作用于该任务
+    pub fn new_job(&self) -> MiningJob {
+        let job = MiningJob { cancelled: Arc::new(AtomicBool::new(false)) };
+        *self.current_job.lock() = job.clone();
+        job
+    }
+
+    /// 取消当前的挖矿任务
+    pub fn cancel(&self) {
+        self.current_job.lock().cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// 当前任务已计算的哈希数
+    pub fn hashes(&self) -> u64 {
+        self.stats.hashes.load(Ordering::Relaxed)
+    }
+
+    /// 当前（或最近一次）任务的算力，单位为哈希/秒
+    pub fn hashrate(&self) -> f64 {
+        match *self.stats.started_at.lock() {
+            Some(started_at) => {
+                let elapsed = started_at.elapsed().as_secs_f64();
+                if elapsed > 0.0 {
+                    self.hashes() as f64 / elapsed
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+
+    /// 以新任务为区块头寻找满足目标值的 nonce
+    ///
+    /// 需要在交出任务之前就能取消时，先调用 [`Miner::new_job`] 再调用 [`Miner::mine_job`]
+    pub fn mine(&self, header: &BlockHeader, target: [u8; 32]) -> ConsensusResult<MiningSolution> {
+        let job = self.new_job();
+        self.mine_job(&job, header, target)
+    }
+
+    /// 执行挖矿任务，任务已被取消时立即返回
+    pub fn mine_job(&self, job: &MiningJob, header: &BlockHeader, target: [u8; 32]) -> ConsensusResult<MiningSolution> {
+        self.stats.hashes.store(0, Ordering::Relaxed);
+        *self.stats.started_at.lock() = Some(Instant::now());
+        let threads = self.pool.current_num_threads() as u64;
+
+        let mut timestamp = header.timestamp;
+        loop {
+            if job.is_cancelled() {
+                return Err(ConsensusError::MiningCancelled.into());
+            }
+
+            let template = HeaderTemplate::new(header, timestamp);
+            let found = AtomicBool::new(false);
+
+            let solution = self.pool.install(|| {
+                (0..threads).into_par_iter().find_map_any(|worker| {
+                    self.search(&template, target, worker, threads, &found, job)
+                })
+            });
+
+            if let Some((nonce, block_hash)) = solution {
+                return Ok(MiningSolution { nonce, timestamp, block_hash });
+            }
+
+            // nonce 空间已耗尽，滚动时间戳
+            timestamp += 1;
+        }
+    }
+
+    /// 单个工作线程按步长搜索 nonce 空间
+    fn search(
+        &self,
+        template: &HeaderTemplate,
+        target: [u8; 32],
+        worker: u64,
+        stride: u64,
+        found: &AtomicBool,
+        job: &MiningJob,
+    ) -> Option<(u64, [u8; 32])> {
+        let mut nonce = worker;
+        let mut pending = 0u64;
+
+        while nonce < self.config.nonce_space {
+            let hash = template.hash(nonce);
+            pending += 1;
+
+            if hash <= target {
+                found.store(true, Ordering::SeqCst);
+                self.stats.hashes.fetch_add(pending, Ordering::Relaxed);
+                return Some((nonce, hash));
+            }
+
+            if pending == CHECK_INTERVAL {
+                self.stats.hashes.fetch_add(pending, Ordering::Relaxed);
+                pending = 0;
+                if found.load(Ordering::Relaxed) || job.cancelled.load(Ordering::Relaxed) {
+                    return None;
+                }
+            }
+
+            nonce = match nonce.checked_add(stride) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        self.stats.hashes.fetch_add(pending, Ordering::Relaxed);
+        None
+    }
+}
+
+impl Default for Miner {
+    fn default() -> Self {
+        Self::new(MinerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Block;
+
+    #[test]
+    fn test_difficulty_to_target() {
+        assert_eq!(difficulty_to_target(0)[0], 0xFF);
+        assert_eq!(difficulty_to_target(4)[0], 0x0F);
+
+        let target = difficulty_to_target(8);
+        assert_eq!(target[0], 0);
+        assert_eq!(target[1], 0xFF);
+    }
+
+    #[test]
+    fn test_mine_matches_block_hash() {
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut block = Block::new(genesis.block_hash, vec![], 1, 8).unwrap();
+        let target = difficulty_to_target(8);
+
+        let miner = Miner::new(MinerConfig { threads: 4, ..MinerConfig::default() });
+        let solution = miner.mine(&block.header, target).unwrap();
+        assert!(solution.block_hash <= target);
+        assert!(miner.hashes() > 0);
+        assert!(miner.hashrate() > 0.0);
+
+        block.set_pow_solution(solution.nonce, solution.timestamp);
+        assert_eq!(block.block_hash, solution.block_hash);
+    }
+
+    #[test]
+    fn test_rolls_timestamp_when_nonce_space_exhausted() {
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut block = Block::new(genesis.block_hash, vec![], 1, 12).unwrap();
+        let target = difficulty_to_target(12);
+
+        // 选一个初始时间戳下 nonce 空间内没有解的区块头，矿工必须滚动时间戳
+        let solvable = |header: &BlockHeader| {
+            let template = HeaderTemplate::new(header, header.timestamp);
+            (0..16).any(|nonce| template.hash(nonce) <= target)
+        };
+        while solvable(&block.header) {
+            block.header.timestamp += 1;
+        }
+
+        let miner = Miner::new(MinerConfig { threads: 2, nonce_space: 16 });
+        let solution = miner.mine(&block.header, target).unwrap();
+        assert!(solution.nonce < 16);
+        assert!(solution.timestamp > block.header.timestamp);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let genesis = Block::create_genesis_block().unwrap();
+        let block = Block::new(genesis.block_hash, vec![], 1, 255).unwrap();
+
+        let miner = Miner::new(MinerConfig { threads: 2, nonce_space: u64::MAX });
+        let handle = miner.clone();
+        let job = miner.new_job();
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let ready = barrier.clone();
+        let worker = std::thread::spawn(move || {
+            ready.wait();
+            miner.mine_job(&job, &block.header, difficulty_to_target(255))
+        });
+
+        // 无论工作线程是否已开始搜索，取消都作用于同一个任务
+        barrier.wait();
+        handle.cancel();
+        assert!(worker.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_cancel_before_job_starts() {
+        let genesis = Block::create_genesis_block().unwrap();
+        let block = Block::new(genesis.block_hash, vec![], 1, 255).unwrap();
+        let miner = Miner::new(MinerConfig { threads: 2, nonce_space: u64::MAX });
+
+        // 任务已交出但尚未开始时到达的取消不会丢失
+        let job = miner.new_job();
+        miner.cancel();
+        assert!(miner.mine_job(&job, &block.header, difficulty_to_target(255)).is_err());
+
+        // 旧任务的取消不影响新任务
+        let job = miner.new_job();
+        assert!(!job.is_cancelled());
+        assert!(miner.mine_job(&job, &block.header, difficulty_to_target(0)).is_ok());
+    }
+}
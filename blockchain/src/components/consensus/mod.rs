@@ -1,21 +1,32 @@
 //! 共识组件模块
 //! 
-//! 提供区块链共识机制，包括PoW、PoS、DPoS等
+//! 提供区块链共识机制，包括PoW、PoS、DPoS、PoA等
 
 pub mod pow;
 pub mod pos;
 pub mod dpos;
 pub mod pbft;
+pub mod hotstuff;
+pub mod miner;
+pub mod validator_set;
+pub mod poa;
+pub mod finality;
 
 pub use pow::ProofOfWork;
 pub use pos::ProofOfStake;
 pub use dpos::DelegatedProofOfStake;
 pub use pbft::PBFT;
+pub use hotstuff::{HotStuff, HotStuffConfig};
+pub use miner::{Miner, MinerConfig, MiningJob, MiningSolution};
+pub use validator_set::{ValidatorSet, ValidatorInfo};
+pub use poa::{ProofOfAuthority, PoaConfig};
+pub use finality::{FinalityGadget, FinalityConfig, Checkpoint, CheckpointVote, FinalityProof};
 
+use std::collections::BTreeMap;
 use std::pin::Pin;
 use std::future::Future;
 
-use crate::core::{Result, BlockchainError, Block};
+use crate::core::{Result, BlockchainError, Block, BlockHeader};
 
 /// 共识组件结果类型
 pub type ConsensusResult<T> = Result<T>;
@@ -27,6 +38,8 @@ pub enum ConsensusError {
     ValidationFailed(String),
     #[error("挖矿失败: {0}")]
     MiningFailed(String),
+    #[error("挖矿已取消")]
+    MiningCancelled,
     #[error("投票失败: {0}")]
     VotingFailed(String),
     #[error("提案失败: {0}")]
@@ -39,22 +52,122 @@ impl From<ConsensusError> for BlockchainError {
     }
 }
 
+/// 共识协议动作：BFT 引擎处理提案、协议消息或超时后需要 `core::Blockchain` 执行的操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusAction {
+    /// 把协议消息广播给所有验证者
+    Broadcast(Vec<u8>),
+    /// 把协议消息发送给指定验证者
+    SendTo { recipient: String, message: Vec<u8> },
+    /// 区块获得法定人数证书，证书写入区块封装后即可导入
+    Certify { block_hash: [u8; 32], view: u64, signatures: BTreeMap<String, Vec<u8>> },
+    /// 区块已最终确认
+    Finalize { height: u64, block_hash: [u8; 32] },
+}
+
 /// 共识组件接口
-pub trait ConsensusComponent {
+///
+/// 统一的共识引擎抽象：`core::Blockchain` 通过该接口完成出块者选择、区块封装、
+/// 封装验证、分叉选择和最终性判断，PoW/PoS/DPoS/PBFT/HotStuff 均实现该接口。
+/// 需要验证者投票的 BFT 共识通过 `propose`、`handle_message` 和 `on_timeout`
+/// 推进协议，返回的动作由链负责发送消息、写入证书和最终确认区块
+pub trait ConsensusComponent: Send + Sync {
+    /// 共识名称
+    fn name(&self) -> &str;
+    
     /// 初始化共识
     fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>>;
     
     /// 关闭共识
     fn shutdown(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>>;
     
-    /// 验证区块
+    /// 验证区块（封装验证）
     fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>>;
     
-    /// 挖矿
-    fn mine_block(&self, block: &mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>>;
+    /// 验证区块头封装，用于同步区块头和轻客户端（默认按不含交易的区块验证）
+    fn validate_header<'a>(&'a self, header: &'a BlockHeader) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + 'a>> {
+        let shell = Block {
+            header: header.clone(),
+            transactions: Vec::new(),
+            merkle_root: header.merkle_root,
+            block_hash: header.block_hash,
+        };
+        Box::pin(async move {
+            self.validate_block(&shell).await
+        })
+    }
+    
+    /// 挖矿（封装区块：PoW 求解 nonce，其他共识由出块者签名）
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>>;
     
     /// 获取共识统计信息
     fn get_stats(&self) -> Pin<Box<dyn Future<Output = ConsensusResult<ConsensusStats>> + Send + '_>>;
+    
+    /// 设置本节点的出块/投票身份
+    fn set_signer(&mut self, _address: String, _private_key: Vec<u8>) -> ConsensusResult<()> {
+        Ok(())
+    }
+    
+    /// 选择指定高度的出块者，返回 `None` 表示任何节点都可以出块
+    fn select_proposer(&self, _parent_hash: &[u8; 32], _height: u64) -> ConsensusResult<Option<String>> {
+        Ok(None)
+    }
+    
+    /// 分叉选择：候选链头是否应取代当前链头（默认最长链，高度相同时保留先收到的链）
+    fn prefer_chain(&self, current: &ChainHead, candidate: &ChainHead) -> bool {
+        candidate.height > current.height
+    }
+    
+    /// 区块被接受后是否立即最终确认（BFT 类共识）
+    fn is_final(&self, _block: &Block) -> bool {
+        false
+    }
+    
+    /// 取消正在进行的区块封装（例如收到新的链头时）
+    fn cancel_sealing(&self) {}
+    
+    /// 区块加入主链后回调，用于维护依赖链历史的共识状态（按高度顺序调用，重组时会重放新分支）；
+    /// 返回的最终确认动作由链执行
+    fn on_block_imported(&mut self, _block: &Block) -> ConsensusResult<Vec<ConsensusAction>> {
+        Ok(Vec::new())
+    }
+    
+    /// 分叉上的区块通过验证后回调，使依赖父区块状态的共识可以继续验证该分叉的后代
+    fn on_fork_block(&mut self, _block: &Block) -> ConsensusResult<()> {
+        Ok(())
+    }
+    
+    /// 出块者提出已封装但尚未获得证书的区块（BFT 共识的提案步骤）；
+    /// 封装后即可通过验证的共识不会被调用
+    fn propose(&mut self, _block: &Block) -> ConsensusResult<Vec<ConsensusAction>> {
+        Err(ConsensusError::ProposalFailed(format!("{} 共识没有提案步骤", self.name())).into())
+    }
+    
+    /// 处理其他验证者发来的协议消息
+    fn handle_message(&mut self, _message: &[u8]) -> ConsensusResult<Vec<ConsensusAction>> {
+        Err(ConsensusError::ValidationFailed(format!("{} 共识没有协议消息", self.name())).into())
+    }
+    
+    /// 本地视图超时，由节点的定时器驱动
+    fn on_timeout(&mut self) -> ConsensusResult<Vec<ConsensusAction>> {
+        Ok(Vec::new())
+    }
+}
+
+/// 链头信息，用于分叉选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChainHead {
+    pub height: u64,
+    pub hash: [u8; 32],
+    /// 累计工作量
+    pub total_difficulty: u128,
+}
+
+impl ChainHead {
+    /// 单个区块的工作量
+    pub fn block_work(difficulty: u32) -> u128 {
+        1u128 << difficulty.min(127)
+    }
 }
 
 /// 共识统计信息
@@ -64,4 +177,7 @@ pub struct ConsensusStats {
     pub total_votes: u64,
     pub consensus_participants: u64,
     pub last_consensus_time: u64,
+    /// 最新已最终确认的区块高度
+    #[serde(default)]
+    pub last_finalized_height: u64,
 }
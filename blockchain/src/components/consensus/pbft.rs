@@ -1,51 +1,194 @@
 //! 实用拜占庭容错 (Practical Byzantine Fault Tolerance) 实现
 
+use std::collections::{BTreeMap, HashMap};
 use std::pin::Pin;
 use std::future::Future;
 
-use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError};
+use serde::{Deserialize, Serialize};
+
+use super::{ConsensusAction, ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError, ValidatorSet};
+use super::validator_set::{sign_block, sign_message};
 use crate::core::{Block};
 
+/// PBFT 协议消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PbftMessage {
+    /// 主节点提出区块，签名即区块封装中的出块者签名
+    PrePrepare {
+        block_hash: [u8; 32],
+        height: u64,
+        primary: String,
+        signature: Vec<u8>,
+    },
+    /// 验证者对区块的提交签名，发送给主节点
+    Commit {
+        block_hash: [u8; 32],
+        validator: String,
+        signature: Vec<u8>,
+    },
+}
+
 /// PBFT实现
 #[derive(Debug)]
 pub struct PBFT {
-    validators: std::collections::HashSet<String>,
+    validators: ValidatorSet, // 每个验证者权重相同
     f: usize, // 最大容错节点数
+    /// 本节点地址与 Ed25519 私钥
+    signer: Option<(String, Vec<u8>)>,
+    /// 本节点作为主节点正在收集提交签名的区块（区块哈希 -> 提交签名）
+    collecting: HashMap<[u8; 32], BTreeMap<String, Vec<u8>>>,
+    /// 本节点最近签过提交签名的区块（高度, 区块哈希），同一高度只为一个区块签名
+    last_commit: Option<(u64, [u8; 32])>,
 }
 
 impl PBFT {
     pub fn new(validators: Vec<String>) -> Self {
-        let validator_set: std::collections::HashSet<String> = validators.into_iter().collect();
-        let n = validator_set.len();
-        let f = (n - 1) / 3; // PBFT要求 n >= 3f + 1
-        
+        let mut validator_set = ValidatorSet::new();
+        for validator in validators {
+            validator_set.insert(validator, 1, Vec::new());
+        }
+        let f = Self::max_faults(validator_set.len());
+
         Self {
             validators: validator_set,
             f,
+            signer: None,
+            collecting: HashMap::new(),
+            last_commit: None,
         }
     }
 
     pub fn add_validator(&mut self, validator: String) {
-        self.validators.insert(validator);
-        let n = self.validators.len();
-        self.f = (n - 1) / 3;
+        self.add_validator_with_key(validator, Vec::new());
+    }
+
+    /// 添加带签名公钥的验证者
+    pub fn add_validator_with_key(&mut self, validator: String, public_key: Vec<u8>) {
+        self.validators.insert(validator, 1, public_key);
+        self.f = Self::max_faults(self.validators.len());
     }
 
     pub fn remove_validator(&mut self, validator: &str) {
         self.validators.remove(validator);
-        let n = self.validators.len();
-        self.f = (n - 1) / 3;
+        self.f = Self::max_faults(self.validators.len());
+    }
+
+    /// PBFT要求 n >= 3f + 1
+    fn max_faults(n: usize) -> usize {
+        n.saturating_sub(1) / 3
     }
 
     fn can_tolerate_faults(&self) -> bool {
         self.validators.len() >= 3 * self.f + 1
     }
+
+    /// 主节点按高度轮换
+    fn primary(&self, height: u64) -> Option<String> {
+        let addresses = self.validators.addresses();
+        if addresses.is_empty() {
+            return None;
+        }
+        Some(addresses[(height % addresses.len() as u64) as usize].clone())
+    }
+
+    /// 提交签名所签的消息
+    pub fn commit_payload(block_hash: &[u8; 32]) -> Vec<u8> {
+        let mut payload = b"pbft-commit".to_vec();
+        payload.extend_from_slice(block_hash);
+        payload
+    }
+
+    /// 本节点对区块生成提交签名，由主节点收集后写入区块封装
+    pub fn sign_commit(&self, block_hash: &[u8; 32]) -> ConsensusResult<(String, Vec<u8>)> {
+        let (address, private_key) = self.signer.as_ref()
+            .ok_or_else(|| ConsensusError::VotingFailed("本节点没有签名身份".to_string()))?;
+        let signature = sign_message(private_key, &Self::commit_payload(block_hash))?;
+        Ok((address.clone(), signature))
+    }
+
+    /// 将收集到的提交签名写入区块封装
+    pub fn add_commit(block: &mut Block, validator: String, signature: Vec<u8>) -> ConsensusResult<()> {
+        let mut seal = block.seal().cloned()
+            .ok_or_else(|| ConsensusError::VotingFailed("区块尚未由主节点签名".to_string()))?;
+        seal.commit_signatures.insert(validator, signature);
+        block.set_seal(seal);
+        Ok(())
+    }
+
+    /// 主节点签名有效且提交签名达到 2f+1
+    fn has_commit_quorum(&self, block: &Block) -> bool {
+        let Some(primary) = self.primary(block.header.height) else {
+            return false;
+        };
+        let Some(seal) = block.seal() else {
+            return false;
+        };
+        if block.header.hash() != block.block_hash || !self.validators.verify_block_signature(block, &primary) {
+            return false;
+        }
+
+        let payload = Self::commit_payload(&block.block_hash);
+        let valid_commits = seal.commit_signatures.iter()
+            .filter(|(validator, signature)| self.validators.verify(validator, &payload, signature))
+            .count();
+        valid_commits > 2 * self.f
+    }
+    
+    fn encode(message: &PbftMessage) -> ConsensusResult<Vec<u8>> {
+        bincode::serialize(message)
+            .map_err(|e| ConsensusError::ProposalFailed(format!("无法编码 PBFT 消息: {}", e)).into())
+    }
+    
+    /// 验证者为主节点的预准备消息签名提交，并发回主节点
+    fn on_pre_prepare(&mut self, block_hash: [u8; 32], height: u64, primary: String, signature: &[u8]) -> ConsensusResult<Vec<ConsensusAction>> {
+        if self.primary(height).as_deref() != Some(primary.as_str())
+            || !self.validators.verify(&primary, &block_hash, signature)
+        {
+            return Err(ConsensusError::ValidationFailed(format!("高度 {} 的预准备消息不是由主节点签名", height)).into());
+        }
+        if self.signer.is_none() {
+            return Ok(Vec::new());
+        }
+        // 不为更低的高度或同一高度的另一个区块签名
+        if let Some((last_height, last_hash)) = self.last_commit
+            && (height < last_height || (height == last_height && block_hash != last_hash))
+        {
+            return Ok(Vec::new());
+        }
+        
+        self.last_commit = Some((height, block_hash));
+        let (validator, signature) = self.sign_commit(&block_hash)?;
+        let message = Self::encode(&PbftMessage::Commit { block_hash, validator, signature })?;
+        Ok(vec![ConsensusAction::SendTo { recipient: primary, message }])
+    }
+    
+    /// 主节点收集提交签名，达到 2f+1 时区块获得证书
+    fn on_commit(&mut self, block_hash: [u8; 32], validator: String, signature: Vec<u8>) -> ConsensusResult<Vec<ConsensusAction>> {
+        if !self.collecting.contains_key(&block_hash) {
+            return Ok(Vec::new());
+        }
+        if !self.validators.verify(&validator, &Self::commit_payload(&block_hash), &signature) {
+            return Err(ConsensusError::VotingFailed(format!("{} 的提交签名无效", validator)).into());
+        }
+        
+        let commits = self.collecting.entry(block_hash).or_default();
+        commits.insert(validator, signature);
+        if commits.len() <= 2 * self.f {
+            return Ok(Vec::new());
+        }
+        let signatures = self.collecting.remove(&block_hash).unwrap_or_default();
+        Ok(vec![ConsensusAction::Certify { block_hash, view: 0, signatures }])
+    }
 }
 
 impl ConsensusComponent for PBFT {
+    fn name(&self) -> &str {
+        "pbft"
+    }
+
     fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
         Box::pin(async move {
-            if !self.can_tolerate_faults() {
+            if self.validators.is_empty() || !self.can_tolerate_faults() {
                 return Err(ConsensusError::ValidationFailed(
                     "验证者数量不足以容忍拜占庭故障".to_string()
                 ).into());
@@ -60,27 +203,30 @@ impl ConsensusComponent for PBFT {
         })
     }
 
-    #[allow(dead_code)]
-    #[allow(unused_variables)]
     fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>> {
+        let is_valid = self.has_commit_quorum(block);
         Box::pin(async move {
-            // 简化的验证逻辑
-            Ok(true)
+            Ok(is_valid)
         })
     }
 
-    #[allow(dead_code)]
-    #[allow(unused_variables)]
-    fn mine_block(&self, block: &mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>> {
         Box::pin(async move {
-            // PBFT的区块生成需要多轮投票
-            // 这里简化实现
-            Ok(())
+            // 主节点签名区块并附上自己的提交签名，其余提交签名通过 add_commit 收集
+            let primary = self.primary(block.header.height)
+                .ok_or_else(|| ConsensusError::MiningFailed("没有可用的验证者".to_string()))?;
+
+            let (address, private_key) = match &self.signer {
+                Some((address, private_key)) if *address == primary => (address, private_key),
+                _ => return Err(ConsensusError::MiningFailed(format!("本高度的主节点是 {}", primary)).into()),
+            };
+
+            sign_block(block, address, private_key)?;
+            let (validator, signature) = self.sign_commit(&block.block_hash)?;
+            Self::add_commit(block, validator, signature)
         })
     }
 
-    #[allow(dead_code)]
-    #[allow(unused_variables)]
     fn get_stats(&self) -> Pin<Box<dyn Future<Output = ConsensusResult<ConsensusStats>> + Send + '_>> {
         Box::pin(async move {
             Ok(ConsensusStats {
@@ -91,7 +237,147 @@ impl ConsensusComponent for PBFT {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_secs(),
+                last_finalized_height: 0,
             })
         })
     }
+
+    fn set_signer(&mut self, address: String, private_key: Vec<u8>) -> ConsensusResult<()> {
+        if !self.validators.contains(&address) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 不是验证者", address)).into());
+        }
+        self.signer = Some((address, private_key));
+        Ok(())
+    }
+
+    fn select_proposer(&self, _parent_hash: &[u8; 32], height: u64) -> ConsensusResult<Option<String>> {
+        self.primary(height)
+            .map(Some)
+            .ok_or_else(|| ConsensusError::ValidationFailed("没有可用的验证者".to_string()).into())
+    }
+
+    /// 带有 2f+1 提交签名的区块立即最终确认
+    fn is_final(&self, block: &Block) -> bool {
+        self.has_commit_quorum(block)
+    }
+    
+    /// 主节点广播预准备消息，并开始收集提交签名（自己的提交签名已在封装中）
+    fn propose(&mut self, block: &Block) -> ConsensusResult<Vec<ConsensusAction>> {
+        let height = block.header.height;
+        let seal = block.seal()
+            .filter(|seal| self.signer.as_ref().is_some_and(|(address, _)| *address == seal.signer))
+            .filter(|seal| self.primary(height).as_ref() == Some(&seal.signer))
+            .ok_or_else(|| ConsensusError::ProposalFailed(format!("区块没有本节点作为高度 {} 主节点的签名", height)))?;
+        
+        let message = Self::encode(&PbftMessage::PrePrepare {
+            block_hash: block.block_hash,
+            height,
+            primary: seal.signer.clone(),
+            signature: seal.signature.clone(),
+        })?;
+        // 主节点同一时间只收集一个区块的提交签名
+        self.collecting.clear();
+        self.collecting.insert(block.block_hash, seal.commit_signatures.clone());
+        self.last_commit = Some((height, block.block_hash));
+        Ok(vec![ConsensusAction::Broadcast(message)])
+    }
+    
+    fn handle_message(&mut self, message: &[u8]) -> ConsensusResult<Vec<ConsensusAction>> {
+        let message: PbftMessage = bincode::deserialize(message)
+            .map_err(|e| ConsensusError::ValidationFailed(format!("无法解码 PBFT 消息: {}", e)))?;
+        match message {
+            PbftMessage::PrePrepare { block_hash, height, primary, signature } => {
+                self.on_pre_prepare(block_hash, height, primary, &signature)
+            }
+            PbftMessage::Commit { block_hash, validator, signature } => self.on_commit(block_hash, validator, signature),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+
+    /// 四个验证者，每个节点一个 PBFT 实例
+    async fn setup() -> Vec<PBFT> {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let keys: Vec<_> = (0..4).map(|_| engine.generate_keypair("ed25519").unwrap()).collect();
+        keys.iter()
+            .enumerate()
+            .map(|(i, (private_key, _))| {
+                let mut node = PBFT::new(Vec::new());
+                for (j, (_, public_key)) in keys.iter().enumerate() {
+                    node.add_validator_with_key(format!("v{}", j), public_key.clone());
+                }
+                node.set_signer(format!("v{}", i), private_key.clone()).unwrap();
+                node
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_commits_certify_proposed_block() {
+        let mut nodes = setup().await;
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut block = Block::new(genesis.block_hash, Vec::new(), 1, 1).unwrap();
+        // v1 是高度 1 的主节点
+        nodes[1].mine_block(&mut block).await.unwrap();
+        assert!(!nodes[1].validate_block(&block).await.unwrap());
+        let Some(ConsensusAction::Broadcast(pre_prepare)) = nodes[1].propose(&block).unwrap().pop() else {
+            panic!("primary broadcasts a pre-prepare");
+        };
+        assert!(nodes[0].propose(&block).is_err());
+
+        let mut certificate = None;
+        for i in [0, 2, 3] {
+            let Some(ConsensusAction::SendTo { recipient, message }) = nodes[i].handle_message(&pre_prepare).unwrap().pop() else {
+                panic!("validator sends its commit to the primary");
+            };
+            assert_eq!(recipient, "v1");
+            if let Some(ConsensusAction::Certify { block_hash, signatures, .. }) = nodes[1].handle_message(&message).unwrap().pop() {
+                assert_eq!(block_hash, block.block_hash);
+                certificate = Some(signatures);
+            }
+        }
+
+        // 主节点自己的提交签名加上前两个验证者即达到 2f+1
+        let signatures = certificate.unwrap();
+        assert_eq!(signatures.len(), 3);
+        for (validator, signature) in signatures {
+            PBFT::add_commit(&mut block, validator, signature).unwrap();
+        }
+        assert!(nodes[0].validate_block(&block).await.unwrap());
+        assert!(nodes[0].is_final(&block));
+    }
+
+    #[tokio::test]
+    async fn test_validator_signs_one_block_per_height() {
+        let mut nodes = setup().await;
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut first = Block::new(genesis.block_hash, Vec::new(), 1, 1).unwrap();
+        let mut second = Block::new(genesis.block_hash, Vec::new(), 1, 2).unwrap();
+        nodes[1].mine_block(&mut first).await.unwrap();
+        nodes[1].mine_block(&mut second).await.unwrap();
+
+        let mut pre_prepares = Vec::new();
+        for block in [&first, &second] {
+            let Some(ConsensusAction::Broadcast(message)) = nodes[1].propose(block).unwrap().pop() else {
+                panic!("primary broadcasts a pre-prepare");
+            };
+            pre_prepares.push(message);
+        }
+        assert_eq!(nodes[0].handle_message(&pre_prepares[0]).unwrap().len(), 1);
+        assert!(nodes[0].handle_message(&pre_prepares[1]).unwrap().is_empty());
+
+        // 非主节点签名的预准备消息被拒绝
+        let forged = PBFT::encode(&PbftMessage::PrePrepare {
+            block_hash: first.block_hash,
+            height: 1,
+            primary: "v2".to_string(),
+            signature: vec![0u8; 64],
+        }).unwrap();
+        assert!(nodes[0].handle_message(&forged).is_err());
+    }
 }
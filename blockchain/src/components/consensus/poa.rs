@@ -0,0 +1,515 @@
+//! 权威证明 (Proof of Authority) 实现
+//!
+//! 面向许可链的轮换出块：时间被划分为固定长度的步骤，每个步骤由权威节点列表中
+//! 轮到的节点签名出块；与 Clique 一样，单个签名者在最近 `N/2 + 1` 个区块中最多出一个块，
+//! 出块者可以在区块封装中附带增删权威节点的投票，过半数权威同意后生效。
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::validator_set::sign_message;
+use super::{ConsensusAction, ConsensusComponent, ConsensusError, ConsensusResult, ConsensusStats, ValidatorSet};
+use crate::core::{AuthorityVote, Block, BlockSeal};
+
+/// 保留的历史快照数量，超过该深度的重组无法处理
+const MAX_SNAPSHOTS: usize = 1024;
+
+/// 权威证明配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoaConfig {
+    /// 每个出块步骤的时长（秒）
+    pub step_duration_secs: u64,
+}
+
+impl Default for PoaConfig {
+    fn default() -> Self {
+        Self { step_duration_secs: 5 }
+    }
+}
+
+/// 某个区块之后的权威状态快照
+#[derive(Debug, Clone, Default)]
+struct Snapshot {
+    /// 快照对应区块的高度
+    height: u64,
+    authorities: ValidatorSet,
+    /// 候选者 -> (投票者 -> 是否同意授权)
+    tally: BTreeMap<String, BTreeMap<String, bool>>,
+    /// 最近的出块者（高度 -> 签名者）
+    recent_signers: BTreeMap<u64, String>,
+    /// 最新区块所在的步骤
+    last_step: Option<u64>,
+}
+
+impl Snapshot {
+    /// 单个签名者在连续多少个区块中最多出一个块
+    fn signer_limit(&self) -> u64 {
+        self.authorities.len() as u64 / 2 + 1
+    }
+
+    /// 签名者是否在最近的区块中出过块
+    fn signed_recently(&self, signer: &str, height: u64) -> bool {
+        let window_start = height.saturating_sub(self.signer_limit() - 1);
+        self.recent_signers
+            .range(window_start..height)
+            .any(|(_, recent)| recent == signer)
+    }
+
+    /// 指定步骤轮到的权威节点
+    fn step_authority(&self, step: u64) -> Option<String> {
+        let addresses = self.authorities.addresses();
+        if addresses.is_empty() {
+            return None;
+        }
+        Some(addresses[(step % addresses.len() as u64) as usize].clone())
+    }
+
+    /// 记录投票，过半数权威同意时执行增删
+    fn apply_vote(&mut self, voter: &str, vote: &AuthorityVote) {
+        let already_applied = self.authorities.contains(&vote.candidate) == vote.authorize;
+        if already_applied {
+            return;
+        }
+
+        let votes = self.tally.entry(vote.candidate.clone()).or_default();
+        votes.insert(voter.to_string(), vote.authorize);
+        let in_favour = votes.iter()
+            .filter(|(voter, authorize)| **authorize == vote.authorize && self.authorities.contains(voter))
+            .count();
+        if in_favour <= self.authorities.len() / 2 {
+            return;
+        }
+
+        self.tally.remove(&vote.candidate);
+        if vote.authorize {
+            self.authorities.insert(vote.candidate.clone(), 1, vote.public_key.clone());
+        } else {
+            self.authorities.remove(&vote.candidate);
+            // 被移除的权威节点的投票作废
+            for votes in self.tally.values_mut() {
+                votes.remove(&vote.candidate);
+            }
+        }
+    }
+}
+
+/// 权威证明实现
+#[derive(Debug)]
+pub struct ProofOfAuthority {
+    config: PoaConfig,
+    /// 每个已导入区块之后的快照（区块哈希 -> 快照），分叉上的区块各自拥有快照
+    snapshots: HashMap<[u8; 32], Snapshot>,
+    /// 主链链头的区块哈希
+    head: [u8; 32],
+    /// 本节点地址与 Ed25519 私钥
+    signer: Option<(String, Vec<u8>)>,
+    /// 本节点希望发起的投票（候选者 -> 投票）
+    proposals: BTreeMap<String, AuthorityVote>,
+}
+
+impl ProofOfAuthority {
+    pub fn new(config: PoaConfig, authorities: ValidatorSet, genesis_hash: [u8; 32]) -> Self {
+        let genesis = Snapshot {
+            authorities,
+            ..Snapshot::default()
+        };
+        Self {
+            config,
+            snapshots: HashMap::from([(genesis_hash, genesis)]),
+            head: genesis_hash,
+            signer: None,
+            proposals: BTreeMap::new(),
+        }
+    }
+
+    /// 当前的权威节点集合
+    pub fn authorities(&self) -> &ValidatorSet {
+        &self.latest_snapshot().authorities
+    }
+
+    /// 发起增删权威节点的投票，之后本节点出块时附带该投票
+    pub fn propose(&mut self, candidate: String, public_key: Vec<u8>, authorize: bool) {
+        self.proposals.insert(candidate.clone(), AuthorityVote {
+            candidate,
+            public_key,
+            authorize,
+        });
+    }
+
+    /// 撤回投票
+    pub fn discard_proposal(&mut self, candidate: &str) {
+        self.proposals.remove(candidate);
+    }
+
+    fn step_of(&self, timestamp: u64) -> u64 {
+        timestamp / self.config.step_duration_secs.max(1)
+    }
+
+    fn latest_snapshot(&self) -> &Snapshot {
+        self.snapshots.get(&self.head).expect("链头快照始终存在")
+    }
+
+    /// 父区块之后的状态；父区块未导入或已被裁剪时返回 `None`
+    fn parent_snapshot(&self, parent_hash: &[u8; 32]) -> Option<&Snapshot> {
+        self.snapshots.get(parent_hash)
+    }
+
+    /// 在父区块快照的基础上记录区块的出块者和投票
+    fn record_block(&mut self, block: &Block) -> ConsensusResult<()> {
+        if self.snapshots.contains_key(&block.block_hash) {
+            return Ok(());
+        }
+        let seal = block.seal()
+            .ok_or_else(|| ConsensusError::ValidationFailed("区块缺少权威签名".to_string()))?;
+        let height = block.header.height;
+        let mut snapshot = self.parent_snapshot(&block.header.previous_hash)
+            .ok_or_else(|| ConsensusError::ValidationFailed(format!("高度 {} 的父区块快照未知", height)))?
+            .clone();
+
+        snapshot.height = height;
+        snapshot.recent_signers.insert(height, seal.signer.clone());
+        let window_start = height.saturating_sub(snapshot.signer_limit());
+        snapshot.recent_signers = snapshot.recent_signers.split_off(&window_start);
+        snapshot.last_step = Some(self.step_of(block.header.timestamp));
+        if let Some(vote) = &seal.authority_vote {
+            snapshot.apply_vote(&seal.signer, vote);
+        }
+        self.snapshots.insert(block.block_hash, snapshot);
+        Ok(())
+    }
+
+    /// 出块者签名的消息：区块哈希和附带的投票
+    fn seal_payload(block_hash: &[u8; 32], vote: Option<&AuthorityVote>) -> Vec<u8> {
+        let mut payload = block_hash.to_vec();
+        if let Some(vote) = vote {
+            payload.push(vote.authorize as u8);
+            payload.extend_from_slice(vote.candidate.as_bytes());
+            payload.extend_from_slice(&vote.public_key);
+        }
+        payload
+    }
+
+    /// 检查签名者是否可以在该高度和步骤出块
+    fn check_producer(&self, snapshot: &Snapshot, signer: &str, height: u64, step: u64) -> Result<(), String> {
+        let expected = snapshot.step_authority(step).ok_or("没有权威节点")?;
+        if expected != signer {
+            return Err(format!("步骤 {} 的出块者是 {}", step, expected));
+        }
+        if snapshot.last_step.is_some_and(|last_step| step <= last_step) {
+            return Err(format!("步骤 {} 已经出过块", step));
+        }
+        if snapshot.signed_recently(signer, height) {
+            return Err(format!("{} 最近已出块", signer));
+        }
+        Ok(())
+    }
+
+    /// 以指定时间戳签名封装区块
+    pub fn seal_block(&self, block: &mut Block, timestamp: u64) -> ConsensusResult<()> {
+        let (address, private_key) = self.signer.as_ref()
+            .ok_or_else(|| ConsensusError::MiningFailed("本节点没有签名身份".to_string()))?;
+        let height = block.header.height;
+        let snapshot = self.parent_snapshot(&block.header.previous_hash)
+            .ok_or_else(|| ConsensusError::MiningFailed(format!("高度 {} 的父区块快照未知", height)))?;
+        self.check_producer(snapshot, address, height, self.step_of(timestamp))
+            .map_err(ConsensusError::MiningFailed)?;
+
+        // 只附带尚未生效且本节点还没投过的票
+        let vote = self.proposals.values()
+            .find(|vote| {
+                snapshot.authorities.contains(&vote.candidate) != vote.authorize
+                    && snapshot.tally.get(&vote.candidate)
+                        .and_then(|votes| votes.get(address)) != Some(&vote.authorize)
+            })
+            .cloned();
+
+        block.header.timestamp = timestamp;
+        block.set_nonce(block.header.nonce);
+        let signature = sign_message(private_key, &Self::seal_payload(&block.block_hash, vote.as_ref()))?;
+        block.set_seal(BlockSeal {
+            signer: address.clone(),
+            signature,
+            authority_vote: vote,
+            ..BlockSeal::default()
+        });
+        Ok(())
+    }
+
+    fn verify_seal(&self, block: &Block) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.verify_seal_at(block, now)
+    }
+
+    /// 以 `now` 为本地时间校验封装；步骤超前本地步骤一步以上的区块无效，
+    /// 否则出块者可以预先占用未来的步骤
+    fn verify_seal_at(&self, block: &Block, now: u64) -> bool {
+        let Some(seal) = block.seal() else {
+            return false;
+        };
+        let height = block.header.height;
+        let step = self.step_of(block.header.timestamp);
+        let Some(snapshot) = self.parent_snapshot(&block.header.previous_hash) else {
+            return false;
+        };
+        let payload = Self::seal_payload(&block.block_hash, seal.authority_vote.as_ref());
+
+        block.header.hash() == block.block_hash
+            && step <= self.step_of(now) + 1
+            && self.check_producer(snapshot, &seal.signer, height, step).is_ok()
+            && snapshot.authorities.verify(&seal.signer, &payload, &seal.signature)
+    }
+}
+
+impl ConsensusComponent for ProofOfAuthority {
+    fn name(&self) -> &str {
+        "poa"
+    }
+
+    fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+        Box::pin(async move {
+            if self.authorities().is_empty() {
+                return Err(ConsensusError::ValidationFailed("没有权威节点".to_string()).into());
+            }
+            Ok(())
+        })
+    }
+
+    fn shutdown(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+        Box::pin(async move {
+            Ok(())
+        })
+    }
+
+    fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>> {
+        let is_valid = self.verify_seal(block);
+        Box::pin(async move {
+            Ok(is_valid)
+        })
+    }
+
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            self.seal_block(block, now)
+        })
+    }
+
+    fn get_stats(&self) -> Pin<Box<dyn Future<Output = ConsensusResult<ConsensusStats>> + Send + '_>> {
+        Box::pin(async move {
+            let snapshot = self.latest_snapshot();
+            Ok(ConsensusStats {
+                total_blocks_mined: snapshot.height,
+                total_votes: snapshot.tally.values().map(|votes| votes.len() as u64).sum(),
+                consensus_participants: snapshot.authorities.len() as u64,
+                last_consensus_time: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+                last_finalized_height: 0,
+            })
+        })
+    }
+
+    fn set_signer(&mut self, address: String, private_key: Vec<u8>) -> ConsensusResult<()> {
+        if !self.authorities().contains(&address) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 不是权威节点", address)).into());
+        }
+        self.signer = Some((address, private_key));
+        Ok(())
+    }
+
+    /// 出块者由当前时间所在的步骤决定
+    fn select_proposer(&self, parent_hash: &[u8; 32], height: u64) -> ConsensusResult<Option<String>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.parent_snapshot(parent_hash)
+            .ok_or_else(|| ConsensusError::ValidationFailed(format!("高度 {} 的父区块快照未知", height)))?
+            .step_authority(self.step_of(now))
+            .map(Some)
+            .ok_or_else(|| ConsensusError::ValidationFailed("没有权威节点".to_string()).into())
+    }
+
+    fn on_block_imported(&mut self, block: &Block) -> ConsensusResult<Vec<ConsensusAction>> {
+        self.record_block(block)?;
+        self.head = block.block_hash;
+
+        // 旧分支的快照保留到超出重组深度，重组回旧分支时无需重放
+        let floor = block.header.height.saturating_sub(MAX_SNAPSHOTS as u64);
+        self.snapshots.retain(|_, snapshot| snapshot.height >= floor);
+        Ok(Vec::new())
+    }
+
+    fn on_fork_block(&mut self, block: &Block) -> ConsensusResult<()> {
+        self.record_block(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+
+    /// 创建 n 个权威节点，每个节点一个 PoA 实例
+    async fn setup(n: usize) -> (Vec<ProofOfAuthority>, Vec<(String, Vec<u8>, Vec<u8>)>, Block) {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let mut authorities = ValidatorSet::new();
+        let mut keys = Vec::new();
+        for name in ["alice", "bob", "carol", "dave"].iter().take(n) {
+            let (private_key, public_key) = engine.generate_keypair("ed25519").unwrap();
+            authorities.insert(name.to_string(), 1, public_key.clone());
+            keys.push((name.to_string(), private_key, public_key));
+        }
+
+        let genesis = Block::create_genesis_block().unwrap();
+        let nodes = keys.iter()
+            .map(|(address, private_key, _)| {
+                let mut poa = ProofOfAuthority::new(PoaConfig { step_duration_secs: 5 }, authorities.clone(), genesis.block_hash);
+                poa.set_signer(address.clone(), private_key.clone()).unwrap();
+                poa
+            })
+            .collect();
+        (nodes, keys, genesis)
+    }
+
+    /// 由指定节点在指定步骤出块，并让所有节点导入
+    async fn produce(nodes: &mut [ProofOfAuthority], producer: usize, parent: &Block, step: u64) -> ConsensusResult<Block> {
+        let mut block = Block::new(parent.block_hash, Vec::new(), parent.header.height + 1, 1)?;
+        nodes[producer].seal_block(&mut block, step * 5)?;
+        for node in nodes.iter_mut() {
+            assert!(node.validate_block(&block).await?);
+            node.on_block_imported(&block)?;
+        }
+        Ok(block)
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_steps() {
+        let (mut nodes, _, genesis) = setup(3).await;
+
+        // 地址排序为 alice, bob, carol；步骤 1 轮到 bob
+        let block = produce(&mut nodes, 1, &genesis, 1).await.unwrap();
+        assert_eq!(block.seal().unwrap().signer, "bob");
+
+        let mut wrong = Block::new(block.block_hash, Vec::new(), 2, 1).unwrap();
+        assert!(nodes[0].seal_block(&mut wrong, 5 * 5).is_err());
+
+        // 伪造签名者的区块无法通过验证
+        nodes[2].seal_block(&mut wrong, 5 * 5).unwrap();
+        let mut forged = wrong.clone();
+        let mut seal = forged.seal().unwrap().clone();
+        seal.signer = "alice".to_string();
+        forged.set_seal(seal);
+        assert!(!nodes[0].validate_block(&forged).await.unwrap());
+        assert!(nodes[0].validate_block(&wrong).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_signer_limit() {
+        let (mut nodes, _, genesis) = setup(3).await;
+
+        let first = produce(&mut nodes, 0, &genesis, 3).await.unwrap();
+        // alice 在下一个区块再次轮到（步骤 6），但最近已出块
+        let mut again = Block::new(first.block_hash, Vec::new(), 2, 1).unwrap();
+        assert!(nodes[0].seal_block(&mut again, 6 * 5).is_err());
+
+        // 同一步骤不能出两个块
+        let mut same_step = Block::new(first.block_hash, Vec::new(), 2, 1).unwrap();
+        assert!(nodes[0].seal_block(&mut same_step, 3 * 5).is_err());
+
+        let second = produce(&mut nodes, 1, &first, 7).await.unwrap();
+        produce(&mut nodes, 0, &second, 9).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_vote_to_add_and_remove_authority() {
+        let (mut nodes, keys, genesis) = setup(4).await;
+        let (_, _, dave_key) = keys[3].clone();
+
+        // 只有 alice、bob、carol 是权威节点
+        for node in nodes.iter_mut() {
+            node.snapshots.get_mut(&genesis.block_hash).unwrap().authorities.remove("dave");
+        }
+        nodes[0].propose("dave".to_string(), dave_key.clone(), true);
+        nodes[1].propose("dave".to_string(), dave_key, true);
+
+        let first = produce(&mut nodes, 0, &genesis, 3).await.unwrap();
+        assert!(!nodes[2].authorities().contains("dave"));
+        let second = produce(&mut nodes, 1, &first, 4).await.unwrap();
+        assert!(nodes[2].authorities().contains("dave"));
+        assert_eq!(nodes[2].authorities().len(), 4);
+
+        // 四个权威节点需要三票才能移除 carol
+        for node in nodes.iter_mut().take(3) {
+            node.discard_proposal("dave");
+            node.propose("carol".to_string(), Vec::new(), false);
+        }
+        // 地址排序为 alice, bob, carol, dave
+        let third = produce(&mut nodes, 3, &second, 7).await.unwrap();
+        let fourth = produce(&mut nodes, 0, &third, 8).await.unwrap();
+        let fifth = produce(&mut nodes, 1, &fourth, 9).await.unwrap();
+        assert!(nodes[3].authorities().contains("carol"));
+        produce(&mut nodes, 2, &fifth, 10).await.unwrap();
+        assert!(!nodes[3].authorities().contains("carol"));
+        assert_eq!(nodes[3].authorities().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_steps_ahead_of_local_time() {
+        let (nodes, _, genesis) = setup(3).await;
+        let now = 1_000 * 5;
+
+        // 步骤 1001 轮到 carol，1002 轮到 alice
+        let mut next_step = Block::new(genesis.block_hash, Vec::new(), 1, 1).unwrap();
+        nodes[2].seal_block(&mut next_step, 1_001 * 5).unwrap();
+        assert!(nodes[0].verify_seal_at(&next_step, now));
+
+        let mut ahead = Block::new(genesis.block_hash, Vec::new(), 1, 1).unwrap();
+        nodes[0].seal_block(&mut ahead, 1_002 * 5).unwrap();
+        assert!(!nodes[1].verify_seal_at(&ahead, now));
+        assert!(nodes[1].verify_seal_at(&ahead, now + 5));
+    }
+
+    #[tokio::test]
+    async fn test_fork_blocks_use_their_parent_snapshot() {
+        let (mut nodes, _, genesis) = setup(3).await;
+        // 主链上 bob 在步骤 1 出块，分叉上 carol 在步骤 2 出同高度的块
+        let canonical = produce(&mut nodes, 1, &genesis, 1).await.unwrap();
+        let mut fork = Block::new(genesis.block_hash, Vec::new(), 1, 1).unwrap();
+        nodes[2].seal_block(&mut fork, 2 * 5).unwrap();
+        for node in nodes.iter_mut() {
+            assert!(node.validate_block(&fork).await.unwrap());
+            node.on_fork_block(&fork).unwrap();
+        }
+
+        // 步骤 4 轮到 bob：他只在主链上刚出过块，分叉上可以出块
+        let mut bob_on_fork = Block::new(fork.block_hash, Vec::new(), 2, 1).unwrap();
+        nodes[1].seal_block(&mut bob_on_fork, 4 * 5).unwrap();
+        assert!(nodes[0].validate_block(&bob_on_fork).await.unwrap());
+        let mut bob_on_canonical = Block::new(canonical.block_hash, Vec::new(), 2, 1).unwrap();
+        assert!(nodes[1].seal_block(&mut bob_on_canonical, 4 * 5).is_err());
+
+        // 步骤 5 轮到 carol：情况相反
+        let mut carol_on_canonical = Block::new(canonical.block_hash, Vec::new(), 2, 1).unwrap();
+        nodes[2].seal_block(&mut carol_on_canonical, 5 * 5).unwrap();
+        assert!(nodes[0].validate_block(&carol_on_canonical).await.unwrap());
+        let mut carol_on_fork = Block::new(fork.block_hash, Vec::new(), 2, 1).unwrap();
+        assert!(nodes[2].seal_block(&mut carol_on_fork, 5 * 5).is_err());
+
+        // 父区块未知的区块无法验证
+        let mut orphan = Block::new([7u8; 32], Vec::new(), 2, 1).unwrap();
+        assert!(nodes[2].seal_block(&mut orphan, 5 * 5).is_err());
+    }
+}
@@ -3,27 +3,36 @@
 use std::pin::Pin;
 use std::future::Future;
 
-use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError};
+use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError, ValidatorSet};
+use super::validator_set::{proposer_seed, sign_block};
 use crate::core::{Block};
 
 /// 权益证明实现
 #[derive(Debug)]
 pub struct ProofOfStake {
-    validators: std::collections::HashMap<String, u64>, // 验证者地址 -> 权益数量
+    validators: ValidatorSet,
     min_stake: u64,
+    /// 本节点地址与 Ed25519 私钥
+    signer: Option<(String, Vec<u8>)>,
 }
 
 impl ProofOfStake {
     pub fn new(min_stake: u64) -> Self {
         Self {
-            validators: std::collections::HashMap::new(),
+            validators: ValidatorSet::new(),
             min_stake,
+            signer: None,
         }
     }
 
     pub fn add_validator(&mut self, address: String, stake: u64) {
+        self.add_validator_with_key(address, stake, Vec::new());
+    }
+
+    /// 添加带签名公钥的验证者（参与出块和 BFT 投票时需要）
+    pub fn add_validator_with_key(&mut self, address: String, stake: u64, public_key: Vec<u8>) {
         if stake >= self.min_stake {
-            self.validators.insert(address, stake);
+            self.validators.insert(address, stake, public_key);
         }
     }
 
@@ -31,34 +40,30 @@ impl ProofOfStake {
         self.validators.remove(address);
     }
 
-    fn select_validator(&self) -> Option<String> {
-        if self.validators.is_empty() {
-            return None;
-        }
+    /// 获取验证者集合
+    pub fn validator_set(&self) -> &ValidatorSet {
+        &self.validators
+    }
 
-        let total_stake: u64 = self.validators.values().sum();
+    /// 按权益加权、以父区块哈希为种子确定性地选择验证者
+    fn select_validator(&self, parent_hash: &[u8; 32], height: u64) -> Option<String> {
+        let total_stake = self.validators.total_stake();
         if total_stake == 0 {
             return None;
         }
 
-        // 简化的随机选择逻辑
-        use rand::Rng;
-        let mut rng = rand::rng();
-        let random_value: u64 = rng.random_range(0..total_stake);
-        
-        let mut current_stake = 0u64;
-        for (address, stake) in &self.validators {
-            current_stake += stake;
-            if random_value < current_stake {
-                return Some(address.clone());
-            }
-        }
-
-        None
+        let random_value = proposer_seed(parent_hash, height) % total_stake;
+        self.validators
+            .select_weighted(random_value)
+            .map(|validator| validator.address.clone())
     }
 }
 
 impl ConsensusComponent for ProofOfStake {
+    fn name(&self) -> &str {
+        "pos"
+    }
+
     fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
         Box::pin(async move {
             Ok(())
@@ -71,23 +76,25 @@ impl ConsensusComponent for ProofOfStake {
         })
     }
 
-    #[allow(unused_variables)]
     fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>> {
+        let is_valid = block.header.hash() == block.block_hash
+            && self.select_validator(&block.header.previous_hash, block.header.height)
+                .is_some_and(|proposer| self.validators.verify_block_signature(block, &proposer));
         Box::pin(async move {
-            // 简化的验证逻辑
-            Ok(true)
+            Ok(is_valid)
         })
     }
 
-    #[allow(unused_variables)]
-    fn mine_block(&self, block: &mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>> {
         Box::pin(async move {
-            if let Some(validator) = self.select_validator() {
-                // 设置区块的验证者信息
-                // 这里需要根据实际的Block结构来设置
-                Ok(())
-            } else {
-                Err(ConsensusError::MiningFailed("没有可用的验证者".to_string()).into())
+            let proposer = self.select_validator(&block.header.previous_hash, block.header.height)
+                .ok_or_else(|| ConsensusError::MiningFailed("没有可用的验证者".to_string()))?;
+
+            match &self.signer {
+                Some((address, private_key)) if *address == proposer => {
+                    sign_block(block, address, private_key)
+                }
+                _ => Err(ConsensusError::MiningFailed(format!("本高度的出块者是 {}", proposer)).into()),
             }
         })
     }
@@ -102,7 +109,22 @@ impl ConsensusComponent for ProofOfStake {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_secs(),
+                last_finalized_height: 0,
             })
         })
     }
+
+    fn set_signer(&mut self, address: String, private_key: Vec<u8>) -> ConsensusResult<()> {
+        if !self.validators.contains(&address) {
+            return Err(ConsensusError::ValidationFailed(format!("{} 不是验证者", address)).into());
+        }
+        self.signer = Some((address, private_key));
+        Ok(())
+    }
+
+    fn select_proposer(&self, parent_hash: &[u8; 32], height: u64) -> ConsensusResult<Option<String>> {
+        self.select_validator(parent_hash, height)
+            .map(Some)
+            .ok_or_else(|| ConsensusError::ValidationFailed("没有可用的验证者".to_string()).into())
+    }
 }
@@ -3,50 +3,50 @@
 use std::pin::Pin;
 use std::future::Future;
 
-use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError};
+use super::{ConsensusComponent, ConsensusResult, ConsensusStats, ConsensusError, ChainHead};
+use super::miner::{Miner, MinerConfig, difficulty_to_target};
 use crate::core::{Block};
-use sha2::{Sha256, Digest};
 
 /// 工作量证明实现
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct ProofOfWork {
     difficulty: u32,
-    target: [u8; 32],
+    miner: Miner,
 }
 
 impl ProofOfWork {
     pub fn new(difficulty: u32) -> Self {
-        let mut target = [0u8; 32];
-        let leading_zeros = difficulty / 8;
-        let remaining_bits = difficulty % 8;
-        
-        for i in 0..leading_zeros {
-            target[i as usize] = 0;
-        }
-        
-        if remaining_bits > 0 {
-            target[leading_zeros as usize] = 0xFF >> remaining_bits;
+        Self::with_miner_config(difficulty, MinerConfig::default())
+    }
+
+    /// 使用指定的矿工配置（线程数、nonce 空间）创建
+    pub fn with_miner_config(difficulty: u32, config: MinerConfig) -> Self {
+        Self {
+            difficulty,
+            miner: Miner::new(config),
         }
-        
-        Self { difficulty, target }
     }
 
-    fn calculate_hash(&self, block: &Block) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(&block.header.previous_hash);
-        hasher.update(&block.header.merkle_root);
-        hasher.update(&block.header.timestamp.to_le_bytes());
-        hasher.update(&block.header.nonce.to_le_bytes());
-        hasher.finalize().into()
+    /// 获取矿工句柄，可用于取消挖矿和读取算力
+    pub fn miner(&self) -> &Miner {
+        &self.miner
     }
 
-    fn is_valid_hash(&self, hash: &[u8; 32]) -> bool {
-        hash < &self.target
+    /// 验证区块哈希满足区块头声明的难度，且该难度不低于最低难度
+    fn is_valid_pow(&self, block: &Block) -> bool {
+        let hash = block.header.hash();
+        hash == block.block_hash
+            && block.header.difficulty >= self.difficulty
+            && hash <= difficulty_to_target(block.header.difficulty)
     }
 }
 
 impl ConsensusComponent for ProofOfWork {
+    fn name(&self) -> &str {
+        "pow"
+    }
+
     fn initialize(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
         Box::pin(async move {
             Ok(())
@@ -54,51 +54,37 @@ impl ConsensusComponent for ProofOfWork {
     }
 
     fn shutdown(&mut self) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
+        self.miner.cancel();
         Box::pin(async move {
             Ok(())
         })
     }
 
     fn validate_block(&self, block: &Block) -> Pin<Box<dyn Future<Output = ConsensusResult<bool>> + Send + '_>> {
-        let hash = self.calculate_hash(block);
-        let is_valid = self.is_valid_hash(&hash);
+        let is_valid = self.is_valid_pow(block);
         Box::pin(async move {
             Ok(is_valid)
         })
     }
 
-    fn mine_block(&self, block: &mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + '_>> {
-        let _difficulty = self.difficulty;
-        let target = self.target;
-        let block_data = (
-            block.header.previous_hash,
-            block.header.merkle_root,
-            block.header.timestamp,
-        );
-        
+    fn mine_block<'a>(&'a self, block: &'a mut Block) -> Pin<Box<dyn Future<Output = ConsensusResult<()>> + Send + 'a>> {
         Box::pin(async move {
-            let mut nonce = 0u64;
-            let max_nonce = u64::MAX;
-            
-            while nonce < max_nonce {
-                // 重新计算哈希
-                let mut hasher = sha2::Sha256::new();
-                hasher.update(&block_data.0);
-                hasher.update(&block_data.1);
-                hasher.update(&block_data.2.to_le_bytes());
-                hasher.update(&nonce.to_le_bytes());
-                let hash: [u8; 32] = hasher.finalize().into();
-                
-                if &hash < &target {
-                    // 这里我们无法直接修改block，因为它在闭包外
-                    // 在实际应用中，应该通过其他方式返回nonce和hash
-                    return Ok(());
-                }
-                
-                nonce += 1;
+            if block.header.difficulty < self.difficulty {
+                return Err(ConsensusError::MiningFailed("区块难度低于最低难度".to_string()).into());
             }
-            
-            Err(ConsensusError::MiningFailed("无法找到有效的nonce".to_string()).into())
+
+            // 只把区块头交给矿工线程，挖矿期间不触碰交易列表，也不阻塞异步运行时；
+            // 任务在交出前创建，期间到达的取消请求同样生效
+            let miner = self.miner.clone();
+            let job = miner.new_job();
+            let header = block.header.clone();
+            let target = difficulty_to_target(header.difficulty);
+            let solution = tokio::task::spawn_blocking(move || miner.mine_job(&job, &header, target))
+                .await
+                .map_err(|e| ConsensusError::MiningFailed(format!("挖矿任务异常: {}", e)))??;
+
+            block.set_pow_solution(solution.nonce, solution.timestamp);
+            Ok(())
         })
     }
 
@@ -112,7 +98,17 @@ impl ConsensusComponent for ProofOfWork {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_secs(),
+                last_finalized_height: 0,
             })
         })
     }
+
+    /// 累计工作量最大的链胜出
+    fn prefer_chain(&self, current: &ChainHead, candidate: &ChainHead) -> bool {
+        candidate.total_difficulty > current.total_difficulty
+    }
+
+    fn cancel_sealing(&self) {
+        self.miner.cancel();
+    }
 }
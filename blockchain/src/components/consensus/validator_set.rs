@@ -0,0 +1,198 @@
+//! 验证者集合
+//!
+//! PoS 与 BFT 类共识（HotStuff 等）共用的验证者集合和签名工具
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+
+use super::{ConsensusResult, ConsensusError};
+use crate::components::cryptography::signature::{Ed25519Algorithm, SignatureAlgorithm};
+use crate::core::{Block, BlockSeal};
+
+/// 验证者信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorInfo {
+    /// 验证者地址
+    pub address: String,
+    /// 权益数量（投票权重）
+    pub stake: u64,
+    /// Ed25519 公钥，未登记公钥的验证者无法参与签名投票
+    pub public_key: Vec<u8>,
+}
+
+/// 验证者集合
+///
+/// 使用有序映射保存，保证所有节点遍历顺序一致（领导者轮换依赖该顺序）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidatorSet {
+    validators: BTreeMap<String, ValidatorInfo>,
+}
+
+impl ValidatorSet {
+    pub fn new() -> Self {
+        Self {
+            validators: BTreeMap::new(),
+        }
+    }
+
+    /// 添加或更新验证者
+    pub fn insert(&mut self, address: String, stake: u64, public_key: Vec<u8>) {
+        self.validators.insert(address.clone(), ValidatorInfo {
+            address,
+            stake,
+            public_key,
+        });
+    }
+
+    pub fn remove(&mut self, address: &str) -> Option<ValidatorInfo> {
+        self.validators.remove(address)
+    }
+
+    pub fn get(&self, address: &str) -> Option<&ValidatorInfo> {
+        self.validators.get(address)
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.validators.contains_key(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.validators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ValidatorInfo> {
+        self.validators.values()
+    }
+
+    /// 按地址排序的验证者列表
+    pub fn addresses(&self) -> Vec<String> {
+        self.validators.keys().cloned().collect()
+    }
+
+    /// 总权益
+    pub fn total_stake(&self) -> u64 {
+        self.validators.values().map(|v| v.stake).sum()
+    }
+
+    /// 法定人数阈值：严格超过总权益的 2/3
+    pub fn quorum_threshold(&self) -> u64 {
+        self.total_stake() * 2 / 3 + 1
+    }
+
+    /// 计算一组签名者的权益之和（重复地址和未知地址不计入）
+    pub fn voting_power<'a>(&self, voters: impl IntoIterator<Item = &'a str>) -> u64 {
+        let mut seen = std::collections::HashSet::new();
+        voters
+            .into_iter()
+            .filter(|voter| seen.insert(*voter))
+            .filter_map(|voter| self.validators.get(voter))
+            .map(|v| v.stake)
+            .sum()
+    }
+
+    /// 签名者的权益是否达到法定人数
+    pub fn has_quorum<'a>(&self, voters: impl IntoIterator<Item = &'a str>) -> bool {
+        !self.is_empty() && self.voting_power(voters) >= self.quorum_threshold()
+    }
+
+    /// 按权益加权选择验证者，`random_value` 取值范围为 `0..total_stake`
+    pub fn select_weighted(&self, random_value: u64) -> Option<&ValidatorInfo> {
+        let mut current_stake = 0u64;
+        for validator in self.validators.values() {
+            current_stake += validator.stake;
+            if random_value < current_stake {
+                return Some(validator);
+            }
+        }
+        None
+    }
+
+    /// 验证某个验证者对消息的签名
+    pub fn verify(&self, address: &str, message: &[u8], signature: &[u8]) -> bool {
+        match self.validators.get(address) {
+            Some(validator) => Ed25519Algorithm
+                .verify(message, signature, &validator.public_key)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// 验证区块封装中出块者的签名
+    pub fn verify_block_signature(&self, block: &Block, expected_signer: &str) -> bool {
+        match block.seal() {
+            Some(seal) => seal.signer == expected_signer
+                && self.verify(&seal.signer, &block.block_hash, &seal.signature),
+            None => false,
+        }
+    }
+}
+
+/// 由父区块哈希和高度得到确定性的随机数，所有节点计算结果一致
+pub fn proposer_seed(parent_hash: &[u8; 32], height: u64) -> u64 {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(parent_hash);
+    hasher.update(height.to_be_bytes());
+    let hash = hasher.finalize();
+
+    let mut seed = [0u8; 8];
+    seed.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(seed)
+}
+
+/// 出块者对区块哈希签名并写入区块封装
+pub fn sign_block(block: &mut Block, signer: &str, private_key: &[u8]) -> ConsensusResult<()> {
+    let signature = sign_message(private_key, &block.block_hash)?;
+    block.set_seal(BlockSeal {
+        signer: signer.to_string(),
+        signature,
+        ..BlockSeal::default()
+    });
+    Ok(())
+}
+
+/// 使用 Ed25519 私钥对共识消息签名
+pub fn sign_message(private_key: &[u8], message: &[u8]) -> ConsensusResult<Vec<u8>> {
+    Ed25519Algorithm
+        .sign(message, private_key)
+        .map_err(|e| ConsensusError::VotingFailed(format!("签名失败: {}", e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+
+    #[test]
+    fn test_quorum_threshold() {
+        let mut set = ValidatorSet::new();
+        for i in 0..4 {
+            set.insert(format!("validator_{}", i), 10, vec![]);
+        }
+
+        assert_eq!(set.quorum_threshold(), 27);
+        assert!(!set.has_quorum(["validator_0", "validator_1"]));
+        assert!(!set.has_quorum(["validator_0", "validator_0", "validator_0"]));
+        assert!(set.has_quorum(["validator_0", "validator_1", "validator_2"]));
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify() {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let (private_key, public_key) = engine.generate_keypair("ed25519").unwrap();
+
+        let mut set = ValidatorSet::new();
+        set.insert("alice".to_string(), 100, public_key);
+
+        let signature = sign_message(&private_key, b"vote").unwrap();
+        assert!(set.verify("alice", b"vote", &signature));
+        assert!(!set.verify("alice", b"other", &signature));
+        assert!(!set.verify("bob", b"vote", &signature));
+    }
+}
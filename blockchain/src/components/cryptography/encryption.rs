@@ -4,7 +4,6 @@ use crate::components::{ComponentResult, ComponentError};
 use aes_gcm::{Aes256Gcm, Key, aead::{Aead, KeyInit, generic_array::GenericArray}};
 #[cfg(feature = "crypto-advanced")]
 use chacha20poly1305::{ChaCha20Poly1305, Key as ChaChaKey, Nonce as ChaChaNonce};
-#[cfg(feature = "crypto-advanced")]
 // use chacha20poly1305::aead::KeyInit as ChaChaKeyInit;
 use std::collections::HashMap;
 
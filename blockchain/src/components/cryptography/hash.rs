@@ -86,6 +86,57 @@ impl HashAlgorithm for Blake2sAlgorithm {
     }
 }
 
+/// Keccak-256 算法（以太坊使用的原始 Keccak 填充，不同于 NIST SHA3-256）
+#[cfg(feature = "keccak")]
+pub struct Keccak256Algorithm;
+
+#[cfg(feature = "keccak")]
+impl Keccak256Algorithm {
+    /// 海绵结构的吞吐率（1600 - 2 * 256 位）
+    const RATE: usize = 136;
+
+    /// 计算 Keccak-256
+    pub fn digest(data: &[u8]) -> [u8; 32] {
+        let mut state = [0u64; 25];
+        let mut blocks = data.chunks_exact(Self::RATE);
+        for block in &mut blocks {
+            Self::absorb(&mut state, block);
+        }
+
+        // 填充：0x01 ... 0x80
+        let remainder = blocks.remainder();
+        let mut last = [0u8; Self::RATE];
+        last[..remainder.len()].copy_from_slice(remainder);
+        last[remainder.len()] ^= 0x01;
+        last[Self::RATE - 1] ^= 0x80;
+        Self::absorb(&mut state, &last);
+
+        let mut out = [0u8; 32];
+        for (chunk, lane) in out.chunks_exact_mut(8).zip(state.iter()) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        out
+    }
+
+    fn absorb(state: &mut [u64; 25], block: &[u8]) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+            *lane ^= u64::from_le_bytes(bytes.try_into().expect("8-byte chunk"));
+        }
+        keccak::f1600(state);
+    }
+}
+
+#[cfg(feature = "keccak")]
+impl HashAlgorithm for Keccak256Algorithm {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        Self::digest(data).to_vec()
+    }
+
+    fn name(&self) -> &str {
+        "keccak256"
+    }
+}
+
 impl HashEngine {
     /// 创建新的哈希引擎
     pub fn new() -> Self {
@@ -102,6 +153,8 @@ impl HashEngine {
         self.register_algorithm("sha512", Box::new(Sha512Algorithm));
         self.register_algorithm("blake2b", Box::new(Blake2bAlgorithm));
         self.register_algorithm("blake2s", Box::new(Blake2sAlgorithm));
+        #[cfg(feature = "keccak")]
+        self.register_algorithm("keccak256", Box::new(Keccak256Algorithm));
         
         self.initialized = true;
         Ok(())
@@ -191,6 +244,12 @@ impl HashEngine {
         result
     }
     
+    /// Keccak-256 哈希
+    #[cfg(feature = "keccak")]
+    pub fn keccak256(&self, data: &[u8]) -> [u8; 32] {
+        Keccak256Algorithm::digest(data)
+    }
+    
     /// 双 SHA256 哈希
     pub fn double_sha256(&self, data: &[u8]) -> [u8; 32] {
         let first_hash = self.sha256(data);
@@ -357,4 +416,22 @@ mod tests {
         assert!(algorithms.contains(&"blake2b".to_string()));
         assert!(algorithms.contains(&"blake2s".to_string()));
     }
+    
+    #[cfg(feature = "keccak")]
+    #[test]
+    fn test_keccak256_vectors() {
+        assert_eq!(
+            hex::encode(Keccak256Algorithm::digest(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(Keccak256Algorithm::digest(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        // 跨越一个吞吐块的输入
+        assert_eq!(
+            hex::encode(Keccak256Algorithm::digest(&[b'a'; 200])),
+            "96ea54061def936c4be90b518992fdc6f12f535068a256229aca54267b4d084d"
+        );
+    }
 }
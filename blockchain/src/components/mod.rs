@@ -10,7 +10,7 @@ pub mod consensus;
 pub use cryptography::{CryptographyComponent, HashEngine, SignatureEngine, EncryptionEngine};
 pub use network::{NetworkComponent, P2PNetwork, MessageRouter, PeerManager};
 pub use storage::{StorageComponent, BlockStorage, StateStorage, TransactionStorage};
-pub use consensus::{ConsensusComponent, ProofOfWork, ProofOfStake, DelegatedProofOfStake, PBFT};
+pub use consensus::{ConsensusComponent, ProofOfWork, ProofOfStake, DelegatedProofOfStake, PBFT, HotStuff};
 
 // 组件错误类型
 #[derive(Debug, thiserror::Error)]
@@ -0,0 +1,302 @@
+// 地址簿
+//
+// 记录可连接的对端地址，分为 new（听说过）和 tried（成功连接过）两类桶。
+// 地址按密钥哈希后的子网分组落桶，单个子网或单个来源最多占满少数几个桶，
+// 难以用大量地址挤掉其他对端；地址簿连同桶密钥一起持久化到磁盘，重启后桶分布不变。
+use crate::components::{ComponentError, ComponentResult};
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+/// 地址簿的默认文件名
+pub const ADDRESS_BOOK_FILE: &str = "peers.json";
+
+/// new 桶数量
+pub const NEW_BUCKET_COUNT: usize = 64;
+
+/// tried 桶数量
+pub const TRIED_BUCKET_COUNT: usize = 16;
+
+/// 每个桶的容量
+pub const BUCKET_SIZE: usize = 64;
+
+/// 从未连接成功的地址连续失败这么多次后删除
+const MAX_FAILED_ATTEMPTS: u32 = 10;
+
+/// 地址记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressEntry {
+    /// 对端的 TCP 地址
+    pub address: SocketAddr,
+    /// 告知本节点该地址的来源
+    pub source: Option<IpAddr>,
+    /// 首次记录时间（Unix 秒）
+    pub first_seen: u64,
+    /// 最近一次连接成功时间（Unix 秒），0 表示从未成功
+    pub last_success: u64,
+    /// 自上次成功以来的连接尝试次数
+    pub attempts: u32,
+    /// 是否在 tried 桶中
+    pub tried: bool,
+}
+
+/// 持久化格式
+#[derive(Serialize, Deserialize)]
+struct AddressBookFile {
+    key: [u8; 32],
+    entries: Vec<AddressEntry>,
+}
+
+/// 地址簿
+#[derive(Debug, Clone)]
+pub struct AddressBook {
+    /// 桶密钥，使外部无法预测地址落在哪个桶
+    key: [u8; 32],
+    entries: HashMap<SocketAddr, AddressEntry>,
+    path: Option<PathBuf>,
+}
+
+impl Default for AddressBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AddressBook {
+    /// 创建空的内存地址簿
+    pub fn new() -> Self {
+        Self {
+            key: rand::random(),
+            entries: HashMap::new(),
+            path: None,
+        }
+    }
+
+    /// 从文件加载地址簿，文件不存在时创建空地址簿；`save` 写回该文件
+    pub fn load(path: impl AsRef<Path>) -> ComponentResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        match std::fs::read_to_string(&path) {
+            Ok(json) => {
+                let file: AddressBookFile = serde_json::from_str(&json)
+                    .map_err(|e| ComponentError::ConfigurationError(format!("Invalid address book {}: {}", path.display(), e)))?;
+                Ok(Self {
+                    key: file.key,
+                    entries: file.entries.into_iter().map(|entry| (entry.address, entry)).collect(),
+                    path: Some(path),
+                })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self { path: Some(path), ..Self::new() }),
+            Err(e) => Err(ComponentError::StorageError(format!("Failed to read {}: {}", path.display(), e))),
+        }
+    }
+
+    /// 写回加载时的文件，内存地址簿不做任何事
+    pub fn save(&self) -> ComponentResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut entries: Vec<AddressEntry> = self.entries.values().cloned().collect();
+        entries.sort_by_key(|entry| entry.address);
+        let json = serde_json::to_string_pretty(&AddressBookFile { key: self.key, entries })
+            .map_err(|e| ComponentError::StorageError(e.to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ComponentError::StorageError(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| ComponentError::StorageError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// 记录新听说的地址，返回是否为新地址
+    ///
+    /// 所在 new 桶已满时淘汰桶内最差的地址（失败次数最多、最早记录）
+    pub fn add(&mut self, address: SocketAddr, source: Option<IpAddr>) -> bool {
+        if self.entries.contains_key(&address) || address.port() == 0 {
+            return false;
+        }
+        let bucket = self.new_bucket(&address, source.as_ref());
+        let in_bucket: Vec<&AddressEntry> = self.entries.values()
+            .filter(|entry| !entry.tried && self.new_bucket(&entry.address, entry.source.as_ref()) == bucket)
+            .collect();
+        if in_bucket.len() >= BUCKET_SIZE {
+            let worst = in_bucket.iter()
+                .max_by_key(|entry| (entry.attempts, std::cmp::Reverse(entry.first_seen)))
+                .map(|entry| entry.address);
+            if let Some(worst) = worst {
+                self.entries.remove(&worst);
+            }
+        }
+        self.entries.insert(address, AddressEntry {
+            address,
+            source,
+            first_seen: unix_now(),
+            last_success: 0,
+            attempts: 0,
+            tried: false,
+        });
+        true
+    }
+
+    /// 记录一次连接尝试；从未成功的地址失败过多时删除
+    pub fn mark_attempt(&mut self, address: &SocketAddr) {
+        let remove = match self.entries.get_mut(address) {
+            Some(entry) => {
+                entry.attempts += 1;
+                entry.last_success == 0 && entry.attempts >= MAX_FAILED_ATTEMPTS
+            }
+            None => false,
+        };
+        if remove {
+            self.entries.remove(address);
+        }
+    }
+
+    /// 连接成功，把地址移入 tried 桶；tried 桶已满时把其中最久未成功的地址退回 new 桶
+    pub fn mark_good(&mut self, address: &SocketAddr) {
+        if !self.entries.contains_key(address) {
+            self.add(*address, None);
+        }
+        let bucket = self.tried_bucket(address);
+        let in_bucket: Vec<&AddressEntry> = self.entries.values()
+            .filter(|entry| entry.tried && entry.address != *address && self.tried_bucket(&entry.address) == bucket)
+            .collect();
+        if in_bucket.len() >= BUCKET_SIZE
+            && let Some(oldest) = in_bucket.iter().min_by_key(|entry| entry.last_success).map(|entry| entry.address)
+            && let Some(entry) = self.entries.get_mut(&oldest)
+        {
+            entry.tried = false;
+        }
+        if let Some(entry) = self.entries.get_mut(address) {
+            entry.tried = true;
+            entry.attempts = 0;
+            entry.last_success = unix_now();
+        }
+    }
+
+    /// 随机选择最多 `count` 个候选地址，tried 和 new 各占一半，不足时互相补齐
+    pub fn select(&self, count: usize) -> Vec<SocketAddr> {
+        let mut rng = rand::rng();
+        let (mut tried, mut new): (Vec<&AddressEntry>, Vec<&AddressEntry>) =
+            self.entries.values().partition(|entry| entry.tried);
+        tried.shuffle(&mut rng);
+        new.shuffle(&mut rng);
+
+        let from_new = (count / 2).max(count.saturating_sub(tried.len())).min(new.len());
+        let from_tried = count.saturating_sub(from_new).min(tried.len());
+        tried.iter().take(from_tried)
+            .chain(new.iter().take(from_new))
+            .map(|entry| entry.address)
+            .collect()
+    }
+
+    pub fn get(&self, address: &SocketAddr) -> Option<&AddressEntry> {
+        self.entries.get(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// tried 桶中的地址数
+    pub fn tried_count(&self) -> usize {
+        self.entries.values().filter(|entry| entry.tried).count()
+    }
+
+    fn new_bucket(&self, address: &SocketAddr, source: Option<&IpAddr>) -> usize {
+        let source_group = source.map(group).unwrap_or_default();
+        self.bucket(&[b"new".as_slice(), &source_group, &group(&address.ip())], NEW_BUCKET_COUNT)
+    }
+
+    fn tried_bucket(&self, address: &SocketAddr) -> usize {
+        self.bucket(&[b"tried".as_slice(), &group(&address.ip()), address.to_string().as_bytes()], TRIED_BUCKET_COUNT)
+    }
+
+    fn bucket(&self, parts: &[&[u8]], count: usize) -> usize {
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        for part in parts {
+            hasher.update(part);
+        }
+        let digest = hasher.finalize();
+        (u64::from_le_bytes(digest[..8].try_into().unwrap()) % count as u64) as usize
+    }
+}
+
+/// 地址分组：IPv4 /16、IPv6 /32，回环地址各自成组
+fn group(address: &IpAddr) -> Vec<u8> {
+    match address {
+        _ if address.is_loopback() => address.to_string().into_bytes(),
+        IpAddr::V4(v4) => v4.octets()[..2].to_vec(),
+        IpAddr::V6(v6) => v6.octets()[..4].to_vec(),
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(address: &str) -> SocketAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn test_add_and_mark_good() {
+        let mut book = AddressBook::new();
+        assert!(book.add(addr("10.0.0.1:8333"), None));
+        assert!(!book.add(addr("10.0.0.1:8333"), None));
+        assert_eq!(book.tried_count(), 0);
+
+        book.mark_good(&addr("10.0.0.1:8333"));
+        assert_eq!(book.tried_count(), 1);
+        assert_eq!(book.select(10), vec![addr("10.0.0.1:8333")]);
+
+        // 从未成功的地址失败过多后被删除
+        book.add(addr("10.0.0.2:8333"), None);
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            book.mark_attempt(&addr("10.0.0.2:8333"));
+        }
+        assert!(book.get(&addr("10.0.0.2:8333")).is_none());
+    }
+
+    #[test]
+    fn test_single_source_cannot_flood_new_buckets() {
+        let mut book = AddressBook::new();
+        let source: IpAddr = "198.51.100.1".parse().unwrap();
+        for i in 0..1000u32 {
+            let address = SocketAddr::from(([10, 20, (i / 250) as u8, (i % 250) as u8], 8333));
+            book.add(address, Some(source));
+        }
+        // 同一来源、同一子网的地址只落入一个桶
+        assert_eq!(book.len(), BUCKET_SIZE);
+    }
+
+    #[test]
+    fn test_persistence_roundtrip() {
+        let path = std::env::temp_dir().join(format!("peers-{}.json", uuid::Uuid::new_v4()));
+        let mut book = AddressBook::load(&path).unwrap();
+        book.add(addr("10.0.0.1:8333"), None);
+        book.add(addr("10.1.0.1:8333"), None);
+        book.mark_good(&addr("10.1.0.1:8333"));
+        book.save().unwrap();
+
+        let reloaded = AddressBook::load(&path).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.tried_count(), 1);
+        assert_eq!(reloaded.key, book.key);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
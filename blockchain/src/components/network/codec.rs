@@ -0,0 +1,401 @@
+// 网络线路编解码
+//
+// 帧格式（大端）：
+//   magic(4) | 协议版本(2) | 网络ID(4) | 消息类型(1) | 负载长度(4) | 校验和(4) | 负载
+// 校验和为负载双重 SHA-256 的前 4 字节，负载为 bincode 编码的消息体。
+use crate::components::ComponentError;
+use super::message::{
+    NetworkMessage, PeerDiscoveryMessage, SyncRequestMessage, SyncResponseMessage, PingMessage, PongMessage,
+    InvMessage, GetDataMessage, CompactBlockMessage, GetBlockTxnMessage, BlockTxnMessage,
+    LightRequestMessage, LightResponseMessage, ConsensusMessage,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use sha2::{Digest, Sha256};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// 网络魔数
+pub const MAGIC: [u8; 4] = *b"BCRS";
+
+/// 当前协议版本
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// 默认网络ID
+pub const DEFAULT_NETWORK_ID: u32 = 1;
+
+/// 帧头长度
+pub const HEADER_LEN: usize = 19;
+
+/// 默认最大负载长度（4 MiB）
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+
+/// 线路上的消息类型编号，新增类型只能追加，不能修改已有编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageType {
+    Transaction = 0x01,
+    Block = 0x02,
+    PeerDiscovery = 0x03,
+    SyncRequest = 0x04,
+    SyncResponse = 0x05,
+    Ping = 0x06,
+    Pong = 0x07,
+    Inv = 0x08,
+    GetData = 0x09,
+    CompactBlock = 0x0a,
+    GetBlockTxn = 0x0b,
+    BlockTxn = 0x0c,
+    LightRequest = 0x0d,
+    LightResponse = 0x0e,
+    Consensus = 0x0f,
+}
+
+impl MessageType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Transaction),
+            0x02 => Some(Self::Block),
+            0x03 => Some(Self::PeerDiscovery),
+            0x04 => Some(Self::SyncRequest),
+            0x05 => Some(Self::SyncResponse),
+            0x06 => Some(Self::Ping),
+            0x07 => Some(Self::Pong),
+            0x08 => Some(Self::Inv),
+            0x09 => Some(Self::GetData),
+            0x0a => Some(Self::CompactBlock),
+            0x0b => Some(Self::GetBlockTxn),
+            0x0c => Some(Self::BlockTxn),
+            0x0d => Some(Self::LightRequest),
+            0x0e => Some(Self::LightResponse),
+            0x0f => Some(Self::Consensus),
+            _ => None,
+        }
+    }
+
+    pub fn of(message: &NetworkMessage) -> Self {
+        match message {
+            NetworkMessage::Transaction(_) => Self::Transaction,
+            NetworkMessage::Block(_) => Self::Block,
+            NetworkMessage::PeerDiscovery(_) => Self::PeerDiscovery,
+            NetworkMessage::SyncRequest(_) => Self::SyncRequest,
+            NetworkMessage::SyncResponse(_) => Self::SyncResponse,
+            NetworkMessage::Ping(_) => Self::Ping,
+            NetworkMessage::Pong(_) => Self::Pong,
+            NetworkMessage::Inv(_) => Self::Inv,
+            NetworkMessage::GetData(_) => Self::GetData,
+            NetworkMessage::CompactBlock(_) => Self::CompactBlock,
+            NetworkMessage::GetBlockTxn(_) => Self::GetBlockTxn,
+            NetworkMessage::BlockTxn(_) => Self::BlockTxn,
+            NetworkMessage::LightRequest(_) => Self::LightRequest,
+            NetworkMessage::LightResponse(_) => Self::LightResponse,
+            NetworkMessage::Consensus(_) => Self::Consensus,
+        }
+    }
+}
+
+/// 编解码错误
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid magic: {0:02x?}")]
+    InvalidMagic([u8; 4]),
+
+    #[error("Unsupported protocol version: {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("Network id mismatch: expected {expected}, got {actual}")]
+    NetworkMismatch { expected: u32, actual: u32 },
+
+    #[error("Unknown message type: {0:#04x}")]
+    UnknownMessageType(u8),
+
+    #[error("Frame too large: {size} bytes (max {max})")]
+    FrameTooLarge { size: usize, max: usize },
+
+    #[error("Checksum mismatch")]
+    ChecksumMismatch,
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("Handshake failed: {0}")]
+    HandshakeFailed(String),
+
+    #[error("Handshake timed out")]
+    HandshakeTimeout,
+
+    #[error("Peer authentication failed")]
+    AuthenticationFailed,
+
+    #[error("Frame decryption failed")]
+    DecryptionFailed,
+
+    #[error("Nonce exhausted")]
+    NonceExhausted,
+
+    #[error("Connection rejected: {0}")]
+    ConnectionRejected(String),
+}
+
+impl CodecError {
+    /// 是否为对端违反协议（应断开并惩罚对端），而不是本地 IO 问题或慢速链路
+    pub fn is_protocol_violation(&self) -> bool {
+        self.penalty() > 0
+    }
+
+    /// 对端地址因该错误累加的违规分
+    ///
+    /// 版本或网络不一致多为配置问题，只记少量分数；格式错误需要多次才会封禁；
+    /// 认证或解密失败只可能来自伪造数据，分数最高。IO 错误、超时和本地拒绝不计分。
+    pub fn penalty(&self) -> u32 {
+        match self {
+            CodecError::Io(_) | CodecError::HandshakeTimeout | CodecError::ConnectionRejected(_) => 0,
+            CodecError::UnsupportedVersion(_) | CodecError::NetworkMismatch { .. } => 10,
+            CodecError::InvalidMagic(_)
+            | CodecError::UnknownMessageType(_)
+            | CodecError::FrameTooLarge { .. }
+            | CodecError::ChecksumMismatch
+            | CodecError::Serialization(_)
+            | CodecError::HandshakeFailed(_)
+            | CodecError::NonceExhausted => 20,
+            CodecError::AuthenticationFailed | CodecError::DecryptionFailed => 50,
+        }
+    }
+}
+
+impl From<CodecError> for ComponentError {
+    fn from(err: CodecError) -> Self {
+        ComponentError::NetworkError(err.to_string())
+    }
+}
+
+/// 负载校验和
+pub fn checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(Sha256::digest(payload));
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// 编码消息体
+pub fn encode_payload(message: &NetworkMessage) -> Result<Vec<u8>, CodecError> {
+    let result = match message {
+        NetworkMessage::Transaction(tx) => bincode::serialize(tx),
+        NetworkMessage::Block(block) => bincode::serialize(block),
+        NetworkMessage::PeerDiscovery(msg) => bincode::serialize(msg),
+        NetworkMessage::SyncRequest(msg) => bincode::serialize(msg),
+        NetworkMessage::SyncResponse(msg) => bincode::serialize(msg),
+        NetworkMessage::Ping(msg) => bincode::serialize(msg),
+        NetworkMessage::Pong(msg) => bincode::serialize(msg),
+        NetworkMessage::Inv(msg) => bincode::serialize(msg),
+        NetworkMessage::GetData(msg) => bincode::serialize(msg),
+        NetworkMessage::CompactBlock(msg) => bincode::serialize(msg),
+        NetworkMessage::GetBlockTxn(msg) => bincode::serialize(msg),
+        NetworkMessage::BlockTxn(msg) => bincode::serialize(msg),
+        NetworkMessage::LightRequest(msg) => bincode::serialize(msg),
+        NetworkMessage::LightResponse(msg) => bincode::serialize(msg),
+        NetworkMessage::Consensus(msg) => bincode::serialize(msg),
+    };
+    result.map_err(|e| CodecError::Serialization(e.to_string()))
+}
+
+/// 按消息类型解码消息体
+pub fn decode_payload(message_type: MessageType, payload: &[u8]) -> Result<NetworkMessage, CodecError> {
+    fn decode<T: serde::de::DeserializeOwned>(payload: &[u8]) -> Result<T, CodecError> {
+        bincode::deserialize(payload).map_err(|e| CodecError::Serialization(e.to_string()))
+    }
+
+    Ok(match message_type {
+        MessageType::Transaction => NetworkMessage::Transaction(decode(payload)?),
+        MessageType::Block => NetworkMessage::Block(decode(payload)?),
+        MessageType::PeerDiscovery => NetworkMessage::PeerDiscovery(decode::<PeerDiscoveryMessage>(payload)?),
+        MessageType::SyncRequest => NetworkMessage::SyncRequest(decode::<SyncRequestMessage>(payload)?),
+        MessageType::SyncResponse => NetworkMessage::SyncResponse(decode::<SyncResponseMessage>(payload)?),
+        MessageType::Ping => NetworkMessage::Ping(decode::<PingMessage>(payload)?),
+        MessageType::Pong => NetworkMessage::Pong(decode::<PongMessage>(payload)?),
+        MessageType::Inv => NetworkMessage::Inv(decode::<InvMessage>(payload)?),
+        MessageType::GetData => NetworkMessage::GetData(decode::<GetDataMessage>(payload)?),
+        MessageType::CompactBlock => NetworkMessage::CompactBlock(decode::<CompactBlockMessage>(payload)?),
+        MessageType::GetBlockTxn => NetworkMessage::GetBlockTxn(decode::<GetBlockTxnMessage>(payload)?),
+        MessageType::BlockTxn => NetworkMessage::BlockTxn(decode::<BlockTxnMessage>(payload)?),
+        MessageType::LightRequest => NetworkMessage::LightRequest(decode::<LightRequestMessage>(payload)?),
+        MessageType::LightResponse => NetworkMessage::LightResponse(decode::<LightResponseMessage>(payload)?),
+        MessageType::Consensus => NetworkMessage::Consensus(decode::<ConsensusMessage>(payload)?),
+    })
+}
+
+/// 帧编解码器
+#[derive(Debug, Clone)]
+pub struct NetworkCodec {
+    network_id: u32,
+    max_frame_size: usize,
+}
+
+impl NetworkCodec {
+    pub fn new(network_id: u32) -> Self {
+        Self {
+            network_id,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn network_id(&self) -> u32 {
+        self.network_id
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+}
+
+impl Decoder for NetworkCodec {
+    type Item = NetworkMessage;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        // 先校验帧头，超大帧在负载到达之前就拒绝
+        let mut header = &src[..HEADER_LEN];
+        let mut magic = [0u8; 4];
+        header.copy_to_slice(&mut magic);
+        if magic != MAGIC {
+            return Err(CodecError::InvalidMagic(magic));
+        }
+        let version = header.get_u16();
+        if version != PROTOCOL_VERSION {
+            return Err(CodecError::UnsupportedVersion(version));
+        }
+        let network_id = header.get_u32();
+        if network_id != self.network_id {
+            return Err(CodecError::NetworkMismatch { expected: self.network_id, actual: network_id });
+        }
+        let type_byte = header.get_u8();
+        let message_type = MessageType::from_u8(type_byte).ok_or(CodecError::UnknownMessageType(type_byte))?;
+        let length = header.get_u32() as usize;
+        if length > self.max_frame_size {
+            return Err(CodecError::FrameTooLarge { size: length, max: self.max_frame_size });
+        }
+        let mut expected_checksum = [0u8; 4];
+        header.copy_to_slice(&mut expected_checksum);
+
+        if src.len() < HEADER_LEN + length {
+            src.reserve(HEADER_LEN + length - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+        let payload = src.split_to(length);
+        if checksum(&payload) != expected_checksum {
+            return Err(CodecError::ChecksumMismatch);
+        }
+
+        decode_payload(message_type, &payload).map(Some)
+    }
+}
+
+impl Encoder<NetworkMessage> for NetworkCodec {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: NetworkMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let payload = encode_payload(&item)?;
+        if payload.len() > self.max_frame_size {
+            return Err(CodecError::FrameTooLarge { size: payload.len(), max: self.max_frame_size });
+        }
+
+        dst.reserve(HEADER_LEN + payload.len());
+        dst.put_slice(&MAGIC);
+        dst.put_u16(PROTOCOL_VERSION);
+        dst.put_u32(self.network_id);
+        dst.put_u8(MessageType::of(&item) as u8);
+        dst.put_u32(payload.len() as u32);
+        dst.put_slice(&checksum(&payload));
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(timestamp: u64) -> NetworkMessage {
+        NetworkMessage::Ping(PingMessage {
+            timestamp,
+            peer_id: "peer_a".to_string(),
+        })
+    }
+
+    fn encode(codec: &mut NetworkCodec, message: NetworkMessage) -> BytesMut {
+        let mut buffer = BytesMut::new();
+        codec.encode(message, &mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_roundtrip_and_partial_frames() {
+        let mut codec = NetworkCodec::new(7);
+        let mut stream = encode(&mut codec, ping(1));
+        stream.extend_from_slice(&encode(&mut codec, ping(2)));
+
+        // 逐字节到达时，只有完整帧才会被解码
+        let mut buffer = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in stream {
+            buffer.put_u8(byte);
+            if let Some(message) = codec.decode(&mut buffer).unwrap() {
+                decoded.push(message);
+            }
+        }
+
+        assert_eq!(decoded.len(), 2);
+        assert!(matches!(&decoded[1], NetworkMessage::Ping(p) if p.timestamp == 2));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_reject_oversized_frame_from_header() {
+        let mut codec = NetworkCodec::new(7);
+        let mut buffer = encode(&mut codec, ping(1));
+        buffer.truncate(HEADER_LEN);
+
+        let mut small = NetworkCodec::new(7).with_max_frame_size(4);
+        let err = small.decode(&mut buffer).unwrap_err();
+        assert!(matches!(err, CodecError::FrameTooLarge { .. }));
+        assert!(err.is_protocol_violation());
+    }
+
+    #[test]
+    fn test_timeouts_and_io_errors_are_not_violations() {
+        let io = CodecError::Io(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(!io.is_protocol_violation());
+        assert!(!CodecError::HandshakeTimeout.is_protocol_violation());
+        assert!(CodecError::ChecksumMismatch.penalty() < CodecError::DecryptionFailed.penalty());
+    }
+
+    #[test]
+    fn test_reject_other_network_and_bad_magic() {
+        let mut buffer = encode(&mut NetworkCodec::new(1), ping(1));
+        let err = NetworkCodec::new(2).decode(&mut buffer.clone()).unwrap_err();
+        assert!(matches!(err, CodecError::NetworkMismatch { expected: 2, actual: 1 }));
+
+        buffer[0] = b'X';
+        assert!(matches!(NetworkCodec::new(1).decode(&mut buffer), Err(CodecError::InvalidMagic(_))));
+    }
+
+    #[test]
+    fn test_reject_corrupted_payload() {
+        let mut codec = NetworkCodec::new(1);
+        let mut buffer = encode(&mut codec, ping(1));
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0xFF;
+
+        assert!(matches!(codec.decode(&mut buffer), Err(CodecError::ChecksumMismatch)));
+    }
+}
@@ -0,0 +1,427 @@
+// 紧凑区块中继
+//
+// 广播区块时只发送区块头、每笔交易的 6 字节短 ID 和少量预填交易。
+// 短 ID 为以区块哈希和随机数派生的密钥对交易哈希做 SipHash-2-4 的低 48 位，
+// 每个区块的密钥不同，无法离线构造碰撞。接收方用交易池中的交易还原区块，
+// 只按索引请求缺失的交易；还原后 Merkle 根不符（短 ID 碰撞）时退回请求完整区块。
+use crate::core::{Block, BlockHeader, Transaction};
+use crate::components::{ComponentError, ComponentResult};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+
+/// 短 ID 只保留 SipHash 输出的低 48 位
+pub const SHORT_ID_MASK: u64 = 0xffff_ffff_ffff;
+
+/// 单个紧凑区块允许的最大交易数
+pub const MAX_COMPACT_TRANSACTIONS: usize = 100_000;
+
+/// 同时等待补齐交易的区块数上限，超出时丢弃最早的
+pub const MAX_PENDING_BLOCKS: usize = 16;
+
+/// 记录的预填提示数上限
+const MAX_PREFILL_HINTS: usize = 64;
+
+/// SipHash-2-4
+pub fn siphash24(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    let chunks = data.chunks_exact(8);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        let m = u64::from_le_bytes(chunk.try_into().unwrap());
+        v[3] ^= m;
+        sip_round(&mut v);
+        sip_round(&mut v);
+        v[0] ^= m;
+    }
+
+    let mut last = (data.len() as u64 & 0xff) << 56;
+    for (i, byte) in tail.iter().enumerate() {
+        last |= (*byte as u64) << (8 * i);
+    }
+    v[3] ^= last;
+    sip_round(&mut v);
+    sip_round(&mut v);
+    v[0] ^= last;
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+/// 预填交易：接收方大概率没有的交易随紧凑区块一起发送
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefilledTransaction {
+    /// 交易在区块中的索引
+    pub index: u32,
+    pub transaction: Transaction,
+}
+
+/// 紧凑区块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactBlock {
+    pub header: BlockHeader,
+    /// 与区块哈希一起派生短 ID 密钥
+    pub nonce: u64,
+    /// 未预填交易的短 ID，按区块中的顺序排列
+    pub short_ids: Vec<u64>,
+    /// 按索引升序排列的预填交易
+    pub prefilled: Vec<PrefilledTransaction>,
+}
+
+impl CompactBlock {
+    /// 从完整区块构造紧凑区块，`prefill` 中的索引直接附带完整交易
+    pub fn from_block(block: &Block, nonce: u64, prefill: &[u32]) -> Self {
+        let (k0, k1) = short_id_keys(&block.block_hash, nonce);
+        let mut short_ids = Vec::new();
+        let mut prefilled = Vec::new();
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let index = index as u32;
+            if prefill.contains(&index) {
+                prefilled.push(PrefilledTransaction { index, transaction: tx.clone() });
+            } else {
+                short_ids.push(short_id(k0, k1, &tx.hash()));
+            }
+        }
+        Self {
+            header: block.header.clone(),
+            nonce,
+            short_ids,
+            prefilled,
+        }
+    }
+
+    pub fn block_hash(&self) -> [u8; 32] {
+        self.header.block_hash
+    }
+
+    /// 区块中的交易总数
+    pub fn transaction_count(&self) -> usize {
+        self.short_ids.len() + self.prefilled.len()
+    }
+
+    /// 用交易池中的交易还原区块；找不到或有歧义的交易记为缺失
+    ///
+    /// 预填索引越界、未升序或交易数超限时返回错误，说明对端违反协议
+    pub fn reconstruct<'a>(&self, mempool: impl IntoIterator<Item = &'a Transaction>) -> ComponentResult<PartialBlock> {
+        let total = self.transaction_count();
+        if total > MAX_COMPACT_TRANSACTIONS {
+            return Err(ComponentError::NetworkError(format!("Compact block has too many transactions: {}", total)));
+        }
+        let mut slots: Vec<Option<Transaction>> = vec![None; total];
+        let mut previous = None;
+        for prefilled in &self.prefilled {
+            let index = prefilled.index as usize;
+            if index >= total || previous.is_some_and(|previous| index <= previous) {
+                return Err(ComponentError::NetworkError(format!("Invalid prefilled transaction index: {}", index)));
+            }
+            slots[index] = Some(prefilled.transaction.clone());
+            previous = Some(index);
+        }
+
+        // 短 ID -> 槽位索引；区块内重复的短 ID 无法区分，全部按缺失处理
+        let mut positions: HashMap<u64, Option<usize>> = HashMap::new();
+        let empty_slots = slots.iter().enumerate().filter(|(_, slot)| slot.is_none()).map(|(index, _)| index);
+        for (short_id, index) in self.short_ids.iter().zip(empty_slots) {
+            positions.entry(*short_id)
+                .and_modify(|position| *position = None)
+                .or_insert(Some(index));
+        }
+
+        let (k0, k1) = short_id_keys(&self.block_hash(), self.nonce);
+        let mut matched: HashMap<usize, Option<&Transaction>> = HashMap::new();
+        for tx in mempool {
+            let hash = tx.hash();
+            if let Some(Some(index)) = positions.get(&short_id(k0, k1, &hash)) {
+                // 交易池中两笔不同交易的短 ID 相同时同样按缺失处理
+                matched.entry(*index)
+                    .and_modify(|found| {
+                        if found.is_some_and(|found| found.hash() != hash) {
+                            *found = None;
+                        }
+                    })
+                    .or_insert(Some(tx));
+            }
+        }
+        for (index, tx) in matched {
+            if let Some(tx) = tx {
+                slots[index] = Some(tx.clone());
+            }
+        }
+
+        Ok(PartialBlock { header: self.header.clone(), slots })
+    }
+}
+
+/// 由区块哈希和随机数派生短 ID 密钥
+fn short_id_keys(block_hash: &[u8; 32], nonce: u64) -> (u64, u64) {
+    let mut hasher = Sha256::new();
+    hasher.update(block_hash);
+    hasher.update(nonce.to_le_bytes());
+    let digest = hasher.finalize();
+    (
+        u64::from_le_bytes(digest[..8].try_into().unwrap()),
+        u64::from_le_bytes(digest[8..16].try_into().unwrap()),
+    )
+}
+
+/// 交易的短 ID
+pub fn short_id(k0: u64, k1: u64, tx_hash: &[u8; 32]) -> u64 {
+    siphash24(k0, k1, tx_hash) & SHORT_ID_MASK
+}
+
+/// 正在还原的区块
+#[derive(Debug, Clone)]
+pub struct PartialBlock {
+    header: BlockHeader,
+    slots: Vec<Option<Transaction>>,
+}
+
+impl PartialBlock {
+    pub fn block_hash(&self) -> [u8; 32] {
+        self.header.block_hash
+    }
+
+    /// 缺失交易的索引（升序）
+    pub fn missing(&self) -> Vec<u32> {
+        self.slots.iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.is_none())
+            .map(|(index, _)| index as u32)
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.slots.iter().all(Option::is_some)
+    }
+
+    /// 按 `missing` 的顺序填入对端补发的交易，数量必须一致
+    pub fn fill(&mut self, transactions: Vec<Transaction>) -> ComponentResult<()> {
+        let missing = self.missing();
+        if missing.len() != transactions.len() {
+            return Err(ComponentError::NetworkError(format!(
+                "Expected {} block transactions, got {}", missing.len(), transactions.len()
+            )));
+        }
+        for (index, tx) in missing.into_iter().zip(transactions) {
+            self.slots[index as usize] = Some(tx);
+        }
+        Ok(())
+    }
+
+    /// 组装完整区块；尚有缺失交易时返回 `None`
+    ///
+    /// 不校验 Merkle 根，调用方需用 `Block::has_valid_merkle_root` 检查短 ID 碰撞
+    pub fn into_block(self) -> Option<Block> {
+        let transactions: Option<Vec<Transaction>> = self.slots.into_iter().collect();
+        Some(Block {
+            merkle_root: self.header.merkle_root,
+            block_hash: self.header.block_hash,
+            header: self.header,
+            transactions: transactions?,
+        })
+    }
+}
+
+/// 紧凑区块统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactStats {
+    /// 收到的紧凑区块
+    pub received: u64,
+    /// 无需额外往返即还原的区块
+    pub reconstructed: u64,
+    /// 需要请求缺失交易的区块
+    pub round_trips: u64,
+    /// 请求的缺失交易数
+    pub transactions_requested: u64,
+    /// 退回请求完整区块的次数
+    pub fallbacks: u64,
+}
+
+/// 紧凑区块中继状态
+#[derive(Debug, Default)]
+pub struct CompactRelay {
+    /// 等待对端补发交易的区块：区块哈希 -> （对端，部分区块）
+    pending: HashMap<[u8; 32], (String, PartialBlock)>,
+    pending_order: VecDeque<[u8; 32]>,
+    /// 还原时缺失的交易索引，转发给其他对端时作为预填交易
+    prefill_hints: HashMap<[u8; 32], Vec<u32>>,
+    prefill_order: VecDeque<[u8; 32]>,
+    stats: CompactStats,
+}
+
+impl CompactRelay {
+    pub fn stats(&self) -> CompactStats {
+        self.stats.clone()
+    }
+
+    pub fn on_received(&mut self) {
+        self.stats.received += 1;
+    }
+
+    pub fn on_reconstructed(&mut self) {
+        self.stats.reconstructed += 1;
+    }
+
+    pub fn on_fallback(&mut self) {
+        self.stats.fallbacks += 1;
+    }
+
+    pub fn is_pending(&self, block_hash: &[u8; 32]) -> bool {
+        self.pending.contains_key(block_hash)
+    }
+
+    /// 记录等待补齐的区块，返回需要向对端请求的交易索引
+    pub fn add_pending(&mut self, peer_id: &str, partial: PartialBlock) -> Vec<u32> {
+        let block_hash = partial.block_hash();
+        let missing = partial.missing();
+        self.stats.round_trips += 1;
+        self.stats.transactions_requested += missing.len() as u64;
+        self.record_prefill(block_hash, missing.clone());
+
+        if self.pending.insert(block_hash, (peer_id.to_string(), partial)).is_none() {
+            self.pending_order.push_back(block_hash);
+        }
+        while self.pending_order.len() > MAX_PENDING_BLOCKS {
+            if let Some(oldest) = self.pending_order.pop_front() {
+                self.pending.remove(&oldest);
+            }
+        }
+        missing
+    }
+
+    /// 取出向该对端请求过交易的区块；不是它的请求时返回 `None`
+    pub fn take_pending(&mut self, peer_id: &str, block_hash: &[u8; 32]) -> Option<PartialBlock> {
+        if self.pending.get(block_hash).is_none_or(|(requested_from, _)| requested_from != peer_id) {
+            return None;
+        }
+        self.pending_order.retain(|hash| hash != block_hash);
+        self.pending.remove(block_hash).map(|(_, partial)| partial)
+    }
+
+    /// 区块已通过其他途径收到，放弃等待
+    pub fn cancel(&mut self, block_hash: &[u8; 32]) {
+        self.pending.remove(block_hash);
+        self.pending_order.retain(|hash| hash != block_hash);
+    }
+
+    /// 取出转发该区块时应预填的交易索引
+    pub fn take_prefill(&mut self, block_hash: &[u8; 32]) -> Vec<u32> {
+        self.prefill_order.retain(|hash| hash != block_hash);
+        self.prefill_hints.remove(block_hash).unwrap_or_default()
+    }
+
+    fn record_prefill(&mut self, block_hash: [u8; 32], indexes: Vec<u32>) {
+        if self.prefill_hints.insert(block_hash, indexes).is_none() {
+            self.prefill_order.push_back(block_hash);
+        }
+        while self.prefill_order.len() > MAX_PREFILL_HINTS {
+            if let Some(oldest) = self.prefill_order.pop_front() {
+                self.prefill_hints.remove(&oldest);
+            }
+        }
+    }
+
+    /// 对端断开：放弃向它请求的区块
+    pub fn remove_peer(&mut self, peer_id: &str) {
+        self.pending.retain(|_, (requested_from, _)| requested_from != peer_id);
+        let pending = &self.pending;
+        self.pending_order.retain(|hash| pending.contains_key(hash));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{TxInput, TxOutput};
+    use crate::core::transaction::OutPoint;
+
+    fn transaction(seed: u8) -> Transaction {
+        let input = TxInput::new(OutPoint::new([seed; 32], 0), 100, format!("sender_{}", seed));
+        Transaction::new(vec![input], vec![TxOutput::new(90, format!("receiver_{}", seed))])
+    }
+
+    fn block_with(transactions: Vec<Transaction>) -> Block {
+        Block::new([1u8; 32], transactions, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_siphash24_reference_vector() {
+        // SipHash 论文附录的测试向量：密钥 00..0f，消息 00..0e
+        let message: Vec<u8> = (0u8..15).collect();
+        assert_eq!(siphash24(0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908, &message), 0xa129_ca61_49be_45e5);
+        assert_eq!(siphash24(0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908, &[]), 0x726f_db47_dd0e_0e31);
+    }
+
+    #[test]
+    fn test_reconstruct_from_mempool() {
+        let transactions: Vec<Transaction> = (1..=5).map(transaction).collect();
+        let block = block_with(transactions.clone());
+        let compact = CompactBlock::from_block(&block, 7, &[0]);
+        assert_eq!(compact.short_ids.len(), 4);
+        assert_eq!(compact.prefilled[0].index, 0);
+
+        // 交易池顺序与区块无关，还有无关交易
+        let mempool = vec![transactions[3].clone(), transaction(9), transactions[1].clone(),
+            transactions[4].clone(), transactions[2].clone()];
+        let partial = compact.reconstruct(&mempool).unwrap();
+        assert!(partial.is_complete());
+        let rebuilt = partial.into_block().unwrap();
+        assert!(rebuilt.has_valid_merkle_root());
+        assert_eq!(rebuilt.block_hash, block.block_hash);
+    }
+
+    #[test]
+    fn test_fill_missing_transactions() {
+        let transactions: Vec<Transaction> = (1..=4).map(transaction).collect();
+        let block = block_with(transactions.clone());
+        let compact = CompactBlock::from_block(&block, 1, &[]);
+
+        let mut partial = compact.reconstruct(&transactions[..2]).unwrap();
+        assert_eq!(partial.missing(), vec![2, 3]);
+        assert!(partial.fill(vec![transactions[2].clone()]).is_err());
+        partial.fill(vec![transactions[2].clone(), transactions[3].clone()]).unwrap();
+        assert!(partial.into_block().unwrap().has_valid_merkle_root());
+
+        // 预填索引越界是协议错误
+        let mut invalid = compact.clone();
+        invalid.prefilled.push(PrefilledTransaction { index: 10, transaction: transaction(1) });
+        assert!(invalid.reconstruct(&transactions).is_err());
+    }
+
+    #[test]
+    fn test_pending_blocks_belong_to_requested_peer() {
+        let transactions: Vec<Transaction> = (1..=3).map(transaction).collect();
+        let block = block_with(transactions);
+        let partial = CompactBlock::from_block(&block, 1, &[]).reconstruct(&[]).unwrap();
+
+        let mut relay = CompactRelay::default();
+        assert_eq!(relay.add_pending("a", partial), vec![0, 1, 2]);
+        assert!(relay.take_pending("b", &block.block_hash).is_none());
+        assert!(relay.take_pending("a", &block.block_hash).is_some());
+        assert!(!relay.is_pending(&block.block_hash));
+        // 缺失的交易在继续转发时预填
+        assert_eq!(relay.take_prefill(&block.block_hash), vec![0, 1, 2]);
+        assert_eq!(relay.stats().transactions_requested, 3);
+    }
+}
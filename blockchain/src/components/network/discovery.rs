@@ -0,0 +1,759 @@
+// 节点发现
+//
+// Kademlia 风格的 UDP 节点发现：节点ID为身份公钥的 SHA-256，按异或距离分入 256 个 k 桶。
+// 每个数据包都带发送方身份公钥和签名，ping/pong 验证端点存活，find_node 返回距目标最近的节点，
+// 迭代查找每轮并发询问 ALPHA 个最近的未询问节点直到无法更近。只有回应过本节点请求的节点才进入路由表，
+// 新发现的节点写入地址簿供 TCP 连接使用；后台任务定期随机查找以刷新路由表。
+// 与 discv4 相同，只回答已绑定端点（回应过本节点请求）的 find_node，避免向伪造的源地址反射大量数据；
+// 查询前先确认对端已绑定本节点，必要时 ping 对端并等待它的反向 ping。
+use super::address_book::AddressBook;
+use super::secure::{self, NodeIdentity};
+use crate::components::{ComponentError, ComponentResult};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+
+/// k 桶容量，也是 find_node 返回的节点数
+pub const K_BUCKET_SIZE: usize = 16;
+
+/// 迭代查找时每轮并发询问的节点数
+pub const ALPHA: usize = 3;
+
+/// 节点ID位数，即 k 桶数量
+const ID_BITS: usize = 256;
+
+/// 接收缓冲区大小，超过的数据包被截断后无法通过签名校验
+const MAX_PACKET_SIZE: usize = 2048;
+
+/// 端点绑定的有效期
+const BOND_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 记录的绑定端点上限，超过时先清理过期记录，仍然满额则不再记录
+const MAX_ENDPOINTS: usize = 4096;
+
+/// 节点发现配置
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// 单个请求的超时时间
+    pub request_timeout: Duration,
+    /// 定期刷新路由表的间隔
+    pub refresh_interval: Duration,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_millis(500),
+            refresh_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// 节点记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeRecord {
+    /// 身份公钥
+    pub public_key: [u8; 32],
+    /// 节点发现使用的 UDP 地址
+    pub address: SocketAddr,
+    /// P2P 连接使用的 TCP 端口
+    pub tcp_port: u16,
+}
+
+impl NodeRecord {
+    /// Kademlia 节点ID
+    pub fn node_id(&self) -> [u8; 32] {
+        node_id(&self.public_key)
+    }
+
+    /// 与 P2P 握手认证出的节点ID一致
+    pub fn peer_id(&self) -> String {
+        secure::peer_id_from_public_key(&self.public_key)
+    }
+
+    /// P2P 连接地址
+    pub fn tcp_address(&self) -> SocketAddr {
+        SocketAddr::new(self.address.ip(), self.tcp_port)
+    }
+}
+
+/// 由身份公钥计算 Kademlia 节点ID
+pub fn node_id(public_key: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(public_key).into()
+}
+
+/// 异或距离
+fn distance(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    std::array::from_fn(|i| a[i] ^ b[i])
+}
+
+/// 目标所在的 k 桶下标（异或距离最高位的位置），与自身相同时返回 None
+fn bucket_index(local: &[u8; 32], other: &[u8; 32]) -> Option<usize> {
+    let distance = distance(local, other);
+    let leading_zeros = distance.iter()
+        .position(|byte| *byte != 0)
+        .map(|i| i * 8 + distance[i].leading_zeros() as usize)?;
+    Some(ID_BITS - 1 - leading_zeros)
+}
+
+/// 节点发现消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiscoveryMessage {
+    Ping,
+    Pong,
+    FindNode { target: [u8; 32] },
+    Neighbors { nodes: Vec<NodeRecord> },
+}
+
+/// 签名覆盖的数据包内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PacketBody {
+    network_id: u32,
+    request_id: u64,
+    tcp_port: u16,
+    message: DiscoveryMessage,
+}
+
+/// UDP 数据包：发送方公钥、签名和编码后的内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Packet {
+    public_key: [u8; 32],
+    signature: Vec<u8>,
+    body: Vec<u8>,
+}
+
+fn encode_packet(identity: &NodeIdentity, body: &PacketBody) -> ComponentResult<Vec<u8>> {
+    let body = bincode::serialize(body).map_err(|e| ComponentError::NetworkError(e.to_string()))?;
+    let packet = Packet {
+        public_key: identity.public_key(),
+        signature: identity.sign(&body).to_vec(),
+        body,
+    };
+    bincode::serialize(&packet).map_err(|e| ComponentError::NetworkError(e.to_string()))
+}
+
+/// 解码并校验签名，返回发送方公钥和内容
+fn decode_packet(data: &[u8]) -> ComponentResult<([u8; 32], PacketBody)> {
+    let packet: Packet = bincode::deserialize(data)
+        .map_err(|e| ComponentError::NetworkError(format!("Malformed discovery packet: {}", e)))?;
+    let signature: [u8; 64] = packet.signature.as_slice().try_into()
+        .map_err(|_| ComponentError::CryptographyError("Invalid signature length".to_string()))?;
+    if !secure::verify_signature(&packet.public_key, &packet.body, &signature) {
+        return Err(ComponentError::CryptographyError("Invalid discovery packet signature".to_string()));
+    }
+    let body = bincode::deserialize(&packet.body)
+        .map_err(|e| ComponentError::NetworkError(format!("Malformed discovery packet: {}", e)))?;
+    Ok((packet.public_key, body))
+}
+
+/// 路由表插入结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertResult {
+    /// 新加入路由表
+    Inserted,
+    /// 已在路由表中，刷新了活跃时间和地址
+    Updated,
+    /// k 桶已满，节点放入候补；应 ping 桶内最久未活跃的节点，无响应时移除它
+    BucketFull { oldest: NodeRecord },
+    /// 自身
+    Ignored,
+}
+
+/// Kademlia 路由表
+#[derive(Debug)]
+pub struct RoutingTable {
+    local_id: [u8; 32],
+    /// 每个 k 桶按最近活跃时间排序，末尾最新
+    buckets: Vec<VecDeque<(NodeRecord, Instant)>>,
+    /// 桶满时的候补节点
+    replacements: Vec<VecDeque<NodeRecord>>,
+}
+
+impl RoutingTable {
+    pub fn new(local_id: [u8; 32]) -> Self {
+        Self {
+            local_id,
+            buckets: vec![VecDeque::new(); ID_BITS],
+            replacements: vec![VecDeque::new(); ID_BITS],
+        }
+    }
+
+    /// 记录一个已验证存活的节点
+    pub fn insert(&mut self, record: NodeRecord) -> InsertResult {
+        let id = record.node_id();
+        let Some(index) = bucket_index(&self.local_id, &id) else {
+            return InsertResult::Ignored;
+        };
+        let bucket = &mut self.buckets[index];
+        if let Some(position) = bucket.iter().position(|(node, _)| node.node_id() == id) {
+            bucket.remove(position);
+            bucket.push_back((record, Instant::now()));
+            return InsertResult::Updated;
+        }
+        if bucket.len() < K_BUCKET_SIZE {
+            bucket.push_back((record, Instant::now()));
+            return InsertResult::Inserted;
+        }
+
+        let oldest = bucket.front().map(|(node, _)| node.clone()).expect("bucket is full");
+        let replacements = &mut self.replacements[index];
+        replacements.retain(|node| node.node_id() != id);
+        replacements.push_back(record);
+        if replacements.len() > K_BUCKET_SIZE {
+            replacements.pop_front();
+        }
+        InsertResult::BucketFull { oldest }
+    }
+
+    /// 移除无响应的节点，由最新的候补节点补位
+    pub fn remove(&mut self, id: &[u8; 32]) {
+        let Some(index) = bucket_index(&self.local_id, id) else {
+            return;
+        };
+        let bucket = &mut self.buckets[index];
+        let before = bucket.len();
+        bucket.retain(|(node, _)| node.node_id() != *id);
+        if bucket.len() < before
+            && let Some(replacement) = self.replacements[index].pop_back()
+        {
+            bucket.push_back((replacement, Instant::now()));
+        }
+    }
+
+    pub fn contains(&self, id: &[u8; 32]) -> bool {
+        bucket_index(&self.local_id, id)
+            .is_some_and(|index| self.buckets[index].iter().any(|(node, _)| node.node_id() == *id))
+    }
+
+    /// 距目标最近的 `count` 个节点，按异或距离升序
+    pub fn closest(&self, target: &[u8; 32], count: usize) -> Vec<NodeRecord> {
+        let mut nodes: Vec<NodeRecord> = self.buckets.iter()
+            .flat_map(|bucket| bucket.iter().map(|(node, _)| node.clone()))
+            .collect();
+        nodes.sort_by_key(|node| distance(&node.node_id(), target));
+        nodes.truncate(count);
+        nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+type PendingRequests = HashMap<u64, (SocketAddr, oneshot::Sender<(NodeRecord, DiscoveryMessage)>)>;
+
+/// 端点 -> (节点ID, 记录时间)
+type Endpoints = HashMap<SocketAddr, ([u8; 32], Instant)>;
+
+/// 记录端点，满额时清理过期记录
+fn remember(endpoints: &mut Endpoints, address: SocketAddr, id: [u8; 32]) {
+    if endpoints.len() >= MAX_ENDPOINTS && !endpoints.contains_key(&address) {
+        endpoints.retain(|_, (_, since)| since.elapsed() < BOND_EXPIRY);
+        if endpoints.len() >= MAX_ENDPOINTS {
+            return;
+        }
+    }
+    endpoints.insert(address, (id, Instant::now()));
+}
+
+/// 端点的记录是否未过期；`id` 给出时还要求节点ID一致
+fn is_fresh(endpoints: &Endpoints, address: &SocketAddr, id: Option<&[u8; 32]>) -> bool {
+    endpoints.get(address)
+        .is_some_and(|(known, since)| id.is_none_or(|id| known == id) && since.elapsed() < BOND_EXPIRY)
+}
+
+struct DiscoveryInner {
+    socket: Arc<UdpSocket>,
+    identity: Arc<NodeIdentity>,
+    local_id: [u8; 32],
+    network_id: u32,
+    tcp_port: u16,
+    config: DiscoveryConfig,
+    table: Mutex<RoutingTable>,
+    address_book: Arc<Mutex<AddressBook>>,
+    pending: Mutex<PendingRequests>,
+    /// 回应过本节点请求的端点，只回答这些端点的 find_node
+    bonds: Mutex<Endpoints>,
+    /// ping 过本节点的端点；本节点已回应 pong，对端因此绑定了本节点
+    pinged_by: Mutex<Endpoints>,
+    ping_received: Notify,
+    next_request_id: AtomicU64,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Drop for DiscoveryInner {
+    fn drop(&mut self) {
+        for task in self.tasks.get_mut().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+/// 节点发现服务，克隆后共享同一个套接字和路由表
+#[derive(Clone)]
+pub struct Discovery {
+    inner: Arc<DiscoveryInner>,
+}
+
+impl std::fmt::Debug for Discovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Discovery")
+            .field("local_addr", &self.local_addr())
+            .field("nodes", &self.node_count())
+            .finish()
+    }
+}
+
+impl Discovery {
+    /// 绑定 UDP 端口并启动接收任务
+    ///
+    /// `tcp_port` 随每个数据包发送，告诉对端本节点的 P2P 端口；发现的节点写入 `address_book`。
+    pub async fn bind(
+        identity: Arc<NodeIdentity>,
+        bind_addr: SocketAddr,
+        tcp_port: u16,
+        network_id: u32,
+        address_book: AddressBook,
+        config: DiscoveryConfig,
+    ) -> ComponentResult<Self> {
+        let socket = UdpSocket::bind(bind_addr).await
+            .map_err(|e| ComponentError::NetworkError(format!("Failed to bind discovery socket {}: {}", bind_addr, e)))?;
+        let local_id = node_id(&identity.public_key());
+        let discovery = Self {
+            inner: Arc::new(DiscoveryInner {
+                socket: Arc::new(socket),
+                identity,
+                local_id,
+                network_id,
+                tcp_port,
+                config,
+                table: Mutex::new(RoutingTable::new(local_id)),
+                address_book: Arc::new(Mutex::new(address_book)),
+                pending: Mutex::new(HashMap::new()),
+                bonds: Mutex::new(HashMap::new()),
+                pinged_by: Mutex::new(HashMap::new()),
+                ping_received: Notify::new(),
+                next_request_id: AtomicU64::new(rand::random()),
+                tasks: Mutex::new(Vec::new()),
+            }),
+        };
+
+        let socket = Arc::clone(&discovery.inner.socket);
+        let weak = Arc::downgrade(&discovery.inner);
+        let task = tokio::spawn(async move {
+            let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+            loop {
+                let Ok((len, from)) = socket.recv_from(&mut buffer).await else {
+                    continue;
+                };
+                let Some(inner) = weak.upgrade() else {
+                    break;
+                };
+                let discovery = Discovery { inner };
+                if let Err(e) = discovery.handle_packet(&buffer[..len], from).await {
+                    log::debug!("Dropping discovery packet from {}: {}", from, e);
+                }
+            }
+        });
+        discovery.inner.tasks.lock().unwrap().push(task);
+        Ok(discovery)
+    }
+
+    /// 本地 UDP 地址
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.inner.socket.local_addr().ok()
+    }
+
+    /// 本节点的 Kademlia 节点ID
+    pub fn local_id(&self) -> [u8; 32] {
+        self.inner.local_id
+    }
+
+    /// 路由表中的节点数
+    pub fn node_count(&self) -> usize {
+        self.inner.table.lock().unwrap().len()
+    }
+
+    /// 路由表中距目标最近的节点
+    pub fn closest(&self, target: &[u8; 32], count: usize) -> Vec<NodeRecord> {
+        self.inner.table.lock().unwrap().closest(target, count)
+    }
+
+    /// 共享的地址簿
+    pub fn address_book(&self) -> Arc<Mutex<AddressBook>> {
+        Arc::clone(&self.inner.address_book)
+    }
+
+    /// ping 指定地址，成功时对端进入路由表
+    pub async fn ping(&self, address: SocketAddr) -> ComponentResult<NodeRecord> {
+        let (record, _) = self.request(address, DiscoveryMessage::Ping).await?;
+        Ok(record)
+    }
+
+    /// 向指定节点请求距目标最近的节点
+    pub async fn find_node(&self, address: SocketAddr, target: [u8; 32]) -> ComponentResult<Vec<NodeRecord>> {
+        self.ensure_bond(address).await?;
+        match self.request(address, DiscoveryMessage::FindNode { target }).await? {
+            (_, DiscoveryMessage::Neighbors { nodes }) => Ok(nodes),
+            (_, other) => Err(ComponentError::NetworkError(format!("Unexpected response {:?}", other))),
+        }
+    }
+
+    /// 迭代查找距目标最近的节点
+    pub async fn lookup(&self, target: [u8; 32]) -> Vec<NodeRecord> {
+        let mut closest = self.closest(&target, K_BUCKET_SIZE);
+        let mut queried: HashSet<[u8; 32]> = HashSet::from([self.inner.local_id]);
+        loop {
+            let batch: Vec<NodeRecord> = closest.iter()
+                .filter(|node| !queried.contains(&node.node_id()))
+                .take(ALPHA)
+                .cloned()
+                .collect();
+            if batch.is_empty() {
+                break;
+            }
+            queried.extend(batch.iter().map(NodeRecord::node_id));
+
+            let responses = futures::future::join_all(
+                batch.iter().map(|node| self.find_node(node.address, target))
+            ).await;
+            for (node, response) in batch.iter().zip(responses) {
+                match response {
+                    Ok(nodes) => {
+                        for found in nodes {
+                            let id = found.node_id();
+                            if id != self.inner.local_id && !closest.iter().any(|known| known.node_id() == id) {
+                                closest.push(found);
+                            }
+                        }
+                    }
+                    Err(_) => {
+                        self.inner.table.lock().unwrap().remove(&node.node_id());
+                        closest.retain(|known| known.node_id() != node.node_id());
+                    }
+                }
+            }
+            closest.sort_by_key(|node| distance(&node.node_id(), &target));
+            closest.truncate(K_BUCKET_SIZE);
+        }
+        closest
+    }
+
+    /// 从引导节点加入网络：ping 每个引导节点，再查找自身填充路由表
+    pub async fn bootstrap(&self, bootstrap_nodes: &[SocketAddr]) -> ComponentResult<usize> {
+        let responses = futures::future::join_all(bootstrap_nodes.iter().map(|address| self.ping(*address))).await;
+        if !bootstrap_nodes.is_empty() && responses.iter().all(Result::is_err) {
+            return Err(ComponentError::NetworkError("No bootstrap node responded".to_string()));
+        }
+        self.lookup(self.inner.local_id).await;
+        Ok(self.node_count())
+    }
+
+    /// 刷新路由表：查找自身和一个随机目标，然后保存地址簿
+    pub async fn refresh(&self) -> ComponentResult<()> {
+        self.lookup(self.inner.local_id).await;
+        self.lookup(rand::random()).await;
+        self.inner.address_book.lock().unwrap().save()
+    }
+
+    /// 启动定期刷新任务
+    pub fn spawn_refresh(&self) {
+        let weak: Weak<DiscoveryInner> = Arc::downgrade(&self.inner);
+        let interval = self.inner.config.refresh_interval;
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(inner) = weak.upgrade() else {
+                    break;
+                };
+                if let Err(e) = (Discovery { inner }).refresh().await {
+                    log::warn!("Discovery refresh failed: {}", e);
+                }
+            }
+        });
+        self.inner.tasks.lock().unwrap().push(task);
+    }
+
+    /// 确保对端已绑定本节点：近期没有收到对端的 ping 时先 ping 对端，再等待它的反向 ping
+    ///
+    /// 对端可能已经绑定过本节点而不再反向 ping，等待超时后照常发出请求。
+    async fn ensure_bond(&self, address: SocketAddr) -> ComponentResult<()> {
+        let pinged = || is_fresh(&self.inner.pinged_by.lock().unwrap(), &address, None);
+        if pinged() {
+            return Ok(());
+        }
+        self.ping(address).await?;
+        let deadline = tokio::time::Instant::now() + self.inner.config.request_timeout;
+        loop {
+            let notified = self.inner.ping_received.notified();
+            if pinged() || tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn send(&self, address: SocketAddr, request_id: u64, message: DiscoveryMessage) -> ComponentResult<()> {
+        let body = PacketBody {
+            network_id: self.inner.network_id,
+            request_id,
+            tcp_port: self.inner.tcp_port,
+            message,
+        };
+        let packet = encode_packet(&self.inner.identity, &body)?;
+        self.inner.socket.send_to(&packet, address).await
+            .map_err(|e| ComponentError::NetworkError(format!("Failed to send to {}: {}", address, e)))?;
+        Ok(())
+    }
+
+    /// 发送请求并等待同一地址的响应；响应方已证明端点存活，加入路由表
+    async fn request(&self, address: SocketAddr, message: DiscoveryMessage) -> ComponentResult<(NodeRecord, DiscoveryMessage)> {
+        let request_id = self.inner.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.inner.pending.lock().unwrap().insert(request_id, (address, sender));
+
+        let result = match self.send(address, request_id, message).await {
+            Ok(()) => tokio::time::timeout(self.inner.config.request_timeout, receiver).await
+                .map_err(|_| ComponentError::NetworkError(format!("Discovery request to {} timed out", address)))
+                .and_then(|response| response.map_err(|_| ComponentError::NetworkError("Discovery stopped".to_string()))),
+            Err(e) => Err(e),
+        };
+        self.inner.pending.lock().unwrap().remove(&request_id);
+
+        let (record, response) = result?;
+        self.add_verified(record.clone());
+        Ok((record, response))
+    }
+
+    /// 记录已验证的节点，k 桶已满时在后台检查最旧的节点
+    fn add_verified(&self, record: NodeRecord) {
+        self.inner.address_book.lock().unwrap().add(record.tcp_address(), Some(record.address.ip()));
+        let result = self.inner.table.lock().unwrap().insert(record);
+        if let InsertResult::BucketFull { oldest } = result {
+            let discovery = self.clone();
+            tokio::spawn(async move {
+                if discovery.ping(oldest.address).await.is_err() {
+                    discovery.inner.table.lock().unwrap().remove(&oldest.node_id());
+                }
+            });
+        }
+    }
+
+    async fn handle_packet(&self, data: &[u8], from: SocketAddr) -> ComponentResult<()> {
+        let (public_key, body) = decode_packet(data)?;
+        if body.network_id != self.inner.network_id {
+            return Err(ComponentError::NetworkError(format!("Network id mismatch: {}", body.network_id)));
+        }
+        let record = NodeRecord { public_key, address: from, tcp_port: body.tcp_port };
+        if record.node_id() == self.inner.local_id {
+            return Ok(());
+        }
+
+        let id = record.node_id();
+        match body.message {
+            DiscoveryMessage::Ping => {
+                self.send(from, body.request_id, DiscoveryMessage::Pong).await?;
+                remember(&mut self.inner.pinged_by.lock().unwrap(), from, id);
+                self.inner.ping_received.notify_waiters();
+                // 反向 ping 验证对端端点后再加入路由表；每个端点同时只有一个未完成的请求，不会被放大
+                let bonded = is_fresh(&self.inner.bonds.lock().unwrap(), &from, Some(&id));
+                let requested = self.inner.pending.lock().unwrap().values().any(|(address, _)| *address == from);
+                if !bonded && !requested {
+                    let discovery = self.clone();
+                    tokio::spawn(async move {
+                        let _ = discovery.ping(from).await;
+                    });
+                }
+            }
+            DiscoveryMessage::FindNode { target } => {
+                // 源地址可能是伪造的，未绑定的端点得不到回复
+                if !is_fresh(&self.inner.bonds.lock().unwrap(), &from, Some(&id)) {
+                    return Err(ComponentError::NetworkError(format!("find_node from unbonded endpoint {}", from)));
+                }
+                let nodes: Vec<NodeRecord> = self.closest(&target, K_BUCKET_SIZE + 1)
+                    .into_iter()
+                    .filter(|node| node.node_id() != record.node_id())
+                    .take(K_BUCKET_SIZE)
+                    .collect();
+                self.send(from, body.request_id, DiscoveryMessage::Neighbors { nodes }).await?;
+            }
+            response @ (DiscoveryMessage::Pong | DiscoveryMessage::Neighbors { .. }) => {
+                let mut pending = self.inner.pending.lock().unwrap();
+                // 只接受发往该地址的请求的响应
+                if pending.get(&body.request_id).is_some_and(|(address, _)| *address == from)
+                    && let Some((_, sender)) = pending.remove(&body.request_id)
+                {
+                    // 对端用随机请求ID回应，证明它确实在该地址上，随即完成绑定
+                    remember(&mut self.inner.bonds.lock().unwrap(), from, id);
+                    let _ = sender.send((record, response));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn spawn_node(network_id: u32) -> Discovery {
+        static NEXT_TCP_PORT: std::sync::atomic::AtomicU16 = std::sync::atomic::AtomicU16::new(9000);
+        let tcp_port = NEXT_TCP_PORT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let config = DiscoveryConfig { request_timeout: Duration::from_millis(300), ..DiscoveryConfig::default() };
+        Discovery::bind(
+            Arc::new(NodeIdentity::generate()),
+            "127.0.0.1:0".parse().unwrap(),
+            tcp_port,
+            network_id,
+            AddressBook::new(),
+            config,
+        ).await.unwrap()
+    }
+
+    fn record(seed: u8) -> NodeRecord {
+        NodeRecord {
+            public_key: NodeIdentity::from_secret_key(&[seed; 32]).unwrap().public_key(),
+            address: SocketAddr::from(([127, 0, 0, 1], 10000 + seed as u16)),
+            tcp_port: 9000,
+        }
+    }
+
+    #[test]
+    fn test_routing_table_buckets_and_closest() {
+        let local = record(0);
+        let mut table = RoutingTable::new(local.node_id());
+        assert_eq!(table.insert(local.clone()), InsertResult::Ignored);
+
+        let nodes: Vec<NodeRecord> = (1..=40).map(record).collect();
+        for node in &nodes {
+            table.insert(node.clone());
+        }
+        assert_eq!(table.insert(nodes[0].clone()), InsertResult::Updated);
+
+        let target = nodes[7].node_id();
+        let closest = table.closest(&target, 3);
+        assert_eq!(closest[0], nodes[7]);
+        assert!(closest.windows(2).all(|pair| {
+            distance(&pair[0].node_id(), &target) <= distance(&pair[1].node_id(), &target)
+        }));
+    }
+
+    #[test]
+    fn test_full_bucket_uses_replacements() {
+        let mut table = RoutingTable::new([0u8; 32]);
+        // 本地ID全零时，节点ID最高位为 1 的节点都落入最后一个桶
+        let same_bucket: Vec<NodeRecord> = (1..=255u8)
+            .map(record)
+            .filter(|node| bucket_index(&[0u8; 32], &node.node_id()) == Some(ID_BITS - 1))
+            .take(K_BUCKET_SIZE + 1)
+            .collect();
+        assert_eq!(same_bucket.len(), K_BUCKET_SIZE + 1);
+
+        for node in &same_bucket[..K_BUCKET_SIZE] {
+            assert_eq!(table.insert(node.clone()), InsertResult::Inserted);
+        }
+        let extra = same_bucket[K_BUCKET_SIZE].clone();
+        assert_eq!(table.insert(extra.clone()), InsertResult::BucketFull { oldest: same_bucket[0].clone() });
+        assert!(!table.contains(&extra.node_id()));
+
+        // 最旧节点无响应被移除后，候补节点补位
+        table.remove(&same_bucket[0].node_id());
+        assert!(table.contains(&extra.node_id()));
+        assert_eq!(table.len(), K_BUCKET_SIZE);
+    }
+
+    #[test]
+    fn test_packet_signature_is_verified() {
+        let identity = NodeIdentity::generate();
+        let body = PacketBody { network_id: 1, request_id: 7, tcp_port: 9000, message: DiscoveryMessage::Ping };
+        let data = encode_packet(&identity, &body).unwrap();
+        let (public_key, decoded) = decode_packet(&data).unwrap();
+        assert_eq!(public_key, identity.public_key());
+        assert_eq!(decoded.request_id, 7);
+
+        let mut packet: Packet = bincode::deserialize(&data).unwrap();
+        packet.public_key = NodeIdentity::generate().public_key();
+        assert!(decode_packet(&bincode::serialize(&packet).unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ping_adds_both_nodes() {
+        let a = spawn_node(1).await;
+        let b = spawn_node(1).await;
+        let other_network = spawn_node(2).await;
+
+        let record = a.ping(b.local_addr().unwrap()).await.unwrap();
+        assert_eq!(record.node_id(), b.local_id());
+        assert_eq!(a.node_count(), 1);
+        assert!(a.address_book().lock().unwrap().get(&record.tcp_address()).is_some());
+
+        // b 反向 ping 后也记录了 a
+        for _ in 0..50 {
+            if b.node_count() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(b.node_count(), 1);
+
+        assert!(a.ping(other_network.local_addr().unwrap()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_node_requires_bond() {
+        let a = spawn_node(1).await;
+        let b = spawn_node(1).await;
+        a.ping(b.local_addr().unwrap()).await.unwrap();
+
+        // 从未回应过 a 的端点（例如伪造的源地址）发出的 find_node 得不到任何回复
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let body = PacketBody {
+            network_id: 1,
+            request_id: 1,
+            tcp_port: 9000,
+            message: DiscoveryMessage::FindNode { target: b.local_id() },
+        };
+        let packet = encode_packet(&NodeIdentity::generate(), &body).unwrap();
+        socket.send_to(&packet, a.local_addr().unwrap()).await.unwrap();
+        let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+        assert!(tokio::time::timeout(Duration::from_millis(300), socket.recv_from(&mut buffer)).await.is_err());
+
+        // 通过 ping/pong 完成绑定的节点可以查询
+        let c = spawn_node(1).await;
+        let nodes = c.find_node(a.local_addr().unwrap(), b.local_id()).await.unwrap();
+        assert_eq!(nodes[0].node_id(), b.local_id());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_across_many_nodes() {
+        let mut nodes = Vec::new();
+        for _ in 0..24 {
+            nodes.push(spawn_node(1).await);
+        }
+        let bootstrap = [nodes[0].local_addr().unwrap()];
+        for node in &nodes[1..] {
+            node.bootstrap(&bootstrap).await.unwrap();
+        }
+
+        // 后加入的节点通过引导节点找到最早加入的节点，反之亦然
+        let first = &nodes[1];
+        let last = &nodes[23];
+        let found = last.lookup(first.local_id()).await;
+        assert_eq!(found[0].node_id(), first.local_id());
+        let found = first.lookup(last.local_id()).await;
+        assert_eq!(found[0].node_id(), last.local_id());
+        assert!(first.node_count() > 2);
+        assert!(first.address_book().lock().unwrap().len() > 2);
+    }
+}
@@ -0,0 +1,373 @@
+// 库存广播（gossip）
+//
+// 公告/请求模式：新对象先以 inv 公告哈希，对端用 getdata 请求尚未拥有的对象，再发送完整交易或区块。
+// 每个对端维护已知库存过滤器，不向对端发送它已经拥有的条目；全局的已见缓存丢弃重复消息。
+// 交易按对端随机延迟涓流公告，区块立即公告；只有通过交易池或区块验证的对象才会继续转发。
+// 每个对端未完成的请求数有上限，超时未送达的请求被清除，公告了对象却不提供的对端由调用方扣分。
+use super::message::{InventoryItem, InventoryKind};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// 单个 inv / getdata 消息最多包含的条目数
+pub const MAX_INV_PER_MESSAGE: usize = 1000;
+
+/// 广播配置
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+    /// 每个对端记录的已知库存条目上限
+    pub known_inventory_size: usize,
+    /// 已见消息缓存上限
+    pub seen_cache_size: usize,
+    /// 交易公告的最大随机延迟
+    pub max_trickle_delay: Duration,
+    /// getdata 请求超时时间，超时后允许向其他对端请求
+    pub request_timeout: Duration,
+    /// 每个对端未完成的 getdata 条目上限
+    pub max_requests_per_peer: usize,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            known_inventory_size: 5000,
+            seen_cache_size: 50_000,
+            max_trickle_delay: Duration::from_millis(500),
+            request_timeout: Duration::from_secs(30),
+            max_requests_per_peer: 2 * MAX_INV_PER_MESSAGE,
+        }
+    }
+}
+
+/// 广播统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GossipStats {
+    /// 发出的公告条目数
+    pub announced: u64,
+    /// 发出的请求条目数
+    pub requested: u64,
+    /// 收到的公告条目数
+    pub inv_received: u64,
+    /// 丢弃的重复对象数
+    pub duplicates_dropped: u64,
+}
+
+/// 有容量上限的集合，满时淘汰最早插入的元素
+#[derive(Debug, Clone)]
+pub struct BoundedSet<T> {
+    items: HashSet<T>,
+    order: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Eq + Hash + Clone> BoundedSet<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: HashSet::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 插入元素，返回是否为新元素
+    pub fn insert(&mut self, item: T) -> bool {
+        if !self.items.insert(item.clone()) {
+            return false;
+        }
+        self.order.push_back(item);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.items.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.items.contains(item)
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// 库存广播状态
+#[derive(Debug)]
+pub struct Gossip {
+    config: GossipConfig,
+    /// 已见过（收到或本地产生）的对象
+    seen: BoundedSet<InventoryItem>,
+    /// 每个对端已知的库存
+    known: HashMap<String, BoundedSet<InventoryItem>>,
+    /// 已发出 getdata 的条目：（对端，请求时间）
+    requested: HashMap<InventoryItem, (String, Instant)>,
+    /// 每个对端未完成的请求数
+    in_flight: HashMap<String, usize>,
+    /// 等待涓流公告的交易：对端 -> （到期时间，条目）
+    trickle: HashMap<String, Vec<(Instant, InventoryItem)>>,
+    stats: GossipStats,
+}
+
+impl Default for Gossip {
+    fn default() -> Self {
+        Self::new(GossipConfig::default())
+    }
+}
+
+impl Gossip {
+    pub fn new(config: GossipConfig) -> Self {
+        Self {
+            seen: BoundedSet::new(config.seen_cache_size),
+            config,
+            known: HashMap::new(),
+            requested: HashMap::new(),
+            in_flight: HashMap::new(),
+            trickle: HashMap::new(),
+            stats: GossipStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> GossipStats {
+        self.stats.clone()
+    }
+
+    pub fn has_seen(&self, item: &InventoryItem) -> bool {
+        self.seen.contains(item)
+    }
+
+    /// 对端是否已知该条目
+    pub fn peer_knows(&self, peer_id: &str, item: &InventoryItem) -> bool {
+        self.known.get(peer_id).is_some_and(|known| known.contains(item))
+    }
+
+    /// 记录对端已拥有该条目
+    pub fn mark_known(&mut self, peer_id: &str, item: InventoryItem) {
+        let capacity = self.config.known_inventory_size;
+        self.known.entry(peer_id.to_string())
+            .or_insert_with(|| BoundedSet::new(capacity))
+            .insert(item);
+    }
+
+    /// 对象通过验证后安排向尚不知道它的对端公告
+    ///
+    /// 区块立即返回待发送的公告；交易为每个对端随机延迟，由 `due_announcements` 取出
+    pub fn relay(&mut self, item: InventoryItem, peers: &[String], now: Instant) -> Vec<(String, Vec<InventoryItem>)> {
+        self.seen.insert(item);
+        let mut immediate = Vec::new();
+        for peer_id in peers {
+            if self.peer_knows(peer_id, &item) {
+                continue;
+            }
+            self.mark_known(peer_id, item);
+            match item.kind {
+                InventoryKind::Block => {
+                    self.stats.announced += 1;
+                    immediate.push((peer_id.clone(), vec![item]));
+                }
+                InventoryKind::Transaction => {
+                    let max_delay = self.config.max_trickle_delay.as_millis() as u64;
+                    let delay = Duration::from_millis(rand::rng().random_range(0..=max_delay));
+                    self.trickle.entry(peer_id.clone()).or_default().push((now + delay, item));
+                }
+            }
+        }
+        immediate
+    }
+
+    /// 取出已到期的交易公告，按对端合并
+    pub fn due_announcements(&mut self, now: Instant) -> Vec<(String, Vec<InventoryItem>)> {
+        let mut announcements = Vec::new();
+        for (peer_id, queue) in self.trickle.iter_mut() {
+            let mut due = Vec::new();
+            queue.retain(|(at, item)| {
+                if *at <= now {
+                    due.push(*item);
+                    false
+                } else {
+                    true
+                }
+            });
+            self.stats.announced += due.len() as u64;
+            for chunk in due.chunks(MAX_INV_PER_MESSAGE) {
+                announcements.push((peer_id.clone(), chunk.to_vec()));
+            }
+        }
+        self.trickle.retain(|_, queue| !queue.is_empty());
+        announcements
+    }
+
+    /// 处理对端的公告，返回需要向它请求的条目
+    ///
+    /// 已见过的条目和正在请求的条目不会重复请求；对端未完成的请求达到上限后不再向它请求
+    pub fn on_inv(&mut self, peer_id: &str, items: &[InventoryItem], now: Instant) -> Vec<InventoryItem> {
+        let mut wanted = Vec::new();
+        for item in items.iter().take(MAX_INV_PER_MESSAGE) {
+            self.stats.inv_received += 1;
+            self.mark_known(peer_id, *item);
+            if self.seen.contains(item) || self.requested.contains_key(item) {
+                continue;
+            }
+            let in_flight = self.in_flight.entry(peer_id.to_string()).or_insert(0);
+            if *in_flight >= self.config.max_requests_per_peer {
+                continue;
+            }
+            *in_flight += 1;
+            self.requested.insert(*item, (peer_id.to_string(), now));
+            wanted.push(*item);
+        }
+        self.stats.requested += wanted.len() as u64;
+        wanted
+    }
+
+    /// 处理对端的请求：请求过的条目视为对端已知，不再向它公告
+    pub fn on_getdata(&mut self, peer_id: &str, items: &[InventoryItem]) -> Vec<InventoryItem> {
+        let items: Vec<InventoryItem> = items.iter().take(MAX_INV_PER_MESSAGE).copied().collect();
+        for item in &items {
+            self.mark_known(peer_id, *item);
+        }
+        items
+    }
+
+    /// 清除超时未送达的请求，之后可以向其他对端请求这些条目
+    ///
+    /// 每个超时的条目返回一次被请求的对端，由调用方扣分
+    pub fn take_expired(&mut self, now: Instant) -> Vec<String> {
+        let timeout = self.config.request_timeout;
+        let expired: Vec<InventoryItem> = self.requested.iter()
+            .filter(|(_, (_, requested_at))| now.duration_since(*requested_at) >= timeout)
+            .map(|(item, _)| *item)
+            .collect();
+        expired.iter().filter_map(|item| self.take_request(item)).collect()
+    }
+
+    /// 移除一个未完成的请求，返回被请求的对端
+    fn take_request(&mut self, item: &InventoryItem) -> Option<String> {
+        let (peer_id, _) = self.requested.remove(item)?;
+        if let Some(in_flight) = self.in_flight.get_mut(&peer_id) {
+            *in_flight = in_flight.saturating_sub(1);
+            if *in_flight == 0 {
+                self.in_flight.remove(&peer_id);
+            }
+        }
+        Some(peer_id)
+    }
+
+    /// 收到完整对象；返回 false 表示重复对象，应直接丢弃
+    pub fn on_object(&mut self, peer_id: &str, item: InventoryItem) -> bool {
+        self.mark_known(peer_id, item);
+        self.take_request(&item);
+        if self.seen.insert(item) {
+            true
+        } else {
+            self.stats.duplicates_dropped += 1;
+            false
+        }
+    }
+
+    /// 对端断开：清理它的过滤器、待发公告和未完成的请求
+    pub fn remove_peer(&mut self, peer_id: &str) {
+        self.known.remove(peer_id);
+        self.trickle.remove(peer_id);
+        self.in_flight.remove(peer_id);
+        self.requested.retain(|_, (requested_from, _)| requested_from != peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peers(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_bounded_set_evicts_oldest() {
+        let mut set = BoundedSet::new(2);
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(!set.insert(2));
+        assert!(set.insert(3));
+        assert_eq!(set.len(), 2);
+        assert!(!set.contains(&1));
+        assert!(set.contains(&3));
+    }
+
+    #[test]
+    fn test_block_relay_skips_peers_that_know_it() {
+        let mut gossip = Gossip::default();
+        let item = InventoryItem::block([1u8; 32]);
+        assert!(gossip.on_object("a", item));
+
+        let announcements = gossip.relay(item, &peers(&["a", "b"]), Instant::now());
+        assert_eq!(announcements, vec![("b".to_string(), vec![item])]);
+        // 再次转发不会重复公告
+        assert!(gossip.relay(item, &peers(&["a", "b"]), Instant::now()).is_empty());
+        assert!(!gossip.on_object("b", item));
+        assert_eq!(gossip.stats().duplicates_dropped, 1);
+    }
+
+    #[test]
+    fn test_transactions_trickle_with_delay() {
+        let config = GossipConfig { max_trickle_delay: Duration::from_millis(100), ..GossipConfig::default() };
+        let mut gossip = Gossip::new(config);
+        let now = Instant::now();
+        let item = InventoryItem::transaction([2u8; 32]);
+        assert!(gossip.relay(item, &peers(&["a", "b"]), now).is_empty());
+
+        let later = now + Duration::from_millis(100);
+        let mut due = gossip.due_announcements(later);
+        due.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(due, vec![("a".to_string(), vec![item]), ("b".to_string(), vec![item])]);
+        assert!(gossip.due_announcements(later).is_empty());
+    }
+
+    #[test]
+    fn test_inv_requests_each_item_once() {
+        let mut gossip = Gossip::default();
+        let now = Instant::now();
+        let seen = InventoryItem::transaction([3u8; 32]);
+        let fresh = InventoryItem::transaction([4u8; 32]);
+        gossip.relay(seen, &[], now);
+
+        assert_eq!(gossip.on_inv("a", &[seen, fresh], now), vec![fresh]);
+        // 正在向 a 请求，b 的公告不再触发请求
+        assert!(gossip.on_inv("b", &[fresh], now).is_empty());
+        // 超时后 a 被记为未送达，改向 b 请求
+        let later = now + GossipConfig::default().request_timeout;
+        assert_eq!(gossip.take_expired(later), vec!["a".to_string()]);
+        assert_eq!(gossip.on_inv("b", &[fresh], later), vec![fresh]);
+
+        gossip.remove_peer("b");
+        assert_eq!(gossip.on_inv("c", &[fresh], later), vec![fresh]);
+    }
+
+    #[test]
+    fn test_requests_per_peer_are_capped_and_expire() {
+        let config = GossipConfig { max_requests_per_peer: 2, ..GossipConfig::default() };
+        let mut gossip = Gossip::new(config);
+        let now = Instant::now();
+        let items: Vec<InventoryItem> = (0..4u8).map(|i| InventoryItem::transaction([i; 32])).collect();
+
+        // 对端未完成的请求达到上限后，它公告的其他条目不再请求
+        assert_eq!(gossip.on_inv("a", &items, now), items[..2].to_vec());
+        assert!(gossip.on_inv("a", &items[2..], now).is_empty());
+        assert_eq!(gossip.on_inv("b", &items[2..], now), items[2..].to_vec());
+
+        // 送达的对象释放名额，超时的请求被清除并归咎于被请求的对端
+        assert!(gossip.on_object("a", items[0]));
+        let later = now + GossipConfig::default().request_timeout;
+        let mut expired = gossip.take_expired(later);
+        expired.sort();
+        assert_eq!(expired, vec!["a".to_string(), "b".to_string(), "b".to_string()]);
+        assert!(gossip.take_expired(later).is_empty());
+        assert_eq!(gossip.on_inv("a", &items[1..3], later), items[1..3].to_vec());
+    }
+}
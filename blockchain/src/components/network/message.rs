@@ -1,6 +1,7 @@
 // 消息路由实现
-use crate::core::{Transaction, Block};
+use crate::core::{Transaction, Block, BlockHeader, TxInclusionProof, BalanceProof};
 use crate::components::{ComponentResult, ComponentError};
+use super::compact::CompactBlock;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -26,6 +27,37 @@ pub enum NetworkMessage {
     SyncResponse(SyncResponseMessage),
     Ping(PingMessage),
     Pong(PongMessage),
+    Inv(InvMessage),
+    GetData(GetDataMessage),
+    CompactBlock(CompactBlockMessage),
+    GetBlockTxn(GetBlockTxnMessage),
+    BlockTxn(BlockTxnMessage),
+    LightRequest(LightRequestMessage),
+    LightResponse(LightResponseMessage),
+    Consensus(ConsensusMessage),
+}
+
+impl NetworkMessage {
+    /// 消息类型名称，用于查找消息处理器
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NetworkMessage::Transaction(_) => "transaction",
+            NetworkMessage::Block(_) => "block",
+            NetworkMessage::PeerDiscovery(_) => "peer_discovery",
+            NetworkMessage::SyncRequest(_) => "sync_request",
+            NetworkMessage::SyncResponse(_) => "sync_response",
+            NetworkMessage::Ping(_) => "ping",
+            NetworkMessage::Pong(_) => "pong",
+            NetworkMessage::Inv(_) => "inv",
+            NetworkMessage::GetData(_) => "get_data",
+            NetworkMessage::CompactBlock(_) => "compact_block",
+            NetworkMessage::GetBlockTxn(_) => "get_block_txn",
+            NetworkMessage::BlockTxn(_) => "block_txn",
+            NetworkMessage::LightRequest(_) => "light_request",
+            NetworkMessage::LightResponse(_) => "light_response",
+            NetworkMessage::Consensus(_) => "consensus",
+        }
+    }
 }
 
 /// 对等节点发现消息
@@ -39,15 +71,144 @@ pub struct PeerDiscoveryMessage {
 /// 同步请求消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncRequestMessage {
-    pub from_height: u64,
-    pub to_height: u64,
+    pub request: SyncRequest,
     pub peer_id: String,
 }
 
+/// 同步请求内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncRequest {
+    /// 请求区块头：从定位器中第一个对端已知的区块之后开始，最多 `max_headers` 个
+    Headers {
+        locator: Vec<[u8; 32]>,
+        max_headers: u32,
+    },
+    /// 按哈希请求完整区块
+    Bodies {
+        hashes: Vec<[u8; 32]>,
+    },
+}
+
 /// 同步响应消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncResponseMessage {
-    pub blocks: Vec<Block>,
+    pub response: SyncResponse,
+    pub peer_id: String,
+}
+
+/// 同步响应内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncResponse {
+    Headers(Vec<BlockHeader>),
+    Bodies(Vec<Block>),
+}
+
+/// 轻客户端请求消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightRequestMessage {
+    /// 请求编号，响应中原样返回
+    pub request_id: u64,
+    pub request: LightRequest,
+    pub peer_id: String,
+}
+
+/// 轻客户端请求内容（区块头通过 `SyncRequest::Headers` 同步）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LightRequest {
+    /// 交易包含在指定区块中的 Merkle 证明
+    TxProof {
+        block_hash: [u8; 32],
+        tx_hash: [u8; 32],
+    },
+    /// 账户在全节点当前链头的余额证明
+    BalanceProof {
+        address: String,
+    },
+}
+
+/// 轻客户端响应消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightResponseMessage {
+    pub request_id: u64,
+    pub response: LightResponse,
+    pub peer_id: String,
+}
+
+/// 轻客户端响应内容，无法证明时为 `None`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LightResponse {
+    TxProof(Option<TxInclusionProof>),
+    BalanceProof(Option<BalanceProof>),
+}
+
+/// 库存条目：对象类型和哈希
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InventoryItem {
+    pub kind: InventoryKind,
+    pub hash: [u8; 32],
+}
+
+/// 库存对象类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InventoryKind {
+    Transaction,
+    Block,
+}
+
+impl InventoryItem {
+    pub fn transaction(hash: [u8; 32]) -> Self {
+        Self { kind: InventoryKind::Transaction, hash }
+    }
+
+    pub fn block(hash: [u8; 32]) -> Self {
+        Self { kind: InventoryKind::Block, hash }
+    }
+}
+
+/// 库存公告：告诉对端本节点拥有哪些对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvMessage {
+    pub items: Vec<InventoryItem>,
+    pub peer_id: String,
+}
+
+/// 按库存条目请求完整对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetDataMessage {
+    pub items: Vec<InventoryItem>,
+    pub peer_id: String,
+}
+
+/// 紧凑区块广播
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactBlockMessage {
+    pub block: CompactBlock,
+    pub peer_id: String,
+}
+
+/// 按索引请求紧凑区块中缺失的交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetBlockTxnMessage {
+    pub block_hash: [u8; 32],
+    pub indexes: Vec<u32>,
+    pub peer_id: String,
+}
+
+/// 回复 `GetBlockTxn`：按请求的索引顺序排列的交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTxnMessage {
+    pub block_hash: [u8; 32],
+    pub transactions: Vec<Transaction>,
+    pub peer_id: String,
+}
+
+/// BFT 共识协议消息，只在验证者之间直接发送，不经过库存转发
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsensusMessage {
+    /// 由共识引擎编码的协议消息
+    pub payload: Vec<u8>,
+    /// 提案附带的区块（尚未获得证书）
+    pub block: Option<Block>,
     pub peer_id: String,
 }
 
@@ -131,18 +292,8 @@ impl MessageRouter {
     
     /// 处理消息
     async fn process_message(&self, message: &NetworkMessage) -> ComponentResult<()> {
-        let message_type = match message {
-            NetworkMessage::Transaction(_) => "transaction",
-            NetworkMessage::Block(_) => "block",
-            NetworkMessage::PeerDiscovery(_) => "peer_discovery",
-            NetworkMessage::SyncRequest(_) => "sync_request",
-            NetworkMessage::SyncResponse(_) => "sync_response",
-            NetworkMessage::Ping(_) => "ping",
-            NetworkMessage::Pong(_) => "pong",
-        };
-        
         let handlers = self.handlers.read().await;
-        if let Some(handler) = handlers.get(message_type) {
+        if let Some(handler) = handlers.get(message.type_name()) {
             handler.handle_message(message)?;
         }
         
@@ -228,7 +379,7 @@ impl MessageHandler for SyncRequestHandler {
     fn handle_message(&self, message: &NetworkMessage) -> ComponentResult<()> {
         if let NetworkMessage::SyncRequest(sync_msg) = message {
             // 处理同步请求消息
-            println!("Sync request from {}: {} to {}", sync_msg.peer_id, sync_msg.from_height, sync_msg.to_height);
+            println!("Sync request from {}: {:?}", sync_msg.peer_id, sync_msg.request);
         }
         Ok(())
     }
@@ -245,7 +396,11 @@ impl MessageHandler for SyncResponseHandler {
     fn handle_message(&self, message: &NetworkMessage) -> ComponentResult<()> {
         if let NetworkMessage::SyncResponse(sync_msg) = message {
             // 处理同步响应消息
-            println!("Sync response from {}: {} blocks", sync_msg.peer_id, sync_msg.blocks.len());
+            let (kind, count) = match &sync_msg.response {
+                SyncResponse::Headers(headers) => ("headers", headers.len()),
+                SyncResponse::Bodies(blocks) => ("blocks", blocks.len()),
+            };
+            println!("Sync response from {}: {} {}", sync_msg.peer_id, count, kind);
         }
         Ok(())
     }
@@ -2,73 +2,325 @@
 pub mod p2p;
 pub mod message;
 pub mod peer;
+pub mod codec;
+pub mod sync;
+pub mod gossip;
+pub mod compact;
+pub mod address_book;
+pub mod discovery;
+pub mod secure;
+#[cfg(feature = "quinn")]
+pub mod quic;
 
-pub use p2p::P2PNetwork;
-pub use message::{MessageRouter, NetworkMessage};
-pub use peer::PeerManager;
+pub use p2p::{P2PNetwork, Transport};
+pub use codec::{NetworkCodec, CodecError, MessageType};
+pub use secure::{NodeIdentity, SecureSession, SecureCodec};
+pub use message::{
+    MessageRouter, NetworkMessage, SyncRequest, SyncResponse, SyncRequestMessage, SyncResponseMessage,
+    InventoryItem, InventoryKind, InvMessage, GetDataMessage, CompactBlockMessage, GetBlockTxnMessage, BlockTxnMessage,
+    LightRequest, LightResponse, LightRequestMessage, LightResponseMessage, ConsensusMessage,
+};
+pub use sync::{BlockSync, SyncConfig, SyncProgress, SyncProgressHandle};
+pub use gossip::{Gossip, GossipConfig, GossipStats};
+pub use compact::{CompactBlock, CompactRelay, CompactStats, PartialBlock, PrefilledTransaction};
+pub use address_book::{AddressBook, AddressEntry, ADDRESS_BOOK_FILE};
+pub use discovery::{Discovery, DiscoveryConfig, NodeRecord};
+pub use peer::{PeerManager, PeerConfig, Misbehavior, BanList, BanEntry, BAN_LIST_FILE};
 
 use crate::core::{Transaction, Block, Result, BlockchainError};
 use crate::components::{ComponentResult};
+use std::net::SocketAddr;
+use tokio::sync::mpsc;
+
+/// 收到的需要链处理的消息（对端ID，消息）
+type Inbox = (String, NetworkMessage);
 
 /// 网络组件
 pub struct NetworkComponent {
     pub p2p_network: P2PNetwork,
     pub message_router: MessageRouter,
     pub peer_manager: PeerManager,
+    /// 区块同步状态
+    pub block_sync: BlockSync,
+    /// 库存广播状态
+    pub gossip: Gossip,
+    /// 紧凑区块中继状态
+    pub compact: CompactRelay,
+    /// 节点发现服务（`start_discovery` 后可用）
+    pub discovery: Option<Discovery>,
+    /// 链规范中的引导节点（UDP 地址）
+    pub bootstrap_nodes: Vec<SocketAddr>,
+    inbox_sender: mpsc::UnboundedSender<Inbox>,
+    inbox: mpsc::UnboundedReceiver<Inbox>,
 }
 
 impl NetworkComponent {
     /// 创建新的网络组件
     pub fn new() -> Self {
+        Self::with_p2p_network(P2PNetwork::new())
+    }
+    
+    /// 使用已配置的 P2P 网络（网络ID、节点身份）创建网络组件，对端管理器与网络共享
+    pub fn with_p2p_network(p2p_network: P2PNetwork) -> Self {
+        let (inbox_sender, inbox) = mpsc::unbounded_channel();
         Self {
-            p2p_network: P2PNetwork::new(),
+            peer_manager: p2p_network.peer_manager(),
+            p2p_network,
             message_router: MessageRouter::new(),
-            peer_manager: PeerManager::new(),
+            block_sync: BlockSync::default(),
+            gossip: Gossip::default(),
+            compact: CompactRelay::default(),
+            discovery: None,
+            bootstrap_nodes: Vec::new(),
+            inbox_sender,
+            inbox,
         }
     }
     
     /// 初始化网络组件
     pub async fn initialize(&mut self) -> ComponentResult<()> {
         self.p2p_network.initialize().await?;
+        // 同步和广播消息需要访问链数据，转交给链在 `Blockchain::poll_network` 中处理
+        for message_type in [
+            "sync_request", "sync_response", "inv", "get_data", "transaction", "block",
+            "compact_block", "get_block_txn", "block_txn", "light_request", "light_response", "consensus",
+        ] {
+            let handler = InboxHandler { message_type, sender: self.inbox_sender.clone() };
+            self.p2p_network.register_message_handler(message_type, Box::new(handler)).await;
+        }
         self.message_router.initialize().await?;
         self.peer_manager.initialize().await?;
         Ok(())
     }
     
-    /// 广播交易
+    /// 广播交易：只公告哈希，按随机延迟涓流发送给尚未拥有它的对端
     pub async fn broadcast_transaction(&mut self, tx: &Transaction) -> Result<()> {
-        let message = NetworkMessage::Transaction(tx.clone());
-        self.message_router.broadcast(message).await
-            .map_err(|e| BlockchainError::NetworkError(format!("Failed to broadcast transaction: {}", e)))?;
-        Ok(())
+        self.relay_inventory(InventoryItem::transaction(tx.hash())).await
     }
     
-    /// 广播区块
+    /// 广播区块：立即向尚未拥有它的对端发送紧凑区块
+    ///
+    /// 本节点还原该区块时缺失的交易对端大概率也没有，随紧凑区块预填发送
     pub async fn broadcast_block(&mut self, block: &Block) -> Result<()> {
-        let message = NetworkMessage::Block(block.clone());
-        self.message_router.broadcast(message).await
-            .map_err(|e| BlockchainError::NetworkError(format!("Failed to broadcast block: {}", e)))?;
+        let peers = self.p2p_network.get_peer_ids().await;
+        let announcements = self.gossip.relay(InventoryItem::block(block.block_hash), &peers, std::time::Instant::now());
+        let prefill = self.compact.take_prefill(&block.block_hash);
+        if announcements.is_empty() {
+            return Ok(());
+        }
+        let message = NetworkMessage::CompactBlock(CompactBlockMessage {
+            block: CompactBlock::from_block(block, rand::random(), &prefill),
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        for (peer_id, _) in announcements {
+            self.send_or_drop(&peer_id, &message).await;
+        }
+        Ok(())
+    }
+    
+    /// 向所有对端发送共识协议消息，`block` 为提案附带的区块
+    ///
+    /// 验证者地址与对端的对应关系未知，定向消息同样发送给所有对端，由接收方的共识引擎按签名校验
+    pub async fn broadcast_consensus(&mut self, payload: Vec<u8>, block: Option<Block>) -> Result<()> {
+        let message = NetworkMessage::Consensus(ConsensusMessage {
+            payload,
+            block,
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        for peer_id in self.p2p_network.get_peer_ids().await {
+            self.send_or_drop(&peer_id, &message).await;
+        }
+        Ok(())
+    }
+    
+    /// 公告已通过验证的对象
+    async fn relay_inventory(&mut self, item: InventoryItem) -> Result<()> {
+        let peers = self.p2p_network.get_peer_ids().await;
+        let announcements = self.gossip.relay(item, &peers, std::time::Instant::now());
+        for (peer_id, items) in announcements {
+            self.send_inventory(&peer_id, items).await;
+        }
+        Ok(())
+    }
+    
+    /// 发送已到期的交易公告
+    pub async fn flush_announcements(&mut self) -> Result<()> {
+        for (peer_id, items) in self.gossip.due_announcements(std::time::Instant::now()) {
+            self.send_inventory(&peer_id, items).await;
+        }
+        Ok(())
+    }
+    
+    /// 清除超时未送达的请求：公告了对象却没有提供的对端按条目扣分
+    pub async fn expire_requests(&mut self) {
+        for peer_id in self.gossip.take_expired(std::time::Instant::now()) {
+            self.report_peer(&peer_id, Misbehavior::Spam).await;
+        }
+    }
+    
+    /// 向对端发送 inv；发送失败视为对端已断开
+    async fn send_inventory(&mut self, peer_id: &str, items: Vec<InventoryItem>) {
+        let message = NetworkMessage::Inv(InvMessage {
+            items,
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        self.send_or_drop(peer_id, &message).await;
+    }
+    
+    /// 处理对端的公告，向它请求本地还没有的对象
+    pub async fn handle_inv(&mut self, peer_id: &str, items: &[InventoryItem]) -> Result<()> {
+        let wanted = self.gossip.on_inv(peer_id, items, std::time::Instant::now());
+        if wanted.is_empty() {
+            return Ok(());
+        }
+        let message = NetworkMessage::GetData(GetDataMessage {
+            items: wanted,
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        self.send_or_drop(peer_id, &message).await;
         Ok(())
     }
     
-    /// 与对等节点同步
+    /// 向对端请求紧凑区块中缺失的交易
+    pub async fn request_block_transactions(&mut self, peer_id: &str, block_hash: [u8; 32], indexes: Vec<u32>) -> Result<()> {
+        let message = NetworkMessage::GetBlockTxn(GetBlockTxnMessage {
+            block_hash,
+            indexes,
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        self.send_or_drop(peer_id, &message).await;
+        Ok(())
+    }
+    
+    /// 紧凑区块无法还原时向对端请求完整区块
+    pub async fn request_full_block(&mut self, peer_id: &str, block_hash: [u8; 32]) -> Result<()> {
+        self.compact.on_fallback();
+        let message = NetworkMessage::GetData(GetDataMessage {
+            items: vec![InventoryItem::block(block_hash)],
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        self.send_or_drop(peer_id, &message).await;
+        Ok(())
+    }
+    
+    /// 回复 getdata：发送完整的交易或区块
+    pub async fn send_object(&mut self, peer_id: &str, message: NetworkMessage) -> Result<()> {
+        self.send_or_drop(peer_id, &message).await;
+        Ok(())
+    }
+    
+    async fn send_or_drop(&mut self, peer_id: &str, message: &NetworkMessage) {
+        if let Err(e) = self.p2p_network.send_message(peer_id, message).await {
+            log::warn!("Failed to send {} to {}: {}", message.type_name(), peer_id, e);
+            self.gossip.remove_peer(peer_id);
+            self.compact.remove_peer(peer_id);
+        }
+    }
+    
+    /// 与对等节点同步：向已连接的对端发送到期的区块头和区块体请求
     pub async fn sync_with_peers(&mut self) -> Result<()> {
-        let peers = self.peer_manager.get_active_peers().await;
-        for peer in peers {
-            self.sync_with_peer(peer).await?;
+        let peers = self.p2p_network.get_peer_ids().await;
+        let local_peer_id = self.p2p_network.get_peer_id();
+        for (peer_id, request) in self.block_sync.next_requests(&peers, std::time::Instant::now()) {
+            let message = NetworkMessage::SyncRequest(SyncRequestMessage {
+                request,
+                peer_id: local_peer_id.clone(),
+            });
+            if let Err(e) = self.p2p_network.send_message(&peer_id, &message).await {
+                log::warn!("Failed to send sync request to {}: {}", peer_id, e);
+                self.block_sync.on_peer_disconnected(&peer_id);
+            }
         }
         Ok(())
     }
     
-    /// 与特定对等节点同步
-    #[allow(dead_code)]
-    #[allow(unused_variables)]
-    async fn sync_with_peer(&mut self, peer: String) -> Result<()> {
-        // 实现与特定对等节点的同步逻辑
-        // 这里应该包含区块同步、交易同步等
+    /// 回复同步请求
+    pub async fn send_sync_response(&mut self, peer_id: &str, response: SyncResponse) -> Result<()> {
+        let message = NetworkMessage::SyncResponse(SyncResponseMessage {
+            response,
+            peer_id: self.p2p_network.get_peer_id(),
+        });
+        self.p2p_network.send_message(peer_id, &message).await
+            .map_err(|e| BlockchainError::NetworkError(format!("Failed to send sync response: {}", e)))
+    }
+    
+    /// 记录对端违规；对端被封禁时同时清理它的广播和同步状态
+    pub async fn report_peer(&mut self, peer_id: &str, misbehavior: Misbehavior) {
+        match self.p2p_network.report_peer(peer_id, misbehavior).await {
+            Ok(true) => {
+                self.gossip.remove_peer(peer_id);
+                self.compact.remove_peer(peer_id);
+                self.block_sync.on_peer_disconnected(peer_id);
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to report peer {}: {}", peer_id, e),
+        }
+    }
+    
+    /// 取出所有待处理的同步和广播消息
+    pub fn drain_messages(&mut self) -> Vec<(String, NetworkMessage)> {
+        let mut messages = Vec::new();
+        while let Ok(message) = self.inbox.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+    
+    /// 等待下一条待处理消息，收件箱关闭时返回 `None`
+    pub async fn next_message(&mut self) -> Option<(String, NetworkMessage)> {
+        self.inbox.recv().await
+    }
+    
+    /// 启动节点发现：绑定 UDP 端口，从引导节点加入网络并定期刷新路由表
+    ///
+    /// P2P 网络需已启动，其监听端口随发现数据包告知对端；`address_book` 通常从数据目录加载。
+    pub async fn start_discovery(&mut self, port: u16, address_book: AddressBook, config: DiscoveryConfig) -> Result<()> {
+        let tcp_port = self.p2p_network.get_listen_addr().map(|addr| addr.port()).unwrap_or(0);
+        let discovery = Discovery::bind(
+            self.p2p_network.identity(),
+            SocketAddr::from(([0, 0, 0, 0], port)),
+            tcp_port,
+            self.p2p_network.get_network_id(),
+            address_book,
+            config,
+        ).await.map_err(|e| BlockchainError::NetworkError(e.to_string()))?;
+        
+        if !self.bootstrap_nodes.is_empty()
+            && let Err(e) = discovery.bootstrap(&self.bootstrap_nodes).await
+        {
+            log::warn!("Discovery bootstrap failed: {}", e);
+        }
+        discovery.spawn_refresh();
+        self.discovery = Some(discovery);
         Ok(())
     }
     
+    /// 从地址簿挑选地址建立出站连接，直到已连接数达到 `target`，返回新连接的对端
+    pub async fn connect_discovered_peers(&mut self, target: usize) -> Result<Vec<String>> {
+        let Some(discovery) = &self.discovery else {
+            return Ok(Vec::new());
+        };
+        let address_book = discovery.address_book();
+        let connected = self.p2p_network.get_connection_count().await;
+        let candidates = address_book.lock().unwrap().select(target.saturating_sub(connected) * 2);
+        
+        let mut peers = Vec::new();
+        for address in candidates {
+            if self.p2p_network.get_connection_count().await >= target {
+                break;
+            }
+            address_book.lock().unwrap().mark_attempt(&address);
+            match self.p2p_network.connect_to_peer(&address.to_string()).await {
+                Ok(peer_id) => {
+                    address_book.lock().unwrap().mark_good(&address);
+                    peers.push(peer_id);
+                }
+                Err(e) => log::debug!("Failed to connect to discovered peer {}: {}", address, e),
+            }
+        }
+        Ok(peers)
+    }
+    
     /// 获取对等节点数量
     #[allow(dead_code)]
     pub async fn get_peer_count(&self) -> usize {
@@ -107,3 +359,20 @@ impl NetworkComponent {
         Ok(())
     }
 }
+
+/// 把需要链处理的消息转入网络组件的收件箱
+struct InboxHandler {
+    message_type: &'static str,
+    sender: mpsc::UnboundedSender<Inbox>,
+}
+
+impl p2p::MessageHandler for InboxHandler {
+    fn handle_message(&self, message: &NetworkMessage, peer_id: &str) -> ComponentResult<Option<NetworkMessage>> {
+        let _ = self.sender.send((peer_id.to_string(), message.clone()));
+        Ok(None)
+    }
+    
+    fn message_type(&self) -> &str {
+        self.message_type
+    }
+}
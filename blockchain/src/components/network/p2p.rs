@@ -1,63 +1,158 @@
 // P2P网络实现
 use crate::components::{ComponentResult, ComponentError};
+use super::codec::{CodecError, NetworkCodec, DEFAULT_NETWORK_ID};
+use super::message::{NetworkMessage, PongMessage};
+use super::peer::{Misbehavior, PeerManager};
+use super::secure::{self, NodeIdentity, SecureCodec};
+#[cfg(feature = "quinn")]
+use super::quic;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::{SocketAddr};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::net::{TcpListener as TokioTcpListener, TcpStream as TokioTcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+/// 握手前后的协议违规累计到该分数时封禁对端 IP，各类违规的分数见 `CodecError::penalty`
+const ADDRESS_BAN_SCORE: u32 = 100;
+
+/// 加密握手超时时间
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Connections = Arc<RwLock<HashMap<String, Connection>>>;
+type MessageHandlers = Arc<RwLock<HashMap<String, Box<dyn MessageHandler + Send + Sync>>>>;
+
+/// 对端连接使用的传输协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// TCP，连接建立后执行 `secure` 模块的加密握手
+    #[default]
+    Tcp,
+    /// QUIC（UDP），由 TLS 1.3 和绑定节点身份的自签名证书完成认证，需要启用 `quinn` 特性
+    #[cfg(feature = "quinn")]
+    Quic,
+}
+
+impl FromStr for Transport {
+    type Err = ComponentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Transport::Tcp),
+            #[cfg(feature = "quinn")]
+            "quic" => Ok(Transport::Quic),
+            other => Err(ComponentError::ConfigurationError(format!("Unsupported transport: {}", other))),
+        }
+    }
+}
 
 /// P2P网络
 pub struct P2PNetwork {
     /// 监听地址
     listen_addr: Option<SocketAddr>,
     /// 连接的对等节点
-    connections: Arc<RwLock<HashMap<String, Connection>>>,
+    connections: Connections,
     /// 是否正在运行
     running: Arc<Mutex<bool>>,
     /// 消息处理器
-    message_handlers: Arc<RwLock<HashMap<String, Box<dyn MessageHandler + Send + Sync>>>>,
+    message_handlers: MessageHandlers,
+    /// 网络ID（写入每个帧头，不同网络的帧会被拒绝）
+    network_id: u32,
+    /// 对端违规分（按 IP 累计）
+    misbehavior: Arc<RwLock<HashMap<IpAddr, u32>>>,
+    /// 本节点身份，节点ID由其公钥派生
+    identity: Arc<NodeIdentity>,
+    /// 对端评分、封禁和连接限额
+    peer_manager: PeerManager,
+    /// 对端连接使用的传输协议
+    transport: Transport,
+    /// QUIC 端点（监听或首次主动连接时创建）
+    #[cfg(feature = "quinn")]
+    quic_endpoint: Option<quinn::Endpoint>,
 }
 
 /// 网络连接
 #[derive(Debug)]
 pub struct Connection {
+    /// 由对端身份公钥派生的节点ID
     pub peer_id: String,
     pub address: SocketAddr,
-    pub stream: Arc<Mutex<TokioTcpStream>>,
+    /// 握手中认证过的对端身份公钥
+    pub public_key: [u8; 32],
+    /// 发往写任务的消息队列，写任务负责分帧编码
+    pub sender: mpsc::UnboundedSender<NetworkMessage>,
     pub connected_at: std::time::Instant,
     pub last_seen: std::time::Instant,
 }
 
 /// 消息处理器 trait
 pub trait MessageHandler: Send + Sync {
-    fn handle_message(&self, message: &[u8], peer_id: &str) -> ComponentResult<Vec<u8>>;
+    /// 处理解码后的消息，返回值会回复给发送方
+    fn handle_message(&self, message: &NetworkMessage, peer_id: &str) -> ComponentResult<Option<NetworkMessage>>;
     fn message_type(&self) -> &str;
 }
 
-/// 网络消息类型
-#[derive(Debug, Clone)]
-pub enum NetworkMessageType {
-    Transaction,
-    Block,
-    PeerDiscovery,
-    SyncRequest,
-    SyncResponse,
-    Ping,
-    Pong,
-}
-
 impl P2PNetwork {
     /// 创建新的P2P网络
     pub fn new() -> Self {
+        Self::with_network_id(DEFAULT_NETWORK_ID)
+    }
+    
+    /// 创建指定网络ID的P2P网络
+    pub fn with_network_id(network_id: u32) -> Self {
         Self {
             listen_addr: None,
             connections: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(Mutex::new(false)),
             message_handlers: Arc::new(RwLock::new(HashMap::new())),
+            network_id,
+            misbehavior: Arc::new(RwLock::new(HashMap::new())),
+            identity: Arc::new(NodeIdentity::generate()),
+            peer_manager: PeerManager::new(),
+            transport: Transport::default(),
+            #[cfg(feature = "quinn")]
+            quic_endpoint: None,
         }
     }
     
+    /// 使用指定的节点身份（通常由 `NodeIdentity::load_or_generate` 从磁盘加载）
+    pub fn with_identity(mut self, identity: NodeIdentity) -> Self {
+        self.identity = Arc::new(identity);
+        self
+    }
+    
+    /// 使用指定的对端管理器（连接限额、持久化的封禁列表）
+    pub fn with_peer_manager(mut self, peer_manager: PeerManager) -> Self {
+        self.peer_manager = peer_manager;
+        self
+    }
+    
+    /// 使用指定的传输协议（需在启动和连接之前设置）
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.set_transport(transport);
+        self
+    }
+    
+    /// 设置传输协议（需在启动和连接之前设置）
+    pub fn set_transport(&mut self, transport: Transport) {
+        self.transport = transport;
+    }
+    
+    /// 对端连接使用的传输协议
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+    
+    /// 与网络共享状态的对端管理器
+    pub fn peer_manager(&self) -> PeerManager {
+        self.peer_manager.clone()
+    }
+    
     /// 初始化P2P网络
     pub async fn initialize(&mut self) -> ComponentResult<()> {
         // 注册默认消息处理器
@@ -69,6 +164,11 @@ impl P2PNetwork {
     
     /// 启动P2P网络
     pub async fn start(&mut self, port: u16) -> ComponentResult<()> {
+        #[cfg(feature = "quinn")]
+        if self.transport == Transport::Quic {
+            return self.start_quic(port).await;
+        }
+        
         let addr = format!("0.0.0.0:{}", port);
         let listener = TokioTcpListener::bind(&addr).await
             .map_err(|e| ComponentError::NetworkError(format!("Failed to bind to {}: {}", addr, e)))?;
@@ -76,39 +176,29 @@ impl P2PNetwork {
         self.listen_addr = Some(listener.local_addr()
             .map_err(|e| ComponentError::NetworkError(format!("Failed to get local address: {}", e)))?);
         
-        let connections = Arc::clone(&self.connections);
+        *self.running.lock().await = true;
         let running = Arc::clone(&self.running);
-        let message_handlers = Arc::clone(&self.message_handlers);
+        let context = self.connection_context();
         
         // 启动监听任务
         tokio::spawn(async move {
-            let mut running_guard = running.lock().await;
-            *running_guard = true;
-            drop(running_guard);
-            
             while *running.lock().await {
                 match listener.accept().await {
                     Ok((stream, addr)) => {
-                        let peer_id = format!("peer_{}", addr);
-                        let connection = Connection {
-                            peer_id: peer_id.clone(),
-                            address: addr,
-                            stream: Arc::new(Mutex::new(stream)),
-                            connected_at: std::time::Instant::now(),
-                            last_seen: std::time::Instant::now(),
-                        };
-                        
-                        connections.write().await.insert(peer_id.clone(), connection);
-                        
-                        // 启动连接处理任务
-                        let connections_clone = Arc::clone(&connections);
-                        let message_handlers_clone = Arc::clone(&message_handlers);
+                        if context.peer_manager.is_banned(&addr.ip()) {
+                            log::debug!("Refusing connection from banned address {}", addr);
+                            continue;
+                        }
+                        // 握手在独立任务中进行，慢速对端不会阻塞监听
+                        let context = context.clone();
                         tokio::spawn(async move {
-                            Self::handle_connection(peer_id, connections_clone, message_handlers_clone).await;
+                            if let Err(e) = context.spawn_connection(stream, addr, false).await {
+                                log::warn!("Handshake with {} failed: {}", addr, e);
+                            }
                         });
                     }
                     Err(e) => {
-                        eprintln!("Failed to accept connection: {}", e);
+                        log::warn!("Failed to accept connection: {}", e);
                     }
                 }
             }
@@ -117,13 +207,31 @@ impl P2PNetwork {
         Ok(())
     }
     
+    /// 在 UDP 端口上启动 QUIC 端点并接受入站连接
+    #[cfg(feature = "quinn")]
+    async fn start_quic(&mut self, port: u16) -> ComponentResult<()> {
+        let endpoint = quic::server_endpoint(&self.identity, SocketAddr::from(([0, 0, 0, 0], port)))?;
+        self.listen_addr = Some(endpoint.local_addr()
+            .map_err(|e| ComponentError::NetworkError(format!("Failed to get local address: {}", e)))?);
+        
+        *self.running.lock().await = true;
+        tokio::spawn(quic::accept_connections(endpoint.clone(), self.connection_context(), Arc::clone(&self.running)));
+        self.quic_endpoint = Some(endpoint);
+        Ok(())
+    }
+    
     /// 停止P2P网络
     pub async fn stop(&mut self) -> ComponentResult<()> {
         let mut running_guard = self.running.lock().await;
         *running_guard = false;
         drop(running_guard);
         
-        // 关闭所有连接
+        #[cfg(feature = "quinn")]
+        if let Some(endpoint) = self.quic_endpoint.take() {
+            endpoint.close(quinn::VarInt::from_u32(0), b"shutdown");
+        }
+        
+        // 关闭所有连接（丢弃发送端后写任务随之结束）
         let mut connections = self.connections.write().await;
         connections.clear();
         
@@ -131,40 +239,69 @@ impl P2PNetwork {
     }
     
     /// 连接到对等节点
-    pub async fn connect_to_peer(&mut self, address: &str) -> ComponentResult<()> {
+    pub async fn connect_to_peer(&mut self, address: &str) -> ComponentResult<String> {
+        #[cfg(feature = "quinn")]
+        if self.transport == Transport::Quic {
+            return self.connect_quic(address).await;
+        }
+        
         let stream = TokioTcpStream::connect(address).await
             .map_err(|e| ComponentError::NetworkError(format!("Failed to connect to {}: {}", address, e)))?;
         
         let addr = stream.peer_addr()
             .map_err(|e| ComponentError::NetworkError(format!("Failed to get peer address: {}", e)))?;
+        if self.peer_manager.is_banned(&addr.ip()) {
+            return Err(ComponentError::NetworkError(format!("Address {} is banned", addr.ip())));
+        }
         
-        let peer_id = format!("peer_{}", addr);
-        let connection = Connection {
-            peer_id: peer_id.clone(),
-            address: addr,
-            stream: Arc::new(Mutex::new(stream)),
-            connected_at: std::time::Instant::now(),
-            last_seen: std::time::Instant::now(),
-        };
-        
-        self.connections.write().await.insert(peer_id.clone(), connection);
+        Ok(self.connection_context().spawn_connection(stream, addr, true).await?)
+    }
+    
+    /// 通过 QUIC 连接到对等节点，未监听时先创建仅用于主动连接的端点
+    #[cfg(feature = "quinn")]
+    async fn connect_quic(&mut self, address: &str) -> ComponentResult<String> {
+        let addr = tokio::net::lookup_host(address).await
+            .map_err(|e| ComponentError::NetworkError(format!("Failed to resolve {}: {}", address, e)))?
+            .next()
+            .ok_or_else(|| ComponentError::NetworkError(format!("No address for {}", address)))?;
+        if self.peer_manager.is_banned(&addr.ip()) {
+            return Err(ComponentError::NetworkError(format!("Address {} is banned", addr.ip())));
+        }
         
-        Ok(())
+        let endpoint = match &self.quic_endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => {
+                let endpoint = quic::client_endpoint(&self.identity)?;
+                self.quic_endpoint = Some(endpoint.clone());
+                endpoint
+            }
+        };
+        Ok(quic::connect(&endpoint, &self.connection_context(), addr).await?)
     }
     
     /// 断开与对等节点的连接
     pub async fn disconnect_peer(&mut self, peer_id: &str) -> ComponentResult<()> {
         self.connections.write().await.remove(peer_id);
+        self.peer_manager.peer_disconnected(peer_id).await;
         Ok(())
     }
     
+    /// 记录对端违规，评分低于阈值时断开并封禁，返回是否已封禁
+    pub async fn report_peer(&mut self, peer_id: &str, misbehavior: Misbehavior) -> ComponentResult<bool> {
+        let banned = self.peer_manager.report_misbehavior(peer_id, misbehavior).await?;
+        if banned {
+            log::warn!("Banned peer {} for {:?}", peer_id, misbehavior);
+            self.connections.write().await.remove(peer_id);
+        }
+        Ok(banned)
+    }
+    
     /// 发送消息到对等节点
-    pub async fn send_message(&mut self, peer_id: &str, message: &[u8]) -> ComponentResult<()> {
+    pub async fn send_message(&mut self, peer_id: &str, message: &NetworkMessage) -> ComponentResult<()> {
         let connections = self.connections.read().await;
         if let Some(connection) = connections.get(peer_id) {
-            let mut stream = connection.stream.lock().await;
-            stream.write_all(message).await
-                .map_err(|e| ComponentError::NetworkError(format!("Failed to send message: {}", e)))?;
+            connection.sender.send(message.clone())
+                .map_err(|_| ComponentError::NetworkError(format!("Connection to {} is closed", peer_id)))?;
         } else {
             return Err(ComponentError::NetworkError(format!("Peer {} not found", peer_id)));
         }
@@ -173,14 +310,14 @@ impl P2PNetwork {
     }
     
     /// 广播消息到所有对等节点
-    pub async fn broadcast_message(&mut self, message: &[u8]) -> ComponentResult<()> {
+    pub async fn broadcast_message(&mut self, message: &NetworkMessage) -> ComponentResult<()> {
         let connections = self.connections.read().await;
         let peer_ids: Vec<String> = connections.keys().cloned().collect();
         drop(connections);
         
         for peer_id in peer_ids {
             if let Err(e) = self.send_message(&peer_id, message).await {
-                eprintln!("Failed to send message to {}: {}", peer_id, e);
+                log::warn!("Failed to send message to {}: {}", peer_id, e);
             }
         }
         
@@ -192,52 +329,15 @@ impl P2PNetwork {
         self.message_handlers.write().await.insert(message_type.to_string(), handler);
     }
     
-    /// 处理连接
-    async fn handle_connection(
-        peer_id: String,
-        connections: Arc<RwLock<HashMap<String, Connection>>>,
-        message_handlers: Arc<RwLock<HashMap<String, Box<dyn MessageHandler + Send + Sync>>>>,
-    ) {
-        let mut buffer = [0u8; 1024];
-        
-        loop {
-            let stream = {
-                let connections = connections.read().await;
-                if let Some(connection) = connections.get(&peer_id) {
-                    Arc::clone(&connection.stream)
-                } else {
-                    break;
-                }
-            };
-            
-            let mut stream_guard = stream.lock().await;
-            match stream_guard.read(&mut buffer).await {
-                Ok(0) => {
-                    // 连接关闭
-                    break;
-                }
-                Ok(n) => {
-                    let message = &buffer[..n];
-                    
-                    // 处理消息
-                    let handlers = message_handlers.read().await;
-                    for (_, handler) in handlers.iter() {
-                        if let Ok(response) = handler.handle_message(message, &peer_id) {
-                            if let Err(e) = stream_guard.write_all(&response).await {
-                                eprintln!("Failed to send response: {}", e);
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to read from connection: {}", e);
-                    break;
-                }
-            }
+    fn connection_context(&self) -> ConnectionContext {
+        ConnectionContext {
+            connections: Arc::clone(&self.connections),
+            message_handlers: Arc::clone(&self.message_handlers),
+            misbehavior: Arc::clone(&self.misbehavior),
+            network_id: self.network_id,
+            identity: Arc::clone(&self.identity),
+            peer_manager: self.peer_manager.clone(),
         }
-        
-        // 清理连接
-        connections.write().await.remove(&peer_id);
     }
     
     /// 获取连接数量
@@ -250,6 +350,21 @@ impl P2PNetwork {
         self.connections.read().await.keys().cloned().collect()
     }
     
+    /// 本节点ID
+    pub fn get_peer_id(&self) -> String {
+        self.identity.peer_id()
+    }
+    
+    /// 本节点身份（节点发现使用同一身份签名）
+    pub fn identity(&self) -> Arc<NodeIdentity> {
+        Arc::clone(&self.identity)
+    }
+    
+    /// 获取对端 IP 的违规分
+    pub async fn get_misbehavior_score(&self, ip: &IpAddr) -> u32 {
+        self.misbehavior.read().await.get(ip).copied().unwrap_or(0)
+    }
+    
     /// 检查是否正在运行
     pub async fn is_running(&self) -> bool {
         *self.running.lock().await
@@ -259,14 +374,198 @@ impl P2PNetwork {
     pub fn get_listen_addr(&self) -> Option<SocketAddr> {
         self.listen_addr
     }
+    
+    /// 获取网络ID
+    pub fn get_network_id(&self) -> u32 {
+        self.network_id
+    }
+}
+
+/// 连接任务共享的网络状态，TCP 和 QUIC 传输共用同一套连接登记和消息分发
+#[derive(Clone)]
+pub(super) struct ConnectionContext {
+    connections: Connections,
+    message_handlers: MessageHandlers,
+    misbehavior: Arc<RwLock<HashMap<IpAddr, u32>>>,
+    pub(super) network_id: u32,
+    pub(super) identity: Arc<NodeIdentity>,
+    pub(super) peer_manager: PeerManager,
+}
+
+impl ConnectionContext {
+    /// 完成加密握手后登记连接并启动分帧读写任务，返回已认证的对等节点ID
+    ///
+    /// 握手数据不合法时按错误类型记录违规分后断开，超时和 IO 错误不计分。
+    async fn spawn_connection(
+        &self,
+        mut stream: TokioTcpStream,
+        address: SocketAddr,
+        initiator: bool,
+    ) -> Result<String, CodecError> {
+        let handshake = tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            secure::handshake(&mut stream, &self.identity, initiator),
+        ).await.unwrap_or(Err(CodecError::HandshakeTimeout));
+        let session = match handshake {
+            Ok(session) => session,
+            Err(e) => {
+                self.penalize(address.ip(), e.penalty()).await;
+                return Err(e);
+            }
+        };
+        
+        let peer_id = session.remote_peer_id();
+        let mut receiver = self.register(&peer_id, address, session.remote_public_key(), !initiator).await?;
+        let (send_cipher, receive_cipher) = session.split();
+        let (read_half, write_half) = stream.into_split();
+        
+        let mut sink = FramedWrite::new(write_half, SecureCodec::new(NetworkCodec::new(self.network_id), send_cipher));
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if let Err(e) = sink.send(message).await {
+                    log::warn!("Failed to send frame: {}", e);
+                    break;
+                }
+            }
+        });
+        
+        let frames = FramedRead::new(read_half, SecureCodec::new(NetworkCodec::new(self.network_id), receive_cipher));
+        let context = self.clone();
+        let reader_peer_id = peer_id.clone();
+        tokio::spawn(async move {
+            context.handle_connection(reader_peer_id, address, frames).await;
+        });
+        
+        Ok(peer_id)
+    }
+    
+    /// 登记已认证的连接，返回写任务的消息队列
+    ///
+    /// 拒绝连接到自身；超过连接限额时拒绝连接，入站满额时可能淘汰一个评分为负的已有连接。
+    pub(super) async fn register(
+        &self,
+        peer_id: &str,
+        address: SocketAddr,
+        public_key: [u8; 32],
+        inbound: bool,
+    ) -> Result<mpsc::UnboundedReceiver<NetworkMessage>, CodecError> {
+        if public_key == self.identity.public_key() {
+            return Err(CodecError::HandshakeFailed("connected to self".to_string()));
+        }
+        let evicted = self.peer_manager.register_connection(peer_id, address, inbound).await
+            .map_err(|e| CodecError::ConnectionRejected(e.to_string()))?;
+        if let Some(evicted) = evicted {
+            log::info!("Evicting peer {} to make room for {}", evicted, peer_id);
+            self.connections.write().await.remove(&evicted);
+        }
+        
+        let (sender, receiver) = mpsc::unbounded_channel::<NetworkMessage>();
+        let connection = Connection {
+            peer_id: peer_id.to_string(),
+            address,
+            public_key,
+            sender,
+            connected_at: std::time::Instant::now(),
+            last_seen: std::time::Instant::now(),
+        };
+        self.connections.write().await.insert(peer_id.to_string(), connection);
+        Ok(receiver)
+    }
+    
+    /// 更新对端活跃时间并交给对应的消息处理器，返回处理器的回复
+    ///
+    /// 连接已被移除（断开、封禁或淘汰）时返回错误，读任务应随之结束。
+    pub(super) async fn dispatch(&self, peer_id: &str, message: &NetworkMessage) -> ComponentResult<Option<NetworkMessage>> {
+        match self.connections.write().await.get_mut(peer_id) {
+            Some(connection) => connection.last_seen = std::time::Instant::now(),
+            None => return Err(ComponentError::NetworkError(format!("Peer {} not found", peer_id))),
+        }
+        
+        let handlers = self.message_handlers.read().await;
+        let Some(handler) = handlers.get(message.type_name()) else {
+            return Ok(None);
+        };
+        match handler.handle_message(message, peer_id) {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                log::warn!("Failed to handle {} from {}: {}", message.type_name(), peer_id, e);
+                Ok(None)
+            }
+        }
+    }
+    
+    /// 经连接的写任务发送消息
+    pub(super) async fn send_to(&self, peer_id: &str, message: NetworkMessage) {
+        if let Some(connection) = self.connections.read().await.get(peer_id) {
+            let _ = connection.sender.send(message);
+        }
+    }
+    
+    /// 移除连接并通知对端管理器
+    pub(super) async fn disconnected(&self, peer_id: &str) {
+        self.connections.write().await.remove(peer_id);
+        self.peer_manager.peer_disconnected(peer_id).await;
+    }
+    
+    /// 累加对端 IP 的违规分，达到封禁分数时封禁该 IP
+    pub(super) async fn penalize(&self, ip: IpAddr, penalty: u32) {
+        if penalty == 0 {
+            return;
+        }
+        let score = {
+            let mut misbehavior = self.misbehavior.write().await;
+            let score = misbehavior.entry(ip).or_insert(0);
+            *score += penalty;
+            *score
+        };
+        if score >= ADDRESS_BAN_SCORE {
+            let duration = self.peer_manager.config().ban_duration;
+            if let Err(e) = self.peer_manager.ban_address(ip, duration, "protocol violation") {
+                log::warn!("Failed to ban {}: {}", ip, e);
+            }
+        }
+    }
+    
+    /// 读取并分发帧，对端违反协议时断开连接并记录违规分
+    async fn handle_connection(
+        &self,
+        peer_id: String,
+        address: SocketAddr,
+        mut frames: FramedRead<tokio::net::tcp::OwnedReadHalf, SecureCodec>,
+    ) {
+        while let Some(frame) = frames.next().await {
+            match frame {
+                Ok(message) => match self.dispatch(&peer_id, &message).await {
+                    Ok(Some(response)) => self.send_to(&peer_id, response).await,
+                    Ok(None) => {}
+                    Err(_) => break,
+                },
+                Err(e) => {
+                    if e.is_protocol_violation() {
+                        self.penalize(address.ip(), e.penalty()).await;
+                        log::warn!("Disconnecting {}: {}", peer_id, e);
+                    }
+                    break;
+                }
+            }
+        }
+        
+        self.disconnected(&peer_id).await;
+    }
 }
 
 /// Ping消息处理器
 struct PingHandler;
 
 impl MessageHandler for PingHandler {
-    fn handle_message(&self, _message: &[u8], _peer_id: &str) -> ComponentResult<Vec<u8>> {
-        Ok(b"pong".to_vec())
+    fn handle_message(&self, message: &NetworkMessage, peer_id: &str) -> ComponentResult<Option<NetworkMessage>> {
+        match message {
+            NetworkMessage::Ping(ping) => Ok(Some(NetworkMessage::Pong(PongMessage {
+                timestamp: ping.timestamp,
+                peer_id: peer_id.to_string(),
+            }))),
+            _ => Ok(None),
+        }
     }
     
     fn message_type(&self) -> &str {
@@ -278,8 +577,8 @@ impl MessageHandler for PingHandler {
 struct PongHandler;
 
 impl MessageHandler for PongHandler {
-    fn handle_message(&self, _message: &[u8], _peer_id: &str) -> ComponentResult<Vec<u8>> {
-        Ok(b"ping".to_vec())
+    fn handle_message(&self, _message: &NetworkMessage, _peer_id: &str) -> ComponentResult<Option<NetworkMessage>> {
+        Ok(None)
     }
     
     fn message_type(&self) -> &str {
@@ -290,7 +589,8 @@ impl MessageHandler for PongHandler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    // use tokio::time::{sleep, Duration};
+    use crate::components::network::message::PingMessage;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_p2p_network_initialization() {
@@ -329,11 +629,100 @@ mod tests {
         assert_eq!(network.get_connection_count().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_framed_ping_pong() {
+        let mut server = P2PNetwork::new();
+        server.initialize().await.unwrap();
+        server.start(0).await.unwrap();
+        let port = server.get_listen_addr().unwrap().port();
+
+        let mut client = P2PNetwork::new();
+        client.initialize().await.unwrap();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        client.register_message_handler("pong", Box::new(RecordingHandler(sender))).await;
+        let peer_id = client.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+        // 节点ID来自握手中认证的身份公钥，而不是对端地址
+        assert_eq!(peer_id, server.get_peer_id());
+
+        let ping = NetworkMessage::Ping(PingMessage { timestamp: 42, peer_id: "client".to_string() });
+        client.send_message(&peer_id, &ping).await.unwrap();
+
+        let pong = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+        assert!(matches!(pong, NetworkMessage::Pong(p) if p.timestamp == 42));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_frame_disconnects_and_penalizes() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut server = P2PNetwork::new();
+        server.initialize().await.unwrap();
+        server.start(0).await.unwrap();
+        let port = server.get_listen_addr().unwrap().port();
+
+        let penalty = CodecError::HandshakeFailed(String::new()).penalty();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        for attempt in 1..=ADDRESS_BAN_SCORE / penalty {
+            // 单次畸形数据不会封禁，反复违规累计到封禁分数后才封禁
+            assert!(!server.peer_manager().is_banned(&ip));
+            let mut stream = TokioTcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            stream.write_all(&[0u8; 64]).await.unwrap();
+
+            // 明文垃圾数据无法通过加密握手，服务端断开连接后读到 EOF
+            let mut buffer = [0u8; 16];
+            let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer)).await.unwrap();
+            assert!(matches!(read, Ok(0) | Err(_)));
+            assert_eq!(server.get_misbehavior_score(&ip).await, attempt * penalty);
+        }
+        assert!(server.peer_manager().is_banned(&ip));
+    }
+
+    #[tokio::test]
+    async fn test_banned_peer_is_disconnected_and_refused() {
+        let mut server = P2PNetwork::new();
+        server.initialize().await.unwrap();
+        server.start(0).await.unwrap();
+        let address = format!("127.0.0.1:{}", server.get_listen_addr().unwrap().port());
+
+        let mut client = P2PNetwork::new();
+        client.initialize().await.unwrap();
+        client.connect_to_peer(&address).await.unwrap();
+        let client_id = client.get_peer_id();
+        for _ in 0..100 {
+            if server.get_connection_count().await == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(!server.report_peer(&client_id, Misbehavior::ProtocolViolation).await.unwrap());
+        assert!(server.report_peer(&client_id, Misbehavior::InvalidBlock).await.unwrap());
+        assert_eq!(server.get_connection_count().await, 0);
+
+        // 被封禁的地址重新连接时握手前即被断开
+        let mut retry = P2PNetwork::new();
+        retry.initialize().await.unwrap();
+        assert!(retry.connect_to_peer(&address).await.is_err());
+    }
+
+    struct RecordingHandler(mpsc::UnboundedSender<NetworkMessage>);
+
+    impl MessageHandler for RecordingHandler {
+        fn handle_message(&self, message: &NetworkMessage, _peer_id: &str) -> ComponentResult<Option<NetworkMessage>> {
+            let _ = self.0.send(message.clone());
+            Ok(None)
+        }
+
+        fn message_type(&self) -> &str {
+            "pong"
+        }
+    }
+
     struct TestHandler;
 
     impl MessageHandler for TestHandler {
-        fn handle_message(&self, _message: &[u8], _peer_id: &str) -> ComponentResult<Vec<u8>> {
-            Ok(b"test_response".to_vec())
+        fn handle_message(&self, _message: &NetworkMessage, _peer_id: &str) -> ComponentResult<Option<NetworkMessage>> {
+            Ok(None)
         }
         
         fn message_type(&self) -> &str {
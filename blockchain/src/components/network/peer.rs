@@ -1,18 +1,198 @@
 // 对等节点管理实现
+//
+// 除连接统计外还负责对端评分和准入：无效区块、无效交易、协议违规和垃圾消息会扣分，
+// 低于阈值时断开并按 IP 限时封禁（封禁列表持久化到磁盘）；入站和出站连接分别限额，
+// 同一子网的连接数也有上限以抵御日蚀攻击，入站满额时优先淘汰评分低、连接时间短的对端。
 use crate::components::{ComponentResult, ComponentError};
+use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::{RwLock};
 use std::time::{Duration, Instant};
 
+/// 封禁列表的默认文件名
+pub const BAN_LIST_FILE: &str = "banlist.json";
+
 /// 对等节点管理器
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PeerManager {
     /// 对等节点列表
     peers: Arc<RwLock<HashMap<String, Peer>>>,
     /// 是否已初始化
     initialized: bool,
+    /// 评分和连接限额配置
+    config: PeerConfig,
+    /// 按 IP 的封禁列表
+    bans: Arc<std::sync::RwLock<BanList>>,
+}
+
+/// 评分和连接限额配置
+#[derive(Debug, Clone)]
+pub struct PeerConfig {
+    /// 最大入站连接数
+    pub max_inbound: usize,
+    /// 最大出站连接数
+    pub max_outbound: usize,
+    /// 同一子网（IPv4 /16、IPv6 /32）的最大连接数，回环地址不受限
+    pub max_per_subnet: usize,
+    /// 评分低于等于该值时断开并封禁
+    pub ban_threshold: i32,
+    /// 评分上限，良好行为最多累积到此
+    pub max_score: i32,
+    /// 封禁时长
+    pub ban_duration: Duration,
+}
+
+impl Default for PeerConfig {
+    fn default() -> Self {
+        Self {
+            max_inbound: 32,
+            max_outbound: 8,
+            max_per_subnet: 4,
+            ban_threshold: -100,
+            max_score: 100,
+            ban_duration: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// 对端违规行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    /// 发送了验证失败的区块
+    InvalidBlock,
+    /// 发送了验证失败的交易
+    InvalidTransaction,
+    /// 违反网络协议（无效响应、畸形消息）
+    ProtocolViolation,
+    /// 未请求或重复的消息
+    Spam,
+}
+
+impl Misbehavior {
+    /// 扣分值
+    pub fn penalty(&self) -> i32 {
+        match self {
+            Misbehavior::InvalidBlock => 100,
+            Misbehavior::ProtocolViolation => 50,
+            Misbehavior::InvalidTransaction => 10,
+            Misbehavior::Spam => 1,
+        }
+    }
+}
+
+/// 封禁记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BanEntry {
+    pub address: IpAddr,
+    /// 解封时间（Unix 秒）
+    pub banned_until: u64,
+    pub reason: String,
+}
+
+/// 封禁列表，设置了路径时每次修改都写回磁盘
+#[derive(Debug, Clone, Default)]
+pub struct BanList {
+    entries: HashMap<IpAddr, BanEntry>,
+    path: Option<PathBuf>,
+}
+
+impl BanList {
+    /// 从文件加载封禁列表，文件不存在时为空列表；之后的修改写回该文件
+    pub fn load(path: impl AsRef<Path>) -> ComponentResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str::<Vec<BanEntry>>(&json)
+                .map_err(|e| ComponentError::ConfigurationError(format!("Invalid ban list {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(ComponentError::StorageError(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        let mut ban_list = Self {
+            entries: entries.into_iter().map(|entry| (entry.address, entry)).collect(),
+            path: Some(path),
+        };
+        ban_list.prune(unix_now());
+        Ok(ban_list)
+    }
+
+    /// 封禁地址
+    pub fn ban(&mut self, address: IpAddr, duration: Duration, reason: &str) -> ComponentResult<()> {
+        let entry = BanEntry {
+            address,
+            banned_until: unix_now().saturating_add(duration.as_secs()),
+            reason: reason.to_string(),
+        };
+        self.entries.insert(address, entry);
+        self.save()
+    }
+
+    /// 解除封禁，返回该地址之前是否被封禁
+    pub fn unban(&mut self, address: &IpAddr) -> ComponentResult<bool> {
+        let removed = self.entries.remove(address).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn is_banned(&self, address: &IpAddr) -> bool {
+        self.entries.get(address).is_some_and(|entry| entry.banned_until > unix_now())
+    }
+
+    /// 未过期的封禁记录
+    pub fn entries(&self) -> Vec<BanEntry> {
+        let now = unix_now();
+        let mut entries: Vec<BanEntry> = self.entries.values()
+            .filter(|entry| entry.banned_until > now)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|entry| entry.address);
+        entries
+    }
+
+    fn prune(&mut self, now: u64) {
+        self.entries.retain(|_, entry| entry.banned_until > now);
+    }
+
+    fn save(&self) -> ComponentResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.entries())
+            .map_err(|e| ComponentError::StorageError(e.to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ComponentError::StorageError(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| ComponentError::StorageError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// 地址所在子网（IPv4 /16、IPv6 /32）；回环地址返回 None，不参与子网限额
+fn subnet(address: &IpAddr) -> Option<IpAddr> {
+    if address.is_loopback() {
+        return None;
+    }
+    Some(match address {
+        IpAddr::V4(v4) => {
+            let [a, b, _, _] = v4.octets();
+            IpAddr::from([a, b, 0, 0])
+        }
+        IpAddr::V6(v6) => {
+            let segments = v6.segments();
+            IpAddr::from([segments[0], segments[1], 0, 0, 0, 0, 0, 0])
+        }
+    })
 }
 
 /// 对等节点信息
@@ -26,14 +206,33 @@ pub struct Peer {
     pub connection_count: u32,
     pub message_count: u64,
     pub latency: Option<Duration>,
+    /// 行为评分，违规扣分、良好行为加分
+    pub score: i32,
+    /// 是否为入站连接
+    pub inbound: bool,
 }
 
 impl PeerManager {
     /// 创建新的对等节点管理器
     pub fn new() -> Self {
+        Self::with_config(PeerConfig::default())
+    }
+    
+    /// 使用指定的评分和连接限额配置
+    pub fn with_config(config: PeerConfig) -> Self {
         Self {
             peers: Arc::new(RwLock::new(HashMap::new())),
             initialized: false,
+            config,
+            bans: Arc::new(std::sync::RwLock::new(BanList::default())),
+        }
+    }
+    
+    /// 使用已加载（通常来自磁盘）的封禁列表
+    pub fn with_ban_list(self, ban_list: BanList) -> Self {
+        Self {
+            bans: Arc::new(std::sync::RwLock::new(ban_list)),
+            ..self
         }
     }
     
@@ -62,12 +261,133 @@ impl PeerManager {
             connection_count: 1,
             message_count: 0,
             latency: None,
+            score: 0,
+            inbound: false,
         };
         
         self.peers.write().await.insert(peer_id, peer);
         Ok(())
     }
     
+    /// 登记新建立的连接
+    ///
+    /// 地址被封禁、超过子网限额或连接已满时返回错误；入站连接已满但存在评分为负的入站对端时，
+    /// 接受新连接并返回应被淘汰的对端（评分最低，同分时连接时间最短）。
+    pub async fn register_connection(&self, peer_id: &str, address: SocketAddr, inbound: bool) -> ComponentResult<Option<String>> {
+        if self.is_banned(&address.ip()) {
+            return Err(ComponentError::NetworkError(format!("Address {} is banned", address.ip())));
+        }
+        
+        let mut peers = self.peers.write().await;
+        if let Some(group) = subnet(&address.ip()) {
+            let same_subnet = peers.values()
+                .filter(|peer| peer.id != peer_id && subnet(&peer.address.ip()) == Some(group))
+                .count();
+            if same_subnet >= self.config.max_per_subnet {
+                return Err(ComponentError::NetworkError(format!("Too many connections from subnet {}", group)));
+            }
+        }
+        
+        let same_direction: Vec<&Peer> = peers.values()
+            .filter(|peer| peer.inbound == inbound && peer.id != peer_id)
+            .collect();
+        let limit = if inbound { self.config.max_inbound } else { self.config.max_outbound };
+        let mut evicted = None;
+        if same_direction.len() >= limit {
+            // 出站连接由本节点发起，满额时直接拒绝；入站满额时只淘汰评分为负的对端
+            let candidate = if inbound {
+                same_direction.iter().min_by_key(|peer| (peer.score, std::cmp::Reverse(peer.connected_at)))
+            } else {
+                None
+            };
+            match candidate {
+                Some(peer) if peer.score < 0 => evicted = Some(peer.id.clone()),
+                _ => {
+                    let direction = if inbound { "inbound" } else { "outbound" };
+                    return Err(ComponentError::NetworkError(format!("Too many {} connections", direction)));
+                }
+            }
+        }
+        if let Some(evicted) = &evicted {
+            peers.remove(evicted);
+        }
+        
+        let now = Instant::now();
+        peers.insert(peer_id.to_string(), Peer {
+            id: peer_id.to_string(),
+            address,
+            connected_at: now,
+            last_seen: now,
+            is_active: true,
+            connection_count: 1,
+            message_count: 0,
+            latency: None,
+            score: 0,
+            inbound,
+        });
+        Ok(evicted)
+    }
+    
+    /// 连接断开，移除对端
+    pub async fn peer_disconnected(&self, peer_id: &str) {
+        self.peers.write().await.remove(peer_id);
+    }
+    
+    /// 记录对端违规并扣分；评分低于阈值时封禁其地址并移除，返回是否已封禁
+    pub async fn report_misbehavior(&self, peer_id: &str, misbehavior: Misbehavior) -> ComponentResult<bool> {
+        let mut peers = self.peers.write().await;
+        let Some(peer) = peers.get_mut(peer_id) else {
+            return Ok(false);
+        };
+        peer.score = peer.score.saturating_sub(misbehavior.penalty());
+        if peer.score > self.config.ban_threshold {
+            return Ok(false);
+        }
+        
+        let address = peer.address.ip();
+        peers.remove(peer_id);
+        drop(peers);
+        self.ban_address(address, self.config.ban_duration, &format!("{:?}", misbehavior))?;
+        Ok(true)
+    }
+    
+    /// 记录对端的良好行为（例如提供了有效区块），评分不超过上限
+    pub async fn reward_peer(&self, peer_id: &str) {
+        if let Some(peer) = self.peers.write().await.get_mut(peer_id) {
+            peer.score = (peer.score + 1).min(self.config.max_score);
+        }
+    }
+    
+    /// 获取对端评分
+    pub async fn get_peer_score(&self, peer_id: &str) -> Option<i32> {
+        self.peers.read().await.get(peer_id).map(|peer| peer.score)
+    }
+    
+    /// 封禁地址
+    pub fn ban_address(&self, address: IpAddr, duration: Duration, reason: &str) -> ComponentResult<()> {
+        self.bans.write().unwrap().ban(address, duration, reason)
+    }
+    
+    /// 解除封禁，返回该地址之前是否被封禁
+    pub fn unban_address(&self, address: &IpAddr) -> ComponentResult<bool> {
+        self.bans.write().unwrap().unban(address)
+    }
+    
+    /// 地址是否被封禁
+    pub fn is_banned(&self, address: &IpAddr) -> bool {
+        self.bans.read().unwrap().is_banned(address)
+    }
+    
+    /// 当前的封禁记录
+    pub fn list_bans(&self) -> Vec<BanEntry> {
+        self.bans.read().unwrap().entries()
+    }
+    
+    /// 评分和连接限额配置
+    pub fn config(&self) -> &PeerConfig {
+        &self.config
+    }
+    
     /// 移除对等节点
     pub async fn remove_peer(&mut self, peer_id: String) -> ComponentResult<()> {
         if !self.initialized {
@@ -404,4 +724,66 @@ mod tests {
         let connection_time = manager.get_peer_connection_time("peer_127.0.0.1:8080").await.unwrap();
         assert!(connection_time >= Duration::from_millis(100));
     }
+
+    fn addr(address: &str) -> SocketAddr {
+        address.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_misbehavior_bans_below_threshold() {
+        let manager = PeerManager::new();
+        manager.register_connection("bad", addr("10.0.0.1:9000"), true).await.unwrap();
+
+        assert!(!manager.report_misbehavior("bad", Misbehavior::ProtocolViolation).await.unwrap());
+        assert_eq!(manager.get_peer_score("bad").await, Some(-50));
+        assert!(manager.report_misbehavior("bad", Misbehavior::ProtocolViolation).await.unwrap());
+
+        // 封禁后对端被移除，同一地址无法再连接
+        assert_eq!(manager.get_peer_score("bad").await, None);
+        assert!(manager.is_banned(&"10.0.0.1".parse().unwrap()));
+        assert!(manager.register_connection("bad2", addr("10.0.0.1:9001"), true).await.is_err());
+
+        assert!(manager.unban_address(&"10.0.0.1".parse().unwrap()).unwrap());
+        assert!(manager.register_connection("bad2", addr("10.0.0.1:9001"), true).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ban_list_persists() {
+        let path = std::env::temp_dir().join(format!("banlist-{}.json", uuid::Uuid::new_v4()));
+        let manager = PeerManager::new().with_ban_list(BanList::load(&path).unwrap());
+        let banned: IpAddr = "192.168.1.7".parse().unwrap();
+        manager.ban_address(banned, Duration::from_secs(3600), "manual").unwrap();
+        manager.ban_address("192.168.1.8".parse().unwrap(), Duration::ZERO, "expired").unwrap();
+
+        // 重启后重新加载，过期的封禁被丢弃
+        let reloaded = BanList::load(&path).unwrap();
+        assert!(reloaded.is_banned(&banned));
+        assert_eq!(reloaded.entries().len(), 1);
+        assert_eq!(reloaded.entries()[0].reason, "manual");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_limits_and_eviction() {
+        let config = PeerConfig { max_inbound: 2, max_outbound: 1, max_per_subnet: 2, ..PeerConfig::default() };
+        let manager = PeerManager::with_config(config);
+
+        manager.register_connection("out1", addr("10.1.0.1:1"), false).await.unwrap();
+        assert!(manager.register_connection("out2", addr("10.2.0.1:1"), false).await.is_err());
+
+        // 同一 /16 子网最多两个连接，回环地址不受限
+        manager.register_connection("in1", addr("10.1.0.2:1"), true).await.unwrap();
+        assert!(manager.register_connection("in2", addr("10.1.9.9:1"), true).await.is_err());
+        manager.register_connection("in2", addr("127.0.0.1:1"), true).await.unwrap();
+
+        // 入站已满且没有评分为负的对端时拒绝新连接
+        assert!(manager.register_connection("in3", addr("10.3.0.1:1"), true).await.is_err());
+
+        // 长期表现良好的对端被保留，评分为负的对端被淘汰
+        manager.reward_peer("in1").await;
+        manager.report_misbehavior("in2", Misbehavior::Spam).await.unwrap();
+        let evicted = manager.register_connection("in3", addr("10.3.0.1:1"), true).await.unwrap();
+        assert_eq!(evicted, Some("in2".to_string()));
+        assert!(manager.get_peer("in1").await.is_some());
+    }
 }
@@ -0,0 +1,579 @@
+// QUIC 传输
+//
+// 与 TCP 路径共用连接登记、消息分发和对端管理（`ConnectionContext`），区别在于：
+// - 加密和认证由 TLS 1.3 完成，双方都出示用节点身份密钥自签名的 Ed25519 证书，
+//   节点ID由证书公钥派生，TLS 握手签名证明对端持有该身份私钥；
+// - 每个请求打开一条双向流，响应写回同一条流；
+// - 区块和交易广播各用一条专用单向流，大区块不会阻塞交易转发。
+// 流上的帧格式与 TCP 相同（`NetworkCodec`），加密已由 QUIC 完成，不再使用 `SecureCodec`。
+use super::codec::{CodecError, NetworkCodec};
+use super::message::NetworkMessage;
+use super::p2p::ConnectionContext;
+use super::secure::{self, NodeIdentity};
+use crate::components::{ComponentError, ComponentResult};
+use futures::{SinkExt, StreamExt};
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::{DigitallySignedStruct, DistinguishedName, SignatureScheme};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+/// ALPN 协议标识
+pub const ALPN_PROTOCOL: &[u8] = b"bcrs/1";
+
+/// 主动连接时使用的服务器名，证书不含域名，校验时只看证书公钥
+const SERVER_NAME: &str = "blockchain-node";
+
+/// QUIC 握手超时时间
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 同一种响应最多保留的待回复请求流，超出时最早的流被关闭（请求方读到流结束）
+const MAX_PENDING_REQUESTS: usize = 64;
+
+/// 消息在 QUIC 连接上使用的流
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// 打开新的双向流发送请求，并从同一条流读取响应
+    Request,
+    /// 写回对应请求的双向流，找不到时使用新的单向流
+    Response,
+    /// 区块专用的单向流
+    Blocks,
+    /// 交易、库存公告和节点地址专用的单向流
+    Gossip,
+}
+
+impl Lane {
+    /// 消息所属的流
+    pub fn of(message: &NetworkMessage) -> Self {
+        match message {
+            NetworkMessage::SyncRequest(_)
+            | NetworkMessage::GetBlockTxn(_)
+            | NetworkMessage::LightRequest(_)
+            | NetworkMessage::Ping(_) => Lane::Request,
+            NetworkMessage::SyncResponse(_)
+            | NetworkMessage::BlockTxn(_)
+            | NetworkMessage::LightResponse(_)
+            | NetworkMessage::Pong(_) => Lane::Response,
+            NetworkMessage::Block(_) | NetworkMessage::CompactBlock(_) | NetworkMessage::Consensus(_) => Lane::Blocks,
+            NetworkMessage::Transaction(_)
+            | NetworkMessage::Inv(_)
+            | NetworkMessage::GetData(_)
+            | NetworkMessage::PeerDiscovery(_) => Lane::Gossip,
+        }
+    }
+}
+
+/// 请求对应的响应类型名
+fn response_type(request: &NetworkMessage) -> Option<&'static str> {
+    match request {
+        NetworkMessage::SyncRequest(_) => Some("sync_response"),
+        NetworkMessage::GetBlockTxn(_) => Some("block_txn"),
+        NetworkMessage::LightRequest(_) => Some("light_response"),
+        NetworkMessage::Ping(_) => Some("pong"),
+        _ => None,
+    }
+}
+
+fn tls_error(e: impl std::fmt::Display) -> ComponentError {
+    ComponentError::CryptographyError(format!("TLS configuration failed: {}", e))
+}
+
+fn stream_error(e: impl std::fmt::Display) -> CodecError {
+    CodecError::Io(std::io::Error::other(e.to_string()))
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// 由节点身份生成的证书链和私钥
+fn credentials(identity: &NodeIdentity) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+    (
+        vec![CertificateDer::from(identity.self_signed_certificate())],
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(identity.pkcs8_private_key())),
+    )
+}
+
+/// 服务端配置：出示身份证书并要求客户端证书
+pub fn server_config(identity: &NodeIdentity) -> ComponentResult<quinn::ServerConfig> {
+    let provider = crypto_provider();
+    let (certificates, key) = credentials(identity);
+    let mut tls = rustls::ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .with_client_cert_verifier(Arc::new(IdentityVerifier { provider }))
+        .with_single_cert(certificates, key)
+        .map_err(tls_error)?;
+    tls.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+    let crypto = QuicServerConfig::try_from(tls).map_err(tls_error)?;
+    Ok(quinn::ServerConfig::with_crypto(Arc::new(crypto)))
+}
+
+/// 客户端配置：出示身份证书，服务端证书按身份公钥校验
+pub fn client_config(identity: &NodeIdentity) -> ComponentResult<quinn::ClientConfig> {
+    let provider = crypto_provider();
+    let (certificates, key) = credentials(identity);
+    let mut tls = rustls::ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(IdentityVerifier { provider }))
+        .with_client_auth_cert(certificates, key)
+        .map_err(tls_error)?;
+    tls.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+    let crypto = QuicClientConfig::try_from(tls).map_err(tls_error)?;
+    Ok(quinn::ClientConfig::new(Arc::new(crypto)))
+}
+
+/// 监听指定 UDP 地址的端点，也可用于主动连接
+pub fn server_endpoint(identity: &NodeIdentity, address: SocketAddr) -> ComponentResult<quinn::Endpoint> {
+    let mut endpoint = quinn::Endpoint::server(server_config(identity)?, address)
+        .map_err(|e| ComponentError::NetworkError(format!("Failed to bind to {}: {}", address, e)))?;
+    endpoint.set_default_client_config(client_config(identity)?);
+    Ok(endpoint)
+}
+
+/// 仅用于主动连接的端点（绑定随机端口）
+pub fn client_endpoint(identity: &NodeIdentity) -> ComponentResult<quinn::Endpoint> {
+    let mut endpoint = quinn::Endpoint::client(SocketAddr::from(([0, 0, 0, 0], 0)))
+        .map_err(|e| ComponentError::NetworkError(format!("Failed to create QUIC endpoint: {}", e)))?;
+    endpoint.set_default_client_config(client_config(identity)?);
+    Ok(endpoint)
+}
+
+/// 证书校验：证书必须携带 Ed25519 公钥，TLS 1.3 握手签名由该公钥验证
+///
+/// 证书是自签名的，不校验颁发者和有效期；节点ID由公钥派生，因此认证的就是对端身份。
+#[derive(Debug)]
+struct IdentityVerifier {
+    provider: Arc<CryptoProvider>,
+}
+
+impl IdentityVerifier {
+    fn check_certificate(&self, certificate: &CertificateDer<'_>) -> Result<(), rustls::Error> {
+        secure::certificate_public_key(certificate)
+            .map(|_| ())
+            .ok_or(rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding))
+    }
+
+    fn check_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, certificate, signature, &self.provider.signature_verification_algorithms)
+    }
+}
+
+impl ServerCertVerifier for IdentityVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.check_certificate(end_entity)?;
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _certificate: &CertificateDer<'_>,
+        _signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::General("TLS 1.2 is not supported".to_string()))
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.check_signature(message, certificate, signature)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+impl ClientCertVerifier for IdentityVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.check_certificate(end_entity)?;
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _certificate: &CertificateDer<'_>,
+        _signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::General("TLS 1.2 is not supported".to_string()))
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.check_signature(message, certificate, signature)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+/// 对端证书中的身份公钥
+fn peer_public_key(connection: &quinn::Connection) -> Option<[u8; 32]> {
+    let identity = connection.peer_identity()?;
+    let certificates = identity.downcast::<Vec<CertificateDer<'static>>>().ok()?;
+    secure::certificate_public_key(certificates.first()?)
+}
+
+/// 接受入站连接，直到端点关闭或网络停止
+pub(super) async fn accept_connections(endpoint: quinn::Endpoint, context: ConnectionContext, running: Arc<Mutex<bool>>) {
+    while let Some(incoming) = endpoint.accept().await {
+        if !*running.lock().await {
+            break;
+        }
+        let address = incoming.remote_address();
+        if context.peer_manager.is_banned(&address.ip()) {
+            log::debug!("Refusing connection from banned address {}", address);
+            incoming.refuse();
+            continue;
+        }
+        // 握手在独立任务中进行，慢速对端不会阻塞监听
+        let context = context.clone();
+        tokio::spawn(async move {
+            let connection = match tokio::time::timeout(HANDSHAKE_TIMEOUT, incoming).await {
+                Ok(Ok(connection)) => connection,
+                // 传输层错误可能来自丢包或 NAT，不计违规分
+                Ok(Err(e)) => {
+                    log::warn!("QUIC handshake with {} failed: {}", address, e);
+                    return;
+                }
+                Err(_) => {
+                    log::warn!("QUIC handshake with {} timed out", address);
+                    return;
+                }
+            };
+            if let Err(e) = spawn_connection(&context, connection, false).await {
+                log::warn!("Rejected QUIC connection from {}: {}", address, e);
+            }
+        });
+    }
+}
+
+/// 主动建立 QUIC 连接，返回已认证的对等节点ID
+pub(super) async fn connect(endpoint: &quinn::Endpoint, context: &ConnectionContext, address: SocketAddr) -> Result<String, CodecError> {
+    let connecting = endpoint.connect(address, SERVER_NAME)
+        .map_err(|e| CodecError::HandshakeFailed(e.to_string()))?;
+    let connection = tokio::time::timeout(HANDSHAKE_TIMEOUT, connecting).await
+        .map_err(|_| CodecError::HandshakeTimeout)?
+        .map_err(|e| CodecError::HandshakeFailed(e.to_string()))?;
+    spawn_connection(context, connection, true).await
+}
+
+/// 登记已认证的连接并启动收发任务，返回对等节点ID
+async fn spawn_connection(context: &ConnectionContext, connection: quinn::Connection, initiator: bool) -> Result<String, CodecError> {
+    let Some(public_key) = peer_public_key(&connection) else {
+        connection.close(quinn::VarInt::from_u32(1), b"missing identity");
+        return Err(CodecError::HandshakeFailed("peer presented no identity certificate".to_string()));
+    };
+    let address = connection.remote_address();
+    let peer_id = secure::peer_id_from_public_key(&public_key);
+    let receiver = match context.register(&peer_id, address, public_key, !initiator).await {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            connection.close(quinn::VarInt::from_u32(1), b"rejected");
+            return Err(e);
+        }
+    };
+
+    let session = Arc::new(QuicSession {
+        connection,
+        context: context.clone(),
+        peer_id: peer_id.clone(),
+        address,
+        pending: StdMutex::new(HashMap::new()),
+    });
+    tokio::spawn(Arc::clone(&session).write_loop(receiver));
+    tokio::spawn(Arc::clone(&session).accept_requests());
+    tokio::spawn(Arc::clone(&session).accept_broadcasts());
+    tokio::spawn(async move {
+        let reason = session.connection.closed().await;
+        log::debug!("QUIC connection to {} closed: {}", session.peer_id, reason);
+        session.context.disconnected(&session.peer_id).await;
+    });
+    Ok(peer_id)
+}
+
+/// 单个 QUIC 连接的状态
+struct QuicSession {
+    connection: quinn::Connection,
+    context: ConnectionContext,
+    peer_id: String,
+    address: SocketAddr,
+    /// 等待本节点回复的入站请求流，按响应类型排队
+    pending: StdMutex<HashMap<&'static str, VecDeque<quinn::SendStream>>>,
+}
+
+impl QuicSession {
+    fn codec(&self) -> NetworkCodec {
+        NetworkCodec::new(self.context.network_id)
+    }
+
+    /// 按消息所属的流发送写任务队列中的消息，队列关闭（断开、封禁或淘汰）时关闭连接
+    async fn write_loop(self: Arc<Self>, mut receiver: mpsc::UnboundedReceiver<NetworkMessage>) {
+        let mut blocks = None;
+        let mut gossip = None;
+        while let Some(message) = receiver.recv().await {
+            let result = match Lane::of(&message) {
+                Lane::Request => {
+                    let session = Arc::clone(&self);
+                    tokio::spawn(async move {
+                        if let Err(e) = session.request(message).await {
+                            log::debug!("Request to {} failed: {}", session.peer_id, e);
+                        }
+                    });
+                    Ok(())
+                }
+                Lane::Response => {
+                    if let Err(e) = self.respond(message).await {
+                        log::debug!("Response to {} failed: {}", self.peer_id, e);
+                    }
+                    Ok(())
+                }
+                Lane::Blocks => self.send_on(&mut blocks, message).await,
+                Lane::Gossip => self.send_on(&mut gossip, message).await,
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to send frame: {}", e);
+                break;
+            }
+        }
+        self.connection.close(quinn::VarInt::from_u32(0), b"closed");
+    }
+
+    /// 在持久的单向流上发送，首次使用时打开
+    async fn send_on(
+        &self,
+        stream: &mut Option<FramedWrite<quinn::SendStream, NetworkCodec>>,
+        message: NetworkMessage,
+    ) -> Result<(), CodecError> {
+        let sink = match stream {
+            Some(sink) => sink,
+            None => {
+                let send = self.connection.open_uni().await.map_err(stream_error)?;
+                stream.insert(FramedWrite::new(send, self.codec()))
+            }
+        };
+        sink.send(message).await
+    }
+
+    /// 在新的双向流上发送请求，并分发同一条流上的响应
+    async fn request(&self, message: NetworkMessage) -> Result<(), CodecError> {
+        let (send, receive) = self.connection.open_bi().await.map_err(stream_error)?;
+        let mut sink = FramedWrite::new(send, self.codec());
+        sink.send(message).await?;
+        sink.into_inner().finish().map_err(stream_error)?;
+
+        let mut frames = FramedRead::new(receive, self.codec());
+        match frames.next().await {
+            Some(Ok(response)) => self.receive(response).await,
+            Some(Err(e)) => self.violation(e).await,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// 把响应写回最早的同类请求流，没有待回复的请求时用新的单向流发送
+    async fn respond(&self, message: NetworkMessage) -> Result<(), CodecError> {
+        let pending = self.pending.lock().unwrap()
+            .get_mut(message.type_name())
+            .and_then(VecDeque::pop_front);
+        let send = match pending {
+            Some(send) => send,
+            None => self.connection.open_uni().await.map_err(stream_error)?,
+        };
+        let mut sink = FramedWrite::new(send, self.codec());
+        sink.send(message).await?;
+        sink.into_inner().finish().map_err(stream_error)
+    }
+
+    /// 接受对端的请求流，每条流一个请求
+    async fn accept_requests(self: Arc<Self>) {
+        while let Ok((send, receive)) = self.connection.accept_bi().await {
+            let session = Arc::clone(&self);
+            tokio::spawn(async move { session.serve_request(send, receive).await });
+        }
+    }
+
+    /// 处理一条请求流：处理器直接给出回复时立即写回，否则留给链稍后经写任务回复
+    async fn serve_request(&self, send: quinn::SendStream, receive: quinn::RecvStream) {
+        let mut frames = FramedRead::new(receive, self.codec());
+        let request = match frames.next().await {
+            Some(Ok(request)) => request,
+            Some(Err(e)) => return self.violation(e).await,
+            None => return,
+        };
+        let Ok(reply) = self.context.dispatch(&self.peer_id, &request).await else {
+            return;
+        };
+
+        let mut send = send;
+        match (reply, response_type(&request)) {
+            (Some(reply), _) => {
+                let mut sink = FramedWrite::new(send, self.codec());
+                if sink.send(reply).await.is_ok() {
+                    send = sink.into_inner();
+                } else {
+                    return;
+                }
+            }
+            (None, Some(response_type)) => {
+                let mut pending = self.pending.lock().unwrap();
+                let queue = pending.entry(response_type).or_default();
+                if queue.len() >= MAX_PENDING_REQUESTS {
+                    // 丢弃时流自动结束，请求方不再等待
+                    queue.pop_front();
+                }
+                queue.push_back(send);
+                return;
+            }
+            (None, None) => {}
+        }
+        let _ = send.finish();
+    }
+
+    /// 接受对端的广播流（区块、交易）和无对应请求的响应
+    async fn accept_broadcasts(self: Arc<Self>) {
+        while let Ok(receive) = self.connection.accept_uni().await {
+            let session = Arc::clone(&self);
+            tokio::spawn(async move {
+                let mut frames = FramedRead::new(receive, session.codec());
+                while let Some(frame) = frames.next().await {
+                    match frame {
+                        Ok(message) => session.receive(message).await,
+                        Err(e) => {
+                            session.violation(e).await;
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    /// 分发收到的消息，处理器的回复经写任务发送
+    async fn receive(&self, message: NetworkMessage) {
+        if let Ok(Some(response)) = self.context.dispatch(&self.peer_id, &message).await {
+            self.context.send_to(&self.peer_id, response).await;
+        }
+    }
+
+    /// 流读取失败；违反协议时记录违规分并关闭整个连接
+    async fn violation(&self, e: CodecError) {
+        if e.is_protocol_violation() {
+            self.context.penalize(self.address.ip(), e.penalty()).await;
+            log::warn!("Disconnecting {}: {}", self.peer_id, e);
+            self.connection.close(quinn::VarInt::from_u32(2), b"protocol violation");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::network::message::PingMessage;
+    use crate::components::network::p2p::{MessageHandler, P2PNetwork, Transport};
+    use crate::core::{Block, Transaction};
+
+    async fn quic_node() -> P2PNetwork {
+        let mut network = P2PNetwork::new().with_transport(Transport::Quic);
+        network.initialize().await.unwrap();
+        network.start(0).await.unwrap();
+        network
+    }
+
+    #[test]
+    fn test_lane_assignment() {
+        let ping = NetworkMessage::Ping(PingMessage { timestamp: 1, peer_id: String::new() });
+        assert_eq!(Lane::of(&ping), Lane::Request);
+        assert_eq!(response_type(&ping), Some("pong"));
+
+        let block = NetworkMessage::Block(Block::new([0u8; 32], Vec::new(), 1, 1).unwrap());
+        let transaction = NetworkMessage::Transaction(Transaction::new(Vec::new(), Vec::new()));
+        assert_eq!(Lane::of(&block), Lane::Blocks);
+        assert_eq!(Lane::of(&transaction), Lane::Gossip);
+        assert_eq!(response_type(&block), None);
+    }
+
+    #[tokio::test]
+    async fn test_quic_connection_authenticates_identity() {
+        let mut alice = quic_node().await;
+        let bob = quic_node().await;
+        let port = bob.get_listen_addr().unwrap().port();
+
+        let peer_id = alice.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+        assert_eq!(peer_id, bob.get_peer_id());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(bob.get_peer_ids().await, vec![alice.get_peer_id()]);
+    }
+
+    #[tokio::test]
+    async fn test_quic_request_response_stream() {
+        let mut alice = quic_node().await;
+        let bob = quic_node().await;
+        let port = bob.get_listen_addr().unwrap().port();
+        let peer_id = alice.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+
+        let (sender, mut pongs) = mpsc::unbounded_channel();
+        alice.register_message_handler("pong", Box::new(ForwardHandler(sender))).await;
+        let ping = NetworkMessage::Ping(PingMessage { timestamp: 42, peer_id: alice.get_peer_id() });
+        alice.send_message(&peer_id, &ping).await.unwrap();
+
+        let pong = tokio::time::timeout(Duration::from_secs(5), pongs.recv()).await.unwrap().unwrap();
+        assert!(matches!(pong, NetworkMessage::Pong(p) if p.timestamp == 42));
+    }
+
+    /// 把收到的消息转发到测试通道
+    struct ForwardHandler(mpsc::UnboundedSender<NetworkMessage>);
+
+    impl MessageHandler for ForwardHandler {
+        fn handle_message(&self, message: &NetworkMessage, _peer_id: &str) -> ComponentResult<Option<NetworkMessage>> {
+            let _ = self.0.send(message.clone());
+            Ok(None)
+        }
+
+        fn message_type(&self) -> &str {
+            "pong"
+        }
+    }
+}
@@ -0,0 +1,644 @@
+// 加密认证传输
+//
+// 每个节点持有一把持久化的 Ed25519 身份密钥，节点ID由公钥派生。
+// 连接建立后先执行 Noise XX 结构的握手：
+//   -> e
+//   <- e, ee, [s, sig]
+//   -> [s, sig]
+// 双方用临时 X25519 密钥协商会话密钥（前向保密），并用身份密钥对握手记录签名完成双向认证，
+// 身份公钥在握手中加密传输。握手完成后每个帧都用 ChaCha20-Poly1305 加密，两个方向使用独立密钥和递增 nonce。
+use super::codec::{CodecError, NetworkCodec, HEADER_LEN};
+use super::message::NetworkMessage;
+use crate::components::{ComponentError, ComponentResult};
+use bytes::{Buf, BufMut, BytesMut};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key as ChaChaKey, Nonce as ChaChaNonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder};
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
+
+/// 握手协议名，参与握手记录哈希
+const PROTOCOL_NAME: &[u8] = b"BCRS_Noise_XX_25519_ChaChaPoly_SHA256";
+
+/// AEAD 认证标签长度
+pub const TAG_LEN: usize = 16;
+
+/// 握手消息最大长度
+const MAX_HANDSHAKE_MESSAGE: usize = 256;
+
+const KEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+/// 握手中加密的身份负载：身份公钥 + 签名
+const IDENTITY_PAYLOAD_LEN: usize = KEY_LEN + SIGNATURE_LEN;
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// 由身份公钥派生节点ID（公钥 SHA-256 的前 20 字节）
+pub fn peer_id_from_public_key(public_key: &[u8; 32]) -> String {
+    hex::encode(&Sha256::digest(public_key)[..20])
+}
+
+/// 节点身份
+pub struct NodeIdentity {
+    signing_key: SigningKey,
+}
+
+impl std::fmt::Debug for NodeIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeIdentity").field("peer_id", &self.peer_id()).finish()
+    }
+}
+
+impl NodeIdentity {
+    /// 随机生成新身份
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(&rand::random::<[u8; 32]>()),
+        }
+    }
+
+    /// 从 32 字节私钥恢复身份
+    pub fn from_secret_key(secret_key: &[u8]) -> ComponentResult<Self> {
+        let bytes: [u8; 32] = secret_key.try_into()
+            .map_err(|_| ComponentError::CryptographyError("Invalid identity key length".to_string()))?;
+        Ok(Self {
+            signing_key: SigningKey::from_bytes(&bytes),
+        })
+    }
+
+    /// 从文件加载身份（十六进制私钥），文件不存在时生成并写入，保证重启后节点ID不变
+    pub fn load_or_generate(path: impl AsRef<Path>) -> ComponentResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| ComponentError::StorageError(format!("Failed to read identity key: {}", e)))?;
+            let secret_key = hex::decode(content.trim())
+                .map_err(|e| ComponentError::CryptographyError(format!("Invalid identity key: {}", e)))?;
+            return Self::from_secret_key(&secret_key);
+        }
+
+        let identity = Self::generate();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ComponentError::StorageError(format!("Failed to create key directory: {}", e)))?;
+        }
+        std::fs::write(path, hex::encode(identity.secret_key()))
+            .map_err(|e| ComponentError::StorageError(format!("Failed to write identity key: {}", e)))?;
+        Ok(identity)
+    }
+
+    /// 私钥
+    pub fn secret_key(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    /// 身份公钥
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// 节点ID
+    pub fn peer_id(&self) -> String {
+        peer_id_from_public_key(&self.public_key())
+    }
+
+    /// 用身份私钥签名
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.signing_key.sign(message).to_bytes()
+    }
+}
+
+/// 校验身份公钥对消息的签名
+pub fn verify_signature(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    VerifyingKey::from_bytes(public_key)
+        .and_then(|key| key.verify_strict(message, &Signature::from_bytes(signature)))
+        .is_ok()
+}
+
+/// Ed25519 的 AlgorithmIdentifier（OID 1.3.101.112，无参数）
+const ED25519_ALGORITHM: [u8; 7] = [0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70];
+
+/// 证书主题（通用名），节点身份由证书公钥而非名称确定
+const CERTIFICATE_SUBJECT: &[u8] = b"blockchain-node";
+
+/// DER 编码一个 TLV
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len < 0x100 {
+        out.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// 读取指定标签的 TLV，返回（内容，剩余部分）
+fn read_der(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&actual, rest) = input.split_first()?;
+    if actual != tag {
+        return None;
+    }
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = match first {
+        0x00..=0x7f => (first as usize, rest),
+        0x81 => (*rest.first()? as usize, &rest[1..]),
+        0x82 => (u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize, rest.get(2..)?),
+        _ => return None,
+    };
+    (rest.len() >= len).then(|| rest.split_at(len))
+}
+
+/// 跳过一个任意标签的 TLV
+fn skip_der(input: &[u8]) -> Option<&[u8]> {
+    read_der(input, *input.first()?).map(|(_, rest)| rest)
+}
+
+impl NodeIdentity {
+    /// 用身份密钥自签名的 X.509 v3 证书（DER），供 QUIC 的 TLS 握手使用
+    pub fn self_signed_certificate(&self) -> Vec<u8> {
+        let name = der(0x30, &der(0x31, &der(0x30, &[
+            der(0x06, &[0x55, 0x04, 0x03]),
+            der(0x0c, CERTIFICATE_SUBJECT),
+        ].concat())));
+        let validity = der(0x30, &[
+            der(0x17, b"700101000000Z"),
+            der(0x18, b"99991231235959Z"),
+        ].concat());
+        let public_key = der(0x30, &[
+            ED25519_ALGORITHM.to_vec(),
+            der(0x03, &[&[0u8][..], &self.public_key()].concat()),
+        ].concat());
+        let tbs = der(0x30, &[
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[1]),
+            ED25519_ALGORITHM.to_vec(),
+            name.clone(),
+            validity,
+            name,
+            public_key,
+        ].concat());
+        let signature = self.sign(&tbs);
+        der(0x30, &[
+            tbs,
+            ED25519_ALGORITHM.to_vec(),
+            der(0x03, &[&[0u8][..], &signature].concat()),
+        ].concat())
+    }
+
+    /// PKCS#8 编码的身份私钥（DER）
+    pub fn pkcs8_private_key(&self) -> Vec<u8> {
+        der(0x30, &[
+            der(0x02, &[0]),
+            ED25519_ALGORITHM.to_vec(),
+            der(0x04, &der(0x04, &self.secret_key())),
+        ].concat())
+    }
+}
+
+/// 从证书中取出 Ed25519 身份公钥，证书不是 Ed25519 密钥时返回 `None`
+///
+/// 只解析到主体公钥字段，证书签名由 TLS 握手签名间接保证（握手签名证明对端持有该私钥）。
+pub fn certificate_public_key(certificate: &[u8]) -> Option<[u8; 32]> {
+    let (certificate, _) = read_der(certificate, 0x30)?;
+    let (tbs, _) = read_der(certificate, 0x30)?;
+    let mut fields = tbs;
+    if fields.first() == Some(&0xa0) {
+        fields = skip_der(fields)?;
+    }
+    // 序列号、签名算法、颁发者、有效期、主题
+    for _ in 0..5 {
+        fields = skip_der(fields)?;
+    }
+    let (public_key_info, _) = read_der(fields, 0x30)?;
+    let key = public_key_info.strip_prefix(&ED25519_ALGORITHM[..])?;
+    let (bits, _) = read_der(key, 0x03)?;
+    bits.strip_prefix(&[0u8])?.try_into().ok()
+}
+
+/// 单方向的加密状态
+pub struct CipherState {
+    cipher: ChaCha20Poly1305,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: [u8; 32]) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(ChaChaKey::from_slice(&key)),
+            nonce: 0,
+        }
+    }
+
+    /// 当前 nonce 后移一位，nonce 用尽时拒绝继续加解密
+    fn next_nonce(&mut self) -> Result<[u8; 12], CodecError> {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.nonce.to_le_bytes());
+        self.nonce = self.nonce.checked_add(1).ok_or(CodecError::NonceExhausted)?;
+        Ok(nonce)
+    }
+
+    /// 加密，`aad` 为附加认证数据
+    pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CodecError> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .encrypt(ChaChaNonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+            .map_err(|_| CodecError::Serialization("Encryption failed".to_string()))
+    }
+
+    /// 解密并校验认证标签，篡改、重放或乱序的密文都会失败
+    pub fn decrypt(&mut self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CodecError> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .decrypt(ChaChaNonce::from_slice(&nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| CodecError::DecryptionFailed)
+    }
+}
+
+/// 握手完成后的加密会话
+pub struct SecureSession {
+    sender: CipherState,
+    receiver: CipherState,
+    remote_public_key: [u8; 32],
+}
+
+impl SecureSession {
+    /// 对端已认证的身份公钥
+    pub fn remote_public_key(&self) -> [u8; 32] {
+        self.remote_public_key
+    }
+
+    /// 对端节点ID
+    pub fn remote_peer_id(&self) -> String {
+        peer_id_from_public_key(&self.remote_public_key)
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, CodecError> {
+        self.sender.encrypt(plaintext, &[])
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, CodecError> {
+        self.receiver.decrypt(ciphertext, &[])
+    }
+
+    /// 拆分为发送和接收两个方向，分别交给写任务和读任务
+    pub fn split(self) -> (CipherState, CipherState) {
+        (self.sender, self.receiver)
+    }
+}
+
+/// 双方从握手中得到的共享秘密
+struct HandshakeKeys {
+    /// 链式密钥
+    chaining_key: [u8; 32],
+    /// 握手记录哈希 h(协议名 || e_i || e_r)
+    transcript: [u8; 32],
+}
+
+impl HandshakeKeys {
+    fn derive(initiator_ephemeral: &[u8; 32], responder_ephemeral: &[u8; 32], shared_secret: &[u8; 32]) -> Self {
+        let transcript = hash(&[PROTOCOL_NAME, initiator_ephemeral, responder_ephemeral]);
+        Self {
+            chaining_key: hash(&[&transcript, shared_secret]),
+            transcript,
+        }
+    }
+
+    fn handshake_cipher(&self, label: &[u8]) -> CipherState {
+        CipherState::new(hash(&[&self.chaining_key, label]))
+    }
+
+    /// 响应方签名的内容
+    fn responder_proof(&self) -> [u8; 32] {
+        hash(&[b"responder", &self.transcript])
+    }
+
+    /// 发起方签名的内容，覆盖响应方已发送的密文
+    fn initiator_proof(&self, responder_payload: &[u8]) -> [u8; 32] {
+        hash(&[b"initiator", &self.transcript, responder_payload])
+    }
+
+    fn into_session(self, initiator: bool, remote_public_key: [u8; 32]) -> SecureSession {
+        let initiator_to_responder = CipherState::new(hash(&[&self.chaining_key, b"i2r"]));
+        let responder_to_initiator = CipherState::new(hash(&[&self.chaining_key, b"r2i"]));
+        let (sender, receiver) = if initiator {
+            (initiator_to_responder, responder_to_initiator)
+        } else {
+            (responder_to_initiator, initiator_to_responder)
+        };
+        SecureSession { sender, receiver, remote_public_key }
+    }
+}
+
+fn read_key(bytes: &[u8]) -> Result<[u8; 32], CodecError> {
+    bytes.try_into().map_err(|_| CodecError::HandshakeFailed("Invalid key length".to_string()))
+}
+
+/// 编码身份负载（身份公钥 + 对 `proof` 的签名）
+fn identity_payload(identity: &NodeIdentity, proof: &[u8]) -> Vec<u8> {
+    let mut payload = identity.public_key().to_vec();
+    payload.extend_from_slice(&identity.sign(proof));
+    payload
+}
+
+/// 校验身份负载，返回对端身份公钥
+fn verify_identity_payload(payload: &[u8], proof: &[u8]) -> Result<[u8; 32], CodecError> {
+    if payload.len() != IDENTITY_PAYLOAD_LEN {
+        return Err(CodecError::HandshakeFailed("Invalid identity payload".to_string()));
+    }
+    let public_key = read_key(&payload[..KEY_LEN])?;
+    let signature: [u8; 64] = payload[KEY_LEN..].try_into()
+        .map_err(|_| CodecError::HandshakeFailed("Invalid signature length".to_string()))?;
+    let verifying_key = VerifyingKey::from_bytes(&public_key).map_err(|_| CodecError::AuthenticationFailed)?;
+    verifying_key
+        .verify_strict(proof, &Signature::from_bytes(&signature))
+        .map_err(|_| CodecError::AuthenticationFailed)?;
+    Ok(public_key)
+}
+
+/// 发起方握手状态
+pub struct InitiatorHandshake {
+    ephemeral: EphemeralSecret,
+    ephemeral_public: [u8; 32],
+}
+
+impl InitiatorHandshake {
+    /// 生成临时密钥，返回握手状态和第一条消息 `e`
+    pub fn new() -> (Self, Vec<u8>) {
+        let ephemeral = EphemeralSecret::random();
+        let ephemeral_public = X25519PublicKey::from(&ephemeral).to_bytes();
+        (Self { ephemeral, ephemeral_public }, ephemeral_public.to_vec())
+    }
+
+    /// 处理响应方的第二条消息，认证对端后返回第三条消息和加密会话
+    pub fn finish(self, identity: &NodeIdentity, message: &[u8]) -> Result<(Vec<u8>, SecureSession), CodecError> {
+        if message.len() != KEY_LEN + IDENTITY_PAYLOAD_LEN + TAG_LEN {
+            return Err(CodecError::HandshakeFailed("Invalid responder message".to_string()));
+        }
+        let responder_ephemeral = read_key(&message[..KEY_LEN])?;
+        let shared_secret = self.ephemeral.diffie_hellman(&X25519PublicKey::from(responder_ephemeral));
+        let keys = HandshakeKeys::derive(&self.ephemeral_public, &responder_ephemeral, shared_secret.as_bytes());
+
+        let responder_payload = &message[KEY_LEN..];
+        let payload = keys.handshake_cipher(b"responder").decrypt(responder_payload, &keys.transcript)?;
+        let remote_public_key = verify_identity_payload(&payload, &keys.responder_proof())?;
+
+        let proof = keys.initiator_proof(responder_payload);
+        let reply = keys.handshake_cipher(b"initiator")
+            .encrypt(&identity_payload(identity, &proof), &keys.transcript)?;
+        Ok((reply, keys.into_session(true, remote_public_key)))
+    }
+}
+
+/// 响应方握手状态
+pub struct ResponderHandshake {
+    keys: HandshakeKeys,
+    responder_payload: Vec<u8>,
+}
+
+impl ResponderHandshake {
+    /// 处理第一条消息，返回握手状态和第二条消息 `e, ee, s`
+    pub fn respond(identity: &NodeIdentity, message: &[u8]) -> Result<(Self, Vec<u8>), CodecError> {
+        let initiator_ephemeral = read_key(message)?;
+        let ephemeral = EphemeralSecret::random();
+        let ephemeral_public = X25519PublicKey::from(&ephemeral).to_bytes();
+        let shared_secret = ephemeral.diffie_hellman(&X25519PublicKey::from(initiator_ephemeral));
+        let keys = HandshakeKeys::derive(&initiator_ephemeral, &ephemeral_public, shared_secret.as_bytes());
+
+        let responder_payload = keys.handshake_cipher(b"responder")
+            .encrypt(&identity_payload(identity, &keys.responder_proof()), &keys.transcript)?;
+
+        let mut reply = ephemeral_public.to_vec();
+        reply.extend_from_slice(&responder_payload);
+        Ok((Self { keys, responder_payload }, reply))
+    }
+
+    /// 处理第三条消息，认证发起方后返回加密会话
+    pub fn finish(self, message: &[u8]) -> Result<SecureSession, CodecError> {
+        let payload = self.keys.handshake_cipher(b"initiator").decrypt(message, &self.keys.transcript)?;
+        let remote_public_key = verify_identity_payload(&payload, &self.keys.initiator_proof(&self.responder_payload))?;
+        Ok(self.keys.into_session(false, remote_public_key))
+    }
+}
+
+async fn write_handshake_message<S: AsyncWrite + Unpin>(stream: &mut S, message: &[u8]) -> Result<(), CodecError> {
+    stream.write_u16(message.len() as u16).await?;
+    stream.write_all(message).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_handshake_message<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Vec<u8>, CodecError> {
+    let length = stream.read_u16().await? as usize;
+    if length == 0 || length > MAX_HANDSHAKE_MESSAGE {
+        return Err(CodecError::HandshakeFailed(format!("Invalid handshake message length {}", length)));
+    }
+    let mut message = vec![0u8; length];
+    stream.read_exact(&mut message).await?;
+    Ok(message)
+}
+
+/// 在字节流上执行握手（每条握手消息带 2 字节长度前缀），出站连接为发起方
+pub async fn handshake<S>(stream: &mut S, identity: &NodeIdentity, initiator: bool) -> Result<SecureSession, CodecError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if initiator {
+        let (state, first) = InitiatorHandshake::new();
+        write_handshake_message(stream, &first).await?;
+        let second = read_handshake_message(stream).await?;
+        let (third, session) = state.finish(identity, &second)?;
+        write_handshake_message(stream, &third).await?;
+        Ok(session)
+    } else {
+        let first = read_handshake_message(stream).await?;
+        let (state, second) = ResponderHandshake::respond(identity, &first)?;
+        write_handshake_message(stream, &second).await?;
+        let third = read_handshake_message(stream).await?;
+        state.finish(&third)
+    }
+}
+
+/// 加密帧编解码器
+///
+/// 线路格式：密文长度(4) | 密文，明文为 [`NetworkCodec`] 编码的完整帧。
+/// 读写两端各持有一个方向的 [`CipherState`]。
+pub struct SecureCodec {
+    inner: NetworkCodec,
+    cipher: CipherState,
+    max_ciphertext: usize,
+}
+
+impl SecureCodec {
+    pub fn new(inner: NetworkCodec, cipher: CipherState) -> Self {
+        let max_ciphertext = HEADER_LEN + inner.max_frame_size() + TAG_LEN;
+        Self { inner, cipher, max_ciphertext }
+    }
+}
+
+impl Decoder for SecureCodec {
+    type Item = NetworkMessage;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < 4 {
+            return Ok(None);
+        }
+        let length = (&src[..4]).get_u32() as usize;
+        if length > self.max_ciphertext {
+            return Err(CodecError::FrameTooLarge { size: length, max: self.max_ciphertext });
+        }
+        if src.len() < 4 + length {
+            src.reserve(4 + length - src.len());
+            return Ok(None);
+        }
+
+        src.advance(4);
+        let ciphertext = src.split_to(length);
+        let mut plaintext = BytesMut::from(&self.cipher.decrypt(&ciphertext, &[])?[..]);
+        match self.inner.decode(&mut plaintext)? {
+            Some(message) if plaintext.is_empty() => Ok(Some(message)),
+            _ => Err(CodecError::Serialization("Encrypted frame does not contain exactly one message".to_string())),
+        }
+    }
+}
+
+impl Encoder<NetworkMessage> for SecureCodec {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: NetworkMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut plaintext = BytesMut::new();
+        self.inner.encode(item, &mut plaintext)?;
+        let ciphertext = self.cipher.encrypt(&plaintext, &[])?;
+
+        dst.reserve(4 + ciphertext.len());
+        dst.put_u32(ciphertext.len() as u32);
+        dst.put_slice(&ciphertext);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::network::message::PingMessage;
+
+    fn handshake_pair(initiator: &NodeIdentity, responder: &NodeIdentity) -> (SecureSession, SecureSession) {
+        let (state, first) = InitiatorHandshake::new();
+        let (responder_state, second) = ResponderHandshake::respond(responder, &first).unwrap();
+        let (third, initiator_session) = state.finish(initiator, &second).unwrap();
+        (initiator_session, responder_state.finish(&third).unwrap())
+    }
+
+    #[test]
+    fn test_handshake_authenticates_both_sides() {
+        let alice = NodeIdentity::generate();
+        let bob = NodeIdentity::generate();
+        let (mut a, mut b) = handshake_pair(&alice, &bob);
+
+        assert_eq!(a.remote_peer_id(), bob.peer_id());
+        assert_eq!(b.remote_peer_id(), alice.peer_id());
+
+        let ciphertext = a.encrypt(b"hello").unwrap();
+        assert_ne!(&ciphertext[..5], b"hello");
+        assert_eq!(b.decrypt(&ciphertext).unwrap(), b"hello");
+        let reply = b.encrypt(b"world").unwrap();
+        assert_eq!(a.decrypt(&reply).unwrap(), b"world");
+    }
+
+    #[test]
+    fn test_tampered_and_replayed_frames_rejected() {
+        let (mut a, mut b) = handshake_pair(&NodeIdentity::generate(), &NodeIdentity::generate());
+
+        let mut tampered = a.encrypt(b"payload").unwrap();
+        tampered[0] ^= 0x01;
+        assert!(matches!(b.decrypt(&tampered), Err(CodecError::DecryptionFailed)));
+
+        // nonce 已前移，重放旧密文无法通过认证
+        let (mut a, mut b) = handshake_pair(&NodeIdentity::generate(), &NodeIdentity::generate());
+        let frame = a.encrypt(b"payload").unwrap();
+        b.decrypt(&frame).unwrap();
+        assert!(b.decrypt(&frame).is_err());
+    }
+
+    #[test]
+    fn test_spoofed_identity_rejected() {
+        let alice = NodeIdentity::generate();
+        let bob = NodeIdentity::generate();
+        let mallory = NodeIdentity::generate();
+
+        // 中间人把响应中的身份公钥替换成 mallory 的，签名无法通过校验
+        let (state, first) = InitiatorHandshake::new();
+        let (_, mut second) = ResponderHandshake::respond(&bob, &first).unwrap();
+        second[KEY_LEN] ^= 0x01;
+        assert!(state.finish(&alice, &second).is_err());
+
+        // mallory 转发 alice 的第一条消息但无法冒充 bob：对 alice 而言认证到的是 mallory
+        let (state, first) = InitiatorHandshake::new();
+        let (_, second) = ResponderHandshake::respond(&mallory, &first).unwrap();
+        let (_, session) = state.finish(&alice, &second).unwrap();
+        assert_ne!(session.remote_peer_id(), bob.peer_id());
+    }
+
+    #[test]
+    fn test_identity_persistence() {
+        let path = std::env::temp_dir().join(format!("node_key_{}", rand::random::<u64>()));
+        let first = NodeIdentity::load_or_generate(&path).unwrap();
+        let second = NodeIdentity::load_or_generate(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(first.peer_id(), second.peer_id());
+        assert_eq!(first.peer_id().len(), 40);
+    }
+
+    #[test]
+    fn test_secure_codec_roundtrip() {
+        let (a, b) = handshake_pair(&NodeIdentity::generate(), &NodeIdentity::generate());
+        let (sender, _) = a.split();
+        let (_, receiver) = b.split();
+        let mut writer = SecureCodec::new(NetworkCodec::new(1), sender);
+        let mut reader = SecureCodec::new(NetworkCodec::new(1), receiver);
+
+        let mut buffer = BytesMut::new();
+        for timestamp in 1..=2 {
+            let ping = NetworkMessage::Ping(PingMessage { timestamp, peer_id: "a".to_string() });
+            writer.encode(ping, &mut buffer).unwrap();
+        }
+
+        assert!(matches!(reader.decode(&mut buffer).unwrap(), Some(NetworkMessage::Ping(p)) if p.timestamp == 1));
+        assert!(matches!(reader.decode(&mut buffer).unwrap(), Some(NetworkMessage::Ping(p)) if p.timestamp == 2));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_self_signed_certificate_binds_identity() {
+        let identity = NodeIdentity::generate();
+        let certificate = identity.self_signed_certificate();
+        assert_eq!(certificate_public_key(&certificate), Some(identity.public_key()));
+
+        // 证书签名覆盖 TBS 部分，由身份私钥签出
+        let (body, _) = read_der(&certificate, 0x30).unwrap();
+        let (_, rest) = read_der(body, 0x30).unwrap();
+        let tbs_len = body.len() - rest.len();
+        let rest = rest.strip_prefix(&ED25519_ALGORITHM[..]).unwrap();
+        let (signature, _) = read_der(rest, 0x03).unwrap();
+        let signature: [u8; 64] = signature[1..].try_into().unwrap();
+        assert!(verify_signature(&identity.public_key(), &body[..tbs_len], &signature));
+
+        let key = identity.pkcs8_private_key();
+        assert!(key.ends_with(&identity.secret_key()));
+        assert_eq!(certificate_public_key(&key), None);
+    }
+}
@@ -0,0 +1,511 @@
+// 区块同步
+//
+// 区块头优先：先用区块定位器向同步对端请求区块头链，逐批校验链接关系；
+// 再把区块体按批次分配给多个对端并行下载（滑动窗口），超时的批次改向其他对端重新请求，
+// 下载完成的区块按高度顺序交给链导入。
+use super::message::SyncRequest;
+use crate::components::{ComponentError, ComponentResult};
+use crate::core::{Block, BlockHeader};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// 单个响应最多返回的区块头数量
+pub const MAX_HEADERS_PER_RESPONSE: u32 = 2000;
+
+/// 单个响应最多返回的区块数量
+pub const MAX_BODIES_PER_RESPONSE: usize = 128;
+
+/// 同步配置
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// 每次请求的区块头数量
+    pub max_headers: u32,
+    /// 每个区块体请求包含的区块数
+    pub batch_size: usize,
+    /// 滑动窗口：尚未导入的区块中最多同时下载多少个
+    pub window_size: usize,
+    /// 请求超时时间，超时后改向其他对端请求
+    pub request_timeout: Duration,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            max_headers: MAX_HEADERS_PER_RESPONSE,
+            batch_size: 16,
+            window_size: 256,
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 同步进度
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// 是否正在同步
+    pub syncing: bool,
+    /// 目标高度（对端链头）
+    pub target_height: u64,
+    /// 已校验的区块头高度
+    pub header_height: u64,
+    /// 已下载的区块数
+    pub downloaded: u64,
+    /// 已验证并导入的高度
+    pub validated_height: u64,
+    /// 进行中的区块请求数
+    pub in_flight: usize,
+}
+
+impl SyncProgress {
+    /// 完成百分比
+    pub fn percent(&self) -> f64 {
+        if self.target_height == 0 {
+            return 100.0;
+        }
+        (self.validated_height.min(self.target_height) as f64 / self.target_height as f64) * 100.0
+    }
+}
+
+impl std::fmt::Display for SyncProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}/{} ({:.1}%), headers {}, downloaded {}, in flight {}",
+            if self.syncing { "syncing" } else { "idle" },
+            self.validated_height,
+            self.target_height,
+            self.percent(),
+            self.header_height,
+            self.downloaded,
+            self.in_flight,
+        )
+    }
+}
+
+/// 可在其他组件（CLI、Web API）中读取的同步进度句柄
+pub type SyncProgressHandle = Arc<RwLock<SyncProgress>>;
+
+/// 进行中的请求
+#[derive(Debug, Clone)]
+struct InFlight {
+    peer_id: String,
+    requested_at: Instant,
+}
+
+/// 区块同步状态机
+///
+/// 本身不做网络 IO：`next_requests` 给出应发送的请求，收到的响应交给 `on_headers` / `on_blocks`，
+/// 再用 `take_ready_blocks` 取出可以按顺序导入的区块。
+#[derive(Debug)]
+pub struct BlockSync {
+    config: SyncConfig,
+    /// 负责提供区块头的对端
+    sync_peer: Option<String>,
+    /// 同步开始时本地链的区块定位器
+    locator: Vec<[u8; 32]>,
+    /// 已校验、尚未导入的区块头
+    headers: BTreeMap<u64, BlockHeader>,
+    /// 最后一个已校验区块头的高度和哈希
+    header_tip: Option<(u64, [u8; 32])>,
+    /// 对端已没有更多区块头
+    headers_complete: bool,
+    header_request: Option<InFlight>,
+    /// 进行中的区块体请求（高度 -> 请求）
+    requested: HashMap<u64, InFlight>,
+    /// 上次请求超时的对端，重新请求时避开
+    timed_out: HashMap<u64, String>,
+    /// 已下载、等待导入的区块
+    bodies: BTreeMap<u64, Block>,
+    next_peer: usize,
+    progress: SyncProgressHandle,
+}
+
+impl Default for BlockSync {
+    fn default() -> Self {
+        Self::new(SyncConfig::default())
+    }
+}
+
+impl BlockSync {
+    pub fn new(config: SyncConfig) -> Self {
+        Self {
+            config,
+            sync_peer: None,
+            locator: Vec::new(),
+            headers: BTreeMap::new(),
+            header_tip: None,
+            headers_complete: false,
+            header_request: None,
+            requested: HashMap::new(),
+            timed_out: HashMap::new(),
+            bodies: BTreeMap::new(),
+            next_peer: 0,
+            progress: Arc::new(RwLock::new(SyncProgress::default())),
+        }
+    }
+
+    /// 当前进度
+    pub fn progress(&self) -> SyncProgress {
+        self.progress.read().unwrap().clone()
+    }
+
+    /// 共享的进度句柄
+    pub fn progress_handle(&self) -> SyncProgressHandle {
+        Arc::clone(&self.progress)
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.progress.read().unwrap().syncing
+    }
+
+    /// 是否已收到过区块头（第一批区块头需要由调用方确认其父区块在本地链上）
+    pub fn has_headers(&self) -> bool {
+        self.header_tip.is_some()
+    }
+
+    /// 开始从 `peer_id` 同步，`locator` 为本地链的区块定位器，`target_height` 为对端报告的链头高度
+    pub fn start(&mut self, peer_id: &str, locator: Vec<[u8; 32]>, local_height: u64, target_height: u64) {
+        self.reset();
+        self.sync_peer = Some(peer_id.to_string());
+        self.locator = locator;
+        {
+            let mut progress = self.progress.write().unwrap();
+            *progress = SyncProgress {
+                syncing: true,
+                target_height: target_height.max(local_height),
+                header_height: local_height,
+                downloaded: 0,
+                validated_height: local_height,
+                in_flight: 0,
+            };
+        }
+    }
+
+    /// 放弃本次同步（例如收到无效区块）
+    pub fn abort(&mut self) {
+        self.reset();
+        self.progress.write().unwrap().syncing = false;
+        self.update_progress();
+    }
+
+    fn reset(&mut self) {
+        self.sync_peer = None;
+        self.locator.clear();
+        self.headers.clear();
+        self.header_tip = None;
+        self.headers_complete = false;
+        self.header_request = None;
+        self.requested.clear();
+        self.timed_out.clear();
+        self.bodies.clear();
+    }
+
+    /// 在可用对端中轮流选择，尽量避开 `avoid`
+    fn pick_peer<'a>(&mut self, peers: &'a [String], avoid: Option<&String>) -> Option<&'a String> {
+        if peers.is_empty() {
+            return None;
+        }
+        for _ in 0..peers.len() {
+            let peer = &peers[self.next_peer % peers.len()];
+            self.next_peer = self.next_peer.wrapping_add(1);
+            if Some(peer) != avoid {
+                return Some(peer);
+            }
+        }
+        peers.first()
+    }
+
+    /// 计算此刻应该发送的请求：区块头请求（含超时重发）和滑动窗口内的区块体批次
+    pub fn next_requests(&mut self, peers: &[String], now: Instant) -> Vec<(String, SyncRequest)> {
+        let mut requests = Vec::new();
+        if !self.is_syncing() || peers.is_empty() {
+            return requests;
+        }
+        let timeout = self.config.request_timeout;
+
+        // 1. 区块头
+        let header_timed_out = self.header_request.as_ref()
+            .is_some_and(|request| now.duration_since(request.requested_at) >= timeout);
+        if !self.headers_complete && (self.header_request.is_none() || header_timed_out) {
+            let previous = self.header_request.take().map(|request| request.peer_id);
+            let peer = match &self.sync_peer {
+                Some(peer) if !header_timed_out && peers.contains(peer) => Some(peer.clone()),
+                _ => self.pick_peer(peers, previous.as_ref()).cloned(),
+            };
+            if let Some(peer) = peer {
+                let mut locator: Vec<[u8; 32]> = self.header_tip.iter().map(|(_, hash)| *hash).collect();
+                locator.extend_from_slice(&self.locator);
+                requests.push((peer.clone(), SyncRequest::Headers {
+                    locator,
+                    max_headers: self.config.max_headers,
+                }));
+                self.sync_peer = Some(peer.clone());
+                self.header_request = Some(InFlight { peer_id: peer, requested_at: now });
+            }
+        }
+
+        // 2. 超时的区块体请求释放回待下载队列
+        let expired: Vec<u64> = self.requested.iter()
+            .filter(|(_, request)| now.duration_since(request.requested_at) >= timeout)
+            .map(|(height, _)| *height)
+            .collect();
+        for height in expired {
+            if let Some(request) = self.requested.remove(&height) {
+                self.timed_out.insert(height, request.peer_id);
+            }
+        }
+
+        // 3. 滑动窗口内尚未请求的区块分批分配给各对端
+        let pending: Vec<(u64, [u8; 32])> = self.headers.iter()
+            .take(self.config.window_size)
+            .filter(|(height, _)| !self.requested.contains_key(height) && !self.bodies.contains_key(height))
+            .map(|(height, header)| (*height, header.block_hash))
+            .collect();
+        for batch in pending.chunks(self.config.batch_size.max(1)) {
+            let avoid = self.timed_out.get(&batch[0].0).cloned();
+            let Some(peer) = self.pick_peer(peers, avoid.as_ref()).cloned() else {
+                break;
+            };
+            for (height, _) in batch {
+                self.requested.insert(*height, InFlight { peer_id: peer.clone(), requested_at: now });
+            }
+            requests.push((peer, SyncRequest::Bodies {
+                hashes: batch.iter().map(|(_, hash)| *hash).collect(),
+            }));
+        }
+
+        self.update_progress();
+        requests
+    }
+
+    /// 处理区块头响应，校验高度连续、哈希正确且首尾相连
+    ///
+    /// 对区块头封装（工作量证明、签名）的校验由调用方用共识引擎完成。
+    pub fn on_headers(&mut self, peer_id: &str, headers: Vec<BlockHeader>) -> ComponentResult<usize> {
+        match &self.header_request {
+            Some(request) if request.peer_id == peer_id => {}
+            _ => return Ok(0),
+        }
+        self.header_request = None;
+
+        if headers.is_empty() {
+            self.headers_complete = true;
+            self.finish_if_done();
+            self.update_progress();
+            return Ok(0);
+        }
+
+        let mut tip = self.header_tip;
+        for header in &headers {
+            if header.hash() != header.block_hash {
+                return Err(ComponentError::NetworkError(format!("区块头哈希无效，高度 {}", header.height)));
+            }
+            if let Some((height, hash)) = tip
+                && (header.height != height + 1 || header.previous_hash != hash)
+            {
+                return Err(ComponentError::NetworkError(format!("区块头不连续，高度 {}", header.height)));
+            }
+            tip = Some((header.height, header.block_hash));
+        }
+
+        let count = headers.len();
+        if count < self.config.max_headers as usize {
+            self.headers_complete = true;
+        }
+        for header in headers {
+            self.headers.insert(header.height, header);
+        }
+        self.header_tip = tip;
+        if let Some((height, _)) = tip {
+            let mut progress = self.progress.write().unwrap();
+            progress.header_height = height;
+            progress.target_height = progress.target_height.max(height);
+        }
+        self.update_progress();
+        Ok(count)
+    }
+
+    /// 处理区块体响应，区块必须与已校验的区块头一致且 Merkle 根正确
+    pub fn on_blocks(&mut self, blocks: Vec<Block>) -> ComponentResult<usize> {
+        let mut accepted = 0;
+        for block in blocks {
+            let height = block.header.height;
+            let Some(header) = self.headers.get(&height) else {
+                continue;
+            };
+            if block.block_hash != header.block_hash || block.header.hash() != header.block_hash {
+                return Err(ComponentError::NetworkError(format!("区块与区块头不一致，高度 {}", height)));
+            }
+            if !block.has_valid_merkle_root() {
+                return Err(ComponentError::NetworkError(format!("区块 Merkle 根无效，高度 {}", height)));
+            }
+            self.requested.remove(&height);
+            self.timed_out.remove(&height);
+            if self.bodies.insert(height, block).is_none() {
+                accepted += 1;
+            }
+        }
+        self.progress.write().unwrap().downloaded += accepted as u64;
+        self.update_progress();
+        Ok(accepted)
+    }
+
+    /// 取出从最低未导入高度开始连续下载完成的区块
+    pub fn take_ready_blocks(&mut self) -> Vec<Block> {
+        let mut ready = Vec::new();
+        while let Some((&height, _)) = self.headers.first_key_value() {
+            let Some(block) = self.bodies.remove(&height) else {
+                break;
+            };
+            self.headers.remove(&height);
+            ready.push(block);
+        }
+        ready
+    }
+
+    /// 区块已导入链
+    pub fn on_imported(&mut self, height: u64) {
+        self.progress.write().unwrap().validated_height = height;
+        self.finish_if_done();
+        self.update_progress();
+    }
+
+    /// 对端断开：释放其进行中的请求
+    pub fn on_peer_disconnected(&mut self, peer_id: &str) {
+        self.requested.retain(|_, request| request.peer_id != peer_id);
+        if self.header_request.as_ref().is_some_and(|request| request.peer_id == peer_id) {
+            self.header_request = None;
+        }
+        if self.sync_peer.as_deref() == Some(peer_id) {
+            self.sync_peer = None;
+        }
+        self.update_progress();
+    }
+
+    fn finish_if_done(&mut self) {
+        if self.headers_complete && self.headers.is_empty() && self.header_request.is_none() {
+            self.progress.write().unwrap().syncing = false;
+        }
+    }
+
+    fn update_progress(&self) {
+        self.progress.write().unwrap().in_flight = self.requested.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(length: u64) -> Vec<Block> {
+        let mut blocks = vec![Block::create_genesis_block().unwrap()];
+        for height in 1..=length {
+            let previous_hash = blocks.last().unwrap().block_hash;
+            blocks.push(Block::new(previous_hash, Vec::new(), height, 1).unwrap());
+        }
+        blocks
+    }
+
+    fn headers(blocks: &[Block]) -> Vec<BlockHeader> {
+        blocks.iter().map(|block| block.header.clone()).collect()
+    }
+
+    fn config() -> SyncConfig {
+        SyncConfig { max_headers: 100, batch_size: 2, window_size: 4, request_timeout: Duration::from_secs(5) }
+    }
+
+    fn peers() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string()]
+    }
+
+    #[test]
+    fn test_headers_first_then_parallel_bodies() {
+        let blocks = chain(6);
+        let mut sync = BlockSync::new(config());
+        sync.start("a", vec![blocks[0].block_hash], 0, 6);
+        let now = Instant::now();
+
+        let requests = sync.next_requests(&peers(), now);
+        assert_eq!(requests.len(), 1);
+        assert!(matches!(&requests[0], (peer, SyncRequest::Headers { .. }) if peer == "a"));
+
+        assert_eq!(sync.on_headers("a", headers(&blocks[1..])).unwrap(), 6);
+        assert_eq!(sync.progress().header_height, 6);
+
+        // 窗口为 4、批次为 2：两批分配给不同对端
+        let requests = sync.next_requests(&peers(), now);
+        assert_eq!(requests.len(), 2);
+        assert_ne!(requests[0].0, requests[1].0);
+        assert_eq!(sync.progress().in_flight, 4);
+
+        // 乱序到达，只有连续的部分可以导入
+        sync.on_blocks(blocks[3..5].to_vec()).unwrap();
+        assert!(sync.take_ready_blocks().is_empty());
+        sync.on_blocks(blocks[1..3].to_vec()).unwrap();
+        let ready = sync.take_ready_blocks();
+        assert_eq!(ready.iter().map(|b| b.header.height).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        sync.on_imported(4);
+
+        // 窗口前移后请求剩余区块
+        let requests = sync.next_requests(&peers(), now);
+        assert_eq!(requests.len(), 1);
+        sync.on_blocks(blocks[5..].to_vec()).unwrap();
+        assert_eq!(sync.take_ready_blocks().len(), 2);
+        sync.on_imported(6);
+
+        let progress = sync.progress();
+        assert!(!progress.syncing);
+        assert_eq!(progress.downloaded, 6);
+        assert_eq!(progress.validated_height, 6);
+    }
+
+    #[test]
+    fn test_timed_out_batch_goes_to_another_peer() {
+        let blocks = chain(2);
+        let mut sync = BlockSync::new(config());
+        sync.start("a", Vec::new(), 0, 2);
+        let start = Instant::now();
+        sync.next_requests(&peers(), start);
+        sync.on_headers("a", headers(&blocks[1..])).unwrap();
+
+        let first = sync.next_requests(&peers(), start);
+        assert_eq!(first.len(), 1);
+        assert!(sync.next_requests(&peers(), start + Duration::from_secs(1)).is_empty());
+
+        let retry = sync.next_requests(&peers(), start + Duration::from_secs(6));
+        assert_eq!(retry.len(), 1);
+        assert_ne!(retry[0].0, first[0].0);
+        assert_eq!(retry[0].1, first[0].1);
+    }
+
+    #[test]
+    fn test_reject_disconnected_headers_and_mismatched_bodies() {
+        let blocks = chain(3);
+        let mut sync = BlockSync::new(config());
+        sync.start("a", Vec::new(), 0, 3);
+        sync.next_requests(&peers(), Instant::now());
+        let mut broken = headers(&blocks[1..]);
+        broken.remove(1);
+        assert!(sync.on_headers("a", broken).is_err());
+
+        sync.start("a", Vec::new(), 0, 3);
+        sync.next_requests(&peers(), Instant::now());
+        sync.on_headers("a", headers(&blocks[1..])).unwrap();
+        let mut forged = blocks[1].clone();
+        forged.header.nonce += 1;
+        assert!(sync.on_blocks(vec![forged]).is_err());
+    }
+
+    #[test]
+    fn test_unsolicited_headers_ignored() {
+        let blocks = chain(2);
+        let mut sync = BlockSync::new(config());
+        sync.start("a", Vec::new(), 0, 2);
+        sync.next_requests(&peers(), Instant::now());
+
+        assert_eq!(sync.on_headers("b", headers(&blocks[1..])).unwrap(), 0);
+        assert!(!sync.has_headers());
+    }
+}
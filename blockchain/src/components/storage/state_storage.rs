@@ -2,6 +2,7 @@
 
 use super::{StorageComponent, StorageResult, StorageStats};
 use crate::core::{State, StateChange};
+use crate::core::state::ContractState;
 use std::collections::{HashMap, BTreeMap};
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -182,6 +183,19 @@ impl StateStorage {
         Ok(())
     }
 
+    /// 获取合约状态（代码、存储、余额和元数据）
+    pub async fn get_contract_state(&self, address: &str) -> StorageResult<Option<ContractState>> {
+        let current_state = self.current_state.read().await;
+        Ok(current_state.get_contract_state(address).await?.cloned())
+    }
+
+    /// 设置合约状态
+    pub async fn set_contract_state(&mut self, contract_state: ContractState) -> StorageResult<()> {
+        let mut current_state = self.current_state.write().await;
+        current_state.set_contract_state(contract_state).await?;
+        Ok(())
+    }
+
     /// 获取状态统计信息
     pub async fn get_state_stats(&self) -> StateStats {
         let states = self.states.read().await;
@@ -196,6 +210,7 @@ impl StateStorage {
             current_balances: current_state.balances.len(),
             current_nonces: current_state.nonces.len(),
             current_storage: current_state.storage.len(),
+            current_contracts: current_state.contract_states.len(),
         }
     }
 
@@ -265,6 +280,7 @@ pub struct StateStats {
     pub current_balances: usize,
     pub current_nonces: usize,
     pub current_storage: usize,
+    pub current_contracts: usize,
 }
 
 impl StorageComponent for StateStorage {
@@ -364,6 +380,23 @@ mod tests {
         assert_eq!(storage.get_storage("contract", "key").await.unwrap(), Some(vec![1, 2, 3]));
     }
 
+    #[tokio::test]
+    async fn test_contract_state_operations() {
+        let mut storage = StateStorage::new();
+        storage.initialize().await.unwrap();
+        
+        let mut contract = ContractState::new("0xabc".to_string(), vec![0, 97, 115, 109]);
+        contract.set_storage("counter".to_string(), vec![7]);
+        assert!(storage.set_contract_state(contract).await.is_ok());
+        
+        // 合约状态随当前状态保存并计入状态根
+        let stored = storage.get_contract_state("0xabc").await.unwrap().unwrap();
+        assert_eq!(stored.get_storage("counter"), Some(&vec![7]));
+        let state = storage.get_current_state().await.unwrap();
+        assert_ne!(state.get_state_root(), State::new().get_state_root());
+        assert_eq!(storage.get_state_stats().await.current_contracts, 1);
+    }
+
     #[tokio::test]
     async fn test_snapshot_operations() {
         let mut storage = StateStorage::new();
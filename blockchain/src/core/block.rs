@@ -1,6 +1,7 @@
 // 区块结构定义
 use serde::{Serialize, Deserialize};
 use crate::core::{Transaction, Result, BlockchainError};
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// 区块结构
@@ -45,6 +46,52 @@ pub struct BlockHeader {
     
     /// 区块哈希
     pub block_hash: [u8; 32],
+    
+    /// 执行本区块后账户余额的 Merkle 根（`State::balance_root`），全零表示未承诺
+    #[serde(default)]
+    pub balance_root: [u8; 32],
+    
+    /// 执行本区块后的完整状态根（`State::compute_state_root`），覆盖余额、nonce、存储和合约状态
+    #[serde(default)]
+    pub state_root: [u8; 32],
+    
+    /// 共识封装（出块者签名等），不参与区块哈希计算
+    #[serde(default)]
+    pub seal: Option<BlockSeal>,
+}
+
+/// 区块封装
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSeal {
+    /// 出块者地址
+    pub signer: String,
+    
+    /// 出块者对区块哈希的签名
+    pub signature: Vec<u8>,
+    
+    /// BFT 共识中验证者的提交签名（验证者地址 -> 签名）
+    pub commit_signatures: BTreeMap<String, Vec<u8>>,
+    
+    /// 提交签名所属的视图（HotStuff 的 QC 视图，PBFT 为 0）
+    #[serde(default)]
+    pub commit_view: u64,
+    
+    /// 权威证明中出块者对增删权威节点的投票
+    #[serde(default)]
+    pub authority_vote: Option<AuthorityVote>,
+}
+
+/// 权威节点增删投票
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorityVote {
+    /// 候选权威节点地址
+    pub candidate: String,
+    
+    /// 候选者的 Ed25519 公钥（移除投票可为空）
+    pub public_key: Vec<u8>,
+    
+    /// `true` 为添加，`false` 为移除
+    pub authorize: bool,
 }
 
 impl Block {
@@ -71,6 +118,9 @@ impl Block {
             nonce: 0,
             height,
             block_hash: [0u8; 32], // 将在挖矿时计算
+            balance_root: [0u8; 32],
+            state_root: [0u8; 32],
+            seal: None,
         };
         
         // 计算区块哈希
@@ -145,6 +195,8 @@ impl Block {
         hasher.update(&header.difficulty.to_be_bytes());
         hasher.update(&header.nonce.to_be_bytes());
         hasher.update(&header.height.to_be_bytes());
+        hasher.update(header.balance_root);
+        hasher.update(header.state_root);
         
         hasher.finalize().into()
     }
@@ -159,6 +211,12 @@ impl Block {
         hasher.finalize().into()
     }
     
+    /// 交易列表是否与区块头中的 Merkle 根一致（不检查时间戳，可用于校验历史区块）
+    pub fn has_valid_merkle_root(&self) -> bool {
+        let merkle_root = Self::calculate_merkle_root(&self.transactions);
+        merkle_root == self.header.merkle_root && merkle_root == self.merkle_root
+    }
+    
     /// 验证区块
     pub fn validate(&self) -> Result<()> {
         // 1. 验证区块头
@@ -232,9 +290,53 @@ impl Block {
         self.header.block_hash = Self::calculate_block_hash(&self.header);
         self.block_hash = self.header.block_hash;
     }
+    
+    /// 替换区块中的交易并更新 Merkle 根（需在封装之前设置）
+    pub fn set_transactions(&mut self, transactions: Vec<Transaction>) {
+        self.merkle_root = Self::calculate_merkle_root(&transactions);
+        self.header.merkle_root = self.merkle_root;
+        self.transactions = transactions;
+        self.header.block_hash = Self::calculate_block_hash(&self.header);
+        self.block_hash = self.header.block_hash;
+    }
+    
+    /// 承诺执行本区块后的余额根（需在封装之前设置）
+    pub fn set_balance_root(&mut self, balance_root: [u8; 32]) {
+        self.header.balance_root = balance_root;
+        self.header.block_hash = Self::calculate_block_hash(&self.header);
+        self.block_hash = self.header.block_hash;
+    }
+    
+    /// 承诺执行本区块后的完整状态根（需在封装之前设置）
+    pub fn set_state_root(&mut self, state_root: [u8; 32]) {
+        self.header.state_root = state_root;
+        self.header.block_hash = Self::calculate_block_hash(&self.header);
+        self.block_hash = self.header.block_hash;
+    }
+    
+    /// 设置共识封装
+    pub fn set_seal(&mut self, seal: BlockSeal) {
+        self.header.seal = Some(seal);
+    }
+    
+    /// 获取共识封装
+    pub fn seal(&self) -> Option<&BlockSeal> {
+        self.header.seal.as_ref()
+    }
+    
+    /// 写入工作量证明结果（nonce 和可能被滚动过的时间戳）
+    pub fn set_pow_solution(&mut self, nonce: u64, timestamp: u64) {
+        self.header.timestamp = timestamp;
+        self.set_nonce(nonce);
+    }
 }
 
 impl BlockHeader {
+    /// 计算区块头哈希
+    pub fn hash(&self) -> [u8; 32] {
+        Block::calculate_block_hash(self)
+    }
+    
     /// 验证区块头
     pub fn validate(&self) -> Result<()> {
         // 1. 验证版本号
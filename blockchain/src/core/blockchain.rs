@@ -1,8 +1,21 @@
 // 区块链核心结构定义
-use crate::core::{Block, BlockHeader, Transaction, State, Result, BlockchainError};
+use crate::core::{Block, BlockHeader, Transaction, State, Result, BlockchainError, ChainSpec, TxInclusionProof, BalanceProof, BalanceTree};
+use crate::core::{BlockContext, ContractExecutor, ContractReceipt, StateJournal, BLOCK_GAS_LIMIT};
 use crate::components::{NetworkComponent};
+use crate::components::network::P2PNetwork;
+use crate::components::network::{
+    BlockTxnMessage, CompactBlock, InventoryItem, InventoryKind, LightRequest, LightResponse, LightResponseMessage,
+    ConsensusMessage, Misbehavior, NetworkMessage, PartialBlock, SyncProgress, SyncRequest, SyncResponse,
+};
+use crate::components::network::sync::{MAX_BODIES_PER_RESPONSE, MAX_HEADERS_PER_RESPONSE};
+use crate::components::consensus::{
+    ConsensusAction, ConsensusComponent, ConsensusStats, ChainHead, ProofOfWork, FinalityGadget, CheckpointVote, FinalityProof,
+};
 // use serde::{Serialize, Deserialize};
-// use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 等待证书的 BFT 提案区块数量上限
+const MAX_PROPOSALS: usize = 64;
 
 /// 区块链主结构
 pub struct Blockchain {
@@ -30,12 +43,51 @@ pub struct Blockchain {
     /// 网络组件
     pub network: NetworkComponent,
     
+    /// 共识引擎（出块者选择、封装、验证、分叉选择和最终性）
+    pub consensus: Box<dyn ConsensusComponent>,
+    
+    /// 最长链共识之上的最终性小工具（可选）
+    pub finality: Option<FinalityGadget>,
+    
+    /// 不在主链上的分叉区块（区块哈希 -> 区块）
+    pub fork_blocks: HashMap<[u8; 32], Block>,
+    
+    /// 最新已最终确认的区块高度（不可回滚）
+    pub finalized_height: u64,
+    
+    /// 最新已最终确认的区块哈希
+    pub finalized_hash: [u8; 32],
+    
+    /// 合约执行器，未设置时包含合约交易的区块无效
+    pub contract_executor: Option<Box<dyn ContractExecutor>>,
+    
+    /// 主链上合约交易的回执（交易哈希 -> 回执）
+    pub contract_receipts: HashMap<[u8; 32], ContractReceipt>,
+    
+    /// 主链各高度区块执行后的状态快照，回滚时直接恢复；只保留最终确认高度及以上的快照
+    state_snapshots: BTreeMap<u64, State>,
+    
+    /// 尚未应用到本地链的最终性证明（最终确认高度 -> 证明），区块到达后再应用
+    pending_finality: BTreeMap<u64, FinalityProof>,
+    
+    /// 链头的余额树（区块哈希，树），为轻客户端生成余额证明时按区块缓存
+    balance_tree: std::sync::Mutex<Option<([u8; 32], BalanceTree)>>,
+    
+    /// 等待法定人数证书的 BFT 提案区块（区块哈希 -> 区块）
+    proposals: HashMap<[u8; 32], Block>,
+    
     // 存储（简化占位）
 }
 
 impl Blockchain {
-    /// 创建新的区块链
+    /// 创建新的区块链（默认使用工作量证明）
     pub fn new(network_id: u32, genesis_block: Block) -> Self {
+        Self::with_consensus(network_id, genesis_block, Box::new(ProofOfWork::new(1)))
+    }
+    
+    /// 使用指定共识引擎创建区块链
+    pub fn with_consensus(network_id: u32, genesis_block: Block, consensus: Box<dyn ConsensusComponent>) -> Self {
+        let genesis_hash = genesis_block.block_hash;
         Self {
             genesis_block: genesis_block.clone(),
             blocks: vec![genesis_block],
@@ -44,10 +96,43 @@ impl Blockchain {
             network_id,
             state: State::new(),
             transaction_pool: Vec::new(),
-            network: NetworkComponent::new(),
+            network: NetworkComponent::with_p2p_network(P2PNetwork::with_network_id(network_id)),
+            consensus,
+            finality: None,
+            fork_blocks: HashMap::new(),
+            finalized_height: 0,
+            finalized_hash: genesis_hash,
+            contract_executor: None,
+            contract_receipts: HashMap::new(),
+            state_snapshots: BTreeMap::new(),
+            pending_finality: BTreeMap::new(),
+            balance_tree: std::sync::Mutex::new(None),
+            proposals: HashMap::new(),
         }
     }
     
+    /// 设置合约执行器
+    pub fn set_contract_executor(&mut self, executor: Box<dyn ContractExecutor>) {
+        self.contract_executor = Some(executor);
+    }
+    
+    /// 获取合约交易的回执
+    pub fn get_contract_receipt(&self, tx_hash: &[u8; 32]) -> Option<&ContractReceipt> {
+        self.contract_receipts.get(tx_hash)
+    }
+    
+    /// 按链规范创建区块链
+    pub fn from_spec(spec: &ChainSpec) -> Result<Self> {
+        let genesis_block = spec.genesis_block()?;
+        let consensus = spec.build_consensus(genesis_block.block_hash)?;
+        let mut blockchain = Self::with_consensus(spec.network_id, genesis_block, consensus);
+        blockchain.difficulty = spec.difficulty();
+        blockchain.finality = spec.build_finality_gadget(blockchain.genesis_block.block_hash)?;
+        blockchain.network.bootstrap_nodes = spec.bootstrap_addresses()?;
+        blockchain.network.p2p_network.set_transport(spec.transport);
+        Ok(blockchain)
+    }
+    
     /// 添加交易到区块链
     pub async fn add_transaction(&mut self, tx: Transaction) -> Result<()> {
         // 1. 验证交易
@@ -63,26 +148,276 @@ impl Blockchain {
     }
     
     /// 挖矿创建新区块
+    ///
+    /// 需要验证者证书的 BFT 共识返回的是已发出的提案，区块获得证书后才会加入链
     pub async fn mine_block(&mut self) -> Result<Block> {
         // 1. 收集交易
         let transactions = self.collect_transactions().await?;
         
-        // 2. 创建区块
-        // TODO: Implement consensus mechanism
-        let block = self.create_simple_block(transactions).await?;
+        // 2. 创建区块模板
+        let previous_hash = self.blocks.last()
+            .map(|last_block| last_block.block_hash)
+            .unwrap_or([0u8; 32]);
+        let mut block = Block::new(previous_hash, Vec::new(), self.current_height + 1, self.difficulty)?;
+        
+        // 3. 逐笔试执行，无法执行的交易直接丢弃，不影响其余交易
+        let mut next_state = self.state.clone();
+        let context = BlockContext { height: block.header.height, timestamp: block.header.timestamp };
+        let executor = self.contract_executor.as_deref_mut();
+        let mut deferred = Vec::new();
+        let transactions = Self::select_transactions(&mut next_state, transactions, executor, &context, &mut deferred).await;
+        self.transaction_pool.extend(deferred);
+        // 区块导入时在链上状态上重新执行，合约执行器先丢弃试执行的结果
+        if let Err(e) = self.restore_executor() {
+            self.transaction_pool.extend(transactions);
+            return Err(e);
+        }
+        block.set_transactions(transactions);
+        block.set_balance_root(next_state.balance_root());
+        block.set_state_root(next_state.compute_state_root());
+        
+        // 4. 由共识引擎封装区块，失败时交易放回交易池；
+        //    封装期间收到的消息暂存，对端发来同高度或更高的有效区块时取消封装
+        let (sealed, deferred) = self.seal_block(&mut block).await;
+        for (peer_id, message) in deferred {
+            if let Err(e) = self.handle_message(&peer_id, message).await {
+                log::warn!("Failed to handle message from {}: {}", peer_id, e);
+            }
+        }
+        if let Err(e) = sealed {
+            self.transaction_pool.extend(block.transactions);
+            return Err(e);
+        }
+        
+        // 5. 尚未获得证书的区块作为提案发给验证者，由 Certify 动作导入；
+        //    交易留在交易池中，区块导入时才移出
+        if !matches!(self.consensus.validate_block(&block).await, Ok(true)) {
+            self.transaction_pool.extend(block.transactions.iter().cloned());
+            let actions = self.consensus.propose(&block)?;
+            self.proposals.insert(block.block_hash, block.clone());
+            self.apply_consensus_actions(actions, Some(&block)).await?;
+            return Ok(block);
+        }
+        
+        // 6. 导入区块，失败时交易同样放回交易池
+        if let Err(e) = self.import_block(block.clone()).await {
+            self.transaction_pool.extend(block.transactions);
+            return Err(e);
+        }
+        
+        // 7. 广播区块
+        self.network.broadcast_block(&block).await?;
+        
+        Ok(block)
+    }
+    
+    /// 封装区块的同时接收网络消息，返回封装结果和封装期间收到的消息
+    ///
+    /// 消息带来的区块头通过共识验证且不低于正在封装的高度时，链头即将改变，继续封装只是浪费算力
+    async fn seal_block(&mut self, block: &mut Block) -> (Result<()>, Vec<(String, NetworkMessage)>) {
+        let height = block.header.height;
+        let mut deferred = Vec::new();
+        let sealing = self.consensus.mine_block(block);
+        tokio::pin!(sealing);
+        
+        loop {
+            tokio::select! {
+                sealed = &mut sealing => return (sealed, deferred),
+                Some((peer_id, message)) = self.network.next_message() => {
+                    let header = match &message {
+                        NetworkMessage::Block(block) => Some(&block.header),
+                        NetworkMessage::CompactBlock(message) => Some(&message.block.header),
+                        _ => None,
+                    };
+                    let new_tip = match header.filter(|header| header.height >= height) {
+                        Some(header) => matches!(self.consensus.validate_header(header).await, Ok(true)),
+                        None => false,
+                    };
+                    if new_tip {
+                        self.consensus.cancel_sealing();
+                    }
+                    deferred.push((peer_id, message));
+                }
+            }
+        }
+    }
+    
+    /// 导入区块（本地出块或从网络收到）
+    ///
+    /// 先由共识引擎验证封装；延长当前链头的区块直接追加，其余区块作为分叉保存并尝试分叉选择
+    pub async fn import_block(&mut self, block: Block) -> Result<()> {
+        if self.contains_block(&block.block_hash) {
+            return Ok(());
+        }
+        
+        // 区块头必须承诺区块中的交易，否则转发者可以替换交易
+        if !block.has_valid_merkle_root() {
+            return Err(BlockchainError::InvalidBlock(format!("Merkle 根不匹配，高度: {}", block.header.height)));
+        }
+        
+        if !self.consensus.validate_block(&block).await? {
+            return Err(BlockchainError::InvalidBlock(format!(
+                "{} 共识验证失败，高度: {}", self.consensus.name(), block.header.height
+            )));
+        }
         
-        // 3. 验证区块
         if self.validate_block(&block).await {
-            // 4. 添加到区块链
-            self.add_block(block.clone()).await?;
-            
-            // 5. 广播区块
-            self.network.broadcast_block(&block).await?;
-            
-            Ok(block)
+            self.add_block(block).await?;
+            return self.apply_finality_proofs().await;
+        }
+        
+        if !self.contains_block(&block.header.previous_hash) {
+            return Err(BlockchainError::InvalidBlock("父区块未知".to_string()));
+        }
+        
+        self.consensus.on_fork_block(&block)?;
+        let block_hash = block.block_hash;
+        self.fork_blocks.insert(block_hash, block);
+        self.register_checkpoint(block_hash);
+        self.try_reorganize(block_hash).await?;
+        self.apply_finality_proofs().await
+    }
+    
+    /// 是否已知该区块（主链或分叉）
+    fn contains_block(&self, block_hash: &[u8; 32]) -> bool {
+        self.fork_blocks.contains_key(block_hash) || self.main_chain_position(block_hash).is_some()
+    }
+    
+    /// 区块在主链上的高度
+    fn main_chain_position(&self, block_hash: &[u8; 32]) -> Option<u64> {
+        self.blocks.iter()
+            .position(|block| block.block_hash == *block_hash)
+            .map(|position| position as u64)
+    }
+    
+    /// 主链到指定高度的累计工作量
+    fn total_difficulty_at(&self, height: u64) -> u128 {
+        self.blocks.iter()
+            .skip(1)
+            .take(height as usize)
+            .map(|block| ChainHead::block_work(block.header.difficulty))
+            .sum()
+    }
+    
+    /// 当前链头
+    pub fn chain_head(&self) -> ChainHead {
+        ChainHead {
+            height: self.current_height,
+            hash: self.blocks.last().map(|block| block.block_hash).unwrap_or([0u8; 32]),
+            total_difficulty: self.total_difficulty_at(self.current_height),
+        }
+    }
+    
+    /// 以某个分叉区块为候选链头进行分叉选择，切换成功返回 `true`
+    ///
+    /// 分叉点低于最终确认高度的分支永远不会被采纳
+    async fn try_reorganize(&mut self, candidate_hash: [u8; 32]) -> Result<bool> {
+        // 1. 沿父哈希回溯到主链，得到候选分支
+        let Some((fork_point, branch)) = self.branch_to(candidate_hash) else {
+            return Ok(false);
+        };
+        if fork_point < self.finalized_height {
+            return Ok(false);
+        }
+        
+        // 2. 分叉选择
+        let branch_work: u128 = branch.iter()
+            .map(|block| ChainHead::block_work(block.header.difficulty))
+            .sum();
+        let candidate = ChainHead {
+            height: fork_point + branch.len() as u64,
+            hash: candidate_hash,
+            total_difficulty: self.total_difficulty_at(fork_point) + branch_work,
+        };
+        if !self.consensus.prefer_chain(&self.chain_head(), &candidate) {
+            return Ok(false);
+        }
+        
+        self.switch_to_branch(fork_point, branch).await?;
+        Ok(true)
+    }
+    
+    /// 沿父哈希从分叉区块回溯到主链，返回分叉点高度和按高度排列的分支区块
+    fn branch_to(&self, block_hash: [u8; 32]) -> Option<(u64, Vec<Block>)> {
+        let mut branch = Vec::new();
+        let mut cursor = block_hash;
+        let fork_point = loop {
+            if let Some(height) = self.main_chain_position(&cursor) {
+                break height;
+            }
+            let block = self.fork_blocks.get(&cursor)?;
+            cursor = block.header.previous_hash;
+            branch.push(block.clone());
+        };
+        branch.reverse();
+        Some((fork_point, branch))
+    }
+    
+    /// 把主链切换到分叉点之后的分支
+    async fn switch_to_branch(&mut self, fork_point: u64, branch: Vec<Block>) -> Result<()> {
+        // 1. 切换前完整校验候选分支，无效区块从分叉中移除，主链保持不变
+        if let Err((invalid_hash, e)) = self.check_branch(fork_point, &branch).await {
+            self.fork_blocks.remove(&invalid_hash);
+            return Err(e);
+        }
+        
+        // 2. 回滚到分叉点，被移除的区块保留为分叉
+        let removed = self.rollback_to(fork_point).await?;
+        for block in removed {
+            self.fork_blocks.insert(block.block_hash, block);
+        }
+        
+        // 3. 应用候选分支（已上链的交易在 add_block 中移出交易池）
+        for block in branch {
+            self.fork_blocks.remove(&block.block_hash);
+            self.add_block(block).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 在分叉点状态的副本上依次校验分支区块的链接、高度、哈希和交易执行结果，
+    /// 失败时返回第一个无效区块的哈希；合约执行器随后重新载入主链状态
+    async fn check_branch(&mut self, fork_point: u64, branch: &[Block]) -> std::result::Result<(), ([u8; 32], BlockchainError)> {
+        let mut state = if fork_point == self.current_height {
+            self.state.clone()
         } else {
-            Err(BlockchainError::InvalidBlock("Block validation failed".to_string()))
+            match self.state_snapshots.get(&fork_point) {
+                Some(snapshot) => {
+                    if let Some(executor) = self.contract_executor.as_deref_mut() {
+                        executor.restore(snapshot).map_err(|e| (branch[0].block_hash, e))?;
+                    }
+                    snapshot.clone()
+                }
+                None => {
+                    let e = BlockchainError::InvalidState(format!("缺少高度 {} 的状态快照", fork_point));
+                    return Err((branch[0].block_hash, e));
+                }
+            }
+        };
+        
+        let mut result = Ok(());
+        let (mut parent_hash, mut parent_height) = (self.blocks[fork_point as usize].block_hash, fork_point);
+        for block in branch {
+            if block.header.previous_hash != parent_hash
+                || block.header.height != parent_height + 1
+                || block.header.hash() != block.block_hash
+            {
+                result = Err((block.block_hash, BlockchainError::InvalidBlock(format!(
+                    "分支区块未接在父区块上，高度: {}", block.header.height
+                ))));
+                break;
+            }
+            
+            if let Err(e) = self.execute_block(&mut state, block).await {
+                result = Err((block.block_hash, e));
+                break;
+            }
+            (parent_hash, parent_height) = (block.block_hash, block.header.height);
         }
+        
+        self.restore_executor().map_err(|e| (parent_hash, e))?;
+        result
     }
     
     /// 添加区块到区块链
@@ -92,37 +427,103 @@ impl Blockchain {
             return Err(BlockchainError::InvalidBlock("Block validation failed".to_string()));
         }
         
-        // 2. 执行交易
-        self.execute_transactions(&block.transactions).await?;
+        // 2. 执行交易，区块承诺的余额根和状态根必须与执行结果一致；区块无效时合约执行器重新载入链上状态
+        let mut next_state = self.state.clone();
+        let executed = self.execute_block(&mut next_state, &block).await;
+        let executed = match executed {
+            Ok(receipts) => self.consensus.on_block_imported(&block).map(|actions| (receipts, actions)),
+            Err(e) => Err(e),
+        };
+        let (receipts, consensus_actions) = match executed {
+            Ok(executed) => executed,
+            Err(e) => {
+                self.restore_executor()?;
+                return Err(e);
+            }
+        };
+        // 执行前的状态即父区块之后的状态，其中也包含不由区块产生的余额和合约状态
+        self.state_snapshots.insert(self.current_height, std::mem::replace(&mut self.state, next_state));
+        self.contract_receipts.extend(receipts.into_iter().map(|receipt| (receipt.tx_hash, receipt)));
         
-        // 3. 更新状态
+        // 3. 更新状态，已打包的交易移出交易池
         self.update_state(&block).await?;
+        let included: HashSet<[u8; 32]> = block.transactions.iter().map(Transaction::hash).collect();
+        self.transaction_pool.retain(|tx| !included.contains(&tx.hash()));
         
         // 4. 存储区块
         // TODO: store block via storage component when available
         
-        // 5. 添加到区块链
+        // 5. 添加到区块链，链头改变后正在进行的封装作废；BFT 类共识的区块立即最终确认
+        self.consensus.cancel_sealing();
+        let is_final = self.consensus.is_final(&block);
+        let (height, block_hash) = (block.header.height, block.block_hash);
         self.blocks.push(block);
         self.current_height += 1;
+        self.register_checkpoint(block_hash);
+        
+        if is_final {
+            self.finalize_block(height, block_hash)?;
+        }
+        for action in consensus_actions {
+            if let ConsensusAction::Finalize { height, block_hash } = action {
+                self.apply_consensus_finality(height, block_hash)?;
+            }
+        }
         
         Ok(())
     }
     
+    /// 在 `state` 上执行区块中的交易，检查区块承诺的余额根和状态根
+    async fn execute_block(&mut self, state: &mut State, block: &Block) -> Result<Vec<ContractReceipt>> {
+        let context = BlockContext { height: block.header.height, timestamp: block.header.timestamp };
+        let executor = self.contract_executor.as_deref_mut();
+        let receipts = Self::execute_transactions(state, &block.transactions, executor, &context).await?;
+        if block.header.balance_root != [0u8; 32] && block.header.balance_root != state.balance_root() {
+            return Err(BlockchainError::InvalidBlock(format!("余额根不匹配，高度: {}", block.header.height)));
+        }
+        if block.header.state_root != state.compute_state_root() {
+            return Err(BlockchainError::InvalidBlock(format!("状态根不匹配，高度: {}", block.header.height)));
+        }
+        Ok(receipts)
+    }
+    
+    /// 合约执行器重新载入链上状态
+    fn restore_executor(&mut self) -> Result<()> {
+        match self.contract_executor.as_deref_mut() {
+            Some(executor) => executor.restore(&self.state),
+            None => Ok(()),
+        }
+    }
+    
     /// 验证交易
     async fn validate_transaction(&self, tx: &Transaction) -> Result<()> {
-        // 1. 验证交易格式
-        if tx.inputs.is_empty() || tx.outputs.is_empty() {
-            return Err(BlockchainError::InvalidTransaction("Empty inputs or outputs".to_string()));
-        }
-        
-        // 2. 验证签名
-        for input in &tx.inputs {
-            if !self.verify_signature(tx, &input.signature, &input.public_key).await? {
-                return Err(BlockchainError::InvalidTransaction("Invalid signature".to_string()));
+        // 1. 验证交易格式、金额和签名，合约交易由执行器处理，不需要输入输出
+        tx.validate()?;
+        if let Some(contract) = &tx.contract {
+            if self.contract_executor.is_none() {
+                return Err(BlockchainError::InvalidTransaction("No contract executor configured".to_string()));
+            }
+            // 余额必须足以支付转账金额和按 gas 上限计算的费用（格式检查已排除溢出）
+            let max_cost = contract.max_cost().unwrap_or(u64::MAX);
+            if self.state.get_balance(&contract.sender).await? < max_cost {
+                return Err(BlockchainError::InvalidTransaction("Insufficient balance for value and gas".to_string()));
             }
+            // nonce 必须紧接状态中的 nonce 和交易池中该发送方已有的合约交易
+            let pending = self.transaction_pool.iter()
+                .filter_map(|pooled| pooled.contract.as_ref())
+                .filter(|pooled| pooled.sender == contract.sender)
+                .count() as u64;
+            let expected = self.state.get_nonce(&contract.sender).await? + pending;
+            if contract.nonce != expected {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "Invalid nonce for {}: expected {}, got {}", contract.sender, expected, contract.nonce
+                )));
+            }
+        } else if tx.inputs.is_empty() || tx.outputs.is_empty() {
+            return Err(BlockchainError::InvalidTransaction("Empty inputs or outputs".to_string()));
         }
         
-        // 3. 验证余额
+        // 2. 验证余额
         for input in &tx.inputs {
             let balance = self.state.get_balance(&input.address).await?;
             if balance < input.amount {
@@ -133,96 +534,133 @@ impl Blockchain {
         Ok(())
     }
     
-    /// 创建简单区块
-    async fn create_simple_block(&self, transactions: Vec<Transaction>) -> Result<Block> {
-        let height = self.current_height + 1;
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        let previous_hash = if let Some(last_block) = self.blocks.last() {
-            last_block.header.block_hash
-        } else {
-            [0u8; 32]
-        };
-        
-        let merkle_root = self.calculate_merkle_root(&transactions);
-        
-        let mut _block_hash = [0u8; 32];
-        let mut nonce = 0u64;
+    /// 验证区块是否延长当前链头（封装由共识引擎验证）
+    async fn validate_block(&self, block: &Block) -> bool {
+        let tip_hash = self.blocks.last().map(|last_block| last_block.block_hash);
+        block.header.height == self.current_height + 1
+            && tip_hash == Some(block.header.previous_hash)
+            && block.header.hash() == block.block_hash
+    }
+    
+    /// 在给定状态上执行交易，返回合约交易的回执
+    ///
+    /// 区块可能来自网络，执行前先检查合约交易的 gas 上限之和不超过区块 gas 上限，再逐笔重新验证
+    /// 交易格式和签名，拒绝区块内的重复交易和重复花费；余额按区块内的执行顺序累计检查
+    async fn execute_transactions(
+        state: &mut State,
+        transactions: &[Transaction],
+        mut executor: Option<&mut (dyn ContractExecutor + 'static)>,
+        context: &BlockContext,
+    ) -> Result<Vec<ContractReceipt>> {
+        let block_gas = transactions.iter()
+            .filter_map(|tx| tx.contract.as_ref())
+            .try_fold(0u64, |total, contract| total.checked_add(contract.gas_limit));
+        if block_gas.is_none_or(|gas| gas > BLOCK_GAS_LIMIT) {
+            return Err(BlockchainError::InvalidBlock(format!("合约交易的 gas 上限之和超过 {}", BLOCK_GAS_LIMIT)));
+        }
         
-        // 简化的挖矿过程
-        loop {
-            let header = BlockHeader {
-                version: 1,
-                previous_hash,
-                merkle_root,
-                timestamp,
-                difficulty: self.difficulty,
-                nonce,
-                height,
-                block_hash: [0u8; 32], // 临时值
-            };
+        let mut receipts = Vec::new();
+        let mut seen_transactions = HashSet::new();
+        let mut spent_outputs = HashSet::new();
+        for tx in transactions {
+            // 1. 验证交易本身
+            tx.validate()?;
+            if !seen_transactions.insert(tx.hash()) {
+                return Err(BlockchainError::InvalidTransaction(format!("区块内重复的交易 {}", hex::encode(tx.hash()))));
+            }
+            if let Some(input) = tx.inputs.iter().find(|input| !spent_outputs.insert(input.previous_output.clone())) {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "区块内重复花费的输出 {}:{}", hex::encode(input.previous_output.tx_hash), input.previous_output.output_index
+                )));
+            }
             
-            _block_hash = self.simple_hash(&header);
+            // 2. 更新输入地址余额
+            for input in &tx.inputs {
+                let current_balance = state.get_balance(&input.address).await?;
+                let balance = current_balance.checked_sub(input.amount).ok_or_else(|| {
+                    BlockchainError::InsufficientBalance(format!(
+                        "{} 余额 {} 不足 {}", input.address, current_balance, input.amount
+                    ))
+                })?;
+                state.set_balance(&input.address, balance).await?;
+            }
             
-            if self.verify_proof_of_work(&Block { header: BlockHeader { block_hash: _block_hash, ..header }, transactions: transactions.clone(), block_hash: _block_hash, merkle_root }).await? {
-                break;
+            // 3. 更新输出地址余额
+            for output in &tx.outputs {
+                let current_balance = state.get_balance(&output.address).await?;
+                let balance = current_balance.checked_add(output.amount).ok_or_else(|| {
+                    BlockchainError::InvalidTransaction(format!("{} 余额溢出", output.address))
+                })?;
+                state.set_balance(&output.address, balance).await?;
             }
             
-            nonce += 1;
-            if nonce > 1000000 { // 防止无限循环
-                return Err(BlockchainError::InvalidBlock("挖矿超时".to_string()));
-            }
-        }
-        
-        Ok(Block {
-            header: BlockHeader {
-                version: 1,
-                previous_hash,
-                merkle_root,
-                timestamp,
-                difficulty: self.difficulty,
-                nonce,
-                height,
-                block_hash: _block_hash,
-            },
-            transactions,
-            block_hash: _block_hash,
-            merkle_root,
-        })
-    }
-    
-    /// 验证区块
-    async fn validate_block(&self, _block: &Block) -> bool {
-        // 简化的验证逻辑
-        true
+            // 4. 执行合约交易
+            if let Some(contract) = &tx.contract {
+                let executor = executor.as_deref_mut().ok_or_else(|| {
+                    BlockchainError::InvalidTransaction("No contract executor configured".to_string())
+                })?;
+                receipts.push(executor.execute(state, tx.hash(), contract, context)?);
+            }
+        }
+        
+        Ok(receipts)
     }
     
-    /// 执行交易
-    async fn execute_transactions(&mut self, transactions: &[Transaction]) -> Result<()> {
-        for tx in transactions {
-            // 1. 更新输入地址余额
-            for input in &tx.inputs {
-                let current_balance = self.state.get_balance(&input.address).await?;
-                self.state.set_balance(&input.address, current_balance - input.amount).await?;
+    /// 在 `state` 上逐笔试执行候选交易，返回可以打包的交易
+    ///
+    /// 执行失败（nonce 不符、余额不足等）以及与已选交易重复或重复花费的交易被丢弃，不放回交易池；
+    /// 区块 gas 上限已用完时合约交易放回交易池，留给后续区块
+    async fn select_transactions(
+        state: &mut State,
+        candidates: Vec<Transaction>,
+        mut executor: Option<&mut (dyn ContractExecutor + 'static)>,
+        context: &BlockContext,
+        deferred: &mut Vec<Transaction>,
+    ) -> Vec<Transaction> {
+        let mut selected = Vec::new();
+        let mut seen_transactions = HashSet::new();
+        let mut spent_outputs = HashSet::new();
+        let mut block_gas = 0u64;
+        for tx in candidates {
+            let conflicting = seen_transactions.contains(&tx.hash())
+                || tx.inputs.iter().any(|input| spent_outputs.contains(&input.previous_output));
+            if conflicting {
+                log::debug!("Dropped conflicting transaction {}", hex::encode(tx.hash()));
+                continue;
+            }
+            let gas_limit = tx.contract.as_ref().map_or(0, |contract| contract.gas_limit);
+            if block_gas.saturating_add(gas_limit) > BLOCK_GAS_LIMIT {
+                deferred.push(tx);
+                continue;
             }
             
-            // 2. 更新输出地址余额
-            for output in &tx.outputs {
-                let current_balance = self.state.get_balance(&output.address).await?;
-                self.state.set_balance(&output.address, current_balance + output.amount).await?;
+            // 合约执行器对无效交易不修改状态，只需记下输入输出地址的余额，失败时撤销
+            let mut journal = StateJournal::default();
+            for address in tx.inputs.iter().map(|input| &input.address).chain(tx.outputs.iter().map(|output| &output.address)) {
+                journal.record_balance(state, address);
+            }
+            let transactions = std::slice::from_ref(&tx);
+            match Self::execute_transactions(state, transactions, executor.as_deref_mut(), context).await {
+                Ok(_) => {
+                    block_gas += gas_limit;
+                    seen_transactions.insert(tx.hash());
+                    spent_outputs.extend(tx.inputs.iter().map(|input| input.previous_output.clone()));
+                    selected.push(tx);
+                }
+                Err(e) => {
+                    journal.revert(state);
+                    log::debug!("Dropped transaction {}: {}", hex::encode(tx.hash()), e);
+                }
             }
         }
         
-        Ok(())
+        selected
     }
     
     /// 更新状态
     async fn update_state(&mut self, block: &Block) -> Result<()> {
-        // 更新状态根
-        self.state.update_state_root_with_hash(block.header.block_hash);
+        // 状态根与区块头承诺的一致，不随区块哈希变化
+        self.state.update_state_root();
         
         // 更新其他状态信息
         self.state.set_latest_block_hash(block.header.block_hash);
@@ -244,69 +682,657 @@ impl Blockchain {
             }
         }
         
-        Ok(transactions)
+        // 合约交易排在普通交易之后，按发送方分组（按首次出现的顺序），组内按 nonce 排序
+        let (mut ordered, contracts): (Vec<Transaction>, Vec<Transaction>) =
+            transactions.into_iter().partition(|tx| tx.contract.is_none());
+        let mut groups: Vec<(String, Vec<Transaction>)> = Vec::new();
+        for tx in contracts {
+            let sender = tx.contract.as_ref().map(|contract| contract.sender.clone()).unwrap_or_default();
+            match groups.iter_mut().find(|(group, _)| *group == sender) {
+                Some((_, group)) => group.push(tx),
+                None => groups.push((sender, vec![tx])),
+            }
+        }
+        for (_, mut group) in groups {
+            group.sort_by_key(|tx| tx.contract.as_ref().map(|contract| contract.nonce));
+            ordered.extend(group);
+        }
+        
+        Ok(ordered)
     }
     
-    /// 验证签名
-    async fn verify_signature(&self, _tx: &Transaction, _signature: &[u8], _public_key: &[u8]) -> Result<bool> {
-        // 这里应该实现具体的签名验证逻辑
-        // 暂时返回true作为占位符
-        Ok(true)
+    /// 获取最新区块
+    pub fn get_latest_block(&self) -> Option<&Block> {
+        self.blocks.last()
     }
     
-    /// 计算Merkle根
-    fn calculate_merkle_root(&self, transactions: &[Transaction]) -> [u8; 32] {
-        use crate::core::merkle::MerkleTree;
+    /// 获取指定高度的区块
+    pub fn get_block_by_height(&self, height: u64) -> Option<&Block> {
+        if height <= self.current_height {
+            self.blocks.get(height as usize)
+        } else {
+            None
+        }
+    }
+    
+    /// 标记区块为最终确认
+    ///
+    /// 最终确认只能向前推进，且必须与本地链上对应高度的区块一致
+    pub fn finalize_block(&mut self, height: u64, block_hash: [u8; 32]) -> Result<()> {
+        let block = self.get_block_by_height(height).ok_or_else(|| {
+            BlockchainError::ConsensusFailed(format!("无法最终确认未知高度的区块: {}", height))
+        })?;
         
-        let tx_hashes: Vec<[u8; 32]> = transactions
-            .iter()
-            .map(|tx| tx.hash())
-            .collect();
+        if block.block_hash != block_hash {
+            return Err(BlockchainError::ConsensusFailed(format!(
+                "最终确认的区块与本地链冲突，高度: {}", height
+            )));
+        }
         
-        let merkle_tree = MerkleTree::new(tx_hashes);
-        merkle_tree.expect("Failed to build Merkle tree").root()
+        if height < self.finalized_height {
+            return Ok(());
+        }
+        
+        self.finalized_height = height;
+        self.finalized_hash = block_hash;
+        self.state_snapshots = self.state_snapshots.split_off(&height);
+        Ok(())
+    }
+    
+    /// 对主链上最新的检查点投票，没有新的检查点时返回 `None`
+    pub fn cast_checkpoint_vote(&self) -> Result<Option<CheckpointVote>> {
+        let Some(gadget) = &self.finality else {
+            return Ok(None);
+        };
+        
+        let epoch_length = gadget.epoch_length();
+        let height = self.current_height / epoch_length * epoch_length;
+        let block = self.get_block_by_height(height).ok_or_else(|| {
+            BlockchainError::ConsensusFailed(format!("主链缺少检查点区块: {}", height))
+        })?;
+        match gadget.checkpoint_at(height, block.block_hash) {
+            Some(target) if target.epoch > gadget.justified().epoch => Ok(Some(gadget.vote(target)?)),
+            _ => Ok(None),
+        }
     }
     
-    /// 验证工作量证明
-    async fn verify_proof_of_work(&self, block: &Block) -> Result<bool> {
-        let block_hash = block.header.block_hash;
-        let target = self.calculate_target();
+    /// 处理检查点投票，检查点被最终确认时同步推进本地最终确认高度
+    pub async fn on_checkpoint_vote(&mut self, vote: CheckpointVote) -> Result<Option<FinalityProof>> {
+        let gadget = self.finality.as_mut().ok_or_else(|| {
+            BlockchainError::ConsensusFailed("未启用最终性小工具".to_string())
+        })?;
+        
+        let proof = gadget.on_vote(vote)?;
+        if let Some(proof) = &proof {
+            self.pending_finality.insert(proof.finalized.height, proof.clone());
+        }
+        self.apply_finality_proofs().await?;
+        Ok(proof)
+    }
+    
+    /// 把检查点区块（主链或分叉）登记到最终性小工具，因此产生的最终性证明排队等待应用
+    fn register_checkpoint(&mut self, block_hash: [u8; 32]) {
+        let Some(gadget) = &self.finality else {
+            return;
+        };
+        let Some(height) = self.get_block_by_hash(&block_hash).map(|block| block.header.height) else {
+            return;
+        };
+        let Some(checkpoint) = gadget.checkpoint_at(height, block_hash).filter(|checkpoint| checkpoint.height > 0) else {
+            return;
+        };
+        let Some(parent_hash) = self.ancestor_hash(block_hash, height - gadget.epoch_length()) else {
+            return;
+        };
         
-        // 检查区块哈希是否小于目标值
-        Ok(block_hash < target)
+        let gadget = self.finality.as_mut().expect("已检查最终性小工具存在");
+        match gadget.add_checkpoint(checkpoint, parent_hash) {
+            Ok(proofs) => {
+                for proof in proofs {
+                    self.pending_finality.insert(proof.finalized.height, proof);
+                }
+            }
+            Err(e) => log::warn!("Failed to register checkpoint at height {}: {}", height, e),
+        }
+    }
+    
+    /// 区块（主链或分叉）在指定高度的祖先
+    fn ancestor_hash(&self, mut block_hash: [u8; 32], height: u64) -> Option<[u8; 32]> {
+        loop {
+            if let Some(position) = self.main_chain_position(&block_hash) {
+                return (position >= height).then(|| self.blocks[height as usize].block_hash);
+            }
+            let block = self.fork_blocks.get(&block_hash)?;
+            if block.header.height == height {
+                return Some(block_hash);
+            }
+            block_hash = block.header.previous_hash;
+        }
+    }
+    
+    /// 应用排队的最终性证明
+    ///
+    /// 被最终确认的区块在主链上时直接推进最终确认高度；在分叉上时不论分叉选择结果都切换到该分支；
+    /// 区块尚未到达的证明继续排队。与本地已最终确认的链冲突时返回错误
+    async fn apply_finality_proofs(&mut self) -> Result<()> {
+        loop {
+            let ready = self.pending_finality.iter()
+                .map(|(height, proof)| (*height, proof.finalized.block_hash))
+                .find(|(height, block_hash)| *height <= self.finalized_height || self.contains_block(block_hash));
+            let Some((height, block_hash)) = ready else {
+                return Ok(());
+            };
+            self.pending_finality.remove(&height);
+            
+            let conflict = || BlockchainError::ConsensusFailed(format!(
+                "最终确认的区块与本地已最终确认的链冲突，高度: {}", height
+            ));
+            if height <= self.finalized_height {
+                if self.get_block_by_height(height).map(|block| block.block_hash) != Some(block_hash) {
+                    return Err(conflict());
+                }
+                continue;
+            }
+            if self.main_chain_position(&block_hash) != Some(height) {
+                let (fork_point, branch) = self.branch_to(block_hash)
+                    .filter(|(fork_point, _)| *fork_point >= self.finalized_height)
+                    .ok_or_else(conflict)?;
+                self.switch_to_branch(fork_point, branch).await?;
+            }
+            self.finalize_block(height, block_hash)?;
+        }
+    }
+    
+    /// 共识统计信息（包含本地最终确认高度）
+    pub async fn consensus_stats(&self) -> Result<ConsensusStats> {
+        let mut stats = self.consensus.get_stats().await?;
+        stats.last_finalized_height = stats.last_finalized_height.max(self.finalized_height);
+        Ok(stats)
+    }
+    
+    /// 指定高度的区块是否已最终确认
+    pub fn is_finalized(&self, height: u64) -> bool {
+        height <= self.finalized_height
     }
     
-    /// 计算目标值
-    fn calculate_target(&self) -> [u8; 32] {
-        let mut target = [0u8; 32];
-        let leading_zeros = self.difficulty / 8;
-        let remaining_bits = self.difficulty % 8;
+    /// 获取最终确认高度
+    pub fn get_finalized_height(&self) -> u64 {
+        self.finalized_height
+    }
+    
+    /// 回滚到指定高度（分叉选择切换分支时使用）
+    ///
+    /// 已最终确认的区块永远不会被回滚；状态恢复为该高度的快照，合约执行器随之重新载入。
+    /// 被移除区块中的交易按恢复后的状态重新验证，仍然有效的放回交易池
+    pub async fn rollback_to(&mut self, height: u64) -> Result<Vec<Block>> {
+        if height < self.finalized_height {
+            return Err(BlockchainError::ConsensusFailed(format!(
+                "不能回滚已最终确认的区块: 目标高度 {} 低于最终确认高度 {}",
+                height, self.finalized_height
+            )));
+        }
         
-        for i in 0..leading_zeros {
-            target[i as usize] = 0;
+        if height >= self.current_height {
+            return Ok(Vec::new());
         }
         
-        if remaining_bits > 0 {
-            target[leading_zeros as usize] = 0xFF >> remaining_bits;
+        let snapshot = self.state_snapshots.get(&height).cloned().ok_or_else(|| {
+            BlockchainError::InvalidState(format!("缺少高度 {} 的状态快照", height))
+        })?;
+        if let Some(executor) = self.contract_executor.as_deref_mut() {
+            executor.restore(&snapshot)?;
         }
+        self.state = snapshot;
+        self.state_snapshots.retain(|snapshot_height, _| *snapshot_height < height);
         
-        target
+        let removed = self.blocks.split_off(height as usize + 1);
+        self.current_height = height;
+        
+        for tx in removed.iter().flat_map(|block| &block.transactions) {
+            self.contract_receipts.remove(&tx.hash());
+        }
+        let requeued = removed.iter().flat_map(|block| block.transactions.iter().cloned()).collect();
+        self.revalidate_pool(requeued).await;
+        
+        Ok(removed)
     }
     
-    /// 获取最新区块
-    pub fn get_latest_block(&self) -> Option<&Block> {
-        self.blocks.last()
+    /// 把 `transactions` 和交易池中的交易按当前状态重新验证，丢弃失效和重复的交易
+    async fn revalidate_pool(&mut self, transactions: Vec<Transaction>) {
+        let candidates: Vec<Transaction> = transactions.into_iter()
+            .chain(std::mem::take(&mut self.transaction_pool))
+            .collect();
+        let mut seen = HashSet::new();
+        for tx in candidates {
+            if !seen.insert(tx.hash()) {
+                continue;
+            }
+            match self.validate_transaction(&tx).await {
+                Ok(()) => self.transaction_pool.push(tx),
+                Err(e) => log::debug!("Dropped transaction {}: {}", hex::encode(tx.hash()), e),
+            }
+        }
     }
     
-    /// 获取指定高度的区块
-    pub fn get_block_by_height(&self, height: u64) -> Option<&Block> {
-        if height <= self.current_height {
-            self.blocks.get(height as usize)
-        } else {
-            None
+    /// 按哈希查找区块（主链或分叉）
+    pub fn get_block_by_hash(&self, block_hash: &[u8; 32]) -> Option<&Block> {
+        self.main_chain_position(block_hash)
+            .and_then(|height| self.blocks.get(height as usize))
+            .or_else(|| self.fork_blocks.get(block_hash))
+    }
+    
+    /// 区块定位器：从链头开始先取 10 个连续区块，之后步长逐次加倍，最后总是包含创世区块
+    pub fn block_locator(&self) -> Vec<[u8; 32]> {
+        let mut locator = Vec::new();
+        let mut height = self.current_height;
+        let mut step = 1;
+        loop {
+            locator.push(self.blocks[height as usize].block_hash);
+            if height == 0 {
+                break;
+            }
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            height = height.saturating_sub(step);
+        }
+        locator
+    }
+    
+    /// 定位器中第一个位于本地主链上的区块之后的区块头（都不认识时从创世区块之后开始）
+    pub fn headers_after(&self, locator: &[[u8; 32]], max_headers: usize) -> Vec<BlockHeader> {
+        let fork_point = locator.iter()
+            .find_map(|hash| self.main_chain_position(hash))
+            .unwrap_or(0);
+        self.blocks.iter()
+            .skip(fork_point as usize + 1)
+            .take(max_headers)
+            .map(|block| block.header.clone())
+            .collect()
+    }
+    
+    /// 响应对端的同步请求
+    pub fn serve_sync_request(&self, request: &SyncRequest) -> SyncResponse {
+        match request {
+            SyncRequest::Headers { locator, max_headers } => {
+                let max_headers = (*max_headers).min(MAX_HEADERS_PER_RESPONSE) as usize;
+                SyncResponse::Headers(self.headers_after(locator, max_headers))
+            }
+            SyncRequest::Bodies { hashes } => SyncResponse::Bodies(
+                hashes.iter()
+                    .take(MAX_BODIES_PER_RESPONSE)
+                    .filter_map(|hash| self.get_block_by_hash(hash).cloned())
+                    .collect(),
+            ),
+        }
+    }
+    
+    /// 响应轻客户端请求；余额证明基于当前链头，链头未承诺余额根时无法证明
+    pub fn serve_light_request(&self, request: &LightRequest) -> LightResponse {
+        match request {
+            LightRequest::TxProof { block_hash, tx_hash } => LightResponse::TxProof(
+                self.get_block_by_hash(block_hash)
+                    .and_then(|block| TxInclusionProof::generate(block, tx_hash).ok().flatten()),
+            ),
+            LightRequest::BalanceProof { address } => {
+                let tip = &self.blocks[self.blocks.len() - 1];
+                let mut cache = self.balance_tree.lock().unwrap();
+                if cache.as_ref().is_none_or(|(block_hash, _)| *block_hash != tip.block_hash) {
+                    // 链头之后的状态可能还有不由区块产生的变化，只有与链头承诺一致时才缓存
+                    *cache = self.state.balance_tree().ok()
+                        .filter(|tree| tree.root() == tip.header.balance_root)
+                        .map(|tree| (tip.block_hash, tree));
+                }
+                let proof = cache.as_ref()
+                    .and_then(|(_, tree)| tree.prove(address).ok().flatten())
+                    .map(|(balance, proof)| BalanceProof {
+                        block_hash: tip.block_hash,
+                        address: address.clone(),
+                        balance,
+                        proof,
+                    });
+                LightResponse::BalanceProof(proof)
+            }
+        }
+    }
+    
+    /// 用共识引擎校验区块头封装（工作量证明、出块签名或证书），不需要区块体
+    pub async fn validate_header(&self, header: &BlockHeader) -> Result<bool> {
+        self.consensus.validate_header(header).await
+    }
+    
+    /// 开始从对端同步，`target_height` 为对端报告的链头高度（未知时为 0）
+    pub fn start_sync(&mut self, peer_id: &str, target_height: u64) {
+        let locator = self.block_locator();
+        self.network.block_sync.start(peer_id, locator, self.current_height, target_height);
+    }
+    
+    /// 同步进度
+    pub fn sync_progress(&self) -> SyncProgress {
+        self.network.block_sync.progress()
+    }
+    
+    /// 处理网络消息：响应同步请求、推进区块同步，处理库存公告和收到的交易、区块，
+    /// 最后清除超时的对象请求，发出到期的交易公告和新的同步请求
+    pub async fn poll_network(&mut self) -> Result<SyncProgress> {
+        for (peer_id, message) in self.network.drain_messages() {
+            // 单条消息处理失败只影响它的来源对端，同一批次中其他对端的消息照常处理
+            if let Err(e) = self.handle_message(&peer_id, message).await {
+                log::warn!("Failed to handle message from {}: {}", peer_id, e);
+            }
+        }
+        
+        for block in self.network.block_sync.take_ready_blocks() {
+            let height = block.header.height;
+            if let Err(e) = self.import_block(block).await {
+                self.network.block_sync.abort();
+                return Err(e);
+            }
+            self.network.block_sync.on_imported(height);
+        }
+        
+        self.network.expire_requests().await;
+        self.network.flush_announcements().await?;
+        self.network.sync_with_peers().await?;
+        Ok(self.network.block_sync.progress())
+    }
+    
+    /// 处理一条来自对端的网络消息
+    async fn handle_message(&mut self, peer_id: &str, message: NetworkMessage) -> Result<()> {
+        match message {
+            NetworkMessage::SyncRequest(request) => {
+                let response = self.serve_sync_request(&request.request);
+                if let Err(e) = self.network.send_sync_response(peer_id, response).await {
+                    log::warn!("{}", e);
+                }
+            }
+            NetworkMessage::SyncResponse(response) => {
+                if let Err(e) = self.handle_sync_response(peer_id, response.response).await {
+                    // 对端提供了无效数据：断开并把它的请求交给其他对端
+                    log::warn!("Invalid sync response from {}: {}", peer_id, e);
+                    self.network.report_peer(peer_id, Misbehavior::ProtocolViolation).await;
+                    self.network.block_sync.on_peer_disconnected(peer_id);
+                    let _ = self.network.p2p_network.disconnect_peer(peer_id).await;
+                }
+            }
+            NetworkMessage::Inv(inv) => self.handle_inv(peer_id, inv.items).await?,
+            NetworkMessage::GetData(request) => self.serve_get_data(peer_id, &request.items).await?,
+            // 重复对象直接丢弃；add_transaction 验证通过后才会继续转发
+            NetworkMessage::Transaction(tx)
+                if self.network.gossip.on_object(peer_id, InventoryItem::transaction(tx.hash())) =>
+            {
+                if let Err(e) = self.add_transaction(tx).await {
+                    log::debug!("Rejected transaction from {}: {}", peer_id, e);
+                    self.network.report_peer(peer_id, Misbehavior::InvalidTransaction).await;
+                }
+            }
+            NetworkMessage::Block(block)
+                if self.network.gossip.on_object(peer_id, InventoryItem::block(block.block_hash)) =>
+            {
+                self.network.compact.cancel(&block.block_hash);
+                self.handle_relayed_block(peer_id, block).await?;
+            }
+            NetworkMessage::Transaction(_) | NetworkMessage::Block(_) => {
+                self.network.report_peer(peer_id, Misbehavior::Spam).await;
+            }
+            NetworkMessage::LightRequest(request) => {
+                let message = NetworkMessage::LightResponse(LightResponseMessage {
+                    request_id: request.request_id,
+                    response: self.serve_light_request(&request.request),
+                    peer_id: self.network.p2p_network.get_peer_id(),
+                });
+                self.network.send_object(peer_id, message).await?;
+            }
+            NetworkMessage::CompactBlock(message) => self.handle_compact_block(peer_id, message.block).await?,
+            NetworkMessage::GetBlockTxn(request) => {
+                self.serve_block_transactions(peer_id, request.block_hash, &request.indexes).await?;
+            }
+            NetworkMessage::BlockTxn(response) => {
+                self.handle_block_transactions(peer_id, response.block_hash, response.transactions).await?;
+            }
+            NetworkMessage::Consensus(message) => self.handle_consensus_message(peer_id, message).await?,
+            _ => {}
+        }
+        Ok(())
+    }
+    
+    /// 本地是否已有该库存对象
+    fn has_inventory(&self, item: &InventoryItem) -> bool {
+        match item.kind {
+            InventoryKind::Transaction => self.transaction_pool.iter().any(|tx| tx.hash() == item.hash),
+            InventoryKind::Block => self.contains_block(&item.hash),
+        }
+    }
+    
+    /// 处理库存公告：只请求本地还没有的对象
+    async fn handle_inv(&mut self, peer_id: &str, items: Vec<InventoryItem>) -> Result<()> {
+        let (known, unknown): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| self.has_inventory(item));
+        for item in known {
+            self.network.gossip.mark_known(peer_id, item);
+        }
+        self.network.handle_inv(peer_id, &unknown).await
+    }
+    
+    /// 回复 getdata：发送交易池中的交易和已知区块，找不到的条目忽略
+    async fn serve_get_data(&mut self, peer_id: &str, items: &[InventoryItem]) -> Result<()> {
+        for item in self.network.gossip.on_getdata(peer_id, items) {
+            let message = match item.kind {
+                InventoryKind::Transaction => self.transaction_pool.iter()
+                    .find(|tx| tx.hash() == item.hash)
+                    .map(|tx| NetworkMessage::Transaction(tx.clone())),
+                InventoryKind::Block => self.get_block_by_hash(&item.hash)
+                    .map(|block| NetworkMessage::Block(block.clone())),
+            };
+            if let Some(message) = message {
+                self.network.send_object(peer_id, message).await?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// 处理紧凑区块：用交易池还原区块，缺失的交易按索引向来源对端请求
+    async fn handle_compact_block(&mut self, peer_id: &str, compact: CompactBlock) -> Result<()> {
+        let item = InventoryItem::block(compact.block_hash());
+        self.network.compact.on_received();
+        self.network.gossip.mark_known(peer_id, item);
+        // 多个对端会推送同一区块，重复的紧凑区块直接忽略
+        if self.network.gossip.has_seen(&item) || self.network.compact.is_pending(&item.hash) {
+            return Ok(());
+        }
+        if !self.contains_block(&compact.header.previous_hash) {
+            if !self.network.block_sync.is_syncing() {
+                self.start_sync(peer_id, compact.header.height);
+            }
+            return Ok(());
+        }
+        
+        let partial = match compact.reconstruct(&self.transaction_pool) {
+            Ok(partial) => partial,
+            Err(e) => {
+                log::debug!("Invalid compact block from {}: {}", peer_id, e);
+                self.network.report_peer(peer_id, Misbehavior::ProtocolViolation).await;
+                return Ok(());
+            }
+        };
+        if partial.is_complete() {
+            self.network.compact.on_reconstructed();
+            return self.finish_compact_block(peer_id, partial).await;
+        }
+        let missing = self.network.compact.add_pending(peer_id, partial);
+        self.network.request_block_transactions(peer_id, item.hash, missing).await
+    }
+    
+    /// 回复 getblocktxn：按索引发送区块中的交易，索引越界视为违反协议
+    async fn serve_block_transactions(&mut self, peer_id: &str, block_hash: [u8; 32], indexes: &[u32]) -> Result<()> {
+        let Some(block) = self.get_block_by_hash(&block_hash) else {
+            return Ok(());
+        };
+        let transactions: Option<Vec<Transaction>> = indexes.iter()
+            .map(|index| block.transactions.get(*index as usize).cloned())
+            .collect();
+        let Some(transactions) = transactions else {
+            self.network.report_peer(peer_id, Misbehavior::ProtocolViolation).await;
+            return Ok(());
+        };
+        let message = NetworkMessage::BlockTxn(BlockTxnMessage {
+            block_hash,
+            transactions,
+            peer_id: self.network.p2p_network.get_peer_id(),
+        });
+        self.network.send_object(peer_id, message).await
+    }
+    
+    /// 用对端补发的交易补齐紧凑区块
+    async fn handle_block_transactions(&mut self, peer_id: &str, block_hash: [u8; 32], transactions: Vec<Transaction>) -> Result<()> {
+        let Some(mut partial) = self.network.compact.take_pending(peer_id, &block_hash) else {
+            // 未请求过或已通过其他途径收到
+            self.network.report_peer(peer_id, Misbehavior::Spam).await;
+            return Ok(());
+        };
+        if let Err(e) = partial.fill(transactions) {
+            log::debug!("Invalid block transactions from {}: {}", peer_id, e);
+            self.network.report_peer(peer_id, Misbehavior::ProtocolViolation).await;
+            return Ok(());
+        }
+        self.finish_compact_block(peer_id, partial).await
+    }
+    
+    /// 导入还原的区块；Merkle 根不符说明短 ID 碰撞，改为请求完整区块
+    async fn finish_compact_block(&mut self, peer_id: &str, partial: PartialBlock) -> Result<()> {
+        let block_hash = partial.block_hash();
+        match partial.into_block() {
+            Some(block) if block.has_valid_merkle_root() => {
+                if self.network.gossip.on_object(peer_id, InventoryItem::block(block_hash)) {
+                    self.handle_relayed_block(peer_id, block).await?;
+                }
+                Ok(())
+            }
+            _ => self.network.request_full_block(peer_id, block_hash).await,
         }
     }
     
+    /// 导入广播收到的区块，验证通过后继续转发；父区块未知时向该对端发起同步
+    async fn handle_relayed_block(&mut self, peer_id: &str, block: Block) -> Result<()> {
+        if !self.contains_block(&block.header.previous_hash) {
+            if !self.network.block_sync.is_syncing() {
+                self.start_sync(peer_id, block.header.height);
+            }
+            return Ok(());
+        }
+        match self.import_block(block.clone()).await {
+            Ok(()) => {
+                self.network.peer_manager.reward_peer(peer_id).await;
+                self.network.broadcast_block(&block).await
+            }
+            Err(e) => {
+                log::debug!("Rejected block from {}: {}", peer_id, e);
+                self.network.report_peer(peer_id, Misbehavior::InvalidBlock).await;
+                Ok(())
+            }
+        }
+    }
+    
+    /// 处理验证者发来的共识协议消息：记录提案附带的区块，交给共识引擎并执行返回的动作
+    async fn handle_consensus_message(&mut self, peer_id: &str, message: ConsensusMessage) -> Result<()> {
+        if let Some(block) = message.block {
+            if block.header.hash() != block.block_hash || !block.has_valid_merkle_root() {
+                self.network.report_peer(peer_id, Misbehavior::InvalidBlock).await;
+                return Ok(());
+            }
+            let height = self.current_height;
+            self.proposals.retain(|_, proposal| proposal.header.height > height);
+            if block.header.height > height && self.proposals.len() < MAX_PROPOSALS {
+                self.proposals.insert(block.block_hash, block);
+            }
+        }
+        
+        match self.consensus.handle_message(&message.payload) {
+            Ok(actions) => self.apply_consensus_actions(actions, None).await,
+            Err(e) => {
+                log::debug!("Rejected consensus message from {}: {}", peer_id, e);
+                self.network.report_peer(peer_id, Misbehavior::ProtocolViolation).await;
+                Ok(())
+            }
+        }
+    }
+    
+    /// 共识视图超时，由节点定时器驱动（如按 `HotStuff::current_timeout` 的间隔调用）
+    pub async fn on_consensus_timeout(&mut self) -> Result<()> {
+        let actions = self.consensus.on_timeout()?;
+        self.apply_consensus_actions(actions, None).await
+    }
+    
+    /// 执行共识引擎返回的协议动作，`proposal` 为本节点刚提出的区块，随广播的提案一起发送
+    async fn apply_consensus_actions(&mut self, actions: Vec<ConsensusAction>, proposal: Option<&Block>) -> Result<()> {
+        for action in actions {
+            match action {
+                ConsensusAction::Broadcast(payload) => {
+                    self.network.broadcast_consensus(payload, proposal.cloned()).await?;
+                }
+                ConsensusAction::SendTo { message, .. } => self.network.broadcast_consensus(message, None).await?,
+                ConsensusAction::Certify { block_hash, view, signatures } => {
+                    self.certify_proposal(block_hash, view, signatures).await?;
+                }
+                ConsensusAction::Finalize { height, block_hash } => self.apply_consensus_finality(height, block_hash)?,
+            }
+        }
+        Ok(())
+    }
+    
+    /// 把法定人数证书写入提案区块并导入，导入后转发给其他节点
+    async fn certify_proposal(&mut self, block_hash: [u8; 32], view: u64, signatures: BTreeMap<String, Vec<u8>>) -> Result<()> {
+        // 没有提案区块时等待已获得证书的区块经转发或同步到达
+        let Some(mut block) = self.proposals.remove(&block_hash) else {
+            return Ok(());
+        };
+        let mut seal = block.seal().cloned().unwrap_or_default();
+        seal.commit_view = view;
+        seal.commit_signatures.extend(signatures);
+        block.set_seal(seal);
+        
+        if let Err(e) = self.import_block(block.clone()).await {
+            log::warn!("Failed to import certified block {}: {}", hex::encode(block_hash), e);
+            return Ok(());
+        }
+        self.network.broadcast_block(&block).await
+    }
+    
+    /// 执行共识引擎的最终确认；区块尚未导入时跳过，导入时由 `is_final` 再次确认
+    fn apply_consensus_finality(&mut self, height: u64, block_hash: [u8; 32]) -> Result<()> {
+        if height > self.current_height {
+            return Ok(());
+        }
+        self.finalize_block(height, block_hash)
+    }
+    
+    /// 校验并记录同步响应
+    async fn handle_sync_response(&mut self, peer_id: &str, response: SyncResponse) -> Result<()> {
+        let to_error = |e: crate::components::ComponentError| BlockchainError::NetworkError(e.to_string());
+        match response {
+            SyncResponse::Headers(headers) => {
+                // 第一批区块头必须接在本地已知的区块上
+                if let Some(first) = headers.first()
+                    && !self.network.block_sync.has_headers()
+                {
+                    let parent_height = first.height.checked_sub(1);
+                    if parent_height.is_none() || self.main_chain_position(&first.previous_hash) != parent_height {
+                        return Err(BlockchainError::InvalidBlock("区块头未接在本地链上".to_string()));
+                    }
+                }
+                for header in &headers {
+                    if !self.validate_header(header).await? {
+                        return Err(BlockchainError::InvalidBlock(format!("区块头封装无效，高度 {}", header.height)));
+                    }
+                }
+                self.network.block_sync.on_headers(peer_id, headers).map_err(to_error)?;
+            }
+            SyncResponse::Bodies(blocks) => {
+                self.network.block_sync.on_blocks(blocks).map_err(to_error)?;
+            }
+        }
+        Ok(())
+    }
+    
     /// 获取区块链高度
     pub fn get_height(&self) -> u64 {
         self.current_height
@@ -326,23 +1352,608 @@ impl Blockchain {
     pub fn adjust_difficulty(&mut self, new_difficulty: u32) {
         self.difficulty = new_difficulty;
     }
-    
-    /// 简单哈希计算
-    fn simple_hash(&self, header: &BlockHeader) -> [u8; 32] {
-        // 简化的哈希计算
-        use sha2::Digest;
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(&header.version.to_le_bytes());
-        hasher.update(&header.previous_hash);
-        hasher.update(&header.merkle_root);
-        hasher.update(&header.timestamp.to_le_bytes());
-        hasher.update(&header.difficulty.to_le_bytes());
-        hasher.update(&header.nonce.to_le_bytes());
-        hasher.update(&header.height.to_le_bytes());
-        
-        let hash = hasher.finalize();
-        let mut result = [0u8; 32];
-        result.copy_from_slice(&hash);
-        result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+    use crate::components::consensus::{HotStuff, HotStuffConfig, ValidatorSet, PBFT};
+    use crate::core::{TxInput, TxOutput, address_from_public_key};
+    use crate::core::transaction::OutPoint;
+
+    async fn new_chain() -> Blockchain {
+        let mut chain = Blockchain::new(1, Block::create_genesis_block().unwrap());
+        chain.network.initialize().await.unwrap();
+        chain
+    }
+
+    /// 由种子确定的 secp256k1 账户：(私钥, 地址)
+    fn account(seed: u8) -> ([u8; 32], String) {
+        let private_key = [seed; 32];
+        let public_key = SignatureEngine::new().derive_public_key(&private_key, "ecdsa").unwrap();
+        (private_key, address_from_public_key(&public_key))
+    }
+
+    /// 种子账户花费指定输出点向 `to` 转账的已签名交易
+    fn transfer(from: u8, to: &str, amount: u64, outpoint: u8) -> Transaction {
+        let (private_key, address) = account(from);
+        let mut tx = Transaction::new(
+            vec![TxInput::new(OutPoint::new([outpoint; 32], 0), amount, address)],
+            vec![TxOutput::new(amount, to.to_string())],
+        );
+        tx.sign(&private_key).unwrap();
+        tx
+    }
+
+    /// 在指定父区块上用工作量证明封装一个空区块，空区块不改变状态，承诺父区块之后的状态根
+    async fn sealed_block(previous_hash: [u8; 32], height: u64, difficulty: u32, state_root: [u8; 32]) -> Block {
+        let mut block = Block::new(previous_hash, Vec::new(), height, difficulty).unwrap();
+        block.set_state_root(state_root);
+        ProofOfWork::new(1).mine_block(&mut block).await.unwrap();
+        block
+    }
+
+    #[tokio::test]
+    async fn test_mine_block_through_consensus() {
+        let mut chain = new_chain().await;
+        let block = chain.mine_block().await.unwrap();
+
+        assert_eq!(chain.get_height(), 1);
+        assert_eq!(block.header.previous_hash, chain.genesis_block.block_hash);
+        assert!(chain.consensus.validate_block(&block).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reorganize_to_heavier_fork() {
+        let mut chain = new_chain().await;
+        chain.mine_block().await.unwrap();
+        let orphaned = chain.mine_block().await.unwrap();
+
+        // 高度更低但累计工作量更大的分支
+        let heavy = sealed_block(chain.genesis_block.block_hash, 1, 6, chain.state.compute_state_root()).await;
+        chain.import_block(heavy.clone()).await.unwrap();
+
+        assert_eq!(chain.get_height(), 1);
+        assert_eq!(chain.get_latest_block().unwrap().block_hash, heavy.block_hash);
+        assert!(chain.fork_blocks.contains_key(&orphaned.block_hash));
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_snapshot_and_requeues_valid_transactions() {
+        let mut chain = new_chain().await;
+        let (_, alice) = account(1);
+        let (_, bob) = account(2);
+        chain.state.set_balance(&alice, 100).await.unwrap();
+        let first = transfer(1, &bob, 60, 1);
+        chain.add_transaction(first.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        chain.add_transaction(transfer(2, "carol", 50, 2)).await.unwrap();
+        chain.mine_block().await.unwrap();
+        assert_eq!(chain.state.get_balance("carol").await.unwrap(), 50);
+
+        // 不由区块产生的余额随快照恢复；依赖被回滚区块的交易不再放回交易池
+        let removed = chain.rollback_to(0).await.unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), 100);
+        assert_eq!(chain.state.get_balance(&bob).await.unwrap(), 0);
+        assert_eq!(chain.transaction_pool.iter().map(Transaction::hash).collect::<Vec<_>>(), vec![first.hash()]);
+
+        chain.mine_block().await.unwrap();
+        assert_eq!(chain.state.get_balance(&bob).await.unwrap(), 60);
+    }
+
+    #[tokio::test]
+    async fn test_mine_block_undoes_partially_applied_transaction() {
+        let mut chain = new_chain().await;
+        let (private_key, alice) = account(1);
+        chain.state.set_balance(&alice, 100).await.unwrap();
+        let root = chain.state.get_state_root();
+
+        // 每个输入单独都不超过余额，第二个输入扣款时失败，第一个输入的扣款随之撤销
+        let mut overdraw = Transaction::new(
+            vec![
+                TxInput::new(OutPoint::new([1u8; 32], 0), 60, alice.clone()),
+                TxInput::new(OutPoint::new([2u8; 32], 0), 60, alice.clone()),
+            ],
+            vec![TxOutput::new(120, "bob".to_string())],
+        );
+        overdraw.sign(&private_key).unwrap();
+        chain.add_transaction(overdraw).await.unwrap();
+        chain.add_transaction(transfer(1, "carol", 30, 3)).await.unwrap();
+
+        let block = chain.mine_block().await.unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), 70);
+        assert_eq!(chain.state.get_balance("carol").await.unwrap(), 30);
+        assert!(!chain.state.balances.contains_key("bob"));
+        assert_ne!(chain.state.get_state_root(), root);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_blocks_with_invalid_transactions() {
+        let mut chain = new_chain().await;
+        let (_, alice) = account(1);
+        chain.state.set_balance(&alice, 100).await.unwrap();
+        let genesis_hash = chain.genesis_block.block_hash;
+        let parent_state = chain.state.clone();
+        let sealed = |transactions: Vec<Transaction>| {
+            let mut state = parent_state.clone();
+            async move {
+                let mut block = Block::new(genesis_hash, transactions, 1, 1).unwrap();
+                let _ = Blockchain::execute_transactions(&mut state, &block.transactions, None, &BlockContext::default()).await;
+                block.set_state_root(state.compute_state_root());
+                ProofOfWork::new(1).mine_block(&mut block).await.unwrap();
+                block
+            }
+        };
+
+        // 每笔交易单独都不超过余额，合计超过
+        let overspend = sealed(vec![transfer(1, "bob", 60, 1), transfer(1, "carol", 60, 2)]).await;
+        let error = chain.import_block(overspend).await.unwrap_err();
+        assert!(matches!(error, BlockchainError::InsufficientBalance(_)), "{}", error);
+
+        // 同一输出点在区块内花费两次
+        let double_spend = sealed(vec![transfer(1, "bob", 10, 3), transfer(1, "carol", 10, 3)]).await;
+        assert!(chain.import_block(double_spend).await.is_err());
+
+        // 签名后被改写收款人的交易
+        let mut redirected = transfer(1, "bob", 10, 4);
+        redirected.outputs[0].address = "mallory".to_string();
+        assert!(chain.import_block(sealed(vec![redirected]).await).await.is_err());
+
+        // 交易有效但承诺的状态根与执行结果不符
+        let mut wrong_root = Block::new(genesis_hash, vec![transfer(1, "bob", 60, 1)], 1, 1).unwrap();
+        wrong_root.set_state_root(chain.state.compute_state_root());
+        ProofOfWork::new(1).mine_block(&mut wrong_root).await.unwrap();
+        let error = chain.import_block(wrong_root).await.unwrap_err();
+        assert!(error.to_string().contains("状态根不匹配"), "{}", error);
+
+        assert_eq!(chain.get_height(), 0);
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), 100);
+        let valid = sealed(vec![transfer(1, "bob", 60, 1)]).await;
+        chain.import_block(valid).await.unwrap();
+        assert_eq!(chain.state.get_balance("bob").await.unwrap(), 60);
+    }
+
+    #[tokio::test]
+    async fn test_reorganize_rejects_branch_with_invalid_block() {
+        let mut chain = new_chain().await;
+        chain.state.set_balance("alice", 100).await.unwrap();
+        chain.mine_block().await.unwrap();
+        let tip = chain.mine_block().await.unwrap();
+        let state_root = chain.state.balance_root();
+
+        // 第一个区块有效，第二个区块承诺了错误的余额根但工作量足以触发切换
+        let first = sealed_block(chain.genesis_block.block_hash, 1, 1, chain.state.compute_state_root()).await;
+        chain.import_block(first.clone()).await.unwrap();
+        let mut second = Block::new(first.block_hash, Vec::new(), 2, 8).unwrap();
+        second.set_balance_root([9u8; 32]);
+        second.set_state_root(chain.state.compute_state_root());
+        ProofOfWork::new(1).mine_block(&mut second).await.unwrap();
+        assert!(chain.import_block(second.clone()).await.is_err());
+
+        assert_eq!(chain.get_height(), 2);
+        assert_eq!(chain.chain_head().hash, tip.block_hash);
+        assert_eq!(chain.state.balance_root(), state_root);
+        assert!(chain.fork_blocks.contains_key(&first.block_hash));
+        assert!(!chain.fork_blocks.contains_key(&second.block_hash));
+        assert_eq!(chain.mine_block().await.unwrap().header.height, 3);
+    }
+
+    #[tokio::test]
+    async fn test_reorganize_never_reverts_finalized() {
+        let mut chain = new_chain().await;
+        let first = chain.mine_block().await.unwrap();
+        chain.mine_block().await.unwrap();
+        chain.finalize_block(1, first.block_hash).unwrap();
+
+        let heavy = sealed_block(chain.genesis_block.block_hash, 1, 8, chain.state.compute_state_root()).await;
+        chain.import_block(heavy.clone()).await.unwrap();
+
+        assert_eq!(chain.get_height(), 2);
+        assert_eq!(chain.get_block_by_height(1).unwrap().block_hash, first.block_hash);
+        assert!(chain.fork_blocks.contains_key(&heavy.block_hash));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_votes_finalize_blocks() {
+        use crate::components::consensus::{FinalityConfig, ValidatorSet};
+        use crate::components::cryptography::SignatureEngine;
+
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let (private_key, public_key) = engine.generate_keypair("ed25519").unwrap();
+        let mut validators = ValidatorSet::new();
+        validators.insert("alice".to_string(), 1, public_key);
+
+        let mut chain = new_chain().await;
+        let mut gadget = FinalityGadget::new(
+            FinalityConfig { epoch_length: 2 },
+            validators,
+            chain.genesis_block.block_hash,
+        );
+        gadget.set_signer("alice".to_string(), private_key).unwrap();
+        chain.finality = Some(gadget);
+
+        for _ in 0..4 {
+            chain.mine_block().await.unwrap();
+            if let Some(vote) = chain.cast_checkpoint_vote().unwrap() {
+                chain.on_checkpoint_vote(vote).await.unwrap();
+            }
+        }
+
+        assert_eq!(chain.get_finalized_height(), 2);
+        assert!(chain.cast_checkpoint_vote().unwrap().is_none());
+        assert_eq!(chain.consensus_stats().await.unwrap().last_finalized_height, 2);
+
+        let proof = chain.finality.as_ref().unwrap().finality_proof(2).unwrap();
+        assert_eq!(proof.finalized.block_hash, chain.get_block_by_height(2).unwrap().block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_finalized_fork_checkpoint_reorganizes_chain() {
+        use crate::components::consensus::{Checkpoint, FinalityConfig, ValidatorSet};
+        use crate::components::consensus::validator_set::sign_message;
+        use crate::components::cryptography::SignatureEngine;
+
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let (private_key, public_key) = engine.generate_keypair("ed25519").unwrap();
+        let mut validators = ValidatorSet::new();
+        validators.insert("alice".to_string(), 1, public_key);
+
+        let mut chain = new_chain().await;
+        chain.finality = Some(FinalityGadget::new(
+            FinalityConfig { epoch_length: 2 },
+            validators,
+            chain.genesis_block.block_hash,
+        ));
+        for _ in 0..5 {
+            chain.mine_block().await.unwrap();
+        }
+
+        // 工作量较小的分叉，本身不会被分叉选择采纳；时间戳提前一秒以区别于主链区块
+        let mut fork = Vec::new();
+        let mut previous_hash = chain.genesis_block.block_hash;
+        for height in 1..=4 {
+            let mut block = Block::new(previous_hash, Vec::new(), height, 1).unwrap();
+            block.header.timestamp -= 1;
+            block.set_state_root(chain.state.compute_state_root());
+            ProofOfWork::new(1).mine_block(&mut block).await.unwrap();
+            previous_hash = block.block_hash;
+            fork.push(block);
+        }
+
+        // 投票先于分叉区块到达：创世 -> 分叉纪元 1 -> 分叉纪元 2，最终确认分叉上高度 2 的区块
+        let genesis = Checkpoint::genesis(chain.genesis_block.block_hash);
+        let epoch_1 = Checkpoint { epoch: 1, height: 2, block_hash: fork[1].block_hash };
+        let epoch_2 = Checkpoint { epoch: 2, height: 4, block_hash: fork[3].block_hash };
+        for (source, target) in [(genesis, epoch_1), (epoch_1, epoch_2)] {
+            let signature = sign_message(&private_key, &CheckpointVote::payload(&source, &target)).unwrap();
+            let vote = CheckpointVote { validator: "alice".to_string(), source, target, signature };
+            assert!(chain.on_checkpoint_vote(vote).await.unwrap().is_none());
+        }
+        assert_eq!(chain.get_finalized_height(), 0);
+
+        for block in &fork {
+            chain.import_block(block.clone()).await.unwrap();
+        }
+        assert_eq!(chain.get_finalized_height(), 2);
+        assert_eq!(chain.get_block_by_height(2).unwrap().block_hash, fork[1].block_hash);
+        assert_eq!(chain.finality.as_ref().unwrap().finalized(), epoch_1);
+
+        // 原主链更重，但分叉点低于最终确认高度，不会再切换回去
+        let block = chain.mine_block().await.unwrap();
+        assert_eq!(block.header.previous_hash, chain.blocks[block.header.height as usize - 1].block_hash);
+        assert!(chain.is_finalized(2));
+        assert_eq!(chain.get_block_by_height(2).unwrap().block_hash, fork[1].block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_from_spec() {
+        let spec = ChainSpec::from_json(r#"{
+            "name": "devnet",
+            "network_id": 3,
+            "genesis_timestamp": 0,
+            "consensus": { "type": "proof_of_work", "difficulty": 2 }
+        }"#).unwrap();
+
+        let mut chain = Blockchain::from_spec(&spec).unwrap();
+        chain.network.initialize().await.unwrap();
+        assert_eq!(chain.consensus.name(), "pow");
+        assert_eq!(chain.get_difficulty(), 2);
+
+        let block = chain.mine_block().await.unwrap();
+        assert_eq!(block.header.difficulty, 2);
+    }
+
+    #[tokio::test]
+    async fn test_block_locator_and_headers_after() {
+        let mut chain = new_chain().await;
+        for _ in 0..15 {
+            chain.mine_block().await.unwrap();
+        }
+
+        // 15, 14, ..., 6 连续 10 个，之后步长加倍：4, 0
+        let locator = chain.block_locator();
+        let heights: Vec<u64> = locator.iter()
+            .map(|hash| chain.get_block_by_hash(hash).unwrap().header.height)
+            .collect();
+        assert_eq!(heights, vec![15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 4, 0]);
+
+        let headers = chain.headers_after(&[[9u8; 32], chain.blocks[12].block_hash], 2);
+        assert_eq!(headers.iter().map(|h| h.height).collect::<Vec<_>>(), vec![13, 14]);
+    }
+
+    #[tokio::test]
+    async fn test_headers_first_sync_between_nodes() {
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut server = Blockchain::new(1, genesis.clone());
+        server.network.initialize().await.unwrap();
+        server.network.start(0).await.unwrap();
+        for _ in 0..5 {
+            server.mine_block().await.unwrap();
+        }
+
+        let mut client = Blockchain::new(1, genesis);
+        client.network.initialize().await.unwrap();
+        let port = server.network.p2p_network.get_listen_addr().unwrap().port();
+        let server_id = client.network.p2p_network.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+        client.start_sync(&server_id, 5);
+        assert!(client.sync_progress().syncing);
+
+        for _ in 0..200 {
+            server.poll_network().await.unwrap();
+            client.poll_network().await.unwrap();
+            if !client.sync_progress().syncing {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let progress = client.sync_progress();
+        assert!(!progress.syncing);
+        assert_eq!(progress.validated_height, 5);
+        assert_eq!(progress.downloaded, 5);
+        assert_eq!(client.chain_head().hash, server.chain_head().hash);
+    }
+
+    /// 启动矿工节点并让跟随者连接上它
+    async fn connected_pair() -> (Blockchain, Blockchain) {
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut miner = Blockchain::new(1, genesis.clone());
+        miner.network.initialize().await.unwrap();
+        miner.network.start(0).await.unwrap();
+
+        let mut follower = Blockchain::new(1, genesis);
+        follower.network.initialize().await.unwrap();
+        let port = miner.network.p2p_network.get_listen_addr().unwrap().port();
+        follower.network.p2p_network.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+        // 等待矿工一侧登记连接
+        for _ in 0..100 {
+            if miner.network.p2p_network.get_connection_count().await == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        (miner, follower)
+    }
+
+    /// 启动使用各自共识引擎的验证者节点并两两连接
+    async fn validator_mesh(genesis: &Block, engines: Vec<Box<dyn ConsensusComponent>>) -> Vec<Blockchain> {
+        let mut nodes: Vec<Blockchain> = Vec::new();
+        for consensus in engines {
+            let mut node = Blockchain::with_consensus(1, genesis.clone(), consensus);
+            node.network.initialize().await.unwrap();
+            node.network.start(0).await.unwrap();
+            for peer in &nodes {
+                let port = peer.network.p2p_network.get_listen_addr().unwrap().port();
+                node.network.p2p_network.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+            }
+            nodes.push(node);
+        }
+        for _ in 0..100 {
+            let mut connected = 0;
+            for node in &nodes {
+                connected += node.network.p2p_network.get_connection_count().await;
+            }
+            if connected == nodes.len() * (nodes.len() - 1) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        nodes
+    }
+
+    /// 轮询所有节点的网络消息，直到所有节点达到指定高度
+    async fn poll_until_height(nodes: &mut [Blockchain], height: u64) {
+        for _ in 0..300 {
+            for node in nodes.iter_mut() {
+                node.poll_network().await.unwrap();
+            }
+            if nodes.iter().all(|node| node.get_height() >= height) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pbft_blocks_are_certified_through_the_network() {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let keys: Vec<_> = (0..4).map(|_| engine.generate_keypair("ed25519").unwrap()).collect();
+        let engines = keys.iter()
+            .enumerate()
+            .map(|(i, (private_key, _))| {
+                let mut pbft = PBFT::new(Vec::new());
+                for (j, (_, public_key)) in keys.iter().enumerate() {
+                    pbft.add_validator_with_key(format!("v{}", j), public_key.clone());
+                }
+                pbft.set_signer(format!("v{}", i), private_key.clone()).unwrap();
+                Box::new(pbft) as Box<dyn ConsensusComponent>
+            })
+            .collect();
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut nodes = validator_mesh(&genesis, engines).await;
+        let (_, alice) = account(1);
+        for node in nodes.iter_mut() {
+            node.state.set_balance(&alice, 100).await.unwrap();
+        }
+        let tx = transfer(1, "bob", 60, 1);
+        nodes[1].transaction_pool.push(tx.clone());
+
+        // v1 是高度 1 的主节点：区块先作为提案发出，交易在获得证书前留在交易池
+        let proposal = nodes[1].mine_block().await.unwrap();
+        assert_eq!(nodes[1].get_height(), 0);
+        assert_eq!(nodes[1].transaction_pool.len(), 1);
+        assert!(nodes[0].mine_block().await.is_err());
+
+        poll_until_height(&mut nodes, 1).await;
+        for node in &nodes {
+            let head = node.get_latest_block().unwrap();
+            assert_eq!(head.block_hash, proposal.block_hash);
+            assert!(head.seal().unwrap().commit_signatures.len() >= 3);
+            assert_eq!(node.get_finalized_height(), 1);
+            assert_eq!(node.state.get_balance("bob").await.unwrap(), 60);
+        }
+        assert!(nodes[1].transaction_pool.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hotstuff_drives_blocks_through_the_chain() {
+        let mut engine = SignatureEngine::new();
+        engine.initialize().await.unwrap();
+        let keys: Vec<_> = (0..4).map(|_| engine.generate_keypair("ed25519").unwrap()).collect();
+        let mut validators = ValidatorSet::new();
+        for (i, (_, public_key)) in keys.iter().enumerate() {
+            validators.insert(format!("validator_{}", i), 100, public_key.clone());
+        }
+        let genesis = Block::create_genesis_block().unwrap();
+        let engines = keys.iter()
+            .enumerate()
+            .map(|(i, (private_key, _))| {
+                let mut hotstuff = HotStuff::new(HotStuffConfig::default(), validators.clone(), genesis.block_hash);
+                hotstuff.set_signer(format!("validator_{}", i), private_key.clone()).unwrap();
+                Box::new(hotstuff) as Box<dyn ConsensusComponent>
+            })
+            .collect();
+        let mut nodes = validator_mesh(&genesis, engines).await;
+
+        // 每个高度由当前视图的领导者提出，区块获得 QC 后才加入各节点的链
+        for height in 1..=4u64 {
+            let mut proposed = false;
+            for _ in 0..300 {
+                for (i, node) in nodes.iter_mut().enumerate() {
+                    let leader = node.consensus.select_proposer(&node.chain_head().hash, height).unwrap();
+                    if !proposed && node.get_height() == height - 1 && leader == Some(format!("validator_{}", i)) {
+                        let proposal = node.mine_block().await.unwrap();
+                        assert_eq!(node.get_height(), height - 1);
+                        assert_eq!(proposal.header.height, height);
+                        proposed = true;
+                    }
+                    node.poll_network().await.unwrap();
+                }
+                if nodes.iter().all(|node| node.get_height() == height) {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            assert!(nodes.iter().all(|node| node.get_height() == height));
+        }
+        // 第 4 个区块的 QC 按三链规则最终确认前两个区块
+        for node in &nodes {
+            assert_eq!(node.chain_head().hash, nodes[0].chain_head().hash);
+            assert!(node.get_finalized_height() >= 2);
+        }
+
+        // 不参与投票的节点通过区块头同步追上，区块头按封装中的 QC 验证
+        let observer = HotStuff::new(HotStuffConfig::default(), validators, genesis.block_hash);
+        let mut follower = Blockchain::with_consensus(1, genesis, Box::new(observer));
+        follower.network.initialize().await.unwrap();
+        let port = nodes[0].network.p2p_network.get_listen_addr().unwrap().port();
+        let peer_id = follower.network.p2p_network.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+        follower.start_sync(&peer_id, 4);
+        for _ in 0..200 {
+            nodes[0].poll_network().await.unwrap();
+            follower.poll_network().await.unwrap();
+            if !follower.sync_progress().syncing {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(follower.chain_head().hash, nodes[0].chain_head().hash);
+        assert!(follower.get_finalized_height() >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_block_gossip_compact_relay() {
+        let (mut miner, mut follower) = connected_pair().await;
+
+        let block = miner.mine_block().await.unwrap();
+        for _ in 0..200 {
+            miner.poll_network().await.unwrap();
+            follower.poll_network().await.unwrap();
+            if follower.get_height() == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(follower.chain_head().hash, block.block_hash);
+
+        // 跟随者不会把区块公告回来源节点
+        for _ in 0..5 {
+            miner.poll_network().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(miner.network.gossip.stats().inv_received, 0);
+        // 区块以紧凑区块直接推送，不再经过 inv/getdata
+        assert_eq!(follower.network.gossip.stats().requested, 0);
+        assert_eq!(follower.network.compact.stats().reconstructed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_new_tip_cancels_sealing() {
+        let (mut miner, mut follower) = connected_pair().await;
+        // 跟随者的难度高到无法在测试期间找到解，只能由对端的新区块取消
+        follower.adjust_difficulty(64);
+
+        let (sealed, mined) = tokio::join!(follower.mine_block(), miner.mine_block());
+        assert!(sealed.is_err());
+        let block = mined.unwrap();
+        // 封装被取消，对端的区块随暂存消息导入
+        assert_eq!(follower.chain_head().hash, block.block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_compact_block_requests_missing_transactions() {
+        let (mut miner, mut follower) = connected_pair().await;
+        let transactions: Vec<Transaction> = (1..=3u8).map(|seed| transfer(seed, "receiver", 10, seed)).collect();
+        for chain in [&mut miner, &mut follower] {
+            for seed in 1..=3u8 {
+                chain.state.set_balance(&account(seed).1, 100).await.unwrap();
+            }
+        }
+        // 跟随者的交易池里只有前两笔交易
+        miner.transaction_pool.extend(transactions.iter().cloned());
+        follower.transaction_pool.extend(transactions[..2].iter().cloned());
+
+        let block = miner.mine_block().await.unwrap();
+        assert_eq!(block.transactions.len(), 3);
+        for _ in 0..200 {
+            miner.poll_network().await.unwrap();
+            follower.poll_network().await.unwrap();
+            if follower.get_height() == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(follower.chain_head().hash, block.block_hash);
+
+        let stats = follower.network.compact.stats();
+        assert_eq!(stats.round_trips, 1);
+        assert_eq!(stats.transactions_requested, 1);
+        assert_eq!(stats.fallbacks, 0);
     }
 }
@@ -0,0 +1,291 @@
+// 链规范
+// 描述网络参数、创世区块和共识引擎，所有节点从同一份链规范启动
+
+use serde::{Serialize, Deserialize};
+
+use crate::core::{Block, Result, BlockchainError};
+use crate::components::network::Transport;
+use crate::components::consensus::{
+    ConsensusComponent, ProofOfWork, ProofOfStake, DelegatedProofOfStake, PBFT, HotStuff,
+    HotStuffConfig, ValidatorSet, FinalityGadget, FinalityConfig, ProofOfAuthority, PoaConfig,
+};
+
+/// 链规范
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainSpec {
+    /// 链名称
+    pub name: String,
+
+    /// 网络ID
+    pub network_id: u32,
+
+    /// 创世区块时间戳（固定值，保证各节点创世哈希一致）
+    pub genesis_timestamp: u64,
+
+    /// 共识引擎配置
+    pub consensus: ConsensusSpec,
+
+    /// 最长链共识（PoW/PoS/DPoS）之上的检查点最终性，省略表示不启用
+    #[serde(default)]
+    pub finality: Option<FinalitySpec>,
+
+    /// 节点发现的引导节点（UDP 地址，如 "seed.example.org:30303"）
+    #[serde(default)]
+    pub bootstrap_nodes: Vec<String>,
+
+    /// 对端连接的传输协议（"tcp" 或 "quic"），省略时使用 TCP
+    #[serde(default)]
+    pub transport: Transport,
+}
+
+/// 最终性小工具配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalitySpec {
+    /// 纪元长度（区块数）
+    pub epoch_length: u64,
+
+    /// 参与检查点投票的验证者
+    pub validators: Vec<ValidatorSpec>,
+}
+
+/// 共识引擎配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConsensusSpec {
+    /// 工作量证明
+    ProofOfWork {
+        difficulty: u32,
+    },
+
+    /// 权益证明
+    ProofOfStake {
+        min_stake: u64,
+        validators: Vec<ValidatorSpec>,
+    },
+
+    /// 委托权益证明（`stake` 为初始票数）
+    DelegatedProofOfStake {
+        max_delegates: usize,
+        delegates: Vec<ValidatorSpec>,
+    },
+
+    /// 实用拜占庭容错
+    Pbft {
+        validators: Vec<ValidatorSpec>,
+    },
+
+    /// HotStuff BFT
+    HotStuff {
+        validators: Vec<ValidatorSpec>,
+    },
+
+    /// 权威证明（许可链），权威节点可通过出块投票增删
+    ProofOfAuthority {
+        step_duration_secs: u64,
+        authorities: Vec<ValidatorSpec>,
+    },
+}
+
+/// 创世验证者
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorSpec {
+    /// 验证者地址
+    pub address: String,
+
+    /// 权益数量（BFT 共识可省略）
+    #[serde(default)]
+    pub stake: u64,
+
+    /// 十六进制编码的 Ed25519 公钥
+    pub public_key: String,
+}
+
+impl ValidatorSpec {
+    /// 解码公钥
+    pub fn public_key_bytes(&self) -> Result<Vec<u8>> {
+        hex::decode(&self.public_key).map_err(|e| {
+            BlockchainError::SerializationError(format!("验证者 {} 的公钥无效: {}", self.address, e))
+        })
+    }
+}
+
+impl ChainSpec {
+    /// 从 JSON 解析链规范
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| BlockchainError::SerializationError(format!("链规范解析失败: {}", e)))
+    }
+
+    /// 从文件加载链规范
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::StorageError(format!("读取链规范失败: {}", e)))?;
+        Self::from_json(&json)
+    }
+
+    /// 解析引导节点地址，主机名会被解析为所有对应的 IP
+    pub fn bootstrap_addresses(&self) -> Result<Vec<std::net::SocketAddr>> {
+        use std::net::ToSocketAddrs;
+        let mut addresses = Vec::new();
+        for node in &self.bootstrap_nodes {
+            let resolved = node.to_socket_addrs()
+                .map_err(|e| BlockchainError::NetworkError(format!("引导节点地址 {} 无效: {}", node, e)))?;
+            addresses.extend(resolved);
+        }
+        Ok(addresses)
+    }
+
+    /// 区块难度（非 PoW 共识固定为 1）
+    pub fn difficulty(&self) -> u32 {
+        match &self.consensus {
+            ConsensusSpec::ProofOfWork { difficulty } => *difficulty,
+            _ => 1,
+        }
+    }
+
+    /// 构建创世区块
+    pub fn genesis_block(&self) -> Result<Block> {
+        let mut genesis = Block::new([0u8; 32], Vec::new(), 0, self.difficulty())?;
+        genesis.header.timestamp = self.genesis_timestamp;
+        genesis.set_nonce(0);
+        Ok(genesis)
+    }
+
+    /// 按链规范构建共识引擎
+    pub fn build_consensus(&self, genesis_hash: [u8; 32]) -> Result<Box<dyn ConsensusComponent>> {
+        let engine: Box<dyn ConsensusComponent> = match &self.consensus {
+            ConsensusSpec::ProofOfWork { difficulty } => Box::new(ProofOfWork::new(*difficulty)),
+            ConsensusSpec::ProofOfStake { min_stake, validators } => {
+                let mut pos = ProofOfStake::new(*min_stake);
+                for validator in validators {
+                    pos.add_validator_with_key(
+                        validator.address.clone(),
+                        validator.stake,
+                        validator.public_key_bytes()?,
+                    );
+                }
+                Box::new(pos)
+            }
+            ConsensusSpec::DelegatedProofOfStake { max_delegates, delegates } => {
+                let mut dpos = DelegatedProofOfStake::new(*max_delegates);
+                for delegate in delegates {
+                    dpos.register_delegate_key(delegate.address.clone(), delegate.public_key_bytes()?);
+                    dpos.vote_for_delegate(delegate.address.clone(), delegate.stake);
+                }
+                Box::new(dpos)
+            }
+            ConsensusSpec::Pbft { validators } => {
+                let mut pbft = PBFT::new(Vec::new());
+                for validator in validators {
+                    pbft.add_validator_with_key(validator.address.clone(), validator.public_key_bytes()?);
+                }
+                Box::new(pbft)
+            }
+            ConsensusSpec::HotStuff { validators } => {
+                let mut set = ValidatorSet::new();
+                for validator in validators {
+                    set.insert(validator.address.clone(), validator.stake.max(1), validator.public_key_bytes()?);
+                }
+                Box::new(HotStuff::new(HotStuffConfig::default(), set, genesis_hash))
+            }
+            ConsensusSpec::ProofOfAuthority { step_duration_secs, authorities } => {
+                let mut set = ValidatorSet::new();
+                for authority in authorities {
+                    set.insert(authority.address.clone(), 1, authority.public_key_bytes()?);
+                }
+                let config = PoaConfig { step_duration_secs: *step_duration_secs };
+                Box::new(ProofOfAuthority::new(config, set, genesis_hash))
+            }
+        };
+        Ok(engine)
+    }
+
+    /// 按链规范构建最终性小工具
+    pub fn build_finality_gadget(&self, genesis_hash: [u8; 32]) -> Result<Option<FinalityGadget>> {
+        let Some(finality) = &self.finality else {
+            return Ok(None);
+        };
+
+        let mut validators = ValidatorSet::new();
+        for validator in &finality.validators {
+            validators.insert(validator.address.clone(), validator.stake.max(1), validator.public_key_bytes()?);
+        }
+        let config = FinalityConfig { epoch_length: finality.epoch_length };
+        Ok(Some(FinalityGadget::new(config, validators, genesis_hash)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POS_SPEC: &str = r#"{
+        "name": "testnet",
+        "network_id": 7,
+        "genesis_timestamp": 1700000000,
+        "consensus": {
+            "type": "proof_of_stake",
+            "min_stake": 10,
+            "validators": [
+                { "address": "alice", "stake": 100, "public_key": "00" },
+                { "address": "bob", "stake": 50, "public_key": "01" }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_and_build() {
+        let spec = ChainSpec::from_json(POS_SPEC).unwrap();
+        assert_eq!(spec.network_id, 7);
+        assert_eq!(spec.difficulty(), 1);
+
+        let engine = spec.build_consensus([0u8; 32]).unwrap();
+        assert_eq!(engine.name(), "pos");
+        assert!(spec.bootstrap_nodes.is_empty());
+        assert_eq!(spec.transport, Transport::Tcp);
+
+        let json = POS_SPEC.replacen("\"network_id\"", "\"transport\": \"carrier-pigeon\", \"network_id\"", 1);
+        assert!(ChainSpec::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_addresses() {
+        let mut spec = ChainSpec::from_json(POS_SPEC).unwrap();
+        spec.bootstrap_nodes = vec!["127.0.0.1:30303".to_string()];
+        assert_eq!(spec.bootstrap_addresses().unwrap(), vec!["127.0.0.1:30303".parse().unwrap()]);
+
+        spec.bootstrap_nodes.push("not an address".to_string());
+        assert!(spec.bootstrap_addresses().is_err());
+    }
+
+    #[test]
+    fn test_genesis_is_deterministic() {
+        let spec = ChainSpec::from_json(POS_SPEC).unwrap();
+        let a = spec.genesis_block().unwrap();
+        let b = spec.genesis_block().unwrap();
+        assert_eq!(a.block_hash, b.block_hash);
+        assert_eq!(a.header.timestamp, 1700000000);
+    }
+
+    #[test]
+    fn test_build_poa() {
+        let spec = ChainSpec::from_json(r#"{
+            "name": "private",
+            "network_id": 9,
+            "genesis_timestamp": 0,
+            "consensus": {
+                "type": "proof_of_authority",
+                "step_duration_secs": 2,
+                "authorities": [{ "address": "alice", "public_key": "00" }]
+            }
+        }"#).unwrap();
+
+        assert_eq!(spec.build_consensus([0u8; 32]).unwrap().name(), "poa");
+    }
+
+    #[test]
+    fn test_invalid_public_key() {
+        let spec = ChainSpec::from_json(&POS_SPEC.replace("\"00\"", "\"zz\"")).unwrap();
+        assert!(spec.build_consensus([0u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// 合约交易
+//
+// 合约的部署和调用作为普通交易打包进区块：`Transaction::contract` 携带 `ContractTransaction`，
+// 执行区块时交给链上配置的 `ContractExecutor`（启用 `smart-contracts` 特性时由 `SmartContractEngine` 实现）。
+// 合约代码和存储保存在 `State::contract_states` 中，因此受状态根覆盖，并随 `StateStorage` 一起保存。
+//
+// 合约交易和普通交易一样用 secp256k1 私钥签名（`Transaction::sign`）：`sender` 必须是由
+// `public_key` 推导的地址，签名覆盖交易哈希。余额和 nonce 都以该地址为键。
+//
+// 无论执行成功与否，发送方都按 `gas_used × gas_price` 支付费用（费用销毁）；单笔交易的
+// `gas_limit` 不超过 `MAX_TX_GAS`，一个区块中合约交易的 `gas_limit` 之和不超过 `BLOCK_GAS_LIMIT`，
+// 因此执行一个区块的工作量有上限。
+use serde::{Serialize, Deserialize};
+use crate::core::{BlockchainError, Result, State};
+use crate::smart_contracts::abi::ContractABI;
+
+/// 单笔合约交易的 gas 上限
+pub const MAX_TX_GAS: u64 = 10_000_000;
+
+/// 一个区块中合约交易 `gas_limit` 之和的上限
+pub const BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+/// 每笔合约交易的固有 gas，在执行前就失败的交易同样支付
+pub const TX_BASE_GAS: u64 = 21_000;
+
+/// 部署和升级提议按代码字节收取的 gas
+pub const CODE_BYTE_GAS: u64 = 200;
+
+/// 最低 gas 价格
+pub const MIN_GAS_PRICE: u64 = 1;
+
+/// 合约交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractTransaction {
+    /// 发送方地址，由 `public_key` 推导（见 `address_from_public_key`）
+    pub sender: String,
+
+    /// 发送方 nonce，必须等于状态中的当前值；部署交易以它派生合约地址
+    pub nonce: u64,
+
+    /// 转给合约的金额
+    pub value: u64,
+
+    /// 执行 gas 上限（含固有 gas），不超过 `MAX_TX_GAS`
+    pub gas_limit: u64,
+
+    /// 每单位 gas 的价格，不低于 `MIN_GAS_PRICE`
+    #[serde(default)]
+    pub gas_price: u64,
+
+    /// 部署或调用
+    pub action: ContractAction,
+
+    /// 发送方公钥
+    #[serde(default)]
+    pub public_key: Vec<u8>,
+
+    /// 发送方对交易哈希的签名，不参与哈希
+    #[serde(default)]
+    pub signature: Vec<u8>,
+}
+
+/// 合约交易的操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ContractAction {
+    /// 部署合约：`evm` 为 true 时 `code` 为 EVM 部署字节码，否则为 WebAssembly 模块；
+    /// 给出 `salt` 时合约地址由发送方、盐和代码决定，与 nonce 无关
+    Deploy {
+        code: Vec<u8>,
+        interface: ContractABI,
+        evm: bool,
+        salt: Option<[u8; 32]>,
+    },
+
+    /// 调用合约方法
+    Call {
+        contract: String,
+        method: String,
+        params: Vec<u8>,
+    },
+
+    /// 合约升级治理操作，交易发送方为提议者或批准者
+    Upgrade {
+        contract: String,
+        operation: UpgradeOperation,
+    },
+}
+
+/// 合约升级的治理操作，时间以所在区块的时间戳为准
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UpgradeOperation {
+    /// 提议替换为新代码和接口，回执输出为提议编号（小端 u64）
+    Propose { code: Vec<u8>, interface: ContractABI },
+    /// 批准提议，`signature` 为批准者登记的公钥对 `approval_digest` 的签名
+    Approve { id: u64, signature: Vec<u8> },
+    /// 在取消窗口内取消提议
+    Cancel { id: u64 },
+    /// 执行已获批准且时间锁到期的提议
+    Execute { id: u64 },
+}
+
+/// 合约交易回执
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractReceipt {
+    /// 交易哈希
+    pub tx_hash: [u8; 32],
+
+    /// 是否执行成功；失败的交易仍然打包，递增 nonce 并支付 gas 费用，其余修改全部回滚
+    pub success: bool,
+
+    /// 部署交易创建的合约地址
+    pub contract_address: Option<String>,
+
+    /// 返回数据
+    pub output: Vec<u8>,
+
+    /// 消耗的 gas（含固有 gas），发送方为此支付 `gas_used × gas_price`
+    pub gas_used: u64,
+
+    /// 失败原因
+    pub error: Option<String>,
+}
+
+/// 执行合约交易时的区块信息
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockContext {
+    /// 区块高度
+    pub height: u64,
+
+    /// 区块时间戳
+    pub timestamp: u64,
+}
+
+/// 合约执行器：在区块执行过程中处理合约交易
+///
+/// 执行器可以缓存合约（例如已编译的模块），缓存对应它上次执行或 `restore` 后的状态；
+/// 在其他状态上执行之前需要先调用 `restore`。
+pub trait ContractExecutor: Send + Sync {
+    /// 在 `state` 上执行合约交易
+    ///
+    /// 交易本身无效（nonce 不符、余额不足以支付金额和最高 gas 费用）时返回错误且不修改 `state`，
+    /// 包含它的区块随之无效；合约执行失败不是错误，记入回执
+    fn execute(
+        &mut self,
+        state: &mut State,
+        tx_hash: [u8; 32],
+        transaction: &ContractTransaction,
+        block: &BlockContext,
+    ) -> Result<ContractReceipt>;
+    
+    /// 按 `state` 重新载入执行器缓存的合约，例如链回滚或丢弃试执行的状态之后
+    fn restore(&mut self, state: &State) -> Result<()>;
+}
+
+impl ContractTransaction {
+    /// 部署交易
+    pub fn deploy(sender: String, nonce: u64, code: Vec<u8>, interface: ContractABI, value: u64, gas_limit: u64) -> Self {
+        Self {
+            sender,
+            nonce,
+            value,
+            gas_limit,
+            gas_price: MIN_GAS_PRICE,
+            action: ContractAction::Deploy { code, interface, evm: false, salt: None },
+            public_key: Vec::new(),
+            signature: Vec::new(),
+        }
+    }
+
+    /// 调用交易
+    pub fn call(sender: String, nonce: u64, contract: String, method: String, params: Vec<u8>, value: u64, gas_limit: u64) -> Self {
+        Self {
+            sender,
+            nonce,
+            value,
+            gas_limit,
+            gas_price: MIN_GAS_PRICE,
+            action: ContractAction::Call { contract, method, params },
+            public_key: Vec::new(),
+            signature: Vec::new(),
+        }
+    }
+
+    /// 发送方至少需要的余额：转账金额加上按 `gas_limit` 计算的最高费用，溢出时为 `None`
+    pub fn max_cost(&self) -> Option<u64> {
+        self.gas_limit.checked_mul(self.gas_price)?.checked_add(self.value)
+    }
+
+    /// 检查 gas 上限和价格
+    pub fn check_gas(&self) -> Result<()> {
+        if self.gas_limit < TX_BASE_GAS || self.gas_limit > MAX_TX_GAS {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Gas limit {} outside [{}, {}]", self.gas_limit, TX_BASE_GAS, MAX_TX_GAS
+            )));
+        }
+        if self.gas_price < MIN_GAS_PRICE {
+            return Err(BlockchainError::InvalidTransaction(format!("Gas price below {}", MIN_GAS_PRICE)));
+        }
+        if self.max_cost().is_none() {
+            return Err(BlockchainError::InvalidTransaction("Transaction cost overflow".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+// 轻客户端
+//
+// 只下载并验证区块头（哈希链接和共识封装：工作量证明、出块签名或 BFT 提交签名），
+// 不保存区块体和状态。交易是否上链、账户余额多少都向全节点请求 Merkle 证明：
+// 交易证明对应区块头的 `merkle_root`，余额证明对应区块头承诺的 `balance_root`。
+use crate::core::{Block, BlockHeader, ChainSpec, MerkleProof, MerkleTree, Result, BlockchainError, balance_leaf};
+use crate::components::NetworkComponent;
+use crate::components::network::{
+    LightRequest, LightRequestMessage, LightResponse, Misbehavior, NetworkMessage, P2PNetwork, SyncRequest,
+    SyncRequestMessage, SyncResponse,
+};
+use crate::components::network::sync::MAX_HEADERS_PER_RESPONSE;
+use crate::components::consensus::{ChainHead, ConsensusComponent, ProofOfWork};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// 交易包含证明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxInclusionProof {
+    pub block_hash: [u8; 32],
+    /// 交易哈希到区块 Merkle 根的路径
+    pub proof: MerkleProof,
+}
+
+impl TxInclusionProof {
+    /// 为区块中的交易生成证明，交易不在区块中时返回 `None`
+    pub fn generate(block: &Block, tx_hash: &[u8; 32]) -> Result<Option<Self>> {
+        let hashes: Vec<[u8; 32]> = block.transactions.iter().map(|tx| tx.hash()).collect();
+        let Some(index) = hashes.iter().position(|hash| hash == tx_hash) else {
+            return Ok(None);
+        };
+        let proof = MerkleTree::new(hashes)?.generate_proof(index)?;
+        Ok(Some(Self { block_hash: block.block_hash, proof }))
+    }
+
+    /// 证明是否说明该交易包含在给定区块头对应的区块中
+    pub fn verify(&self, header: &BlockHeader, tx_hash: &[u8; 32]) -> bool {
+        self.block_hash == header.block_hash
+            && self.proof.leaf_hash == *tx_hash
+            && self.proof.root_hash == header.merkle_root
+            && self.proof.verify()
+    }
+}
+
+/// 账户余额证明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceProof {
+    /// 证明对应的区块（执行该区块后的余额）
+    pub block_hash: [u8; 32],
+    pub address: String,
+    pub balance: u64,
+    /// 余额叶子到区块头 `balance_root` 的路径
+    pub proof: MerkleProof,
+}
+
+impl BalanceProof {
+    pub fn verify(&self, header: &BlockHeader) -> bool {
+        self.block_hash == header.block_hash
+            && header.balance_root != [0u8; 32]
+            && self.proof.leaf_hash == balance_leaf(&self.address, self.balance)
+            && self.proof.root_hash == header.balance_root
+            && self.proof.verify()
+    }
+}
+
+/// 轻客户端配置
+#[derive(Debug, Clone)]
+pub struct LightClientConfig {
+    /// 等待全节点响应的超时
+    pub request_timeout: Duration,
+    /// 余额证明对应的区块最多落后已验证链头的区块数，为 0 时必须是链头
+    pub max_balance_depth: u64,
+}
+
+impl Default for LightClientConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(10),
+            max_balance_depth: 0,
+        }
+    }
+}
+
+/// 轻客户端
+pub struct LightClient {
+    pub network_id: u32,
+    /// 网络组件，连接全节点后才能同步和请求证明
+    pub network: NetworkComponent,
+    /// 已验证的主链区块头，从创世区块开始
+    headers: Vec<BlockHeader>,
+    /// 区块哈希 -> 主链高度
+    index: HashMap<[u8; 32], u64>,
+    consensus: Box<dyn ConsensusComponent>,
+    config: LightClientConfig,
+    next_request_id: u64,
+}
+
+impl LightClient {
+    /// 创建轻客户端（默认使用工作量证明）
+    pub fn new(network_id: u32, genesis_header: BlockHeader) -> Self {
+        Self::with_consensus(network_id, genesis_header, Box::new(ProofOfWork::new(1)))
+    }
+
+    /// 使用指定共识引擎验证区块头
+    pub fn with_consensus(network_id: u32, genesis_header: BlockHeader, consensus: Box<dyn ConsensusComponent>) -> Self {
+        Self {
+            network_id,
+            network: NetworkComponent::with_p2p_network(P2PNetwork::with_network_id(network_id)),
+            index: HashMap::from([(genesis_header.block_hash, 0)]),
+            headers: vec![genesis_header],
+            consensus,
+            config: LightClientConfig::default(),
+            next_request_id: 0,
+        }
+    }
+
+    /// 按链规范创建轻客户端
+    pub fn from_spec(spec: &ChainSpec) -> Result<Self> {
+        let genesis_block = spec.genesis_block()?;
+        let consensus = spec.build_consensus(genesis_block.block_hash)?;
+        let mut client = Self::with_consensus(spec.network_id, genesis_block.header, consensus);
+        client.network.p2p_network.set_transport(spec.transport);
+        Ok(client)
+    }
+
+    pub fn with_config(mut self, config: LightClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 已验证区块头链的高度
+    pub fn height(&self) -> u64 {
+        self.headers.len() as u64 - 1
+    }
+
+    /// 已验证的最新区块头
+    pub fn best_header(&self) -> &BlockHeader {
+        self.headers.last().expect("header chain contains genesis")
+    }
+
+    /// 按哈希查找主链上已验证的区块头
+    pub fn header_by_hash(&self, block_hash: &[u8; 32]) -> Option<&BlockHeader> {
+        self.index.get(block_hash).map(|height| &self.headers[*height as usize])
+    }
+
+    /// 区块的确认数（包含该区块本身），不在主链上时返回 `None`
+    pub fn confirmations(&self, block_hash: &[u8; 32]) -> Option<u64> {
+        self.index.get(block_hash).map(|height| self.height() - height + 1)
+    }
+
+    /// 从一个已连接的全节点同步区块头，返回同步后的高度
+    pub async fn sync_headers(&mut self) -> Result<u64> {
+        let peer_id = self.pick_peer().await?;
+        loop {
+            let message = NetworkMessage::SyncRequest(SyncRequestMessage {
+                request: SyncRequest::Headers {
+                    locator: self.locator(),
+                    max_headers: MAX_HEADERS_PER_RESPONSE,
+                },
+                peer_id: self.network.p2p_network.get_peer_id(),
+            });
+            self.send(&peer_id, &message).await?;
+            let headers = self.wait_for(&peer_id, |message| match message {
+                NetworkMessage::SyncResponse(response) => match response.response {
+                    SyncResponse::Headers(headers) => Some(headers),
+                    SyncResponse::Bodies(_) => None,
+                },
+                _ => None,
+            }).await?;
+
+            let count = headers.len();
+            if let Err(e) = self.apply_headers(headers).await {
+                self.network.report_peer(&peer_id, Misbehavior::InvalidBlock).await;
+                return Err(e);
+            }
+            if count < MAX_HEADERS_PER_RESPONSE as usize {
+                return Ok(self.height());
+            }
+        }
+    }
+
+    /// 向全节点请求并验证交易包含证明
+    ///
+    /// 返回 `false` 表示全节点无法证明该交易在此区块中；区块不在已验证的区块头链上时先同步区块头
+    pub async fn verify_tx_inclusion(&mut self, tx_hash: [u8; 32], block_hash: [u8; 32]) -> Result<bool> {
+        if self.header_by_hash(&block_hash).is_none() {
+            self.sync_headers().await?;
+        }
+        if self.header_by_hash(&block_hash).is_none() {
+            return Ok(false);
+        }
+
+        let peer_id = self.pick_peer().await?;
+        let response = self.request(&peer_id, LightRequest::TxProof { block_hash, tx_hash }).await?;
+        let LightResponse::TxProof(proof) = response else {
+            return Err(self.reject(&peer_id, "交易证明响应类型错误").await);
+        };
+        let Some(proof) = proof else {
+            return Ok(false);
+        };
+        let header = self.header_by_hash(&block_hash).expect("checked above");
+        if !proof.verify(header, &tx_hash) {
+            return Err(self.reject(&peer_id, "交易包含证明无效").await);
+        }
+        Ok(true)
+    }
+
+    /// 向全节点请求账户余额并用区块头承诺的余额根验证，返回余额和它对应的区块高度
+    ///
+    /// 余额对应证明中的区块（全节点当前的链头），该区块必须在已验证的区块头链上，
+    /// 且落后链头不超过 `max_balance_depth` 个区块，旧区块的余额不被接受
+    pub async fn get_verified_balance(&mut self, address: &str) -> Result<(u64, u64)> {
+        let peer_id = self.pick_peer().await?;
+        let response = self.request(&peer_id, LightRequest::BalanceProof { address: address.to_string() }).await?;
+        let LightResponse::BalanceProof(proof) = response else {
+            return Err(self.reject(&peer_id, "余额证明响应类型错误").await);
+        };
+        let Some(proof) = proof else {
+            return Err(BlockchainError::InvalidState(format!("全节点无法证明账户 {} 的余额", address)));
+        };
+        if self.header_by_hash(&proof.block_hash).is_none() {
+            self.sync_headers().await?;
+        }
+        let Some(header) = self.header_by_hash(&proof.block_hash) else {
+            return Err(BlockchainError::InvalidState("余额证明对应的区块不在已验证的区块头链上".to_string()));
+        };
+        let height = header.height;
+        if proof.address != address || !proof.verify(header) {
+            return Err(self.reject(&peer_id, "余额证明无效").await);
+        }
+        if self.height() - height > self.config.max_balance_depth {
+            return Err(BlockchainError::InvalidState(format!(
+                "余额证明对应的区块高度 {} 落后已验证链头 {} 太多", height, self.height()
+            )));
+        }
+        Ok((proof.balance, height))
+    }
+
+    /// 区块定位器：从链头开始先取 10 个连续区块，之后步长逐次加倍，最后总是包含创世区块
+    fn locator(&self) -> Vec<[u8; 32]> {
+        let mut locator = Vec::new();
+        let mut height = self.height();
+        let mut step = 1;
+        loop {
+            locator.push(self.headers[height as usize].block_hash);
+            if height == 0 {
+                break;
+            }
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            height = height.saturating_sub(step);
+        }
+        locator
+    }
+
+    /// 验证并接入一批连续的区块头；与本地链分叉时按共识的分叉选择规则决定是否切换
+    async fn apply_headers(&mut self, headers: Vec<BlockHeader>) -> Result<()> {
+        let Some(first) = headers.first() else {
+            return Ok(());
+        };
+        let fork_height = match (first.height.checked_sub(1), self.index.get(&first.previous_hash)) {
+            (Some(parent_height), Some(height)) if *height == parent_height => parent_height,
+            _ => return Err(BlockchainError::InvalidBlock("区块头未接在本地链上".to_string())),
+        };
+
+        let mut previous = &self.headers[fork_height as usize];
+        for header in &headers {
+            if header.previous_hash != previous.block_hash || header.height != previous.height + 1 {
+                return Err(BlockchainError::InvalidBlock(format!("区块头不连续，高度 {}", header.height)));
+            }
+            if header.hash() != header.block_hash || !self.consensus.validate_header(header).await? {
+                return Err(BlockchainError::InvalidBlock(format!("区块头封装无效，高度 {}", header.height)));
+            }
+            self.consensus.on_fork_block(&header_shell(header))?;
+            previous = header;
+        }
+
+        let candidate = ChainHead {
+            height: previous.height,
+            hash: previous.block_hash,
+            total_difficulty: self.total_difficulty(fork_height) + headers.iter()
+                .map(|header| ChainHead::block_work(header.difficulty))
+                .sum::<u128>(),
+        };
+        let current = ChainHead {
+            height: self.height(),
+            hash: self.best_header().block_hash,
+            total_difficulty: self.total_difficulty(self.height()),
+        };
+        if !self.consensus.prefer_chain(&current, &candidate) {
+            return Ok(());
+        }
+
+        for removed in self.headers.split_off(fork_height as usize + 1) {
+            self.index.remove(&removed.block_hash);
+        }
+        for header in headers {
+            self.consensus.on_block_imported(&header_shell(&header))?;
+            self.index.insert(header.block_hash, header.height);
+            self.headers.push(header);
+        }
+        Ok(())
+    }
+
+    /// 主链到指定高度的累计工作量
+    fn total_difficulty(&self, height: u64) -> u128 {
+        self.headers.iter()
+            .skip(1)
+            .take(height as usize)
+            .map(|header| ChainHead::block_work(header.difficulty))
+            .sum()
+    }
+
+    async fn request(&mut self, peer_id: &str, request: LightRequest) -> Result<LightResponse> {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let message = NetworkMessage::LightRequest(LightRequestMessage {
+            request_id,
+            request,
+            peer_id: self.network.p2p_network.get_peer_id(),
+        });
+        self.send(peer_id, &message).await?;
+        self.wait_for(peer_id, |message| match message {
+            NetworkMessage::LightResponse(response) if response.request_id == request_id => Some(response.response),
+            _ => None,
+        }).await
+    }
+
+    async fn send(&mut self, peer_id: &str, message: &NetworkMessage) -> Result<()> {
+        self.network.p2p_network.send_message(peer_id, message).await
+            .map_err(|e| BlockchainError::NetworkError(format!("Failed to send {} to {}: {}", message.type_name(), peer_id, e)))
+    }
+
+    /// 等待对端的匹配消息，其他消息（区块广播等）轻客户端不处理，直接丢弃
+    async fn wait_for<T>(&mut self, peer_id: &str, mut matches: impl FnMut(NetworkMessage) -> Option<T>) -> Result<T> {
+        let deadline = tokio::time::Instant::now() + self.config.request_timeout;
+        loop {
+            match tokio::time::timeout_at(deadline, self.network.next_message()).await {
+                Ok(Some((from, message))) if from == peer_id => {
+                    if let Some(value) = matches(message) {
+                        return Ok(value);
+                    }
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => return Err(BlockchainError::NetworkError("Network inbox closed".to_string())),
+                Err(_) => return Err(BlockchainError::NetworkError(format!("Request to {} timed out", peer_id))),
+            }
+        }
+    }
+
+    async fn pick_peer(&self) -> Result<String> {
+        self.network.p2p_network.get_peer_ids().await
+            .into_iter()
+            .next()
+            .ok_or_else(|| BlockchainError::NetworkError("No connected full node".to_string()))
+    }
+
+    /// 对端提供了无效证明：记为违规并返回错误
+    async fn reject(&mut self, peer_id: &str, reason: &str) -> BlockchainError {
+        self.network.report_peer(peer_id, Misbehavior::ProtocolViolation).await;
+        BlockchainError::InvalidState(format!("{}: {}", reason, peer_id))
+    }
+}
+
+/// 只有区块头的区块，供共识引擎验证封装
+fn header_shell(header: &BlockHeader) -> Block {
+    Block {
+        header: header.clone(),
+        transactions: Vec::new(),
+        merkle_root: header.merkle_root,
+        block_hash: header.block_hash,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+    use crate::core::{Blockchain, Transaction, TxInput, TxOutput, address_from_public_key};
+    use crate::core::transaction::OutPoint;
+
+    const ALICE_KEY: [u8; 32] = [1u8; 32];
+
+    fn alice() -> String {
+        address_from_public_key(&SignatureEngine::new().derive_public_key(&ALICE_KEY, "ecdsa").unwrap())
+    }
+
+    fn transfer(seed: u8, amount: u64) -> Transaction {
+        let input = TxInput::new(OutPoint::new([seed; 32], 0), amount, alice());
+        let mut tx = Transaction::new(vec![input], vec![TxOutput::new(amount, "bob".to_string())]);
+        tx.sign(&ALICE_KEY).unwrap();
+        tx
+    }
+
+    /// 启动一个已挖出 3 个区块的全节点，并让轻客户端连接上它
+    async fn full_node_and_client() -> (Blockchain, LightClient, Transaction) {
+        let genesis = Block::create_genesis_block().unwrap();
+        let mut full_node = Blockchain::new(1, genesis.clone());
+        full_node.network.initialize().await.unwrap();
+        full_node.network.start(0).await.unwrap();
+        full_node.state.set_balance(&alice(), 100).await.unwrap();
+
+        let tx = transfer(1, 30);
+        full_node.mine_block().await.unwrap();
+        full_node.transaction_pool.push(tx.clone());
+        full_node.transaction_pool.push(transfer(2, 5));
+        full_node.mine_block().await.unwrap();
+        full_node.mine_block().await.unwrap();
+
+        let config = LightClientConfig { request_timeout: Duration::from_millis(500), ..LightClientConfig::default() };
+        let mut client = LightClient::new(1, genesis.header).with_config(config);
+        client.network.initialize().await.unwrap();
+        let port = full_node.network.p2p_network.get_listen_addr().unwrap().port();
+        client.network.p2p_network.connect_to_peer(&format!("127.0.0.1:{}", port)).await.unwrap();
+        (full_node, client, tx)
+    }
+
+    /// 在后台任务中运行全节点的网络处理
+    fn serve(mut full_node: Blockchain) -> tokio::task::JoinHandle<Blockchain> {
+        tokio::spawn(async move {
+            for _ in 0..300 {
+                full_node.poll_network().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            full_node
+        })
+    }
+
+    #[test]
+    fn test_tx_inclusion_proof() {
+        let transactions: Vec<Transaction> = (1..=5).map(|seed| transfer(seed, 1)).collect();
+        let block = Block::new([0u8; 32], transactions.clone(), 1, 1).unwrap();
+        for tx in &transactions {
+            let proof = TxInclusionProof::generate(&block, &tx.hash()).unwrap().unwrap();
+            assert!(proof.verify(&block.header, &tx.hash()));
+        }
+        let proof = TxInclusionProof::generate(&block, &transactions[0].hash()).unwrap().unwrap();
+        assert!(!proof.verify(&block.header, &transactions[1].hash()));
+        assert!(TxInclusionProof::generate(&block, &[9u8; 32]).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_light_client_verifies_transaction_and_balance() {
+        let (full_node, mut client, tx) = full_node_and_client().await;
+        let block_hash = full_node.get_block_by_height(2).unwrap().block_hash;
+        let head = full_node.chain_head();
+        let server = serve(full_node);
+
+        assert_eq!(client.sync_headers().await.unwrap(), 3);
+        assert_eq!(client.best_header().block_hash, head.hash);
+        assert_eq!(client.confirmations(&block_hash), Some(2));
+
+        assert!(client.verify_tx_inclusion(tx.hash(), block_hash).await.unwrap());
+        let genesis_hash = client.headers[0].block_hash;
+        assert!(!client.verify_tx_inclusion(tx.hash(), genesis_hash).await.unwrap());
+
+        assert_eq!(client.get_verified_balance(&alice()).await.unwrap(), (65, 3));
+        assert_eq!(client.get_verified_balance("bob").await.unwrap(), (35, 3));
+        assert!(client.get_verified_balance("carol").await.is_err());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_light_client_rejects_stale_balance() {
+        let (mut full_node, mut client, _) = full_node_and_client().await;
+        full_node.mine_block().await.unwrap();
+        let headers: Vec<BlockHeader> = full_node.blocks.iter().skip(1).map(|block| block.header.clone()).collect();
+        client.apply_headers(headers).await.unwrap();
+        assert_eq!(client.height(), 4);
+
+        // 全节点回到旧链头，它的余额证明有效但已经过时
+        full_node.rollback_to(3).await.unwrap();
+        let server = serve(full_node);
+        assert!(client.get_verified_balance("bob").await.is_err());
+
+        client.config.max_balance_depth = 1;
+        assert_eq!(client.get_verified_balance("bob").await.unwrap(), (35, 3));
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_light_client_rejects_invalid_headers() {
+        let (full_node, mut client, _) = full_node_and_client().await;
+        let mut headers: Vec<BlockHeader> = full_node.blocks.iter().skip(1).map(|block| block.header.clone()).collect();
+
+        // 篡改余额根后区块哈希不再匹配
+        headers[1].balance_root = [7u8; 32];
+        assert!(client.apply_headers(headers.clone()).await.is_err());
+        assert_eq!(client.height(), 0);
+
+        // 不接在本地链上的区块头
+        assert!(client.apply_headers(headers[1..].to_vec()).await.is_err());
+        assert_eq!(client.height(), 0);
+    }
+}
@@ -169,12 +169,12 @@ impl MerkleTree {
                 current_index - 1
             };
             
-            if sibling_index < current_level.len() {
-                path.push(MerkleProofNode {
-                    hash: current_level[sibling_index].hash,
-                    is_left: !is_left,
-                });
-            }
+            // 奇数宽度的层中最后一个节点与自身配对
+            let sibling = current_level.get(sibling_index).unwrap_or(&current_level[current_index]);
+            path.push(MerkleProofNode {
+                hash: sibling.hash,
+                is_left: !is_left,
+            });
             
             // 移动到上一层
             current_index /= 2;
@@ -392,6 +392,17 @@ mod tests {
         assert!(MerkleTree::verify_proof(&proof));
     }
     
+    #[test]
+    fn test_merkle_proof_odd_width_levels() {
+        // 5 个叶子补齐为 6 个，上一层 3 个节点，最后一个节点与自身配对
+        let data: Vec<[u8; 32]> = (1u8..=5).map(|i| [i; 32]).collect();
+        let tree = MerkleTree::new(data).unwrap();
+        for index in 0..5 {
+            let proof = tree.generate_proof(index).unwrap();
+            assert!(proof.verify(), "leaf {}", index);
+        }
+    }
+    
     #[test]
     fn test_merkle_tree_serialization() {
         let data = vec![
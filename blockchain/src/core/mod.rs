@@ -6,13 +6,20 @@ pub mod block;
 pub mod transaction;
 pub mod state;
 pub mod merkle;
+pub mod chain_spec;
+pub mod light_client;
+pub mod contract;
 
 // 重新导出核心类型
 pub use blockchain::Blockchain;
-pub use block::{Block, BlockHeader};
-pub use transaction::{Transaction, TxInput, TxOutput, Witness};
-pub use state::{State, StateChange, StateKey, StateValue};
+pub use block::{Block, BlockHeader, BlockSeal, AuthorityVote};
+pub use transaction::{Transaction, TxInput, TxOutput, Witness, address_from_public_key};
+pub use state::{State, StateChange, StateKey, StateValue, StateJournal, JournalEntry, BalanceTree, balance_leaf};
 pub use merkle::{MerkleTree, MerkleProof};
+pub use chain_spec::{ChainSpec, ConsensusSpec, ValidatorSpec, FinalitySpec};
+pub use light_client::{LightClient, LightClientConfig, TxInclusionProof, BalanceProof};
+pub use contract::{ContractTransaction, ContractAction, ContractReceipt, ContractExecutor, BlockContext, UpgradeOperation};
+pub use contract::{BLOCK_GAS_LIMIT, CODE_BYTE_GAS, MAX_TX_GAS, MIN_GAS_PRICE, TX_BASE_GAS};
 
 // 核心错误类型
 #[derive(Debug, thiserror::Error)]
@@ -26,6 +33,9 @@ pub enum BlockchainError {
     #[error("Invalid state: {0}")]
     InvalidState(String),
     
+    #[error("Insufficient balance: {0}")]
+    InsufficientBalance(String),
+    
     #[error("Consensus failed: {0}")]
     ConsensusFailed(String),
     
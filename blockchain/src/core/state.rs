@@ -1,7 +1,7 @@
 // 状态管理模块
 use serde::{Serialize, Deserialize};
-use crate::core::{Result, BlockchainError};
-use std::collections::HashMap;
+use crate::core::{Result, BlockchainError, MerkleTree, MerkleProof};
+use std::collections::{BTreeMap, HashMap, HashSet};
 // use std::sync::Arc;
 // use tokio::sync::RwLock;
 
@@ -20,6 +20,11 @@ pub struct State {
     /// 账户余额
     pub balances: HashMap<String, u64>,
     
+    /// 超出 `u64` 的账户余额（32 字节大端整数），只有 EVM 账户会用到；
+    /// 同一账户只出现在 `balances` 与本映射之一中
+    #[serde(default)]
+    pub wide_balances: HashMap<String, Vec<u8>>,
+    
     /// 账户nonce
     pub nonces: HashMap<String, u64>,
     
@@ -44,6 +49,10 @@ pub struct ContractState {
     
     /// 合约余额
     pub balance: u64,
+    
+    /// 合约元数据（接口、执行后端、所有者等），由合约引擎编码，核心层不解释
+    #[serde(default)]
+    pub metadata: Vec<u8>,
 }
 
 /// 状态变更
@@ -128,6 +137,33 @@ pub enum StateValue {
     Contract(ContractState),
 }
 
+/// 状态修改日志
+///
+/// 修改前按修改顺序记录被修改项的原值，倒序撤销即可恢复修改前的状态，代价与修改的项数成正比，
+/// 而不需要复制整个状态。合约项在日志中只记录第一次修改前的值。
+#[derive(Debug, Clone, Default)]
+pub struct StateJournal {
+    entries: Vec<JournalEntry>,
+    /// 已记录整个合约项的地址
+    recorded: HashSet<String>,
+}
+
+/// 修改日志项，记录修改前的值，`None` 表示原先不存在
+#[derive(Debug, Clone)]
+pub enum JournalEntry {
+    /// 账户余额（`balances` 与 `wide_balances` 中的值）
+    Balance(String, Option<u64>, Option<Vec<u8>>),
+    
+    /// 账户 nonce
+    Nonce(String, Option<u64>),
+    
+    /// 整个合约项
+    Contract(String, Option<ContractState>),
+    
+    /// 合约项中的一个存储槽
+    ContractStorage(String, String, Option<Vec<u8>>),
+}
+
 impl State {
     /// 创建新状态
     pub fn new() -> Self {
@@ -136,6 +172,7 @@ impl State {
             latest_block_hash: [0u8; 32],
             latest_block_height: 0,
             balances: HashMap::new(),
+            wide_balances: HashMap::new(),
             nonces: HashMap::new(),
             contract_states: HashMap::new(),
             storage: HashMap::new(),
@@ -150,7 +187,6 @@ impl State {
     /// 设置账户余额
     pub async fn set_balance(&mut self, address: &str, balance: u64) -> Result<()> {
         self.balances.insert(address.to_string(), balance);
-        self.update_state_root();
         Ok(())
     }
     
@@ -179,7 +215,6 @@ impl State {
     /// 设置账户nonce
     pub async fn set_nonce(&mut self, address: &str, nonce: u64) -> Result<()> {
         self.nonces.insert(address.to_string(), nonce);
-        self.update_state_root();
         Ok(())
     }
     
@@ -200,7 +235,6 @@ impl State {
     pub async fn set_storage(&mut self, contract: &str, key: &str, value: Vec<u8>) -> Result<()> {
         let storage_key = format!("{}:{}", contract, key);
         self.storage.insert(storage_key, value);
-        self.update_state_root();
         Ok(())
     }
     
@@ -208,7 +242,6 @@ impl State {
     pub async fn delete_storage(&mut self, contract: &str, key: &str) -> Result<()> {
         let storage_key = format!("{}:{}", contract, key);
         self.storage.remove(&storage_key);
-        self.update_state_root();
         Ok(())
     }
     
@@ -220,14 +253,12 @@ impl State {
     /// 设置合约状态
     pub async fn set_contract_state(&mut self, contract_state: ContractState) -> Result<()> {
         self.contract_states.insert(contract_state.address.clone(), contract_state);
-        self.update_state_root();
         Ok(())
     }
     
     /// 删除合约状态
     pub async fn delete_contract_state(&mut self, address: &str) -> Result<()> {
         self.contract_states.remove(address);
-        self.update_state_root();
         Ok(())
     }
     
@@ -294,37 +325,49 @@ impl State {
         Ok(())
     }
     
-    /// 更新状态根
-    fn update_state_root(&mut self) {
+    /// 按当前内容刷新 `state_root` 字段；修改状态的方法不会自动刷新，需要时由调用方在修改结束后调用
+    pub(crate) fn update_state_root(&mut self) {
+        self.state_root = self.compute_state_root();
+    }
+    
+    /// 计算完整状态根，即区块头承诺的 `state_root`
+    ///
+    /// 各映射按键排序后哈希，不同节点上相同的状态得到相同的根；合约按地址、代码、
+    /// 排序后的存储、余额和元数据计入
+    pub fn compute_state_root(&self) -> [u8; 32] {
         use sha2::{Sha256, Digest};
         
         let mut hasher = Sha256::new();
         
         // 哈希余额
-        for (address, balance) in &self.balances {
+        for (address, balance) in self.balances.iter().collect::<BTreeMap<_, _>>() {
+            hasher.update(address.as_bytes());
+            hasher.update(balance.to_be_bytes());
+        }
+        for (address, balance) in self.wide_balances.iter().collect::<BTreeMap<_, _>>() {
             hasher.update(address.as_bytes());
-            hasher.update(&balance.to_be_bytes());
+            hasher.update(balance);
         }
         
         // 哈希nonce
-        for (address, nonce) in &self.nonces {
+        for (address, nonce) in self.nonces.iter().collect::<BTreeMap<_, _>>() {
             hasher.update(address.as_bytes());
-            hasher.update(&nonce.to_be_bytes());
+            hasher.update(nonce.to_be_bytes());
         }
         
         // 哈希存储
-        for (key, value) in &self.storage {
+        for (key, value) in self.storage.iter().collect::<BTreeMap<_, _>>() {
             hasher.update(key.as_bytes());
             hasher.update(value);
         }
         
         // 哈希合约状态
-        for (address, contract_state) in &self.contract_states {
+        for (address, contract_state) in self.contract_states.iter().collect::<BTreeMap<_, _>>() {
             hasher.update(address.as_bytes());
-            hasher.update(&contract_state.serialize().unwrap_or_default());
+            hasher.update(contract_state.digest());
         }
         
-        self.state_root = hasher.finalize().into();
+        hasher.finalize().into()
     }
     
     /// 设置最新区块哈希
@@ -337,14 +380,30 @@ impl State {
         self.latest_block_height = height;
     }
     
-    /// 更新状态根
-    pub fn update_state_root_with_hash(&mut self, block_hash: [u8; 32]) {
-        self.state_root = block_hash;
+    /// 获取状态根，按当前内容计算
+    pub fn get_state_root(&self) -> [u8; 32] {
+        self.compute_state_root()
     }
     
-    /// 获取状态根
-    pub fn get_state_root(&self) -> [u8; 32] {
-        self.state_root
+    /// 账户余额的 Merkle 根：按地址排序的 `balance_leaf`，没有账户时为全零
+    pub fn balance_root(&self) -> [u8; 32] {
+        self.balance_tree().map(|tree| tree.root()).unwrap_or([0u8; 32])
+    }
+    
+    /// 证明账户余额包含在 `balance_root` 中，账户不存在时返回 `None`
+    pub fn balance_proof(&self, address: &str) -> Result<Option<MerkleProof>> {
+        Ok(self.balance_tree()?.prove(address)?.map(|(_, proof)| proof))
+    }
+    
+    /// 当前余额的 Merkle 树；需要反复生成证明时应缓存结果，而不是每次重建
+    pub fn balance_tree(&self) -> Result<BalanceTree> {
+        let sorted: BTreeMap<&String, &u64> = self.balances.iter().collect();
+        let tree = MerkleTree::new(sorted.iter().map(|(address, balance)| balance_leaf(address, **balance)).collect())?;
+        let accounts = sorted.into_iter()
+            .enumerate()
+            .map(|(index, (address, balance))| (address.clone(), (index, *balance)))
+            .collect();
+        Ok(BalanceTree { tree, accounts })
     }
     
     /// 获取最新区块哈希
@@ -380,6 +439,79 @@ impl State {
     }
 }
 
+impl StateJournal {
+    /// 记录账户余额修改前的值
+    pub fn record_balance(&mut self, state: &State, key: &str) {
+        self.entries.push(JournalEntry::Balance(
+            key.to_string(),
+            state.balances.get(key).copied(),
+            state.wide_balances.get(key).cloned(),
+        ));
+    }
+    
+    /// 记录账户 nonce 修改前的值
+    pub fn record_nonce(&mut self, state: &State, key: &str) {
+        self.entries.push(JournalEntry::Nonce(key.to_string(), state.nonces.get(key).copied()));
+    }
+    
+    /// 记录合约项修改前的值；已经记录过整个合约项时，撤销到那一项即可，不再重复复制
+    pub fn record_contract(&mut self, state: &State, key: &str) {
+        if self.recorded.insert(key.to_string()) {
+            self.entries.push(JournalEntry::Contract(key.to_string(), state.contract_states.get(key).cloned()));
+        }
+    }
+    
+    /// 追加已经记录好原值的日志项，例如 EVM 执行时记录的修改
+    pub fn push(&mut self, entry: JournalEntry) {
+        if let JournalEntry::Contract(key, _) = &entry {
+            self.recorded.insert(key.clone());
+        }
+        self.entries.push(entry);
+    }
+    
+    /// 按顺序追加另一份日志
+    pub fn extend(&mut self, other: StateJournal) {
+        self.entries.extend(other.entries);
+        self.recorded.extend(other.recorded);
+    }
+    
+    /// 日志中修改过的合约地址
+    pub fn contracts(&self) -> HashSet<&str> {
+        self.entries.iter()
+            .filter_map(|entry| match entry {
+                JournalEntry::Contract(key, _) | JournalEntry::ContractStorage(key, _, _) => Some(key.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+    
+    /// 倒序撤销日志中的全部修改
+    pub fn revert(self, state: &mut State) {
+        fn restore<V>(map: &mut HashMap<String, V>, key: String, old: Option<V>) {
+            match old {
+                Some(value) => map.insert(key, value),
+                None => map.remove(&key),
+            };
+        }
+        
+        for entry in self.entries.into_iter().rev() {
+            match entry {
+                JournalEntry::Balance(key, old, old_wide) => {
+                    restore(&mut state.balances, key.clone(), old);
+                    restore(&mut state.wide_balances, key, old_wide);
+                }
+                JournalEntry::Nonce(key, old) => restore(&mut state.nonces, key, old),
+                JournalEntry::Contract(key, old) => restore(&mut state.contract_states, key, old),
+                JournalEntry::ContractStorage(key, slot, old) => {
+                    if let Some(contract) = state.contract_states.get_mut(&key) {
+                        restore(&mut contract.storage, slot, old);
+                    }
+                }
+            }
+        }
+    }
+}
+
 impl ContractState {
     /// 创建新合约状态
     pub fn new(address: String, code: Vec<u8>) -> Self {
@@ -388,7 +520,26 @@ impl ContractState {
             code,
             storage: HashMap::new(),
             balance: 0,
+            metadata: Vec::new(),
+        }
+    }
+    
+    /// 合约状态摘要：长度前缀的代码、按键排序的存储、余额和元数据的 SHA-256
+    pub fn digest(&self) -> [u8; 32] {
+        use sha2::{Sha256, Digest};
+        
+        let mut hasher = Sha256::new();
+        hasher.update((self.code.len() as u64).to_be_bytes());
+        hasher.update(&self.code);
+        for (key, value) in self.storage.iter().collect::<BTreeMap<_, _>>() {
+            hasher.update((key.len() as u64).to_be_bytes());
+            hasher.update(key.as_bytes());
+            hasher.update((value.len() as u64).to_be_bytes());
+            hasher.update(value);
         }
+        hasher.update(self.balance.to_be_bytes());
+        hasher.update(&self.metadata);
+        hasher.finalize().into()
     }
     
     /// 获取存储值
@@ -450,6 +601,39 @@ impl StateChange {
     }
 }
 
+/// 余额 Merkle 树，记录每个账户的叶子位置和余额，可在状态变化后继续为建树时的余额生成证明
+#[derive(Debug, Clone)]
+pub struct BalanceTree {
+    tree: MerkleTree,
+    /// 地址 -> （叶子下标，余额）
+    accounts: HashMap<String, (usize, u64)>,
+}
+
+impl BalanceTree {
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+    
+    /// 账户余额及其证明，账户不存在时返回 `None`
+    pub fn prove(&self, address: &str) -> Result<Option<(u64, MerkleProof)>> {
+        let Some((index, balance)) = self.accounts.get(address) else {
+            return Ok(None);
+        };
+        Ok(Some((*balance, self.tree.generate_proof(*index)?)))
+    }
+}
+
+/// 余额树的叶子：地址长度（大端 u32）| 地址 | 余额（大端 u64）的 SHA-256
+pub fn balance_leaf(address: &str, balance: u64) -> [u8; 32] {
+    use sha2::{Sha256, Digest};
+    
+    let mut hasher = Sha256::new();
+    hasher.update((address.len() as u32).to_be_bytes());
+    hasher.update(address.as_bytes());
+    hasher.update(balance.to_be_bytes());
+    hasher.finalize().into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -474,6 +658,25 @@ mod tests {
         assert!(state.subtract_balance("address1", 2000).await.is_err());
     }
     
+    #[tokio::test]
+    async fn test_balance_proof() {
+        let mut state = State::new();
+        assert_eq!(state.balance_root(), [0u8; 32]);
+        for (address, balance) in [("carol", 30), ("alice", 10), ("bob", 20)] {
+            state.set_balance(address, balance).await.unwrap();
+        }
+        
+        let proof = state.balance_proof("bob").unwrap().unwrap();
+        assert!(proof.verify());
+        assert_eq!(proof.leaf_hash, balance_leaf("bob", 20));
+        assert_eq!(proof.root_hash, state.balance_root());
+        assert!(state.balance_proof("dave").unwrap().is_none());
+        
+        // 余额变化后旧证明不再对应新的根
+        state.set_balance("bob", 21).await.unwrap();
+        assert_ne!(proof.root_hash, state.balance_root());
+    }
+    
     #[tokio::test]
     async fn test_state_nonce_operations() {
         let mut state = State::new();
@@ -517,4 +720,63 @@ mod tests {
         state.apply_change(&change).await.unwrap();
         assert_eq!(state.get_balance("address1").await.unwrap(), 1000);
     }
+    
+    #[tokio::test]
+    async fn test_journal_reverts_recorded_changes() {
+        let mut state = State::new();
+        state.set_balance("alice", 100).await.unwrap();
+        let mut contract = ContractState::new("counter".to_string(), vec![1, 2, 3]);
+        contract.set_storage("count".to_string(), vec![1]);
+        state.set_contract_state(contract).await.unwrap();
+        let root = state.get_state_root();
+        
+        let mut journal = StateJournal::default();
+        journal.record_balance(&state, "alice");
+        state.set_balance("alice", 40).await.unwrap();
+        journal.record_balance(&state, "bob");
+        state.set_balance("bob", 60).await.unwrap();
+        journal.record_nonce(&state, "alice");
+        state.increment_nonce("alice").await.unwrap();
+        journal.push(JournalEntry::ContractStorage("counter".to_string(), "count".to_string(), Some(vec![1])));
+        state.contract_states.get_mut("counter").unwrap().set_storage("count".to_string(), vec![2]);
+        journal.record_contract(&state, "counter");
+        state.contract_states.get_mut("counter").unwrap().balance = 5;
+        journal.record_contract(&state, "created");
+        state.set_contract_state(ContractState::new("created".to_string(), vec![4])).await.unwrap();
+        assert_ne!(state.get_state_root(), root);
+        
+        let mut contracts: Vec<&str> = journal.contracts().into_iter().collect();
+        contracts.sort();
+        assert_eq!(contracts, vec!["counter", "created"]);
+        
+        // 新出现的键被删除，而不是写成零值
+        journal.revert(&mut state);
+        assert_eq!(state.get_state_root(), root);
+        assert!(!state.balances.contains_key("bob"));
+        assert!(!state.nonces.contains_key("alice"));
+        assert!(!state.contract_states.contains_key("created"));
+    }
+    
+    #[tokio::test]
+    async fn test_state_root_covers_contracts_deterministically() {
+        let mut first = State::new();
+        let mut second = State::new();
+        for (index, address) in ["0x01", "0x02", "0x03"].iter().enumerate() {
+            let mut contract = ContractState::new(address.to_string(), vec![index as u8]);
+            contract.set_storage("a".to_string(), vec![1]);
+            contract.set_storage("b".to_string(), vec![2]);
+            first.set_contract_state(contract.clone()).await.unwrap();
+        }
+        for address in ["0x03", "0x01", "0x02"] {
+            let contract = first.contract_states[address].clone();
+            second.set_contract_state(contract).await.unwrap();
+        }
+        assert_eq!(first.get_state_root(), second.get_state_root());
+        
+        // 存储变化改变状态根
+        let mut contract = first.contract_states["0x02"].clone();
+        contract.set_storage("b".to_string(), vec![3]);
+        first.set_contract_state(contract).await.unwrap();
+        assert_ne!(first.get_state_root(), second.get_state_root());
+    }
 }
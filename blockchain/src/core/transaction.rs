@@ -1,6 +1,6 @@
 // 交易结构定义
 use serde::{Serialize, Deserialize};
-use crate::core::{Result, BlockchainError};
+use crate::core::{Result, BlockchainError, ContractTransaction};
 // use std::collections::HashMap;
 
 /// 交易结构
@@ -20,6 +20,10 @@ pub struct Transaction {
     
     /// 见证数据（可选）
     pub witness: Option<Witness>,
+    
+    /// 合约部署或调用（可选）
+    #[serde(default)]
+    pub contract: Option<ContractTransaction>,
 }
 
 /// 交易输入
@@ -61,7 +65,7 @@ pub struct TxOutput {
 }
 
 /// 输出点（引用前一个输出）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct OutPoint {
     /// 交易哈希
     pub tx_hash: [u8; 32],
@@ -86,9 +90,23 @@ impl Transaction {
             outputs,
             locktime: 0,
             witness: None,
+            contract: None,
         }
     }
     
+    /// 创建合约交易，没有输入输出
+    pub fn contract(contract: ContractTransaction) -> Self {
+        Self {
+            contract: Some(contract),
+            ..Self::new(Vec::new(), Vec::new())
+        }
+    }
+    
+    /// 是否为合约交易
+    pub fn is_contract(&self) -> bool {
+        self.contract.is_some()
+    }
+    
     /// 创建转账交易
     pub fn create_transfer(
         from_address: String,
@@ -137,17 +155,18 @@ impl Transaction {
         Ok(tx)
     }
     
-    /// 签名交易
-    pub fn sign(&mut self, _private_key: &[u8]) -> Result<()> {
-        // 计算交易哈希
-        let _tx_hash = self.hash();
-        
-        // 为每个输入签名
+    /// 用 secp256k1 私钥签名交易：每个输入和合约交易都对交易哈希签名并附带公钥
+    ///
+    /// 交易哈希不包含签名和公钥，签名前后哈希不变
+    pub fn sign(&mut self, private_key: &[u8]) -> Result<()> {
+        let (public_key, signature) = sign_digest(&self.hash(), private_key)?;
         for input in &mut self.inputs {
-            // 这里应该实现具体的签名逻辑
-            // 暂时使用占位符
-            input.signature = vec![0u8; 64]; // 64字节签名
-            input.public_key = vec![0u8; 33]; // 33字节公钥
+            input.public_key = public_key.clone();
+            input.signature = signature.clone();
+        }
+        if let Some(contract) = &mut self.contract {
+            contract.public_key = public_key;
+            contract.signature = signature;
         }
         
         Ok(())
@@ -155,18 +174,24 @@ impl Transaction {
     
     /// 验证交易
     pub fn validate(&self) -> Result<()> {
-        // 1. 验证基本格式
-        if self.inputs.is_empty() {
+        // 1. 验证基本格式，合约交易可以没有输入输出
+        if let Some(contract) = &self.contract {
+            if contract.sender.is_empty() {
+                return Err(BlockchainError::InvalidTransaction("Empty contract sender".to_string()));
+            }
+            contract.check_gas()?;
+        } else if self.inputs.is_empty() {
             return Err(BlockchainError::InvalidTransaction("No inputs".to_string()));
         }
         
-        if self.outputs.is_empty() {
+        if self.outputs.is_empty() && self.contract.is_none() {
             return Err(BlockchainError::InvalidTransaction("No outputs".to_string()));
         }
         
         // 2. 验证输入输出金额
-        let input_total: u64 = self.inputs.iter().map(|i| i.amount).sum();
-        let output_total: u64 = self.outputs.iter().map(|o| o.amount).sum();
+        let overflow = || BlockchainError::InvalidTransaction("Amount overflow".to_string());
+        let input_total = self.inputs.iter().try_fold(0u64, |total, i| total.checked_add(i.amount)).ok_or_else(overflow)?;
+        let output_total = self.outputs.iter().try_fold(0u64, |total, o| total.checked_add(o.amount)).ok_or_else(overflow)?;
         
         if input_total < output_total {
             return Err(BlockchainError::InvalidTransaction("Insufficient input amount".to_string()));
@@ -178,6 +203,13 @@ impl Transaction {
                 return Err(BlockchainError::InvalidTransaction("Invalid signature".to_string()));
             }
         }
+        if let Some(contract) = &self.contract {
+            let signed = contract.sender == address_from_public_key(&contract.public_key)
+                && verify_digest(&self.hash(), &contract.signature, &contract.public_key)?;
+            if !signed {
+                return Err(BlockchainError::InvalidTransaction("Invalid contract signature".to_string()));
+            }
+        }
         
         // 4. 验证锁定时间
         if self.locktime > 0 {
@@ -187,11 +219,12 @@ impl Transaction {
         Ok(())
     }
     
-    /// 验证签名
-    fn verify_signature(&self, _input: &TxInput) -> Result<bool> {
-        // 这里应该实现具体的签名验证逻辑
-        // 暂时返回true作为占位符
-        Ok(true)
+    /// 验证签名：签名覆盖交易哈希，输入地址必须由签名公钥推导
+    fn verify_signature(&self, input: &TxInput) -> Result<bool> {
+        if input.address != address_from_public_key(&input.public_key) {
+            return Ok(false);
+        }
+        verify_digest(&self.hash(), &input.signature, &input.public_key)
     }
     
     /// 计算交易哈希
@@ -214,13 +247,16 @@ impl Transaction {
         // 输入数量
         data.extend_from_slice(&(self.inputs.len() as u32).to_be_bytes());
         
-        // 输入
+        // 输入（签名和公钥不参与哈希）
         for input in &self.inputs {
             data.extend_from_slice(&input.previous_output.tx_hash);
             data.extend_from_slice(&input.previous_output.output_index.to_be_bytes());
             data.extend_from_slice(&(input.script_sig.len() as u32).to_be_bytes());
             data.extend_from_slice(&input.script_sig);
             data.extend_from_slice(&input.sequence.to_be_bytes());
+            data.extend_from_slice(&(input.address.len() as u32).to_be_bytes());
+            data.extend_from_slice(input.address.as_bytes());
+            data.extend_from_slice(&input.amount.to_be_bytes());
         }
         
         // 输出数量
@@ -231,11 +267,19 @@ impl Transaction {
             data.extend_from_slice(&output.amount.to_be_bytes());
             data.extend_from_slice(&(output.script_pubkey.len() as u32).to_be_bytes());
             data.extend_from_slice(&output.script_pubkey);
+            data.extend_from_slice(&(output.address.len() as u32).to_be_bytes());
+            data.extend_from_slice(output.address.as_bytes());
         }
         
         // 锁定时间
         data.extend_from_slice(&self.locktime.to_be_bytes());
         
+        // 合约操作（签名和公钥不参与哈希）
+        if let Some(contract) = &self.contract {
+            let fields = (&contract.sender, contract.nonce, contract.value, contract.gas_limit, contract.gas_price, &contract.action);
+            data.extend_from_slice(&bincode::serialize(&fields).unwrap_or_default());
+        }
+        
         data
     }
     
@@ -297,6 +341,31 @@ impl Transaction {
     }
 }
 
+/// 由 secp256k1 公钥推导账户地址："0x" + SHA-256(公钥) 的前 20 字节
+pub fn address_from_public_key(public_key: &[u8]) -> String {
+    use sha2::{Sha256, Digest};
+    
+    let hash = Sha256::digest(public_key);
+    format!("0x{}", hex::encode(&hash[..20]))
+}
+
+/// 用 secp256k1 私钥签名 32 字节摘要，返回 (公钥, DER 签名)
+pub(crate) fn sign_digest(digest: &[u8; 32], private_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    use crate::components::cryptography::signature::{EcdsaAlgorithm, SignatureAlgorithm, SignatureEngine};
+    
+    let to_error = |e: crate::components::ComponentError| BlockchainError::CryptographicError(e.to_string());
+    let public_key = SignatureEngine::new().derive_public_key(private_key, "ecdsa").map_err(to_error)?;
+    let signature = EcdsaAlgorithm::new().sign(digest, private_key).map_err(to_error)?;
+    Ok((public_key, signature))
+}
+
+/// 验证 secp256k1 签名，公钥或签名格式错误视为无效签名
+pub(crate) fn verify_digest(digest: &[u8; 32], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+    use crate::components::cryptography::signature::{EcdsaAlgorithm, SignatureAlgorithm};
+    
+    Ok(EcdsaAlgorithm::new().verify(digest, signature, public_key).unwrap_or(false))
+}
+
 impl TxInput {
     /// 创建新的交易输入
     pub fn new(previous_output: OutPoint, amount: u64, address: String) -> Self {
@@ -371,6 +440,7 @@ impl OutPoint {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::{MAX_TX_GAS, TX_BASE_GAS};
     
     #[test]
     fn test_transaction_creation() {
@@ -390,15 +460,74 @@ mod tests {
     
     #[test]
     fn test_transaction_validation() {
+        let private_key = [1u8; 32];
+        let (public_key, _) = sign_digest(&[0u8; 32], &private_key).unwrap();
         let input = TxInput::new(
             OutPoint::new([1u8; 32], 0),
             1000,
-            "address1".to_string(),
+            address_from_public_key(&public_key),
         );
         let output = TxOutput::new(900, "address2".to_string());
         
-        let tx = Transaction::new(vec![input], vec![output]);
+        let mut tx = Transaction::new(vec![input], vec![output]);
+        assert!(tx.validate().is_err());
+        tx.sign(&private_key).unwrap();
         assert!(tx.validate().is_ok());
+        
+        // 签名覆盖金额和收款地址
+        let mut redirected = tx.clone();
+        redirected.outputs[0].address = "address3".to_string();
+        assert!(redirected.validate().is_err());
+        
+        // 输入地址必须属于签名者
+        let mut stolen = Transaction::new(
+            vec![TxInput::new(OutPoint::new([2u8; 32], 0), 1000, "address1".to_string())],
+            vec![TxOutput::new(900, "address2".to_string())],
+        );
+        stolen.sign(&private_key).unwrap();
+        assert!(stolen.validate().is_err());
+    }
+    
+    #[test]
+    fn test_contract_transaction_validation_and_hash() {
+        let private_key = [1u8; 32];
+        let (public_key, _) = sign_digest(&[0u8; 32], &private_key).unwrap();
+        let sender = address_from_public_key(&public_key);
+        let call = |sender: &str, nonce| Transaction::contract(ContractTransaction::call(
+            sender.to_string(), nonce, "0xdef".to_string(), "increment".to_string(), vec![], 0, 100_000,
+        ));
+        
+        // 合约交易没有输入输出，哈希覆盖合约交易内容，不包含签名
+        let mut tx = call(&sender, 0);
+        assert!(tx.is_contract());
+        assert!(tx.validate().is_err());
+        let unsigned_hash = tx.hash();
+        tx.sign(&private_key).unwrap();
+        assert_eq!(tx.hash(), unsigned_hash);
+        assert!(tx.validate().is_ok());
+        assert_ne!(tx.hash(), call(&sender, 1).hash());
+        
+        // 签名覆盖 nonce，发送方必须是签名者
+        let mut replayed = tx.clone();
+        replayed.contract.as_mut().unwrap().nonce = 1;
+        assert!(replayed.validate().is_err());
+        
+        let mut impersonated = call("0xabc", 0);
+        impersonated.sign(&private_key).unwrap();
+        assert!(impersonated.validate().is_err());
+        
+        let mut anonymous = call("", 0);
+        anonymous.sign(&private_key).unwrap();
+        assert!(anonymous.validate().is_err());
+        
+        // gas 上限和价格必须在允许范围内
+        for (gas_limit, gas_price) in [(TX_BASE_GAS - 1, 1), (MAX_TX_GAS + 1, 1), (100_000, 0), (MAX_TX_GAS, u64::MAX)] {
+            let mut tx = call(&sender, 0);
+            let contract = tx.contract.as_mut().unwrap();
+            (contract.gas_limit, contract.gas_price) = (gas_limit, gas_price);
+            tx.sign(&private_key).unwrap();
+            assert!(tx.validate().is_err());
+        }
     }
     
     #[test]
@@ -1,26 +1,47 @@
 //! 发现协议实现
+//!
+//! 节点发现的协议层入口：委托给网络组件的 Kademlia 发现服务（`NetworkComponent::start_discovery`），
+//! 返回地址簿中可供 P2P 连接的 TCP 地址。
 
-use super::ProtocolResult;
-// use crate::core::{Result, BlockchainError};
+use super::{ProtocolResult, ProtocolError};
+use crate::components::network::Discovery;
+use crate::components::network::discovery::K_BUCKET_SIZE;
 
 /// 发现协议
 #[derive(Debug)]
 pub struct DiscoveryProtocol {
-    // 发现协议相关状态
+    discovery: Option<Discovery>,
 }
 
 impl DiscoveryProtocol {
     pub fn new() -> Self {
-        Self {}
+        Self { discovery: None }
     }
 
+    /// 使用已启动的发现服务
+    pub fn with_discovery(discovery: Discovery) -> Self {
+        Self { discovery: Some(discovery) }
+    }
+
+    /// 随机查找一次以发现新节点，返回地址簿中的候选 TCP 地址
     pub async fn discover_peers(&mut self) -> ProtocolResult<Vec<String>> {
-        // 简化的节点发现
-        Ok(vec![])
+        let discovery = self.discovery()?;
+        discovery.lookup(rand::random()).await;
+        let addresses = discovery.address_book().lock().unwrap().select(K_BUCKET_SIZE);
+        Ok(addresses.into_iter().map(|address| address.to_string()).collect())
     }
 
-    pub async fn announce_peer(&mut self, _peer_info: &str) -> ProtocolResult<()> {
-        // 宣布节点信息
+    /// 向指定 UDP 地址（"ip:port"）的节点宣布自己，对端响应后进入路由表
+    pub async fn announce_peer(&mut self, peer_info: &str) -> ProtocolResult<()> {
+        let address = peer_info.parse()
+            .map_err(|e| ProtocolError::DiscoveryFailed(format!("无效的节点地址 {}: {}", peer_info, e)))?;
+        self.discovery()?.ping(address).await
+            .map_err(|e| ProtocolError::DiscoveryFailed(e.to_string()))?;
         Ok(())
     }
+
+    fn discovery(&self) -> ProtocolResult<&Discovery> {
+        self.discovery.as_ref()
+            .ok_or_else(|| ProtocolError::DiscoveryFailed("节点发现未启动".to_string()).into())
+    }
 }
@@ -1,7 +1,11 @@
 //! 同步协议实现
+//!
+//! 区块头优先同步的线路层入口：请求和响应以 bincode 编码的
+//! `SyncRequestMessage` / `SyncResponseMessage` 传输，同步状态由链的网络组件维护。
 
 use super::{ProtocolResult, ProtocolError};
-// use crate::core::{Result, BlockchainError};
+use crate::components::network::{SyncRequest, SyncRequestMessage, SyncResponseMessage};
+use crate::core::Blockchain;
 
 /// 同步协议
 #[derive(Debug)]
@@ -14,18 +18,39 @@ impl SyncProtocol {
         Self {}
     }
 
-    pub async fn sync_blocks(&mut self, peer_height: u64) -> ProtocolResult<Vec<u8>> {
-        // 简化的区块同步
+    /// 开始从对端同步，返回发往该对端的第一个区块头请求；本地已不低于对端时返回空
+    pub async fn sync_blocks(&mut self, chain: &mut Blockchain, peer_id: &str, peer_height: u64) -> ProtocolResult<Vec<u8>> {
         if peer_height == 0 {
             return Err(ProtocolError::SyncFailed("无效的区块高度".to_string()).into());
         }
+        if peer_height <= chain.get_height() {
+            return Ok(vec![]);
+        }
+
+        chain.start_sync(peer_id, peer_height);
+        let peers = [peer_id.to_string()];
+        let request = chain.network.block_sync.next_requests(&peers, std::time::Instant::now())
+            .into_iter()
+            .find(|(_, request)| matches!(request, SyncRequest::Headers { .. }))
+            .map(|(_, request)| request)
+            .ok_or_else(|| ProtocolError::SyncFailed("没有需要请求的区块头".to_string()))?;
 
-        // 返回同步请求
-        Ok(vec![])
+        let message = SyncRequestMessage {
+            request,
+            peer_id: chain.network.p2p_network.get_peer_id(),
+        };
+        bincode::serialize(&message).map_err(|e| ProtocolError::MessageParsingFailed(e.to_string()).into())
     }
 
-    pub async fn handle_sync_request(&mut self, _request: &[u8]) -> ProtocolResult<Vec<u8>> {
-        // 处理同步请求
-        Ok(vec![])
+    /// 处理编码后的同步请求，返回编码后的响应
+    pub async fn handle_sync_request(&mut self, chain: &Blockchain, request: &[u8]) -> ProtocolResult<Vec<u8>> {
+        let request: SyncRequestMessage = bincode::deserialize(request)
+            .map_err(|e| ProtocolError::MessageParsingFailed(e.to_string()))?;
+
+        let response = SyncResponseMessage {
+            response: chain.serve_sync_request(&request.request),
+            peer_id: chain.network.p2p_network.get_peer_id(),
+        };
+        bincode::serialize(&response).map_err(|e| ProtocolError::MessageParsingFailed(e.to_string()).into())
     }
 }
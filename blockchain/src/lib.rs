@@ -35,7 +35,6 @@ pub mod tools;
 pub mod types;
 pub mod monitoring;
 pub mod cli;
-pub mod consensus;
 pub mod web_api;
 // #[cfg(feature = "advanced")]
 // pub mod performance;
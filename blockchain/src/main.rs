@@ -9,9 +9,6 @@ mod simple_blockchain;
 mod smart_contract;
 mod tools;
 mod types;
-mod monitoring;
-mod cli;
-mod consensus;
 #[cfg(feature = "advanced")]
 mod performance;
 #[cfg(feature = "advanced")]
@@ -21,17 +18,17 @@ mod security;
 #[cfg(feature = "crypto-advanced")]
 mod advanced_cryptography_simple;
 
+// 合约宿主函数依赖库中的密码学组件，直接使用库模块
 #[cfg(feature = "smart-contracts")]
-mod smart_contract_engine;
+use blockchain::smart_contract_engine;
 
+// P2P 节点依赖库中的加密传输组件，直接使用库模块
 #[cfg(feature = "p2p")]
-mod p2p_network;
+use blockchain::p2p_network;
 
 #[cfg(feature = "database")]
 mod database;
 
-mod web_api;
-
 use simple_blockchain::*;
 use std::io::{self, Write};
 
@@ -361,6 +358,7 @@ fn demonstrate_smart_contracts() {
             },
         ],
         events: vec![],
+        storage: vec![],
     };
 
     // 注意：这里使用空的 WASM 代码，实际应用中需要有效的 WASM 字节码
@@ -404,11 +402,13 @@ fn demonstrate_smart_contracts() {
 #[cfg(feature = "p2p")]
 fn demonstrate_p2p_network() {
     use p2p_network::*;
+    use blockchain::components::network::NodeIdentity;
+    use std::sync::Arc;
 
     let message_handler = Arc::new(DefaultMessageHandler);
     
     let node = P2PNode::new(
-        "demo_node".to_string(),
+        NodeIdentity::generate(),
         "1.0.0".to_string(),
         vec!["blockchain".to_string(), "smart_contracts".to_string()],
         message_handler,
@@ -417,9 +417,10 @@ fn demonstrate_p2p_network() {
     // 演示消息创建
     let handshake_msg = NetworkMessage::new(MessageType::Handshake(HandshakeMessage {
         version: "1.0.0".to_string(),
-        node_id: "demo_node".to_string(),
+        node_id: node.node_id.clone(),
         capabilities: vec!["blockchain".to_string()],
         timestamp: 1234567890,
+        ..Default::default()
     }));
     
     let serialized = handshake_msg.serialize().unwrap();
@@ -9,12 +9,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{mpsc, RwLock};
-use tokio_tungstenite::{accept_async, connect_async, tungstenite::Message};
+use tokio_tungstenite::{accept_async, client_async, tungstenite::Message, WebSocketStream};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::components::network::gossip::BoundedSet;
+use crate::components::network::secure::{InitiatorHandshake, NodeIdentity, ResponderHandshake, SecureSession};
+
 /// 网络错误类型
 #[derive(Error, Debug, Clone, Serialize, Deserialize)]
 pub enum NetworkError {
@@ -32,6 +36,45 @@ pub enum NetworkError {
     Timeout,
     #[error("Authentication failed")]
     AuthenticationFailed,
+    #[error("Network id mismatch: expected {expected}, got {actual}")]
+    NetworkMismatch { expected: u32, actual: u32 },
+    #[error("Genesis hash mismatch")]
+    GenesisMismatch,
+    #[error("No common protocol version")]
+    IncompatibleVersion,
+    #[error("Connected to self")]
+    SelfConnection,
+}
+
+/// 本节点支持的最低协议版本
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// 本节点支持的最高协议版本
+pub const MAX_PROTOCOL_VERSION: u32 = 1;
+/// 握手超时时间
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// 支持区块同步的能力标识
+pub const CAPABILITY_SYNC: &str = "sync";
+/// 消息默认可转发的跳数
+pub const DEFAULT_MESSAGE_TTL: u32 = 10;
+/// 消息最长存活时间（秒），超过后不再处理或转发
+pub const MESSAGE_MAX_AGE_SECS: u64 = 300;
+/// 已处理消息ID缓存的容量
+const SEEN_MESSAGE_CACHE_SIZE: usize = 10_000;
+
+/// 本节点所在链的身份和链头，握手时发送给对端
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainInfo {
+    pub network_id: u32,
+    pub genesis_hash: [u8; 32],
+    pub best_height: u64,
+    pub best_hash: [u8; 32],
+}
+
+/// 协议版本协商：取双方版本区间交集中的最高版本
+pub fn negotiate_version(local: (u32, u32), remote: (u32, u32)) -> Option<u32> {
+    let low = local.0.max(remote.0);
+    let high = local.1.min(remote.1);
+    (low <= high).then_some(high)
 }
 
 /// 消息类型枚举
@@ -54,12 +97,19 @@ pub enum MessageType {
 }
 
 /// 握手消息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HandshakeMessage {
     pub version: String,
     pub node_id: String,
     pub capabilities: Vec<String>,
     pub timestamp: u64,
+    /// 支持的协议版本区间
+    pub min_protocol_version: u32,
+    pub max_protocol_version: u32,
+    /// 链身份与链头
+    pub chain: ChainInfo,
+    /// 每个节点启动时随机生成，用于识别连接到自己的情况
+    pub nonce: u64,
 }
 
 /// 区块消息
@@ -101,7 +151,7 @@ pub struct NetworkMessage {
     pub id: String,
     pub message_type: MessageType,
     pub timestamp: u64,
-    pub ttl: u32, // 生存时间
+    pub ttl: u32, // 剩余转发跳数
 }
 
 impl NetworkMessage {
@@ -115,7 +165,7 @@ impl NetworkMessage {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            ttl: 10, // 默认 TTL
+            ttl: DEFAULT_MESSAGE_TTL,
         }
     }
 
@@ -131,7 +181,7 @@ impl NetworkMessage {
         bincode::deserialize(data).map_err(|_| NetworkError::DeserializationFailed)
     }
 
-    /// 检查消息是否过期
+    /// 检查消息是否过期：跳数耗尽或超过最长存活时间
     /// Check if message is expired
     pub fn is_expired(&self) -> bool {
         let now = std::time::SystemTime::now()
@@ -139,7 +189,14 @@ impl NetworkMessage {
             .unwrap()
             .as_secs();
         
-        now - self.timestamp > self.ttl as u64
+        self.ttl == 0 || now.saturating_sub(self.timestamp) > MESSAGE_MAX_AGE_SECS
+    }
+
+    /// 转发用的副本，跳数减一；跳数耗尽时不再转发
+    /// Copy for relaying with one hop consumed
+    pub fn relayed(&self) -> Option<Self> {
+        let ttl = self.ttl.checked_sub(1).filter(|ttl| *ttl > 0)?;
+        Some(Self { ttl, ..self.clone() })
     }
 }
 
@@ -152,6 +209,19 @@ pub struct PeerInfo {
     pub capabilities: Vec<String>,
     pub last_seen: u64,
     pub is_connected: bool,
+    /// 协商后的协议版本
+    pub protocol_version: u32,
+    /// 对端握手时报告的链头
+    pub best_height: u64,
+    pub best_hash: [u8; 32],
+    /// 是否为对端发起的连接
+    pub inbound: bool,
+}
+
+impl PeerInfo {
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
 }
 
 /// 消息处理器 trait
@@ -162,6 +232,7 @@ pub trait MessageHandler: Send + Sync {
 
 /// P2P 网络节点
 pub struct P2PNode {
+    /// 由身份公钥派生的节点ID
     pub node_id: String,
     pub version: String,
     pub capabilities: Vec<String>,
@@ -170,13 +241,38 @@ pub struct P2PNode {
     pub incoming_tx: mpsc::UnboundedReceiver<NetworkMessage>,
     pub outgoing_tx: mpsc::UnboundedSender<NetworkMessage>,
     pub listen_address: Option<SocketAddr>,
+    /// 本节点的链身份与链头
+    pub chain_info: Arc<RwLock<ChainInfo>>,
+    /// 本次运行的握手随机数
+    nonce: u64,
+    /// 节点身份密钥
+    identity: Arc<NodeIdentity>,
+    /// 握手后发现对端链头更高时通知同步模块
+    sync_sender: Option<mpsc::UnboundedSender<PeerInfo>>,
+    /// 已处理的消息ID
+    seen_messages: Arc<std::sync::Mutex<BoundedSet<String>>>,
+}
+
+/// 连接处理任务需要的节点状态
+#[derive(Clone)]
+struct ConnectionContext {
+    node_id: String,
+    version: String,
+    capabilities: Vec<String>,
+    chain_info: Arc<RwLock<ChainInfo>>,
+    nonce: u64,
+    identity: Arc<NodeIdentity>,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    sync_sender: Option<mpsc::UnboundedSender<PeerInfo>>,
+    /// 已处理的消息ID，丢弃经不同路径重复到达的消息
+    seen_messages: Arc<std::sync::Mutex<BoundedSet<String>>>,
 }
 
 impl P2PNode {
-    /// 创建新的 P2P 节点
+    /// 创建新的 P2P 节点，节点ID由身份公钥派生
     /// Create new P2P node
     pub fn new(
-        node_id: String,
+        identity: NodeIdentity,
         version: String,
         capabilities: Vec<String>,
         message_handler: Arc<dyn MessageHandler>,
@@ -185,7 +281,7 @@ impl P2PNode {
         let (outgoing_tx, outgoing_rx) = mpsc::unbounded_channel();
 
         Self {
-            node_id,
+            node_id: identity.peer_id(),
             version,
             capabilities,
             peers: Arc::new(RwLock::new(HashMap::new())),
@@ -193,6 +289,47 @@ impl P2PNode {
             incoming_tx: incoming_rx,
             outgoing_tx,
             listen_address: None,
+            chain_info: Arc::new(RwLock::new(ChainInfo::default())),
+            nonce: rand::random(),
+            identity: Arc::new(identity),
+            sync_sender: None,
+            seen_messages: Arc::new(std::sync::Mutex::new(BoundedSet::new(SEEN_MESSAGE_CACHE_SIZE))),
+        }
+    }
+
+    /// 设置链身份（网络ID、创世哈希和当前链头）
+    pub fn with_chain_info(self, chain_info: ChainInfo) -> Self {
+        Self {
+            chain_info: Arc::new(RwLock::new(chain_info)),
+            ..self
+        }
+    }
+
+    /// 更新本地链头，之后的握手会报告新的链头
+    pub async fn update_best_block(&self, height: u64, hash: [u8; 32]) {
+        let mut chain_info = self.chain_info.write().await;
+        chain_info.best_height = height;
+        chain_info.best_hash = hash;
+    }
+
+    /// 订阅同步请求：握手完成且对端链头高于本地并支持同步时推送该对端
+    pub fn sync_requests(&mut self) -> mpsc::UnboundedReceiver<PeerInfo> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.sync_sender = Some(sender);
+        receiver
+    }
+
+    fn connection_context(&self) -> ConnectionContext {
+        ConnectionContext {
+            node_id: self.node_id.clone(),
+            version: self.version.clone(),
+            capabilities: self.capabilities.clone(),
+            chain_info: Arc::clone(&self.chain_info),
+            nonce: self.nonce,
+            identity: Arc::clone(&self.identity),
+            peers: Arc::clone(&self.peers),
+            sync_sender: self.sync_sender.clone(),
+            seen_messages: Arc::clone(&self.seen_messages),
         }
     }
 
@@ -202,39 +339,27 @@ impl P2PNode {
         let listener = TcpListener::bind(address).await
             .map_err(|_| NetworkError::ConnectionFailed)?;
 
-        self.listen_address = Some(address);
+        let local_address = listener.local_addr().map_err(|_| NetworkError::ConnectionFailed)?;
+        self.listen_address = Some(local_address);
 
-        println!("🚀 P2P 节点启动在地址: {}", address);
-
-        // 启动消息处理循环
-        let peers = Arc::clone(&self.peers);
-        let message_handler = Arc::clone(&self.message_handler);
-        let node_id = self.node_id.clone();
-
-        // 注意：这里暂时注释掉消息处理循环，因为incoming_tx不能克隆
-        // 在实际实现中，需要重新设计消息处理架构
-        // tokio::spawn(async move {
-        //     while let Some(message) = incoming_tx.recv().await {
-        //         // 处理接收到的消息
-        //         if let Err(e) = message_handler.handle_message(message, node_id.clone()).await {
-        //             eprintln!("消息处理错误: {}", e);
-        //         }
-        //     }
-        // });
+        println!("🚀 P2P 节点启动在地址: {}", local_address);
 
         // 接受连接
-        while let Ok((stream, addr)) = listener.accept().await {
-            let peers = Arc::clone(&self.peers);
-            let node_id = self.node_id.clone();
-            let version = self.version.clone();
-            let capabilities = self.capabilities.clone();
-
-            tokio::spawn(async move {
-                if let Err(e) = Self::handle_connection(stream, addr, peers, node_id, version, capabilities).await {
-                    eprintln!("连接处理错误: {}", e);
-                }
-            });
-        }
+        let context = self.connection_context();
+        tokio::spawn(async move {
+            while let Ok((stream, addr)) = listener.accept().await {
+                let context = context.clone();
+                tokio::spawn(async move {
+                    let result = match accept_async(stream).await {
+                        Ok(ws_stream) => Self::handle_connection(ws_stream, addr, true, context).await,
+                        Err(_) => Err(NetworkError::ConnectionFailed),
+                    };
+                    if let Err(e) = result {
+                        eprintln!("连接处理错误: {}", e);
+                    }
+                });
+            }
+        });
 
         Ok(())
     }
@@ -245,13 +370,13 @@ impl P2PNode {
         let stream = TcpStream::connect(address).await
             .map_err(|_| NetworkError::ConnectionFailed)?;
 
-        let peers = Arc::clone(&self.peers);
-        let node_id = self.node_id.clone();
-        let version = self.version.clone();
-        let capabilities = self.capabilities.clone();
+        // 出站连接执行客户端 WebSocket 握手
+        let (ws_stream, _) = client_async(format!("ws://{}/", address), stream).await
+            .map_err(|_| NetworkError::ConnectionFailed)?;
 
+        let context = self.connection_context();
         tokio::spawn(async move {
-            if let Err(e) = Self::handle_connection(stream, address, peers, node_id, version, capabilities).await {
+            if let Err(e) = Self::handle_connection(ws_stream, address, false, context).await {
                 eprintln!("连接处理错误: {}", e);
             }
         });
@@ -259,70 +384,162 @@ impl P2PNode {
         Ok(())
     }
 
-    /// 处理连接
-    /// Handle connection
-    async fn handle_connection(
-        stream: TcpStream,
-        addr: SocketAddr,
-        peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
-        node_id: String,
-        version: String,
-        capabilities: Vec<String>,
-    ) -> Result<(), NetworkError> {
-        let ws_stream = accept_async(stream).await
-            .map_err(|_| NetworkError::ConnectionFailed)?;
+    /// 读取下一条二进制消息，跳过 WebSocket 心跳帧
+    async fn next_binary(ws_stream: &mut WebSocketStream<TcpStream>) -> Result<Vec<u8>, NetworkError> {
+        loop {
+            match ws_stream.next().await {
+                Some(Ok(Message::Binary(data))) => return Ok(data.to_vec()),
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                _ => return Err(NetworkError::InvalidMessageFormat),
+            }
+        }
+    }
 
-        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    /// 建立加密会话：出站连接为发起方，双方通过身份密钥相互认证
+    async fn secure_handshake(
+        ws_stream: &mut WebSocketStream<TcpStream>,
+        inbound: bool,
+        identity: &NodeIdentity,
+    ) -> Result<SecureSession, NetworkError> {
+        let send = |data: Vec<u8>| Message::Binary(data.into());
+        if inbound {
+            let first = Self::next_binary(ws_stream).await?;
+            let (state, second) = ResponderHandshake::respond(identity, &first)
+                .map_err(|_| NetworkError::AuthenticationFailed)?;
+            ws_stream.send(send(second)).await.map_err(|_| NetworkError::ConnectionFailed)?;
+            let third = Self::next_binary(ws_stream).await?;
+            state.finish(&third).map_err(|_| NetworkError::AuthenticationFailed)
+        } else {
+            let (state, first) = InitiatorHandshake::new();
+            ws_stream.send(send(first)).await.map_err(|_| NetworkError::ConnectionFailed)?;
+            let second = Self::next_binary(ws_stream).await?;
+            let (third, session) = state.finish(identity, &second)
+                .map_err(|_| NetworkError::AuthenticationFailed)?;
+            ws_stream.send(send(third)).await.map_err(|_| NetworkError::ConnectionFailed)?;
+            Ok(session)
+        }
+    }
 
-        // 发送握手消息
+    /// 在加密会话上双方对称地发送握手并校验对端握手，成功时返回对端信息
+    async fn perform_handshake(
+        ws_stream: &mut WebSocketStream<TcpStream>,
+        session: &mut SecureSession,
+        addr: SocketAddr,
+        inbound: bool,
+        context: &ConnectionContext,
+    ) -> Result<PeerInfo, NetworkError> {
+        let local_chain = context.chain_info.read().await.clone();
         let handshake = NetworkMessage::new(MessageType::Handshake(HandshakeMessage {
-            version: version.clone(),
-            node_id: node_id.clone(),
-            capabilities: capabilities.clone(),
+            version: context.version.clone(),
+            node_id: context.node_id.clone(),
+            capabilities: context.capabilities.clone(),
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: MAX_PROTOCOL_VERSION,
+            chain: local_chain.clone(),
+            nonce: context.nonce,
         }));
-
-        let handshake_data = handshake.serialize()?;
-        ws_sender.send(Message::Binary(handshake_data.into())).await
+        let ciphertext = session.encrypt(&handshake.serialize()?)
+            .map_err(|_| NetworkError::SerializationFailed)?;
+        ws_stream.send(Message::Binary(ciphertext.into())).await
             .map_err(|_| NetworkError::ConnectionFailed)?;
 
-        // 接收握手响应
-        if let Some(msg) = ws_receiver.next().await {
-            match msg {
-                Ok(Message::Binary(data)) => {
-                    let response = NetworkMessage::deserialize(&data)?;
-                    
-                    if let MessageType::Handshake(handshake_msg) = response.message_type {
-                        // 添加对等节点
-                        let peer_info = PeerInfo {
-                            id: handshake_msg.node_id.clone(),
-                            address: addr,
-                            version: handshake_msg.version,
-                            capabilities: handshake_msg.capabilities,
-                            last_seen: std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
-                            is_connected: true,
-                        };
-
-                        peers.write().await.insert(handshake_msg.node_id, peer_info);
-                        println!("✅ 成功连接到对等节点: {}", addr);
-                    }
-                }
-                _ => return Err(NetworkError::InvalidMessageFormat),
+        let data = session.decrypt(&Self::next_binary(ws_stream).await?)
+            .map_err(|_| NetworkError::AuthenticationFailed)?;
+        let remote = match NetworkMessage::deserialize(&data)?.message_type {
+            MessageType::Handshake(remote) => remote,
+            _ => return Err(NetworkError::InvalidMessageFormat),
+        };
+
+        // 声明的节点ID必须与加密握手中认证的身份一致
+        if remote.node_id != session.remote_peer_id() {
+            return Err(NetworkError::AuthenticationFailed);
+        }
+        if remote.nonce == context.nonce {
+            return Err(NetworkError::SelfConnection);
+        }
+        if remote.chain.network_id != local_chain.network_id {
+            return Err(NetworkError::NetworkMismatch {
+                expected: local_chain.network_id,
+                actual: remote.chain.network_id,
+            });
+        }
+        if remote.chain.genesis_hash != local_chain.genesis_hash {
+            return Err(NetworkError::GenesisMismatch);
+        }
+        let protocol_version = negotiate_version(
+            (MIN_PROTOCOL_VERSION, MAX_PROTOCOL_VERSION),
+            (remote.min_protocol_version, remote.max_protocol_version),
+        ).ok_or(NetworkError::IncompatibleVersion)?;
+
+        Ok(PeerInfo {
+            id: remote.node_id,
+            address: addr,
+            version: remote.version,
+            capabilities: remote.capabilities,
+            last_seen: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            is_connected: true,
+            protocol_version,
+            best_height: remote.chain.best_height,
+            best_hash: remote.chain.best_hash,
+            inbound,
+        })
+    }
+
+    /// 处理连接
+    /// Handle connection
+    async fn handle_connection(
+        mut ws_stream: WebSocketStream<TcpStream>,
+        addr: SocketAddr,
+        inbound: bool,
+        context: ConnectionContext,
+    ) -> Result<(), NetworkError> {
+        // 握手失败（超时、身份认证失败、其他链、自连接、版本不兼容）时直接断开
+        let handshake = tokio::time::timeout(HANDSHAKE_TIMEOUT, async {
+            let mut session = Self::secure_handshake(&mut ws_stream, inbound, &context.identity).await?;
+            let peer_info = Self::perform_handshake(&mut ws_stream, &mut session, addr, inbound, &context).await?;
+            Ok((session, peer_info))
+        }).await.unwrap_or(Err(NetworkError::Timeout));
+        let (mut session, peer_info) = match handshake {
+            Ok(result) => result,
+            Err(e) => {
+                let _ = ws_stream.close(None).await;
+                return Err(e);
             }
+        };
+
+        let peer_id = peer_info.id.clone();
+        let local_height = context.chain_info.read().await.best_height;
+        if peer_info.best_height > local_height
+            && peer_info.supports(CAPABILITY_SYNC)
+            && let Some(sync_sender) = &context.sync_sender
+        {
+            let _ = sync_sender.send(peer_info.clone());
         }
+        context.peers.write().await.insert(peer_id.clone(), peer_info);
+        println!("✅ 成功连接到对等节点: {}", addr);
+
+        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
         // 处理消息循环
         while let Some(msg) = ws_receiver.next().await {
             match msg {
                 Ok(Message::Binary(data)) => {
+                    // 解密失败说明帧被篡改或乱序，断开连接
+                    let Ok(data) = session.decrypt(&data) else {
+                        eprintln!("解密失败，断开连接: {}", addr);
+                        break;
+                    };
                     if let Ok(network_message) = NetworkMessage::deserialize(&data) {
-                        if !network_message.is_expired() {
+                        let is_new = !network_message.is_expired()
+                            && context.seen_messages.lock().unwrap().insert(network_message.id.clone());
+                        if is_new {
                             // 处理消息
                             println!("📨 收到消息: {:?}", network_message.message_type);
                         }
@@ -345,7 +562,7 @@ impl P2PNode {
         }
 
         // 清理对等节点
-        if let Some(peer_info) = peers.write().await.values_mut().find(|p| p.address == addr) {
+        if let Some(peer_info) = context.peers.write().await.get_mut(&peer_id) {
             peer_info.is_connected = false;
         }
 
@@ -490,10 +707,27 @@ mod tests {
             node_id: "test_node".to_string(),
             capabilities: vec!["blockchain".to_string(), "smart_contracts".to_string()],
             timestamp: 1234567890,
+            ..Default::default()
         };
         
         let message = NetworkMessage::new(MessageType::Handshake(handshake));
-        assert_eq!(message.ttl, 10);
+        assert_eq!(message.ttl, DEFAULT_MESSAGE_TTL);
+    }
+
+    #[test]
+    fn test_message_ttl_counts_hops() {
+        let mut message = NetworkMessage::new(MessageType::Ping);
+        message.ttl = 2;
+        let relayed = message.relayed().unwrap();
+        assert_eq!(relayed.ttl, 1);
+        assert_eq!(relayed.id, message.id);
+        assert!(relayed.relayed().is_none());
+
+        // 时间戳在未来也不会下溢
+        message.timestamp = u64::MAX;
+        assert!(!message.is_expired());
+        message.timestamp = 0;
+        assert!(message.is_expired());
     }
 
     #[test]
@@ -505,9 +739,79 @@ mod tests {
             capabilities: vec!["blockchain".to_string()],
             last_seen: 1234567890,
             is_connected: true,
+            protocol_version: 1,
+            best_height: 0,
+            best_hash: [0u8; 32],
+            inbound: false,
         };
         
         assert_eq!(peer.id, "peer_1");
         assert!(peer.is_connected);
+        assert!(peer.supports("blockchain"));
+    }
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version((1, 3), (2, 5)), Some(3));
+        assert_eq!(negotiate_version((1, 1), (1, 1)), Some(1));
+        assert_eq!(negotiate_version((1, 2), (3, 4)), None);
+    }
+
+    fn test_node(chain_info: ChainInfo) -> P2PNode {
+        P2PNode::new(
+            NodeIdentity::generate(),
+            "1.0.0".to_string(),
+            vec![CAPABILITY_SYNC.to_string()],
+            Arc::new(DefaultMessageHandler),
+        ).with_chain_info(chain_info)
+    }
+
+    async fn wait_for_peers(node: &P2PNode, count: usize) -> bool {
+        for _ in 0..100 {
+            if node.get_connected_peer_count().await >= count {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_handshake_populates_peer_and_triggers_sync() {
+        let chain = ChainInfo { network_id: 7, genesis_hash: [1u8; 32], ..Default::default() };
+        let mut server = test_node(ChainInfo { best_height: 42, best_hash: [9u8; 32], ..chain.clone() });
+        server.start_server("127.0.0.1:0".parse().unwrap()).await.unwrap();
+
+        let mut client = test_node(chain);
+        let mut sync_requests = client.sync_requests();
+        client.connect_to_peer(server.listen_address.unwrap()).await.unwrap();
+
+        let peer = tokio::time::timeout(Duration::from_secs(5), sync_requests.recv()).await.unwrap().unwrap();
+        assert_eq!(peer.id, server.node_id);
+        assert_eq!(peer.best_height, 42);
+        assert_eq!(peer.protocol_version, MAX_PROTOCOL_VERSION);
+        assert!(!peer.inbound);
+
+        assert!(wait_for_peers(&server, 1).await);
+        let inbound = server.get_peers().await.pop().unwrap();
+        assert_eq!(inbound.id, client.node_id);
+        assert!(inbound.inbound);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_other_chain_and_self() {
+        let chain = ChainInfo { network_id: 7, genesis_hash: [1u8; 32], ..Default::default() };
+        let mut server = test_node(chain.clone());
+        server.start_server("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let address = server.listen_address.unwrap();
+
+        let other_genesis = test_node(ChainInfo { genesis_hash: [2u8; 32], ..chain });
+        other_genesis.connect_to_peer(address).await.unwrap();
+        // 自连接：握手随机数相同
+        server.connect_to_peer(address).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(server.get_connected_peer_count().await, 0);
+        assert_eq!(other_genesis.get_connected_peer_count().await, 0);
     }
 }
@@ -1,766 +0,0 @@
-//! # 智能合约引擎
-//! 
-//! 基于 WebAssembly 的智能合约执行引擎
-//! Smart contract engine based on WebAssembly
-
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use thiserror::Error;
-use sha2::{Sha256, Digest};
-
-/// 智能合约错误类型
-#[derive(Error, Debug, Clone, Serialize, Deserialize)]
-pub enum ContractError {
-    #[error("Contract compilation failed")]
-    CompilationFailed,
-    #[error("Contract execution failed")]
-    ExecutionFailed,
-    #[error("Invalid contract code")]
-    InvalidCode,
-    #[error("Insufficient gas")]
-    InsufficientGas,
-    #[error("Contract not found")]
-    ContractNotFound,
-    #[error("Method not found")]
-    MethodNotFound,
-    #[error("Invalid parameters")]
-    InvalidParameters,
-    #[error("Runtime error: {0}")]
-    RuntimeError(String),
-}
-
-/// 智能合约状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractState {
-    pub storage: HashMap<String, Vec<u8>>,
-    pub balance: u64,
-    pub owner: String,
-    pub version: u32,
-    pub last_updated: u64,
-    pub code_hash: String,
-    pub is_active: bool,
-    pub call_count: u64,
-    pub total_gas_used: u64,
-}
-
-/// 合约事件
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractEvent {
-    pub contract_address: String,
-    pub event_name: String,
-    pub parameters: Vec<serde_json::Value>,
-    pub timestamp: u64,
-    pub block_height: u64,
-}
-
-/// 合约调用记录
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractCall {
-    pub caller: String,
-    pub contract_address: String,
-    pub method_name: String,
-    pub parameters: Vec<serde_json::Value>,
-    pub gas_used: u64,
-    pub result: serde_json::Value,
-    pub timestamp: u64,
-    pub success: bool,
-}
-
-/// 合约权限
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractPermissions {
-    pub can_deploy: bool,
-    pub can_call: bool,
-    pub can_upgrade: bool,
-    pub can_destroy: bool,
-    pub allowed_methods: Vec<String>,
-    pub restricted_methods: Vec<String>,
-}
-
-/// 合约分类
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub enum ContractCategory {
-    Token,
-    NFT,
-    DeFi,
-    Game,
-    Governance,
-    Utility,
-    Custom,
-}
-
-impl Default for ContractState {
-    fn default() -> Self {
-        Self {
-            storage: HashMap::new(),
-            balance: 0,
-            owner: String::new(),
-            version: 1,
-            last_updated: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            code_hash: String::new(),
-            is_active: true,
-            call_count: 0,
-            total_gas_used: 0,
-        }
-    }
-}
-
-/// 智能合约执行上下文
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExecutionContext {
-    pub caller: String,
-    pub value: u64,
-    pub gas_limit: u64,
-    pub gas_used: u64,
-    pub block_height: u64,
-    pub timestamp: u64,
-    pub contract_address: String,
-}
-
-/// 智能合约执行结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExecutionResult {
-    pub success: bool,
-    pub output: Vec<u8>,
-    pub gas_used: u64,
-    pub logs: Vec<String>,
-    pub state_changes: HashMap<String, Vec<u8>>,
-    pub error_message: Option<String>,
-}
-
-/// 智能合约接口定义
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractInterface {
-    pub name: String,
-    pub methods: Vec<ContractMethod>,
-    pub events: Vec<ContractEventDefinition>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractMethod {
-    pub name: String,
-    pub inputs: Vec<ContractParameter>,
-    pub outputs: Vec<ContractParameter>,
-    pub payable: bool,
-    pub constant: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractParameter {
-    pub name: String,
-    pub param_type: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractEventDefinition {
-    pub name: String,
-    pub parameters: Vec<ContractParameter>,
-}
-
-/// 合约ABI定义
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractABI {
-    pub name: String,
-    pub methods: Vec<ContractMethod>,
-    pub events: Vec<ContractEventDefinition>,
-}
-
-/// 合约模板
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractTemplate {
-    pub name: String,
-    pub description: String,
-    pub category: ContractCategory,
-    pub code: Vec<u8>,
-    pub abi: ContractABI,
-    pub version: String,
-    pub author: String,
-}
-
-/// 智能合约实例
-#[derive(Debug)]
-pub struct ContractInstance {
-    pub address: String,
-    pub code: Vec<u8>,
-    pub state: Arc<Mutex<ContractState>>,
-    pub interface: ContractInterface,
-}
-
-impl ContractInstance {
-    /// 创建新的合约实例
-    /// Create new contract instance
-    pub fn new(
-        address: String,
-        code: Vec<u8>,
-        owner: String,
-        interface: ContractInterface,
-    ) -> Result<Self, ContractError> {
-        let mut state = ContractState::default();
-        state.owner = owner;
-
-        Ok(Self {
-            address,
-            code: code.clone(),
-            state: Arc::new(Mutex::new(state)),
-            interface,
-        })
-    }
-
-    /// 编译 WASM 模块
-    /// Compile WASM module
-    pub fn compile(&mut self) -> Result<(), ContractError> {
-        // 暂时简化实现，等待 WASM 依赖更新
-        if self.code.is_empty() {
-            return Err(ContractError::InvalidCode);
-        }
-        
-        // 这里应该编译 WASM 代码，暂时只做基本验证
-        println!("合约编译: {} (代码大小: {} bytes)", self.address, self.code.len());
-        Ok(())
-    }
-
-    /// 执行合约方法
-    /// Execute contract method
-    pub fn execute(
-        &self,
-        method_name: &str,
-        params: &[u8],
-        context: &ExecutionContext,
-    ) -> Result<ExecutionResult, ContractError> {
-        // 检查方法是否存在
-        let method = self.interface
-            .methods
-            .iter()
-            .find(|m| m.name == method_name)
-            .ok_or(ContractError::MethodNotFound)?;
-
-        // 检查是否为常量方法
-        if method.constant && context.value > 0 {
-            return Err(ContractError::InvalidParameters);
-        }
-
-        // 检查 gas 限制
-        if context.gas_used >= context.gas_limit {
-            return Err(ContractError::InsufficientGas);
-        }
-
-        let mut result = ExecutionResult {
-            success: false,
-            output: Vec::new(),
-            gas_used: 0,
-            logs: Vec::new(),
-            state_changes: HashMap::new(),
-            error_message: None,
-        };
-
-        // 暂时简化实现，模拟合约执行
-        result.success = true;
-        result.gas_used = context.gas_used + 1000;
-        result.output = format!("Method '{}' executed with {} bytes input", method_name, params.len()).into_bytes();
-        result.logs.push(format!("Executed method: {}", method_name));
-
-        Ok(result)
-    }
-
-    /// 使用 Wasmtime 执行合约 (暂时注释掉)
-    /// Execute contract with Wasmtime (temporarily disabled)
-    #[allow(dead_code)]
-    fn execute_with_wasmtime(
-        &self,
-        _module: &str, // module: &wasmtime::Module,
-        method_name: &str,
-        params: &[u8],
-        context: &ExecutionContext,
-    ) -> Result<ExecutionResult, ContractError> {
-        // 暂时简化实现
-        Ok(ExecutionResult {
-            success: true,
-            output: format!("Wasmtime execution of {}", method_name).into_bytes(),
-            gas_used: context.gas_used + 1000,
-            logs: Vec::new(),
-            state_changes: HashMap::new(),
-            error_message: None,
-        })
-    }
-
-    /// 使用 Wasmer 执行合约 (暂时注释掉)
-    /// Execute contract with Wasmer (temporarily disabled)
-    #[allow(dead_code)]
-    fn execute_with_wasmer(
-        &self,
-        _module: &str, // module: &wasmer::Module,
-        method_name: &str,
-        _params: &[u8],
-        context: &ExecutionContext,
-    ) -> Result<ExecutionResult, ContractError> {
-        // 暂时简化实现
-        Ok(ExecutionResult {
-            success: true,
-            output: format!("Wasmer execution of {}", method_name).into_bytes(),
-            gas_used: context.gas_used + 1000,
-            logs: Vec::new(),
-            state_changes: HashMap::new(),
-            error_message: None,
-        })
-    }
-
-    /// 获取合约状态
-    /// Get contract state
-    pub fn get_state(&self) -> ContractState {
-        self.state.lock().unwrap().clone()
-    }
-
-    /// 更新合约状态
-    /// Update contract state
-    pub fn update_state<F>(&self, updater: F) -> Result<(), ContractError>
-    where
-        F: FnOnce(&mut ContractState),
-    {
-        let mut state = self.state.lock().unwrap();
-        updater(&mut state);
-        state.last_updated = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        Ok(())
-    }
-
-    /// 获取存储值
-    /// Get storage value
-    pub fn get_storage(&self, key: &str) -> Option<Vec<u8>> {
-        self.state.lock().unwrap().storage.get(key).cloned()
-    }
-
-    /// 设置存储值
-    /// Set storage value
-    pub fn set_storage(&self, key: String, value: Vec<u8>) -> Result<(), ContractError> {
-        let mut state = self.state.lock().unwrap();
-        state.storage.insert(key, value);
-        state.last_updated = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        Ok(())
-    }
-}
-
-/// 智能合约引擎
-/// Smart contract engine
-pub struct SmartContractEngine {
-    contracts: HashMap<String, ContractInstance>,
-    events: HashMap<String, Vec<ContractEvent>>,
-    calls: HashMap<String, Vec<ContractCall>>,
-    next_address: u64,
-}
-
-impl SmartContractEngine {
-    /// 创建新的合约引擎
-    /// Create new contract engine
-    pub fn new() -> Self {
-        Self {
-            contracts: HashMap::new(),
-            events: HashMap::new(),
-            calls: HashMap::new(),
-            next_address: 1,
-        }
-    }
-
-    /// 部署合约
-    /// Deploy contract
-    pub fn deploy_contract(
-        &mut self,
-        code: Vec<u8>,
-        owner: String,
-        interface: ContractInterface,
-        initial_value: u64,
-    ) -> Result<String, ContractError> {
-        let address = format!("contract_{}", self.next_address);
-        self.next_address += 1;
-
-        let mut contract = ContractInstance::new(
-            address.clone(),
-            code,
-            owner.clone(),
-            interface,
-        )?;
-
-        // 编译合约
-        contract.compile()?;
-
-        // 设置初始余额
-        contract.update_state(|state| {
-            state.balance = initial_value;
-        })?;
-
-        self.contracts.insert(address.clone(), contract);
-
-        Ok(address)
-    }
-
-    /// 调用合约方法
-    /// Call contract method
-    pub fn call_contract(
-        &self,
-        address: &str,
-        method_name: &str,
-        params: &[u8],
-        context: ExecutionContext,
-    ) -> Result<ExecutionResult, ContractError> {
-        let contract = self.contracts
-            .get(address)
-            .ok_or(ContractError::ContractNotFound)?;
-
-        contract.execute(method_name, params, &context)
-    }
-
-    /// 获取合约
-    /// Get contract
-    pub fn get_contract(&self, address: &str) -> Option<&ContractInstance> {
-        self.contracts.get(address)
-    }
-
-    /// 获取所有合约地址
-    /// Get all contract addresses
-    pub fn get_contract_addresses(&self) -> Vec<String> {
-        self.contracts.keys().cloned().collect()
-    }
-
-    /// 获取合约状态
-    /// Get contract state
-    pub fn get_contract_state(&self, address: &str) -> Result<ContractState, ContractError> {
-        let contract = self.contracts
-            .get(address)
-            .ok_or(ContractError::ContractNotFound)?;
-
-        Ok(contract.get_state())
-    }
-}
-
-impl Default for SmartContractEngine {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl SmartContractEngine {
-    /// 获取合约统计信息
-    /// Get contract statistics
-    pub fn get_contract_stats(&self) -> ContractStats {
-        let total_contracts = self.contracts.len();
-        let active_contracts = self.contracts.values()
-            .filter(|contract| {
-                let state = contract.state.lock().unwrap();
-                state.is_active
-            })
-            .count();
-        let total_calls: u64 = self.contracts.values()
-            .map(|contract| {
-                let state = contract.state.lock().unwrap();
-                state.call_count
-            })
-            .sum();
-        let total_gas_used: u64 = self.contracts.values()
-            .map(|contract| {
-                let state = contract.state.lock().unwrap();
-                state.total_gas_used
-            })
-            .sum();
-
-        ContractStats {
-            total_contracts,
-            active_contracts,
-            total_calls,
-            total_gas_used,
-            average_gas_per_call: if total_calls > 0 {
-                total_gas_used / total_calls
-            } else {
-                0
-            },
-        }
-    }
-
-    /// 获取合约事件历史
-    /// Get contract event history
-    pub fn get_contract_events(&self, contract_address: &str, limit: Option<usize>) -> Vec<ContractEvent> {
-        let events = self.events.get(contract_address)
-            .cloned()
-            .unwrap_or_default();
-        
-        let limit = limit.unwrap_or(events.len());
-        events.into_iter().take(limit).collect()
-    }
-
-    /// 获取合约调用历史
-    /// Get contract call history
-    pub fn get_contract_calls(&self, contract_address: &str, limit: Option<usize>) -> Vec<ContractCall> {
-        let calls = self.calls.get(contract_address)
-            .cloned()
-            .unwrap_or_default();
-        
-        let limit = limit.unwrap_or(calls.len());
-        calls.into_iter().take(limit).collect()
-    }
-
-    /// 升级合约
-    /// Upgrade contract
-    pub fn upgrade_contract(&mut self, address: &str, new_code: Vec<u8>, caller: &str) -> Result<(), ContractError> {
-        if let Some(contract) = self.contracts.get_mut(address) {
-            let mut state = contract.state.lock().unwrap();
-            if state.owner != caller {
-                return Err(ContractError::RuntimeError("Not authorized to upgrade".to_string()));
-            }
-
-            // 验证新代码
-            if new_code.is_empty() {
-                return Err(ContractError::InvalidCode);
-            }
-
-            // 更新合约代码
-            state.code_hash = Sha256::digest(&new_code)
-                .iter()
-                .map(|b| format!("{:02x}", b))
-                .collect();
-            state.version += 1;
-            state.last_updated = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-
-            Ok(())
-        } else {
-            Err(ContractError::ContractNotFound)
-        }
-    }
-
-    /// 销毁合约
-    /// Destroy contract
-    pub fn destroy_contract(&mut self, address: &str, caller: &str) -> Result<u64, ContractError> {
-        if let Some(contract) = self.contracts.get_mut(address) {
-            let mut state = contract.state.lock().unwrap();
-            if state.owner != caller {
-                return Err(ContractError::RuntimeError("Not authorized to destroy".to_string()));
-            }
-
-            let balance = state.balance;
-            state.is_active = false;
-            state.balance = 0;
-
-            Ok(balance)
-        } else {
-            Err(ContractError::ContractNotFound)
-        }
-    }
-
-    /// 暂停合约
-    /// Pause contract
-    pub fn pause_contract(&mut self, address: &str, caller: &str) -> Result<(), ContractError> {
-        if let Some(contract) = self.contracts.get_mut(address) {
-            let mut state = contract.state.lock().unwrap();
-            if state.owner != caller {
-                return Err(ContractError::RuntimeError("Not authorized to pause".to_string()));
-            }
-
-            state.is_active = false;
-            Ok(())
-        } else {
-            Err(ContractError::ContractNotFound)
-        }
-    }
-
-    /// 恢复合约
-    /// Resume contract
-    pub fn resume_contract(&mut self, address: &str, caller: &str) -> Result<(), ContractError> {
-        if let Some(contract) = self.contracts.get_mut(address) {
-            let mut state = contract.state.lock().unwrap();
-            if state.owner != caller {
-                return Err(ContractError::RuntimeError("Not authorized to resume".to_string()));
-            }
-
-            state.is_active = true;
-            Ok(())
-        } else {
-            Err(ContractError::ContractNotFound)
-        }
-    }
-
-    /// 批量部署合约
-    /// Batch deploy contracts
-    pub fn batch_deploy(&mut self, deployments: Vec<ContractDeployment>) -> Vec<Result<String, ContractError>> {
-        deployments.into_iter()
-            .map(|deployment| {
-                self.deploy_contract(
-                    deployment.code,
-                    deployment.owner,
-                    deployment.interface,
-                    deployment.initial_value,
-                )
-            })
-            .collect()
-    }
-
-    /// 获取合约模板
-    /// Get contract templates
-    pub fn get_contract_templates(&self) -> Vec<ContractTemplate> {
-        // 创建模拟的WASM代码而不是从文件读取
-        let erc20_code = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]; // 最小WASM模块
-        let storage_code = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]; // 最小WASM模块
-        
-        vec![
-            ContractTemplate {
-                name: "ERC20 Token".to_string(),
-                description: "Standard ERC20 token contract".to_string(),
-                category: ContractCategory::Token,
-                code: erc20_code,
-                abi: ContractABI {
-                    name: "ERC20".to_string(),
-                    methods: vec![
-                        ContractMethod {
-                            name: "transfer".to_string(),
-                            inputs: vec![
-                                ContractParameter { name: "to".to_string(), param_type: "address".to_string() },
-                                ContractParameter { name: "amount".to_string(), param_type: "uint256".to_string() },
-                            ],
-                            outputs: vec![ContractParameter { name: "success".to_string(), param_type: "bool".to_string() }],
-                            payable: false,
-                            constant: false,
-                        },
-                        ContractMethod {
-                            name: "balanceOf".to_string(),
-                            inputs: vec![ContractParameter { name: "owner".to_string(), param_type: "address".to_string() }],
-                            outputs: vec![ContractParameter { name: "balance".to_string(), param_type: "uint256".to_string() }],
-                            payable: false,
-                            constant: true,
-                        },
-                    ],
-                    events: vec![],
-                },
-                version: "1.0.0".to_string(),
-                author: "Blockchain Team".to_string(),
-            },
-            ContractTemplate {
-                name: "Simple Storage".to_string(),
-                description: "Simple key-value storage contract".to_string(),
-                category: ContractCategory::Utility,
-                code: storage_code,
-                abi: ContractABI {
-                    name: "Storage".to_string(),
-                    methods: vec![
-                        ContractMethod {
-                            name: "set".to_string(),
-                            inputs: vec![
-                                ContractParameter { name: "key".to_string(), param_type: "string".to_string() },
-                                ContractParameter { name: "value".to_string(), param_type: "string".to_string() },
-                            ],
-                            outputs: vec![],
-                            payable: false,
-                            constant: false,
-                        },
-                        ContractMethod {
-                            name: "get".to_string(),
-                            inputs: vec![ContractParameter { name: "key".to_string(), param_type: "string".to_string() }],
-                            outputs: vec![ContractParameter { name: "value".to_string(), param_type: "string".to_string() }],
-                            payable: false,
-                            constant: true,
-                        },
-                    ],
-                    events: vec![],
-                },
-                version: "1.0.0".to_string(),
-                author: "Blockchain Team".to_string(),
-            },
-        ]
-    }
-}
-
-/// 合约统计信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractStats {
-    pub total_contracts: usize,
-    pub active_contracts: usize,
-    pub total_calls: u64,
-    pub total_gas_used: u64,
-    pub average_gas_per_call: u64,
-}
-
-/// 合约部署请求
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractDeployment {
-    pub code: Vec<u8>,
-    pub owner: String,
-    pub interface: ContractInterface,
-    pub initial_value: u64,
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_contract_interface() {
-        let interface = ContractInterface {
-            name: "TestContract".to_string(),
-            methods: vec![
-                ContractMethod {
-                    name: "get_balance".to_string(),
-                    inputs: vec![],
-                    outputs: vec![ContractParameter {
-                        name: "balance".to_string(),
-                        param_type: "u64".to_string(),
-                    }],
-                    payable: false,
-                    constant: true,
-                },
-            ],
-            events: vec![],
-        };
-
-        assert_eq!(interface.name, "TestContract");
-        assert_eq!(interface.methods.len(), 1);
-    }
-
-    #[test]
-    fn test_contract_state() {
-        let state = ContractState::default();
-        assert_eq!(state.balance, 0);
-        assert!(state.storage.is_empty());
-    }
-
-    #[test]
-    fn test_execution_context() {
-        let context = ExecutionContext {
-            caller: "alice".to_string(),
-            value: 100,
-            gas_limit: 10000,
-            gas_used: 0,
-            block_height: 1,
-            timestamp: 1234567890,
-            contract_address: "contract_1".to_string(),
-        };
-
-        assert_eq!(context.caller, "alice");
-        assert_eq!(context.value, 100);
-    }
-
-    #[test]
-    fn test_smart_contract_engine() {
-        let mut engine = SmartContractEngine::new();
-        
-        let interface = ContractInterface {
-            name: "TestContract".to_string(),
-            methods: vec![],
-            events: vec![],
-        };
-
-        // 注意：这里使用空的 WASM 代码，实际测试需要有效的 WASM 字节码
-        let code = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]; // 最小 WASM 模块
-        
-        let result = engine.deploy_contract(code, "alice".to_string(), interface, 1000);
-        
-        // 由于我们使用的是无效的 WASM 代码，部署应该失败
-        assert!(result.is_err());
-    }
-}
@@ -0,0 +1,729 @@
+//! # 链上合约
+//!
+//! 合约地址的派生、合约在 [`State`] 中的持久化，以及作为 [`ContractExecutor`] 执行区块中的合约交易。
+//!
+//! 账户名称按 EVM 的规则对应 20 字节地址（见 [`account_address`]）。WebAssembly 合约与 EVM 合约
+//! 共用部署者的 nonce：按 nonce 部署时地址为 `keccak256(rlp([部署者, nonce]))` 的后 20 字节，
+//! 按盐部署时为 `keccak256(0xff ‖ 部署者 ‖ 盐 ‖ keccak256(代码))` 的后 20 字节，与 EVM 的
+//! CREATE 和 CREATE2 一致。
+//!
+//! 每个合约在 `State::contract_states` 中占一项：WebAssembly 合约由引擎写入代码、存储和余额，
+//! EVM 合约的代码和存储由 EVM 直接写入；两者的所有者、接口和后端等编码在该项的 `metadata` 中。
+//! 调用次数、gas 统计以及事件和调用历史只保存在内存中。
+
+use super::{
+    code_hash, ContractBackend, ContractError, ContractInstance, ContractInterface, ContractUpgrades,
+    ExecutionContext, SmartContractEngine,
+};
+use crate::components::cryptography::hash::Keccak256Algorithm;
+use crate::core::state::ContractState as StoredContract;
+use crate::core::{BlockContext, BlockchainError, ContractAction, ContractExecutor, ContractReceipt, ContractTransaction, State, StateJournal, UpgradeOperation};
+use crate::core::{CODE_BYTE_GAS, TX_BASE_GAS};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// 账户名称对应的 20 字节地址：十六进制地址原样解析，其他名称取 `keccak256(名称)` 的后 20 字节
+pub fn account_address(name: &str) -> [u8; 20] {
+    let parsed = name.strip_prefix("0x")
+        .and_then(|digits| hex::decode(digits).ok())
+        .filter(|bytes| bytes.len() == 20);
+    let mut address = [0u8; 20];
+    match parsed {
+        Some(bytes) => address.copy_from_slice(&bytes),
+        None => address.copy_from_slice(&Keccak256Algorithm::digest(name.as_bytes())[12..]),
+    }
+    address
+}
+
+/// 账户在 [`State`] 中的键：小写的 `0x` 十六进制地址
+pub fn account_key(name: &str) -> String {
+    format!("0x{}", hex::encode(account_address(name)))
+}
+
+/// 按 nonce 部署的合约地址：`keccak256(rlp([部署者地址, nonce]))` 的后 20 字节
+pub fn contract_address(deployer: &str, nonce: u64) -> String {
+    // rlp(nonce)：0 为空串，小于 0x80 的单字节为其本身，其余为长度前缀加去掉前导零的大端字节
+    let bytes = nonce.to_be_bytes();
+    let significant = &bytes[nonce.leading_zeros() as usize / 8..];
+    let mut encoded_nonce = Vec::with_capacity(9);
+    match significant {
+        [] => encoded_nonce.push(0x80),
+        [byte] if *byte < 0x80 => encoded_nonce.push(*byte),
+        _ => {
+            encoded_nonce.push(0x80 + significant.len() as u8);
+            encoded_nonce.extend_from_slice(significant);
+        }
+    }
+
+    // 列表总长不超过 55 字节，使用短列表前缀
+    let mut preimage = vec![0xc0 + 21 + encoded_nonce.len() as u8, 0x80 + 20];
+    preimage.extend_from_slice(&account_address(deployer));
+    preimage.extend_from_slice(&encoded_nonce);
+    format!("0x{}", hex::encode(&Keccak256Algorithm::digest(&preimage)[12..]))
+}
+
+/// 按盐部署的合约地址：`keccak256(0xff ‖ 部署者地址 ‖ 盐 ‖ keccak256(代码))` 的后 20 字节
+pub fn contract_address_with_salt(deployer: &str, salt: &[u8; 32], code: &[u8]) -> String {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(&account_address(deployer));
+    preimage.extend_from_slice(salt);
+    preimage.extend_from_slice(&Keccak256Algorithm::digest(code));
+    format!("0x{}", hex::encode(&Keccak256Algorithm::digest(&preimage)[12..]))
+}
+
+/// 合约项 `metadata` 的内容
+#[derive(Serialize, Deserialize)]
+struct ContractMetadata {
+    owner: String,
+    interface: ContractInterface,
+    backend: ContractBackend,
+    version: u32,
+    is_active: bool,
+    non_reentrant: bool,
+    #[serde(default)]
+    upgrades: ContractUpgrades,
+}
+
+impl SmartContractEngine {
+    /// 从保存的状态恢复引擎，例如节点重启后从 `StateStorage` 读出的状态
+    /// Restore engine from persisted state
+    pub fn from_state(state: State) -> Result<Self, ContractError> {
+        let mut engine = Self::new();
+        engine.state = state;
+        engine.load_state()?;
+        Ok(engine)
+    }
+
+    /// 按引擎状态重建合约表
+    /// Rebuild contracts from engine state
+    ///
+    /// 代码未变的合约沿用已编译的模块和调用统计，其余 WebAssembly 合约经运行时缓存重新编译；
+    /// 状态中已不存在的合约从合约表中移除。
+    pub fn load_state(&mut self) -> Result<(), ContractError> {
+        self.contracts = Arc::new(self.load_contracts(&self.state)?);
+        Ok(())
+    }
+
+    /// 按 `state` 中的合约项构建合约表
+    fn load_contracts(&self, state: &State) -> Result<HashMap<String, Arc<ContractInstance>>, ContractError> {
+        state.contract_states.values()
+            .filter(|entry| !entry.metadata.is_empty())
+            .map(|entry| Ok((entry.address.clone(), self.load_contract(entry)?)))
+            .collect()
+    }
+
+    /// 只按引擎状态重新载入指定的合约，状态中已不存在的从合约表中移除
+    fn reload_contracts<'a>(&mut self, addresses: impl IntoIterator<Item = &'a str>) -> Result<(), ContractError> {
+        for address in addresses {
+            let loaded = match self.state.contract_states.get(address).filter(|entry| !entry.metadata.is_empty()) {
+                Some(entry) => Some(self.load_contract(entry)?),
+                None => None,
+            };
+            let contracts = Arc::make_mut(&mut self.contracts);
+            match loaded {
+                Some(contract) => contracts.insert(address.to_string(), contract),
+                None => contracts.remove(address),
+            };
+        }
+        Ok(())
+    }
+
+    /// 由合约项构建合约实例，代码未变时沿用合约表中已编译的模块和调用统计
+    fn load_contract(&self, entry: &StoredContract) -> Result<Arc<ContractInstance>, ContractError> {
+        let metadata: ContractMetadata = serde_json::from_slice(&entry.metadata)
+            .map_err(|e| ContractError::RuntimeError(format!("Invalid contract metadata for {}: {}", entry.address, e)))?;
+        let backend = metadata.backend;
+        let previous = self.contracts
+            .get(&entry.address)
+            .filter(|previous| previous.code == entry.code && previous.backend == backend);
+
+        let mut contract = ContractInstance::new(entry.address.clone(), entry.code.clone(), metadata.owner, metadata.interface)?;
+        contract.backend = backend;
+        contract.upgrades = metadata.upgrades;
+        match (previous, backend) {
+            (Some(previous), _) => contract.module = previous.module.clone(),
+            (None, ContractBackend::Wasm) => contract.compile(&self.runtime)?,
+            (None, ContractBackend::Evm) => {}
+        }
+
+        let stats = previous.map(|previous| previous.get_state()).map(|state| (state.call_count, state.total_gas_used));
+        contract.update_state(|state| {
+            state.code_hash = code_hash(&entry.code);
+            state.version = metadata.version;
+            state.is_active = metadata.is_active;
+            state.non_reentrant = metadata.non_reentrant;
+            if backend == ContractBackend::Wasm {
+                state.storage = entry.storage.clone();
+                state.balance = entry.balance;
+            }
+            (state.call_count, state.total_gas_used) = stats.unwrap_or_default();
+        })?;
+        Ok(Arc::new(contract))
+    }
+
+    /// 部署者下一个合约的地址，地址已被占用时返回错误
+    pub(super) fn new_contract_address(&self, owner: &str, code: &[u8], salt: Option<&[u8; 32]>) -> Result<String, ContractError> {
+        let address = match salt {
+            Some(salt) => contract_address_with_salt(owner, salt, code),
+            None => contract_address(owner, self.state.nonces.get(&account_key(owner)).copied().unwrap_or(0)),
+        };
+        let occupied = self.state.contract_states
+            .get(&address)
+            .is_some_and(|entry| !entry.code.is_empty() || !entry.metadata.is_empty());
+        if occupied || self.contracts.contains_key(&address) {
+            return Err(ContractError::RuntimeError(format!("Contract address {} already in use", address)));
+        }
+        Ok(address)
+    }
+
+    pub(super) fn increment_nonce(&mut self, account: &str) {
+        let key = account_key(account);
+        if let Some(journal) = &mut self.journal {
+            journal.record_nonce(&self.state, &key);
+        }
+        *self.state.nonces.entry(key).or_insert(0) += 1;
+    }
+
+    /// 把合约写回引擎状态
+    pub(super) fn persist(&mut self, address: &str) {
+        if let Some(contract) = self.contracts.get(address).cloned() {
+            self.write_entry(&contract);
+        }
+    }
+
+    /// 把 EVM 执行记录的状态修改并入当前交易的修改日志
+    #[cfg(feature = "web3")]
+    pub(super) fn record_changes(&mut self, changes: StateJournal) {
+        if let Some(journal) = &mut self.journal {
+            journal.extend(changes);
+        }
+    }
+
+    fn write_entry(&mut self, contract: &ContractInstance) {
+        if let Some(journal) = &mut self.journal {
+            journal.record_contract(&self.state, &contract.address);
+        }
+        let state = contract.get_state();
+        let metadata = serde_json::to_vec(&ContractMetadata {
+            owner: state.owner,
+            interface: contract.interface.clone(),
+            backend: contract.backend,
+            version: state.version,
+            is_active: state.is_active,
+            non_reentrant: state.non_reentrant,
+            upgrades: contract.upgrades.clone(),
+        })
+        .expect("contract metadata is serializable");
+
+        match contract.backend {
+            ContractBackend::Wasm => {
+                self.state.contract_states.insert(contract.address.clone(), StoredContract {
+                    storage: state.storage,
+                    balance: state.balance,
+                    metadata,
+                    ..StoredContract::new(contract.address.clone(), contract.code.clone())
+                });
+            }
+            ContractBackend::Evm => {
+                self.state.contract_states
+                    .entry(contract.address.clone())
+                    .or_insert_with(|| StoredContract::new(contract.address.clone(), contract.code.clone()))
+                    .metadata = metadata;
+            }
+        }
+    }
+
+    /// 从账户余额中扣除 `amount`
+    fn debit(&mut self, account: &str, amount: u64) -> Result<(), ContractError> {
+        let key = account_key(account);
+        let balance = self.state.balances.get(&key).copied().unwrap_or(0);
+        if balance < amount {
+            return Err(ContractError::InsufficientBalance);
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.record_balance(&self.state, &key);
+        }
+        self.state.balances.insert(key, balance - amount);
+        Ok(())
+    }
+
+    /// 按代码长度收取 gas，超出 `gas_limit` 时用尽全部 gas 并返回错误
+    fn charge_code(&mut self, code: &[u8], gas_limit: u64) -> Result<(), ContractError> {
+        let gas = (code.len() as u64).saturating_mul(CODE_BYTE_GAS);
+        if gas > gas_limit {
+            self.gas_meter += gas_limit;
+            return Err(ContractError::InsufficientGas);
+        }
+        self.gas_meter += gas;
+        Ok(())
+    }
+
+    /// 执行合约交易中的部署或调用，返回创建的合约地址和输出；消耗的执行 gas 计入 `gas_meter`
+    ///
+    /// 可用的执行 gas 为 `gas_limit` 减去固有 gas。WebAssembly 合约的部署和升级提议按代码长度收取 gas；
+    /// WebAssembly 合约收到的金额从发送方余额转入合约余额，合约转给外部账户的金额计入该账户余额；
+    /// EVM 合约的转账由 EVM 在状态上完成。
+    fn apply(
+        &mut self,
+        transaction: &ContractTransaction,
+        block: &BlockContext,
+    ) -> Result<(Option<String>, Vec<u8>), ContractError> {
+        let context = ExecutionContext {
+            caller: transaction.sender.clone(),
+            value: transaction.value,
+            gas_limit: transaction.gas_limit - TX_BASE_GAS,
+            gas_used: 0,
+            block_height: block.height,
+            timestamp: block.timestamp,
+            contract_address: String::new(),
+        };
+
+        match &transaction.action {
+            ContractAction::Deploy { code, interface, evm: false, salt } => {
+                self.charge_code(code, context.gas_limit)?;
+                self.debit(&transaction.sender, transaction.value)?;
+                let address = self.deploy_wasm(code.clone(), transaction.sender.clone(), interface.clone().into(), transaction.value, *salt)?;
+                Ok((Some(address), Vec::new()))
+            }
+            #[cfg(feature = "web3")]
+            ContractAction::Deploy { code, interface, evm: true, salt } => {
+                let address = self.deploy_evm(code.clone(), interface.clone().into(), *salt, &context)?;
+                Ok((Some(address), Vec::new()))
+            }
+            #[cfg(not(feature = "web3"))]
+            ContractAction::Deploy { evm: true, .. } => {
+                Err(ContractError::RuntimeError("EVM backend requires the web3 feature".to_string()))
+            }
+            ContractAction::Call { contract, method, params } => {
+                let target = self.contracts.get(contract).cloned().ok_or(ContractError::ContractNotFound)?;
+                if target.backend == ContractBackend::Wasm && transaction.value > 0 {
+                    self.debit(&transaction.sender, transaction.value)?;
+                    target.update_state(|state| state.balance += transaction.value)?;
+                }
+
+                let context = ExecutionContext { contract_address: contract.clone(), ..context };
+                let result = self.call_contract(contract, method, params, context)?;
+                if target.backend == ContractBackend::Wasm {
+                    for transfer in result.transfers.iter().filter(|transfer| !self.contracts.contains_key(&transfer.to)) {
+                        let key = account_key(&transfer.to);
+                        if let Some(journal) = &mut self.journal {
+                            journal.record_balance(&self.state, &key);
+                        }
+                        *self.state.balances.entry(key).or_insert(0) += transfer.amount;
+                    }
+                }
+                Ok((None, result.output))
+            }
+            ContractAction::Upgrade { contract, operation } => {
+                if transaction.value > 0 {
+                    return Err(ContractError::InvalidParameters);
+                }
+                let context = ExecutionContext { contract_address: contract.clone(), ..context };
+                let output = match operation {
+                    UpgradeOperation::Propose { code, interface } => {
+                        self.charge_code(code, context.gas_limit)?;
+                        let id = self.propose_upgrade(contract, code.clone(), interface.clone().into(), &context)?;
+                        id.to_le_bytes().to_vec()
+                    }
+                    UpgradeOperation::Approve { id, signature } => {
+                        self.approve_upgrade(contract, *id, signature, &context).map(|_| Vec::new())?
+                    }
+                    UpgradeOperation::Cancel { id } => self.cancel_upgrade(contract, *id, &context).map(|_| Vec::new())?,
+                    UpgradeOperation::Execute { id } => self.execute_upgrade(contract, *id, &context).map(|_| Vec::new())?,
+                };
+                Ok((None, output))
+            }
+        }
+    }
+}
+
+impl ContractExecutor for SmartContractEngine {
+    /// 在 `state` 上执行合约交易：执行期间 `state` 与引擎状态交换，执行后换回，不复制状态
+    ///
+    /// nonce 必须等于发送方当前的 nonce，余额必须足以支付转账金额和最高 gas 费用，否则交易无效。
+    /// 执行失败时按修改日志撤销全部修改并重新载入涉及的合约，只把发送方的 nonce 加一。
+    /// 无论成功与否，发送方都按固有 gas 加上执行消耗的 gas（不超过 `gas_limit`）乘以 `gas_price`
+    /// 支付费用，费用销毁。
+    fn execute(
+        &mut self,
+        state: &mut State,
+        tx_hash: [u8; 32],
+        transaction: &ContractTransaction,
+        block: &BlockContext,
+    ) -> crate::core::Result<ContractReceipt> {
+        std::mem::swap(&mut self.state, state);
+        let receipt = self.execute_in_place(tx_hash, transaction, block);
+        std::mem::swap(&mut self.state, state);
+        receipt
+    }
+
+    /// 按 `state` 重建合约表，引擎自身的状态不变
+    fn restore(&mut self, state: &State) -> crate::core::Result<()> {
+        self.contracts = Arc::new(self.load_contracts(state).map_err(|e| BlockchainError::SmartContractError(e.to_string()))?);
+        Ok(())
+    }
+}
+
+impl SmartContractEngine {
+    /// 在引擎状态上执行合约交易，见 [`ContractExecutor::execute`]
+    fn execute_in_place(
+        &mut self,
+        tx_hash: [u8; 32],
+        transaction: &ContractTransaction,
+        block: &BlockContext,
+    ) -> crate::core::Result<ContractReceipt> {
+        let engine_error = |e: ContractError| BlockchainError::SmartContractError(e.to_string());
+        let sender = account_key(&transaction.sender);
+        let nonce = self.state.nonces.get(&sender).copied().unwrap_or(0);
+        if transaction.nonce != nonce {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Invalid nonce for {}: expected {}, got {}",
+                transaction.sender, nonce, transaction.nonce
+            )));
+        }
+        transaction.check_gas()?;
+        let max_cost = transaction.max_cost().unwrap_or(u64::MAX);
+        if self.state.balances.get(&sender).copied().unwrap_or(0) < max_cost {
+            return Err(BlockchainError::InvalidTransaction("Insufficient balance for value and gas".to_string()));
+        }
+
+        self.gas_meter = 0;
+        self.journal = Some(StateJournal::default());
+        let applied = self.apply(transaction, block);
+        let journal = self.journal.take().unwrap_or_default();
+        let gas_used = TX_BASE_GAS.saturating_add(self.gas_meter).min(transaction.gas_limit);
+        let receipt = match applied {
+            Ok((contract_address, output)) => ContractReceipt {
+                tx_hash,
+                success: true,
+                contract_address,
+                output,
+                gas_used,
+                error: None,
+            },
+            Err(error) => {
+                // 合约表中的实例可能在写回状态之前就已修改（例如收到的转账），与日志中的合约一起重新载入
+                let mut touched: HashSet<String> = journal.contracts().into_iter().map(str::to_string).collect();
+                if let ContractAction::Call { contract, .. } | ContractAction::Upgrade { contract, .. } = &transaction.action {
+                    touched.insert(contract.clone());
+                }
+                journal.revert(&mut self.state);
+                self.reload_contracts(touched.iter().map(String::as_str)).map_err(engine_error)?;
+                ContractReceipt {
+                    tx_hash,
+                    success: false,
+                    contract_address: None,
+                    output: Vec::new(),
+                    gas_used,
+                    error: Some(error.to_string()),
+                }
+            }
+        };
+
+        // 执行前已检查余额足以支付金额和最高费用，合约不能转出发送方的余额，扣费不会失败
+        self.debit(&transaction.sender, gas_used * transaction.gas_price).map_err(engine_error)?;
+        self.state.nonces.insert(sender, transaction.nonce + 1);
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+    use crate::core::transaction::OutPoint;
+    use crate::core::{address_from_public_key, Block, Blockchain, Transaction, TxInput, TxOutput};
+    use crate::core::{BLOCK_GAS_LIMIT, MAX_TX_GAS};
+    use crate::smart_contract_engine::ContractMethod;
+    use crate::smart_contracts::abi::ContractABI;
+
+    /// 计数器合约：`increment` 把存储中的计数加一并返回新值，`fund` 接收转账，`spin` 永不返回
+    const COUNTER_WAT: &str = r#"
+        (module
+          (import "env" "storage_read" (func $read (param i32 i32) (result i32)))
+          (import "env" "storage_write" (func $write (param i32 i32 i32 i32)))
+          (import "env" "return_data_copy" (func $copy (param i32 i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "count")
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "increment") (param i32 i32) (result i64)
+            (if (i32.eq (call $read (i32.const 0) (i32.const 5)) (i32.const 1))
+              (then (call $copy (i32.const 64) (i32.const 0) (i32.const 1))))
+            (i32.store8 (i32.const 64) (i32.add (i32.load8_u (i32.const 64)) (i32.const 1)))
+            (call $write (i32.const 0) (i32.const 5) (i32.const 64) (i32.const 1))
+            (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 1)))
+          (func (export "fund") (param i32 i32) (result i64) (i64.const 0))
+          (func (export "spin") (param i32 i32) (result i64) (loop $l (br $l)) (i64.const 0)))
+    "#;
+
+    const ALICE: &str = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0";
+
+    fn counter_abi() -> ContractABI {
+        let method = |name: &str, payable: bool| ContractMethod {
+            name: name.to_string(),
+            inputs: vec![],
+            outputs: vec![],
+            payable,
+            constant: false,
+        };
+        ContractABI {
+            name: "Counter".to_string(),
+            methods: vec![method("increment", false), method("fund", true), method("spin", true)],
+            events: vec![],
+            storage: vec![],
+        }
+    }
+
+    #[test]
+    fn test_contract_addresses_are_derived_from_deployer() {
+        // 与以太坊 CREATE 的地址一致
+        assert_eq!(contract_address(ALICE, 0), "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d");
+        assert_eq!(contract_address(ALICE, 1), "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8");
+        assert_ne!(contract_address(ALICE, 0x80), contract_address(ALICE, 0x7f));
+        assert_eq!(account_key("alice"), format!("0x{}", hex::encode(account_address("alice"))));
+
+        // EIP-1014 示例 1
+        assert_eq!(
+            contract_address_with_salt("0x0000000000000000000000000000000000000000", &[0u8; 32], &[0x00]),
+            "0x4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"
+        );
+
+        let mut engine = SmartContractEngine::new();
+        let code = COUNTER_WAT.as_bytes().to_vec();
+        let first = engine.deploy_contract(code.clone(), ALICE.to_string(), counter_abi().into(), 0).unwrap();
+        let second = engine.deploy_contract(code.clone(), ALICE.to_string(), counter_abi().into(), 0).unwrap();
+        assert_eq!((first.as_str(), second.as_str()), ("0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d", "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8"));
+        assert_eq!(engine.state().nonces[ALICE], 2);
+
+        // 同一盐和代码只能部署一次
+        let salted = engine.deploy_contract_with_salt(code.clone(), ALICE.to_string(), counter_abi().into(), 0, [7u8; 32]).unwrap();
+        assert_eq!(salted, contract_address_with_salt(ALICE, &[7u8; 32], &code));
+        assert!(engine.deploy_contract_with_salt(code, ALICE.to_string(), counter_abi().into(), 0, [7u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_contracts_survive_restart_from_persisted_state() {
+        let mut engine = SmartContractEngine::new();
+        let address = engine.deploy_contract(COUNTER_WAT.as_bytes().to_vec(), ALICE.to_string(), counter_abi().into(), 5).unwrap();
+        let context = ExecutionContext {
+            caller: ALICE.to_string(),
+            value: 0,
+            gas_limit: 1_000_000,
+            gas_used: 0,
+            block_height: 1,
+            timestamp: 0,
+            contract_address: address.clone(),
+        };
+        engine.call_contract(&address, "increment", &[], context.clone()).unwrap();
+        engine.pause_contract(&address, ALICE).unwrap();
+        engine.resume_contract(&address, ALICE).unwrap();
+
+        // 代码、存储和余额都写入状态并计入状态根
+        let stored = &engine.state().contract_states[&address];
+        assert_eq!(stored.get_storage("count"), Some(&vec![1]));
+        assert_eq!(stored.balance, 5);
+        let root = engine.state().get_state_root();
+
+        let bytes = engine.state().serialize().unwrap();
+        let mut restored = SmartContractEngine::from_state(State::deserialize(&bytes).unwrap()).unwrap();
+        assert_eq!(restored.state().get_state_root(), root);
+        assert_eq!(restored.get_contract_state(&address).unwrap().balance, 5);
+        let result = restored.call_contract(&address, "increment", &[], context).unwrap();
+        assert_eq!(result.output, vec![2]);
+        assert_ne!(restored.state().get_state_root(), root);
+
+        // 部署者的 nonce 同样恢复，下一个地址不会重复
+        let next = restored.deploy_contract(COUNTER_WAT.as_bytes().to_vec(), ALICE.to_string(), counter_abi().into(), 0).unwrap();
+        assert_eq!(next, contract_address(ALICE, 1));
+    }
+
+    /// 由种子确定的 secp256k1 账户：(私钥, 地址)
+    fn account(seed: u8) -> ([u8; 32], String) {
+        let private_key = [seed; 32];
+        let public_key = SignatureEngine::new().derive_public_key(&private_key, "ecdsa").unwrap();
+        (private_key, address_from_public_key(&public_key))
+    }
+
+    fn signed(contract: ContractTransaction, private_key: &[u8]) -> Transaction {
+        let mut tx = Transaction::contract(contract);
+        tx.sign(private_key).unwrap();
+        tx
+    }
+
+    async fn contract_chain() -> Blockchain {
+        let mut chain = Blockchain::new(1, Block::create_genesis_block().unwrap());
+        chain.network.initialize().await.unwrap();
+        chain.set_contract_executor(Box::new(SmartContractEngine::new()));
+        chain
+    }
+
+    #[tokio::test]
+    async fn test_contract_transactions_execute_in_blocks() {
+        let (key, alice) = account(1);
+        let mut chain = contract_chain().await;
+        chain.state.set_balance(&alice, 100_000_000).await.unwrap();
+
+        // 未签名或冒用发送方的交易不能进入交易池
+        let deploy = ContractTransaction::deploy(alice.clone(), 0, COUNTER_WAT.as_bytes().to_vec(), counter_abi(), 10, 1_000_000);
+        assert!(chain.add_transaction(Transaction::contract(deploy.clone())).await.is_err());
+        assert!(chain.add_transaction(signed(deploy.clone(), &account(2).0)).await.is_err());
+
+        // 部署交易，合约地址由发送方和 nonce 决定
+        let deploy = signed(deploy, &key);
+        chain.add_transaction(deploy.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        let receipt = chain.get_contract_receipt(&deploy.hash()).unwrap().clone();
+        let address = receipt.contract_address.clone().unwrap();
+        assert!(receipt.success);
+        assert_eq!(address, contract_address(&alice, 0));
+        // 部署按代码长度收取 gas，费用和转账金额一起从发送方扣除
+        assert_eq!(receipt.gas_used, TX_BASE_GAS + COUNTER_WAT.len() as u64 * CODE_BYTE_GAS);
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), 100_000_000 - 10 - receipt.gas_used);
+        let root = chain.state.get_state_root();
+
+        // 调用交易修改链上状态中的合约存储
+        let call = |nonce, method: &str| signed(
+            ContractTransaction::call(alice.clone(), nonce, address.clone(), method.to_string(), vec![], 0, 1_000_000),
+            &key,
+        );
+        let increment = call(1, "increment");
+        chain.add_transaction(increment.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        assert_eq!(chain.get_contract_receipt(&increment.hash()).unwrap().output, vec![1]);
+        assert_eq!(chain.state.contract_states[&address].get_storage("count"), Some(&vec![1]));
+        assert_eq!(chain.state.contract_states[&address].balance, 10);
+        assert_ne!(chain.state.get_state_root(), root);
+
+        // 执行失败的交易仍然打包，递增 nonce 并支付固有 gas
+        let balance = chain.state.get_balance(&alice).await.unwrap();
+        let failing = call(2, "missing");
+        chain.add_transaction(failing.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        let receipt = chain.get_contract_receipt(&failing.hash()).unwrap();
+        assert!(!receipt.success);
+        assert_eq!(receipt.gas_used, TX_BASE_GAS);
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), balance - TX_BASE_GAS);
+        assert_eq!(chain.state.nonces[&alice], 3);
+        assert_eq!(chain.state.contract_states[&address].get_storage("count"), Some(&vec![1]));
+
+        // nonce 过期或跳号的交易在入池时被拒绝
+        assert!(chain.add_transaction(call(0, "increment")).await.is_err());
+        assert!(chain.add_transaction(call(4, "increment")).await.is_err());
+
+        // 同一发送方的连续交易可以一起入池，并按 nonce 顺序打包
+        let (third, fourth) = (call(3, "increment"), call(4, "increment"));
+        chain.add_transaction(third.clone()).await.unwrap();
+        chain.add_transaction(fourth.clone()).await.unwrap();
+        let block = chain.mine_block().await.unwrap();
+        assert_eq!(block.transactions.iter().map(Transaction::hash).collect::<Vec<_>>(), vec![third.hash(), fourth.hash()]);
+        assert_eq!(chain.state.contract_states[&address].get_storage("count"), Some(&vec![3]));
+
+        // 绕过入池检查的过期交易在出块时被丢弃，链继续出块
+        chain.transaction_pool.push(call(0, "increment"));
+        let fifth = call(5, "increment");
+        chain.transaction_pool.push(fifth.clone());
+        let block = chain.mine_block().await.unwrap();
+        assert_eq!(block.transactions.iter().map(Transaction::hash).collect::<Vec<_>>(), vec![fifth.hash()]);
+        assert!(chain.transaction_pool.is_empty());
+        assert_eq!(chain.state.nonces[&alice], 6);
+        assert_eq!(chain.current_height, 5);
+    }
+
+    #[tokio::test]
+    async fn test_transferred_balance_funds_contract_deployment() {
+        let (alice_key, alice) = account(1);
+        let (bob_key, bob) = account(2);
+        let mut chain = contract_chain().await;
+        chain.state.set_balance(&bob, 2_000_000).await.unwrap();
+
+        // 普通转账记入的余额与合约交易使用同一个账户键
+        let mut transfer = Transaction::new(
+            vec![TxInput::new(OutPoint::new([9u8; 32], 0), 1_000_050, bob.clone())],
+            vec![TxOutput::new(1_000_050, alice.clone())],
+        );
+        transfer.sign(&bob_key).unwrap();
+        chain.add_transaction(transfer).await.unwrap();
+        chain.mine_block().await.unwrap();
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), 1_000_050);
+
+        let deploy = signed(
+            ContractTransaction::deploy(alice.clone(), 0, COUNTER_WAT.as_bytes().to_vec(), counter_abi(), 30, 1_000_000),
+            &alice_key,
+        );
+        chain.add_transaction(deploy.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        let receipt = chain.get_contract_receipt(&deploy.hash()).unwrap();
+        assert!(receipt.success);
+        let address = receipt.contract_address.clone().unwrap();
+        let balance = 1_000_050 - 30 - receipt.gas_used;
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), balance);
+        assert_eq!(chain.state.contract_states[&address].balance, 30);
+
+        // 余额不足以支付金额和最高 gas 费用的交易在入池时被拒绝
+        let overdrawn = signed(
+            ContractTransaction::call(alice.clone(), 1, address, "fund".to_string(), vec![], balance - 100_000 + 1, 100_000),
+            &alice_key,
+        );
+        assert!(chain.add_transaction(overdrawn).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_contract_gas_is_capped_and_charged() {
+        let (key, alice) = account(1);
+        let mut chain = contract_chain().await;
+        chain.state.set_balance(&alice, 100_000_000).await.unwrap();
+        let deploy = signed(ContractTransaction::deploy(alice.clone(), 0, COUNTER_WAT.as_bytes().to_vec(), counter_abi(), 0, 1_000_000), &key);
+        chain.add_transaction(deploy.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        let address = chain.get_contract_receipt(&deploy.hash()).unwrap().contract_address.clone().unwrap();
+        let spin = |nonce, gas_limit| signed(
+            ContractTransaction::call(alice.clone(), nonce, address.clone(), "spin".to_string(), vec![], 0, gas_limit),
+            &key,
+        );
+
+        // 超过单笔上限的交易不能入池
+        assert!(chain.add_transaction(spin(1, MAX_TX_GAS + 1)).await.is_err());
+
+        // 死循环在 gas 用尽时终止，发送方支付全部 gas，转给合约的金额退回
+        let balance = chain.state.get_balance(&alice).await.unwrap();
+        let looping = signed(
+            ContractTransaction::call(alice.clone(), 1, address.clone(), "spin".to_string(), vec![], 500, 1_000_000),
+            &key,
+        );
+        chain.add_transaction(looping.clone()).await.unwrap();
+        chain.mine_block().await.unwrap();
+        let receipt = chain.get_contract_receipt(&looping.hash()).unwrap();
+        assert!(!receipt.success);
+        assert_eq!(receipt.gas_used, 1_000_000);
+        assert_eq!(chain.state.get_balance(&alice).await.unwrap(), balance - 1_000_000);
+        assert_eq!(chain.state.contract_states[&address].balance, 0);
+
+        // 执行器撤销失败交易时重新载入了合约，之后的转账从原余额累加
+        let fund = signed(
+            ContractTransaction::call(alice.clone(), 2, address.clone(), "fund".to_string(), vec![], 7, 100_000),
+            &key,
+        );
+        chain.add_transaction(fund).await.unwrap();
+        chain.mine_block().await.unwrap();
+        assert_eq!(chain.state.contract_states[&address].balance, 7);
+
+        // 超出区块 gas 上限的交易留在交易池中，由下一个区块打包
+        let per_block = BLOCK_GAS_LIMIT / MAX_TX_GAS;
+        for nonce in 3..per_block + 4 {
+            chain.add_transaction(spin(nonce, MAX_TX_GAS)).await.unwrap();
+        }
+        assert_eq!(chain.mine_block().await.unwrap().transactions.len() as u64, per_block);
+        assert_eq!(chain.transaction_pool.len(), 1);
+        assert_eq!(chain.mine_block().await.unwrap().transactions.len(), 1);
+
+        // 从网络收到的超出区块 gas 上限的区块被拒绝
+        let transactions = (per_block + 4..2 * per_block + 5).map(|nonce| spin(nonce, MAX_TX_GAS)).collect();
+        let tip = chain.chain_head();
+        let mut block = Block::new(tip.hash, transactions, tip.height + 1, 1).unwrap();
+        chain.consensus.mine_block(&mut block).await.unwrap();
+        assert!(chain.import_block(block).await.is_err());
+        assert_eq!(chain.chain_head().hash, tip.hash);
+    }
+}
@@ -0,0 +1,165 @@
+//! # EVM gas 表
+//!
+//! 按 Cancun 规则计费：EIP-2929 冷/热访问、EIP-2200/3529 的 SSTORE 计费与退款、
+//! EIP-3860 初始化代码按字计费。
+
+use super::U256;
+
+/// 停止、返回等免费指令
+pub const ZERO: u64 = 0;
+/// `ADDRESS`、`CALLER`、`POP` 等读取环境的指令
+pub const BASE: u64 = 2;
+/// 加减、比较、位运算、`PUSH`/`DUP`/`SWAP` 和内存读写
+pub const VERY_LOW: u64 = 3;
+/// 乘除、取模、`SIGNEXTEND`、`SELFBALANCE`
+pub const LOW: u64 = 5;
+/// `ADDMOD`、`MULMOD`、`JUMP`
+pub const MID: u64 = 8;
+/// `JUMPI`、`EXP` 的基础费用
+pub const HIGH: u64 = 10;
+pub const JUMPDEST: u64 = 1;
+pub const BLOCKHASH: u64 = 20;
+/// `EXP` 指数每字节
+pub const EXP_BYTE: u64 = 50;
+pub const KECCAK256: u64 = 30;
+pub const KECCAK256_WORD: u64 = 6;
+/// 拷贝类指令每字
+pub const COPY_WORD: u64 = 3;
+pub const MEMORY_WORD: u64 = 3;
+pub const QUAD_COEFFICIENT_DIV: u64 = 512;
+
+/// 本交易中已访问过的账户或存储槽
+pub const WARM_STORAGE_READ: u64 = 100;
+/// 首次访问账户
+pub const COLD_ACCOUNT_ACCESS: u64 = 2600;
+/// 首次访问存储槽
+pub const COLD_SLOAD: u64 = 2100;
+/// 存储槽从零写为非零
+pub const SSTORE_SET: u64 = 20000;
+/// 存储槽从非零改写（不含冷访问费用）
+pub const SSTORE_RESET: u64 = 5000 - COLD_SLOAD;
+/// 清空存储槽的退款（EIP-3529）
+pub const SSTORE_CLEARS_REFUND: i64 = 4800;
+/// 剩余 gas 不超过该值时禁止 SSTORE（EIP-2200）
+pub const SSTORE_SENTRY: u64 = 2300;
+pub const TRANSIENT_STORAGE: u64 = 100;
+
+pub const LOG: u64 = 375;
+pub const LOG_TOPIC: u64 = 375;
+pub const LOG_DATA_BYTE: u64 = 8;
+
+pub const CREATE: u64 = 32000;
+/// 初始化代码每字（EIP-3860）
+pub const INITCODE_WORD: u64 = 2;
+/// 部署代码每字节
+pub const CODE_DEPOSIT_BYTE: u64 = 200;
+/// 调用时转账
+pub const CALL_VALUE: u64 = 9000;
+/// 转账时随调用附送的 gas
+pub const CALL_STIPEND: u64 = 2300;
+/// 转账到空账户
+pub const NEW_ACCOUNT: u64 = 25000;
+pub const SELFDESTRUCT: u64 = 5000;
+
+/// 交易基础费用
+pub const TRANSACTION: u64 = 21000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const TX_DATA_NON_ZERO: u64 = 16;
+/// 退款上限为已用 gas 的 1/5（EIP-3529）
+pub const MAX_REFUND_QUOTIENT: u64 = 5;
+
+/// 部署代码上限（EIP-170）
+pub const MAX_CODE_SIZE: usize = 24576;
+/// 初始化代码上限（EIP-3860）
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// 字节数向上取整为字数
+pub fn words(len: usize) -> u64 {
+    (len as u64).div_ceil(32)
+}
+
+/// 内存扩展到 `words` 字的总费用
+pub fn memory_cost(words: u64) -> u64 {
+    MEMORY_WORD * words + words * words / QUAD_COEFFICIENT_DIV
+}
+
+/// 交易的固有 gas
+pub fn intrinsic(data: &[u8], create: bool) -> u64 {
+    let zeros = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zeros = data.len() as u64 - zeros;
+    let mut gas = TRANSACTION + zeros * TX_DATA_ZERO + non_zeros * TX_DATA_NON_ZERO;
+    if create {
+        gas += CREATE + INITCODE_WORD * words(data.len());
+    }
+    gas
+}
+
+/// SSTORE 的费用和退款变化（不含冷访问费用）
+///
+/// `original` 为交易开始时的值，`current` 为写入前的值，`new` 为写入的值。
+pub fn sstore(original: U256, current: U256, new: U256) -> (u64, i64) {
+    if current == new {
+        return (WARM_STORAGE_READ, 0);
+    }
+    if original == current {
+        if original.is_zero() {
+            return (SSTORE_SET, 0);
+        }
+        let refund = if new.is_zero() { SSTORE_CLEARS_REFUND } else { 0 };
+        return (SSTORE_RESET, refund);
+    }
+
+    let mut refund = 0;
+    if !original.is_zero() {
+        if current.is_zero() {
+            refund -= SSTORE_CLEARS_REFUND;
+        } else if new.is_zero() {
+            refund += SSTORE_CLEARS_REFUND;
+        }
+    }
+    if original == new {
+        refund += if original.is_zero() {
+            (SSTORE_SET - WARM_STORAGE_READ) as i64
+        } else {
+            (SSTORE_RESET - WARM_STORAGE_READ) as i64
+        };
+    }
+    (WARM_STORAGE_READ, refund)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u64) -> U256 {
+        U256::from(value)
+    }
+
+    #[test]
+    fn test_memory_cost_is_quadratic() {
+        assert_eq!(memory_cost(1), 3);
+        assert_eq!(memory_cost(32), 98);
+        assert_eq!(memory_cost(1024), 3 * 1024 + 2048);
+    }
+
+    #[test]
+    fn test_sstore_schedule_matches_eip_2200() {
+        // 0 -> 0 -> 1：新建
+        assert_eq!(sstore(word(0), word(0), word(1)), (SSTORE_SET, 0));
+        // 1 -> 1 -> 0：清空并退款
+        assert_eq!(sstore(word(1), word(1), word(0)), (SSTORE_RESET, SSTORE_CLEARS_REFUND));
+        // 1 -> 0 -> 1：撤销清空，收回退款并退还重置费用
+        assert_eq!(sstore(word(1), word(0), word(1)), (WARM_STORAGE_READ, -SSTORE_CLEARS_REFUND + 2800));
+        // 0 -> 1 -> 0：撤销新建
+        assert_eq!(sstore(word(0), word(1), word(0)), (WARM_STORAGE_READ, 19900));
+        // 未改变
+        assert_eq!(sstore(word(1), word(2), word(2)), (WARM_STORAGE_READ, 0));
+    }
+
+    #[test]
+    fn test_intrinsic_gas_counts_calldata_and_initcode() {
+        assert_eq!(intrinsic(&[], false), 21000);
+        assert_eq!(intrinsic(&[0, 1], false), 21000 + 4 + 16);
+        assert_eq!(intrinsic(&[0; 33], true), 21000 + 33 * 4 + 32000 + 2 * 2);
+    }
+}
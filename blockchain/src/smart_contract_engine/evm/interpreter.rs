@@ -0,0 +1,801 @@
+//! # EVM 解释器
+//!
+//! 每个调用帧有独立的栈（最多 1024 项）、按字扩展的内存和 gas 余额。
+//! `CALL`/`CREATE` 系列指令递归执行子帧：子帧失败只回滚它自己的状态修改，
+//! 未用完的 gas 退回调用方，异常终止的帧耗尽全部 gas。
+
+use super::opcode::*;
+use super::world::World;
+use super::{create2_address, create_address, gas, keccak, precompiles};
+use super::{Address, BlockEnv, Halt, Log, Status, H256, U256, U512};
+
+/// 栈深度上限
+const STACK_LIMIT: usize = 1024;
+/// 调用深度上限
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+
+/// 调用帧的输入
+#[derive(Debug, Clone)]
+pub(super) struct Message {
+    /// `CALLER` 返回的地址
+    pub caller: Address,
+    /// 执行上下文地址，存储、`ADDRESS` 和 `SELFBALANCE` 都针对该地址
+    pub address: Address,
+    /// 加载代码的地址，`DELEGATECALL` 和 `CALLCODE` 时与 `address` 不同
+    pub code_address: Address,
+    /// `CALLVALUE` 返回的值
+    pub value: U256,
+    /// 是否从 `caller` 向 `address` 转账 `value`
+    pub transfer: bool,
+    pub input: Vec<u8>,
+    pub gas: u64,
+    pub is_static: bool,
+    pub depth: usize,
+}
+
+/// 调用帧的结果
+pub(super) struct FrameResult {
+    pub status: Status,
+    /// 退回调用方的 gas
+    pub gas_left: u64,
+    /// 返回数据或回滚数据
+    pub output: Vec<u8>,
+    /// CREATE 成功时新合约的地址
+    pub created: Option<Address>,
+}
+
+impl FrameResult {
+    fn halt(halt: Halt, gas_left: u64) -> Self {
+        Self { status: Status::Halt(halt), gas_left, output: Vec::new(), created: None }
+    }
+}
+
+/// 帧正常结束的方式
+enum Exit {
+    Return(Vec<u8>),
+    Revert(Vec<u8>),
+}
+
+/// 执行中的调用帧
+struct Frame {
+    code: Vec<u8>,
+    /// 每个字节是否为合法的跳转目标（不在 PUSH 立即数中的 JUMPDEST）
+    jumpdests: Vec<bool>,
+    pc: usize,
+    stack: Vec<U256>,
+    memory: Vec<u8>,
+    gas: u64,
+    /// 最近一次子调用的返回数据
+    return_data: Vec<u8>,
+    message: Message,
+}
+
+impl Frame {
+    fn new(code: Vec<u8>, message: Message) -> Self {
+        let mut jumpdests = vec![false; code.len()];
+        let mut pc = 0;
+        while pc < code.len() {
+            jumpdests[pc] = code[pc] == JUMPDEST;
+            pc += 1 + immediate_len(code[pc]);
+        }
+        Self {
+            code,
+            jumpdests,
+            pc: 0,
+            stack: Vec::with_capacity(32),
+            memory: Vec::new(),
+            gas: message.gas,
+            return_data: Vec::new(),
+            message,
+        }
+    }
+
+    fn charge(&mut self, cost: u64) -> Result<(), Halt> {
+        self.gas = self.gas.checked_sub(cost).ok_or(Halt::OutOfGas)?;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<U256, Halt> {
+        self.stack.pop().ok_or(Halt::StackUnderflow)
+    }
+
+    fn push(&mut self, value: U256) -> Result<(), Halt> {
+        if self.stack.len() == STACK_LIMIT {
+            return Err(Halt::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn push_with(&mut self, cost: u64, value: U256) -> Result<(), Halt> {
+        self.charge(cost)?;
+        self.push(value)
+    }
+
+    fn unary(&mut self, cost: u64, op: impl FnOnce(U256) -> U256) -> Result<(), Halt> {
+        self.charge(cost)?;
+        let a = self.pop()?;
+        self.push(op(a))
+    }
+
+    fn binary(&mut self, cost: u64, op: impl FnOnce(U256, U256) -> U256) -> Result<(), Halt> {
+        self.charge(cost)?;
+        let (a, b) = (self.pop()?, self.pop()?);
+        self.push(op(a, b))
+    }
+
+    fn ternary(&mut self, cost: u64, op: impl FnOnce(U256, U256, U256) -> U256) -> Result<(), Halt> {
+        self.charge(cost)?;
+        let (a, b, c) = (self.pop()?, self.pop()?, self.pop()?);
+        self.push(op(a, b, c))
+    }
+
+    /// 为访问 `[offset, offset + len)` 扩展内存并计费，返回转换后的偏移和长度
+    fn memory_range(&mut self, offset: U256, len: U256) -> Result<(usize, usize), Halt> {
+        if len.is_zero() {
+            return Ok((0, 0));
+        }
+        if offset.bits() > 32 || len.bits() > 32 {
+            return Err(Halt::OutOfGas);
+        }
+        let (offset, len) = (offset.low_u64() as usize, len.low_u64() as usize);
+        let current = gas::words(self.memory.len());
+        let required = gas::words(offset + len);
+        if required > current {
+            self.charge(gas::memory_cost(required) - gas::memory_cost(current))?;
+            self.memory.resize(required as usize * 32, 0);
+        }
+        Ok((offset, len))
+    }
+
+    /// 拷贝类指令：扩展目标内存并按字计费，返回目标范围
+    fn copy_range(&mut self, dest: U256, len: U256) -> Result<(usize, usize), Halt> {
+        self.charge(gas::VERY_LOW)?;
+        let (dest, len) = self.memory_range(dest, len)?;
+        self.charge(gas::COPY_WORD * gas::words(len))?;
+        Ok((dest, len))
+    }
+
+    fn jump(&mut self, dest: U256) -> Result<(), Halt> {
+        let dest = usize::try_from(dest).map_err(|_| Halt::InvalidJump)?;
+        if !self.jumpdests.get(dest).copied().unwrap_or(false) {
+            return Err(Halt::InvalidJump);
+        }
+        self.pc = dest;
+        Ok(())
+    }
+
+    fn read_only(&self) -> Result<(), Halt> {
+        if self.message.is_static {
+            return Err(Halt::StaticStateChange);
+        }
+        Ok(())
+    }
+}
+
+/// 一次交易或顶层调用中执行各调用帧的解释器
+pub(super) struct Machine<'a> {
+    pub world: World<'a>,
+    pub block: BlockEnv,
+    pub origin: Address,
+    pub gas_price: U256,
+}
+
+impl Machine<'_> {
+    /// 执行消息调用：转账后运行预编译合约或目标代码
+    pub fn call(&mut self, message: Message) -> FrameResult {
+        if message.depth > CALL_DEPTH_LIMIT {
+            return FrameResult::halt(Halt::CallDepthExceeded, message.gas);
+        }
+        let checkpoint = self.world.checkpoint();
+        if message.transfer && !self.world.transfer(&message.caller, &message.address, message.value) {
+            return FrameResult::halt(Halt::InsufficientBalance, message.gas);
+        }
+
+        if let Some(result) = precompiles::run(&message.code_address, &message.input, message.gas) {
+            return match result {
+                Ok((output, cost)) => FrameResult { status: Status::Success, gas_left: message.gas - cost, output, created: None },
+                Err(halt) => {
+                    self.world.revert(checkpoint);
+                    FrameResult::halt(halt, 0)
+                }
+            };
+        }
+
+        let code = self.world.code(&message.code_address).to_vec();
+        if code.is_empty() {
+            return FrameResult { status: Status::Success, gas_left: message.gas, output: Vec::new(), created: None };
+        }
+        let mut frame = Frame::new(code, message);
+        let result = self.run(&mut frame);
+        self.finish(&frame, result, checkpoint)
+    }
+
+    /// 执行 CREATE/CREATE2：递增创建者 nonce，运行初始化代码并保存其返回的代码
+    pub fn create(
+        &mut self,
+        caller: Address,
+        value: U256,
+        init_code: Vec<u8>,
+        gas_limit: u64,
+        salt: Option<H256>,
+        depth: usize,
+    ) -> FrameResult {
+        if depth > CALL_DEPTH_LIMIT {
+            return FrameResult::halt(Halt::CallDepthExceeded, gas_limit);
+        }
+        if self.world.balance(&caller) < value {
+            return FrameResult::halt(Halt::InsufficientBalance, gas_limit);
+        }
+        let nonce = self.world.nonce(&caller);
+        let Some(next_nonce) = nonce.checked_add(1) else {
+            return FrameResult::halt(Halt::NonceOverflow, gas_limit);
+        };
+        self.world.set_nonce(&caller, next_nonce);
+
+        let address = match salt {
+            Some(salt) => create2_address(&caller, salt, &init_code),
+            None => create_address(&caller, nonce),
+        };
+        self.world.warm_account(&address);
+        if self.world.has_collision(&address) {
+            return FrameResult::halt(Halt::CreateCollision, 0);
+        }
+
+        let checkpoint = self.world.checkpoint();
+        self.world.create_account(&address);
+        self.world.transfer(&caller, &address, value);
+
+        let message = Message {
+            caller,
+            address,
+            code_address: address,
+            value,
+            transfer: false,
+            input: Vec::new(),
+            gas: gas_limit,
+            is_static: false,
+            depth,
+        };
+        let mut frame = Frame::new(init_code, message);
+        let result = match self.run(&mut frame) {
+            Ok(Exit::Return(code)) => self.deposit(&mut frame, &address, code),
+            other => other,
+        };
+        let mut outcome = self.finish(&frame, result, checkpoint);
+        if outcome.status == Status::Success {
+            outcome.created = Some(address);
+        }
+        outcome
+    }
+
+    /// 校验并保存初始化代码返回的合约代码
+    fn deposit(&mut self, frame: &mut Frame, address: &Address, code: Vec<u8>) -> Result<Exit, Halt> {
+        if code.len() > gas::MAX_CODE_SIZE {
+            return Err(Halt::CodeSizeLimit);
+        }
+        // EIP-3541：0xEF 开头的代码保留给 EOF
+        if code.first() == Some(&0xef) {
+            return Err(Halt::InvalidCodePrefix);
+        }
+        frame.charge(gas::CODE_DEPOSIT_BYTE * code.len() as u64)?;
+        self.world.set_code(address, code);
+        Ok(Exit::Return(Vec::new()))
+    }
+
+    fn finish(&mut self, frame: &Frame, result: Result<Exit, Halt>, checkpoint: super::world::Checkpoint) -> FrameResult {
+        let (status, gas_left, output) = match result {
+            Ok(Exit::Return(output)) => (Status::Success, frame.gas, output),
+            Ok(Exit::Revert(output)) => (Status::Revert, frame.gas, output),
+            Err(halt) => (Status::Halt(halt), 0, Vec::new()),
+        };
+        if status != Status::Success {
+            self.world.revert(checkpoint);
+        }
+        FrameResult { status, gas_left, output, created: None }
+    }
+
+    /// 访问账户的费用，首次访问时为冷访问
+    fn access_cost(&mut self, address: &Address) -> u64 {
+        if self.world.warm_account(address) {
+            gas::COLD_ACCOUNT_ACCESS
+        } else {
+            gas::WARM_STORAGE_READ
+        }
+    }
+
+    fn run(&mut self, frame: &mut Frame) -> Result<Exit, Halt> {
+        loop {
+            let opcode = frame.code.get(frame.pc).copied().unwrap_or(STOP);
+            frame.pc += 1;
+            match opcode {
+                STOP => return Ok(Exit::Return(Vec::new())),
+                ADD => frame.binary(gas::VERY_LOW, |a, b| a.overflowing_add(b).0)?,
+                MUL => frame.binary(gas::LOW, |a, b| a.overflowing_mul(b).0)?,
+                SUB => frame.binary(gas::VERY_LOW, |a, b| a.overflowing_sub(b).0)?,
+                DIV => frame.binary(gas::LOW, |a, b| a.checked_div(b).unwrap_or_default())?,
+                SDIV => frame.binary(gas::LOW, signed_div)?,
+                MOD => frame.binary(gas::LOW, |a, b| a.checked_rem(b).unwrap_or_default())?,
+                SMOD => frame.binary(gas::LOW, signed_rem)?,
+                ADDMOD => frame.ternary(gas::MID, |a, b, n| modulo(U512::from(a) + U512::from(b), n))?,
+                MULMOD => frame.ternary(gas::MID, |a, b, n| modulo(a.full_mul(b), n))?,
+                EXP => {
+                    let (base, exponent) = (frame.pop()?, frame.pop()?);
+                    frame.charge(gas::HIGH + gas::EXP_BYTE * exponent.bits().div_ceil(8) as u64)?;
+                    frame.push(base.overflowing_pow(exponent).0)?;
+                }
+                SIGNEXTEND => frame.binary(gas::LOW, sign_extend)?,
+
+                LT => frame.binary(gas::VERY_LOW, |a, b| flag(a < b))?,
+                GT => frame.binary(gas::VERY_LOW, |a, b| flag(a > b))?,
+                SLT => frame.binary(gas::VERY_LOW, |a, b| flag(signed_lt(a, b)))?,
+                SGT => frame.binary(gas::VERY_LOW, |a, b| flag(signed_lt(b, a)))?,
+                EQ => frame.binary(gas::VERY_LOW, |a, b| flag(a == b))?,
+                ISZERO => frame.unary(gas::VERY_LOW, |a| flag(a.is_zero()))?,
+                AND => frame.binary(gas::VERY_LOW, |a, b| a & b)?,
+                OR => frame.binary(gas::VERY_LOW, |a, b| a | b)?,
+                XOR => frame.binary(gas::VERY_LOW, |a, b| a ^ b)?,
+                NOT => frame.unary(gas::VERY_LOW, |a| !a)?,
+                BYTE => frame.binary(gas::VERY_LOW, |index, value| match usize::try_from(index) {
+                    Ok(index) if index < 32 => U256::from(value.byte(31 - index)),
+                    _ => U256::zero(),
+                })?,
+                SHL => frame.binary(gas::VERY_LOW, |shift, value| match usize::try_from(shift) {
+                    Ok(shift) if shift < 256 => value << shift,
+                    _ => U256::zero(),
+                })?,
+                SHR => frame.binary(gas::VERY_LOW, |shift, value| match usize::try_from(shift) {
+                    Ok(shift) if shift < 256 => value >> shift,
+                    _ => U256::zero(),
+                })?,
+                SAR => frame.binary(gas::VERY_LOW, arithmetic_shr)?,
+
+                KECCAK256 => {
+                    let (offset, len) = (frame.pop()?, frame.pop()?);
+                    frame.charge(gas::KECCAK256)?;
+                    let (offset, len) = frame.memory_range(offset, len)?;
+                    frame.charge(gas::KECCAK256_WORD * gas::words(len))?;
+                    let hash = keccak(&frame.memory[offset..offset + len]);
+                    frame.push(U256::from_big_endian(hash.as_bytes()))?;
+                }
+
+                ADDRESS => frame.push_with(gas::BASE, address_word(&frame.message.address))?,
+                BALANCE => {
+                    let address = word_address(frame.pop()?);
+                    frame.charge(self.access_cost(&address))?;
+                    frame.push(self.world.balance(&address))?;
+                }
+                ORIGIN => frame.push_with(gas::BASE, address_word(&self.origin))?,
+                CALLER => frame.push_with(gas::BASE, address_word(&frame.message.caller))?,
+                CALLVALUE => frame.push_with(gas::BASE, frame.message.value)?,
+                CALLDATALOAD => {
+                    frame.charge(gas::VERY_LOW)?;
+                    let offset = frame.pop()?;
+                    let mut word = [0u8; 32];
+                    copy_padded(&mut word, &frame.message.input, offset);
+                    frame.push(U256::from_big_endian(&word))?;
+                }
+                CALLDATASIZE => frame.push_with(gas::BASE, U256::from(frame.message.input.len()))?,
+                CALLDATACOPY => {
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let (dest, len) = frame.copy_range(dest, len)?;
+                    copy_padded(&mut frame.memory[dest..dest + len], &frame.message.input, offset);
+                }
+                CODESIZE => frame.push_with(gas::BASE, U256::from(frame.code.len()))?,
+                CODECOPY => {
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let (dest, len) = frame.copy_range(dest, len)?;
+                    copy_padded(&mut frame.memory[dest..dest + len], &frame.code, offset);
+                }
+                GASPRICE => frame.push_with(gas::BASE, self.gas_price)?,
+                EXTCODESIZE => {
+                    let address = word_address(frame.pop()?);
+                    frame.charge(self.access_cost(&address))?;
+                    frame.push(U256::from(self.world.code(&address).len()))?;
+                }
+                EXTCODECOPY => {
+                    let address = word_address(frame.pop()?);
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    frame.charge(self.access_cost(&address))?;
+                    let (dest, len) = frame.memory_range(dest, len)?;
+                    frame.charge(gas::COPY_WORD * gas::words(len))?;
+                    copy_padded(&mut frame.memory[dest..dest + len], self.world.code(&address), offset);
+                }
+                RETURNDATASIZE => frame.push_with(gas::BASE, U256::from(frame.return_data.len()))?,
+                RETURNDATACOPY => {
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let end = offset.checked_add(len).ok_or(Halt::ReturnDataOutOfBounds)?;
+                    if end > U256::from(frame.return_data.len()) {
+                        return Err(Halt::ReturnDataOutOfBounds);
+                    }
+                    let (dest, len) = frame.copy_range(dest, len)?;
+                    copy_padded(&mut frame.memory[dest..dest + len], &frame.return_data, offset);
+                }
+                EXTCODEHASH => {
+                    let address = word_address(frame.pop()?);
+                    frame.charge(self.access_cost(&address))?;
+                    frame.push(U256::from_big_endian(self.world.code_hash(&address).as_bytes()))?;
+                }
+
+                BLOCKHASH => {
+                    frame.charge(gas::BLOCKHASH)?;
+                    let number = frame.pop()?;
+                    frame.push(U256::from_big_endian(self.block.hash(number).as_bytes()))?;
+                }
+                COINBASE => frame.push_with(gas::BASE, address_word(&self.block.coinbase))?,
+                TIMESTAMP => frame.push_with(gas::BASE, U256::from(self.block.timestamp))?,
+                NUMBER => frame.push_with(gas::BASE, U256::from(self.block.number))?,
+                PREVRANDAO => frame.push_with(gas::BASE, U256::from_big_endian(self.block.prev_randao.as_bytes()))?,
+                GASLIMIT => frame.push_with(gas::BASE, U256::from(self.block.gas_limit))?,
+                CHAINID => frame.push_with(gas::BASE, U256::from(self.block.chain_id))?,
+                SELFBALANCE => frame.push_with(gas::LOW, self.world.balance(&frame.message.address))?,
+                BASEFEE => frame.push_with(gas::BASE, U256::from(self.block.base_fee))?,
+                // 不支持 blob 交易：没有 blob 哈希，blob 基础费用取最小值 1
+                BLOBHASH => frame.unary(gas::VERY_LOW, |_| U256::zero())?,
+                BLOBBASEFEE => frame.push_with(gas::BASE, U256::one())?,
+
+                POP => {
+                    frame.charge(gas::BASE)?;
+                    frame.pop()?;
+                }
+                MLOAD => {
+                    frame.charge(gas::VERY_LOW)?;
+                    let offset = frame.pop()?;
+                    let (offset, _) = frame.memory_range(offset, U256::from(32))?;
+                    let value = U256::from_big_endian(&frame.memory[offset..offset + 32]);
+                    frame.push(value)?;
+                }
+                MSTORE => {
+                    frame.charge(gas::VERY_LOW)?;
+                    let (offset, value) = (frame.pop()?, frame.pop()?);
+                    let (offset, _) = frame.memory_range(offset, U256::from(32))?;
+                    value.to_big_endian(&mut frame.memory[offset..offset + 32]);
+                }
+                MSTORE8 => {
+                    frame.charge(gas::VERY_LOW)?;
+                    let (offset, value) = (frame.pop()?, frame.pop()?);
+                    let (offset, _) = frame.memory_range(offset, U256::one())?;
+                    frame.memory[offset] = value.byte(0);
+                }
+                SLOAD => {
+                    let slot = word_hash(frame.pop()?);
+                    let address = frame.message.address;
+                    let cost = if self.world.warm_slot(&address, slot) { gas::COLD_SLOAD } else { gas::WARM_STORAGE_READ };
+                    frame.charge(cost)?;
+                    frame.push(self.world.sload(&address, slot))?;
+                }
+                SSTORE => {
+                    frame.read_only()?;
+                    if frame.gas <= gas::SSTORE_SENTRY {
+                        return Err(Halt::OutOfGas);
+                    }
+                    let (slot, value) = (word_hash(frame.pop()?), frame.pop()?);
+                    let address = frame.message.address;
+                    let cold = if self.world.warm_slot(&address, slot) { gas::COLD_SLOAD } else { 0 };
+                    let original = self.world.original(&address, slot);
+                    let current = self.world.sload(&address, slot);
+                    let (cost, refund) = gas::sstore(original, current, value);
+                    frame.charge(cost + cold)?;
+                    self.world.add_refund(refund);
+                    self.world.sstore(&address, slot, value);
+                }
+                JUMP => {
+                    frame.charge(gas::MID)?;
+                    let dest = frame.pop()?;
+                    frame.jump(dest)?;
+                }
+                JUMPI => {
+                    frame.charge(gas::HIGH)?;
+                    let (dest, condition) = (frame.pop()?, frame.pop()?);
+                    if !condition.is_zero() {
+                        frame.jump(dest)?;
+                    }
+                }
+                PC => frame.push_with(gas::BASE, U256::from(frame.pc - 1))?,
+                MSIZE => frame.push_with(gas::BASE, U256::from(frame.memory.len()))?,
+                GAS => {
+                    frame.charge(gas::BASE)?;
+                    frame.push(U256::from(frame.gas))?;
+                }
+                JUMPDEST => frame.charge(gas::JUMPDEST)?,
+                TLOAD => {
+                    frame.charge(gas::TRANSIENT_STORAGE)?;
+                    let slot = word_hash(frame.pop()?);
+                    frame.push(self.world.tload(&frame.message.address, slot))?;
+                }
+                TSTORE => {
+                    frame.read_only()?;
+                    frame.charge(gas::TRANSIENT_STORAGE)?;
+                    let (slot, value) = (word_hash(frame.pop()?), frame.pop()?);
+                    self.world.tstore(&frame.message.address, slot, value);
+                }
+                MCOPY => {
+                    let (dest, source, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let (_, len) = frame.copy_range(dest.max(source), len)?;
+                    if len > 0 {
+                        let (dest, source) = (dest.low_u64() as usize, source.low_u64() as usize);
+                        frame.memory.copy_within(source..source + len, dest);
+                    }
+                }
+
+                PUSH0 => frame.push_with(gas::BASE, U256::zero())?,
+                PUSH1..=PUSH32 => {
+                    let len = immediate_len(opcode);
+                    let mut bytes = [0u8; 32];
+                    let available = frame.code.len().saturating_sub(frame.pc).min(len);
+                    bytes[32 - len..32 - len + available].copy_from_slice(&frame.code[frame.pc..frame.pc + available]);
+                    frame.pc += len;
+                    frame.push_with(gas::VERY_LOW, U256::from_big_endian(&bytes))?;
+                }
+                DUP1..=DUP16 => {
+                    let depth = (opcode - DUP1) as usize + 1;
+                    frame.charge(gas::VERY_LOW)?;
+                    let value = *frame.stack.len().checked_sub(depth)
+                        .and_then(|index| frame.stack.get(index))
+                        .ok_or(Halt::StackUnderflow)?;
+                    frame.push(value)?;
+                }
+                SWAP1..=SWAP16 => {
+                    let depth = (opcode - SWAP1) as usize + 1;
+                    frame.charge(gas::VERY_LOW)?;
+                    let top = frame.stack.len().checked_sub(1).ok_or(Halt::StackUnderflow)?;
+                    let other = top.checked_sub(depth).ok_or(Halt::StackUnderflow)?;
+                    frame.stack.swap(top, other);
+                }
+                LOG0..=LOG4 => {
+                    frame.read_only()?;
+                    let topic_count = (opcode - LOG0) as usize;
+                    let (offset, len) = (frame.pop()?, frame.pop()?);
+                    frame.charge(gas::LOG + gas::LOG_TOPIC * topic_count as u64)?;
+                    let (offset, len) = frame.memory_range(offset, len)?;
+                    frame.charge(gas::LOG_DATA_BYTE * len as u64)?;
+                    let topics = (0..topic_count).map(|_| frame.pop().map(word_hash)).collect::<Result<_, _>>()?;
+                    self.world.logs.push(Log {
+                        address: frame.message.address,
+                        topics,
+                        data: frame.memory[offset..offset + len].to_vec(),
+                    });
+                }
+
+                CREATE | CREATE2 => self.create_opcode(frame, opcode == CREATE2)?,
+                CALL | CALLCODE | DELEGATECALL | STATICCALL => self.call_opcode(frame, opcode)?,
+                RETURN | REVERT => {
+                    let (offset, len) = (frame.pop()?, frame.pop()?);
+                    let (offset, len) = frame.memory_range(offset, len)?;
+                    let output = frame.memory[offset..offset + len].to_vec();
+                    return Ok(if opcode == RETURN { Exit::Return(output) } else { Exit::Revert(output) });
+                }
+                SELFDESTRUCT => {
+                    frame.read_only()?;
+                    let beneficiary = word_address(frame.pop()?);
+                    let mut cost = gas::SELFDESTRUCT;
+                    if self.world.warm_account(&beneficiary) {
+                        cost += gas::COLD_ACCOUNT_ACCESS;
+                    }
+                    if !self.world.balance(&frame.message.address).is_zero() && self.world.is_empty(&beneficiary) {
+                        cost += gas::NEW_ACCOUNT;
+                    }
+                    frame.charge(cost)?;
+                    self.world.selfdestruct(&frame.message.address, &beneficiary);
+                    return Ok(Exit::Return(Vec::new()));
+                }
+                _ => return Err(Halt::InvalidOpcode(opcode)),
+            }
+        }
+    }
+
+    /// `CREATE`/`CREATE2`：转发除 1/64 外的全部 gas
+    fn create_opcode(&mut self, frame: &mut Frame, create2: bool) -> Result<(), Halt> {
+        frame.read_only()?;
+        let (value, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+        let salt = if create2 { Some(word_hash(frame.pop()?)) } else { None };
+        frame.charge(gas::CREATE)?;
+        let (offset, len) = frame.memory_range(offset, len)?;
+        if len > gas::MAX_INITCODE_SIZE {
+            return Err(Halt::OutOfGas);
+        }
+        let words = gas::words(len);
+        let hashing = if create2 { gas::KECCAK256_WORD * words } else { 0 };
+        frame.charge(gas::INITCODE_WORD * words + hashing)?;
+
+        let init_code = frame.memory[offset..offset + len].to_vec();
+        let gas_limit = frame.gas - frame.gas / 64;
+        frame.charge(gas_limit)?;
+        let result = self.create(frame.message.address, value, init_code, gas_limit, salt, frame.message.depth + 1);
+
+        frame.gas += result.gas_left;
+        frame.return_data = if result.status == Status::Revert { result.output } else { Vec::new() };
+        frame.push(result.created.as_ref().map(address_word).unwrap_or_default())
+    }
+
+    /// `CALL`/`CALLCODE`/`DELEGATECALL`/`STATICCALL`：转发请求的 gas，最多为剩余的 63/64
+    fn call_opcode(&mut self, frame: &mut Frame, opcode: u8) -> Result<(), Halt> {
+        let requested = frame.pop()?;
+        let target = word_address(frame.pop()?);
+        let value = if matches!(opcode, CALL | CALLCODE) { frame.pop()? } else { U256::zero() };
+        let (in_offset, in_len, out_offset, out_len) = (frame.pop()?, frame.pop()?, frame.pop()?, frame.pop()?);
+        if opcode == CALL && !value.is_zero() {
+            frame.read_only()?;
+        }
+
+        let (in_offset, in_len) = frame.memory_range(in_offset, in_len)?;
+        let (out_offset, out_len) = frame.memory_range(out_offset, out_len)?;
+        let mut cost = self.access_cost(&target);
+        if !value.is_zero() {
+            cost += gas::CALL_VALUE;
+            if opcode == CALL && self.world.is_empty(&target) {
+                cost += gas::NEW_ACCOUNT;
+            }
+        }
+        frame.charge(cost)?;
+
+        let available = frame.gas - frame.gas / 64;
+        let mut gas_limit = if requested > U256::from(available) { available } else { requested.low_u64() };
+        frame.charge(gas_limit)?;
+        if !value.is_zero() {
+            gas_limit += gas::CALL_STIPEND;
+        }
+
+        let current = &frame.message;
+        let (caller, address, value, transfer) = match opcode {
+            CALL => (current.address, target, value, true),
+            CALLCODE => (current.address, current.address, value, true),
+            DELEGATECALL => (current.caller, current.address, current.value, false),
+            _ => (current.address, target, U256::zero(), false),
+        };
+        let message = Message {
+            caller,
+            address,
+            code_address: target,
+            value,
+            transfer,
+            input: frame.memory[in_offset..in_offset + in_len].to_vec(),
+            gas: gas_limit,
+            is_static: current.is_static || opcode == STATICCALL,
+            depth: current.depth + 1,
+        };
+        let result = self.call(message);
+
+        frame.gas += result.gas_left;
+        let copied = out_len.min(result.output.len());
+        frame.memory[out_offset..out_offset + copied].copy_from_slice(&result.output[..copied]);
+        frame.return_data = result.output;
+        frame.push(flag(result.status == Status::Success))
+    }
+}
+
+fn flag(value: bool) -> U256 {
+    if value { U256::one() } else { U256::zero() }
+}
+
+fn address_word(address: &Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+fn word_address(word: U256) -> Address {
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+fn word_hash(word: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+/// 从 `source[offset..]` 拷贝到 `target`，超出部分补零
+fn copy_padded(target: &mut [u8], source: &[u8], offset: U256) {
+    target.fill(0);
+    let Ok(offset) = usize::try_from(offset) else {
+        return;
+    };
+    if offset < source.len() {
+        let len = target.len().min(source.len() - offset);
+        target[..len].copy_from_slice(&source[offset..offset + len]);
+    }
+}
+
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+/// 二进制补码取负
+fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+fn magnitude(value: U256) -> U256 {
+    if is_negative(value) { negate(value) } else { value }
+}
+
+fn signed_div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    // 最小负数除以 -1 时商溢出回最小负数，与补码取负的结果一致
+    let quotient = magnitude(a) / magnitude(b);
+    if is_negative(a) != is_negative(b) { negate(quotient) } else { quotient }
+}
+
+fn signed_rem(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    let remainder = magnitude(a) % magnitude(b);
+    if is_negative(a) { negate(remainder) } else { remainder }
+}
+
+fn signed_lt(a: U256, b: U256) -> bool {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => a < b,
+    }
+}
+
+fn arithmetic_shr(shift: U256, value: U256) -> U256 {
+    let negative = is_negative(value);
+    match usize::try_from(shift) {
+        Ok(shift) if shift < 256 => {
+            if negative { !(!value >> shift) } else { value >> shift }
+        }
+        _ if negative => U256::MAX,
+        _ => U256::zero(),
+    }
+}
+
+/// 把第 `byte` 个低位字节的最高位作为符号位扩展到 256 位
+fn sign_extend(byte: U256, value: U256) -> U256 {
+    if byte >= U256::from(31) {
+        return value;
+    }
+    let sign_bit = byte.low_u64() as usize * 8 + 7;
+    let mask = (U256::one() << sign_bit) - 1;
+    if value.bit(sign_bit) { value | !mask } else { value & mask }
+}
+
+fn modulo(value: U512, modulus: U256) -> U256 {
+    if modulus.is_zero() {
+        return U256::zero();
+    }
+    U256::try_from(value % U512::from(modulus)).expect("remainder is below the 256-bit modulus")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minus(value: u64) -> U256 {
+        negate(U256::from(value))
+    }
+
+    #[test]
+    fn test_signed_arithmetic_uses_twos_complement() {
+        assert_eq!(signed_div(minus(8), U256::from(2)), minus(4));
+        assert_eq!(signed_div(minus(8), minus(2)), U256::from(4));
+        let min = U256::one() << 255;
+        assert_eq!(signed_div(min, U256::MAX), min);
+        assert_eq!(signed_rem(minus(7), U256::from(3)), minus(1));
+        assert_eq!(signed_rem(U256::from(7), minus(3)), U256::one());
+        assert!(signed_lt(minus(1), U256::zero()));
+        assert!(!signed_lt(U256::one(), minus(5)));
+    }
+
+    #[test]
+    fn test_shifts_and_sign_extension() {
+        assert_eq!(arithmetic_shr(U256::from(4), minus(16)), minus(1));
+        assert_eq!(arithmetic_shr(U256::from(300), minus(16)), U256::MAX);
+        assert_eq!(arithmetic_shr(U256::from(1), U256::from(16)), U256::from(8));
+        assert_eq!(sign_extend(U256::zero(), U256::from(0xff)), U256::MAX);
+        assert_eq!(sign_extend(U256::zero(), U256::from(0x17f)), U256::from(0x7f));
+        assert_eq!(sign_extend(U256::from(40), U256::from(0xff)), U256::from(0xff));
+    }
+
+    #[test]
+    fn test_modular_arithmetic_does_not_overflow() {
+        assert_eq!(modulo(U512::from(U256::MAX) + U512::from(U256::MAX), U256::from(10)), U256::from(0));
+        assert_eq!(modulo(U256::MAX.full_mul(U256::from(2)), U256::from(7)), U256::from(2));
+        assert_eq!(modulo(U512::from(5), U256::zero()), U256::zero());
+    }
+}
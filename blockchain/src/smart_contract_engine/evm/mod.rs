@@ -0,0 +1,487 @@
+//! # EVM 执行后端
+//!
+//! 在 [`State`] 上执行 Solidity 编译出的 EVM 字节码，操作码集和 gas 表按 Cancun 分叉
+//! （不含 blob 交易和 EOF）。账户、代码和存储在 `State` 中的保存方式见 `world` 模块，
+//! 预编译合约见 [`precompiles`]。
+//!
+//! - [`Evm::transact`] 按以太坊交易规则执行：校验 nonce 和余额、预付 gas、扣除固有 gas，
+//!   结束后按 EIP-3529 退款并向出块者支付小费，以太坊状态测试按此运行；
+//! - [`Evm::call`] / [`Evm::create`] 执行不收交易费用的消息调用，
+//!   供 [`SmartContractEngine`](super::SmartContractEngine) 中以 [`ContractBackend::Evm`](super::ContractBackend::Evm)
+//!   部署的合约使用。
+//!
+//! 地址在 `State` 中写作带 `0x` 前缀的小写十六进制。引擎中不是十六进制地址的调用方名称
+//! 经 [`address_of`] 映射为 `keccak256(名称)` 的后 20 字节。
+
+pub mod gas;
+mod interpreter;
+pub mod opcode;
+pub mod precompiles;
+#[cfg(test)]
+mod state_tests;
+mod world;
+
+pub use ethabi::ethereum_types::{Address, H256, U256};
+
+use crate::components::cryptography::hash::Keccak256Algorithm;
+use crate::core::{State, StateJournal};
+use ethabi::ethereum_types::U512;
+use interpreter::{FrameResult, Machine, Message};
+use std::collections::HashMap;
+use thiserror::Error;
+use world::{Effects, World};
+
+/// 区块环境
+#[derive(Debug, Clone)]
+pub struct BlockEnv {
+    pub number: u64,
+    pub timestamp: u64,
+    pub coinbase: Address,
+    pub gas_limit: u64,
+    pub base_fee: u64,
+    pub prev_randao: H256,
+    pub chain_id: u64,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: 0,
+            timestamp: 0,
+            coinbase: Address::zero(),
+            gas_limit: 30_000_000,
+            base_fee: 0,
+            prev_randao: H256::zero(),
+            chain_id: 1,
+        }
+    }
+}
+
+impl BlockEnv {
+    /// 最近 256 个区块的哈希
+    ///
+    /// 执行环境不保存区块历史，按以太坊状态测试的约定取 `keccak256(十进制区块高度)`。
+    pub fn hash(&self, number: U256) -> H256 {
+        match u64::try_from(number) {
+            Ok(number) if number < self.number && self.number - number <= 256 => keccak(number.to_string().as_bytes()),
+            _ => H256::zero(),
+        }
+    }
+}
+
+/// 以太坊交易
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub caller: Address,
+    /// 为 `None` 时创建合约，`data` 为初始化代码
+    pub to: Option<Address>,
+    pub nonce: u64,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub gas_limit: u64,
+    pub gas_price: u64,
+}
+
+/// 合约日志
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+/// 值转移记录，金额为完整的 256 位
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+}
+
+/// 异常终止的原因，发生时当前调用帧耗尽全部 gas 并回滚
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    #[error("out of gas")]
+    OutOfGas,
+    #[error("stack underflow")]
+    StackUnderflow,
+    #[error("stack overflow")]
+    StackOverflow,
+    #[error("invalid jump destination")]
+    InvalidJump,
+    #[error("invalid opcode 0x{0:02x}")]
+    InvalidOpcode(u8),
+    #[error("state modification in static call")]
+    StaticStateChange,
+    #[error("return data out of bounds")]
+    ReturnDataOutOfBounds,
+    #[error("call depth exceeded")]
+    CallDepthExceeded,
+    #[error("insufficient balance for transfer")]
+    InsufficientBalance,
+    #[error("contract address collision")]
+    CreateCollision,
+    #[error("contract code size limit exceeded")]
+    CodeSizeLimit,
+    #[error("contract code starts with 0xEF")]
+    InvalidCodePrefix,
+    #[error("nonce overflow")]
+    NonceOverflow,
+}
+
+/// 执行状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    /// `REVERT`：回滚状态，未用完的 gas 退回
+    Revert,
+    Halt(Halt),
+}
+
+/// 交易或消息调用的执行结果
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub status: Status,
+    /// 返回数据或回滚数据
+    pub output: Vec<u8>,
+    /// 扣除退款后消耗的 gas
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    pub logs: Vec<Log>,
+    /// 创建的合约地址
+    pub created: Option<Address>,
+    pub transfers: Vec<Transfer>,
+    /// 各账户被写入的存储槽及最终值，空值表示已清零
+    pub storage_changes: HashMap<Address, HashMap<String, Vec<u8>>>,
+    /// 本次执行对状态的修改日志，撤销后状态恢复为执行前
+    pub journal: StateJournal,
+}
+
+impl Outcome {
+    pub fn is_success(&self) -> bool {
+        self.status == Status::Success
+    }
+
+    /// 按 Solidity 的 `Error(string)` 解码回滚原因
+    pub fn revert_reason(&self) -> Option<String> {
+        if self.status != Status::Revert {
+            return None;
+        }
+        let data = self.output.strip_prefix(&[0x08, 0xc3, 0x79, 0xa0][..])?;
+        ethabi::decode(&[ethabi::ParamType::String], data).ok()?.pop()?.into_string()
+    }
+}
+
+/// 交易校验失败，状态未做任何修改
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EvmError {
+    #[error("nonce mismatch: expected {expected}, got {actual}")]
+    NonceMismatch { expected: u64, actual: u64 },
+    #[error("insufficient funds for gas * price + value")]
+    InsufficientFunds,
+    #[error("intrinsic gas too low: need {0}")]
+    IntrinsicGasTooLow(u64),
+    #[error("gas price below block base fee")]
+    GasPriceBelowBaseFee,
+    #[error("gas limit exceeds block gas limit")]
+    GasLimitExceeded,
+    #[error("initcode size limit exceeded")]
+    InitcodeTooLarge,
+}
+
+pub(crate) fn keccak(data: &[u8]) -> H256 {
+    H256(Keccak256Algorithm::digest(data))
+}
+
+/// `State` 中使用的地址写法
+pub fn address_hex(address: &Address) -> String {
+    format!("0x{}", hex::encode(address.as_bytes()))
+}
+
+/// 解析带 `0x` 前缀的 20 字节十六进制地址
+pub fn parse_address(text: &str) -> Option<Address> {
+    let bytes = hex::decode(text.strip_prefix("0x")?).ok()?;
+    (bytes.len() == 20).then(|| Address::from_slice(&bytes))
+}
+
+/// 引擎中的账户名称对应的 EVM 地址：十六进制地址原样解析，其他名称取 `keccak256(名称)` 的后 20 字节
+pub fn address_of(name: &str) -> Address {
+    parse_address(name).unwrap_or_else(|| Address::from_slice(&keccak(name.as_bytes())[12..]))
+}
+
+/// CREATE 的合约地址：`keccak256(rlp([sender, nonce]))` 的后 20 字节
+pub fn create_address(sender: &Address, nonce: u64) -> Address {
+    let mut stream = rlp::RlpStream::new_list(2);
+    stream.append(&sender.as_bytes());
+    stream.append(&nonce);
+    Address::from_slice(&keccak(&stream.out())[12..])
+}
+
+/// CREATE2 的合约地址（EIP-1014）：`keccak256(0xff ‖ sender ‖ salt ‖ keccak256(init_code))` 的后 20 字节
+pub fn create2_address(sender: &Address, salt: H256, init_code: &[u8]) -> Address {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(sender.as_bytes());
+    preimage.extend_from_slice(salt.as_bytes());
+    preimage.extend_from_slice(keccak(init_code).as_bytes());
+    Address::from_slice(&keccak(&preimage)[12..])
+}
+
+/// 在 `State` 上执行交易和消息调用的 EVM
+pub struct Evm<'a> {
+    state: &'a mut State,
+    block: BlockEnv,
+}
+
+impl<'a> Evm<'a> {
+    pub fn new(state: &'a mut State, block: BlockEnv) -> Self {
+        Self { state, block }
+    }
+
+    /// 执行以太坊交易
+    ///
+    /// 校验失败时返回错误且不修改状态；执行失败（回滚或异常终止）时仍扣除 gas 费用并递增 nonce。
+    pub fn transact(&mut self, transaction: &Transaction) -> Result<Outcome, EvmError> {
+        let create = transaction.to.is_none();
+        if transaction.gas_price < self.block.base_fee {
+            return Err(EvmError::GasPriceBelowBaseFee);
+        }
+        if transaction.gas_limit > self.block.gas_limit {
+            return Err(EvmError::GasLimitExceeded);
+        }
+        if create && transaction.data.len() > gas::MAX_INITCODE_SIZE {
+            return Err(EvmError::InitcodeTooLarge);
+        }
+        let intrinsic = gas::intrinsic(&transaction.data, create);
+        if intrinsic > transaction.gas_limit {
+            return Err(EvmError::IntrinsicGasTooLow(intrinsic));
+        }
+
+        let caller = transaction.caller;
+        let mut world = World::new(self.state);
+        let nonce = world.nonce(&caller);
+        if nonce != transaction.nonce {
+            return Err(EvmError::NonceMismatch { expected: nonce, actual: transaction.nonce });
+        }
+        let gas_price = U256::from(transaction.gas_price);
+        let fee = U256::from(transaction.gas_limit) * gas_price;
+        if world.balance(&caller) < fee + transaction.value {
+            return Err(EvmError::InsufficientFunds);
+        }
+        world.debit(&caller, fee);
+
+        let mut machine = Self::machine(world, self.block.clone(), caller, transaction.to, gas_price);
+        // EIP-3651：出块者地址一开始就是热地址
+        machine.world.warm_account(&self.block.coinbase);
+        let gas_limit = transaction.gas_limit - intrinsic;
+        let result = match transaction.to {
+            Some(to) => {
+                machine.world.set_nonce(&caller, nonce + 1);
+                machine.call(Message {
+                    caller,
+                    address: to,
+                    code_address: to,
+                    value: transaction.value,
+                    transfer: true,
+                    input: transaction.data.clone(),
+                    gas: gas_limit,
+                    is_static: false,
+                    depth: 0,
+                })
+            }
+            None => machine.create(caller, transaction.value, transaction.data.clone(), gas_limit, None, 0),
+        };
+
+        let (gas_used, gas_refunded) = settle(transaction.gas_limit, result.gas_left, machine.world.refund);
+        let mut world = machine.world;
+        world.credit(&caller, U256::from(transaction.gas_limit - gas_used) * gas_price);
+        let tip = gas_price - U256::from(self.block.base_fee);
+        world.credit(&self.block.coinbase, U256::from(gas_used) * tip);
+        Ok(outcome(result, gas_used, gas_refunded, world.finish()))
+    }
+
+    /// 消息调用：不收交易费用，不递增调用方 nonce
+    pub fn call(
+        &mut self,
+        caller: Address,
+        address: Address,
+        value: U256,
+        input: Vec<u8>,
+        gas_limit: u64,
+        is_static: bool,
+    ) -> Outcome {
+        let world = World::new(self.state);
+        let mut machine = Self::machine(world, self.block.clone(), caller, Some(address), U256::zero());
+        let result = machine.call(Message {
+            caller,
+            address,
+            code_address: address,
+            value,
+            transfer: true,
+            input,
+            gas: gas_limit,
+            is_static,
+            depth: 0,
+        });
+        let (gas_used, gas_refunded) = settle(gas_limit, result.gas_left, machine.world.refund);
+        outcome(result, gas_used, gas_refunded, machine.world.finish())
+    }
+
+    /// 用初始化代码创建合约，`salt` 不为空时按 CREATE2 计算地址
+    pub fn create(
+        &mut self,
+        caller: Address,
+        value: U256,
+        init_code: Vec<u8>,
+        gas_limit: u64,
+        salt: Option<H256>,
+    ) -> Outcome {
+        let world = World::new(self.state);
+        let mut machine = Self::machine(world, self.block.clone(), caller, None, U256::zero());
+        let result = machine.create(caller, value, init_code, gas_limit, salt, 0);
+        let (gas_used, gas_refunded) = settle(gas_limit, result.gas_left, machine.world.refund);
+        outcome(result, gas_used, gas_refunded, machine.world.finish())
+    }
+
+    /// 调用方、目标地址和预编译合约一开始就是热地址（EIP-2929）
+    fn machine(mut world: World<'_>, block: BlockEnv, caller: Address, to: Option<Address>, gas_price: U256) -> Machine<'_> {
+        world.warm_account(&caller);
+        for address in to.into_iter().chain(precompiles::addresses()) {
+            world.warm_account(&address);
+        }
+        Machine { world, block, origin: caller, gas_price }
+    }
+}
+
+/// 扣除退款后的 gas 用量和退款额，退款不超过用量的 1/5
+fn settle(gas_limit: u64, gas_left: u64, refund: i64) -> (u64, u64) {
+    let used = gas_limit - gas_left;
+    let refund = (refund.max(0) as u64).min(used / gas::MAX_REFUND_QUOTIENT);
+    (used - refund, refund)
+}
+
+fn outcome(result: FrameResult, gas_used: u64, gas_refunded: u64, effects: Effects) -> Outcome {
+    Outcome {
+        status: result.status,
+        output: result.output,
+        gas_used,
+        gas_refunded,
+        logs: effects.logs,
+        created: result.created,
+        transfers: effects.transfers,
+        storage_changes: effects.storage,
+        journal: effects.journal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(text: &str) -> Address {
+        parse_address(text).unwrap()
+    }
+
+    #[test]
+    fn test_create_addresses() {
+        let sender = address("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        assert_eq!(address_hex(&create_address(&sender, 0)), "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d");
+        assert_eq!(address_hex(&create_address(&sender, 1)), "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8");
+
+        // EIP-1014 示例 0 和示例 2
+        assert_eq!(
+            address_hex(&create2_address(&Address::zero(), H256::zero(), &[0x00])),
+            "0x4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"
+        );
+        let salt = H256::from_slice(&hex::decode("000000000000000000000000feed000000000000000000000000000000000000").unwrap());
+        assert_eq!(
+            address_hex(&create2_address(&address("0xdeadbeef00000000000000000000000000000000"), salt, &[0x00])),
+            "0xd04116cdd17bebe565eb2422f2497e06cc1c9833"
+        );
+    }
+
+    #[test]
+    fn test_address_of_engine_names() {
+        let hex = "0x00000000000000000000000000000000000000aa";
+        assert_eq!(address_hex(&address_of(hex)), hex);
+        assert_eq!(address_of("alice"), Address::from_slice(&keccak(b"alice")[12..]));
+        assert_eq!(parse_address("0x1234"), None);
+    }
+
+    #[test]
+    fn test_invalid_transaction_leaves_state_untouched() {
+        let sender = Address::from_low_u64_be(0xaa);
+        let mut state = State::new();
+        state.balances.insert(address_hex(&sender), 1_000_000);
+        let transaction = Transaction {
+            caller: sender,
+            to: Some(Address::from_low_u64_be(0xbb)),
+            nonce: 0,
+            value: U256::from(1),
+            data: Vec::new(),
+            gas_limit: 21_000,
+            gas_price: 10,
+        };
+        let block = BlockEnv { base_fee: 10, ..Default::default() };
+
+        let mut evm = Evm::new(&mut state, block.clone());
+        assert_eq!(evm.transact(&Transaction { nonce: 1, ..transaction.clone() }).unwrap_err(), EvmError::NonceMismatch { expected: 0, actual: 1 });
+        assert_eq!(evm.transact(&Transaction { gas_limit: 20_999, ..transaction.clone() }).unwrap_err(), EvmError::IntrinsicGasTooLow(21_000));
+        assert_eq!(evm.transact(&Transaction { gas_price: 9, ..transaction.clone() }).unwrap_err(), EvmError::GasPriceBelowBaseFee);
+        assert_eq!(evm.transact(&Transaction { value: U256::from(790_001), ..transaction.clone() }).unwrap_err(), EvmError::InsufficientFunds);
+        assert_eq!(state.balances[&address_hex(&sender)], 1_000_000);
+        assert!(state.nonces.is_empty());
+
+        let outcome = Evm::new(&mut state, block).transact(&transaction).unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(outcome.gas_used, 21_000);
+        assert_eq!(state.balances[&address_hex(&sender)], 1_000_000 - 210_000 - 1);
+        assert_eq!(state.nonces[&address_hex(&sender)], 1);
+    }
+
+    #[test]
+    fn test_balances_above_u64() {
+        let sender = Address::from_low_u64_be(0xaa);
+        let recipient = Address::from_low_u64_be(0xbb);
+        let mut state = State::new();
+        let mut balance = [0u8; 32];
+        (U256::one() << 70).to_big_endian(&mut balance);
+        state.wide_balances.insert(address_hex(&sender), balance.to_vec());
+        let transaction = Transaction {
+            caller: sender,
+            to: Some(recipient),
+            nonce: 0,
+            value: U256::one() << 65,
+            data: Vec::new(),
+            gas_limit: 21_000,
+            gas_price: 0,
+        };
+
+        let outcome = Evm::new(&mut state, BlockEnv::default()).transact(&transaction).unwrap();
+        assert!(outcome.is_success());
+        let balance_of = |state: &State, address: &Address| U256::from_big_endian(&state.wide_balances[&address_hex(address)]);
+        assert_eq!(balance_of(&state, &sender), (U256::one() << 70) - (U256::one() << 65));
+        assert_eq!(balance_of(&state, &recipient), U256::one() << 65);
+        assert!(!state.balances.contains_key(&address_hex(&recipient)));
+        assert_eq!(outcome.transfers, vec![Transfer { from: sender, to: recipient, value: U256::one() << 65 }]);
+    }
+
+    #[test]
+    fn test_revert_reason_decodes_error_string() {
+        let mut output = vec![0x08, 0xc3, 0x79, 0xa0];
+        output.extend(ethabi::encode(&[ethabi::Token::String("too late".to_string())]));
+        let outcome = Outcome {
+            status: Status::Revert,
+            output,
+            gas_used: 0,
+            gas_refunded: 0,
+            logs: Vec::new(),
+            created: None,
+            transfers: Vec::new(),
+            storage_changes: HashMap::new(),
+            journal: StateJournal::default(),
+        };
+        assert_eq!(outcome.revert_reason().as_deref(), Some("too late"));
+        assert_eq!(Outcome { output: vec![0xde, 0xad], ..outcome }.revert_reason(), None);
+    }
+}
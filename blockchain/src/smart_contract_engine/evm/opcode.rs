@@ -0,0 +1,147 @@
+//! # EVM 操作码
+//!
+//! Cancun 分叉的全部操作码。`PUSH1..PUSH32`、`DUP1..DUP16`、`SWAP1..SWAP16`、`LOG0..LOG4`
+//! 只列出首尾两个，其余按编号连续排列。
+
+macro_rules! opcodes {
+    ($($name:ident = $value:literal,)*) => {
+        $(pub const $name: u8 = $value;)*
+
+        /// 按助记符查找操作码，`PUSH7`、`DUP3` 等编号形式也可识别
+        pub fn by_name(name: &str) -> Option<u8> {
+            match name {
+                $(stringify!($name) => Some($name),)*
+                _ => numbered(name),
+            }
+        }
+    };
+}
+
+opcodes! {
+    STOP = 0x00,
+    ADD = 0x01,
+    MUL = 0x02,
+    SUB = 0x03,
+    DIV = 0x04,
+    SDIV = 0x05,
+    MOD = 0x06,
+    SMOD = 0x07,
+    ADDMOD = 0x08,
+    MULMOD = 0x09,
+    EXP = 0x0a,
+    SIGNEXTEND = 0x0b,
+    LT = 0x10,
+    GT = 0x11,
+    SLT = 0x12,
+    SGT = 0x13,
+    EQ = 0x14,
+    ISZERO = 0x15,
+    AND = 0x16,
+    OR = 0x17,
+    XOR = 0x18,
+    NOT = 0x19,
+    BYTE = 0x1a,
+    SHL = 0x1b,
+    SHR = 0x1c,
+    SAR = 0x1d,
+    KECCAK256 = 0x20,
+    ADDRESS = 0x30,
+    BALANCE = 0x31,
+    ORIGIN = 0x32,
+    CALLER = 0x33,
+    CALLVALUE = 0x34,
+    CALLDATALOAD = 0x35,
+    CALLDATASIZE = 0x36,
+    CALLDATACOPY = 0x37,
+    CODESIZE = 0x38,
+    CODECOPY = 0x39,
+    GASPRICE = 0x3a,
+    EXTCODESIZE = 0x3b,
+    EXTCODECOPY = 0x3c,
+    RETURNDATASIZE = 0x3d,
+    RETURNDATACOPY = 0x3e,
+    EXTCODEHASH = 0x3f,
+    BLOCKHASH = 0x40,
+    COINBASE = 0x41,
+    TIMESTAMP = 0x42,
+    NUMBER = 0x43,
+    PREVRANDAO = 0x44,
+    GASLIMIT = 0x45,
+    CHAINID = 0x46,
+    SELFBALANCE = 0x47,
+    BASEFEE = 0x48,
+    BLOBHASH = 0x49,
+    BLOBBASEFEE = 0x4a,
+    POP = 0x50,
+    MLOAD = 0x51,
+    MSTORE = 0x52,
+    MSTORE8 = 0x53,
+    SLOAD = 0x54,
+    SSTORE = 0x55,
+    JUMP = 0x56,
+    JUMPI = 0x57,
+    PC = 0x58,
+    MSIZE = 0x59,
+    GAS = 0x5a,
+    JUMPDEST = 0x5b,
+    TLOAD = 0x5c,
+    TSTORE = 0x5d,
+    MCOPY = 0x5e,
+    PUSH0 = 0x5f,
+    PUSH1 = 0x60,
+    PUSH32 = 0x7f,
+    DUP1 = 0x80,
+    DUP16 = 0x8f,
+    SWAP1 = 0x90,
+    SWAP16 = 0x9f,
+    LOG0 = 0xa0,
+    LOG4 = 0xa4,
+    CREATE = 0xf0,
+    CALL = 0xf1,
+    CALLCODE = 0xf2,
+    RETURN = 0xf3,
+    DELEGATECALL = 0xf4,
+    CREATE2 = 0xf5,
+    STATICCALL = 0xfa,
+    REVERT = 0xfd,
+    INVALID = 0xfe,
+    SELFDESTRUCT = 0xff,
+}
+
+fn numbered(name: &str) -> Option<u8> {
+    let (first, count, number) = [("PUSH", PUSH1, 32), ("DUP", DUP1, 16), ("SWAP", SWAP1, 16), ("LOG", LOG0, 5)]
+        .into_iter()
+        .find_map(|(prefix, first, count)| Some((first, count, name.strip_prefix(prefix)?.parse::<u8>().ok()?)))?;
+    let index = if first == LOG0 { number } else { number.checked_sub(1)? };
+    (index < count).then_some(first + index)
+}
+
+/// `PUSHn` 的立即数字节数，其他操作码为 0
+pub fn immediate_len(opcode: u8) -> usize {
+    if (PUSH1..=PUSH32).contains(&opcode) {
+        (opcode - PUSH1 + 1) as usize
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_by_name_resolves_numbered_families() {
+        assert_eq!(by_name("SSTORE"), Some(SSTORE));
+        assert_eq!(by_name("PUSH1"), Some(0x60));
+        assert_eq!(by_name("PUSH20"), Some(0x73));
+        assert_eq!(by_name("DUP16"), Some(DUP16));
+        assert_eq!(by_name("SWAP2"), Some(0x91));
+        assert_eq!(by_name("LOG0"), Some(LOG0));
+        assert_eq!(by_name("LOG3"), Some(0xa3));
+        assert_eq!(by_name("PUSH33"), None);
+        assert_eq!(by_name("DUP0"), None);
+        assert_eq!(by_name("LOG5"), None);
+        assert_eq!(immediate_len(PUSH32), 32);
+        assert_eq!(immediate_len(PUSH0), 0);
+    }
+}
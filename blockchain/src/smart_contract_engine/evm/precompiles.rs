@@ -0,0 +1,97 @@
+//! # 预编译合约
+//!
+//! | 地址 | 合约 | gas |
+//! |------|------|-----|
+//! | `0x01` | ecrecover：`hash ‖ v ‖ r ‖ s` 恢复签名者地址，失败时返回空 | 3000 |
+//! | `0x02` | SHA-256 | 60 + 每字 12 |
+//! | `0x04` | identity：原样返回输入 | 15 + 每字 3 |
+//!
+//! 其余预编译地址按普通空账户处理。
+
+use super::{gas, keccak, Address, Halt, U256};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, Secp256k1};
+use sha2::{Digest, Sha256};
+
+pub const ECRECOVER: u64 = 0x01;
+pub const SHA256: u64 = 0x02;
+pub const IDENTITY: u64 = 0x04;
+
+type Precompile = fn(&[u8]) -> Vec<u8>;
+
+/// 已实现的预编译合约地址，交易开始时即为热地址
+pub fn addresses() -> [Address; 3] {
+    [ECRECOVER, SHA256, IDENTITY].map(Address::from_low_u64_be)
+}
+
+/// 执行预编译合约，返回输出和消耗的 gas；`address` 不是预编译合约时返回 `None`
+pub(super) fn run(address: &Address, input: &[u8], gas_limit: u64) -> Option<Result<(Vec<u8>, u64), Halt>> {
+    if address.as_bytes()[..19].iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    let (cost, output): (u64, Precompile) = match address.to_low_u64_be() {
+        ECRECOVER => (3000, ecrecover),
+        SHA256 => (60 + 12 * gas::words(input.len()), |input| Sha256::digest(input).to_vec()),
+        IDENTITY => (15 + 3 * gas::words(input.len()), <[u8]>::to_vec),
+        _ => return None,
+    };
+    if cost > gas_limit {
+        return Some(Err(Halt::OutOfGas));
+    }
+    Some(Ok((output(input), cost)))
+}
+
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let mut padded = [0u8; 128];
+    let len = input.len().min(128);
+    padded[..len].copy_from_slice(&input[..len]);
+
+    let v = U256::from_big_endian(&padded[32..64]);
+    if v != U256::from(27) && v != U256::from(28) {
+        return Vec::new();
+    }
+    let Ok(recovery_id) = RecoveryId::try_from(v.low_u64() as i32 - 27) else {
+        return Vec::new();
+    };
+    let Ok(signature) = RecoverableSignature::from_compact(&padded[64..128], recovery_id) else {
+        return Vec::new();
+    };
+    let digest: [u8; 32] = padded[..32].try_into().expect("32-byte digest");
+    let Ok(public_key) = Secp256k1::verification_only().recover_ecdsa(Message::from_digest(digest), &signature) else {
+        return Vec::new();
+    };
+
+    let mut output = vec![0u8; 32];
+    output[12..].copy_from_slice(&keccak(&public_key.serialize_uncompressed()[1..])[12..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precompile_gas_and_outputs() {
+        let sha256 = Address::from_low_u64_be(SHA256);
+        let (output, gas) = run(&sha256, b"", 1_000).unwrap().unwrap();
+        assert_eq!(hex::encode(output), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(gas, 60);
+        assert_eq!(run(&sha256, &[0u8; 33], 83), Some(Err(Halt::OutOfGas)));
+
+        let identity = Address::from_low_u64_be(IDENTITY);
+        assert_eq!(run(&identity, b"hello", 1_000), Some(Ok((b"hello".to_vec(), 18))));
+        assert_eq!(run(&Address::from_low_u64_be(3), b"", 1_000), None);
+        assert_eq!(run(&Address::from_low_u64_be(0x0100), b"", 1_000), None);
+    }
+
+    #[test]
+    fn test_ecrecover_rejects_invalid_signatures() {
+        let ecrecover = Address::from_low_u64_be(ECRECOVER);
+        let mut input = [0u8; 128];
+        input[63] = 29;
+        assert_eq!(run(&ecrecover, &input, 3_000), Some(Ok((Vec::new(), 3_000))));
+        input[63] = 27;
+        assert_eq!(run(&ecrecover, &input, 3_000), Some(Ok((Vec::new(), 3_000))));
+        assert_eq!(run(&ecrecover, &input, 2_999), Some(Err(Halt::OutOfGas)));
+    }
+}
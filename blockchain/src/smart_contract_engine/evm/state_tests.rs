@@ -0,0 +1,522 @@
+//! # 以太坊一致性测试
+//!
+//! 直接运行 ethereum/tests 与 execution-spec-tests 生成的测试文件，不做任何修改，
+//! 执行后按以太坊账户树计算状态根，与文件中记录的哈希比较：
+//!
+//! - `fixtures/blockchain_tests/` 中的区块链测试：依次执行各有效区块（含 EIP-4788、EIP-2935、
+//!   EIP-7002、EIP-7251 的系统调用和提款），比较每个区块头的 `stateRoot`，最后比较 `postState`。
+//!   目前收录的文件取自 execution-spec-tests 的发布包，以测试 ID 为键；只有 RLP 的无效区块跳过。
+//! - `GeneralStateTests` 格式的状态测试：`post.Cancun` 的每一项用 `indexes` 从 `transaction`
+//!   的候选列表中选出一笔交易执行，比较状态根 `hash` 和日志哈希 `keccak256(rlp(logs))`。
+//!   `fixtures/state_tests/` 收录覆盖算术、存储与退款、值转移、合约创建、REVERT、日志以及
+//!   EIP-1153/EIP-5656 的 Cancun 用例，随普通测试运行，目录结构与 ethereum/tests 一致，
+//!   上游文件可直接放入。完整的 ethereum/tests 用环境变量 `ETHEREUM_TESTS` 指向其检出目录，
+//!   `cargo test -- --ignored` 运行。带访问列表或 blob 的交易不受支持，计入跳过的用例。
+//!
+//! 状态根的叶子键为 `keccak256(地址)`，值为 `rlp([nonce, balance, storageRoot, codeHash])`。
+
+use super::*;
+use crate::core::state::ContractState;
+use alloy::primitives::{Address as TrieAddress, B256, U256 as TrieU256};
+use alloy::trie::root::{state_root_unhashed, storage_root_unhashed};
+use alloy::trie::TrieAccount;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+const FORK: &str = "Cancun";
+
+/// 系统调用的调用方（EIP-4788）
+const SYSTEM_ADDRESS: &str = "0xfffffffffffffffffffffffffffffffffffffffe";
+const SYSTEM_CALL_GAS: u64 = 30_000_000;
+const BEACON_ROOTS_ADDRESS: &str = "0x000f3df6d732807ef1319fb7b8bb8522d0beac02";
+const HISTORY_STORAGE_ADDRESS: &str = "0x0000f90827f1c53a10cb7a02335b175320002935";
+const WITHDRAWAL_REQUEST_ADDRESS: &str = "0x00000961ef480eb55e80d19ad83579a64c007002";
+const CONSOLIDATION_REQUEST_ADDRESS: &str = "0x0000bbddc7ce488642fb579f8b00f3a590007251";
+
+#[derive(Deserialize)]
+struct Account {
+    balance: String,
+    nonce: String,
+    code: String,
+    storage: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Env {
+    current_coinbase: String,
+    current_gas_limit: String,
+    current_number: String,
+    current_timestamp: String,
+    current_base_fee: String,
+    #[serde(default)]
+    current_random: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionTemplate {
+    data: Vec<String>,
+    gas_limit: Vec<String>,
+    #[serde(default)]
+    gas_price: Option<String>,
+    #[serde(default)]
+    max_fee_per_gas: Option<String>,
+    #[serde(default)]
+    max_priority_fee_per_gas: Option<String>,
+    nonce: String,
+    sender: String,
+    to: String,
+    value: Vec<String>,
+    #[serde(default)]
+    access_lists: Vec<Option<Vec<serde_json::Value>>>,
+    #[serde(default)]
+    blob_versioned_hashes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Indexes {
+    data: usize,
+    gas: usize,
+    value: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Expectation {
+    indexes: Indexes,
+    hash: String,
+    logs: String,
+    #[serde(default)]
+    expect_exception: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StateTest {
+    env: Env,
+    pre: BTreeMap<String, Account>,
+    transaction: TransactionTemplate,
+    post: BTreeMap<String, Vec<Expectation>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockHeader {
+    parent_hash: String,
+    coinbase: String,
+    state_root: String,
+    number: String,
+    gas_limit: String,
+    timestamp: String,
+    mix_hash: String,
+    #[serde(default)]
+    base_fee_per_gas: Option<String>,
+    #[serde(default)]
+    parent_beacon_block_root: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockTransaction {
+    nonce: String,
+    #[serde(default)]
+    gas_price: Option<String>,
+    #[serde(default)]
+    max_fee_per_gas: Option<String>,
+    #[serde(default)]
+    max_priority_fee_per_gas: Option<String>,
+    gas_limit: String,
+    to: String,
+    value: String,
+    data: String,
+    sender: String,
+}
+
+#[derive(Deserialize)]
+struct Withdrawal {
+    address: String,
+    /// 以 Gwei 为单位
+    amount: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Block {
+    #[serde(default)]
+    block_header: Option<BlockHeader>,
+    #[serde(default)]
+    transactions: Vec<BlockTransaction>,
+    #[serde(default)]
+    withdrawals: Vec<Withdrawal>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockchainTest {
+    genesis_block_header: BlockHeader,
+    pre: BTreeMap<String, Account>,
+    blocks: Vec<Block>,
+    post_state: BTreeMap<String, Account>,
+}
+
+fn number(text: &str) -> U256 {
+    let digits = text.trim_start_matches("0x");
+    if digits.is_empty() {
+        U256::zero()
+    } else {
+        U256::from_str_radix(digits, 16).expect("hex number")
+    }
+}
+
+fn bytes(text: &str) -> Vec<u8> {
+    hex::decode(text.trim_start_matches("0x")).expect("hex bytes")
+}
+
+fn address(text: &str) -> Address {
+    parse_address(text).expect("20-byte address")
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+fn hash(text: &str) -> H256 {
+    H256::from(word(number(text)))
+}
+
+/// 按 `State` 的写法整理存储：键为 32 字节槽号，值为 32 字节大端整数，去掉零值
+fn storage(entries: &BTreeMap<String, String>) -> HashMap<String, Vec<u8>> {
+    entries
+        .iter()
+        .map(|(slot, value)| (number(slot), number(value)))
+        .filter(|(_, value)| !value.is_zero())
+        .map(|(slot, value)| (format!("0x{}", hex::encode(word(slot))), word(value).to_vec()))
+        .collect()
+}
+
+fn load(accounts: &BTreeMap<String, Account>) -> State {
+    let mut state = State::new();
+    for (key, account) in accounts {
+        let key = address_hex(&address(key));
+        let balance = number(&account.balance);
+        if balance.bits() > 64 {
+            state.wide_balances.insert(key.clone(), word(balance).to_vec());
+        } else {
+            state.balances.insert(key.clone(), balance.low_u64());
+        }
+        let nonce = number(&account.nonce).low_u64();
+        if nonce != 0 {
+            state.nonces.insert(key.clone(), nonce);
+        }
+        let code = bytes(&account.code);
+        let storage = storage(&account.storage);
+        if !code.is_empty() || !storage.is_empty() {
+            state.contract_states.insert(
+                key.clone(),
+                ContractState { storage, ..ContractState::new(key, code) },
+            );
+        }
+    }
+    state
+}
+
+/// 以太坊账户树的根
+fn state_root(state: &State) -> H256 {
+    let keys: BTreeSet<&String> = state.balances.keys()
+        .chain(state.wide_balances.keys())
+        .chain(state.nonces.keys())
+        .chain(state.contract_states.keys())
+        .collect();
+    let accounts = keys.into_iter().map(|key| {
+        let balance = match state.wide_balances.get(key) {
+            Some(balance) => U256::from_big_endian(balance),
+            None => U256::from(state.balances.get(key).copied().unwrap_or(0)),
+        };
+        let contract = state.contract_states.get(key);
+        let storage = contract.into_iter().flat_map(|contract| &contract.storage).map(|(slot, value)| {
+            (B256::from(hash(slot).0), TrieU256::from_be_slice(value))
+        });
+        let account = TrieAccount {
+            nonce: state.nonces.get(key).copied().unwrap_or(0),
+            balance: TrieU256::from_be_bytes(word(balance)),
+            storage_root: storage_root_unhashed(storage),
+            code_hash: B256::from(keccak(contract.map(|contract| contract.code.as_slice()).unwrap_or_default()).0),
+        };
+        (TrieAddress::from_slice(address(key).as_bytes()), account)
+    });
+    H256::from(state_root_unhashed(accounts).0)
+}
+
+/// EIP-1559 交易的实际单价为 `min(maxFeePerGas, baseFee + maxPriorityFeePerGas)`
+fn gas_price(legacy: Option<&String>, max_fee: Option<&String>, priority_fee: Option<&String>, base_fee: u64) -> u64 {
+    match (legacy, max_fee, priority_fee) {
+        (Some(price), _, _) => number(price).low_u64(),
+        (None, Some(max_fee), Some(priority_fee)) => {
+            number(max_fee).low_u64().min(base_fee.saturating_add(number(priority_fee).low_u64()))
+        }
+        _ => panic!("transaction without a gas price"),
+    }
+}
+
+fn logs_hash(logs: &[Log]) -> H256 {
+    let mut stream = rlp::RlpStream::new_list(logs.len());
+    for log in logs {
+        stream.begin_list(3);
+        stream.append(&log.address.as_bytes());
+        stream.begin_list(log.topics.len());
+        for topic in &log.topics {
+            stream.append(&topic.as_bytes());
+        }
+        stream.append(&log.data);
+    }
+    keccak(&stream.out())
+}
+
+fn header_env(header: &BlockHeader) -> BlockEnv {
+    BlockEnv {
+        number: number(&header.number).low_u64(),
+        timestamp: number(&header.timestamp).low_u64(),
+        coinbase: address(&header.coinbase),
+        gas_limit: number(&header.gas_limit).low_u64(),
+        base_fee: header.base_fee_per_gas.as_deref().map(|fee| number(fee).low_u64()).unwrap_or(0),
+        prev_randao: hash(&header.mix_hash),
+        chain_id: 1,
+    }
+}
+
+/// 以系统地址调用系统合约，合约不存在时跳过
+fn system_call(state: &mut State, env: &BlockEnv, contract: &str, input: Vec<u8>) {
+    let contract = address(contract);
+    if !state.contract_states.contains_key(&address_hex(&contract)) {
+        return;
+    }
+    let outcome = Evm::new(state, env.clone()).call(address(SYSTEM_ADDRESS), contract, U256::zero(), input, SYSTEM_CALL_GAS, false);
+    assert!(outcome.is_success(), "system call to {contract:#x} failed: {:?}", outcome.status);
+}
+
+/// 执行一个区块，返回失败描述
+fn execute_block(state: &mut State, block: &Block, header: &BlockHeader) -> Vec<String> {
+    let env = header_env(header);
+    if let Some(root) = &header.parent_beacon_block_root {
+        system_call(state, &env, BEACON_ROOTS_ADDRESS, bytes(root));
+    }
+    system_call(state, &env, HISTORY_STORAGE_ADDRESS, bytes(&header.parent_hash));
+
+    let mut failures = Vec::new();
+    for (index, transaction) in block.transactions.iter().enumerate() {
+        let transaction = Transaction {
+            caller: address(&transaction.sender),
+            to: (!transaction.to.is_empty()).then(|| address(&transaction.to)),
+            nonce: number(&transaction.nonce).low_u64(),
+            value: number(&transaction.value),
+            data: bytes(&transaction.data),
+            gas_limit: number(&transaction.gas_limit).low_u64(),
+            gas_price: gas_price(
+                transaction.gas_price.as_ref(),
+                transaction.max_fee_per_gas.as_ref(),
+                transaction.max_priority_fee_per_gas.as_ref(),
+                env.base_fee,
+            ),
+        };
+        if let Err(error) = Evm::new(state, env.clone()).transact(&transaction) {
+            failures.push(format!("transaction {index} rejected: {error}"));
+        }
+    }
+
+    for withdrawal in &block.withdrawals {
+        let key = address_hex(&address(&withdrawal.address));
+        let amount = number(&withdrawal.amount) * U256::exp10(9);
+        let balance = match state.wide_balances.remove(&key) {
+            Some(balance) => U256::from_big_endian(&balance),
+            None => U256::from(state.balances.remove(&key).unwrap_or(0)),
+        } + amount;
+        if balance.bits() > 64 {
+            state.wide_balances.insert(key, word(balance).to_vec());
+        } else {
+            state.balances.insert(key, balance.low_u64());
+        }
+    }
+
+    system_call(state, &env, WITHDRAWAL_REQUEST_ADDRESS, Vec::new());
+    system_call(state, &env, CONSOLIDATION_REQUEST_ADDRESS, Vec::new());
+    failures
+}
+
+/// 运行一个区块链测试，返回失败描述
+fn run_blockchain_test(name: &str, test: &BlockchainTest) -> Vec<String> {
+    let mut failures = Vec::new();
+    let mut state = load(&test.pre);
+    let genesis = state_root(&state);
+    if genesis != hash(&test.genesis_block_header.state_root) {
+        failures.push(format!("{name}: genesis state root {genesis:#x}, expected {}", test.genesis_block_header.state_root));
+    }
+
+    for block in &test.blocks {
+        let Some(header) = &block.block_header else {
+            continue;
+        };
+        let label = format!("{name} block {}", number(&header.number));
+        failures.extend(execute_block(&mut state, block, header).into_iter().map(|failure| format!("{label}: {failure}")));
+        let root = state_root(&state);
+        if root != hash(&header.state_root) {
+            failures.push(format!("{label}: state root {root:#x}, expected {}", header.state_root));
+        }
+    }
+
+    let root = state_root(&state);
+    let expected = state_root(&load(&test.post_state));
+    if root != expected {
+        failures.push(format!("{name}: post state root {root:#x}, expected {expected:#x}"));
+    }
+    failures
+}
+
+fn block_env(env: &Env) -> BlockEnv {
+    BlockEnv {
+        number: number(&env.current_number).low_u64(),
+        timestamp: number(&env.current_timestamp).low_u64(),
+        coinbase: address(&env.current_coinbase),
+        gas_limit: number(&env.current_gas_limit).low_u64(),
+        base_fee: number(&env.current_base_fee).low_u64(),
+        prev_randao: env.current_random.as_deref().map(hash).unwrap_or_default(),
+        chain_id: 1,
+    }
+}
+
+fn transaction(template: &TransactionTemplate, indexes: &Indexes, base_fee: u64) -> Transaction {
+    Transaction {
+        caller: address(&template.sender),
+        to: (!template.to.is_empty()).then(|| address(&template.to)),
+        nonce: number(&template.nonce).low_u64(),
+        value: number(&template.value[indexes.value]),
+        data: bytes(&template.data[indexes.data]),
+        gas_limit: number(&template.gas_limit[indexes.gas]).low_u64(),
+        gas_price: gas_price(
+            template.gas_price.as_ref(),
+            template.max_fee_per_gas.as_ref(),
+            template.max_priority_fee_per_gas.as_ref(),
+            base_fee,
+        ),
+    }
+}
+
+/// 运行一个状态测试的全部期望结果，返回失败描述；不支持的交易返回 `None`
+fn run_state_test(name: &str, test: &StateTest) -> Option<Vec<String>> {
+    let template = &test.transaction;
+    let has_access_list = template.access_lists.iter().flatten().any(|list| !list.is_empty());
+    if has_access_list || !template.blob_versioned_hashes.is_empty() {
+        return None;
+    }
+
+    let mut failures = Vec::new();
+    let env = block_env(&test.env);
+    for (index, expectation) in test.post.get(FORK).into_iter().flatten().enumerate() {
+        let label = format!("{name}[{index}]");
+        let mut state = load(&test.pre);
+        let transaction = transaction(template, &expectation.indexes, env.base_fee);
+        let result = Evm::new(&mut state, env.clone()).transact(&transaction);
+
+        match (&result, &expectation.expect_exception) {
+            (Ok(_), Some(exception)) => failures.push(format!("{label}: expected {exception}, transaction succeeded")),
+            (Err(error), None) => failures.push(format!("{label}: transaction rejected: {error}")),
+            _ => {}
+        }
+        let logs = result.map(|outcome| logs_hash(&outcome.logs)).unwrap_or_else(|_| logs_hash(&[]));
+        if logs != hash(&expectation.logs) {
+            failures.push(format!("{label}: logs hash {logs:#x}, expected {}", expectation.logs));
+        }
+        let root = state_root(&state);
+        if root != hash(&expectation.hash) {
+            failures.push(format!("{label}: state root {root:#x}, expected {}", expectation.hash));
+        }
+    }
+    Some(failures)
+}
+
+fn json_files(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(directory).expect("readable fixture directory") {
+        let path = entry.expect("directory entry").path();
+        if path.is_dir() {
+            files.extend(json_files(&path));
+        } else if path.extension().is_some_and(|extension| extension == "json") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_blockchain_test_fixtures() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/smart_contract_engine/evm/fixtures/blockchain_tests");
+    let mut cases = 0;
+    let mut failures = Vec::new();
+    for file in json_files(&directory) {
+        let json = std::fs::read_to_string(&file).expect("readable fixture");
+        let tests: BTreeMap<String, BlockchainTest> = serde_json::from_str(&json).expect("valid blockchain test fixture");
+        for (name, test) in &tests {
+            cases += 1;
+            failures.extend(run_blockchain_test(name, test));
+        }
+    }
+    assert!(cases > 0, "no blockchain test cases loaded");
+    assert!(failures.is_empty(), "{} blockchain test mismatches:\n{}", failures.len(), failures.join("\n"));
+}
+
+/// 运行目录下的全部状态测试，返回（运行的用例数，跳过的用例数，失败描述）
+fn run_state_tests(directory: &Path) -> (usize, usize, Vec<String>) {
+    let mut cases = 0;
+    let mut skipped = 0;
+    let mut failures = Vec::new();
+    for file in json_files(directory) {
+        let json = std::fs::read_to_string(&file).expect("readable fixture");
+        let tests: BTreeMap<String, StateTest> = serde_json::from_str(&json).expect("valid state test fixture");
+        for (name, test) in &tests {
+            let count = test.post.get(FORK).map_or(0, Vec::len);
+            match run_state_test(name, test) {
+                Some(mismatches) => {
+                    cases += count;
+                    failures.extend(mismatches);
+                }
+                None => skipped += count,
+            }
+        }
+    }
+    (cases, skipped, failures)
+}
+
+#[test]
+fn test_state_test_fixtures() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/smart_contract_engine/evm/fixtures/state_tests");
+    let (cases, skipped, failures) = run_state_tests(&directory);
+    assert!(cases > 0, "no state test cases loaded");
+    assert_eq!(skipped, 0, "vendored state tests must all be supported");
+    assert!(failures.is_empty(), "{} state test mismatches:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+#[ignore = "需要用环境变量 ETHEREUM_TESTS 指向 ethereum/tests 的检出目录"]
+fn test_general_state_tests() {
+    let root = std::env::var("ETHEREUM_TESTS").expect("ETHEREUM_TESTS points to an ethereum/tests checkout");
+    let (cases, skipped, failures) = run_state_tests(&Path::new(&root).join("GeneralStateTests"));
+    println!("{cases} state test cases run, {skipped} skipped");
+    assert!(cases > 0, "no state test cases loaded");
+    assert!(failures.is_empty(), "{} state test mismatches:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn test_empty_roots() {
+    // 以太坊中空账户树的根和空日志列表的哈希
+    assert_eq!(
+        format!("{:#x}", state_root(&State::new())),
+        "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+    );
+    assert_eq!(
+        format!("{:#x}", logs_hash(&[])),
+        "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+    );
+}
@@ -0,0 +1,387 @@
+//! # 带日志的世界状态
+//!
+//! EVM 账户直接保存在 [`State`] 中：余额和 nonce 分别在 `balances`、`nonces`，
+//! 超出 `u64` 的余额保存在 `wide_balances`，代码和存储在 `contract_states`
+//! （键为带 `0x` 前缀的小写十六进制地址）。
+//! 存储槽的键为 32 字节槽号的十六进制，值为 32 字节大端整数，零值不保存。
+//!
+//! 每次修改都记入撤销日志，调用帧失败时回滚到进入该帧时的检查点。
+//! 冷/热访问集合、瞬时存储和退款计数同样随帧回滚。交易结束时留下的状态修改转换为
+//! [`StateJournal`] 交给调用方，调用方可以在 EVM 之外撤销整笔交易。
+
+use super::{address_hex, keccak, Address, Log, Transfer, H256, U256};
+use crate::core::state::{ContractState, JournalEntry, State, StateJournal};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// 撤销日志条目，保存修改前的值
+enum Entry {
+    Balance(String, Option<u64>, Option<Vec<u8>>),
+    Nonce(String, Option<u64>),
+    Account(String, Option<ContractState>),
+    Storage(Address, String, Option<Vec<u8>>),
+    Transient(Address, H256, U256),
+    WarmAccount(Address),
+    WarmSlot(Address, H256),
+    Created(Address),
+    Refund(i64),
+}
+
+/// 调用帧的检查点
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Checkpoint {
+    journal: usize,
+    logs: usize,
+    transfers: usize,
+    destructed: usize,
+}
+
+/// 一次交易执行期间的世界状态
+pub(super) struct World<'a> {
+    state: &'a mut State,
+    journal: Vec<Entry>,
+    pub logs: Vec<Log>,
+    pub transfers: Vec<Transfer>,
+    /// 累计退款，帧内可以暂时为负
+    pub refund: i64,
+    warm_accounts: HashSet<Address>,
+    warm_slots: HashSet<(Address, H256)>,
+    /// 交易开始时各存储槽的值
+    original: HashMap<(Address, H256), U256>,
+    transient: HashMap<(Address, H256), U256>,
+    /// 本交易中创建的合约，只有它们能被 SELFDESTRUCT 删除（EIP-6780）
+    created: HashSet<Address>,
+    destructed: Vec<Address>,
+}
+
+/// 交易提交后的副作用
+pub(super) struct Effects {
+    pub logs: Vec<Log>,
+    pub transfers: Vec<Transfer>,
+    /// 各账户被写入的存储槽及最终值，空值表示已删除
+    pub storage: HashMap<Address, HashMap<String, Vec<u8>>>,
+    /// 本交易对状态的全部修改
+    pub journal: StateJournal,
+}
+
+fn slot_key(slot: H256) -> String {
+    format!("0x{}", hex::encode(slot.as_bytes()))
+}
+
+impl<'a> World<'a> {
+    pub fn new(state: &'a mut State) -> Self {
+        Self {
+            state,
+            journal: Vec::new(),
+            logs: Vec::new(),
+            transfers: Vec::new(),
+            refund: 0,
+            warm_accounts: HashSet::new(),
+            warm_slots: HashSet::new(),
+            original: HashMap::new(),
+            transient: HashMap::new(),
+            created: HashSet::new(),
+            destructed: Vec::new(),
+        }
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            journal: self.journal.len(),
+            logs: self.logs.len(),
+            transfers: self.transfers.len(),
+            destructed: self.destructed.len(),
+        }
+    }
+
+    /// 撤销检查点之后的全部修改
+    pub fn revert(&mut self, checkpoint: Checkpoint) {
+        while self.journal.len() > checkpoint.journal {
+            match self.journal.pop().expect("journal length checked") {
+                Entry::Balance(key, old, old_wide) => {
+                    restore(&mut self.state.balances, key.clone(), old);
+                    restore(&mut self.state.wide_balances, key, old_wide);
+                }
+                Entry::Nonce(key, old) => restore(&mut self.state.nonces, key, old),
+                Entry::Account(key, old) => restore(&mut self.state.contract_states, key, old),
+                Entry::Storage(address, key, old) => {
+                    if let Some(account) = self.state.contract_states.get_mut(&address_hex(&address)) {
+                        restore(&mut account.storage, key, old);
+                    }
+                }
+                Entry::Transient(address, slot, old) => {
+                    self.transient.insert((address, slot), old);
+                }
+                Entry::WarmAccount(address) => {
+                    self.warm_accounts.remove(&address);
+                }
+                Entry::WarmSlot(address, slot) => {
+                    self.warm_slots.remove(&(address, slot));
+                }
+                Entry::Created(address) => {
+                    self.created.remove(&address);
+                }
+                Entry::Refund(old) => self.refund = old,
+            }
+        }
+        self.logs.truncate(checkpoint.logs);
+        self.transfers.truncate(checkpoint.transfers);
+        self.destructed.truncate(checkpoint.destructed);
+    }
+
+    /// 余额、nonce 均为零且没有代码的账户视为空账户（EIP-161）
+    pub fn is_empty(&self, address: &Address) -> bool {
+        self.balance(address).is_zero() && self.nonce(address) == 0 && self.code(address).is_empty()
+    }
+
+    pub fn balance(&self, address: &Address) -> U256 {
+        let key = address_hex(address);
+        match self.state.wide_balances.get(&key) {
+            Some(balance) => U256::from_big_endian(balance),
+            None => U256::from(self.state.balances.get(&key).copied().unwrap_or(0)),
+        }
+    }
+
+    /// 写入余额：能放进 `u64` 的保存在 `balances`，否则保存在 `wide_balances`
+    fn set_balance(&mut self, address: &Address, balance: U256) {
+        let key = address_hex(address);
+        let (old, old_wide) = if balance.bits() <= 64 {
+            (self.state.balances.insert(key.clone(), balance.low_u64()), self.state.wide_balances.remove(&key))
+        } else {
+            let mut bytes = vec![0u8; 32];
+            balance.to_big_endian(&mut bytes);
+            (self.state.balances.remove(&key), self.state.wide_balances.insert(key.clone(), bytes))
+        };
+        self.journal.push(Entry::Balance(key, old, old_wide));
+    }
+
+    /// 增加余额，溢出时返回 `false`
+    pub fn credit(&mut self, address: &Address, amount: U256) -> bool {
+        if amount.is_zero() {
+            return true;
+        }
+        match self.balance(address).checked_add(amount) {
+            Some(total) => {
+                self.set_balance(address, total);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 扣减余额，不足时返回 `false`
+    pub fn debit(&mut self, address: &Address, amount: U256) -> bool {
+        if amount.is_zero() {
+            return true;
+        }
+        match self.balance(address).checked_sub(amount) {
+            Some(remaining) => {
+                self.set_balance(address, remaining);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 转账，余额不足时不做任何修改并返回 `false`
+    pub fn transfer(&mut self, from: &Address, to: &Address, value: U256) -> bool {
+        if value.is_zero() {
+            return true;
+        }
+        let checkpoint = self.checkpoint();
+        if !self.debit(from, value) || !self.credit(to, value) {
+            self.revert(checkpoint);
+            return false;
+        }
+        self.transfers.push(Transfer { from: *from, to: *to, value });
+        true
+    }
+
+    pub fn nonce(&self, address: &Address) -> u64 {
+        self.state.nonces.get(&address_hex(address)).copied().unwrap_or(0)
+    }
+
+    pub fn set_nonce(&mut self, address: &Address, nonce: u64) {
+        let key = address_hex(address);
+        let old = self.state.nonces.insert(key.clone(), nonce);
+        self.journal.push(Entry::Nonce(key, old));
+    }
+
+    pub fn code(&self, address: &Address) -> &[u8] {
+        self.state.contract_states.get(&address_hex(address)).map(|account| account.code.as_slice()).unwrap_or_default()
+    }
+
+    /// 代码哈希，空账户为零（EIP-1052）
+    pub fn code_hash(&self, address: &Address) -> H256 {
+        if self.is_empty(address) {
+            H256::zero()
+        } else {
+            keccak(self.code(address))
+        }
+    }
+
+    fn account_mut(&mut self, address: &Address) -> &mut ContractState {
+        let key = address_hex(address);
+        if !self.state.contract_states.contains_key(&key) {
+            self.journal.push(Entry::Account(key.clone(), None));
+        }
+        self.state.contract_states.entry(key.clone()).or_insert_with(|| ContractState::new(key, Vec::new()))
+    }
+
+    pub fn set_code(&mut self, address: &Address, code: Vec<u8>) {
+        let key = address_hex(address);
+        let old = self.state.contract_states.get(&key).cloned();
+        self.journal.push(Entry::Account(key, old));
+        self.account_mut(address).code = code;
+    }
+
+    /// 为 CREATE 初始化新账户：nonce 置 1（EIP-161），已有余额保留
+    pub fn create_account(&mut self, address: &Address) {
+        self.set_nonce(address, 1);
+        self.account_mut(address);
+        self.created.insert(*address);
+        self.journal.push(Entry::Created(*address));
+    }
+
+    /// 地址上已有合约或已使用过 nonce 时不能再创建合约
+    pub fn has_collision(&self, address: &Address) -> bool {
+        self.nonce(address) != 0
+            || !self.code(address).is_empty()
+            || self.state.contract_states.get(&address_hex(address)).is_some_and(|account| !account.storage.is_empty())
+    }
+
+    pub fn sload(&self, address: &Address, slot: H256) -> U256 {
+        self.state.contract_states
+            .get(&address_hex(address))
+            .and_then(|account| account.storage.get(&slot_key(slot)))
+            .map(|value| U256::from_big_endian(value))
+            .unwrap_or_default()
+    }
+
+    /// 交易开始时存储槽的值
+    pub fn original(&mut self, address: &Address, slot: H256) -> U256 {
+        if let Some(value) = self.original.get(&(*address, slot)) {
+            return *value;
+        }
+        let value = self.sload(address, slot);
+        self.original.insert((*address, slot), value);
+        value
+    }
+
+    pub fn sstore(&mut self, address: &Address, slot: H256, value: U256) {
+        self.original(address, slot);
+        let key = slot_key(slot);
+        let account = self.account_mut(address);
+        let old = if value.is_zero() {
+            account.storage.remove(&key)
+        } else {
+            let mut bytes = vec![0u8; 32];
+            value.to_big_endian(&mut bytes);
+            account.storage.insert(key.clone(), bytes)
+        };
+        self.journal.push(Entry::Storage(*address, key, old));
+    }
+
+    pub fn tload(&self, address: &Address, slot: H256) -> U256 {
+        self.transient.get(&(*address, slot)).copied().unwrap_or_default()
+    }
+
+    pub fn tstore(&mut self, address: &Address, slot: H256, value: U256) {
+        let old = self.transient.insert((*address, slot), value).unwrap_or_default();
+        self.journal.push(Entry::Transient(*address, slot, old));
+    }
+
+    /// 标记账户为已访问，返回此前是否为冷账户
+    pub fn warm_account(&mut self, address: &Address) -> bool {
+        let cold = self.warm_accounts.insert(*address);
+        if cold {
+            self.journal.push(Entry::WarmAccount(*address));
+        }
+        cold
+    }
+
+    /// 标记存储槽为已访问，返回此前是否为冷存储槽
+    pub fn warm_slot(&mut self, address: &Address, slot: H256) -> bool {
+        let cold = self.warm_slots.insert((*address, slot));
+        if cold {
+            self.journal.push(Entry::WarmSlot(*address, slot));
+        }
+        cold
+    }
+
+    pub fn add_refund(&mut self, delta: i64) {
+        if delta != 0 {
+            self.journal.push(Entry::Refund(self.refund));
+            self.refund += delta;
+        }
+    }
+
+    /// 把全部余额转给受益人；本交易创建的合约在交易结束时删除
+    pub fn selfdestruct(&mut self, address: &Address, beneficiary: &Address) {
+        let balance = self.balance(address);
+        if address == beneficiary {
+            if self.created.contains(address) {
+                self.debit(address, balance);
+            }
+        } else {
+            self.transfer(address, beneficiary, balance);
+        }
+        if self.created.contains(address) {
+            self.destructed.push(*address);
+        }
+    }
+
+    /// 删除自毁的账户，返回本交易的副作用
+    pub fn finish(self) -> Effects {
+        let mut journal = StateJournal::default();
+        for entry in &self.journal {
+            match entry {
+                Entry::Balance(key, old, old_wide) => journal.push(JournalEntry::Balance(key.clone(), *old, old_wide.clone())),
+                Entry::Nonce(key, old) => journal.push(JournalEntry::Nonce(key.clone(), *old)),
+                Entry::Account(key, old) => journal.push(JournalEntry::Contract(key.clone(), old.clone())),
+                Entry::Storage(address, key, old) => {
+                    journal.push(JournalEntry::ContractStorage(address_hex(address), key.clone(), old.clone()))
+                }
+                _ => {}
+            }
+        }
+        for address in &self.destructed {
+            let key = address_hex(address);
+            journal.record_balance(self.state, &key);
+            journal.record_nonce(self.state, &key);
+            journal.push(JournalEntry::Contract(key.clone(), self.state.contract_states.get(&key).cloned()));
+            self.state.balances.remove(&key);
+            self.state.wide_balances.remove(&key);
+            self.state.nonces.remove(&key);
+            self.state.contract_states.remove(&key);
+        }
+
+        let written: BTreeSet<(Address, &String)> = self.journal.iter()
+            .filter_map(|entry| match entry {
+                Entry::Storage(address, key, _) => Some((*address, key)),
+                _ => None,
+            })
+            .collect();
+        let mut storage: HashMap<Address, HashMap<String, Vec<u8>>> = HashMap::new();
+        for (address, key) in written {
+            let value = self.state.contract_states
+                .get(&address_hex(&address))
+                .and_then(|account| account.storage.get(key).cloned())
+                .unwrap_or_default();
+            storage.entry(address).or_default().insert(key.clone(), value);
+        }
+
+        Effects { logs: self.logs, transfers: self.transfers, storage, journal }
+    }
+}
+
+/// 恢复映射中的旧值，旧值不存在时删除
+fn restore<V>(map: &mut HashMap<String, V>, key: String, old: Option<V>) {
+    match old {
+        Some(value) => {
+            map.insert(key, value);
+        }
+        None => {
+            map.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! # 合约宿主函数
+//!
+//! 合约通过 `env` 模块导入以下函数与链交互。指针和长度均为 i32，地址等字符串按 UTF-8 传递。
+//! 返回变长数据的函数把数据放入返回数据缓冲区并返回其长度，合约再用 `return_data_copy` 取回。
+//!
+//! | 函数 | 签名 | gas |
+//! |------|------|-----|
+//! | `storage_read(key_ptr, key_len) -> i32` | 值放入返回数据，返回长度，不存在返回 -1 | `GAS_STORAGE_READ` + 字节数 |
+//! | `storage_write(key_ptr, key_len, value_ptr, value_len)` | 写入空值等同删除 | `GAS_STORAGE_WRITE` + 字节数 |
+//! | `storage_delete(key_ptr, key_len) -> i32` | 返回删除前是否存在 | `GAS_STORAGE_DELETE` + 字节数 |
+//! | `caller() -> i32` / `contract_address() -> i32` | 地址放入返回数据 | `GAS_HOST_CALL` |
+//! | `value() / block_height() / timestamp() / balance() -> i64` | | `GAS_HOST_CALL` |
+//! | `transfer(to_ptr, to_len, amount: i64) -> i32` | 成功返回 0，余额不足返回 -1 | `GAS_TRANSFER` |
+//! | `emit_event(name_ptr, name_len, topics_ptr, topic_count, data_ptr, data_len)` | 每个主题 32 字节 | `GAS_EVENT` + 每主题 `GAS_EVENT_TOPIC` + 字节数 |
+//! | `sha256 / keccak256 / blake2b256(ptr, len, out_ptr)` | 向 `out_ptr` 写入 32 字节 | `GAS_HASH` + 字节数 |
+//! | `verify_signature(algorithm, msg_ptr, msg_len, sig_ptr, sig_len, key_ptr, key_len) -> i32` | 0 = Ed25519，1 = secp256k1 ECDSA（消息为 32 字节摘要，DER 签名），有效返回 1 | `GAS_VERIFY_SIGNATURE` + 字节数 |
+//! | `return_data_size() -> i32` | | `GAS_HOST_CALL` |
+//! | `return_data_copy(dest_ptr, offset, len)` | 越界时陷入 | `GAS_HOST_CALL` + 字节数 |
+//! | `call(addr_ptr, addr_len, method_ptr, method_len, params_ptr, params_len, value: i64, gas: i64, flags) -> i32` | 调用其他合约，成功返回 0、失败返回 1，输出或错误信息放入返回数据 | `GAS_CALL` + 转值时 `GAS_TRANSFER` + 字节数 + 被调方消耗 |
+//!
+//! gas 从同一燃料池中扣除，费用只取决于参数长度，各节点一致。
+//! 存储写入、转账和事件先记入当前调用帧的日志（见 [`super::journal`]），调用成功后才提交；
+//! 常量方法和静态调用中的写操作会陷入。
+//!
+//! 跨合约调用最多转发剩余 gas 的 63/64（`gas` 为 0 或超出时取上限），未用完的部分退回调用方。
+//! 调用深度超限、被调合约禁止重入、余额不足或被调方执行失败时返回 1，只回滚被调方这一帧。
+
+use super::journal::CallStack;
+use super::{ContractError, ContractEvent, ContractTable, ExecutionContext, ExecutionLimits};
+use crate::components::cryptography::hash::{Blake2bAlgorithm, HashAlgorithm, Keccak256Algorithm, Sha256Algorithm};
+use crate::components::cryptography::signature::{EcdsaAlgorithm, Ed25519Algorithm, SignatureAlgorithm};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use wasmtime::{Caller, Linker, ResourceLimiter};
+
+/// 每次宿主调用的基础费用
+pub const GAS_HOST_CALL: u64 = 20;
+/// 每读写、哈希或拷贝一个字节的费用
+pub const GAS_PER_BYTE: u64 = 1;
+/// 读取存储
+pub const GAS_STORAGE_READ: u64 = 200;
+/// 写入存储
+pub const GAS_STORAGE_WRITE: u64 = 5_000;
+/// 删除存储
+pub const GAS_STORAGE_DELETE: u64 = 1_000;
+/// 转账
+pub const GAS_TRANSFER: u64 = 2_500;
+/// 发出事件
+pub const GAS_EVENT: u64 = 375;
+/// 每个事件主题
+pub const GAS_EVENT_TOPIC: u64 = 375;
+/// 哈希
+pub const GAS_HASH: u64 = 60;
+/// 签名验证
+pub const GAS_VERIFY_SIGNATURE: u64 = 3_000;
+/// 跨合约调用（不含转发给被调方的 gas）
+pub const GAS_CALL: u64 = 700;
+
+/// 单个事件最多的主题数
+pub const MAX_EVENT_TOPICS: usize = 4;
+
+/// 签名算法编号
+pub const SIGNATURE_ED25519: i32 = 0;
+pub const SIGNATURE_ECDSA_SECP256K1: i32 = 1;
+
+/// `call` 的标志位：静态调用，被调方及其后续调用均不得修改状态
+pub const CALL_STATIC: i32 = 1;
+
+/// 合约调用产生的转账
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceTransfer {
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+}
+
+/// 单个调用帧的宿主状态
+///
+/// 存储写入、转账和事件记录在调用栈当前帧的日志中；跨合约调用时调用栈移交给被调方的 Store，返回后取回。
+pub struct HostState {
+    pub(super) context: ExecutionContext,
+    pub(super) limits: ExecutionLimits,
+    /// 常量方法或静态调用：禁止写操作
+    pub(super) read_only: bool,
+    /// 全部合约，供跨合约调用查找被调方
+    contracts: ContractTable,
+    pub(super) stack: CallStack,
+    return_data: Vec<u8>,
+}
+
+impl HostState {
+    pub(super) fn new(
+        context: ExecutionContext,
+        limits: ExecutionLimits,
+        contracts: ContractTable,
+        stack: CallStack,
+        read_only: bool,
+    ) -> Self {
+        Self {
+            context,
+            limits,
+            read_only,
+            contracts,
+            stack,
+            return_data: Vec::new(),
+        }
+    }
+
+    /// 把写入记入当前帧，空值表示删除
+    fn write_storage(&mut self, key: String, value: Vec<u8>) {
+        let address = self.stack.current().address.clone();
+        self.stack.journal_mut().storage.entry(address).or_default().insert(key, value);
+    }
+
+    fn ensure_writable(&self) -> Result<(), ContractError> {
+        if self.read_only {
+            return Err(ContractError::ReadOnlyViolation);
+        }
+        Ok(())
+    }
+
+    fn set_return_data(&mut self, data: Vec<u8>) -> i32 {
+        self.return_data = data;
+        self.return_data.len() as i32
+    }
+}
+
+impl ResourceLimiter for HostState {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        if desired > self.limits.max_memory_bytes {
+            return Err(ContractError::MemoryLimitExceeded.into());
+        }
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        Ok(desired <= self.limits.max_table_elements)
+    }
+}
+
+/// 扣除宿主调用的 gas，不足时燃料清零并陷入
+fn charge(caller: &mut Caller<'_, HostState>, cost: u64) -> wasmtime::Result<()> {
+    let fuel = caller.get_fuel()?;
+    if fuel < cost {
+        caller.set_fuel(0)?;
+        return Err(ContractError::InsufficientGas.into());
+    }
+    caller.set_fuel(fuel - cost)?;
+    Ok(())
+}
+
+/// 按字节数计费
+fn byte_cost(len: i32) -> u64 {
+    len.max(0) as u64 * GAS_PER_BYTE
+}
+
+fn out_of_bounds() -> ContractError {
+    ContractError::Trapped("memory access out of bounds".to_string())
+}
+
+fn read_memory(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> wasmtime::Result<Vec<u8>> {
+    let memory = caller.get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or(ContractError::InvalidCode)?;
+    let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+    if ptr.saturating_add(len) > memory.data_size(&*caller) {
+        return Err(out_of_bounds().into());
+    }
+    let mut buffer = vec![0u8; len];
+    memory.read(&*caller, ptr, &mut buffer).map_err(|_| out_of_bounds())?;
+    Ok(buffer)
+}
+
+fn write_memory(caller: &mut Caller<'_, HostState>, ptr: i32, data: &[u8]) -> wasmtime::Result<()> {
+    let memory = caller.get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or(ContractError::InvalidCode)?;
+    memory.write(&mut *caller, ptr as u32 as usize, data).map_err(|_| out_of_bounds())?;
+    Ok(())
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> wasmtime::Result<String> {
+    String::from_utf8(read_memory(caller, ptr, len)?)
+        .map_err(|_| ContractError::InvalidParameters.into())
+}
+
+/// 计算哈希并写入合约内存
+fn hash_into(
+    caller: &mut Caller<'_, HostState>,
+    algorithm: &dyn HashAlgorithm,
+    ptr: i32,
+    len: i32,
+    out_ptr: i32,
+) -> wasmtime::Result<()> {
+    charge(caller, GAS_HASH + byte_cost(len))?;
+    let data = read_memory(caller, ptr, len)?;
+    write_memory(caller, out_ptr, &algorithm.hash(&data))
+}
+
+/// 在链接器中注册全部宿主函数
+pub(super) fn link(linker: &mut Linker<HostState>) -> wasmtime::Result<()> {
+    linker.func_wrap("env", "storage_read", |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32| {
+        charge(&mut caller, GAS_STORAGE_READ + byte_cost(key_len))?;
+        let key = read_string(&mut caller, key_ptr, key_len)?;
+        match caller.data().stack.storage(&key) {
+            Some(value) => {
+                charge(&mut caller, byte_cost(value.len() as i32))?;
+                Ok(caller.data_mut().set_return_data(value))
+            }
+            None => {
+                caller.data_mut().return_data.clear();
+                Ok(-1)
+            }
+        }
+    })?;
+
+    linker.func_wrap("env", "storage_write", |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32| {
+        caller.data().ensure_writable()?;
+        charge(&mut caller, GAS_STORAGE_WRITE + byte_cost(key_len) + byte_cost(value_len))?;
+        let key = read_string(&mut caller, key_ptr, key_len)?;
+        let value = read_memory(&mut caller, value_ptr, value_len)?;
+        caller.data_mut().write_storage(key, value);
+        Ok(())
+    })?;
+
+    linker.func_wrap("env", "storage_delete", |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32| {
+        caller.data().ensure_writable()?;
+        charge(&mut caller, GAS_STORAGE_DELETE + byte_cost(key_len))?;
+        let key = read_string(&mut caller, key_ptr, key_len)?;
+        let existed = caller.data().stack.storage(&key).is_some();
+        caller.data_mut().write_storage(key, Vec::new());
+        Ok(existed as i32)
+    })?;
+
+    linker.func_wrap("env", "caller", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        let address = caller.data().context.caller.clone().into_bytes();
+        Ok(caller.data_mut().set_return_data(address))
+    })?;
+
+    linker.func_wrap("env", "contract_address", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        let address = caller.data().context.contract_address.clone().into_bytes();
+        Ok(caller.data_mut().set_return_data(address))
+    })?;
+
+    linker.func_wrap("env", "value", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        Ok(caller.data().context.value as i64)
+    })?;
+
+    linker.func_wrap("env", "block_height", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        Ok(caller.data().context.block_height as i64)
+    })?;
+
+    linker.func_wrap("env", "timestamp", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        Ok(caller.data().context.timestamp as i64)
+    })?;
+
+    linker.func_wrap("env", "balance", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        Ok(caller.data().stack.balance() as i64)
+    })?;
+
+    linker.func_wrap("env", "transfer", |mut caller: Caller<'_, HostState>, to_ptr: i32, to_len: i32, amount: i64| {
+        caller.data().ensure_writable()?;
+        charge(&mut caller, GAS_TRANSFER + byte_cost(to_len))?;
+        let to = read_string(&mut caller, to_ptr, to_len)?;
+        let amount = u64::try_from(amount).map_err(|_| ContractError::InvalidParameters)?;
+        if amount > caller.data().stack.balance() {
+            return Ok(-1);
+        }
+        let host = caller.data_mut();
+        let from = host.context.contract_address.clone();
+        host.stack.journal_mut().transfers.push(BalanceTransfer { from, to, amount });
+        Ok(0)
+    })?;
+
+    linker.func_wrap("env", "emit_event", |mut caller: Caller<'_, HostState>, name_ptr: i32, name_len: i32, topics_ptr: i32, topic_count: i32, data_ptr: i32, data_len: i32| {
+        caller.data().ensure_writable()?;
+        let topic_count = usize::try_from(topic_count)
+            .ok()
+            .filter(|count| *count <= MAX_EVENT_TOPICS)
+            .ok_or(ContractError::InvalidParameters)?;
+        charge(&mut caller, GAS_EVENT + GAS_EVENT_TOPIC * topic_count as u64 + byte_cost(name_len) + byte_cost(data_len))?;
+        let event_name = read_string(&mut caller, name_ptr, name_len)?;
+        let topics = read_memory(&mut caller, topics_ptr, (topic_count * 32) as i32)?
+            .chunks_exact(32)
+            .map(|topic| topic.try_into().expect("32-byte topic"))
+            .collect();
+        let data = read_memory(&mut caller, data_ptr, data_len)?;
+
+        let host = caller.data_mut();
+        let event = ContractEvent {
+            contract_address: host.context.contract_address.clone(),
+            event_name,
+            parameters: Vec::new(),
+            topics,
+            data,
+            timestamp: host.context.timestamp,
+            block_height: host.context.block_height,
+        };
+        let journal = host.stack.journal_mut();
+        journal.logs.push(format!("Event: {}", event.event_name));
+        journal.events.push(event);
+        Ok(())
+    })?;
+
+    linker.func_wrap("env", "sha256", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, out_ptr: i32| {
+        hash_into(&mut caller, &Sha256Algorithm, ptr, len, out_ptr)
+    })?;
+
+    linker.func_wrap("env", "keccak256", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, out_ptr: i32| {
+        hash_into(&mut caller, &Keccak256Algorithm, ptr, len, out_ptr)
+    })?;
+
+    linker.func_wrap("env", "blake2b256", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, out_ptr: i32| {
+        hash_into(&mut caller, &Blake2bAlgorithm, ptr, len, out_ptr)
+    })?;
+
+    linker.func_wrap("env", "verify_signature", |mut caller: Caller<'_, HostState>, algorithm: i32, msg_ptr: i32, msg_len: i32, sig_ptr: i32, sig_len: i32, key_ptr: i32, key_len: i32| {
+        charge(&mut caller, GAS_VERIFY_SIGNATURE + byte_cost(msg_len) + byte_cost(sig_len) + byte_cost(key_len))?;
+        let message = read_memory(&mut caller, msg_ptr, msg_len)?;
+        let signature = read_memory(&mut caller, sig_ptr, sig_len)?;
+        let public_key = read_memory(&mut caller, key_ptr, key_len)?;
+        let valid = match algorithm {
+            SIGNATURE_ED25519 => Ed25519Algorithm.verify(&message, &signature, &public_key),
+            SIGNATURE_ECDSA_SECP256K1 => EcdsaAlgorithm::new().verify(&message, &signature, &public_key),
+            _ => return Err(ContractError::InvalidParameters.into()),
+        };
+        // 格式错误的签名或公钥视为无效签名
+        Ok(valid.unwrap_or(false) as i32)
+    })?;
+
+    linker.func_wrap("env", "return_data_size", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, GAS_HOST_CALL)?;
+        Ok(caller.data().return_data.len() as i32)
+    })?;
+
+    linker.func_wrap("env", "return_data_copy", |mut caller: Caller<'_, HostState>, dest_ptr: i32, offset: i32, len: i32| {
+        charge(&mut caller, GAS_HOST_CALL + byte_cost(len))?;
+        let (offset, len) = (offset as u32 as usize, len as u32 as usize);
+        let data = caller.data().return_data
+            .get(offset..offset.saturating_add(len))
+            .ok_or_else(|| ContractError::Trapped("return data out of bounds".to_string()))?
+            .to_vec();
+        write_memory(&mut caller, dest_ptr, &data)
+    })?;
+
+    linker.func_wrap("env", "call", |mut caller: Caller<'_, HostState>, addr_ptr: i32, addr_len: i32, method_ptr: i32, method_len: i32, params_ptr: i32, params_len: i32, value: i64, gas: i64, flags: i32| {
+        let value = u64::try_from(value).map_err(|_| ContractError::InvalidParameters)?;
+        let mut cost = GAS_CALL + byte_cost(addr_len) + byte_cost(method_len) + byte_cost(params_len);
+        if value > 0 {
+            caller.data().ensure_writable()?;
+            cost += GAS_TRANSFER;
+        }
+        charge(&mut caller, cost)?;
+        let address = read_string(&mut caller, addr_ptr, addr_len)?;
+        let method = read_string(&mut caller, method_ptr, method_len)?;
+        let params = read_memory(&mut caller, params_ptr, params_len)?;
+
+        // 调用方至少保留剩余 gas 的 1/64
+        let available = caller.get_fuel()?;
+        let cap = available - available / 64;
+        let forwarded = match u64::try_from(gas) {
+            Ok(gas) if gas > 0 => gas.min(cap),
+            _ => cap,
+        };
+        caller.set_fuel(available - forwarded)?;
+
+        let request = NestedCall { address, method, params, value, gas: forwarded, is_static: flags & CALL_STATIC != 0 };
+        let (outcome, gas_used) = nested_call(caller.data_mut(), request);
+        let remaining = caller.get_fuel()?;
+        caller.set_fuel(remaining + forwarded - gas_used)?;
+
+        let (status, data) = match outcome {
+            Ok(output) => (0, output),
+            Err(error) => (1, error.to_string().into_bytes()),
+        };
+        caller.data_mut().return_data = data;
+        Ok(status)
+    })?;
+
+    Ok(())
+}
+
+/// 跨合约调用请求
+struct NestedCall {
+    address: String,
+    method: String,
+    params: Vec<u8>,
+    value: u64,
+    gas: u64,
+    is_static: bool,
+}
+
+/// 在新的调用帧中执行被调合约，返回结果和消耗的 gas
+///
+/// 调用栈移交给被调方，返回后取回：成功时被调方的日志并入当前帧，失败时丢弃。
+fn nested_call(host: &mut HostState, request: NestedCall) -> (Result<Vec<u8>, ContractError>, u64) {
+    let Some(callee) = host.contracts.get(&request.address).map(Arc::clone) else {
+        return (Err(ContractError::ContractNotFound), 0);
+    };
+
+    let checked = (|| {
+        if host.stack.depth() >= host.limits.max_call_depth {
+            return Err(ContractError::CallDepthExceeded);
+        }
+        if host.stack.contains(&request.address) && callee.state.lock().unwrap().non_reentrant {
+            return Err(ContractError::ReentrantCall);
+        }
+        let method = callee.check_call(&request.method, request.value)?;
+        if request.value > 0 && request.is_static {
+            return Err(ContractError::InvalidParameters);
+        }
+        if request.value > host.stack.balance() {
+            return Err(ContractError::InsufficientBalance);
+        }
+        Ok(method.constant)
+    })();
+    let constant = match checked {
+        Ok(constant) => constant,
+        Err(error) => return (Err(error), 0),
+    };
+
+    let context = ExecutionContext {
+        caller: host.context.contract_address.clone(),
+        value: request.value,
+        gas_limit: request.gas,
+        gas_used: 0,
+        block_height: host.context.block_height,
+        timestamp: host.context.timestamp,
+        contract_address: request.address.clone(),
+    };
+
+    let mut stack = std::mem::take(&mut host.stack);
+    stack.push(request.address.clone(), Arc::clone(&callee.state));
+    if request.value > 0 {
+        stack.journal_mut().transfers.push(BalanceTransfer {
+            from: context.caller.clone(),
+            to: request.address,
+            amount: request.value,
+        });
+    }
+
+    let read_only = host.read_only || request.is_static || constant;
+    let (outcome, mut stack, gas_used) = callee.run(&request.method, &request.params, context, Arc::clone(&host.contracts), stack, read_only);
+    stack.pop(outcome.is_ok());
+    host.stack = stack;
+    (outcome, gas_used)
+}
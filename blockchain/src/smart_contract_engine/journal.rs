@@ -0,0 +1,142 @@
+//! # 调用栈与帧日志
+//!
+//! 一次顶层调用中的每个合约调用帧都有自己的日志，记录存储写入、转账、事件和日志输出。
+//! 内层调用成功时日志并入外层帧，失败时整帧丢弃，只回滚它自己的修改；
+//! 顶层调用成功后，最外层帧的日志才写回各合约状态。
+
+use super::{BalanceTransfer, ContractEvent, ContractState, ContractTable};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// 单个调用帧的修改日志
+#[derive(Debug, Default)]
+pub(super) struct Journal {
+    /// 按合约地址分组的存储写入，空值表示删除
+    pub(super) storage: HashMap<String, HashMap<String, Vec<u8>>>,
+    pub(super) transfers: Vec<BalanceTransfer>,
+    pub(super) events: Vec<ContractEvent>,
+    pub(super) logs: Vec<String>,
+}
+
+impl Journal {
+    /// 并入成功返回的内层帧
+    fn merge(&mut self, inner: Journal) {
+        for (address, changes) in inner.storage {
+            self.storage.entry(address).or_default().extend(changes);
+        }
+        self.transfers.extend(inner.transfers);
+        self.events.extend(inner.events);
+        self.logs.extend(inner.logs);
+    }
+
+    /// 写回合约状态：`origin` 为顶层合约，其余合约从合约表中查找
+    pub(super) fn commit(&self, origin: &str, origin_state: &Arc<Mutex<ContractState>>, contracts: &ContractTable) {
+        let state_of = |address: &str| {
+            if address == origin {
+                Some(Arc::clone(origin_state))
+            } else {
+                contracts.get(address).map(|contract| Arc::clone(&contract.state))
+            }
+        };
+
+        for (address, changes) in &self.storage {
+            let Some(state) = state_of(address) else { continue };
+            let mut state = state.lock().unwrap();
+            for (key, value) in changes {
+                if value.is_empty() {
+                    state.storage.remove(key);
+                } else {
+                    state.storage.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        // 转出方必为合约；转给外部账户的余额由调用方按 `ExecutionResult::transfers` 入账
+        for transfer in &self.transfers {
+            if let Some(state) = state_of(&transfer.from) {
+                let mut state = state.lock().unwrap();
+                state.balance = state.balance.saturating_sub(transfer.amount);
+            }
+            if let Some(state) = state_of(&transfer.to) {
+                state.lock().unwrap().balance += transfer.amount;
+            }
+        }
+    }
+}
+
+/// 调用帧
+#[derive(Debug)]
+pub(super) struct Frame {
+    /// 正在执行的合约
+    pub(super) address: String,
+    pub(super) state: Arc<Mutex<ContractState>>,
+    pub(super) journal: Journal,
+}
+
+/// 调用栈，栈顶为当前帧
+#[derive(Debug, Default)]
+pub(super) struct CallStack {
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub(super) fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 合约是否已在栈中（即再次调用它属于重入）
+    pub(super) fn contains(&self, address: &str) -> bool {
+        self.frames.iter().any(|frame| frame.address == address)
+    }
+
+    pub(super) fn push(&mut self, address: String, state: Arc<Mutex<ContractState>>) {
+        self.frames.push(Frame { address, state, journal: Journal::default() });
+    }
+
+    /// 弹出当前帧：成功时并入外层帧，失败时丢弃；栈底帧原样返回给顶层提交
+    pub(super) fn pop(&mut self, success: bool) -> Option<Frame> {
+        let frame = self.frames.pop()?;
+        match self.frames.last_mut() {
+            Some(parent) if success => {
+                parent.journal.merge(frame.journal);
+                None
+            }
+            Some(_) => None,
+            None => Some(frame),
+        }
+    }
+
+    pub(super) fn current(&self) -> &Frame {
+        self.frames.last().expect("call stack is never empty during execution")
+    }
+
+    pub(super) fn journal_mut(&mut self) -> &mut Journal {
+        &mut self.frames.last_mut().expect("call stack is never empty during execution").journal
+    }
+
+    /// 读取当前合约的存储，从栈顶向下查找未提交的写入
+    pub(super) fn storage(&self, key: &str) -> Option<Vec<u8>> {
+        let current = self.current();
+        self.frames.iter()
+            .rev()
+            .find_map(|frame| frame.journal.storage.get(&current.address)?.get(key))
+            .cloned()
+            .or_else(|| current.state.lock().unwrap().storage.get(key).cloned())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// 当前合约的余额，计入栈中各帧尚未提交的转入和转出
+    pub(super) fn balance(&self) -> u64 {
+        let current = self.current();
+        let base = current.state.lock().unwrap().balance;
+        let (incoming, outgoing) = self.frames.iter()
+            .flat_map(|frame| &frame.journal.transfers)
+            .fold((0u64, 0u64), |(incoming, outgoing), transfer| {
+                (
+                    incoming + if transfer.to == current.address { transfer.amount } else { 0 },
+                    outgoing + if transfer.from == current.address { transfer.amount } else { 0 },
+                )
+            });
+        (base + incoming).saturating_sub(outgoing)
+    }
+}
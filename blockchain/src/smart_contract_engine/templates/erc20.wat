@@ -0,0 +1,198 @@
+;; ERC20 代币模板
+;;
+;; 参数和返回值按 Solidity ABI 编码（不含选择器），金额只支持 64 位以内。
+;; 余额以账户地址的十六进制字符串（"0x" 加 40 位小写十六进制）为键，值为 8 字节大端整数；
+;; 调用方必须是这种格式的地址。第一个调用 `mint` 的账户成为铸币者，部署后应立即铸币。
+;; 余额不足、铸币者不符或金额超出 64 位时以 `unreachable` 回滚。
+(module
+  (import "env" "storage_read" (func $read (param i32 i32) (result i32)))
+  (import "env" "storage_write" (func $write (param i32 i32 i32 i32)))
+  (import "env" "return_data_copy" (func $copy (param i32 i32 i32)))
+  (import "env" "caller" (func $caller (result i32)))
+  (import "env" "keccak256" (func $keccak (param i32 i32 i32)))
+  (import "env" "emit_event" (func $emit (param i32 i32 i32 i32 i32 i32)))
+  (memory (export "memory") 1)
+
+  ;; 0: 事件签名，前 8 字节兼作事件名；48: 十六进制字符表；64: 铸币者的存储键
+  (data (i32.const 0) "Transfer(address,address,uint256)")
+  (data (i32.const 48) "0123456789abcdef")
+  (data (i32.const 64) "minter")
+  ;; 128: 发送方键；176: 接收方键；224: 存储值；256: 事件主题（签名、from、to）；
+  ;; 352: 事件数据；384: 返回值；512: 铸币者
+  (global $from i32 (i32.const 128))
+  (global $to i32 (i32.const 176))
+  (global $value i32 (i32.const 224))
+  (global $topics i32 (i32.const 256))
+  (global $data i32 (i32.const 352))
+  (global $output i32 (i32.const 384))
+  (global $minter i32 (i32.const 512))
+
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+
+  (func $fill (param $ptr i32) (param $len i32) (param $byte i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (i32.store8 (local.get $ptr) (local.get $byte))
+        (local.set $ptr (i32.add (local.get $ptr) (i32.const 1)))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next))))
+
+  (func $move (param $dst i32) (param $src i32) (param $len i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (i32.store8 (local.get $dst) (i32.load8_u (local.get $src)))
+        (local.set $dst (i32.add (local.get $dst) (i32.const 1)))
+        (local.set $src (i32.add (local.get $src) (i32.const 1)))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next))))
+
+  (func $equal (param $a i32) (param $b i32) (param $len i32) (result i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (if (i32.ne (i32.load8_u (local.get $a)) (i32.load8_u (local.get $b)))
+          (then (return (i32.const 0))))
+        (local.set $a (i32.add (local.get $a) (i32.const 1)))
+        (local.set $b (i32.add (local.get $b) (i32.const 1)))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; 8 字节大端整数
+  (func $load_u64 (param $ptr i32) (result i64)
+    (local $i i32) (local $acc i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (i32.const 8)))
+        (local.set $acc (i64.or (i64.shl (local.get $acc) (i64.const 8))
+          (i64.extend_i32_u (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $acc))
+
+  (func $store_u64 (param $ptr i32) (param $value i64)
+    (local $i i32)
+    (local.set $i (i32.const 8))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (i32.store8 (i32.add (local.get $ptr) (local.get $i)) (i32.wrap_i64 (local.get $value)))
+        (local.set $value (i64.shr_u (local.get $value) (i64.const 8)))
+        (br $next))))
+
+  ;; ABI 编码的 uint256，超出 64 位时回滚
+  (func $word (param $ptr i32) (result i64)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (i32.const 24)))
+        (if (i32.load8_u (i32.add (local.get $ptr) (local.get $i))) (then unreachable))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (call $load_u64 (i32.add (local.get $ptr) (i32.const 24))))
+
+  (func $put_word (param $ptr i32) (param $value i64)
+    (call $fill (local.get $ptr) (i32.const 24) (i32.const 0))
+    (call $store_u64 (i32.add (local.get $ptr) (i32.const 24)) (local.get $value)))
+
+  ;; ABI 编码的地址（32 字节字的后 20 字节）转为存储键
+  (func $address_key (param $word i32) (param $key i32)
+    (local $i i32) (local $byte i32)
+    (i32.store8 (local.get $key) (i32.const 48))
+    (i32.store8 (i32.add (local.get $key) (i32.const 1)) (i32.const 120))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (i32.const 20)))
+        (local.set $byte (i32.load8_u (i32.add (local.get $word) (i32.add (i32.const 12) (local.get $i)))))
+        (i32.store8 (i32.add (local.get $key) (i32.add (i32.const 2) (i32.shl (local.get $i) (i32.const 1))))
+          (i32.load8_u (i32.add (i32.const 48) (i32.shr_u (local.get $byte) (i32.const 4)))))
+        (i32.store8 (i32.add (local.get $key) (i32.add (i32.const 3) (i32.shl (local.get $i) (i32.const 1))))
+          (i32.load8_u (i32.add (i32.const 48) (i32.and (local.get $byte) (i32.const 15)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  (func $nibble (param $char i32) (result i32)
+    (if (i32.ge_u (local.get $char) (i32.const 97)) (then (return (i32.sub (local.get $char) (i32.const 87)))))
+    (if (i32.ge_u (local.get $char) (i32.const 65)) (then (return (i32.sub (local.get $char) (i32.const 55)))))
+    (i32.sub (local.get $char) (i32.const 48)))
+
+  ;; 存储键转为 ABI 编码的地址
+  (func $key_address (param $key i32) (param $word i32)
+    (local $i i32) (local $char i32)
+    (call $fill (local.get $word) (i32.const 12) (i32.const 0))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (i32.const 20)))
+        (local.set $char (i32.add (local.get $key) (i32.add (i32.const 2) (i32.shl (local.get $i) (i32.const 1)))))
+        (i32.store8 (i32.add (local.get $word) (i32.add (i32.const 12) (local.get $i)))
+          (i32.or (i32.shl (call $nibble (i32.load8_u (local.get $char))) (i32.const 4))
+            (call $nibble (i32.load8_u (i32.add (local.get $char) (i32.const 1))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; 调用方地址写入发送方键
+  (func $caller_key
+    (if (i32.ne (call $caller) (i32.const 42)) (then unreachable))
+    (call $copy (global.get $from) (i32.const 0) (i32.const 42)))
+
+  (func $balance (param $key i32) (result i64)
+    (if (i32.ne (call $read (local.get $key) (i32.const 42)) (i32.const 8))
+      (then (return (i64.const 0))))
+    (call $copy (global.get $value) (i32.const 0) (i32.const 8))
+    (call $load_u64 (global.get $value)))
+
+  (func $set_balance (param $key i32) (param $amount i64)
+    (call $store_u64 (global.get $value) (local.get $amount))
+    (call $write (local.get $key) (i32.const 42) (global.get $value) (i32.const 8)))
+
+  (func $credit (param $key i32) (param $amount i64)
+    (local $balance i64)
+    (local.set $balance (i64.add (call $balance (local.get $key)) (local.get $amount)))
+    (if (i64.lt_u (local.get $balance) (local.get $amount)) (then unreachable))
+    (call $set_balance (local.get $key) (local.get $balance)))
+
+  ;; Transfer(from, to, value)，from 的主题须已写好
+  (func $emit_transfer (param $params i32) (param $amount i64)
+    (call $keccak (i32.const 0) (i32.const 33) (global.get $topics))
+    (call $move (i32.add (global.get $topics) (i32.const 64)) (local.get $params) (i32.const 32))
+    (call $put_word (global.get $data) (local.get $amount))
+    (call $emit (i32.const 0) (i32.const 8) (global.get $topics) (i32.const 3) (global.get $data) (i32.const 32)))
+
+  (func $return_word (param $value i64) (result i64)
+    (call $put_word (global.get $output) (local.get $value))
+    (i64.or (i64.shl (i64.extend_i32_u (global.get $output)) (i64.const 32)) (i64.const 32)))
+
+  (func (export "mint") (param $params i32) (param $len i32) (result i64)
+    (local $amount i64)
+    (call $caller_key)
+    (if (i32.eq (call $read (i32.const 64) (i32.const 6)) (i32.const -1))
+      (then (call $write (i32.const 64) (i32.const 6) (global.get $from) (i32.const 42)))
+      (else
+        (call $copy (global.get $minter) (i32.const 0) (i32.const 42))
+        (if (i32.eqz (call $equal (global.get $minter) (global.get $from) (i32.const 42))) (then unreachable))))
+    (local.set $amount (call $word (i32.add (local.get $params) (i32.const 32))))
+    (call $address_key (local.get $params) (global.get $to))
+    (call $credit (global.get $to) (local.get $amount))
+    (call $fill (i32.add (global.get $topics) (i32.const 32)) (i32.const 32) (i32.const 0))
+    (call $emit_transfer (local.get $params) (local.get $amount))
+    (i64.const 0))
+
+  (func (export "transfer") (param $params i32) (param $len i32) (result i64)
+    (local $amount i64) (local $balance i64)
+    (call $caller_key)
+    (local.set $amount (call $word (i32.add (local.get $params) (i32.const 32))))
+    (local.set $balance (call $balance (global.get $from)))
+    (if (i64.lt_u (local.get $balance) (local.get $amount)) (then unreachable))
+    (call $set_balance (global.get $from) (i64.sub (local.get $balance) (local.get $amount)))
+    (call $address_key (local.get $params) (global.get $to))
+    (call $credit (global.get $to) (local.get $amount))
+    (call $key_address (global.get $from) (i32.add (global.get $topics) (i32.const 32)))
+    (call $emit_transfer (local.get $params) (local.get $amount))
+    (call $return_word (i64.const 1)))
+
+  (func (export "balanceOf") (param $params i32) (param $len i32) (result i64)
+    (call $address_key (local.get $params) (global.get $to))
+    (call $return_word (call $balance (global.get $to)))))
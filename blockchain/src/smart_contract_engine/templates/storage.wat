@@ -0,0 +1,58 @@
+;; 键值存储模板
+;;
+;; 参数和返回值按 Solidity ABI 编码（不含选择器）：`set(string,string)` 写入，`get(string)` 读取，
+;; 不存在的键返回空字符串。
+(module
+  (import "env" "storage_read" (func $read (param i32 i32) (result i32)))
+  (import "env" "storage_write" (func $write (param i32 i32 i32 i32)))
+  (import "env" "return_data_copy" (func $copy (param i32 i32 i32)))
+  (memory (export "memory") 1)
+
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+
+  ;; ABI 编码的 32 字节字的低 32 位
+  (func $word (param $ptr i32) (result i32)
+    (local $i i32) (local $acc i32)
+    (local.set $i (i32.const 28))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (i32.const 32)))
+        (local.set $acc (i32.or (i32.shl (local.get $acc) (i32.const 8))
+          (i32.load8_u (i32.add (local.get $ptr) (local.get $i)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $acc))
+
+  (func $put_word (param $ptr i32) (param $value i32)
+    (i32.store8 (i32.add (local.get $ptr) (i32.const 28)) (i32.shr_u (local.get $value) (i32.const 24)))
+    (i32.store8 (i32.add (local.get $ptr) (i32.const 29)) (i32.shr_u (local.get $value) (i32.const 16)))
+    (i32.store8 (i32.add (local.get $ptr) (i32.const 30)) (i32.shr_u (local.get $value) (i32.const 8)))
+    (i32.store8 (i32.add (local.get $ptr) (i32.const 31)) (local.get $value)))
+
+  ;; 第 `index` 个 string 参数的内容地址，长度为其前 32 字节
+  (func $string (param $params i32) (param $index i32) (result i32)
+    (i32.add (i32.add (local.get $params) (call $word (i32.add (local.get $params) (i32.shl (local.get $index) (i32.const 5)))))
+      (i32.const 32)))
+
+  (func $length (param $data i32) (result i32)
+    (call $word (i32.sub (local.get $data) (i32.const 32))))
+
+  (func (export "set") (param $params i32) (param $len i32) (result i64)
+    (local $key i32) (local $value i32)
+    (local.set $key (call $string (local.get $params) (i32.const 0)))
+    (local.set $value (call $string (local.get $params) (i32.const 1)))
+    (call $write (local.get $key) (call $length (local.get $key)) (local.get $value) (call $length (local.get $value)))
+    (i64.const 0))
+
+  (func (export "get") (param $params i32) (param $len i32) (result i64)
+    (local $key i32) (local $output i32) (local $size i32)
+    (local.set $key (call $string (local.get $params) (i32.const 0)))
+    ;; 返回值写在参数之后的空白内存中，补齐部分保持为零
+    (local.set $output (i32.add (local.get $params) (local.get $len)))
+    (local.set $size (call $read (local.get $key) (call $length (local.get $key))))
+    (if (i32.lt_s (local.get $size) (i32.const 0)) (then (local.set $size (i32.const 0))))
+    (call $put_word (local.get $output) (i32.const 32))
+    (call $put_word (i32.add (local.get $output) (i32.const 32)) (local.get $size))
+    (call $copy (i32.add (local.get $output) (i32.const 64)) (i32.const 0) (local.get $size))
+    (i64.or (i64.shl (i64.extend_i32_u (local.get $output)) (i64.const 32))
+      (i64.extend_i32_u (i32.add (i32.const 64) (i32.and (i32.add (local.get $size) (i32.const 31)) (i32.const -32)))))))
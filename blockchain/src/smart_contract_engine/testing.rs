@@ -0,0 +1,615 @@
+//! # 合约测试框架
+//!
+//! [`TestChain`] 在内存中模拟一条链，供普通的 `#[test]` 函数测试合约：
+//! - 预置一组有余额的测试账户，部署和调用都以合约交易执行，与出块时的 nonce、余额、gas 费用和回滚规则一致；
+//!   交易直接交给执行器，不需要签名；
+//! - 可部署 [`SmartContractEngine::get_contract_templates`] 中的模板，或从文件读取的合约；
+//! - 可推进区块高度和时间，用于测试时间锁等依赖区块信息的逻辑；
+//! - [`CallOutcome`] 带有交易回执和本次调用产生的事件，提供成功、回滚和事件断言；
+//! - 可从导出的状态快照分叉，在已有链状态上测试；
+//! - 按合约和方法统计成功调用的 gas，生成 [`GasReport`]。
+//!
+//! ```ignore
+//! let mut chain = TestChain::new();
+//! let owner = chain.account(0).to_string();
+//! let token = chain.deploy_template(&owner, "ERC20").unwrap();
+//! chain.call_typed(&owner, &token, "mint", &[Token::Address(chain.address(0)), Token::Uint(100.into())])
+//!     .unwrap()
+//!     .0
+//!     .assert_success()
+//!     .assert_event("Transfer");
+//! chain.print_gas_report();
+//! ```
+
+use super::{ContractError, ContractEvent, SmartContractEngine};
+use crate::core::{BlockContext, ContractAction, ContractExecutor, ContractReceipt, ContractTransaction, State, MIN_GAS_PRICE};
+use crate::smart_contracts::abi::ContractABI;
+#[cfg(feature = "web3")]
+use crate::smart_contracts::abi::ethereum::{Address, DecodedEvent, Token};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// 测试账户个数
+pub const DEFAULT_ACCOUNTS: usize = 10;
+/// 每个测试账户的初始余额
+pub const DEFAULT_ACCOUNT_BALANCE: u64 = 1_000_000_000;
+/// 每笔交易的 gas 上限
+pub const DEFAULT_GAS_LIMIT: u64 = 10_000_000;
+/// 第一个区块的时间戳；固定取值使测试结果可重复
+pub const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+/// 每个区块推进的秒数
+pub const BLOCK_INTERVAL: u64 = 10;
+
+/// 内存中的测试链
+pub struct TestChain {
+    engine: SmartContractEngine,
+    accounts: Vec<String>,
+    /// 当前待打包的区块
+    block: BlockContext,
+    transactions: u64,
+    gas: BTreeMap<(String, String), GasUsage>,
+}
+
+/// 一次调用的结果
+#[derive(Debug, Clone)]
+pub struct CallOutcome {
+    pub receipt: ContractReceipt,
+    /// 本次调用产生的事件；回滚的调用没有事件
+    pub events: Vec<ContractEvent>,
+}
+
+/// 单个方法的 gas 统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasUsage {
+    pub calls: u64,
+    pub min: u64,
+    pub max: u64,
+    pub total: u64,
+}
+
+/// 按合约名和方法名排列的 gas 报告
+#[derive(Debug, Clone, Default)]
+pub struct GasReport {
+    entries: BTreeMap<(String, String), GasUsage>,
+}
+
+impl TestChain {
+    /// 从空状态开始的测试链
+    pub fn new() -> Self {
+        Self::fork(State::new()).expect("empty state holds no contracts")
+    }
+
+    /// 在给定状态上分叉，例如节点的 `StateStorage` 中的当前状态；状态中的合约全部可调用
+    pub fn fork(mut state: State) -> Result<Self, ContractError> {
+        let accounts: Vec<String> = (0..DEFAULT_ACCOUNTS)
+            .map(|index| super::account_key(&format!("test-account-{}", index)))
+            .collect();
+        for account in &accounts {
+            state.balances.insert(account.clone(), DEFAULT_ACCOUNT_BALANCE);
+        }
+
+        let block = BlockContext { height: state.latest_block_height + 1, timestamp: GENESIS_TIMESTAMP };
+        Ok(Self {
+            engine: SmartContractEngine::from_state(state)?,
+            accounts,
+            block,
+            transactions: 0,
+            gas: BTreeMap::new(),
+        })
+    }
+
+    /// 从 [`Self::snapshot`] 导出的 JSON 快照分叉
+    pub fn from_snapshot(json: &str) -> Result<Self, ContractError> {
+        let state = serde_json::from_str(json)
+            .map_err(|e| ContractError::RuntimeError(format!("Invalid state snapshot: {}", e)))?;
+        Self::fork(state)
+    }
+
+    /// 从快照文件分叉
+    pub fn from_snapshot_file(path: impl AsRef<Path>) -> Result<Self, ContractError> {
+        Self::from_snapshot(&std::fs::read_to_string(path).map_err(io_error)?)
+    }
+
+    /// 以 JSON 导出当前状态
+    pub fn snapshot(&self) -> String {
+        serde_json::to_string(self.engine.state()).expect("state is serializable")
+    }
+
+    /// 把当前状态导出到文件
+    pub fn export_snapshot(&self, path: impl AsRef<Path>) -> Result<(), ContractError> {
+        std::fs::write(path, self.snapshot()).map_err(io_error)
+    }
+
+    pub fn engine(&self) -> &SmartContractEngine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut SmartContractEngine {
+        &mut self.engine
+    }
+
+    pub fn state(&self) -> &State {
+        self.engine.state()
+    }
+
+    /// 测试账户
+    pub fn accounts(&self) -> &[String] {
+        &self.accounts
+    }
+
+    /// 第 `index` 个测试账户
+    pub fn account(&self, index: usize) -> &str {
+        &self.accounts[index]
+    }
+
+    /// 第 `index` 个测试账户的 ABI 地址
+    #[cfg(feature = "web3")]
+    pub fn address(&self, index: usize) -> Address {
+        Address::from(super::account_address(&self.accounts[index]))
+    }
+
+    /// 给账户充值
+    pub fn fund(&mut self, account: &str, amount: u64) {
+        *self.engine.state_mut().balances.entry(super::account_key(account)).or_insert(0) += amount;
+    }
+
+    /// 账户余额
+    pub fn balance(&self, account: &str) -> u64 {
+        self.state().balances.get(&super::account_key(account)).copied().unwrap_or(0)
+    }
+
+    /// 当前待打包区块的高度和时间戳
+    pub fn block(&self) -> BlockContext {
+        self.block
+    }
+
+    /// 出块：区块高度加一，时间推进一个出块间隔
+    pub fn mine(&mut self) {
+        self.advance_blocks(1);
+    }
+
+    /// 推进 `blocks` 个区块
+    pub fn advance_blocks(&mut self, blocks: u64) {
+        self.block.height += blocks;
+        self.block.timestamp += blocks * BLOCK_INTERVAL;
+        self.engine.state_mut().latest_block_height = self.block.height - 1;
+    }
+
+    /// 推进时间，不改变区块高度
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.block.timestamp += seconds;
+    }
+
+    /// 部署 WebAssembly 合约，返回合约地址
+    pub fn deploy(&mut self, from: &str, code: Vec<u8>, abi: ContractABI) -> Result<String, ContractError> {
+        let transaction = ContractTransaction::deploy(from.to_string(), self.nonce(from), code, abi, 0, DEFAULT_GAS_LIMIT);
+        self.deploy_transaction(transaction)
+    }
+
+    /// 部署 EVM 合约，`init_code` 为部署字节码
+    pub fn deploy_evm(&mut self, from: &str, init_code: Vec<u8>, abi: ContractABI) -> Result<String, ContractError> {
+        let transaction = ContractTransaction {
+            sender: from.to_string(),
+            nonce: self.nonce(from),
+            value: 0,
+            gas_limit: DEFAULT_GAS_LIMIT,
+            gas_price: MIN_GAS_PRICE,
+            action: ContractAction::Deploy { code: init_code, interface: abi, evm: true, salt: None },
+            public_key: Vec::new(),
+            signature: Vec::new(),
+        };
+        self.deploy_transaction(transaction)
+    }
+
+    /// 按模板名（如 "ERC20 Token"）或 ABI 名（如 "ERC20"）部署合约模板
+    pub fn deploy_template(&mut self, from: &str, name: &str) -> Result<String, ContractError> {
+        let template = self.engine
+            .get_contract_templates()
+            .into_iter()
+            .find(|template| template.name == name || template.abi.name == name)
+            .ok_or_else(|| ContractError::RuntimeError(format!("Unknown contract template: {}", name)))?;
+        self.deploy(from, template.code, template.abi)
+    }
+
+    /// 部署文件中的合约：`.bin` 和 `.hex` 为十六进制的 EVM 部署字节码，其余（`.wasm`、`.wat`）为 WebAssembly
+    pub fn deploy_file(&mut self, from: &str, path: impl AsRef<Path>, abi: ContractABI) -> Result<String, ContractError> {
+        let path = path.as_ref();
+        let code = std::fs::read(path).map_err(io_error)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("bin" | "hex") => {
+                let text = String::from_utf8(code).map_err(|_| ContractError::InvalidCode)?;
+                let text = text.trim();
+                let init_code = hex::decode(text.strip_prefix("0x").unwrap_or(text)).map_err(|_| ContractError::InvalidCode)?;
+                self.deploy_evm(from, init_code, abi)
+            }
+            _ => self.deploy(from, code, abi),
+        }
+    }
+
+    /// 以交易调用合约方法；合约执行失败时返回回滚的结果，交易本身无效时返回错误
+    pub fn call(&mut self, from: &str, contract: &str, method: &str, params: &[u8]) -> Result<CallOutcome, ContractError> {
+        self.call_with_value(from, contract, method, params, 0)
+    }
+
+    /// 调用合约方法并转账
+    pub fn call_with_value(
+        &mut self,
+        from: &str,
+        contract: &str,
+        method: &str,
+        params: &[u8],
+        value: u64,
+    ) -> Result<CallOutcome, ContractError> {
+        let transaction = ContractTransaction::call(
+            from.to_string(),
+            self.nonce(from),
+            contract.to_string(),
+            method.to_string(),
+            params.to_vec(),
+            value,
+            DEFAULT_GAS_LIMIT,
+        );
+        let outcome = self.send(transaction)?;
+        if outcome.receipt.success {
+            let name = self.engine
+                .get_contract(contract)
+                .map(|contract| contract.interface.name.clone())
+                .unwrap_or_else(|| contract.to_string());
+            self.gas.entry((name, method.to_string())).or_default().record(outcome.receipt.gas_used);
+        }
+        Ok(outcome)
+    }
+
+    /// 按 Solidity ABI 编码参数调用，并解码返回值；回滚时返回值为空
+    #[cfg(feature = "web3")]
+    pub fn call_typed(
+        &mut self,
+        from: &str,
+        contract: &str,
+        method: &str,
+        args: &[Token],
+    ) -> Result<(CallOutcome, Vec<Token>), ContractError> {
+        let definition = self.engine
+            .get_contract(contract)
+            .ok_or(ContractError::ContractNotFound)?
+            .interface
+            .methods
+            .iter()
+            .find(|definition| definition.name == method)
+            .ok_or(ContractError::MethodNotFound)?;
+        let abi_error = |e: crate::smart_contracts::abi::ethereum::AbiError| ContractError::Abi(e.to_string());
+        let call = args.iter().cloned().fold(definition.call().map_err(abi_error)?, |call, arg| call.arg(arg));
+        let params = call.encode_args().map_err(abi_error)?;
+
+        let outcome = self.call(from, contract, method, &params)?;
+        let outputs = match outcome.receipt.success {
+            true => call.decode_output(&outcome.receipt.output).map_err(abi_error)?,
+            false => Vec::new(),
+        };
+        Ok((outcome, outputs))
+    }
+
+    /// 按合约的 ABI 解码事件日志
+    #[cfg(feature = "web3")]
+    pub fn decode_event(&self, event: &ContractEvent) -> Result<DecodedEvent, ContractError> {
+        let contract = self.engine.get_contract(&event.contract_address).ok_or(ContractError::ContractNotFound)?;
+        ContractABI::from(contract.interface.clone())
+            .decode_log(&event.topics, &event.data)
+            .map_err(|e| ContractError::Abi(e.to_string()))
+    }
+
+    /// 成功调用的 gas 统计
+    pub fn gas_report(&self) -> GasReport {
+        GasReport { entries: self.gas.clone() }
+    }
+
+    /// 打印 gas 报告（`cargo test -- --nocapture` 时可见）
+    pub fn print_gas_report(&self) {
+        println!("{}", self.gas_report());
+    }
+
+    fn nonce(&self, account: &str) -> u64 {
+        self.state().nonces.get(&super::account_key(account)).copied().unwrap_or(0)
+    }
+
+    fn deploy_transaction(&mut self, transaction: ContractTransaction) -> Result<String, ContractError> {
+        let outcome = self.send(transaction)?;
+        match outcome.receipt.contract_address {
+            Some(address) if outcome.receipt.success => Ok(address),
+            _ => Err(ContractError::RuntimeError(outcome.receipt.error.unwrap_or_default())),
+        }
+    }
+
+    /// 在当前区块中执行交易，收集本次产生的事件
+    fn send(&mut self, transaction: ContractTransaction) -> Result<CallOutcome, ContractError> {
+        self.transactions += 1;
+        let mut tx_hash = [0u8; 32];
+        tx_hash[24..].copy_from_slice(&self.transactions.to_be_bytes());
+
+        let recorded: HashMap<String, usize> = self.engine.events
+            .iter()
+            .map(|(address, events)| (address.clone(), events.len()))
+            .collect();
+        let mut state = std::mem::replace(self.engine.state_mut(), State::new());
+        let receipt = ContractExecutor::execute(&mut self.engine, &mut state, tx_hash, &transaction, &self.block);
+        *self.engine.state_mut() = state;
+        let receipt = receipt.map_err(|e| ContractError::RuntimeError(e.to_string()))?;
+
+        let events = self.engine.events
+            .iter()
+            .flat_map(|(address, events)| events.iter().skip(recorded.get(address).copied().unwrap_or(0)))
+            .cloned()
+            .collect();
+        Ok(CallOutcome { receipt, events })
+    }
+}
+
+impl Default for TestChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn io_error(error: std::io::Error) -> ContractError {
+    ContractError::RuntimeError(error.to_string())
+}
+
+impl CallOutcome {
+    pub fn is_success(&self) -> bool {
+        self.receipt.success
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.receipt.output
+    }
+
+    pub fn gas_used(&self) -> u64 {
+        self.receipt.gas_used
+    }
+
+    /// 断言调用成功
+    #[track_caller]
+    pub fn assert_success(&self) -> &Self {
+        assert!(
+            self.receipt.success,
+            "expected call to succeed, but it reverted: {}",
+            self.receipt.error.as_deref().unwrap_or_default()
+        );
+        self
+    }
+
+    /// 断言调用回滚
+    #[track_caller]
+    pub fn assert_revert(&self) -> &Self {
+        assert!(!self.receipt.success, "expected call to revert, but it succeeded");
+        self
+    }
+
+    /// 断言调用回滚，且错误信息包含 `reason`
+    #[track_caller]
+    pub fn assert_revert_with(&self, reason: &str) -> &Self {
+        let error = self.assert_revert().receipt.error.as_deref().unwrap_or_default();
+        assert!(error.contains(reason), "expected revert reason containing {:?}, got {:?}", reason, error);
+        self
+    }
+
+    /// 名为 `name` 的事件
+    pub fn events_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a ContractEvent> {
+        self.events.iter().filter(move |event| event.event_name == name)
+    }
+
+    /// 断言产生了名为 `name` 的事件，返回第一个
+    #[track_caller]
+    pub fn assert_event(&self, name: &str) -> &ContractEvent {
+        match self.events.iter().find(|event| event.event_name == name) {
+            Some(event) => event,
+            None => panic!("expected event {:?}, emitted: {:?}", name, self.event_names()),
+        }
+    }
+
+    /// 断言没有产生名为 `name` 的事件
+    #[track_caller]
+    pub fn assert_no_event(&self, name: &str) -> &Self {
+        assert!(self.events_named(name).next().is_none(), "unexpected event {:?}", name);
+        self
+    }
+
+    fn event_names(&self) -> Vec<&str> {
+        self.events.iter().map(|event| event.event_name.as_str()).collect()
+    }
+}
+
+impl GasUsage {
+    fn record(&mut self, gas: u64) {
+        self.min = if self.calls == 0 { gas } else { self.min.min(gas) };
+        self.max = self.max.max(gas);
+        self.total += gas;
+        self.calls += 1;
+    }
+
+    pub fn average(&self) -> u64 {
+        self.total.checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+impl GasReport {
+    /// 合约 `contract`（ABI 名）的方法 `method` 的统计
+    pub fn get(&self, contract: &str, method: &str) -> Option<&GasUsage> {
+        self.entries.get(&(contract.to_string(), method.to_string()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 按合约名、方法名排序的全部统计
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &GasUsage)> {
+        self.entries.iter().map(|((contract, method), usage)| (contract.as_str(), method.as_str(), usage))
+    }
+}
+
+impl fmt::Display for GasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<20} {:<20} {:>8} {:>12} {:>12} {:>12}", "Contract", "Method", "Calls", "Min", "Max", "Avg")?;
+        for (contract, method, usage) in self.entries() {
+            writeln!(
+                f,
+                "{:<20} {:<20} {:>8} {:>12} {:>12} {:>12}",
+                contract, method, usage.calls, usage.min, usage.max, usage.average()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_contracts::abi::{ContractMethod, ContractParameter};
+
+    /// 返回当前区块时间戳和高度（各 8 字节小端）的合约，`fund` 接收转账
+    const CLOCK_WAT: &str = r#"
+        (module
+          (import "env" "timestamp" (func $timestamp (result i64)))
+          (import "env" "block_height" (func $height (result i64)))
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "now") (param i32 i32) (result i64)
+            (i64.store (i32.const 0) (call $timestamp))
+            (i64.store (i32.const 8) (call $height))
+            (i64.const 16))
+          (func (export "fund") (param i32 i32) (result i64) (i64.const 0)))
+    "#;
+
+    fn clock_abi() -> ContractABI {
+        let method = |name: &str, payable: bool| ContractMethod {
+            name: name.to_string(),
+            inputs: vec![],
+            outputs: vec![ContractParameter { name: "value".to_string(), param_type: "bytes".to_string() }],
+            payable,
+            constant: false,
+        };
+        ContractABI {
+            name: "Clock".to_string(),
+            methods: vec![method("now", false), method("fund", true)],
+            events: vec![],
+            storage: vec![],
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("test-chain-{}-{}", std::process::id(), name))
+    }
+
+    fn clock(outcome: &CallOutcome) -> (u64, u64) {
+        let output = outcome.assert_success().output();
+        (u64::from_le_bytes(output[..8].try_into().unwrap()), u64::from_le_bytes(output[8..].try_into().unwrap()))
+    }
+
+    #[test]
+    fn test_accounts_blocks_and_contracts_from_files() {
+        let mut chain = TestChain::new();
+        assert_eq!(chain.accounts().len(), DEFAULT_ACCOUNTS);
+        let alice = chain.account(0).to_string();
+        assert_eq!(chain.balance(&alice), DEFAULT_ACCOUNT_BALANCE);
+
+        let path = temp_path("clock.wat");
+        std::fs::write(&path, CLOCK_WAT).unwrap();
+        let address = chain.deploy_file(&alice, &path, clock_abi()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(clock(&chain.call(&alice, &address, "now", &[]).unwrap()), (GENESIS_TIMESTAMP, 1));
+        chain.advance_blocks(5);
+        chain.advance_time(3);
+        assert_eq!(clock(&chain.call(&alice, &address, "now", &[]).unwrap()), (GENESIS_TIMESTAMP + 5 * BLOCK_INTERVAL + 3, 6));
+        chain.mine();
+        assert_eq!(chain.block().height, 7);
+
+        // 转账和 gas 费用从测试账户扣除，余额不足的交易无效
+        let balance = chain.balance(&alice);
+        let funded = chain.call_with_value(&alice, &address, "fund", &[], 400).unwrap();
+        funded.assert_success();
+        assert_eq!(chain.balance(&alice), balance - 400 - funded.gas_used());
+        assert_eq!(chain.engine().get_contract_state(&address).unwrap().balance, 400);
+        assert!(chain.call_with_value("nobody", &address, "fund", &[], 1).is_err());
+        chain.fund("nobody", 1 + DEFAULT_GAS_LIMIT);
+        chain.call_with_value("nobody", &address, "fund", &[], 1).unwrap().assert_success();
+
+        let missing = chain.call(&alice, &address, "missing", &[]).unwrap();
+        missing.assert_revert_with("Method not found").assert_no_event("Transfer");
+        assert!(chain.deploy_template(&alice, "Unknown").is_err());
+    }
+
+    #[test]
+    fn test_fork_from_exported_snapshot() {
+        let mut chain = TestChain::new();
+        let alice = chain.account(0).to_string();
+        let address = chain.deploy(&alice, CLOCK_WAT.as_bytes().to_vec(), clock_abi()).unwrap();
+        chain.call_with_value(&alice, &address, "fund", &[], 25).unwrap().assert_success();
+        chain.advance_blocks(9);
+
+        let path = temp_path("snapshot.json");
+        chain.export_snapshot(&path).unwrap();
+        let mut fork = TestChain::from_snapshot_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // 分叉继承合约、余额和区块高度，之后的修改互不影响
+        assert_eq!(fork.block().height, 10);
+        assert_eq!(fork.engine().get_contract_state(&address).unwrap().balance, 25);
+        fork.call_with_value(&alice, &address, "fund", &[], 5).unwrap().assert_success();
+        assert_eq!(fork.engine().get_contract_state(&address).unwrap().balance, 30);
+        assert_eq!(chain.engine().get_contract_state(&address).unwrap().balance, 25);
+        assert!(TestChain::from_snapshot("not json").is_err());
+    }
+
+    #[cfg(feature = "web3")]
+    #[test]
+    fn test_templates_events_reverts_and_gas_report() {
+        let mut chain = TestChain::new();
+        let (alice, bob) = (chain.account(0).to_string(), chain.account(1).to_string());
+        let token = chain.deploy_template(&alice, "ERC20 Token").unwrap();
+        let amount = |value: u64| Token::Uint(value.into());
+
+        let (minted, _) = chain.call_typed(&alice, &token, "mint", &[Token::Address(chain.address(0)), amount(100)]).unwrap();
+        let event = chain.decode_event(minted.assert_success().assert_event("Transfer")).unwrap();
+        assert_eq!(event.params[0], ("from".to_string(), Token::Address(Address::zero())));
+        assert_eq!(event.params[2], ("value".to_string(), amount(100)));
+
+        let (sent, outputs) = chain.call_typed(&alice, &token, "transfer", &[Token::Address(chain.address(1)), amount(30)]).unwrap();
+        assert_eq!(outputs, vec![Token::Bool(true)]);
+        let event = chain.decode_event(sent.assert_event("Transfer")).unwrap();
+        assert_eq!(event.params[0], ("from".to_string(), Token::Address(chain.address(0))));
+        assert_eq!(event.params[1], ("to".to_string(), Token::Address(chain.address(1))));
+
+        // 余额不足和非铸币者铸币都回滚，不产生事件
+        let (overdrawn, outputs) = chain.call_typed(&bob, &token, "transfer", &[Token::Address(chain.address(0)), amount(31)]).unwrap();
+        overdrawn.assert_revert_with("unreachable");
+        assert!(overdrawn.events.is_empty() && outputs.is_empty());
+        chain.call_typed(&bob, &token, "mint", &[Token::Address(chain.address(1)), amount(1)]).unwrap().0.assert_revert();
+
+        for (index, expected) in [(0, 70u64), (1, 30)] {
+            let (_, balance) = chain.call_typed(&alice, &token, "balanceOf", &[Token::Address(chain.address(index))]).unwrap();
+            assert_eq!(balance, vec![amount(expected)]);
+        }
+
+        let storage = chain.deploy_template(&bob, "Storage").unwrap();
+        let text = |value: &str| Token::String(value.to_string());
+        chain.call_typed(&bob, &storage, "set", &[text("greeting"), text("hello, world")]).unwrap().0.assert_success();
+        assert_eq!(chain.call_typed(&bob, &storage, "get", &[text("greeting")]).unwrap().1, vec![text("hello, world")]);
+        assert_eq!(chain.call_typed(&bob, &storage, "get", &[text("missing")]).unwrap().1, vec![text("")]);
+
+        // 只统计成功的调用
+        let report = chain.gas_report();
+        let transfer = report.get("ERC20", "transfer").unwrap();
+        assert_eq!(transfer.calls, 1);
+        assert!(transfer.min > 0 && transfer.min == transfer.max && transfer.average() == transfer.total);
+        assert_eq!(report.get("ERC20", "balanceOf").unwrap().calls, 2);
+        assert_eq!(report.get("ERC20", "mint").unwrap().calls, 1);
+        assert_eq!(report.get("Storage", "get").unwrap().calls, 2);
+        let printed = report.to_string();
+        assert!(printed.lines().next().unwrap().starts_with("Contract"));
+        assert_eq!(printed.lines().count(), 1 + report.entries().count());
+        chain.print_gas_report();
+    }
+}
@@ -0,0 +1,684 @@
+//! # 合约升级
+//!
+//! 合约地址不变、代码可替换（代理模式）：升级先提议，经审批并等待时间锁后执行。
+//!
+//! - 审批方式由 [`UpgradePolicy`] 决定：合约所有者单独决定、多签或按投票权的治理投票；
+//!   多签和治理的参与者在策略中登记 secp256k1 公钥，每个批准都要附带该公钥对
+//!   [`approval_digest`] 的签名，提议者同样需要单独批准；
+//! - 提议到可执行之间为取消窗口，任何审批参与者都可以在窗口内取消提议；
+//! - 执行时检查新接口的存储布局与当前版本兼容（已有状态变量只能保留，新变量只能追加在末尾），
+//!   替换代码后若新接口定义了 `migrate` 方法则立即调用它，迁移失败时整个升级撤销；
+//! - 被替换的版本保留在 [`ContractVersion`] 列表中，提议、批准、取消和升级都记入合约的事件历史。
+//!
+//! 升级策略、提议和历史版本随合约元数据保存在引擎状态中。
+
+use super::{
+    code_hash, ContractBackend, ContractError, ContractEvent, ContractInterface, ContractParameter,
+    ExecutionContext, SmartContractEngine,
+};
+use crate::core::transaction::verify_digest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// 不经提议直接升级（[`SmartContractEngine::upgrade_contract`]）时 `migrate` 的 gas 上限
+pub const DEFAULT_MIGRATION_GAS: u64 = 10_000_000;
+
+/// 升级的审批方式
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpgradeApproval {
+    /// 合约所有者单独决定，不需要批准
+    #[default]
+    Owner,
+    /// 多签：`signers` 中至少 `threshold` 人批准
+    Multisig { signers: Vec<String>, threshold: usize },
+    /// 治理投票：批准者的投票权之和达到 `quorum`
+    Governance { voting_power: BTreeMap<String, u64>, quorum: u64 },
+}
+
+/// 升级策略
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradePolicy {
+    pub approval: UpgradeApproval,
+    /// 提议后至少等待的秒数，期间可以取消
+    pub timelock: u64,
+    /// 参与者登记的 secp256k1 公钥，批准的签名用它验证
+    #[serde(default)]
+    pub keys: BTreeMap<String, Vec<u8>>,
+}
+
+/// 升级提议的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpgradeStatus {
+    Pending,
+    Executed,
+    Cancelled,
+}
+
+/// 升级提议
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeProposal {
+    pub id: u64,
+    pub code: Vec<u8>,
+    pub interface: ContractInterface,
+    pub proposer: String,
+    pub proposed_at: u64,
+    /// 最早可执行的时间，此前为取消窗口
+    pub executable_at: u64,
+    pub approvals: Vec<String>,
+    pub status: UpgradeStatus,
+}
+
+/// 被替换的合约版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractVersion {
+    pub version: u32,
+    pub code_hash: String,
+    pub code: Vec<u8>,
+    pub interface: ContractInterface,
+    /// 被替换的时间
+    pub replaced_at: u64,
+}
+
+/// 合约的升级策略、提议和历史版本
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContractUpgrades {
+    pub policy: UpgradePolicy,
+    pub proposals: Vec<UpgradeProposal>,
+    /// 按替换顺序排列的历史版本
+    pub versions: Vec<ContractVersion>,
+}
+
+impl UpgradeApproval {
+    /// `account` 能否参与审批（提议、批准、取消和执行）
+    pub fn is_participant(&self, owner: &str, account: &str) -> bool {
+        match self {
+            Self::Owner => account == owner,
+            Self::Multisig { signers, .. } => signers.iter().any(|signer| signer == account),
+            Self::Governance { voting_power, .. } => voting_power.get(account).is_some_and(|power| *power > 0),
+        }
+    }
+
+    /// `approvals` 是否足以执行升级；所有者审批时只要求执行者是所有者
+    pub fn is_satisfied(&self, approvals: &[String]) -> bool {
+        match self {
+            Self::Owner => true,
+            Self::Multisig { signers, threshold } => {
+                approvals.iter().filter(|account| signers.contains(account)).count() >= *threshold
+            }
+            Self::Governance { voting_power, quorum } => {
+                approvals.iter().filter_map(|account| voting_power.get(account)).sum::<u64>() >= *quorum
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), ContractError> {
+        let valid = match self {
+            Self::Owner => true,
+            Self::Multisig { signers, threshold } => *threshold > 0 && *threshold <= signers.len(),
+            Self::Governance { voting_power, quorum } => *quorum > 0 && *quorum <= voting_power.values().sum::<u64>(),
+        };
+        if valid { Ok(()) } else { Err(ContractError::InvalidParameters) }
+    }
+}
+
+impl UpgradePolicy {
+    /// 审批方式有效，且多签和治理的每个参与者都登记了公钥
+    fn validate(&self) -> Result<(), ContractError> {
+        self.approval.validate()?;
+        let participants: Vec<&String> = match &self.approval {
+            UpgradeApproval::Owner => Vec::new(),
+            UpgradeApproval::Multisig { signers, .. } => signers.iter().collect(),
+            UpgradeApproval::Governance { voting_power, .. } => {
+                voting_power.iter().filter(|(_, power)| **power > 0).map(|(voter, _)| voter).collect()
+            }
+        };
+        if participants.iter().all(|participant| self.keys.get(*participant).is_some_and(|key| !key.is_empty())) {
+            Ok(())
+        } else {
+            Err(ContractError::InvalidParameters)
+        }
+    }
+}
+
+/// 批准签名的消息：`sha256("upgrade-approval" ‖ 合约地址 ‖ 提议编号（大端） ‖ 新代码哈希)`
+///
+/// 签名绑定合约、提议和代码，不能挪用到其他提议上。
+pub fn approval_digest(address: &str, id: u64, code: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"upgrade-approval");
+    hasher.update(address.as_bytes());
+    hasher.update(id.to_be_bytes());
+    hasher.update(code_hash(code).as_bytes());
+    hasher.finalize().into()
+}
+
+/// 检查升级后的存储布局：当前布局中的变量必须按原顺序、原名称和原类型保留，新变量只能追加在末尾
+pub fn check_storage_layout(current: &[ContractParameter], upgraded: &[ContractParameter]) -> Result<(), ContractError> {
+    for (slot, variable) in current.iter().enumerate() {
+        match upgraded.get(slot) {
+            None => {
+                return Err(ContractError::IncompatibleStorageLayout(format!(
+                    "variable `{}` at slot {} was removed", variable.name, slot
+                )));
+            }
+            Some(replacement) if replacement != variable => {
+                return Err(ContractError::IncompatibleStorageLayout(format!(
+                    "slot {} changed from `{} {}` to `{} {}`",
+                    slot, variable.param_type, variable.name, replacement.param_type, replacement.name
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn rejected(reason: &str) -> ContractError {
+    ContractError::UpgradeRejected(reason.to_string())
+}
+
+impl SmartContractEngine {
+    /// 设置升级策略
+    /// Set upgrade policy
+    ///
+    /// 只有所有者可以设置，且只能在审批方式仍为 [`UpgradeApproval::Owner`] 时设置：
+    /// 升级权移交给多签或治理后，所有者不能再单方面收回。
+    pub fn set_upgrade_policy(&mut self, address: &str, policy: UpgradePolicy, caller: &str) -> Result<(), ContractError> {
+        policy.validate()?;
+        let contracts = Arc::make_mut(&mut self.contracts);
+        let contract = Arc::make_mut(contracts.get_mut(address).ok_or(ContractError::ContractNotFound)?);
+        if contract.get_state().owner != caller || contract.upgrades.policy.approval != UpgradeApproval::Owner {
+            return Err(ContractError::RuntimeError("Not authorized to change upgrade policy".to_string()));
+        }
+
+        contract.upgrades.policy = policy;
+        self.persist(address);
+        Ok(())
+    }
+
+    /// 提议升级，返回提议编号；提议者需要另行批准
+    /// Propose an upgrade
+    ///
+    /// 提议时即编译新代码并检查存储布局，`context.timestamp` 加上时间锁为最早可执行时间。
+    pub fn propose_upgrade(
+        &mut self,
+        address: &str,
+        code: Vec<u8>,
+        interface: ContractInterface,
+        context: &ExecutionContext,
+    ) -> Result<u64, ContractError> {
+        let contract = self.contracts.get(address).ok_or(ContractError::ContractNotFound)?;
+        let owner = contract.get_state().owner;
+        if !contract.upgrades.policy.approval.is_participant(&owner, &context.caller) {
+            return Err(ContractError::RuntimeError("Not authorized to upgrade".to_string()));
+        }
+        if code.is_empty() {
+            return Err(ContractError::InvalidCode);
+        }
+        if contract.backend == ContractBackend::Wasm {
+            self.runtime.prepare(&code, &interface)?;
+        }
+        check_storage_layout(&contract.interface.storage, &interface.storage)?;
+
+        let contracts = Arc::make_mut(&mut self.contracts);
+        let contract = Arc::make_mut(contracts.get_mut(address).ok_or(ContractError::ContractNotFound)?);
+        let id = contract.upgrades.proposals.len() as u64 + 1;
+        let executable_at = context.timestamp.saturating_add(contract.upgrades.policy.timelock);
+        contract.upgrades.proposals.push(UpgradeProposal {
+            id,
+            code,
+            interface,
+            proposer: context.caller.clone(),
+            proposed_at: context.timestamp,
+            executable_at,
+            approvals: Vec::new(),
+            status: UpgradeStatus::Pending,
+        });
+
+        self.persist(address);
+        self.record_upgrade_event(address, "UpgradeProposed", context, vec![
+            ("id", serde_json::json!(id)),
+            ("proposer", serde_json::json!(context.caller)),
+            ("executable_at", serde_json::json!(executable_at)),
+        ]);
+        Ok(id)
+    }
+
+    /// 批准升级提议
+    /// Approve an upgrade proposal
+    ///
+    /// `signature` 为批准者登记的公钥对 [`approval_digest`] 的签名，没有登记公钥或签名无效时拒绝。
+    pub fn approve_upgrade(&mut self, address: &str, id: u64, signature: &[u8], context: &ExecutionContext) -> Result<(), ContractError> {
+        let key = self.contracts.get(address)
+            .ok_or(ContractError::ContractNotFound)?
+            .upgrades.policy.keys.get(&context.caller)
+            .cloned()
+            .ok_or_else(|| rejected("approver has no registered key"))?;
+        self.update_proposal(address, id, context, |proposal, _| {
+            if proposal.approvals.contains(&context.caller) {
+                return Err(rejected("already approved"));
+            }
+            let digest = approval_digest(address, proposal.id, &proposal.code);
+            if !verify_digest(&digest, signature, &key).unwrap_or(false) {
+                return Err(rejected("invalid approval signature"));
+            }
+            proposal.approvals.push(context.caller.clone());
+            Ok(())
+        })?;
+        self.record_upgrade_event(address, "UpgradeApproved", context, vec![
+            ("id", serde_json::json!(id)),
+            ("approver", serde_json::json!(context.caller)),
+        ]);
+        Ok(())
+    }
+
+    /// 在取消窗口内取消升级提议
+    /// Cancel an upgrade proposal
+    pub fn cancel_upgrade(&mut self, address: &str, id: u64, context: &ExecutionContext) -> Result<(), ContractError> {
+        self.update_proposal(address, id, context, |proposal, _| {
+            if context.timestamp >= proposal.executable_at {
+                return Err(rejected("cancellation window has closed"));
+            }
+            proposal.status = UpgradeStatus::Cancelled;
+            Ok(())
+        })?;
+        self.record_upgrade_event(address, "UpgradeCancelled", context, vec![
+            ("id", serde_json::json!(id)),
+            ("cancelled_by", serde_json::json!(context.caller)),
+        ]);
+        Ok(())
+    }
+
+    /// 执行已获批准且时间锁已到期的升级
+    /// Execute an approved upgrade
+    ///
+    /// 替换代码和接口，当前版本移入历史版本，版本号加一；新接口定义了 `migrate` 时以
+    /// `context.caller` 调用它，调用失败则撤销整个升级并返回迁移的错误。
+    pub fn execute_upgrade(&mut self, address: &str, id: u64, context: &ExecutionContext) -> Result<(), ContractError> {
+        let mut proposal = None;
+        self.update_proposal(address, id, context, |pending, policy| {
+            if context.timestamp < pending.executable_at {
+                return Err(rejected("timelock has not expired"));
+            }
+            if !policy {
+                return Err(rejected("insufficient approvals"));
+            }
+            proposal = Some(pending.clone());
+            Ok(())
+        })?;
+        let proposal = proposal.expect("proposal checked above");
+
+        let snapshot = self.state.clone();
+        let installed = self.install(address, &proposal, context);
+        let (from, to) = match installed {
+            Ok(versions) => versions,
+            Err(error) => {
+                self.state = snapshot;
+                self.load_state()?;
+                return Err(error);
+            }
+        };
+
+        self.record_upgrade_event(address, "Upgraded", context, vec![
+            ("id", serde_json::json!(id)),
+            ("from_version", serde_json::json!(from)),
+            ("to_version", serde_json::json!(to)),
+            ("code_hash", serde_json::json!(code_hash(&proposal.code))),
+        ]);
+        Ok(())
+    }
+
+    /// 合约的升级提议
+    /// Upgrade proposals of a contract
+    pub fn get_upgrade_proposals(&self, address: &str) -> Vec<UpgradeProposal> {
+        self.contracts.get(address).map(|contract| contract.upgrades.proposals.clone()).unwrap_or_default()
+    }
+
+    /// 合约被替换的历史版本
+    /// Previous versions of a contract
+    pub fn get_contract_versions(&self, address: &str) -> Vec<ContractVersion> {
+        self.contracts.get(address).map(|contract| contract.upgrades.versions.clone()).unwrap_or_default()
+    }
+
+    /// 检查调用方权限和提议状态后修改待执行的提议，`update` 的第二个参数为批准是否已满足
+    fn update_proposal<F>(&mut self, address: &str, id: u64, context: &ExecutionContext, update: F) -> Result<(), ContractError>
+    where
+        F: FnOnce(&mut UpgradeProposal, bool) -> Result<(), ContractError>,
+    {
+        let contracts = Arc::make_mut(&mut self.contracts);
+        let contract = Arc::make_mut(contracts.get_mut(address).ok_or(ContractError::ContractNotFound)?);
+        let owner = contract.get_state().owner;
+        let approval = contract.upgrades.policy.approval.clone();
+        if !approval.is_participant(&owner, &context.caller) {
+            return Err(ContractError::RuntimeError("Not authorized to upgrade".to_string()));
+        }
+        let proposal = contract.upgrades.proposals
+            .iter_mut()
+            .find(|proposal| proposal.id == id)
+            .ok_or_else(|| rejected("unknown proposal"))?;
+        if proposal.status != UpgradeStatus::Pending {
+            return Err(rejected("proposal is not pending"));
+        }
+
+        let satisfied = approval.is_satisfied(&proposal.approvals);
+        update(proposal, satisfied)?;
+        self.persist(address);
+        Ok(())
+    }
+
+    /// 替换代码并运行迁移，返回升级前后的版本号；失败时由调用方恢复状态
+    fn install(&mut self, address: &str, proposal: &UpgradeProposal, context: &ExecutionContext) -> Result<(u32, u32), ContractError> {
+        let current = self.contracts.get(address).ok_or(ContractError::ContractNotFound)?;
+        check_storage_layout(&current.interface.storage, &proposal.interface.storage)?;
+        let module = match current.backend {
+            ContractBackend::Wasm => Some(self.runtime.prepare(&proposal.code, &proposal.interface)?),
+            ContractBackend::Evm => None,
+        };
+
+        let contracts = Arc::make_mut(&mut self.contracts);
+        let contract = Arc::make_mut(contracts.get_mut(address).ok_or(ContractError::ContractNotFound)?);
+        let state = Arc::clone(&contract.state);
+        let mut state = state.lock().unwrap();
+        contract.upgrades.versions.push(ContractVersion {
+            version: state.version,
+            code_hash: state.code_hash.clone(),
+            code: std::mem::replace(&mut contract.code, proposal.code.clone()),
+            interface: std::mem::replace(&mut contract.interface, proposal.interface.clone()),
+            replaced_at: context.timestamp,
+        });
+        if let Some(executed) = contract.upgrades.proposals.iter_mut().find(|executed| executed.id == proposal.id) {
+            executed.status = UpgradeStatus::Executed;
+        }
+        if module.is_some() {
+            contract.module = module;
+        }
+        let from = state.version;
+        state.version += 1;
+        state.code_hash = code_hash(&contract.code);
+        state.last_updated = context.timestamp;
+        let to = state.version;
+        drop(state);
+
+        // EVM 合约的代码保存在账户中
+        let evm_account = self.state.contract_states
+            .get_mut(address)
+            .filter(|_| contract.backend == ContractBackend::Evm);
+        if let Some(account) = evm_account {
+            account.code = proposal.code.clone();
+        }
+        let migrate = contract.interface.methods.iter().any(|method| method.name == "migrate");
+        self.persist(address);
+
+        if migrate {
+            let migration = ExecutionContext {
+                value: 0,
+                gas_used: 0,
+                contract_address: address.to_string(),
+                ..context.clone()
+            };
+            self.call_contract(address, "migrate", &[], migration)?;
+        }
+        Ok((from, to))
+    }
+
+    /// 把升级操作记入合约的事件历史
+    fn record_upgrade_event(&mut self, address: &str, name: &str, context: &ExecutionContext, parameters: Vec<(&str, serde_json::Value)>) {
+        self.events.entry(address.to_string()).or_default().push(ContractEvent {
+            contract_address: address.to_string(),
+            event_name: name.to_string(),
+            parameters: parameters.into_iter()
+                .map(|(name, value)| serde_json::json!({ "name": name, "value": value }))
+                .collect(),
+            topics: Vec::new(),
+            data: Vec::new(),
+            timestamp: context.timestamp,
+            block_height: context.block_height,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cryptography::SignatureEngine;
+    use crate::core::transaction::sign_digest;
+    use crate::smart_contract_engine::ContractMethod;
+
+    const OWNER: &str = "alice";
+
+    /// 计数器合约：`increment` 把计数加上 `step`；给出 `migrate` 函数体时同时导出 `migrate`
+    fn counter_wat(step: i32, migrate: Option<&str>) -> Vec<u8> {
+        let migrate = migrate
+            .map(|body| format!(r#"(func (export "migrate") (param i32 i32) (result i64) {body})"#))
+            .unwrap_or_default();
+        format!(r#"
+            (module
+              (import "env" "storage_read" (func $read (param i32 i32) (result i32)))
+              (import "env" "storage_write" (func $write (param i32 i32 i32 i32)))
+              (import "env" "return_data_copy" (func $copy (param i32 i32 i32)))
+              (memory (export "memory") 1)
+              (data (i32.const 0) "count")
+              (data (i32.const 8) "migrated")
+              (data (i32.const 16) "v2")
+              (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+              (func (export "increment") (param i32 i32) (result i64)
+                (if (i32.eq (call $read (i32.const 0) (i32.const 5)) (i32.const 1))
+                  (then (call $copy (i32.const 64) (i32.const 0) (i32.const 1))))
+                (i32.store8 (i32.const 64) (i32.add (i32.load8_u (i32.const 64)) (i32.const {step})))
+                (call $write (i32.const 0) (i32.const 5) (i32.const 64) (i32.const 1))
+                (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 1)))
+              {migrate})
+        "#).into_bytes()
+    }
+
+    fn variable(name: &str, param_type: &str) -> ContractParameter {
+        ContractParameter { name: name.to_string(), param_type: param_type.to_string() }
+    }
+
+    fn counter_interface(migrate: bool, storage: Vec<ContractParameter>) -> ContractInterface {
+        let method = |name: &str| ContractMethod {
+            name: name.to_string(),
+            inputs: vec![],
+            outputs: vec![],
+            payable: false,
+            constant: false,
+        };
+        let mut methods = vec![method("increment")];
+        if migrate {
+            methods.push(method("migrate"));
+        }
+        ContractInterface { name: "Counter".to_string(), methods, events: vec![], storage }
+    }
+
+    fn context(caller: &str, timestamp: u64) -> ExecutionContext {
+        ExecutionContext {
+            caller: caller.to_string(),
+            value: 0,
+            gas_limit: 1_000_000,
+            gas_used: 0,
+            block_height: 1,
+            timestamp,
+            contract_address: String::new(),
+        }
+    }
+
+    /// 参与者的私钥由名称决定
+    fn private_key(name: &str) -> [u8; 32] {
+        Sha256::digest(name.as_bytes()).into()
+    }
+
+    fn keys(names: &[&str]) -> BTreeMap<String, Vec<u8>> {
+        names.iter()
+            .map(|name| (name.to_string(), SignatureEngine::new().derive_public_key(&private_key(name), "ecdsa").unwrap()))
+            .collect()
+    }
+
+    /// `signer` 对提议的批准签名
+    fn approval(engine: &SmartContractEngine, address: &str, id: u64, signer: &str) -> Vec<u8> {
+        let proposal = engine.get_upgrade_proposals(address).into_iter().find(|proposal| proposal.id == id).unwrap();
+        sign_digest(&approval_digest(address, id, &proposal.code), &private_key(signer)).unwrap().1
+    }
+
+    fn deploy(engine: &mut SmartContractEngine) -> String {
+        let interface = counter_interface(false, vec![variable("count", "uint8")]);
+        engine.deploy_contract(counter_wat(1, None), OWNER.to_string(), interface, 0).unwrap()
+    }
+
+    fn increment(engine: &mut SmartContractEngine, address: &str) -> Vec<u8> {
+        engine.call_contract(address, "increment", &[], context(OWNER, 0)).unwrap().output
+    }
+
+    #[test]
+    fn test_storage_layout_must_be_append_only() {
+        let current = vec![variable("supply", "uint"), variable("owner", "address")];
+        assert!(check_storage_layout(&current, &current).is_ok());
+        assert!(check_storage_layout(&current, &[current.clone(), vec![variable("paused", "bool")]].concat()).is_ok());
+
+        let removed = vec![variable("supply", "uint")];
+        let retyped = vec![variable("supply", "int"), variable("owner", "address")];
+        let reordered = vec![variable("owner", "address"), variable("supply", "uint")];
+        for upgraded in [removed, retyped, reordered] {
+            assert!(matches!(check_storage_layout(&current, &upgraded), Err(ContractError::IncompatibleStorageLayout(_))));
+        }
+
+        // 提议时即拒绝不兼容的布局
+        let mut engine = SmartContractEngine::new();
+        let address = deploy(&mut engine);
+        let interface = counter_interface(false, vec![variable("count", "uint16")]);
+        let result = engine.propose_upgrade(&address, counter_wat(2, None), interface, &context(OWNER, 0));
+        assert!(matches!(result, Err(ContractError::IncompatibleStorageLayout(_))));
+        assert!(engine.get_upgrade_proposals(&address).is_empty());
+    }
+
+    #[test]
+    fn test_timelocked_upgrade_can_be_cancelled_within_window() {
+        let mut engine = SmartContractEngine::new();
+        let address = deploy(&mut engine);
+        let policy = UpgradePolicy { approval: UpgradeApproval::Owner, timelock: 100, keys: BTreeMap::new() };
+        assert!(engine.set_upgrade_policy(&address, policy.clone(), "mallory").is_err());
+        engine.set_upgrade_policy(&address, policy, OWNER).unwrap();
+
+        // 有时间锁时不能直接升级
+        assert!(matches!(engine.upgrade_contract(&address, counter_wat(2, None), OWNER), Err(ContractError::UpgradeRejected(_))));
+
+        let interface = engine.get_contract(&address).unwrap().interface.clone();
+        let id = engine.propose_upgrade(&address, counter_wat(2, None), interface.clone(), &context(OWNER, 1_000)).unwrap();
+        assert!(matches!(engine.execute_upgrade(&address, id, &context(OWNER, 1_050)), Err(ContractError::UpgradeRejected(_))));
+        assert!(engine.cancel_upgrade(&address, id, &context("mallory", 1_050)).is_err());
+        engine.cancel_upgrade(&address, id, &context(OWNER, 1_050)).unwrap();
+        assert_eq!(engine.get_upgrade_proposals(&address)[0].status, UpgradeStatus::Cancelled);
+        assert!(engine.execute_upgrade(&address, id, &context(OWNER, 1_100)).is_err());
+
+        // 时间锁到期后不能再取消
+        let id = engine.propose_upgrade(&address, counter_wat(2, None), interface, &context(OWNER, 2_000)).unwrap();
+        assert!(engine.cancel_upgrade(&address, id, &context(OWNER, 2_100)).is_err());
+        engine.execute_upgrade(&address, id, &context(OWNER, 2_100)).unwrap();
+        assert_eq!(increment(&mut engine, &address), vec![2]);
+        assert_eq!(engine.get_contract(&address).unwrap().get_state().version, 2);
+    }
+
+    #[test]
+    fn test_multisig_and_governance_approval() {
+        let mut engine = SmartContractEngine::new();
+        let address = deploy(&mut engine);
+        let interface = engine.get_contract(&address).unwrap().interface.clone();
+        let signers = vec!["bob".to_string(), "carol".to_string(), "dave".to_string()];
+        let multisig = |threshold, keys| UpgradePolicy {
+            approval: UpgradeApproval::Multisig { signers: signers.clone(), threshold },
+            timelock: 0,
+            keys,
+        };
+        let invalid = multisig(4, keys(&["bob", "carol", "dave"]));
+        assert!(matches!(engine.set_upgrade_policy(&address, invalid, OWNER), Err(ContractError::InvalidParameters)));
+        let unregistered = multisig(2, keys(&["bob", "carol"]));
+        assert!(matches!(engine.set_upgrade_policy(&address, unregistered, OWNER), Err(ContractError::InvalidParameters)));
+        engine.set_upgrade_policy(&address, multisig(2, keys(&["bob", "carol", "dave"])), OWNER).unwrap();
+
+        // 移交给多签后所有者不能再升级或修改策略
+        assert!(engine.set_upgrade_policy(&address, UpgradePolicy::default(), OWNER).is_err());
+        assert!(engine.propose_upgrade(&address, counter_wat(2, None), interface.clone(), &context(OWNER, 0)).is_err());
+
+        // 提议者也要签名批准，批准的身份由登记的公钥验证
+        let id = engine.propose_upgrade(&address, counter_wat(2, None), interface.clone(), &context("bob", 0)).unwrap();
+        engine.approve_upgrade(&address, id, &approval(&engine, &address, id, "bob"), &context("bob", 0)).unwrap();
+        assert!(matches!(engine.execute_upgrade(&address, id, &context("bob", 0)), Err(ContractError::UpgradeRejected(_))));
+        assert!(engine.approve_upgrade(&address, id, &approval(&engine, &address, id, "bob"), &context("bob", 0)).is_err());
+        let forged = approval(&engine, &address, id, "mallory");
+        assert!(matches!(engine.approve_upgrade(&address, id, &forged, &context("carol", 0)), Err(ContractError::UpgradeRejected(_))));
+        assert!(engine.approve_upgrade(&address, id, &[], &context("carol", 0)).is_err());
+        engine.approve_upgrade(&address, id, &approval(&engine, &address, id, "carol"), &context("carol", 0)).unwrap();
+        assert_eq!(engine.get_upgrade_proposals(&address)[0].approvals, vec!["bob".to_string(), "carol".to_string()]);
+        engine.execute_upgrade(&address, id, &context("dave", 0)).unwrap();
+        assert_eq!(increment(&mut engine, &address), vec![2]);
+
+        // 治理投票按投票权计票，签名不能挪用到其他提议
+        let address = deploy(&mut engine);
+        let voting_power = [("bob", 60), ("carol", 30), ("dave", 10)]
+            .into_iter()
+            .map(|(voter, power)| (voter.to_string(), power))
+            .collect();
+        let governance = UpgradePolicy {
+            approval: UpgradeApproval::Governance { voting_power, quorum: 70 },
+            timelock: 0,
+            keys: keys(&["bob", "carol", "dave"]),
+        };
+        engine.set_upgrade_policy(&address, governance, OWNER).unwrap();
+        let first = engine.propose_upgrade(&address, counter_wat(3, None), interface.clone(), &context("carol", 0)).unwrap();
+        let id = engine.propose_upgrade(&address, counter_wat(3, None), interface, &context("carol", 0)).unwrap();
+        let replayed = approval(&engine, &address, first, "bob");
+        assert!(engine.approve_upgrade(&address, id, &replayed, &context("bob", 0)).is_err());
+        for voter in ["carol", "dave"] {
+            engine.approve_upgrade(&address, id, &approval(&engine, &address, id, voter), &context(voter, 0)).unwrap();
+        }
+        assert!(engine.execute_upgrade(&address, id, &context("carol", 0)).is_err());
+        engine.approve_upgrade(&address, id, &approval(&engine, &address, id, "bob"), &context("bob", 0)).unwrap();
+        engine.execute_upgrade(&address, id, &context("carol", 0)).unwrap();
+        assert_eq!(increment(&mut engine, &address), vec![3]);
+    }
+
+    #[test]
+    fn test_upgrade_runs_migration_atomically_and_keeps_history() {
+        let mut engine = SmartContractEngine::new();
+        let address = deploy(&mut engine);
+        assert_eq!(increment(&mut engine, &address), vec![1]);
+        let original = engine.get_contract(&address).unwrap().code.clone();
+        let layout = vec![variable("count", "uint8"), variable("migrated", "bytes")];
+
+        // 迁移失败时升级整体撤销
+        let failing = counter_wat(2, Some("(unreachable)"));
+        let id = engine.propose_upgrade(&address, failing, counter_interface(true, layout.clone()), &context(OWNER, 10)).unwrap();
+        assert!(engine.execute_upgrade(&address, id, &context(OWNER, 10)).is_err());
+        let contract = engine.get_contract(&address).unwrap();
+        assert_eq!((contract.code.clone(), contract.get_state().version), (original.clone(), 1));
+        assert!(engine.get_contract_versions(&address).is_empty());
+        assert_eq!(engine.get_upgrade_proposals(&address)[0].status, UpgradeStatus::Pending);
+        assert_eq!(increment(&mut engine, &address), vec![2]);
+
+        let migrate = "(call $write (i32.const 8) (i32.const 8) (i32.const 16) (i32.const 2)) (i64.const 0)";
+        let upgraded = counter_wat(10, Some(migrate));
+        let id = engine.propose_upgrade(&address, upgraded.clone(), counter_interface(true, layout), &context(OWNER, 20)).unwrap();
+        engine.execute_upgrade(&address, id, &context(OWNER, 20)).unwrap();
+        let contract = engine.get_contract(&address).unwrap();
+        assert_eq!(contract.get_storage("migrated"), Some(b"v2".to_vec()));
+        assert_eq!(contract.get_storage("count"), Some(vec![2]));
+        assert_eq!(increment(&mut engine, &address), vec![12]);
+
+        // 旧版本保留，升级记入事件历史
+        let versions = engine.get_contract_versions(&address);
+        assert_eq!(versions.len(), 1);
+        assert_eq!((versions[0].version, &versions[0].code, versions[0].replaced_at), (1, &original, 20));
+        let upgraded_event = engine.get_contract_events(&address, None)
+            .into_iter()
+            .find(|event| event.event_name == "Upgraded")
+            .unwrap();
+        assert_eq!(upgraded_event.parameters[2], serde_json::json!({ "name": "to_version", "value": 2 }));
+
+        // 升级记录随状态保存
+        let restored = SmartContractEngine::from_state(engine.state().clone()).unwrap();
+        assert_eq!(restored.get_contract(&address).unwrap().code, upgraded);
+        assert_eq!(restored.get_contract_versions(&address).len(), 1);
+        assert_eq!(restored.get_upgrade_proposals(&address).len(), 2);
+    }
+}
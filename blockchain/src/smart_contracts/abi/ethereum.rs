@@ -0,0 +1,410 @@
+//! Solidity ABI 编解码
+//!
+//! 以 [`ContractABI`] 为依据，借助 `ethabi` 实现与以太坊工具链兼容的编码：
+//! - 参数类型字符串按 Solidity 语法解析，如 `uint256`、`address[]`、`(uint256,bytes)`、`string`；
+//! - 调用数据为 4 字节选择器（签名的 Keccak-256 前 4 字节）加参数编码，支持静态、动态类型、元组和数组；
+//! - 事件日志的第一个主题为签名哈希（匿名事件除外），被索引参数依次为其余主题；
+//! - 与 Solidity 编译器输出的 JSON ABI 互相转换。
+
+use super::{ContractABI, ContractEventDefinition, ContractMethod, ContractParameter};
+use ethabi::param_type::Reader;
+use ethabi::{Event, EventParam, Function, Param, RawLog, StateMutability};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub use ethabi::{Address, ParamType, Token, Uint};
+
+/// ABI 错误
+#[derive(Debug, Error)]
+pub enum AbiError {
+    #[error("无效的参数类型 `{0}`")]
+    InvalidType(String),
+    #[error("方法不存在: {0}")]
+    UnknownMethod(String),
+    #[error("没有与日志主题匹配的事件")]
+    UnknownEvent,
+    #[error("参数个数不符: 应为 {expected}，实际为 {actual}")]
+    ArgumentCount { expected: usize, actual: usize },
+    #[error("第 {index} 个参数 `{name}` 的类型应为 {expected}")]
+    ArgumentType { index: usize, name: String, expected: String },
+    #[error("编解码失败: {0}")]
+    Codec(#[from] ethabi::Error),
+}
+
+/// 转换为 ABI 值
+pub trait IntoToken {
+    fn into_token(self) -> Token;
+}
+
+impl IntoToken for Token {
+    fn into_token(self) -> Token {
+        self
+    }
+}
+
+impl IntoToken for bool {
+    fn into_token(self) -> Token {
+        Token::Bool(self)
+    }
+}
+
+impl IntoToken for u64 {
+    fn into_token(self) -> Token {
+        Token::Uint(self.into())
+    }
+}
+
+impl IntoToken for u128 {
+    fn into_token(self) -> Token {
+        Token::Uint(self.into())
+    }
+}
+
+impl IntoToken for Uint {
+    fn into_token(self) -> Token {
+        Token::Uint(self)
+    }
+}
+
+impl IntoToken for Address {
+    fn into_token(self) -> Token {
+        Token::Address(self)
+    }
+}
+
+impl IntoToken for &str {
+    fn into_token(self) -> Token {
+        Token::String(self.to_string())
+    }
+}
+
+impl IntoToken for String {
+    fn into_token(self) -> Token {
+        Token::String(self)
+    }
+}
+
+/// 动态长度字节串 `bytes`
+impl IntoToken for Vec<u8> {
+    fn into_token(self) -> Token {
+        Token::Bytes(self)
+    }
+}
+
+/// 数组 `T[]`
+impl<T: IntoToken + Clone> IntoToken for &[T] {
+    fn into_token(self) -> Token {
+        Token::Array(self.iter().cloned().map(IntoToken::into_token).collect())
+    }
+}
+
+/// 解析参数类型
+pub fn param_type(param_type: &str) -> Result<ParamType, AbiError> {
+    Reader::read(param_type).map_err(|_| AbiError::InvalidType(param_type.to_string()))
+}
+
+fn param(parameter: &ContractParameter) -> Result<Param, AbiError> {
+    Ok(Param { name: parameter.name.clone(), kind: param_type(&parameter.param_type)?, internal_type: None })
+}
+
+fn parameter(name: &str, kind: &ParamType) -> ContractParameter {
+    ContractParameter { name: name.to_string(), param_type: kind.to_string() }
+}
+
+impl ContractMethod {
+    /// 转换为 `ethabi` 的函数定义
+    pub fn to_function(&self) -> Result<Function, AbiError> {
+        let state_mutability = match (self.payable, self.constant) {
+            (true, _) => StateMutability::Payable,
+            (false, true) => StateMutability::View,
+            (false, false) => StateMutability::NonPayable,
+        };
+        #[allow(deprecated)]
+        Ok(Function {
+            name: self.name.clone(),
+            inputs: self.inputs.iter().map(param).collect::<Result<_, _>>()?,
+            outputs: self.outputs.iter().map(param).collect::<Result<_, _>>()?,
+            constant: None,
+            state_mutability,
+        })
+    }
+
+    /// 4 字节函数选择器
+    pub fn selector(&self) -> Result<[u8; 4], AbiError> {
+        Ok(self.to_function()?.short_signature())
+    }
+
+    /// 以给定参数构造调用
+    pub fn call(&self) -> Result<CallBuilder, AbiError> {
+        Ok(CallBuilder { function: self.to_function()?, args: Vec::new() })
+    }
+
+    /// 解码返回数据
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        Ok(self.to_function()?.decode_output(data)?)
+    }
+}
+
+impl ContractEventDefinition {
+    /// 转换为 `ethabi` 的事件定义
+    pub fn to_event(&self) -> Result<Event, AbiError> {
+        let inputs = self.parameters.iter()
+            .enumerate()
+            .map(|(index, parameter)| {
+                Ok(EventParam {
+                    name: parameter.name.clone(),
+                    kind: param_type(&parameter.param_type)?,
+                    indexed: self.indexed.contains(&index),
+                })
+            })
+            .collect::<Result<_, AbiError>>()?;
+        Ok(Event { name: self.name.clone(), inputs, anonymous: self.anonymous })
+    }
+
+    /// 签名哈希，即非匿名事件日志的第一个主题
+    pub fn topic(&self) -> Result<[u8; 32], AbiError> {
+        Ok(self.to_event()?.signature().0)
+    }
+}
+
+/// 解码后的事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedEvent {
+    pub name: String,
+    /// 按定义顺序的参数名与值
+    pub params: Vec<(String, Token)>,
+}
+
+impl ContractABI {
+    /// 转换为 `ethabi` 的合约定义
+    pub fn to_ethabi(&self) -> Result<ethabi::Contract, AbiError> {
+        let mut functions: BTreeMap<String, Vec<Function>> = BTreeMap::new();
+        for method in &self.methods {
+            functions.entry(method.name.clone()).or_default().push(method.to_function()?);
+        }
+        let mut events: BTreeMap<String, Vec<Event>> = BTreeMap::new();
+        for event in &self.events {
+            events.entry(event.name.clone()).or_default().push(event.to_event()?);
+        }
+        Ok(ethabi::Contract { functions, events, ..Default::default() })
+    }
+
+    /// 从 `ethabi` 的合约定义转换；JSON ABI 不含合约名，需另外给出
+    pub fn from_ethabi(name: &str, contract: &ethabi::Contract) -> Self {
+        let parameters = |params: &[Param]| -> Vec<ContractParameter> { params.iter().map(|p| parameter(&p.name, &p.kind)).collect() };
+        Self {
+            name: name.to_string(),
+            methods: contract.functions()
+                .map(|function| ContractMethod {
+                    name: function.name.clone(),
+                    inputs: parameters(&function.inputs),
+                    outputs: parameters(&function.outputs),
+                    payable: function.state_mutability == StateMutability::Payable,
+                    constant: matches!(function.state_mutability, StateMutability::View | StateMutability::Pure),
+                })
+                .collect(),
+            events: contract.events()
+                .map(|event| ContractEventDefinition {
+                    name: event.name.clone(),
+                    parameters: event.inputs.iter().map(|p| parameter(&p.name, &p.kind)).collect(),
+                    indexed: event.inputs.iter().enumerate().filter(|(_, p)| p.indexed).map(|(index, _)| index).collect(),
+                    anonymous: event.anonymous,
+                })
+                .collect(),
+            storage: Vec::new(),
+        }
+    }
+
+    /// 导入 Solidity 编译器输出的 JSON ABI
+    pub fn from_json(name: &str, json: &str) -> Result<Self, AbiError> {
+        let contract = ethabi::Contract::load(json.as_bytes())?;
+        Ok(Self::from_ethabi(name, &contract))
+    }
+
+    /// 导出为 JSON ABI
+    pub fn to_json(&self) -> Result<String, AbiError> {
+        serde_json::to_string(&self.to_ethabi()?).map_err(|err| AbiError::Codec(err.into()))
+    }
+
+    /// 开始构造对 `method` 的调用
+    pub fn call(&self, method: &str) -> Result<CallBuilder, AbiError> {
+        self.method(method).ok_or_else(|| AbiError::UnknownMethod(method.to_string()))?.call()
+    }
+
+    /// 按第一个主题找到事件并解码日志
+    pub fn decode_log(&self, topics: &[[u8; 32]], data: &[u8]) -> Result<DecodedEvent, AbiError> {
+        for definition in &self.events {
+            let event = definition.to_event()?;
+            let matches = if event.anonymous {
+                topics.len() == definition.indexed.len()
+            } else {
+                topics.first() == Some(&event.signature().0)
+            };
+            if !matches {
+                continue;
+            }
+
+            let raw = RawLog { topics: topics.iter().map(|topic| (*topic).into()).collect(), data: data.to_vec() };
+            let log = event.parse_log(raw)?;
+            return Ok(DecodedEvent {
+                name: definition.name.clone(),
+                params: log.params.into_iter().map(|param| (param.name, param.value)).collect(),
+            });
+        }
+        Err(AbiError::UnknownEvent)
+    }
+}
+
+/// 类型化的调用构造器：逐个追加参数，编码时按 ABI 检查个数和类型
+#[derive(Debug, Clone)]
+pub struct CallBuilder {
+    function: Function,
+    args: Vec<Token>,
+}
+
+impl CallBuilder {
+    pub fn arg(mut self, value: impl IntoToken) -> Self {
+        self.args.push(value.into_token());
+        self
+    }
+
+    fn check(&self) -> Result<(), AbiError> {
+        let inputs = &self.function.inputs;
+        if inputs.len() != self.args.len() {
+            return Err(AbiError::ArgumentCount { expected: inputs.len(), actual: self.args.len() });
+        }
+        for (index, (input, arg)) in inputs.iter().zip(&self.args).enumerate() {
+            if !arg.type_check(&input.kind) {
+                return Err(AbiError::ArgumentType { index, name: input.name.clone(), expected: input.kind.to_string() });
+            }
+        }
+        Ok(())
+    }
+
+    /// 只编码参数，不含选择器
+    pub fn encode_args(&self) -> Result<Vec<u8>, AbiError> {
+        self.check()?;
+        Ok(ethabi::encode(&self.args))
+    }
+
+    /// 完整的调用数据：选择器加参数编码
+    pub fn encode(&self) -> Result<Vec<u8>, AbiError> {
+        self.check()?;
+        Ok(self.function.encode_input(&self.args)?)
+    }
+
+    /// 解码本方法的返回数据
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        Ok(self.function.decode_output(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERC20_JSON: &str = r#"[
+        {"type":"function","name":"transfer","stateMutability":"nonpayable",
+         "inputs":[{"name":"to","type":"address"},{"name":"amount","type":"uint256"}],
+         "outputs":[{"name":"","type":"bool"}]},
+        {"type":"function","name":"balanceOf","stateMutability":"view",
+         "inputs":[{"name":"owner","type":"address"}],
+         "outputs":[{"name":"","type":"uint256"}]},
+        {"type":"function","name":"batch","stateMutability":"payable",
+         "inputs":[{"name":"memo","type":"string"},{"name":"amounts","type":"uint256[]"},
+                   {"name":"order","type":"tuple","components":[{"name":"id","type":"uint64"},{"name":"data","type":"bytes"}]}],
+         "outputs":[]},
+        {"type":"event","name":"Transfer","anonymous":false,
+         "inputs":[{"name":"from","type":"address","indexed":true},{"name":"to","type":"address","indexed":true},
+                   {"name":"value","type":"uint256","indexed":false}]}
+    ]"#;
+
+    fn erc20() -> ContractABI {
+        ContractABI::from_json("ERC20", ERC20_JSON).unwrap()
+    }
+
+    #[test]
+    fn test_selectors_and_static_encoding() {
+        let abi = erc20();
+        assert_eq!(abi.method("transfer").unwrap().selector().unwrap(), [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(abi.method("balanceOf").unwrap().selector().unwrap(), [0x70, 0xa0, 0x82, 0x31]);
+
+        let to = Address::from_low_u64_be(0xabcd);
+        let calldata = abi.call("transfer").unwrap().arg(to).arg(1_000u64).encode().unwrap();
+        assert_eq!(calldata.len(), 4 + 64);
+        assert_eq!(&calldata[..4], &[0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(&calldata[4 + 30..4 + 32], &[0xab, 0xcd]);
+        assert_eq!(Uint::from_big_endian(&calldata[36..68]), Uint::from(1_000u64));
+
+        let output = ethabi::encode(&[Token::Bool(true)]);
+        assert_eq!(abi.method("transfer").unwrap().decode_output(&output).unwrap(), vec![Token::Bool(true)]);
+    }
+
+    #[test]
+    fn test_dynamic_types_tuples_and_arrays() {
+        let abi = erc20();
+        let order = Token::Tuple(vec![7u64.into_token(), vec![1u8, 2, 3].into_token()]);
+        let call = abi.call("batch").unwrap().arg("memo").arg(&[1u64, 2, 3][..]).arg(order.clone());
+        let calldata = call.encode().unwrap();
+
+        let function = abi.method("batch").unwrap().to_function().unwrap();
+        assert_eq!(function.signature(), "batch(string,uint256[],(uint64,bytes))");
+        let decoded = function.decode_input(&calldata[4..]).unwrap();
+        assert_eq!(decoded, vec![
+            Token::String("memo".into()),
+            Token::Array(vec![1u64.into_token(), 2u64.into_token(), 3u64.into_token()]),
+            order,
+        ]);
+        assert_eq!(&calldata[4..], call.encode_args().unwrap());
+        assert!(abi.method("batch").unwrap().payable);
+    }
+
+    #[test]
+    fn test_call_builder_rejects_wrong_arguments() {
+        let abi = erc20();
+        let error = abi.call("transfer").unwrap().arg(1u64).encode().unwrap_err();
+        assert!(matches!(error, AbiError::ArgumentCount { expected: 2, actual: 1 }));
+
+        let error = abi.call("transfer").unwrap().arg(true).arg(1u64).encode().unwrap_err();
+        assert!(matches!(error, AbiError::ArgumentType { index: 0, ref name, .. } if name == "to"));
+
+        assert!(matches!(abi.call("mint"), Err(AbiError::UnknownMethod(_))));
+        assert!(matches!(param_type("uint7x"), Err(AbiError::InvalidType(_))));
+    }
+
+    #[test]
+    fn test_decode_transfer_log() {
+        let abi = erc20();
+        let transfer = abi.event("Transfer").unwrap();
+        assert_eq!(transfer.indexed, vec![0, 1]);
+
+        let from = Address::from_low_u64_be(1);
+        let to = Address::from_low_u64_be(2);
+        let address_topic = |address: Address| {
+            let mut topic = [0u8; 32];
+            topic[12..].copy_from_slice(address.as_bytes());
+            topic
+        };
+        let topics = [transfer.topic().unwrap(), address_topic(from), address_topic(to)];
+        let data = ethabi::encode(&[500u64.into_token()]);
+
+        let event = abi.decode_log(&topics, &data).unwrap();
+        assert_eq!(event.name, "Transfer");
+        assert_eq!(event.params, vec![
+            ("from".to_string(), Token::Address(from)),
+            ("to".to_string(), Token::Address(to)),
+            ("value".to_string(), 500u64.into_token()),
+        ]);
+        assert!(matches!(abi.decode_log(&[[0; 32]], &data), Err(AbiError::UnknownEvent)));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let abi = erc20();
+        let json = abi.to_json().unwrap();
+        let imported = ContractABI::from_json("ERC20", &json).unwrap();
+        assert_eq!(imported, abi);
+        assert_eq!(imported.method("batch").unwrap().inputs[2].param_type, "(uint64,bytes)");
+        assert!(imported.method("balanceOf").unwrap().constant);
+    }
+}
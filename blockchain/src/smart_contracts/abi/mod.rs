@@ -0,0 +1,76 @@
+//! 合约 ABI 定义
+//!
+//! 描述合约对外公开的方法和事件，由编译器生成，也用于 WASM 合约引擎的调用检查。
+//! 启用 `web3` 特性后，[`ethereum`] 按 Solidity ABI 规范编解码调用、返回值和事件日志，并导入导出 JSON ABI。
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "web3")]
+pub mod ethereum;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractMethod {
+    pub name: String,
+    pub inputs: Vec<ContractParameter>,
+    pub outputs: Vec<ContractParameter>,
+    pub payable: bool,
+    pub constant: bool,
+}
+
+impl ContractMethod {
+    /// 方法签名，如 `transfer(address,uint)`
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.inputs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractParameter {
+    pub name: String,
+    pub param_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractEventDefinition {
+    pub name: String,
+    pub parameters: Vec<ContractParameter>,
+    /// 作为日志主题的参数在 `parameters` 中的位置
+    #[serde(default)]
+    pub indexed: Vec<usize>,
+    /// 匿名事件不以签名哈希作为第一个主题
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+impl ContractEventDefinition {
+    /// 事件签名，如 `Transfer(address,address,uint)`
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.parameters)
+    }
+}
+
+/// 合约ABI定义
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractABI {
+    pub name: String,
+    pub methods: Vec<ContractMethod>,
+    pub events: Vec<ContractEventDefinition>,
+    /// 状态变量按声明顺序（即存储槽顺序）的布局；升级后的版本只能在末尾追加变量
+    #[serde(default)]
+    pub storage: Vec<ContractParameter>,
+}
+
+impl ContractABI {
+    pub fn method(&self, name: &str) -> Option<&ContractMethod> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+
+    pub fn event(&self, name: &str) -> Option<&ContractEventDefinition> {
+        self.events.iter().find(|event| event.name == name)
+    }
+}
+
+fn signature(name: &str, parameters: &[ContractParameter]) -> String {
+    let types: Vec<&str> = parameters.iter().map(|parameter| parameter.param_type.as_str()).collect();
+    format!("{}({})", name, types.join(","))
+}
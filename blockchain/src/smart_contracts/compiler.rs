@@ -1,21 +0,0 @@
-//! 编译器实现
-
-use super::SmartContractResult;
-// use crate::core::{Result, BlockchainError};
-
-/// 编译器
-#[derive(Debug)]
-pub struct Compiler {
-    // 编译器相关状态
-}
-
-impl Compiler {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    pub async fn compile(&self, source_code: &str) -> SmartContractResult<Vec<u8>> {
-        // 简化的编译过程
-        Ok(source_code.as_bytes().to_vec())
-    }
-}
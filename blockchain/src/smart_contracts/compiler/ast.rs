@@ -0,0 +1,155 @@
+//! 语法树
+
+use super::Span;
+use std::fmt;
+
+/// 值类型与映射类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// 64 位无符号整数
+    Uint,
+    Bool,
+    /// 64 位账户标识
+    Address,
+    /// 只能作为状态变量，键和值都是标量类型
+    Map(Box<Type>, Box<Type>),
+}
+
+impl Type {
+    pub fn is_scalar(&self) -> bool {
+        !matches!(self, Type::Map(..))
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Uint => write!(f, "uint"),
+            Type::Bool => write!(f, "bool"),
+            Type::Address => write!(f, "address"),
+            Type::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Contract {
+    pub name: String,
+    pub span: Span,
+    pub state: Vec<Declaration>,
+    pub events: Vec<Event>,
+    pub functions: Vec<Function>,
+}
+
+/// 状态变量、参数或局部变量的声明
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub name: String,
+    pub params: Vec<Declaration>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// 可由交易调用，出现在 ABI 中
+    Public,
+    /// 只能在合约内部调用
+    Private,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Declaration>,
+    pub returns: Option<Type>,
+    pub visibility: Visibility,
+    /// 只读函数不能写状态、触发事件或调用非只读函数
+    pub view: bool,
+    pub body: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+    /// `uint x = value;`，省略初始值时为零值
+    Let { declaration: Declaration, value: Option<Expression> },
+    /// `target = value;` 或 `target op= value;`
+    Assign { target: Expression, op: Option<BinaryOperator>, value: Expression },
+    If { condition: Expression, then_branch: Vec<Statement>, else_branch: Option<Vec<Statement>> },
+    While { condition: Expression, body: Vec<Statement> },
+    Return(Option<Expression>),
+    Require { condition: Expression, message: Option<String> },
+    Assert(Expression),
+    Emit { event: String, args: Vec<Expression> },
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
+    Number(u64),
+    Bool(bool),
+    Variable(String),
+    /// `map[key]`
+    Index { map: String, key: Box<Expression> },
+    Call { function: String, args: Vec<Expression> },
+    /// `uint(x)` 或 `address(x)`
+    Convert { ty: Type, value: Box<Expression> },
+    Not(Box<Expression>),
+    Binary { op: BinaryOperator, lhs: Box<Expression>, rhs: Box<Expression> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Ne => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Le => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Ge => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        }
+    }
+}
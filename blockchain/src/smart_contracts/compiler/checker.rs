@@ -0,0 +1,297 @@
+//! 名称解析与类型检查
+//!
+//! 检查通过的合约满足代码生成的全部前提：名称都已声明且不重复，表达式类型正确，
+//! 有返回值的函数在所有路径上都会返回，只读函数不修改状态，函数之间没有递归调用。
+
+use super::ast::*;
+use super::{CompileError, Span};
+use std::collections::{HashMap, HashSet};
+
+/// 内置函数 `gasleft()`：剩余 gas
+pub const GASLEFT: &str = "gasleft";
+
+pub fn check(contract: &Contract) -> Result<(), CompileError> {
+    let mut names: HashSet<&str> = HashSet::from([GASLEFT]);
+    for (name, span) in contract.state.iter().map(|d| (&d.name, d.span))
+        .chain(contract.functions.iter().map(|f| (&f.name, f.span)))
+    {
+        if !names.insert(name) {
+            return Err(CompileError::new(span, format!("重复定义 `{}`", name)));
+        }
+    }
+    let mut events = HashSet::new();
+    for event in &contract.events {
+        if !events.insert(&event.name) {
+            return Err(CompileError::new(event.span, format!("重复定义事件 `{}`", event.name)));
+        }
+        check_params(&event.params)?;
+    }
+    for declaration in &contract.state {
+        if matches!(&declaration.ty, Type::Map(key, value) if !key.is_scalar() || !value.is_scalar()) {
+            return Err(CompileError::new(declaration.span, "映射的键和值必须是标量类型"));
+        }
+    }
+
+    let mut calls = HashMap::new();
+    for function in &contract.functions {
+        check_params(&function.params)?;
+        let mut checker = FunctionChecker {
+            contract,
+            function,
+            scopes: vec![function.params.iter().map(|p| (p.name.clone(), p.ty.clone())).collect()],
+            calls: Vec::new(),
+        };
+        checker.block(&function.body)?;
+        if function.returns.is_some() && !always_returns(&function.body) {
+            return Err(CompileError::new(function.span, format!("函数 `{}` 并非所有路径都有返回值", function.name)));
+        }
+        calls.insert(function.name.as_str(), checker.calls);
+    }
+    check_recursion(contract, &calls)
+}
+
+fn check_params(params: &[Declaration]) -> Result<(), CompileError> {
+    let mut seen = HashSet::new();
+    for param in params {
+        if !param.ty.is_scalar() {
+            return Err(CompileError::new(param.span, "参数必须是标量类型"));
+        }
+        if !seen.insert(&param.name) {
+            return Err(CompileError::new(param.span, format!("重复的参数 `{}`", param.name)));
+        }
+    }
+    Ok(())
+}
+
+/// 语句块是否在所有路径上都以 `return` 结束
+fn always_returns(block: &[Statement]) -> bool {
+    block.iter().any(|statement| match &statement.kind {
+        StatementKind::Return(_) => true,
+        StatementKind::If { then_branch, else_branch: Some(else_branch), .. } => {
+            always_returns(then_branch) && always_returns(else_branch)
+        }
+        _ => false,
+    })
+}
+
+/// 调用在代码生成时内联展开，因此函数之间不能有直接或间接的递归
+fn check_recursion(contract: &Contract, calls: &HashMap<&str, Vec<(String, Span)>>) -> Result<(), CompileError> {
+    fn visit<'a>(
+        name: &'a str,
+        calls: &'a HashMap<&str, Vec<(String, Span)>>,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Result<(), CompileError> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        path.push(name);
+        for (callee, span) in &calls[name] {
+            if path.contains(&callee.as_str()) {
+                return Err(CompileError::new(*span, format!("不支持递归调用 `{}`", callee)));
+            }
+            visit(callee, calls, path, done)?;
+        }
+        path.pop();
+        done.insert(name);
+        Ok(())
+    }
+
+    let mut done = HashSet::new();
+    for function in &contract.functions {
+        visit(&function.name, calls, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+struct FunctionChecker<'a> {
+    contract: &'a Contract,
+    function: &'a Function,
+    scopes: Vec<HashMap<String, Type>>,
+    /// 函数体中的调用
+    calls: Vec<(String, Span)>,
+}
+
+impl FunctionChecker<'_> {
+    fn local(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn state(&self, name: &str) -> Option<&Type> {
+        self.contract.state.iter().find(|d| d.name == name).map(|d| &d.ty)
+    }
+
+    fn expect_type(&mut self, expression: &Expression, expected: &Type) -> Result<(), CompileError> {
+        let actual = self.expression(expression)?;
+        if actual != *expected {
+            return Err(CompileError::new(expression.span, format!("类型不匹配: 应为 {}，实际为 {}", expected, actual)));
+        }
+        Ok(())
+    }
+
+    fn modifies_state(&self, span: Span, what: &str) -> Result<(), CompileError> {
+        if self.function.view {
+            return Err(CompileError::new(span, format!("只读函数 `{}` 不能{}", self.function.name, what)));
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &[Statement]) -> Result<(), CompileError> {
+        self.scopes.push(HashMap::new());
+        for statement in block {
+            self.statement(statement)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match &statement.kind {
+            StatementKind::Let { declaration, value } => {
+                if !declaration.ty.is_scalar() {
+                    return Err(CompileError::new(declaration.span, "局部变量必须是标量类型"));
+                }
+                if let Some(value) = value {
+                    self.expect_type(value, &declaration.ty)?;
+                }
+                let name = &declaration.name;
+                if self.local(name).is_some() || self.state(name).is_some() || name == GASLEFT {
+                    return Err(CompileError::new(declaration.span, format!("`{}` 已经声明", name)));
+                }
+                self.scopes.last_mut().expect("function scope").insert(name.clone(), declaration.ty.clone());
+            }
+            StatementKind::Assign { target, op, value } => {
+                let ty = match &target.kind {
+                    ExpressionKind::Variable(name) if self.local(name).is_none() && self.state(name).is_some() => {
+                        self.modifies_state(target.span, "写状态变量")?;
+                        self.expression(target)?
+                    }
+                    ExpressionKind::Variable(_) => self.expression(target)?,
+                    ExpressionKind::Index { .. } => {
+                        self.modifies_state(target.span, "写状态变量")?;
+                        self.expression(target)?
+                    }
+                    _ => return Err(CompileError::new(target.span, "赋值目标必须是变量或映射元素")),
+                };
+                if op.is_some() && ty != Type::Uint {
+                    return Err(CompileError::new(statement.span, format!("复合赋值要求 uint，实际为 {}", ty)));
+                }
+                self.expect_type(value, &ty)?;
+            }
+            StatementKind::If { condition, then_branch, else_branch } => {
+                self.expect_type(condition, &Type::Bool)?;
+                self.block(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.block(else_branch)?;
+                }
+            }
+            StatementKind::While { condition, body } => {
+                self.expect_type(condition, &Type::Bool)?;
+                self.block(body)?;
+            }
+            StatementKind::Return(value) => match (value, &self.function.returns) {
+                (None, None) => {}
+                (Some(value), Some(ty)) => self.expect_type(value, ty)?,
+                (Some(_), None) => return Err(CompileError::new(statement.span, format!("函数 `{}` 没有返回值", self.function.name))),
+                (None, Some(ty)) => return Err(CompileError::new(statement.span, format!("应返回 {}", ty))),
+            },
+            StatementKind::Require { condition, .. } | StatementKind::Assert(condition) => {
+                self.expect_type(condition, &Type::Bool)?;
+            }
+            StatementKind::Emit { event, args } => {
+                self.modifies_state(statement.span, "触发事件")?;
+                let definition = self.contract.events.iter()
+                    .find(|e| e.name == *event)
+                    .ok_or_else(|| CompileError::new(statement.span, format!("未定义的事件 `{}`", event)))?;
+                self.arguments(statement.span, event, &definition.params, args)?;
+            }
+            StatementKind::Expression(expression) => {
+                match &expression.kind {
+                    ExpressionKind::Call { function, args } => {
+                        self.call(expression.span, function, args)?;
+                    }
+                    _ => {
+                        self.expression(expression)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn arguments(&mut self, span: Span, name: &str, params: &[Declaration], args: &[Expression]) -> Result<(), CompileError> {
+        if params.len() != args.len() {
+            return Err(CompileError::new(span, format!("`{}` 需要 {} 个参数，实际为 {}", name, params.len(), args.len())));
+        }
+        for (param, arg) in params.iter().zip(args) {
+            self.expect_type(arg, &param.ty)?;
+        }
+        Ok(())
+    }
+
+    /// 调用的返回类型，无返回值时为 `None`
+    fn call(&mut self, span: Span, name: &str, args: &[Expression]) -> Result<Option<Type>, CompileError> {
+        if name == GASLEFT {
+            self.arguments(span, name, &[], args)?;
+            return Ok(Some(Type::Uint));
+        }
+        let callee = self.contract.functions.iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| CompileError::new(span, format!("未定义的函数 `{}`", name)))?;
+        if self.function.view && !callee.view {
+            return Err(CompileError::new(span, format!("只读函数 `{}` 不能调用非只读函数 `{}`", self.function.name, name)));
+        }
+        self.arguments(span, name, &callee.params, args)?;
+        self.calls.push((name.to_string(), span));
+        Ok(callee.returns.clone())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<Type, CompileError> {
+        let span = expression.span;
+        Ok(match &expression.kind {
+            ExpressionKind::Number(_) => Type::Uint,
+            ExpressionKind::Bool(_) => Type::Bool,
+            ExpressionKind::Variable(name) => match self.local(name).or_else(|| self.state(name)) {
+                Some(Type::Map(..)) => return Err(CompileError::new(span, format!("映射 `{}` 只能按键访问", name))),
+                Some(ty) => ty.clone(),
+                None => return Err(CompileError::new(span, format!("未定义的变量 `{}`", name))),
+            },
+            ExpressionKind::Index { map, key } => {
+                let Some(Type::Map(key_type, value_type)) = self.state(map).cloned() else {
+                    return Err(CompileError::new(span, format!("`{}` 不是映射", map)));
+                };
+                self.expect_type(key, &key_type)?;
+                *value_type
+            }
+            ExpressionKind::Call { function, args } => self.call(span, function, args)?
+                .ok_or_else(|| CompileError::new(span, format!("函数 `{}` 没有返回值", function)))?,
+            ExpressionKind::Convert { ty, value } => {
+                let source = self.expression(value)?;
+                if !matches!(source, Type::Uint | Type::Address) {
+                    return Err(CompileError::new(span, format!("不能把 {} 转换为 {}", source, ty)));
+                }
+                ty.clone()
+            }
+            ExpressionKind::Not(operand) => {
+                self.expect_type(operand, &Type::Bool)?;
+                Type::Bool
+            }
+            ExpressionKind::Binary { op, lhs, rhs } => {
+                let operand = match op {
+                    BinaryOperator::And | BinaryOperator::Or => Type::Bool,
+                    BinaryOperator::Eq | BinaryOperator::Ne => self.expression(lhs)?,
+                    _ => Type::Uint,
+                };
+                if !matches!(op, BinaryOperator::Eq | BinaryOperator::Ne) {
+                    self.expect_type(lhs, &operand)?;
+                }
+                self.expect_type(rhs, &operand)?;
+                match op {
+                    BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul
+                    | BinaryOperator::Div | BinaryOperator::Mod => Type::Uint,
+                    _ => Type::Bool,
+                }
+            }
+        })
+    }
+}
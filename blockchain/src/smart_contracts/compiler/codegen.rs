@@ -0,0 +1,465 @@
+//! 代码生成：类型检查通过的语法树 -> 虚拟机字节码
+//!
+//! 表达式的值总是求到 `r1`，二元运算的左操作数暂存在操作数栈上；
+//! 参数和局部变量各占一个 8 字节的内存槽，每个入口函数从 [`LOCALS`] 开始单调分配。
+//! 内部函数调用在调用处内联展开，每次展开使用新的内存槽，返回语句跳到展开的末尾。
+
+use super::ast::*;
+use super::checker::GASLEFT;
+use super::{selector, CompileError};
+use crate::smart_contracts::abi::ContractABI;
+use crate::smart_contracts::vm::{assemble, BinaryOp, Instruction, Register, MAX_CODE_SIZE};
+use std::collections::HashMap;
+
+/// 选择器和返回值的暂存区
+const SCRATCH: u64 = 0;
+/// `HASH` 的输入区：状态变量序号和映射键
+const HASH_SCRATCH: u64 = 8;
+/// 第一个内存槽
+const LOCALS: u64 = 32;
+
+/// 表达式结果
+const ACC: Register = 1;
+/// 二元运算的右操作数
+const RHS: Register = 2;
+/// 内存地址或存储键
+const ADDR: Register = 3;
+const LEN: Register = 4;
+const AUX: Register = 5;
+
+type Label = usize;
+
+enum ReturnTarget {
+    /// 入口函数：`RETURN` 结束执行
+    Entry,
+    /// 内联展开的函数：写结果槽后跳到展开末尾
+    Inline { end: Label, result: Option<u64> },
+}
+
+/// 赋值目标
+enum Place {
+    Local(u64),
+    State(u64),
+    /// 存储键在操作数栈顶
+    Map,
+}
+
+pub fn generate(contract: &Contract, abi: &ContractABI) -> Result<Vec<u8>, CompileError> {
+    let mut generator = Generator {
+        contract,
+        abi,
+        code: Vec::new(),
+        labels: Vec::new(),
+        next_slot: LOCALS,
+        scopes: Vec::new(),
+        returns: Vec::new(),
+    };
+    generator.contract()?;
+    let code = generator.finish();
+    if code.len() > MAX_CODE_SIZE {
+        return Err(CompileError::new(contract.span, format!("生成的字节码过大: {} 字节", code.len())));
+    }
+    Ok(code)
+}
+
+struct Generator<'a> {
+    contract: &'a Contract,
+    abi: &'a ContractABI,
+    /// 跳转指令的目标暂存标签编号，`finish` 时替换为字节偏移
+    code: Vec<Instruction>,
+    /// 标签 -> 指令序号
+    labels: Vec<Option<usize>>,
+    next_slot: u64,
+    /// 变量名 -> 内存槽
+    scopes: Vec<HashMap<String, u64>>,
+    returns: Vec<ReturnTarget>,
+}
+
+impl Generator<'_> {
+    fn emit(&mut self, instruction: Instruction) {
+        self.code.push(instruction);
+    }
+
+    fn label(&mut self) -> Label {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn bind(&mut self, label: Label) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn load(&mut self, dst: Register, value: u64) {
+        self.emit(Instruction::LoadImm { dst, value });
+    }
+
+    fn alloc(&mut self, slots: u64) -> u64 {
+        let slot = self.next_slot;
+        self.next_slot += 8 * slots;
+        slot
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        // 标签可能指向代码末尾，而跳转目标必须是指令
+        if self.labels.contains(&Some(self.code.len())) {
+            self.emit(Instruction::Halt);
+        }
+        let mut offsets = Vec::with_capacity(self.code.len());
+        let mut offset = 0u32;
+        for instruction in &self.code {
+            offsets.push(offset);
+            offset += instruction.encoded_len() as u32;
+        }
+        let resolve = |label: u32| offsets[self.labels[label as usize].expect("label is bound")];
+        for instruction in &mut self.code {
+            match instruction {
+                Instruction::Jump { target }
+                | Instruction::JumpIfZero { target, .. }
+                | Instruction::JumpIfNotZero { target, .. } => *target = resolve(*target),
+                _ => {}
+            }
+        }
+        assemble(&self.code)
+    }
+
+    /// 选择器分发，然后依次生成各公开函数
+    fn contract(&mut self) -> Result<(), CompileError> {
+        let fallback = self.label();
+        self.emit(Instruction::InputSize { dst: ACC });
+        self.load(RHS, 8);
+        self.emit(Instruction::Binary { op: BinaryOp::Lt, dst: ACC, lhs: ACC, rhs: RHS });
+        self.emit(Instruction::JumpIfNotZero { cond: ACC, target: fallback as u32 });
+        self.load(ADDR, SCRATCH);
+        self.load(LEN, 0);
+        self.load(AUX, 8);
+        self.emit(Instruction::InputCopy { dest: ADDR, offset: LEN, len: AUX });
+        self.emit(Instruction::MLoad { dst: ACC, addr: ADDR });
+
+        let contract = self.contract;
+        let public: Vec<&Function> = contract.functions.iter().filter(|f| f.visibility == Visibility::Public).collect();
+        let mut entries = Vec::new();
+        for function in &public {
+            let entry = self.label();
+            let method = self.abi.method(&function.name).expect("public functions are in the ABI");
+            self.load(RHS, selector(&method.signature()));
+            self.emit(Instruction::Binary { op: BinaryOp::Eq, dst: RHS, lhs: ACC, rhs: RHS });
+            self.emit(Instruction::JumpIfNotZero { cond: RHS, target: entry as u32 });
+            entries.push(entry);
+        }
+        self.bind(fallback);
+        self.load(ADDR, 0);
+        self.emit(Instruction::Revert { offset: ADDR, len: ADDR });
+
+        for (function, entry) in public.into_iter().zip(entries) {
+            self.bind(entry);
+            self.entry(function)?;
+        }
+        Ok(())
+    }
+
+    /// 入口函数：参数依次为选择器之后的 8 字节小端值
+    fn entry(&mut self, function: &Function) -> Result<(), CompileError> {
+        self.next_slot = LOCALS;
+        let mut params = HashMap::new();
+        for (index, param) in function.params.iter().enumerate() {
+            let slot = self.alloc(1);
+            self.load(ADDR, slot);
+            self.load(LEN, 8 + 8 * index as u64);
+            self.load(AUX, 8);
+            self.emit(Instruction::InputCopy { dest: ADDR, offset: LEN, len: AUX });
+            if param.ty == Type::Bool {
+                // 非零即为 true
+                self.emit(Instruction::MLoad { dst: ACC, addr: ADDR });
+                self.emit(Instruction::IsZero { dst: ACC, src: ACC });
+                self.emit(Instruction::IsZero { dst: ACC, src: ACC });
+                self.emit(Instruction::MStore { addr: ADDR, src: ACC });
+            }
+            params.insert(param.name.clone(), slot);
+        }
+        self.scopes = vec![params];
+        self.returns = vec![ReturnTarget::Entry];
+        self.block(&function.body)?;
+        self.emit(Instruction::Halt);
+        Ok(())
+    }
+
+    fn block(&mut self, block: &[Statement]) -> Result<(), CompileError> {
+        self.scopes.push(HashMap::new());
+        for statement in block {
+            self.statement(statement)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn local(&self, name: &str) -> Option<u64> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied()
+    }
+
+    /// 状态变量的序号，标量变量以序号为存储键
+    fn state(&self, name: &str) -> u64 {
+        self.contract.state.iter().position(|d| d.name == name).expect("checked state variable") as u64
+    }
+
+    fn store_local(&mut self, slot: u64) {
+        self.load(ADDR, slot);
+        self.emit(Instruction::MStore { addr: ADDR, src: ACC });
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match &statement.kind {
+            StatementKind::Let { declaration, value } => {
+                match value {
+                    Some(value) => self.expression(value)?,
+                    None => self.load(ACC, 0),
+                }
+                let slot = self.alloc(1);
+                self.store_local(slot);
+                self.scopes.last_mut().expect("block scope").insert(declaration.name.clone(), slot);
+            }
+            StatementKind::Assign { target, op, value } => {
+                let place = match &target.kind {
+                    ExpressionKind::Variable(name) => match self.local(name) {
+                        Some(slot) => Place::Local(slot),
+                        None => Place::State(self.state(name)),
+                    },
+                    ExpressionKind::Index { map, key } => {
+                        let index = self.state(map);
+                        self.map_key(index, key)?;
+                        self.emit(Instruction::Push { src: ACC });
+                        Place::Map
+                    }
+                    _ => unreachable!("checked assignment target"),
+                };
+                if let Some(op) = op {
+                    match place {
+                        Place::Local(slot) => {
+                            self.load(ADDR, slot);
+                            self.emit(Instruction::MLoad { dst: ACC, addr: ADDR });
+                        }
+                        Place::State(key) => {
+                            self.load(ADDR, key);
+                            self.emit(Instruction::SLoad { dst: ACC, key: ADDR });
+                        }
+                        // 存储键在栈顶，这里仍在 ACC 中
+                        Place::Map => self.emit(Instruction::SLoad { dst: ACC, key: ACC }),
+                    }
+                    self.binary_rhs(*op, value)?;
+                } else {
+                    self.expression(value)?;
+                }
+                match place {
+                    Place::Local(slot) => self.store_local(slot),
+                    Place::State(key) => {
+                        self.load(ADDR, key);
+                        self.emit(Instruction::SStore { key: ADDR, value: ACC });
+                    }
+                    Place::Map => {
+                        self.emit(Instruction::Pop { dst: ADDR });
+                        self.emit(Instruction::SStore { key: ADDR, value: ACC });
+                    }
+                }
+            }
+            StatementKind::If { condition, then_branch, else_branch } => {
+                let otherwise = self.label();
+                self.expression(condition)?;
+                self.emit(Instruction::JumpIfZero { cond: ACC, target: otherwise as u32 });
+                self.block(then_branch)?;
+                match else_branch {
+                    Some(else_branch) => {
+                        let end = self.label();
+                        self.emit(Instruction::Jump { target: end as u32 });
+                        self.bind(otherwise);
+                        self.block(else_branch)?;
+                        self.bind(end);
+                    }
+                    None => self.bind(otherwise),
+                }
+            }
+            StatementKind::While { condition, body } => {
+                let start = self.label();
+                let end = self.label();
+                self.bind(start);
+                self.expression(condition)?;
+                self.emit(Instruction::JumpIfZero { cond: ACC, target: end as u32 });
+                self.block(body)?;
+                self.emit(Instruction::Jump { target: start as u32 });
+                self.bind(end);
+            }
+            StatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value)?;
+                }
+                match *self.returns.last().expect("inside a function") {
+                    ReturnTarget::Entry if value.is_some() => {
+                        self.store_local(SCRATCH);
+                        self.load(LEN, 8);
+                        self.emit(Instruction::Return { offset: ADDR, len: LEN });
+                    }
+                    ReturnTarget::Entry => self.emit(Instruction::Halt),
+                    ReturnTarget::Inline { end, result } => {
+                        if let Some(slot) = result {
+                            self.store_local(slot);
+                        }
+                        self.emit(Instruction::Jump { target: end as u32 });
+                    }
+                }
+            }
+            StatementKind::Require { condition, message } => {
+                let ok = self.label();
+                self.expression(condition)?;
+                self.emit(Instruction::JumpIfNotZero { cond: ACC, target: ok as u32 });
+                // 回滚数据为错误信息
+                let message = message.as_deref().unwrap_or_default().as_bytes();
+                for (index, chunk) in message.chunks(8).enumerate() {
+                    let mut word = [0u8; 8];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    self.load(ACC, u64::from_le_bytes(word));
+                    self.store_local(8 * index as u64);
+                }
+                self.load(ADDR, 0);
+                self.load(LEN, message.len() as u64);
+                self.emit(Instruction::Revert { offset: ADDR, len: LEN });
+                self.bind(ok);
+            }
+            StatementKind::Assert(condition) => {
+                // 断言失败时以除以零陷入，消耗全部 gas
+                let ok = self.label();
+                self.expression(condition)?;
+                self.emit(Instruction::JumpIfNotZero { cond: ACC, target: ok as u32 });
+                self.load(RHS, 0);
+                self.emit(Instruction::Binary { op: BinaryOp::Div, dst: ACC, lhs: ACC, rhs: RHS });
+                self.bind(ok);
+            }
+            StatementKind::Emit { event, args } => {
+                let base = self.alloc(args.len() as u64);
+                for (index, arg) in args.iter().enumerate() {
+                    self.expression(arg)?;
+                    self.store_local(base + 8 * index as u64);
+                }
+                let definition = self.abi.event(event).expect("checked event");
+                self.load(ACC, selector(&definition.signature()));
+                self.load(ADDR, base);
+                self.load(LEN, 8 * args.len() as u64);
+                self.emit(Instruction::Log { topic: ACC, offset: ADDR, len: LEN });
+            }
+            StatementKind::Expression(expression) => self.expression(expression)?,
+        }
+        Ok(())
+    }
+
+    /// 映射元素的存储键：`HASH(序号 ‖ 键)`，结果在 ACC
+    fn map_key(&mut self, index: u64, key: &Expression) -> Result<(), CompileError> {
+        self.expression(key)?;
+        self.store_local(HASH_SCRATCH + 8);
+        self.load(ACC, index);
+        self.store_local(HASH_SCRATCH);
+        self.load(LEN, 16);
+        self.emit(Instruction::Hash { dst: ACC, offset: ADDR, len: LEN });
+        Ok(())
+    }
+
+    /// 以 ACC 为左操作数，求右操作数并运算
+    fn binary_rhs(&mut self, op: BinaryOperator, rhs: &Expression) -> Result<(), CompileError> {
+        self.emit(Instruction::Push { src: ACC });
+        self.expression(rhs)?;
+        self.emit(Instruction::Mov { dst: RHS, src: ACC });
+        self.emit(Instruction::Pop { dst: ACC });
+
+        let (op, negate) = match op {
+            BinaryOperator::Add => (BinaryOp::Add, false),
+            BinaryOperator::Sub => (BinaryOp::Sub, false),
+            BinaryOperator::Mul => (BinaryOp::Mul, false),
+            BinaryOperator::Div => (BinaryOp::Div, false),
+            BinaryOperator::Mod => (BinaryOp::Mod, false),
+            BinaryOperator::Eq => (BinaryOp::Eq, false),
+            BinaryOperator::Ne => (BinaryOp::Eq, true),
+            BinaryOperator::Lt => (BinaryOp::Lt, false),
+            BinaryOperator::Le => (BinaryOp::Gt, true),
+            BinaryOperator::Gt => (BinaryOp::Gt, false),
+            BinaryOperator::Ge => (BinaryOp::Lt, true),
+            BinaryOperator::And | BinaryOperator::Or => unreachable!("logical operators short-circuit"),
+        };
+        self.emit(Instruction::Binary { op, dst: ACC, lhs: ACC, rhs: RHS });
+        if negate {
+            self.emit(Instruction::IsZero { dst: ACC, src: ACC });
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<(), CompileError> {
+        match &expression.kind {
+            ExpressionKind::Number(value) => self.load(ACC, *value),
+            ExpressionKind::Bool(value) => self.load(ACC, *value as u64),
+            ExpressionKind::Variable(name) => match self.local(name) {
+                Some(slot) => {
+                    self.load(ADDR, slot);
+                    self.emit(Instruction::MLoad { dst: ACC, addr: ADDR });
+                }
+                None => {
+                    let key = self.state(name);
+                    self.load(ADDR, key);
+                    self.emit(Instruction::SLoad { dst: ACC, key: ADDR });
+                }
+            },
+            ExpressionKind::Index { map, key } => {
+                let index = self.state(map);
+                self.map_key(index, key)?;
+                self.emit(Instruction::SLoad { dst: ACC, key: ACC });
+            }
+            ExpressionKind::Call { function, .. } if function == GASLEFT => self.emit(Instruction::Gas { dst: ACC }),
+            ExpressionKind::Call { function, args } => self.inline(function, args)?,
+            // 地址与整数的表示相同
+            ExpressionKind::Convert { value, .. } => self.expression(value)?,
+            ExpressionKind::Not(operand) => {
+                self.expression(operand)?;
+                self.emit(Instruction::IsZero { dst: ACC, src: ACC });
+            }
+            ExpressionKind::Binary { op: op @ (BinaryOperator::And | BinaryOperator::Or), lhs, rhs } => {
+                let end = self.label();
+                self.expression(lhs)?;
+                let target = end as u32;
+                self.emit(match op {
+                    BinaryOperator::And => Instruction::JumpIfZero { cond: ACC, target },
+                    _ => Instruction::JumpIfNotZero { cond: ACC, target },
+                });
+                self.expression(rhs)?;
+                self.bind(end);
+            }
+            ExpressionKind::Binary { op, lhs, rhs } => {
+                self.expression(lhs)?;
+                self.binary_rhs(*op, rhs)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 内联展开函数调用，返回值在 ACC
+    fn inline(&mut self, name: &str, args: &[Expression]) -> Result<(), CompileError> {
+        let contract = self.contract;
+        let callee = contract.functions.iter().find(|f| f.name == name).expect("checked function");
+        let mut params = HashMap::new();
+        for (param, arg) in callee.params.iter().zip(args) {
+            self.expression(arg)?;
+            let slot = self.alloc(1);
+            self.store_local(slot);
+            params.insert(param.name.clone(), slot);
+        }
+        let result = callee.returns.as_ref().map(|_| self.alloc(1));
+        let end = self.label();
+
+        // 被调函数看不到调用方的局部变量
+        let caller_scopes = std::mem::replace(&mut self.scopes, vec![params]);
+        self.returns.push(ReturnTarget::Inline { end, result });
+        self.block(&callee.body)?;
+        self.returns.pop();
+        self.scopes = caller_scopes;
+
+        self.bind(end);
+        if let Some(slot) = result {
+            self.load(ADDR, slot);
+            self.emit(Instruction::MLoad { dst: ACC, addr: ADDR });
+        }
+        Ok(())
+    }
+}
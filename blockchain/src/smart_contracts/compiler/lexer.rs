@@ -0,0 +1,337 @@
+//! 词法分析
+
+use super::{CompileError, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Identifier(String),
+    Number(u64),
+    Str(String),
+    // 关键字
+    Contract,
+    Function,
+    Event,
+    Emit,
+    Public,
+    Private,
+    View,
+    Returns,
+    Return,
+    If,
+    Else,
+    While,
+    Require,
+    Assert,
+    True,
+    False,
+    Map,
+    Uint,
+    Bool,
+    Address,
+    // 符号
+    LeftBrace,
+    RightBrace,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
+    Comma,
+    Assign,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Bang,
+    EqualEqual,
+    BangEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    AndAnd,
+    OrOr,
+    Eof,
+}
+
+impl Token {
+    fn keyword(word: &str) -> Option<Token> {
+        Some(match word {
+            "contract" => Token::Contract,
+            "function" => Token::Function,
+            "event" => Token::Event,
+            "emit" => Token::Emit,
+            "public" => Token::Public,
+            "private" => Token::Private,
+            "view" => Token::View,
+            "returns" => Token::Returns,
+            "return" => Token::Return,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "require" => Token::Require,
+            "assert" => Token::Assert,
+            "true" => Token::True,
+            "false" => Token::False,
+            "map" => Token::Map,
+            "uint" => Token::Uint,
+            "bool" => Token::Bool,
+            "address" => Token::Address,
+            _ => return None,
+        })
+    }
+
+    /// 用于错误信息的描述
+    pub fn describe(&self) -> String {
+        let symbol = match self {
+            Token::Identifier(name) => return format!("标识符 `{}`", name),
+            Token::Number(value) => return format!("数字 `{}`", value),
+            Token::Str(_) => return "字符串".to_string(),
+            Token::Eof => return "文件结尾".to_string(),
+            Token::Contract => "contract",
+            Token::Function => "function",
+            Token::Event => "event",
+            Token::Emit => "emit",
+            Token::Public => "public",
+            Token::Private => "private",
+            Token::View => "view",
+            Token::Returns => "returns",
+            Token::Return => "return",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::Require => "require",
+            Token::Assert => "assert",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Map => "map",
+            Token::Uint => "uint",
+            Token::Bool => "bool",
+            Token::Address => "address",
+            Token::LeftBrace => "{",
+            Token::RightBrace => "}",
+            Token::LeftParen => "(",
+            Token::RightParen => ")",
+            Token::LeftBracket => "[",
+            Token::RightBracket => "]",
+            Token::Semicolon => ";",
+            Token::Comma => ",",
+            Token::Assign => "=",
+            Token::PlusAssign => "+=",
+            Token::MinusAssign => "-=",
+            Token::StarAssign => "*=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Bang => "!",
+            Token::EqualEqual => "==",
+            Token::BangEqual => "!=",
+            Token::Less => "<",
+            Token::LessEqual => "<=",
+            Token::Greater => ">",
+            Token::GreaterEqual => ">=",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+        };
+        format!("`{}`", symbol)
+    }
+}
+
+/// 把源代码切分为带位置的词法单元，最后一个总是 [`Token::Eof`]
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, CompileError> {
+    Lexer { chars: source.chars().collect(), pos: 0, line: 1, column: 1 }.run()
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.chars.get(self.pos + 1).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn span(&self) -> Span {
+        Span { line: self.line, column: self.column }
+    }
+
+    fn run(mut self) -> Result<Vec<(Token, Span)>, CompileError> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            let span = self.span();
+            let Some(c) = self.bump() else {
+                tokens.push((Token::Eof, span));
+                return Ok(tokens);
+            };
+            let token = match c {
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let mut word = c.to_string();
+                    while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                        word.push(c);
+                        self.bump();
+                    }
+                    Token::keyword(&word).unwrap_or(Token::Identifier(word))
+                }
+                c if c.is_ascii_digit() => self.number(c, span)?,
+                '"' => self.string(span)?,
+                '{' => Token::LeftBrace,
+                '}' => Token::RightBrace,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
+                ';' => Token::Semicolon,
+                ',' => Token::Comma,
+                '/' => Token::Slash,
+                '%' => Token::Percent,
+                '+' => self.either('=', Token::PlusAssign, Token::Plus),
+                '-' => self.either('=', Token::MinusAssign, Token::Minus),
+                '*' => self.either('=', Token::StarAssign, Token::Star),
+                '=' => self.either('=', Token::EqualEqual, Token::Assign),
+                '!' => self.either('=', Token::BangEqual, Token::Bang),
+                '<' => self.either('=', Token::LessEqual, Token::Less),
+                '>' => self.either('=', Token::GreaterEqual, Token::Greater),
+                '&' if self.peek() == Some('&') => {
+                    self.bump();
+                    Token::AndAnd
+                }
+                '|' if self.peek() == Some('|') => {
+                    self.bump();
+                    Token::OrOr
+                }
+                other => return Err(CompileError::new(span, format!("无法识别的字符 `{}`", other))),
+            };
+            tokens.push((token, span));
+        }
+    }
+
+    /// 下一个字符为 `next` 时消耗它并返回 `matched`
+    fn either(&mut self, next: char, matched: Token, otherwise: Token) -> Token {
+        if self.peek() == Some(next) {
+            self.bump();
+            matched
+        } else {
+            otherwise
+        }
+    }
+
+    fn skip_trivia(&mut self) -> Result<(), CompileError> {
+        loop {
+            match (self.peek(), self.peek_next()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('/'), Some('/')) => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let start = self.span();
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match (self.peek(), self.peek_next()) {
+                            (Some('*'), Some('/')) => break,
+                            (None, _) => return Err(CompileError::new(start, "注释没有结束")),
+                            _ => {
+                                self.bump();
+                            }
+                        }
+                    }
+                    self.bump();
+                    self.bump();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// 十进制或 `0x` 开头的十六进制整数
+    fn number(&mut self, first: char, span: Span) -> Result<Token, CompileError> {
+        let radix = if first == '0' && matches!(self.peek(), Some('x' | 'X')) {
+            self.bump();
+            16
+        } else {
+            10
+        };
+        let mut digits = if radix == 10 { first.to_string() } else { String::new() };
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            if c != '_' {
+                digits.push(c);
+            }
+            self.bump();
+        }
+        u64::from_str_radix(&digits, radix)
+            .map(Token::Number)
+            .map_err(|_| CompileError::new(span, format!("无效的整数字面量 `{}`", digits)))
+    }
+
+    fn string(&mut self, span: Span) -> Result<Token, CompileError> {
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(Token::Str(value)),
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    _ => return Err(CompileError::new(span, "无效的转义字符")),
+                },
+                Some('\n') | None => return Err(CompileError::new(span, "字符串没有结束")),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_with_spans() {
+        let tokens = tokenize("uint x = 0x1f; // 注释\n  x += 10_000 /* 多行\n */ >= y").unwrap();
+        let kinds: Vec<&Token> = tokens.iter().map(|(token, _)| token).collect();
+        assert_eq!(kinds, vec![
+            &Token::Uint, &Token::Identifier("x".into()), &Token::Assign, &Token::Number(31), &Token::Semicolon,
+            &Token::Identifier("x".into()), &Token::PlusAssign, &Token::Number(10_000), &Token::GreaterEqual,
+            &Token::Identifier("y".into()), &Token::Eof,
+        ]);
+        assert_eq!(tokens[5].1, Span { line: 2, column: 3 });
+        assert_eq!(tokens[8].1, Span { line: 3, column: 5 });
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        assert_eq!(tokenize("a\n  #").unwrap_err(), CompileError::new(Span { line: 2, column: 3 }, "无法识别的字符 `#`"));
+        assert!(tokenize("99999999999999999999").is_err());
+        assert!(tokenize("\"abc").is_err());
+        assert!(tokenize("/* abc").is_err());
+    }
+}
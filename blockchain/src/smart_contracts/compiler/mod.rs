@@ -0,0 +1,351 @@
+//! 编译器实现
+//!
+//! 把一种小型静态类型合约语言编译为 [`VirtualMachine`](super::VirtualMachine) 的字节码，并生成 [`ContractABI`]。
+//!
+//! ```text
+//! contract Token {
+//!     address owner;
+//!     map<address, uint> balances;
+//!
+//!     event Transfer(address from, address to, uint amount);
+//!
+//!     function transfer(address from, address to, uint amount) public returns (bool) {
+//!         require(balances[from] >= amount, "余额不足");
+//!         balances[from] -= amount;
+//!         balances[to] += amount;
+//!         emit Transfer(from, to, amount);
+//!         return true;
+//!     }
+//! }
+//! ```
+//!
+//! ## 语言
+//!
+//! - 类型：`uint`（64 位无符号整数）、`bool`、`address`（64 位账户标识），
+//!   以及只能作为状态变量的 `map<K, V>`；`uint(x)` 与 `address(x)` 互相转换；
+//! - 函数必须声明 `public`（出现在 ABI 中，可由交易调用）或 `private`，`view` 函数不能修改状态；
+//! - 语句：局部变量声明、赋值与 `+= -= *=`、`if`/`else`、`while`、`return`、
+//!   `require(条件, "信息")`、`assert(条件)`、`emit 事件(参数)`；
+//! - 算术溢出和除以零使执行出错；`require` 失败时回滚，回滚数据为信息文本；`assert` 失败时陷入并耗尽 gas；
+//! - 内部调用在调用处内联展开，因此不支持递归；内置函数 `gasleft()` 返回剩余 gas。
+//!
+//! ## 调用约定
+//!
+//! - 输入为 8 字节选择器加每个参数 8 字节（均为小端），选择器为方法签名（如 `transfer(address,address,uint)`）
+//!   的 SHA-256 的前 8 字节，见 [`selector`]；没有匹配的方法时以空数据回滚；
+//! - 有返回值时输出为 8 字节小端值，`bool` 为 0 或 1；
+//! - 事件记录为日志，主题为事件签名的选择器，数据为各参数的 8 字节小端值；
+//! - 第 i 个状态变量以 i 为存储键，映射元素的存储键为 `HASH(i ‖ 键)`。
+
+pub mod ast;
+mod checker;
+mod codegen;
+mod lexer;
+mod parser;
+
+pub use parser::parse;
+
+use super::abi::{ContractABI, ContractEventDefinition, ContractMethod, ContractParameter};
+use super::{SmartContractError, SmartContractResult};
+use ast::{Contract, Declaration, Visibility};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use thiserror::Error;
+
+/// 源代码位置，行列均从 1 开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// 编译错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{span}: {message}")]
+pub struct CompileError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl CompileError {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into() }
+    }
+}
+
+impl From<CompileError> for SmartContractError {
+    fn from(err: CompileError) -> Self {
+        SmartContractError::CompilationError(err.to_string())
+    }
+}
+
+/// 方法或事件签名的选择器：SHA-256 的前 8 字节（小端）
+pub fn selector(signature: &str) -> u64 {
+    let digest = Sha256::digest(signature.as_bytes());
+    u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"))
+}
+
+/// 编译产物
+#[derive(Debug, Clone)]
+pub struct CompiledContract {
+    pub name: String,
+    pub bytecode: Vec<u8>,
+    pub abi: ContractABI,
+}
+
+impl CompiledContract {
+    /// 按调用约定编码方法调用；方法不存在或参数个数不符时返回 `None`
+    pub fn encode_call(&self, method: &str, args: &[u64]) -> Option<Vec<u8>> {
+        let method = self.abi.method(method).filter(|m| m.inputs.len() == args.len())?;
+        let mut input = selector(&method.signature()).to_le_bytes().to_vec();
+        for arg in args {
+            input.extend_from_slice(&arg.to_le_bytes());
+        }
+        Some(input)
+    }
+}
+
+/// 编译器
+#[derive(Debug)]
+pub struct Compiler {
+    // 编译器相关状态
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// 编译源代码，返回字节码
+    pub async fn compile(&self, source_code: &str) -> SmartContractResult<Vec<u8>> {
+        let compiled = self.compile_contract(source_code).map_err(SmartContractError::from)?;
+        Ok(compiled.bytecode)
+    }
+
+    /// 编译源代码，返回字节码和 ABI
+    pub fn compile_contract(&self, source_code: &str) -> Result<CompiledContract, CompileError> {
+        let contract = parse(source_code)?;
+        checker::check(&contract)?;
+        let abi = generate_abi(&contract);
+        let bytecode = codegen::generate(&contract, &abi)?;
+        Ok(CompiledContract { name: contract.name, bytecode, abi })
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parameters(declarations: &[Declaration]) -> Vec<ContractParameter> {
+    declarations.iter()
+        .map(|d| ContractParameter { name: d.name.clone(), param_type: d.ty.to_string() })
+        .collect()
+}
+
+fn generate_abi(contract: &Contract) -> ContractABI {
+    ContractABI {
+        name: contract.name.clone(),
+        methods: contract.functions.iter()
+            .filter(|f| f.visibility == Visibility::Public)
+            .map(|f| ContractMethod {
+                name: f.name.clone(),
+                inputs: parameters(&f.params),
+                outputs: f.returns.iter()
+                    .map(|ty| ContractParameter { name: String::new(), param_type: ty.to_string() })
+                    .collect(),
+                payable: false,
+                constant: f.view,
+            })
+            .collect(),
+        events: contract.events.iter()
+            .map(|e| ContractEventDefinition {
+                name: e.name.clone(),
+                parameters: parameters(&e.params),
+                indexed: Vec::new(),
+                anonymous: false,
+            })
+            .collect(),
+        storage: parameters(&contract.state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_contracts::vm::{Log, VirtualMachine, VmError};
+
+    const TOKEN: &str = r#"
+        contract Token {
+            uint supply;
+            map<address, uint> balances;
+
+            event Transfer(address from, address to, uint amount);
+
+            function mint(address to, uint amount) public {
+                require(amount > 0, "amount is zero");
+                balances[to] += amount;
+                supply = supply + amount;
+                emit Transfer(address(0), to, amount);
+            }
+
+            function transfer(address from, address to, uint amount) public returns (bool) {
+                if (!hasBalance(from, amount)) {
+                    return false;
+                }
+                balances[from] -= amount;
+                balances[to] += amount;
+                emit Transfer(from, to, amount);
+                return true;
+            }
+
+            function balanceOf(address owner) public view returns (uint) {
+                return balances[owner];
+            }
+
+            function totalSupply() public view returns (uint) {
+                return supply;
+            }
+
+            function hasBalance(address owner, uint amount) private view returns (bool) {
+                return balances[owner] >= amount;
+            }
+        }
+    "#;
+
+    fn call(vm: &mut VirtualMachine, address: &str, contract: &CompiledContract, method: &str, args: &[u64]) -> Result<(bool, Vec<u8>, Vec<Log>), VmError> {
+        let input = contract.encode_call(method, args).expect("known method");
+        let execution = vm.call(address, &input, 1_000_000)?;
+        Ok((execution.success, execution.output, execution.logs))
+    }
+
+    fn value(output: &[u8]) -> u64 {
+        u64::from_le_bytes(output.try_into().expect("8-byte output"))
+    }
+
+    #[tokio::test]
+    async fn test_compile_and_run_token() {
+        let token = Compiler::new().compile_contract(TOKEN).unwrap();
+        let mut vm = VirtualMachine::new();
+        let address = vm.deploy(&token.bytecode).await.unwrap();
+
+        let (success, _, logs) = call(&mut vm, &address, &token, "mint", &[7, 100]).unwrap();
+        assert!(success);
+        let topic = selector("Transfer(address,address,uint)");
+        let data: Vec<u8> = [0u64, 7, 100].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(logs, vec![Log { address: address.clone(), topic, data }]);
+
+        let (_, output, _) = call(&mut vm, &address, &token, "transfer", &[7, 9, 30]).unwrap();
+        assert_eq!(value(&output), 1);
+        let (_, output, logs) = call(&mut vm, &address, &token, "transfer", &[7, 9, 71]).unwrap();
+        assert_eq!((value(&output), logs.len()), (0, 0));
+
+        assert_eq!(value(&call(&mut vm, &address, &token, "balanceOf", &[7]).unwrap().1), 70);
+        assert_eq!(value(&call(&mut vm, &address, &token, "balanceOf", &[9]).unwrap().1), 30);
+        assert_eq!(value(&call(&mut vm, &address, &token, "totalSupply", &[]).unwrap().1), 100);
+        assert_eq!(vm.storage(&address, 0), 100);
+
+        // require 失败时回滚，回滚数据为信息
+        let (success, output, _) = call(&mut vm, &address, &token, "mint", &[7, 0]).unwrap();
+        assert!(!success);
+        assert_eq!(output, b"amount is zero");
+
+        // 未知选择器
+        assert!(!vm.call(&address, &[1, 2, 3, 4, 5, 6, 7, 8], 1_000_000).unwrap().success);
+    }
+
+    #[test]
+    fn test_generated_abi() {
+        let abi = Compiler::new().compile_contract(TOKEN).unwrap().abi;
+        assert_eq!(abi.name, "Token");
+        let names: Vec<&str> = abi.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["mint", "transfer", "balanceOf", "totalSupply"]);
+
+        let transfer = abi.method("transfer").unwrap();
+        assert_eq!(transfer.signature(), "transfer(address,address,uint)");
+        assert_eq!(transfer.outputs[0].param_type, "bool");
+        assert!(!transfer.constant && abi.method("balanceOf").unwrap().constant);
+        assert_eq!(abi.event("Transfer").unwrap().parameters[2].name, "amount");
+
+        // 状态变量按声明顺序构成存储布局
+        let layout: Vec<(&str, &str)> = abi.storage.iter().map(|p| (p.name.as_str(), p.param_type.as_str())).collect();
+        assert_eq!(layout, [("supply", "uint"), ("balances", "map<address, uint>")]);
+    }
+
+    #[tokio::test]
+    async fn test_control_flow_short_circuit_and_traps() {
+        let source = r#"
+            contract Math {
+                function sumTo(uint n) public view returns (uint) {
+                    uint total;
+                    uint i = 1;
+                    while (i <= n) {
+                        total += square(i);
+                        i = i + 1;
+                    }
+                    return total;
+                }
+
+                function safeRatio(uint a, uint b) public view returns (bool) {
+                    return b != 0 && a / b > 1;
+                }
+
+                function checked(uint a) public view returns (uint) {
+                    assert(a < 10);
+                    return a - 1;
+                }
+
+                function square(uint x) private view returns (uint) {
+                    return x * x;
+                }
+            }
+        "#;
+        let math = Compiler::new().compile_contract(source).unwrap();
+        let mut vm = VirtualMachine::new();
+        let address = vm.deploy(&math.bytecode).await.unwrap();
+
+        assert_eq!(value(&call(&mut vm, &address, &math, "sumTo", &[4]).unwrap().1), 30);
+        assert_eq!(value(&call(&mut vm, &address, &math, "safeRatio", &[10, 0]).unwrap().1), 0);
+        assert_eq!(value(&call(&mut vm, &address, &math, "safeRatio", &[10, 3]).unwrap().1), 1);
+        assert!(matches!(call(&mut vm, &address, &math, "checked", &[0]), Err(VmError::ArithmeticOverflow { .. })));
+        assert!(matches!(call(&mut vm, &address, &math, "checked", &[10]), Err(VmError::DivisionByZero { .. })));
+    }
+
+    #[test]
+    fn test_type_errors_have_spans() {
+        let error = |source: &str| Compiler::new().compile_contract(source).unwrap_err();
+
+        let mismatch = error("contract C {\n  function f() public {\n    uint x = true;\n  }\n}");
+        assert_eq!(mismatch.span, Span { line: 3, column: 14 });
+        assert_eq!(mismatch.to_string(), "3:14: 类型不匹配: 应为 uint，实际为 bool");
+
+        let view = error("contract C { uint x; function f() public view { x = 1; } }");
+        assert_eq!(view.message, "只读函数 `f` 不能写状态变量");
+
+        let recursion = error("contract C { function f() private { g(); } function g() private { f(); } }");
+        assert_eq!(recursion.message, "不支持递归调用 `f`");
+
+        assert_eq!(error("contract C { function f() public returns (uint) { if (true) { return 1; } } }").message,
+            "函数 `f` 并非所有路径都有返回值");
+        assert_eq!(error("contract C { function f() public { y = 1; } }").message, "未定义的变量 `y`");
+        assert_eq!(error("contract C { map<uint, uint> m; function f() public view returns (uint) { return m; } }").message,
+            "映射 `m` 只能按键访问");
+    }
+
+    #[tokio::test]
+    async fn test_compile_reports_compilation_error() {
+        let compiler = Compiler::new();
+        let bytecode = compiler.compile("contract Empty { }").await.unwrap();
+        assert!(crate::smart_contracts::vm::verify(&bytecode).is_ok());
+
+        let error = compiler.compile("contract Broken {").await.unwrap_err();
+        assert!(error.to_string().contains("编译错误: 1:18: 应为状态变量、事件或函数，实际为文件结尾"));
+    }
+}
@@ -0,0 +1,405 @@
+//! 语法分析：递归下降
+//!
+//! ```text
+//! contract    = "contract" IDENT "{" member* "}"
+//! member      = type IDENT ";"
+//!             | "event" IDENT "(" params ")" ";"
+//!             | "function" IDENT "(" params ")" ("public" | "private") "view"? ("returns" "(" type ")")? block
+//! type        = "uint" | "bool" | "address" | "map" "<" type "," type ">"
+//! statement   = type IDENT ("=" expr)? ";"
+//!             | "if" "(" expr ")" block ("else" (block | if))?
+//!             | "while" "(" expr ")" block
+//!             | "return" expr? ";"
+//!             | "require" "(" expr ("," STRING)? ")" ";"
+//!             | "assert" "(" expr ")" ";"
+//!             | "emit" IDENT "(" args ")" ";"
+//!             | expr (("=" | "+=" | "-=" | "*=") expr)? ";"
+//! ```
+//!
+//! 运算符优先级从低到高为 `||`、`&&`、`== !=`、`< <= > >=`、`+ -`、`* / %`、`!`。
+
+use super::ast::*;
+use super::lexer::{tokenize, Token};
+use super::{CompileError, Span};
+
+/// 解析一个合约
+pub fn parse(source: &str) -> Result<Contract, CompileError> {
+    let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+    let contract = parser.contract()?;
+    parser.expect(Token::Eof)?;
+    Ok(contract)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> (Token, Span) {
+        let token = self.tokens[self.pos].clone();
+        if token.0 != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, CompileError> {
+        Err(CompileError::new(self.span(), format!("应为{}，实际为{}", expected, self.peek().describe())))
+    }
+
+    fn expect(&mut self, token: Token) -> Result<Span, CompileError> {
+        if *self.peek() == token {
+            Ok(self.advance().1)
+        } else {
+            self.unexpected(&token.describe())
+        }
+    }
+
+    fn identifier(&mut self) -> Result<(String, Span), CompileError> {
+        match self.peek().clone() {
+            Token::Identifier(name) => Ok((name, self.advance().1)),
+            _ => self.unexpected("标识符"),
+        }
+    }
+
+    fn contract(&mut self) -> Result<Contract, CompileError> {
+        let span = self.expect(Token::Contract)?;
+        let (name, _) = self.identifier()?;
+        self.expect(Token::LeftBrace)?;
+        let mut contract = Contract { name, span, state: Vec::new(), events: Vec::new(), functions: Vec::new() };
+        while !self.eat(&Token::RightBrace) {
+            match self.peek() {
+                Token::Event => contract.events.push(self.event()?),
+                Token::Function => contract.functions.push(self.function()?),
+                Token::Uint | Token::Bool | Token::Address | Token::Map => {
+                    let declaration = self.declaration()?;
+                    self.expect(Token::Semicolon)?;
+                    contract.state.push(declaration);
+                }
+                _ => return self.unexpected("状态变量、事件或函数"),
+            }
+        }
+        Ok(contract)
+    }
+
+    fn ty(&mut self) -> Result<Type, CompileError> {
+        let ty = match self.peek() {
+            Token::Uint => Type::Uint,
+            Token::Bool => Type::Bool,
+            Token::Address => Type::Address,
+            Token::Map => {
+                self.advance();
+                self.expect(Token::Less)?;
+                let key = self.ty()?;
+                self.expect(Token::Comma)?;
+                let value = self.ty()?;
+                self.expect(Token::Greater)?;
+                return Ok(Type::Map(Box::new(key), Box::new(value)));
+            }
+            _ => return self.unexpected("类型"),
+        };
+        self.advance();
+        Ok(ty)
+    }
+
+    fn declaration(&mut self) -> Result<Declaration, CompileError> {
+        let span = self.span();
+        let ty = self.ty()?;
+        let (name, _) = self.identifier()?;
+        Ok(Declaration { name, ty, span })
+    }
+
+    fn params(&mut self) -> Result<Vec<Declaration>, CompileError> {
+        self.expect(Token::LeftParen)?;
+        let mut params = Vec::new();
+        if !self.eat(&Token::RightParen) {
+            loop {
+                params.push(self.declaration()?);
+                if self.eat(&Token::RightParen) {
+                    break;
+                }
+                self.expect(Token::Comma)?;
+            }
+        }
+        Ok(params)
+    }
+
+    fn event(&mut self) -> Result<Event, CompileError> {
+        let span = self.expect(Token::Event)?;
+        let (name, _) = self.identifier()?;
+        let params = self.params()?;
+        self.expect(Token::Semicolon)?;
+        Ok(Event { name, params, span })
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        let span = self.expect(Token::Function)?;
+        let (name, _) = self.identifier()?;
+        let params = self.params()?;
+        let visibility = match self.peek() {
+            Token::Public => Visibility::Public,
+            Token::Private => Visibility::Private,
+            _ => return self.unexpected("可见性 `public` 或 `private`"),
+        };
+        self.advance();
+        let view = self.eat(&Token::View);
+        let returns = if self.eat(&Token::Returns) {
+            self.expect(Token::LeftParen)?;
+            let ty = self.ty()?;
+            self.expect(Token::RightParen)?;
+            Some(ty)
+        } else {
+            None
+        };
+        let body = self.block()?;
+        Ok(Function { name, params, returns, visibility, view, body, span })
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.expect(Token::LeftBrace)?;
+        let mut statements = Vec::new();
+        while !self.eat(&Token::RightBrace) {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement, CompileError> {
+        let span = self.span();
+        let kind = match self.peek() {
+            Token::Uint | Token::Bool | Token::Address | Token::Map => {
+                let declaration = self.declaration()?;
+                let value = if self.eat(&Token::Assign) { Some(self.expression()?) } else { None };
+                self.expect(Token::Semicolon)?;
+                StatementKind::Let { declaration, value }
+            }
+            Token::If => return self.if_statement(),
+            Token::While => {
+                self.advance();
+                let condition = self.parenthesized()?;
+                let body = self.block()?;
+                StatementKind::While { condition, body }
+            }
+            Token::Return => {
+                self.advance();
+                let value = if *self.peek() == Token::Semicolon { None } else { Some(self.expression()?) };
+                self.expect(Token::Semicolon)?;
+                StatementKind::Return(value)
+            }
+            Token::Require => {
+                self.advance();
+                self.expect(Token::LeftParen)?;
+                let condition = self.expression()?;
+                let message = if self.eat(&Token::Comma) {
+                    match self.advance() {
+                        (Token::Str(message), _) => Some(message),
+                        _ => {
+                            self.pos -= 1;
+                            return self.unexpected("字符串");
+                        }
+                    }
+                } else {
+                    None
+                };
+                self.expect(Token::RightParen)?;
+                self.expect(Token::Semicolon)?;
+                StatementKind::Require { condition, message }
+            }
+            Token::Assert => {
+                self.advance();
+                let condition = self.parenthesized()?;
+                self.expect(Token::Semicolon)?;
+                StatementKind::Assert(condition)
+            }
+            Token::Emit => {
+                self.advance();
+                let (event, _) = self.identifier()?;
+                let args = self.arguments()?;
+                self.expect(Token::Semicolon)?;
+                StatementKind::Emit { event, args }
+            }
+            _ => {
+                let target = self.expression()?;
+                let op = match self.peek() {
+                    Token::Assign => Some(None),
+                    Token::PlusAssign => Some(Some(BinaryOperator::Add)),
+                    Token::MinusAssign => Some(Some(BinaryOperator::Sub)),
+                    Token::StarAssign => Some(Some(BinaryOperator::Mul)),
+                    _ => None,
+                };
+                let kind = match op {
+                    Some(op) => {
+                        self.advance();
+                        StatementKind::Assign { target, op, value: self.expression()? }
+                    }
+                    None => StatementKind::Expression(target),
+                };
+                self.expect(Token::Semicolon)?;
+                kind
+            }
+        };
+        Ok(Statement { kind, span })
+    }
+
+    fn if_statement(&mut self) -> Result<Statement, CompileError> {
+        let span = self.expect(Token::If)?;
+        let condition = self.parenthesized()?;
+        let then_branch = self.block()?;
+        let else_branch = if !self.eat(&Token::Else) {
+            None
+        } else if *self.peek() == Token::If {
+            Some(vec![self.if_statement()?])
+        } else {
+            Some(self.block()?)
+        };
+        Ok(Statement { kind: StatementKind::If { condition, then_branch, else_branch }, span })
+    }
+
+    fn parenthesized(&mut self) -> Result<Expression, CompileError> {
+        self.expect(Token::LeftParen)?;
+        let expression = self.expression()?;
+        self.expect(Token::RightParen)?;
+        Ok(expression)
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expression>, CompileError> {
+        self.expect(Token::LeftParen)?;
+        let mut args = Vec::new();
+        if !self.eat(&Token::RightParen) {
+            loop {
+                args.push(self.expression()?);
+                if self.eat(&Token::RightParen) {
+                    break;
+                }
+                self.expect(Token::Comma)?;
+            }
+        }
+        Ok(args)
+    }
+
+    fn expression(&mut self) -> Result<Expression, CompileError> {
+        self.binary(0)
+    }
+
+    /// 按优先级 `level` 解析左结合的二元表达式
+    fn binary(&mut self, level: usize) -> Result<Expression, CompileError> {
+        const LEVELS: [&[(Token, BinaryOperator)]; 6] = [
+            &[(Token::OrOr, BinaryOperator::Or)],
+            &[(Token::AndAnd, BinaryOperator::And)],
+            &[(Token::EqualEqual, BinaryOperator::Eq), (Token::BangEqual, BinaryOperator::Ne)],
+            &[
+                (Token::Less, BinaryOperator::Lt),
+                (Token::LessEqual, BinaryOperator::Le),
+                (Token::Greater, BinaryOperator::Gt),
+                (Token::GreaterEqual, BinaryOperator::Ge),
+            ],
+            &[(Token::Plus, BinaryOperator::Add), (Token::Minus, BinaryOperator::Sub)],
+            &[(Token::Star, BinaryOperator::Mul), (Token::Slash, BinaryOperator::Div), (Token::Percent, BinaryOperator::Mod)],
+        ];
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(&(_, op)) = operators.iter().find(|(token, _)| token == self.peek()) {
+            let span = self.advance().1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expression { kind: ExpressionKind::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }, span };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expression, CompileError> {
+        if *self.peek() == Token::Bang {
+            let span = self.advance().1;
+            let operand = self.unary()?;
+            return Ok(Expression { kind: ExpressionKind::Not(Box::new(operand)), span });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, CompileError> {
+        let span = self.span();
+        let kind = match self.peek().clone() {
+            Token::Number(value) => {
+                self.advance();
+                ExpressionKind::Number(value)
+            }
+            Token::True | Token::False => ExpressionKind::Bool(self.advance().0 == Token::True),
+            Token::LeftParen => return self.parenthesized(),
+            Token::Uint | Token::Address => {
+                let ty = self.ty()?;
+                let value = self.parenthesized()?;
+                ExpressionKind::Convert { ty, value: Box::new(value) }
+            }
+            Token::Identifier(name) => {
+                self.advance();
+                match self.peek() {
+                    Token::LeftParen => ExpressionKind::Call { function: name, args: self.arguments()? },
+                    Token::LeftBracket => {
+                        self.advance();
+                        let key = self.expression()?;
+                        self.expect(Token::RightBracket)?;
+                        ExpressionKind::Index { map: name, key: Box::new(key) }
+                    }
+                    _ => ExpressionKind::Variable(name),
+                }
+            }
+            _ => return self.unexpected("表达式"),
+        };
+        Ok(Expression { kind, span })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_precedence_and_else_if() {
+        let contract = parse(
+            "contract C { uint x; function f(uint a) private returns (bool) {
+                if (a == 1) { return true; } else if (a < 2) { x += 1; } else { x = 0; }
+                return a + 2 * 3 > 7 || !(a == 0) && true;
+            } }",
+        ).unwrap();
+        let body = &contract.functions[0].body;
+        assert!(matches!(&body[0].kind, StatementKind::If { else_branch: Some(branch), .. } if matches!(branch[0].kind, StatementKind::If { .. })));
+
+        // (a + (2 * 3) > 7) || ((!(a == 0)) && true)
+        let StatementKind::Return(Some(expression)) = &body[1].kind else { panic!("expected return") };
+        let ExpressionKind::Binary { op: BinaryOperator::Or, lhs, rhs } = &expression.kind else { panic!("expected ||") };
+        let ExpressionKind::Binary { op: BinaryOperator::Gt, lhs: sum, .. } = &lhs.kind else { panic!("expected >") };
+        assert!(matches!(&sum.kind, ExpressionKind::Binary { op: BinaryOperator::Add, rhs, .. }
+            if matches!(rhs.kind, ExpressionKind::Binary { op: BinaryOperator::Mul, .. })));
+        assert!(matches!(&rhs.kind, ExpressionKind::Binary { op: BinaryOperator::And, lhs, .. } if matches!(lhs.kind, ExpressionKind::Not(_))));
+    }
+
+    #[test]
+    fn test_parse_errors_carry_spans() {
+        let error = parse("contract C {\n  function f() { }\n}").unwrap_err();
+        assert_eq!(error.span, Span { line: 2, column: 16 });
+        assert_eq!(error.message, "应为可见性 `public` 或 `private`，实际为`{`");
+
+        let error = parse("contract C { function f() public { require(true, 1); } }").unwrap_err();
+        assert_eq!(error.span, Span { line: 1, column: 50 });
+    }
+}
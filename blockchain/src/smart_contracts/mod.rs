@@ -2,12 +2,13 @@
 //! 
 //! 提供智能合约相关功能
 
+pub mod abi;
 pub mod vm;
 pub mod compiler;
 pub mod runtime;
 
 pub use vm::VirtualMachine;
-pub use compiler::Compiler;
+pub use compiler::{CompileError, CompiledContract, Compiler};
 pub use runtime::Runtime;
 
 use crate::core::{Result, BlockchainError};
@@ -1,27 +0,0 @@
-//! 虚拟机实现
-
-use super::SmartContractResult;
-// use crate::core::{Result, BlockchainError};
-
-/// 虚拟机
-#[derive(Debug)]
-pub struct VirtualMachine {
-    // 虚拟机相关状态
-}
-
-impl VirtualMachine {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    pub async fn execute(&mut self, _bytecode: &[u8], _input: &[u8]) -> SmartContractResult<Vec<u8>> {
-        // 简化的虚拟机执行
-        Ok(vec![])
-    }
-
-    pub async fn deploy(&mut self, bytecode: &[u8]) -> SmartContractResult<String> {
-        // 简化的合约部署
-        let address = format!("0x{:x}", bytecode.len());
-        Ok(address)
-    }
-}
@@ -0,0 +1,462 @@
+//! 指令集：编码、解码、gas 表与反汇编
+
+use super::VmError;
+use std::fmt;
+
+/// 通用寄存器数量
+pub const REGISTER_COUNT: usize = 16;
+
+/// 寄存器编号（0..16）
+pub type Register = u8;
+
+/// 操作码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Opcode {
+    Halt = 0x00,
+    Return = 0x01,
+    Revert = 0x02,
+    LoadImm = 0x10,
+    Mov = 0x11,
+    Push = 0x12,
+    Pop = 0x13,
+    Add = 0x20,
+    Sub = 0x21,
+    Mul = 0x22,
+    Div = 0x23,
+    Mod = 0x24,
+    And = 0x25,
+    Or = 0x26,
+    Xor = 0x27,
+    Shl = 0x28,
+    Shr = 0x29,
+    Eq = 0x2a,
+    Lt = 0x2b,
+    Gt = 0x2c,
+    Not = 0x2d,
+    IsZero = 0x2e,
+    Jump = 0x30,
+    JumpIfZero = 0x31,
+    JumpIfNotZero = 0x32,
+    MLoad = 0x40,
+    MStore = 0x41,
+    MLoad8 = 0x42,
+    MStore8 = 0x43,
+    InputSize = 0x44,
+    InputCopy = 0x45,
+    Hash = 0x46,
+    SLoad = 0x50,
+    SStore = 0x51,
+    Call = 0x60,
+    ReturnDataSize = 0x61,
+    ReturnDataCopy = 0x62,
+    Log = 0x70,
+    Gas = 0x80,
+}
+
+impl Opcode {
+    /// 全部操作码
+    pub const ALL: [Opcode; 39] = [
+        Opcode::Halt, Opcode::Return, Opcode::Revert,
+        Opcode::LoadImm, Opcode::Mov, Opcode::Push, Opcode::Pop,
+        Opcode::Add, Opcode::Sub, Opcode::Mul, Opcode::Div, Opcode::Mod,
+        Opcode::And, Opcode::Or, Opcode::Xor, Opcode::Shl, Opcode::Shr,
+        Opcode::Eq, Opcode::Lt, Opcode::Gt, Opcode::Not, Opcode::IsZero,
+        Opcode::Jump, Opcode::JumpIfZero, Opcode::JumpIfNotZero,
+        Opcode::MLoad, Opcode::MStore, Opcode::MLoad8, Opcode::MStore8,
+        Opcode::InputSize, Opcode::InputCopy, Opcode::Hash,
+        Opcode::SLoad, Opcode::SStore,
+        Opcode::Call, Opcode::ReturnDataSize, Opcode::ReturnDataCopy,
+        Opcode::Log, Opcode::Gas,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|opcode| *opcode as u8 == byte)
+    }
+
+    /// 助记符
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Halt => "HALT",
+            Opcode::Return => "RETURN",
+            Opcode::Revert => "REVERT",
+            Opcode::LoadImm => "LOADI",
+            Opcode::Mov => "MOV",
+            Opcode::Push => "PUSH",
+            Opcode::Pop => "POP",
+            Opcode::Add => "ADD",
+            Opcode::Sub => "SUB",
+            Opcode::Mul => "MUL",
+            Opcode::Div => "DIV",
+            Opcode::Mod => "MOD",
+            Opcode::And => "AND",
+            Opcode::Or => "OR",
+            Opcode::Xor => "XOR",
+            Opcode::Shl => "SHL",
+            Opcode::Shr => "SHR",
+            Opcode::Eq => "EQ",
+            Opcode::Lt => "LT",
+            Opcode::Gt => "GT",
+            Opcode::Not => "NOT",
+            Opcode::IsZero => "ISZERO",
+            Opcode::Jump => "JMP",
+            Opcode::JumpIfZero => "JZ",
+            Opcode::JumpIfNotZero => "JNZ",
+            Opcode::MLoad => "MLOAD",
+            Opcode::MStore => "MSTORE",
+            Opcode::MLoad8 => "MLOAD8",
+            Opcode::MStore8 => "MSTORE8",
+            Opcode::InputSize => "INPUTSIZE",
+            Opcode::InputCopy => "INPUTCOPY",
+            Opcode::Hash => "HASH",
+            Opcode::SLoad => "SLOAD",
+            Opcode::SStore => "SSTORE",
+            Opcode::Call => "CALL",
+            Opcode::ReturnDataSize => "RETSIZE",
+            Opcode::ReturnDataCopy => "RETCOPY",
+            Opcode::Log => "LOG",
+            Opcode::Gas => "GAS",
+        }
+    }
+
+    /// 基础 gas 费用；内存扩展、拷贝字节和转发给被调合约的 gas 另计
+    pub fn gas(self) -> u64 {
+        match self {
+            Opcode::Halt | Opcode::Return | Opcode::Revert => 0,
+            Opcode::LoadImm | Opcode::Mov | Opcode::Push | Opcode::Pop => 2,
+            Opcode::InputSize | Opcode::ReturnDataSize | Opcode::Gas => 2,
+            Opcode::Add | Opcode::Sub | Opcode::And | Opcode::Or | Opcode::Xor
+            | Opcode::Shl | Opcode::Shr | Opcode::Eq | Opcode::Lt | Opcode::Gt
+            | Opcode::Not | Opcode::IsZero => 3,
+            Opcode::Mul | Opcode::Div | Opcode::Mod => 5,
+            Opcode::Jump => 8,
+            Opcode::JumpIfZero | Opcode::JumpIfNotZero => 10,
+            Opcode::MLoad | Opcode::MStore | Opcode::MLoad8 | Opcode::MStore8 => 3,
+            Opcode::InputCopy | Opcode::ReturnDataCopy => 3,
+            Opcode::Hash => 30,
+            Opcode::SLoad => 200,
+            Opcode::SStore => 5_000,
+            Opcode::Call => 700,
+            Opcode::Log => 375,
+        }
+    }
+}
+
+/// 二元运算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Eq,
+    Lt,
+    Gt,
+}
+
+impl BinaryOp {
+    pub fn opcode(self) -> Opcode {
+        match self {
+            BinaryOp::Add => Opcode::Add,
+            BinaryOp::Sub => Opcode::Sub,
+            BinaryOp::Mul => Opcode::Mul,
+            BinaryOp::Div => Opcode::Div,
+            BinaryOp::Mod => Opcode::Mod,
+            BinaryOp::And => Opcode::And,
+            BinaryOp::Or => Opcode::Or,
+            BinaryOp::Xor => Opcode::Xor,
+            BinaryOp::Shl => Opcode::Shl,
+            BinaryOp::Shr => Opcode::Shr,
+            BinaryOp::Eq => Opcode::Eq,
+            BinaryOp::Lt => Opcode::Lt,
+            BinaryOp::Gt => Opcode::Gt,
+        }
+    }
+
+    fn from_opcode(opcode: Opcode) -> Option<Self> {
+        Some(match opcode {
+            Opcode::Add => BinaryOp::Add,
+            Opcode::Sub => BinaryOp::Sub,
+            Opcode::Mul => BinaryOp::Mul,
+            Opcode::Div => BinaryOp::Div,
+            Opcode::Mod => BinaryOp::Mod,
+            Opcode::And => BinaryOp::And,
+            Opcode::Or => BinaryOp::Or,
+            Opcode::Xor => BinaryOp::Xor,
+            Opcode::Shl => BinaryOp::Shl,
+            Opcode::Shr => BinaryOp::Shr,
+            Opcode::Eq => BinaryOp::Eq,
+            Opcode::Lt => BinaryOp::Lt,
+            Opcode::Gt => BinaryOp::Gt,
+            _ => return None,
+        })
+    }
+}
+
+/// 一条指令
+///
+/// 编码为 1 字节操作码加操作数：寄存器各占 1 字节，立即数为 8 字节小端，跳转目标为 4 字节小端的字节偏移。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 停止执行，输出为空
+    Halt,
+    /// 以内存 `[offset, offset + len)` 为输出结束执行
+    Return { offset: Register, len: Register },
+    /// 回滚本次调用的状态修改，内存 `[offset, offset + len)` 为回滚数据
+    Revert { offset: Register, len: Register },
+    LoadImm { dst: Register, value: u64 },
+    Mov { dst: Register, src: Register },
+    /// 把寄存器压入操作数栈
+    Push { src: Register },
+    /// 弹出操作数栈顶到寄存器
+    Pop { dst: Register },
+    /// 算术运算溢出、除以零时陷入；比较结果为 0 或 1；移位量不小于 64 时结果为 0
+    Binary { op: BinaryOp, dst: Register, lhs: Register, rhs: Register },
+    Not { dst: Register, src: Register },
+    IsZero { dst: Register, src: Register },
+    Jump { target: u32 },
+    JumpIfZero { cond: Register, target: u32 },
+    JumpIfNotZero { cond: Register, target: u32 },
+    /// 读取地址处的 8 字节（小端）
+    MLoad { dst: Register, addr: Register },
+    MStore { addr: Register, src: Register },
+    MLoad8 { dst: Register, addr: Register },
+    MStore8 { addr: Register, src: Register },
+    InputSize { dst: Register },
+    /// 把调用输入 `[offset, offset + len)` 拷贝到内存 `dest`
+    InputCopy { dest: Register, offset: Register, len: Register },
+    /// 内存 `[offset, offset + len)` 的 SHA-256 的前 8 字节（小端）
+    Hash { dst: Register, offset: Register, len: Register },
+    SLoad { dst: Register, key: Register },
+    SStore { key: Register, value: Register },
+    /// 调用地址为内存 `[addr, addr + addr_len)` 的合约，`gas` 为 0 时转发上限；成功时 `dst` 为 1，否则为 0
+    Call { dst: Register, addr: Register, addr_len: Register, input: Register, input_len: Register, gas: Register },
+    ReturnDataSize { dst: Register },
+    ReturnDataCopy { dest: Register, offset: Register, len: Register },
+    /// 以 `topic` 为主题记录内存 `[offset, offset + len)`
+    Log { topic: Register, offset: Register, len: Register },
+    /// 剩余 gas
+    Gas { dst: Register },
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> Opcode {
+        match self {
+            Instruction::Halt => Opcode::Halt,
+            Instruction::Return { .. } => Opcode::Return,
+            Instruction::Revert { .. } => Opcode::Revert,
+            Instruction::LoadImm { .. } => Opcode::LoadImm,
+            Instruction::Mov { .. } => Opcode::Mov,
+            Instruction::Push { .. } => Opcode::Push,
+            Instruction::Pop { .. } => Opcode::Pop,
+            Instruction::Binary { op, .. } => op.opcode(),
+            Instruction::Not { .. } => Opcode::Not,
+            Instruction::IsZero { .. } => Opcode::IsZero,
+            Instruction::Jump { .. } => Opcode::Jump,
+            Instruction::JumpIfZero { .. } => Opcode::JumpIfZero,
+            Instruction::JumpIfNotZero { .. } => Opcode::JumpIfNotZero,
+            Instruction::MLoad { .. } => Opcode::MLoad,
+            Instruction::MStore { .. } => Opcode::MStore,
+            Instruction::MLoad8 { .. } => Opcode::MLoad8,
+            Instruction::MStore8 { .. } => Opcode::MStore8,
+            Instruction::InputSize { .. } => Opcode::InputSize,
+            Instruction::InputCopy { .. } => Opcode::InputCopy,
+            Instruction::Hash { .. } => Opcode::Hash,
+            Instruction::SLoad { .. } => Opcode::SLoad,
+            Instruction::SStore { .. } => Opcode::SStore,
+            Instruction::Call { .. } => Opcode::Call,
+            Instruction::ReturnDataSize { .. } => Opcode::ReturnDataSize,
+            Instruction::ReturnDataCopy { .. } => Opcode::ReturnDataCopy,
+            Instruction::Log { .. } => Opcode::Log,
+            Instruction::Gas { .. } => Opcode::Gas,
+        }
+    }
+
+    /// 寄存器操作数
+    fn registers(&self) -> Vec<Register> {
+        match *self {
+            Instruction::Halt | Instruction::Jump { .. } => vec![],
+            Instruction::Push { src: r } | Instruction::Pop { dst: r }
+            | Instruction::LoadImm { dst: r, .. } | Instruction::InputSize { dst: r }
+            | Instruction::ReturnDataSize { dst: r } | Instruction::Gas { dst: r }
+            | Instruction::JumpIfZero { cond: r, .. } | Instruction::JumpIfNotZero { cond: r, .. } => vec![r],
+            Instruction::Return { offset: a, len: b } | Instruction::Revert { offset: a, len: b }
+            | Instruction::Mov { dst: a, src: b } | Instruction::Not { dst: a, src: b }
+            | Instruction::IsZero { dst: a, src: b } | Instruction::MLoad { dst: a, addr: b }
+            | Instruction::MStore { addr: a, src: b } | Instruction::MLoad8 { dst: a, addr: b }
+            | Instruction::MStore8 { addr: a, src: b } | Instruction::SLoad { dst: a, key: b }
+            | Instruction::SStore { key: a, value: b } => vec![a, b],
+            Instruction::Binary { dst: a, lhs: b, rhs: c, .. }
+            | Instruction::InputCopy { dest: a, offset: b, len: c }
+            | Instruction::Hash { dst: a, offset: b, len: c }
+            | Instruction::ReturnDataCopy { dest: a, offset: b, len: c }
+            | Instruction::Log { topic: a, offset: b, len: c } => vec![a, b, c],
+            Instruction::Call { dst, addr, addr_len, input, input_len, gas } => vec![dst, addr, addr_len, input, input_len, gas],
+        }
+    }
+
+    /// 跳转目标
+    pub fn jump_target(&self) -> Option<u32> {
+        match *self {
+            Instruction::Jump { target }
+            | Instruction::JumpIfZero { target, .. }
+            | Instruction::JumpIfNotZero { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    /// 执行后是否不会落到下一条指令
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Instruction::Halt | Instruction::Return { .. } | Instruction::Revert { .. } | Instruction::Jump { .. })
+    }
+
+    /// 编码后的字节数
+    pub fn encoded_len(&self) -> usize {
+        let immediate = match self {
+            Instruction::LoadImm { .. } => 8,
+            Instruction::Jump { .. } | Instruction::JumpIfZero { .. } | Instruction::JumpIfNotZero { .. } => 4,
+            _ => 0,
+        };
+        1 + self.registers().len() + immediate
+    }
+
+    /// 追加编码到 `out`
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.opcode() as u8);
+        out.extend(self.registers());
+        match *self {
+            Instruction::LoadImm { value, .. } => out.extend_from_slice(&value.to_le_bytes()),
+            Instruction::Jump { target } | Instruction::JumpIfZero { target, .. } | Instruction::JumpIfNotZero { target, .. } => {
+                out.extend_from_slice(&target.to_le_bytes())
+            }
+            _ => {}
+        }
+    }
+
+    /// 从 `offset` 处解码一条指令
+    pub fn decode(code: &[u8], offset: usize) -> Result<Self, VmError> {
+        let opcode_byte = *code.get(offset).ok_or(VmError::Truncated { offset })?;
+        let opcode = Opcode::from_byte(opcode_byte).ok_or(VmError::InvalidOpcode { offset, opcode: opcode_byte })?;
+
+        let mut cursor = offset + 1;
+        let mut reg = || -> Result<Register, VmError> {
+            let register = *code.get(cursor).ok_or(VmError::Truncated { offset })?;
+            if register as usize >= REGISTER_COUNT {
+                return Err(VmError::InvalidRegister { offset, register });
+            }
+            cursor += 1;
+            Ok(register)
+        };
+
+        let instruction = match opcode {
+            Opcode::Halt => Instruction::Halt,
+            Opcode::Return => Instruction::Return { offset: reg()?, len: reg()? },
+            Opcode::Revert => Instruction::Revert { offset: reg()?, len: reg()? },
+            Opcode::LoadImm => {
+                let dst = reg()?;
+                let bytes = code.get(offset + 2..offset + 10).ok_or(VmError::Truncated { offset })?;
+                Instruction::LoadImm { dst, value: u64::from_le_bytes(bytes.try_into().expect("8 bytes")) }
+            }
+            Opcode::Mov => Instruction::Mov { dst: reg()?, src: reg()? },
+            Opcode::Push => Instruction::Push { src: reg()? },
+            Opcode::Pop => Instruction::Pop { dst: reg()? },
+            Opcode::Not => Instruction::Not { dst: reg()?, src: reg()? },
+            Opcode::IsZero => Instruction::IsZero { dst: reg()?, src: reg()? },
+            Opcode::Jump => Instruction::Jump { target: read_target(code, offset, offset + 1)? },
+            Opcode::JumpIfZero => {
+                let cond = reg()?;
+                Instruction::JumpIfZero { cond, target: read_target(code, offset, offset + 2)? }
+            }
+            Opcode::JumpIfNotZero => {
+                let cond = reg()?;
+                Instruction::JumpIfNotZero { cond, target: read_target(code, offset, offset + 2)? }
+            }
+            Opcode::MLoad => Instruction::MLoad { dst: reg()?, addr: reg()? },
+            Opcode::MStore => Instruction::MStore { addr: reg()?, src: reg()? },
+            Opcode::MLoad8 => Instruction::MLoad8 { dst: reg()?, addr: reg()? },
+            Opcode::MStore8 => Instruction::MStore8 { addr: reg()?, src: reg()? },
+            Opcode::InputSize => Instruction::InputSize { dst: reg()? },
+            Opcode::InputCopy => Instruction::InputCopy { dest: reg()?, offset: reg()?, len: reg()? },
+            Opcode::Hash => Instruction::Hash { dst: reg()?, offset: reg()?, len: reg()? },
+            Opcode::SLoad => Instruction::SLoad { dst: reg()?, key: reg()? },
+            Opcode::SStore => Instruction::SStore { key: reg()?, value: reg()? },
+            Opcode::Call => Instruction::Call {
+                dst: reg()?,
+                addr: reg()?,
+                addr_len: reg()?,
+                input: reg()?,
+                input_len: reg()?,
+                gas: reg()?,
+            },
+            Opcode::ReturnDataSize => Instruction::ReturnDataSize { dst: reg()? },
+            Opcode::ReturnDataCopy => Instruction::ReturnDataCopy { dest: reg()?, offset: reg()?, len: reg()? },
+            Opcode::Log => Instruction::Log { topic: reg()?, offset: reg()?, len: reg()? },
+            Opcode::Gas => Instruction::Gas { dst: reg()? },
+            binary => Instruction::Binary {
+                op: BinaryOp::from_opcode(binary).expect("remaining opcodes are binary operations"),
+                dst: reg()?,
+                lhs: reg()?,
+                rhs: reg()?,
+            },
+        };
+        Ok(instruction)
+    }
+}
+
+fn read_target(code: &[u8], offset: usize, at: usize) -> Result<u32, VmError> {
+    let bytes = code.get(at..at + 4).ok_or(VmError::Truncated { offset })?;
+    Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode().mnemonic())?;
+        let mut operands: Vec<String> = self.registers().iter().map(|r| format!("r{}", r)).collect();
+        match *self {
+            Instruction::LoadImm { value, .. } => operands.push(value.to_string()),
+            Instruction::Jump { target } | Instruction::JumpIfZero { target, .. } | Instruction::JumpIfNotZero { target, .. } => {
+                operands.push(format!("@{:04x}", target))
+            }
+            _ => {}
+        }
+        if !operands.is_empty() {
+            write!(f, " {}", operands.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 把指令序列编码为字节码
+pub fn assemble(instructions: &[Instruction]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for instruction in instructions {
+        instruction.encode(&mut code);
+    }
+    code
+}
+
+/// 逐条解码字节码，返回每条指令的偏移
+pub fn decode_all(code: &[u8]) -> Result<Vec<(usize, Instruction)>, VmError> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let instruction = Instruction::decode(code, offset)?;
+        instructions.push((offset, instruction));
+        offset += instruction.encoded_len();
+    }
+    Ok(instructions)
+}
+
+/// 反汇编，每行一条指令：`偏移: 助记符 操作数`
+pub fn disassemble(code: &[u8]) -> Result<String, VmError> {
+    Ok(decode_all(code)?
+        .into_iter()
+        .map(|(offset, instruction)| format!("{:04x}: {}\n", offset, instruction))
+        .collect())
+}
@@ -0,0 +1,761 @@
+//! 虚拟机实现
+//!
+//! 寄存器式字节码虚拟机：轻量、确定性，不依赖任何可选特性，可在嵌入式构建中执行合约。
+//!
+//! ## 执行模型
+//!
+//! - 16 个 64 位通用寄存器 `r0`-`r15`，初始为 0；
+//! - 操作数栈（`PUSH`/`POP`），深度上限为 [`MAX_STACK_DEPTH`]；
+//! - 按字节寻址的线性内存，按 32 字节为一字扩展，上限为 [`VmConfig::max_memory`]；
+//! - 合约存储为 64 位键到 64 位值的映射，读取不存在的键得到 0；
+//! - 部署前由 [`verify`] 校验字节码，执行时不会遇到非法指令、非法跳转或栈下溢。
+//!
+//! ## 指令集
+//!
+//! | 操作码 | 助记符 | 操作数 | 语义 | gas |
+//! |--------|--------|--------|------|-----|
+//! | 0x00 | `HALT` | | 停止，输出为空 | 0 |
+//! | 0x01 | `RETURN` | `off, len` | 以内存 `[off, off+len)` 为输出停止 | 0 |
+//! | 0x02 | `REVERT` | `off, len` | 回滚本次调用的存储修改，内存区间为回滚数据 | 0 |
+//! | 0x10 | `LOADI` | `rd, imm64` | `rd = imm` | 2 |
+//! | 0x11 | `MOV` | `rd, rs` | `rd = rs` | 2 |
+//! | 0x12 | `PUSH` | `rs` | 压栈 | 2 |
+//! | 0x13 | `POP` | `rd` | 出栈 | 2 |
+//! | 0x20-0x24 | `ADD SUB MUL DIV MOD` | `rd, ra, rb` | 无符号运算，溢出或除以零时陷入 | 3 / 5 |
+//! | 0x25-0x29 | `AND OR XOR SHL SHR` | `rd, ra, rb` | 位运算，移位量 ≥ 64 时结果为 0 | 3 |
+//! | 0x2a-0x2c | `EQ LT GT` | `rd, ra, rb` | 比较，结果为 0 或 1 | 3 |
+//! | 0x2d / 0x2e | `NOT` / `ISZERO` | `rd, rs` | 按位取反 / 是否为 0 | 3 |
+//! | 0x30 | `JMP` | `target32` | 跳转到字节偏移 | 8 |
+//! | 0x31 / 0x32 | `JZ` / `JNZ` | `rs, target32` | 条件跳转 | 10 |
+//! | 0x40 / 0x41 | `MLOAD` / `MSTORE` | `rd, ra` / `ra, rs` | 读写 8 字节（小端） | 3 |
+//! | 0x42 / 0x43 | `MLOAD8` / `MSTORE8` | `rd, ra` / `ra, rs` | 读写 1 字节 | 3 |
+//! | 0x44 | `INPUTSIZE` | `rd` | 调用输入长度 | 2 |
+//! | 0x45 | `INPUTCOPY` | `rdest, roff, rlen` | 把输入区间拷贝到内存，越界部分补 0 | 3 + 拷贝 |
+//! | 0x46 | `HASH` | `rd, roff, rlen` | 内存区间 SHA-256 的前 8 字节（小端） | 30 + 每字 6 |
+//! | 0x50 / 0x51 | `SLOAD` / `SSTORE` | `rd, rk` / `rk, rv` | 读写合约存储 | 200 / 5000 |
+//! | 0x60 | `CALL` | `rd, raddr, rlen, rin, rinlen, rgas` | 调用其他合约，成功时 `rd = 1` | 700 + 转发 |
+//! | 0x61 | `RETSIZE` | `rd` | 最近一次调用的返回数据长度 | 2 |
+//! | 0x62 | `RETCOPY` | `rdest, roff, rlen` | 拷贝返回数据，越界时陷入 | 3 + 拷贝 |
+//! | 0x70 | `LOG` | `rtopic, roff, rlen` | 记录日志 | 375 + 每字节 8 |
+//! | 0x80 | `GAS` | `rd` | 剩余 gas | 2 |
+//!
+//! 内存每扩展一字另收 [`GAS_MEMORY_WORD`]，拷贝每字收 [`GAS_COPY_WORD`]。
+//! `CALL` 最多转发剩余 gas 的 63/64（`rgas` 为 0 或超出时取上限），被调方未用完的 gas 退回；
+//! 被调方失败或回滚时只撤销它自己的存储修改，返回数据为回滚数据或错误信息。
+
+pub mod instruction;
+pub mod verifier;
+
+pub use instruction::{assemble, disassemble, BinaryOp, Instruction, Opcode, Register, REGISTER_COUNT};
+pub use verifier::{verify, MAX_CODE_SIZE, MAX_STACK_DEPTH};
+
+use super::{SmartContractError, SmartContractResult};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// 内存每扩展 32 字节的费用
+pub const GAS_MEMORY_WORD: u64 = 3;
+/// 每拷贝 32 字节的费用
+pub const GAS_COPY_WORD: u64 = 3;
+/// `HASH` 每 32 字节的费用
+pub const GAS_HASH_WORD: u64 = 6;
+/// 日志数据每字节的费用
+pub const GAS_LOG_BYTE: u64 = 8;
+
+/// 虚拟机错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum VmError {
+    #[error("字节码过大: {0} 字节")]
+    CodeTooLarge(usize),
+    #[error("偏移 {offset} 处的指令不完整")]
+    Truncated { offset: usize },
+    #[error("偏移 {offset} 处的操作码无效: 0x{opcode:02x}")]
+    InvalidOpcode { offset: usize, opcode: u8 },
+    #[error("偏移 {offset} 处的寄存器无效: r{register}")]
+    InvalidRegister { offset: usize, register: u8 },
+    #[error("偏移 {offset} 处的跳转目标无效: {target}")]
+    InvalidJump { offset: usize, target: u32 },
+    #[error("偏移 {offset} 处操作数栈下溢")]
+    StackUnderflow { offset: usize },
+    #[error("偏移 {offset} 处操作数栈溢出")]
+    StackOverflow { offset: usize },
+    #[error("偏移 {offset} 处各路径的栈深不一致")]
+    InconsistentStack { offset: usize },
+    #[error("gas 不足")]
+    OutOfGas,
+    #[error("偏移 {offset} 处算术溢出")]
+    ArithmeticOverflow { offset: usize },
+    #[error("偏移 {offset} 处除以零")]
+    DivisionByZero { offset: usize },
+    #[error("偏移 {offset} 处内存访问越界")]
+    MemoryOutOfBounds { offset: usize },
+    #[error("合约不存在: {0}")]
+    ContractNotFound(String),
+    #[error("调用深度超限")]
+    CallDepthExceeded,
+}
+
+impl From<VmError> for SmartContractError {
+    fn from(err: VmError) -> Self {
+        SmartContractError::VirtualMachineError(err.to_string())
+    }
+}
+
+/// 虚拟机配置
+#[derive(Debug, Clone, Copy)]
+pub struct VmConfig {
+    /// `execute` 使用的 gas 上限
+    pub gas_limit: u64,
+    /// 每个调用帧的内存上限（字节）
+    pub max_memory: usize,
+    /// 合约调用的最大深度（含顶层调用）
+    pub max_call_depth: usize,
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        Self {
+            gas_limit: 10_000_000,
+            max_memory: 1024 * 1024,
+            max_call_depth: 64,
+        }
+    }
+}
+
+/// 日志
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: String,
+    pub topic: u64,
+    pub data: Vec<u8>,
+}
+
+/// 执行结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    /// 为 false 表示执行了 `REVERT`，存储修改已撤销
+    pub success: bool,
+    /// 输出或回滚数据
+    pub output: Vec<u8>,
+    pub gas_used: u64,
+    /// 成功时的日志（含成功返回的内层调用的日志）
+    pub logs: Vec<Log>,
+}
+
+/// 虚拟机访问链上状态的接口
+pub trait Host {
+    /// 当前合约的存储
+    fn storage_load(&mut self, key: u64) -> u64;
+    fn storage_store(&mut self, key: u64, value: u64);
+    /// 以至多 `gas` 的 gas 调用其他合约
+    fn call(&mut self, address: &str, input: &[u8], gas: u64) -> CallOutcome;
+}
+
+/// `CALL` 的结果
+#[derive(Debug, Clone)]
+pub struct CallOutcome {
+    pub success: bool,
+    pub output: Vec<u8>,
+    pub gas_used: u64,
+    pub logs: Vec<Log>,
+}
+
+/// 校验通过的程序
+#[derive(Debug, Clone)]
+pub struct Program {
+    code: Vec<u8>,
+    instructions: BTreeMap<usize, Instruction>,
+}
+
+impl Program {
+    /// 校验字节码
+    pub fn new(code: Vec<u8>) -> Result<Self, VmError> {
+        let instructions = verify(&code)?;
+        Ok(Self { code, instructions })
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    /// 反汇编
+    pub fn disassemble(&self) -> String {
+        self.instructions.iter()
+            .map(|(offset, instruction)| format!("{:04x}: {}\n", offset, instruction))
+            .collect()
+    }
+
+    /// 以 `address` 的身份执行
+    pub fn run(&self, address: &str, input: &[u8], gas_limit: u64, config: &VmConfig, host: &mut dyn Host) -> Result<Execution, VmError> {
+        Machine::new(address, gas_limit, config.max_memory).run(self, input, host)
+    }
+}
+
+/// 单个调用帧的机器状态
+struct Machine<'a> {
+    address: &'a str,
+    registers: [u64; REGISTER_COUNT],
+    stack: Vec<u64>,
+    memory: Vec<u8>,
+    return_data: Vec<u8>,
+    gas_limit: u64,
+    gas_left: u64,
+    max_memory: usize,
+    logs: Vec<Log>,
+}
+
+impl<'a> Machine<'a> {
+    fn new(address: &'a str, gas_limit: u64, max_memory: usize) -> Self {
+        Self {
+            address,
+            registers: [0; REGISTER_COUNT],
+            stack: Vec::new(),
+            memory: Vec::new(),
+            return_data: Vec::new(),
+            gas_limit,
+            gas_left: gas_limit,
+            max_memory,
+            logs: Vec::new(),
+        }
+    }
+
+    fn charge(&mut self, cost: u64) -> Result<(), VmError> {
+        self.gas_left = self.gas_left.checked_sub(cost).ok_or(VmError::OutOfGas)?;
+        Ok(())
+    }
+
+    fn reg(&self, register: Register) -> u64 {
+        self.registers[register as usize]
+    }
+
+    fn set(&mut self, register: Register, value: u64) {
+        self.registers[register as usize] = value;
+    }
+
+    /// 确保内存覆盖 `[start, start + len)`，按新增的字计费，返回区间
+    fn expand(&mut self, start: u64, len: u64, offset: usize) -> Result<std::ops::Range<usize>, VmError> {
+        if len == 0 {
+            return Ok(0..0);
+        }
+        let end = start.checked_add(len)
+            .filter(|end| *end <= self.max_memory as u64)
+            .ok_or(VmError::MemoryOutOfBounds { offset })? as usize;
+        let words = end.div_ceil(32);
+        let current = self.memory.len() / 32;
+        if words > current {
+            self.charge((words - current) as u64 * GAS_MEMORY_WORD)?;
+            self.memory.resize(words * 32, 0);
+        }
+        Ok(start as usize..end)
+    }
+
+    fn charge_copy(&mut self, len: u64) -> Result<(), VmError> {
+        self.charge(len.div_ceil(32).saturating_mul(GAS_COPY_WORD))
+    }
+
+    fn finish(self, success: bool, output: Vec<u8>) -> Execution {
+        Execution {
+            success,
+            output,
+            gas_used: self.gas_limit - self.gas_left,
+            logs: if success { self.logs } else { Vec::new() },
+        }
+    }
+
+    fn run(mut self, program: &Program, input: &[u8], host: &mut dyn Host) -> Result<Execution, VmError> {
+        let mut pc = 0usize;
+        loop {
+            // 执行到代码末尾等同于 HALT
+            let Some(&instruction) = program.instructions.get(&pc) else {
+                return Ok(self.finish(true, Vec::new()));
+            };
+            let offset = pc;
+            self.charge(instruction.opcode().gas())?;
+            pc += instruction.encoded_len();
+
+            match instruction {
+                Instruction::Halt => return Ok(self.finish(true, Vec::new())),
+                Instruction::Return { offset: start, len } | Instruction::Revert { offset: start, len } => {
+                    let range = self.expand(self.reg(start), self.reg(len), offset)?;
+                    let output = self.memory[range].to_vec();
+                    return Ok(self.finish(matches!(instruction, Instruction::Return { .. }), output));
+                }
+                Instruction::LoadImm { dst, value } => self.set(dst, value),
+                Instruction::Mov { dst, src } => self.set(dst, self.reg(src)),
+                Instruction::Push { src } => {
+                    if self.stack.len() >= MAX_STACK_DEPTH {
+                        return Err(VmError::StackOverflow { offset });
+                    }
+                    self.stack.push(self.reg(src));
+                }
+                Instruction::Pop { dst } => {
+                    let value = self.stack.pop().ok_or(VmError::StackUnderflow { offset })?;
+                    self.set(dst, value);
+                }
+                Instruction::Binary { op, dst, lhs, rhs } => {
+                    let value = binary(op, self.reg(lhs), self.reg(rhs), offset)?;
+                    self.set(dst, value);
+                }
+                Instruction::Not { dst, src } => self.set(dst, !self.reg(src)),
+                Instruction::IsZero { dst, src } => self.set(dst, (self.reg(src) == 0) as u64),
+                Instruction::Jump { target } => pc = target as usize,
+                Instruction::JumpIfZero { cond, target } => {
+                    if self.reg(cond) == 0 {
+                        pc = target as usize;
+                    }
+                }
+                Instruction::JumpIfNotZero { cond, target } => {
+                    if self.reg(cond) != 0 {
+                        pc = target as usize;
+                    }
+                }
+                Instruction::MLoad { dst, addr } => {
+                    let range = self.expand(self.reg(addr), 8, offset)?;
+                    let value = u64::from_le_bytes(self.memory[range].try_into().expect("8 bytes"));
+                    self.set(dst, value);
+                }
+                Instruction::MStore { addr, src } => {
+                    let range = self.expand(self.reg(addr), 8, offset)?;
+                    let value = self.reg(src);
+                    self.memory[range].copy_from_slice(&value.to_le_bytes());
+                }
+                Instruction::MLoad8 { dst, addr } => {
+                    let range = self.expand(self.reg(addr), 1, offset)?;
+                    let value = self.memory[range.start] as u64;
+                    self.set(dst, value);
+                }
+                Instruction::MStore8 { addr, src } => {
+                    let range = self.expand(self.reg(addr), 1, offset)?;
+                    self.memory[range.start] = self.reg(src) as u8;
+                }
+                Instruction::InputSize { dst } => self.set(dst, input.len() as u64),
+                Instruction::InputCopy { dest, offset: from, len } => {
+                    let len = self.reg(len);
+                    self.charge_copy(len)?;
+                    let range = self.expand(self.reg(dest), len, offset)?;
+                    let from = usize::try_from(self.reg(from)).unwrap_or(usize::MAX);
+                    for (i, byte) in self.memory[range].iter_mut().enumerate() {
+                        *byte = from.checked_add(i).and_then(|at| input.get(at)).copied().unwrap_or(0);
+                    }
+                }
+                Instruction::Hash { dst, offset: start, len } => {
+                    let len = self.reg(len);
+                    self.charge(len.div_ceil(32).saturating_mul(GAS_HASH_WORD))?;
+                    let range = self.expand(self.reg(start), len, offset)?;
+                    let digest = Sha256::digest(&self.memory[range]);
+                    self.set(dst, u64::from_le_bytes(digest[..8].try_into().expect("8 bytes")));
+                }
+                Instruction::SLoad { dst, key } => {
+                    let value = host.storage_load(self.reg(key));
+                    self.set(dst, value);
+                }
+                Instruction::SStore { key, value } => host.storage_store(self.reg(key), self.reg(value)),
+                Instruction::Call { dst, addr, addr_len, input: input_start, input_len, gas } => {
+                    let address_range = self.expand(self.reg(addr), self.reg(addr_len), offset)?;
+                    let input_range = self.expand(self.reg(input_start), self.reg(input_len), offset)?;
+
+                    // 调用方至少保留剩余 gas 的 1/64
+                    let cap = self.gas_left - self.gas_left / 64;
+                    let forwarded = match self.reg(gas) {
+                        0 => cap,
+                        requested => requested.min(cap),
+                    };
+                    self.gas_left -= forwarded;
+
+                    let outcome = match std::str::from_utf8(&self.memory[address_range]) {
+                        Ok(address) => host.call(address, &self.memory[input_range], forwarded),
+                        Err(_) => CallOutcome { success: false, output: Vec::new(), gas_used: 0, logs: Vec::new() },
+                    };
+                    self.gas_left += forwarded - outcome.gas_used.min(forwarded);
+                    if outcome.success {
+                        self.logs.extend(outcome.logs);
+                    }
+                    self.return_data = outcome.output;
+                    self.set(dst, outcome.success as u64);
+                }
+                Instruction::ReturnDataSize { dst } => self.set(dst, self.return_data.len() as u64),
+                Instruction::ReturnDataCopy { dest, offset: from, len } => {
+                    let len = self.reg(len);
+                    self.charge_copy(len)?;
+                    let source = usize::try_from(self.reg(from)).ok()
+                        .and_then(|from| Some(from..from.checked_add(usize::try_from(len).ok()?)?))
+                        .filter(|source| source.end <= self.return_data.len())
+                        .ok_or(VmError::MemoryOutOfBounds { offset })?;
+                    let range = self.expand(self.reg(dest), len, offset)?;
+                    self.memory[range].copy_from_slice(&self.return_data[source]);
+                }
+                Instruction::Log { topic, offset: start, len } => {
+                    let len = self.reg(len);
+                    self.charge(len.saturating_mul(GAS_LOG_BYTE))?;
+                    let range = self.expand(self.reg(start), len, offset)?;
+                    let log = Log {
+                        address: self.address.to_string(),
+                        topic: self.reg(topic),
+                        data: self.memory[range].to_vec(),
+                    };
+                    self.logs.push(log);
+                }
+                Instruction::Gas { dst } => self.set(dst, self.gas_left),
+            }
+        }
+    }
+}
+
+/// 二元运算，算术溢出和除以零为错误
+fn binary(op: BinaryOp, lhs: u64, rhs: u64, offset: usize) -> Result<u64, VmError> {
+    let overflow = VmError::ArithmeticOverflow { offset };
+    Ok(match op {
+        BinaryOp::Add => lhs.checked_add(rhs).ok_or(overflow)?,
+        BinaryOp::Sub => lhs.checked_sub(rhs).ok_or(overflow)?,
+        BinaryOp::Mul => lhs.checked_mul(rhs).ok_or(overflow)?,
+        BinaryOp::Div => lhs.checked_div(rhs).ok_or(VmError::DivisionByZero { offset })?,
+        BinaryOp::Mod => lhs.checked_rem(rhs).ok_or(VmError::DivisionByZero { offset })?,
+        BinaryOp::And => lhs & rhs,
+        BinaryOp::Or => lhs | rhs,
+        BinaryOp::Xor => lhs ^ rhs,
+        BinaryOp::Shl => u32::try_from(rhs).ok().and_then(|shift| lhs.checked_shl(shift)).unwrap_or(0),
+        BinaryOp::Shr => u32::try_from(rhs).ok().and_then(|shift| lhs.checked_shr(shift)).unwrap_or(0),
+        BinaryOp::Eq => (lhs == rhs) as u64,
+        BinaryOp::Lt => (lhs < rhs) as u64,
+        BinaryOp::Gt => (lhs > rhs) as u64,
+    })
+}
+
+/// 合约地址：部署序号与字节码的 SHA-256 的前 20 字节
+fn contract_address(nonce: u64, code: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(nonce.to_le_bytes());
+    hasher.update(code);
+    format!("0x{}", hex::encode(&hasher.finalize()[..20]))
+}
+
+/// 已部署合约与存储上的执行环境
+///
+/// 存储修改按调用帧记入日志，帧失败或回滚时撤销到进入该帧前的位置。
+struct World<'a> {
+    contracts: &'a HashMap<String, Arc<Program>>,
+    storage: &'a mut HashMap<String, BTreeMap<u64, u64>>,
+    /// (地址, 键, 修改前的值)
+    journal: Vec<(String, u64, Option<u64>)>,
+    frames: Vec<String>,
+    config: VmConfig,
+}
+
+impl<'a> World<'a> {
+    fn new(contracts: &'a HashMap<String, Arc<Program>>, storage: &'a mut HashMap<String, BTreeMap<u64, u64>>, config: VmConfig) -> Self {
+        Self { contracts, storage, journal: Vec::new(), frames: Vec::new(), config }
+    }
+
+    fn rollback(&mut self, checkpoint: usize) {
+        for (address, key, previous) in self.journal.drain(checkpoint..).rev() {
+            let slots = self.storage.entry(address).or_default();
+            match previous {
+                Some(value) => slots.insert(key, value),
+                None => slots.remove(&key),
+            };
+        }
+    }
+
+    /// 在新的调用帧中执行程序，失败或回滚时撤销该帧的存储修改
+    fn execute(&mut self, address: &str, program: &Program, input: &[u8], gas: u64) -> Result<Execution, VmError> {
+        if self.frames.len() >= self.config.max_call_depth {
+            return Err(VmError::CallDepthExceeded);
+        }
+        let checkpoint = self.journal.len();
+        self.frames.push(address.to_string());
+        let config = self.config;
+        let result = program.run(address, input, gas, &config, self);
+        self.frames.pop();
+        if !matches!(result, Ok(Execution { success: true, .. })) {
+            self.rollback(checkpoint);
+        }
+        result
+    }
+
+    fn current(&self) -> &str {
+        self.frames.last().expect("host is only used while a frame is executing")
+    }
+}
+
+impl Host for World<'_> {
+    fn storage_load(&mut self, key: u64) -> u64 {
+        self.storage.get(self.current())
+            .and_then(|slots| slots.get(&key))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn storage_store(&mut self, key: u64, value: u64) {
+        let address = self.current().to_string();
+        let previous = self.storage.entry(address.clone()).or_default().insert(key, value);
+        self.journal.push((address, key, previous));
+    }
+
+    fn call(&mut self, address: &str, input: &[u8], gas: u64) -> CallOutcome {
+        let failed = |error: VmError, gas_used| CallOutcome {
+            success: false,
+            output: error.to_string().into_bytes(),
+            gas_used,
+            logs: Vec::new(),
+        };
+        let Some(program) = self.contracts.get(address).map(Arc::clone) else {
+            return failed(VmError::ContractNotFound(address.to_string()), 0);
+        };
+        match self.execute(address, &program, input, gas) {
+            Ok(execution) => CallOutcome {
+                success: execution.success,
+                output: execution.output,
+                gas_used: execution.gas_used,
+                logs: execution.logs,
+            },
+            Err(VmError::CallDepthExceeded) => failed(VmError::CallDepthExceeded, 0),
+            // 执行出错时转发的 gas 全部消耗
+            Err(error) => failed(error, gas),
+        }
+    }
+}
+
+/// 虚拟机
+#[derive(Debug, Default)]
+pub struct VirtualMachine {
+    config: VmConfig,
+    contracts: HashMap<String, Arc<Program>>,
+    storage: HashMap<String, BTreeMap<u64, u64>>,
+    nonce: u64,
+}
+
+impl VirtualMachine {
+    pub fn new() -> Self {
+        Self::with_config(VmConfig::default())
+    }
+
+    pub fn with_config(config: VmConfig) -> Self {
+        Self {
+            config,
+            contracts: HashMap::new(),
+            storage: HashMap::new(),
+            nonce: 0,
+        }
+    }
+
+    /// 校验并执行一段未部署的字节码，返回输出
+    ///
+    /// 所有存储修改（包括对已部署合约的调用产生的修改）在结束后撤销；回滚视为错误。
+    pub async fn execute(&mut self, bytecode: &[u8], input: &[u8]) -> SmartContractResult<Vec<u8>> {
+        let program = Program::new(bytecode.to_vec()).map_err(SmartContractError::from)?;
+        let address = contract_address(self.nonce, bytecode);
+        let mut world = World::new(&self.contracts, &mut self.storage, self.config);
+        let result = world.execute(&address, &program, input, self.config.gas_limit);
+        world.rollback(0);
+
+        let execution = result.map_err(SmartContractError::from)?;
+        if !execution.success {
+            return Err(SmartContractError::RuntimeError(format!("执行回滚: 0x{}", hex::encode(&execution.output))).into());
+        }
+        Ok(execution.output)
+    }
+
+    /// 校验字节码并部署，返回合约地址
+    pub async fn deploy(&mut self, bytecode: &[u8]) -> SmartContractResult<String> {
+        let program = Program::new(bytecode.to_vec()).map_err(SmartContractError::from)?;
+        let address = contract_address(self.nonce, bytecode);
+        self.nonce += 1;
+        self.contracts.insert(address.clone(), Arc::new(program));
+        Ok(address)
+    }
+
+    /// 调用已部署的合约；回滚或出错时撤销全部存储修改
+    pub fn call(&mut self, address: &str, input: &[u8], gas_limit: u64) -> Result<Execution, VmError> {
+        let program = self.contracts.get(address)
+            .map(Arc::clone)
+            .ok_or_else(|| VmError::ContractNotFound(address.to_string()))?;
+        World::new(&self.contracts, &mut self.storage, self.config).execute(address, &program, input, gas_limit)
+    }
+
+    /// 已部署的程序
+    pub fn program(&self, address: &str) -> Option<&Program> {
+        self.contracts.get(address).map(Arc::as_ref)
+    }
+
+    /// 读取合约存储
+    pub fn storage(&self, address: &str, key: u64) -> u64 {
+        self.storage.get(address)
+            .and_then(|slots| slots.get(&key))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BinaryOp::{Add, Div, Mul, Shl, Sub};
+
+    /// 第 `index` 条指令的字节偏移
+    fn offset_of(program: &[Instruction], index: usize) -> u32 {
+        program[..index].iter().map(|instruction| instruction.encoded_len() as u32).sum()
+    }
+
+    fn run(code: &[u8], input: &[u8], gas: u64) -> Result<Execution, VmError> {
+        let mut vm = VirtualMachine::new();
+        let program = Program::new(code.to_vec())?;
+        World::new(&vm.contracts, &mut vm.storage, VmConfig::default()).execute("0x01", &program, input, gas)
+    }
+
+    /// 循环计算 5!，以 8 字节小端返回
+    fn factorial() -> Vec<Instruction> {
+        let mut program = vec![
+            Instruction::LoadImm { dst: 1, value: 5 },
+            Instruction::LoadImm { dst: 2, value: 1 },
+            Instruction::LoadImm { dst: 3, value: 1 },
+            Instruction::JumpIfZero { cond: 1, target: 0 },
+            Instruction::Binary { op: Mul, dst: 2, lhs: 2, rhs: 1 },
+            Instruction::Binary { op: Sub, dst: 1, lhs: 1, rhs: 3 },
+            Instruction::Jump { target: 0 },
+            Instruction::LoadImm { dst: 4, value: 0 },
+            Instruction::MStore { addr: 4, src: 2 },
+            Instruction::LoadImm { dst: 5, value: 8 },
+            Instruction::Return { offset: 4, len: 5 },
+        ];
+        program[3] = Instruction::JumpIfZero { cond: 1, target: offset_of(&program, 7) };
+        program[6] = Instruction::Jump { target: offset_of(&program, 3) };
+        program
+    }
+
+    #[test]
+    fn test_execute_loop_with_exact_gas() {
+        let execution = run(&assemble(&factorial()), &[], 1_000).unwrap();
+        assert!(execution.success);
+        assert_eq!(execution.output, 120u64.to_le_bytes());
+        // 3 × LOADI + 5 × (JZ + MUL + SUB + JMP) + JZ + LOADI + MSTORE + 1 字内存 + LOADI + RETURN
+        assert_eq!(execution.gas_used, 6 + 5 * 26 + 10 + 2 + 3 + GAS_MEMORY_WORD + 2);
+
+        assert_eq!(run(&assemble(&factorial()), &[], 100), Err(VmError::OutOfGas));
+    }
+
+    #[test]
+    fn test_arithmetic_traps() {
+        let overflow = assemble(&[
+            Instruction::LoadImm { dst: 1, value: u64::MAX },
+            Instruction::LoadImm { dst: 2, value: 1 },
+            Instruction::Binary { op: Add, dst: 3, lhs: 1, rhs: 2 },
+        ]);
+        assert_eq!(run(&overflow, &[], 1_000), Err(VmError::ArithmeticOverflow { offset: 20 }));
+
+        let division = assemble(&[Instruction::Binary { op: Div, dst: 1, lhs: 1, rhs: 0 }]);
+        assert_eq!(run(&division, &[], 1_000), Err(VmError::DivisionByZero { offset: 0 }));
+
+        let shift = assemble(&[
+            Instruction::LoadImm { dst: 1, value: 64 },
+            Instruction::LoadImm { dst: 2, value: 1 },
+            Instruction::Binary { op: Shl, dst: 3, lhs: 2, rhs: 1 },
+            Instruction::Push { src: 3 },
+            Instruction::Pop { dst: 4 },
+            Instruction::MStore { addr: 0, src: 4 },
+            Instruction::LoadImm { dst: 5, value: 8 },
+            Instruction::Return { offset: 0, len: 5 },
+        ]);
+        assert_eq!(run(&shift, &[], 1_000).unwrap().output, [0; 8]);
+    }
+
+    #[test]
+    fn test_verifier_rejects_invalid_bytecode() {
+        // 跳到 LOADI 的立即数中间
+        let into_immediate = assemble(&[Instruction::LoadImm { dst: 1, value: 7 }, Instruction::Jump { target: 3 }]);
+        assert_eq!(verify(&into_immediate).unwrap_err(), VmError::InvalidJump { offset: 10, target: 3 });
+        assert!(matches!(verify(&assemble(&[Instruction::Jump { target: 99 }])), Err(VmError::InvalidJump { .. })));
+
+        assert_eq!(verify(&assemble(&[Instruction::Pop { dst: 1 }])).unwrap_err(), VmError::StackUnderflow { offset: 0 });
+
+        // 跳转路径栈深为 0，顺序路径为 1
+        let inconsistent = assemble(&[
+            Instruction::JumpIfZero { cond: 0, target: 8 },
+            Instruction::Push { src: 1 },
+            Instruction::Halt,
+        ]);
+        assert_eq!(verify(&inconsistent).unwrap_err(), VmError::InconsistentStack { offset: 8 });
+
+        assert_eq!(verify(&[0x11, 16, 0]).unwrap_err(), VmError::InvalidRegister { offset: 0, register: 16 });
+        assert_eq!(verify(&[0xff]).unwrap_err(), VmError::InvalidOpcode { offset: 0, opcode: 0xff });
+        assert_eq!(verify(&[0x10, 1, 0]).unwrap_err(), VmError::Truncated { offset: 0 });
+        assert!(verify(&assemble(&factorial())).is_ok());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let code = assemble(&factorial());
+        let listing = disassemble(&code).unwrap();
+        assert_eq!(listing.lines().next(), Some("0000: LOADI r1, 5"));
+        assert!(listing.contains("001e: JZ r1, @0031"));
+        assert!(listing.contains("0024: MUL r2, r2, r1"));
+        assert_eq!(listing.lines().count(), 11);
+        assert_eq!(Program::new(code).unwrap().disassemble(), listing);
+    }
+
+    /// 计数器：存储键 0 加一，记录日志并返回新值
+    fn counter_code() -> Vec<u8> {
+        assemble(&[
+            Instruction::SLoad { dst: 1, key: 0 },
+            Instruction::LoadImm { dst: 2, value: 1 },
+            Instruction::Binary { op: Add, dst: 1, lhs: 1, rhs: 2 },
+            Instruction::SStore { key: 0, value: 1 },
+            Instruction::MStore { addr: 0, src: 1 },
+            Instruction::LoadImm { dst: 3, value: 7 },
+            Instruction::LoadImm { dst: 4, value: 8 },
+            Instruction::Log { topic: 3, offset: 0, len: 4 },
+            Instruction::Return { offset: 0, len: 4 },
+        ])
+    }
+
+    /// 输入为模式字节加被调地址：调用被调合约，模式为 0 时返回其输出，否则回滚
+    fn forwarder_code() -> Vec<u8> {
+        let mut program = vec![
+            Instruction::InputSize { dst: 1 },
+            Instruction::InputCopy { dest: 0, offset: 0, len: 1 },
+            Instruction::LoadImm { dst: 2, value: 1 },
+            Instruction::Binary { op: Sub, dst: 3, lhs: 1, rhs: 2 },
+            Instruction::Call { dst: 4, addr: 2, addr_len: 3, input: 0, input_len: 0, gas: 0 },
+            Instruction::MLoad8 { dst: 5, addr: 0 },
+            Instruction::JumpIfNotZero { cond: 5, target: 0 },
+            Instruction::ReturnDataSize { dst: 6 },
+            Instruction::ReturnDataCopy { dest: 0, offset: 0, len: 6 },
+            Instruction::Return { offset: 0, len: 6 },
+            Instruction::Revert { offset: 0, len: 0 },
+        ];
+        program[6] = Instruction::JumpIfNotZero { cond: 5, target: offset_of(&program, 10) };
+        assemble(&program)
+    }
+
+    #[tokio::test]
+    async fn test_storage_calls_and_rollback() {
+        let mut vm = VirtualMachine::new();
+        let counter = vm.deploy(&counter_code()).await.unwrap();
+        let forwarder = vm.deploy(&forwarder_code()).await.unwrap();
+        assert_ne!(vm.deploy(&counter_code()).await.unwrap(), counter);
+        assert!(vm.deploy(&[0x10, 1]).await.is_err());
+
+        let input = |mode: u8, address: &str| [&[mode][..], address.as_bytes()].concat();
+
+        let execution = vm.call(&forwarder, &input(0, &counter), 100_000).unwrap();
+        assert!(execution.success);
+        assert_eq!(execution.output, 1u64.to_le_bytes());
+        assert_eq!(execution.logs, vec![Log { address: counter.clone(), topic: 7, data: 1u64.to_le_bytes().to_vec() }]);
+        assert_eq!(vm.storage(&counter, 0), 1);
+
+        // 外层回滚撤销内层调用的修改
+        let execution = vm.call(&forwarder, &input(1, &counter), 100_000).unwrap();
+        assert!(!execution.success && execution.logs.is_empty());
+        assert_eq!(vm.storage(&counter, 0), 1);
+
+        // 被调合约不存在时 CALL 失败，返回数据为错误信息
+        let execution = vm.call(&forwarder, &input(0, "0xmissing"), 100_000).unwrap();
+        assert_eq!(String::from_utf8(execution.output).unwrap(), "合约不存在: 0xmissing");
+
+        // 未部署代码的执行不留下存储修改
+        let output = vm.execute(&forwarder_code(), &input(0, &counter)).await.unwrap();
+        assert_eq!(output, 2u64.to_le_bytes());
+        assert_eq!(vm.storage(&counter, 0), 1);
+    }
+}
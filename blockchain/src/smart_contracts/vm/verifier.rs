@@ -0,0 +1,64 @@
+//! 部署前的字节码校验
+//!
+//! 校验通过的字节码满足：
+//! - 每条指令都能完整解码，操作码和寄存器编号有效；
+//! - 跳转目标都落在指令边界上；
+//! - 沿所有控制流路径，`POP` 不会使操作数栈下溢，栈深不超过上限，
+//!   且汇合点的栈深一致（因此每个偏移处的栈深在执行前即可确定）。
+
+use super::instruction::{decode_all, Instruction};
+use super::VmError;
+use std::collections::{BTreeMap, HashMap};
+
+/// 字节码长度上限
+pub const MAX_CODE_SIZE: usize = 64 * 1024;
+
+/// 操作数栈深度上限
+pub const MAX_STACK_DEPTH: usize = 1024;
+
+/// 校验字节码，返回解码后的指令表（偏移 -> 指令）
+pub fn verify(code: &[u8]) -> Result<BTreeMap<usize, Instruction>, VmError> {
+    if code.len() > MAX_CODE_SIZE {
+        return Err(VmError::CodeTooLarge(code.len()));
+    }
+    let instructions: BTreeMap<usize, Instruction> = decode_all(code)?.into_iter().collect();
+
+    for (&offset, instruction) in &instructions {
+        let invalid = instruction.jump_target().filter(|target| !instructions.contains_key(&(*target as usize)));
+        if let Some(target) = invalid {
+            return Err(VmError::InvalidJump { offset, target });
+        }
+    }
+
+    // 从入口出发传播各偏移处的栈深
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut pending = vec![(0usize, 0usize)];
+    while let Some((offset, depth)) = pending.pop() {
+        let Some(instruction) = instructions.get(&offset) else {
+            // 执行到代码末尾等同于 HALT
+            continue;
+        };
+        match depths.get(&offset) {
+            Some(&known) if known == depth => continue,
+            Some(_) => return Err(VmError::InconsistentStack { offset }),
+            None => {
+                depths.insert(offset, depth);
+            }
+        }
+
+        let after = match instruction {
+            Instruction::Push { .. } if depth >= MAX_STACK_DEPTH => return Err(VmError::StackOverflow { offset }),
+            Instruction::Push { .. } => depth + 1,
+            Instruction::Pop { .. } => depth.checked_sub(1).ok_or(VmError::StackUnderflow { offset })?,
+            _ => depth,
+        };
+        if let Some(target) = instruction.jump_target() {
+            pending.push((target as usize, after));
+        }
+        if !instruction.is_terminal() {
+            pending.push((offset + instruction.encoded_len(), after));
+        }
+    }
+
+    Ok(instructions)
+}
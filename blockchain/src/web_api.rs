@@ -10,6 +10,7 @@ use thiserror::Error;
 
 use crate::simple_blockchain::Blockchain;
 use crate::monitoring::{BlockchainMonitor, PerformanceMetrics, BlockchainMetrics, HealthStatus};
+use crate::components::network::{SyncProgress, SyncProgressHandle};
 
 /// API 错误类型
 #[allow(dead_code)]
@@ -179,6 +180,8 @@ pub struct WebApiServer {
     monitor: Arc<BlockchainMonitor>,
     node_info: NodeInfo,
     network_stats: Arc<Mutex<NetworkStats>>,
+    /// 节点的区块同步进度
+    sync_progress: Option<SyncProgressHandle>,
 }
 
 #[allow(dead_code)]
@@ -207,9 +210,16 @@ impl WebApiServer {
             monitor: Arc::new(BlockchainMonitor::new()),
             node_info,
             network_stats: Arc::new(Mutex::new(network_stats)),
+            sync_progress: None,
         }
     }
     
+    /// 关联节点的同步进度（`BlockSync::progress_handle`）
+    pub fn with_sync_progress(mut self, sync_progress: SyncProgressHandle) -> Self {
+        self.sync_progress = Some(sync_progress);
+        self
+    }
+    
     /// 获取区块链信息
     pub fn get_blockchain_info(&self) -> Result<ApiResponse<BlockchainInfo>, ApiError> {
         let blockchain = self.blockchain.lock().unwrap();
@@ -364,10 +374,21 @@ impl WebApiServer {
     pub fn get_node_info(&self) -> Result<ApiResponse<NodeInfo>, ApiError> {
         let mut node_info = self.node_info.clone();
         node_info.uptime = self.monitor.get_uptime().as_secs();
+        if let Some(progress) = &self.sync_progress {
+            node_info.sync_status = if progress.read().unwrap().syncing { "syncing" } else { "synced" }.to_string();
+        }
         
         Ok(ApiResponse::success(node_info))
     }
 
+    /// 获取区块同步进度
+    pub fn get_sync_progress(&self) -> Result<ApiResponse<SyncProgress>, ApiError> {
+        let progress = self.sync_progress.as_ref()
+            .map(|progress| progress.read().unwrap().clone())
+            .unwrap_or_default();
+        Ok(ApiResponse::success(progress))
+    }
+
     /// 获取网络统计
     pub fn get_network_stats(&self) -> Result<ApiResponse<NetworkStats>, ApiError> {
         let stats = self.network_stats.lock().unwrap().clone();
@@ -581,6 +602,11 @@ impl HttpHandler {
                 Ok(serde_json::to_string(&response)
                     .map_err(|_| ApiError::SerializationError)?)
             }
+            "/sync" => {
+                let response = self.api_server.get_sync_progress()?;
+                Ok(serde_json::to_string(&response)
+                    .map_err(|_| ApiError::SerializationError)?)
+            }
             _ => {
                 // 尝试解析区块请求 /block/{index}
                 if path.starts_with("/block/") {
@@ -662,4 +688,17 @@ mod tests {
         assert!(info_response.success);
         assert!(info_response.data.is_some());
     }
+
+    #[test]
+    fn test_sync_progress_endpoint() {
+        let blockchain = super::super::simple_blockchain::Blockchain::new(2);
+        let progress = SyncProgressHandle::default();
+        progress.write().unwrap().syncing = true;
+        progress.write().unwrap().target_height = 10;
+        let handler = HttpHandler::new(WebApiServer::new(blockchain).with_sync_progress(progress));
+
+        let body = handler.handle_get("/sync").unwrap();
+        assert!(body.contains("\"target_height\":10"));
+        assert_eq!(handler.api_server.get_node_info().unwrap().data.unwrap().sync_status, "syncing");
+    }
 }